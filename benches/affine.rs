@@ -0,0 +1,100 @@
+//! Benchmarks drawing a rotated and zoomed plane with `affine_blit`
+//! against mapping each pixel and drawing it with `put_pixel`.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// Creates a 64x64 checkered texture,
+/// with color 0 in some of the squares for the color key.
+fn texture() -> Surface {
+    let mut surface = Surface::new(64, 64);
+    for y in 0..64 {
+        for x in 0..64 {
+            let color = match (x / 8 + y / 8) % 3 {
+                0 => 0,
+                n => (n * 16 + x / 4) as u8,
+            };
+            surface.put_pixel(x, y, color);
+        }
+    }
+    surface
+}
+
+/// Draws the plane one pixel at a time,
+/// mapping each pixel to the texture with floating point math.
+fn per_pixel(texture: &Surface, u_axis: (f32, f32), v_axis: (f32, f32)) {
+    let (w, h) = (texture.width() as i32, texture.height() as i32);
+    let pixels = texture.pixels();
+    for y in 0..200_u16 {
+        for x in 0..320_u16 {
+            let u = (x as f32 * u_axis.0 + y as f32 * v_axis.0) as i32;
+            let v = (x as f32 * u_axis.1 + y as f32 * v_axis.1) as i32;
+            let color = pixels[(v.rem_euclid(h) * w + u.rem_euclid(w)) as usize];
+            put_pixel(x, y, color);
+        }
+    }
+}
+
+fn bench_affine(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let texture = texture();
+    let screen = Rect::new(0, 0, 320, 200);
+    let (sin, cos) = 0.4_f32.sin_cos();
+    let zoom = 1.5;
+    let rotated = ((cos * zoom, sin * zoom), (-sin * zoom, cos * zoom));
+
+    let mut group = c.benchmark_group("affine");
+    group.bench_function("affine_blit identity", |b| {
+        b.iter(|| {
+            affine_blit(
+                screen,
+                black_box(&texture),
+                (0., 0.),
+                (1., 0.),
+                (0., 1.),
+                true,
+                None,
+            )
+        })
+    });
+    group.bench_function("affine_blit rotated", |b| {
+        b.iter(|| {
+            affine_blit(
+                screen,
+                black_box(&texture),
+                (0., 0.),
+                rotated.0,
+                rotated.1,
+                true,
+                None,
+            )
+        })
+    });
+    group.bench_function("affine_blit rotated, color key, no wrap", |b| {
+        b.iter(|| {
+            affine_blit(
+                screen,
+                black_box(&texture),
+                (-100., -60.),
+                rotated.0,
+                rotated.1,
+                false,
+                Some(0),
+            )
+        })
+    });
+    group.bench_function("per pixel put_pixel", |b| {
+        b.iter(|| per_pixel(black_box(&texture), rotated.0, rotated.1))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_affine);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
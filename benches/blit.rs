@@ -0,0 +1,51 @@
+//! Benchmarks opaque and masked blits
+//! of a full-screen image, a small sprite,
+//! and a sprite which is mostly off the screen.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// Creates an image with a checkered pattern of transparent pixels,
+/// so that masked blits copy short runs.
+fn sprite(width: u16, height: u16) -> Vec<u8> {
+    let mut pixels = vec![0; width as usize * height as usize];
+    for y in 0..height as usize {
+        for x in 0..width as usize {
+            if (x / 3 + y / 2) % 2 == 1 {
+                pixels[y * width as usize + x] = ((x + y) % 200 + 1) as u8;
+            }
+        }
+    }
+    pixels
+}
+
+fn bench_blit(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let cases = [
+        ("full screen", (320, 200), (0, 0)),
+        ("small sprite", (16, 16), (150, 90)),
+        ("heavily clipped", (64, 64), (-60, -60)),
+    ];
+
+    let mut group = c.benchmark_group("blit");
+    for (name, (w, h), (x, y)) in cases {
+        let data = sprite(w, h);
+        group.bench_function(format!("blit {}", name), |b| {
+            b.iter(|| blit(x, y, black_box(&data), w, h, 0, 0, w, h))
+        });
+        group.bench_function(format!("mask_blit {}", name), |b| {
+            b.iter(|| mask_blit(x, y, black_box(&data), w, h, 0, 0, w, h, 0))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_blit);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,78 @@
+//! Benchmarks looking up the nearest palette color
+//! with `nearest_color`, which caches its results,
+//! against scanning the palette for every lookup.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// Creates a palette with every color of a 6x7x6 color cube,
+/// followed by grays.
+fn cube_palette() -> Vec<(u8, u8, u8)> {
+    let level = |i: u8, n: u8| i * 63 / (n - 1);
+    let mut colors = Vec::with_capacity(256);
+    for r in 0..6 {
+        for g in 0..7 {
+            for b in 0..6 {
+                colors.push((level(r, 6), level(g, 7), level(b, 6)));
+            }
+        }
+    }
+    while colors.len() < 256 {
+        let v = (colors.len() - 252) as u8 * 16;
+        colors.push((v, v, v));
+    }
+    colors
+}
+
+/// The colors to look up, like the pixels of a true color image
+/// with many repeated colors.
+fn queries() -> Vec<(u8, u8, u8)> {
+    (0..4096_u32)
+        .map(|i| {
+            let x = i % 64;
+            let y = i / 64;
+            (x as u8, y as u8, ((x + y) / 2) as u8)
+        })
+        .collect()
+}
+
+fn bench_nearest_color(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    for (i, &(r, g, b)) in cube_palette().iter().enumerate() {
+        set_pal(i, r, g, b);
+    }
+    let palette: Vec<u8> = (0..256)
+        .flat_map(|i| {
+            let (r, g, b) = pal(i);
+            [r, g, b]
+        })
+        .collect();
+    let queries = queries();
+
+    let mut group = c.benchmark_group("nearest_color");
+    group.bench_function("nearest_color", |b| {
+        b.iter(|| {
+            for &(r, g, b) in black_box(&queries) {
+                black_box(nearest_color(r, g, b));
+            }
+        })
+    });
+    group.bench_function("naive scan", |b| {
+        b.iter(|| {
+            for &rgb in black_box(&queries) {
+                black_box(nearest_color_in(&palette, rgb));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_nearest_color);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
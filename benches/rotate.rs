@@ -0,0 +1,89 @@
+//! Benchmarks drawing a sprite rotated about its center with `rotate_blit`
+//! against mapping each pixel of its bounding box with floating point math
+//! and drawing it with `put_pixel`.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// Creates a 48x48 ship-like sprite,
+/// with color 0 around it for the color key.
+fn sprite() -> Image {
+    let mut surface = Surface::new(48, 48);
+    for y in 0..48_i32 {
+        for x in 0..48_i32 {
+            let (dx, dy) = (x - 24, y - 24);
+            if dx.abs() + dy.abs() < 22 {
+                surface.put_pixel(x, y, 32 + (x / 3) as u8);
+            }
+        }
+    }
+    let mut image = surface.to_image(&[0; 768]);
+    image.set_color_key(Some(0));
+    image
+}
+
+/// Draws the sprite one pixel at a time,
+/// mapping each pixel of the bounding box back into the image.
+fn per_pixel(center_x: i32, center_y: i32, image: &Image, angle: f32, scale: f32) {
+    let (w, h) = (image.width() as f32, image.height() as f32);
+    let (sin, cos) = angle.sin_cos();
+    let extent_x = ((w * cos).abs() + (h * sin).abs()) * scale / 2.;
+    let extent_y = ((w * sin).abs() + (h * cos).abs()) * scale / 2.;
+    let data = image.data();
+    for y in (center_y - extent_y as i32)..=(center_y + extent_y as i32) {
+        for x in (center_x - extent_x as i32)..=(center_x + extent_x as i32) {
+            if !(0..320).contains(&x) || !(0..200).contains(&y) {
+                continue;
+            }
+            let (dx, dy) = (
+                x as f32 + 0.5 - center_x as f32,
+                y as f32 + 0.5 - center_y as f32,
+            );
+            let u = (dx * cos + dy * sin) / scale + w / 2.;
+            let v = (dy * cos - dx * sin) / scale + h / 2.;
+            if u < 0. || v < 0. || u >= w || v >= h {
+                continue;
+            }
+            let color = data[v as usize * image.width() as usize + u as usize];
+            if color != 0 {
+                put_pixel(x as u16, y as u16, color);
+            }
+        }
+    }
+}
+
+fn bench_rotate(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let sprite = sprite();
+
+    let mut group = c.benchmark_group("rotate");
+    group.bench_function("rotate_blit", |b| {
+        b.iter(|| rotate_blit(160, 100, black_box(&sprite), 0.7, 1., false))
+    });
+    group.bench_function("rotate_blit scaled x3", |b| {
+        b.iter(|| rotate_blit(160, 100, black_box(&sprite), 0.7, 3., false))
+    });
+    group.bench_function("rotate_blit wrapped", |b| {
+        b.iter(|| rotate_blit(160, 100, black_box(&sprite), 0.7, 1., true))
+    });
+    group.bench_function("rotate_blit clipped", |b| {
+        b.iter(|| rotate_blit(310, 190, black_box(&sprite), 0.7, 3., false))
+    });
+    group.bench_function("per pixel put_pixel", |b| {
+        b.iter(|| per_pixel(160, 100, black_box(&sprite), 0.7, 1.))
+    });
+    group.bench_function("per pixel put_pixel scaled x3", |b| {
+        b.iter(|| per_pixel(160, 100, black_box(&sprite), 0.7, 3.))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_rotate);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,104 @@
+//! Benchmarks drawing the wall strips of a raycaster view
+//! with `draw_tex_column`
+//! against drawing each pixel of the strips with `put_pixel`.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// Creates a 64x64 brick texture,
+/// with color 0 in the mortar for the color key.
+fn texture() -> Surface {
+    let mut surface = Surface::new(64, 64);
+    for y in 0..64 {
+        for x in 0..64 {
+            let shift = if y / 8 % 2 == 0 { 0 } else { 8 };
+            let mortar = y % 8 == 0 || (x + shift) % 16 == 0;
+            let color = if mortar {
+                0
+            } else {
+                (32 + x / 4 + y / 8) as u8
+            };
+            surface.put_pixel(x, y, color);
+        }
+    }
+    surface
+}
+
+/// The wall heights of a view with walls at varying distances,
+/// some of them taller than the screen.
+fn heights() -> Vec<u32> {
+    (0..320)
+        .map(|x| 40 + ((x as f32 / 40.).sin().abs() * 260.) as u32)
+        .collect()
+}
+
+/// Draws one strip one pixel at a time,
+/// with the same fixed point stepping as `draw_tex_column`.
+fn per_pixel(x: u16, height: u32, texture: &Surface, tex_x: u32, shade: &[u8; 256]) {
+    let (w, h) = (texture.width() as u32, texture.height() as u32);
+    let pixels = texture.pixels();
+    let y_top = 100 - height as i32 / 2;
+    let step = (h << 16) / height;
+    let mut v = 0;
+    for y in y_top..y_top + height as i32 {
+        if (0..200).contains(&y) {
+            let color = pixels[((v >> 16) * w + tex_x % w) as usize];
+            put_pixel(x, y as u16, shade[color as usize]);
+        }
+        v += step;
+    }
+}
+
+fn bench_tex_column(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let texture = texture();
+    let heights = heights();
+    let mut shade = [0; 256];
+    for (i, s) in shade.iter_mut().enumerate() {
+        *s = (i / 2) as u8;
+    }
+
+    let mut group = c.benchmark_group("tex_column");
+    group.bench_function("draw_tex_column", |b| {
+        b.iter(|| {
+            for (x, &height) in black_box(&heights).iter().enumerate() {
+                let y_top = 100 - height as i32 / 2;
+                draw_tex_column(x as i32, y_top, height, &texture, x as u32, None, None);
+            }
+        })
+    });
+    group.bench_function("draw_tex_column shaded, color key", |b| {
+        b.iter(|| {
+            for (x, &height) in black_box(&heights).iter().enumerate() {
+                let y_top = 100 - height as i32 / 2;
+                draw_tex_column(
+                    x as i32,
+                    y_top,
+                    height,
+                    &texture,
+                    x as u32,
+                    Some(0),
+                    Some(&shade),
+                );
+            }
+        })
+    });
+    group.bench_function("per pixel put_pixel", |b| {
+        b.iter(|| {
+            for (x, &height) in black_box(&heights).iter().enumerate() {
+                per_pixel(x as u16, height, &texture, x as u32, &shade);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_tex_column);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,111 @@
+//! Benchmarks drawing many short strings with `out_text_xy`,
+//! which reuses a text buffer,
+//! against allocating a `CString` per call,
+//! and drawing text with an outline and a shadow
+//! through `out_text_styled`,
+//! against drawing each stamp with `out_text_xy`.
+#![no_main]
+
+use std::ffi::CString;
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+const TEXT: &str = "SCORE: 0001000  LIVES: 3  STAGE: 12";
+
+/// The offsets of the outline stamps around the text.
+const OUTLINE: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Draws the same effect as `out_text_styled`
+/// with one call to `out_text_xy` per stamp.
+fn per_stamp(x: i32, y: i32, text: &str) {
+    set_color(8);
+    out_text_xy(x + 2, y + 2, text);
+    set_color(0);
+    for (dx, dy) in OUTLINE {
+        out_text_xy(x + dx, y + dy, text);
+    }
+    set_color(15);
+    out_text_xy(x, y, text);
+}
+
+/// The lines of a debug overlay, drawn every frame.
+const HUD: [&str; 6] = [
+    "FPS: 60",
+    "X: 123 Y: 45",
+    "ENEMIES: 12",
+    "PARTICLES: 340",
+    "SCROLL: 1024",
+    "FRAME: 98765",
+];
+
+/// Draws text as before the text buffer,
+/// allocating a `CString` on every call.
+fn out_text_xy_allocating(x: i32, y: i32, text: &str) {
+    let text = CString::new(text).unwrap();
+    unsafe {
+        dos_like_sys::outtextxy(x, y, text.as_ptr());
+    }
+}
+
+fn bench_short_strings(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+
+    let mut group = c.benchmark_group("short strings");
+    group.bench_function("out_text_xy", |b| {
+        b.iter(|| {
+            for _ in 0..5 {
+                for (y, line) in (0..).step_by(8).zip(HUD) {
+                    out_text_xy(0, y, black_box(line));
+                }
+            }
+        })
+    });
+    group.bench_function("CString per call", |b| {
+        b.iter(|| {
+            for _ in 0..5 {
+                for (y, line) in (0..).step_by(8).zip(HUD) {
+                    out_text_xy_allocating(0, y, black_box(line));
+                }
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_text(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let effect = TextEffect::new().with_outline(0).with_shadow(2, 2, 8);
+
+    let mut group = c.benchmark_group("styled text");
+    group.bench_function("out_text_xy", |b| {
+        b.iter(|| out_text_xy(8, 8, black_box(TEXT)))
+    });
+    group.bench_function("out_text_styled", |b| {
+        b.iter(|| {
+            set_color(15);
+            out_text_styled(8, 8, black_box(TEXT), effect)
+        })
+    });
+    group.bench_function("per stamp out_text_xy", |b| {
+        b.iter(|| per_stamp(8, 8, black_box(TEXT)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_short_strings, bench_text);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,72 @@
+//! Benchmarks drawing the labels of a HUD with a `TextBatch`
+//! against drawing each label with `out_text_xy`.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+/// The labels of the HUD,
+/// with the font and color of each one.
+fn labels() -> Vec<(i32, i32, Font, u8, String)> {
+    (0..20)
+        .map(|i| {
+            let font = if i % 3 == 0 {
+                Font::DEFAULT_8X16
+            } else {
+                Font::DEFAULT_8X8
+            };
+            let (x, y) = (4 + (i % 4) * 80, 4 + (i / 4) * 38);
+            (
+                x,
+                y,
+                font,
+                9 + (i % 7) as u8,
+                format!("LABEL{:02} {:04}", i, i * 37),
+            )
+        })
+        .collect()
+}
+
+/// Draws each label with its own call to `out_text_xy`,
+/// switching style and color as needed.
+fn per_label(labels: &[(i32, i32, Font, u8, String)]) {
+    for (x, y, font, color, text) in labels {
+        set_text_style(*font, false, false, false);
+        set_color(*color);
+        out_text_xy(*x, *y, text);
+    }
+}
+
+/// Draws the labels through a batch.
+fn batched(batch: &mut TextBatch, labels: &[(i32, i32, Font, u8, String)]) {
+    for (x, y, font, color, text) in labels {
+        set_text_style(*font, false, false, false);
+        set_color(*color);
+        batch.add(*x, *y, text);
+    }
+    batch.flush();
+}
+
+fn bench_text_batch(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let labels = labels();
+    let mut batch = TextBatch::new();
+
+    let mut group = c.benchmark_group("hud labels");
+    group.bench_function("TextBatch", |b| {
+        b.iter(|| batched(&mut batch, black_box(&labels)))
+    });
+    group.bench_function("out_text_xy per label", |b| {
+        b.iter(|| per_label(black_box(&labels)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_text_batch);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
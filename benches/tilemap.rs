@@ -0,0 +1,96 @@
+//! Benchmarks drawing a scrolling tile map
+//! against drawing each visible tile with `blit`.
+#![no_main]
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, Criterion};
+use dos_like::*;
+
+const TILE: u32 = 16;
+const MAP_W: u32 = 64;
+const MAP_H: u32 = 64;
+
+/// Creates a tile set of 64 tiles with a different color each.
+fn tileset() -> SpriteSheet {
+    let size = TILE * 8;
+    // built as a screen dump, which is the simplest image format to write
+    let mut dump = b"DLSD".to_vec();
+    dump.extend_from_slice(&(size as u16).to_le_bytes());
+    dump.extend_from_slice(&(size as u16).to_le_bytes());
+    dump.extend_from_slice(&256u16.to_le_bytes());
+    dump.extend_from_slice(&[0; 768]);
+    dump.extend((0..size * size).map(|i| {
+        let (x, y) = (i % size / TILE, i / size / TILE);
+        (y * 8 + x) as u8 + 16
+    }));
+    let image = load_screen_dump_from_memory(&dump).unwrap();
+    SpriteSheet::new(image, TILE, TILE, 0, 0)
+}
+
+/// Draws the visible tiles one by one with `blit`,
+/// leaving the partial ones at the edges to the clipping of the screen.
+fn per_tile(map: &[u16], tileset: &SpriteSheet, camera_x: i32, camera_y: i32, dst: Rect) {
+    let (tile_w, tile_h) = (TILE as i32, TILE as i32);
+    let first_x = camera_x.div_euclid(tile_w);
+    let first_y = camera_y.div_euclid(tile_h);
+    let last_x = (camera_x + dst.width as i32 - 1).div_euclid(tile_w);
+    let last_y = (camera_y + dst.height as i32 - 1).div_euclid(tile_h);
+    for ty in first_y.max(0)..=last_y.min(MAP_H as i32 - 1) {
+        for tx in first_x.max(0)..=last_x.min(MAP_W as i32 - 1) {
+            let tile = map[(ty as u32 * MAP_W + tx as u32) as usize];
+            if tile == EMPTY_TILE {
+                continue;
+            }
+            tileset.draw_frame(
+                tile as usize,
+                dst.x + tx * tile_w - camera_x,
+                dst.y + ty * tile_h - camera_y,
+                SpriteDrawOptions::new(),
+            );
+        }
+    }
+}
+
+fn bench_tilemap(c: &mut Criterion) {
+    set_video_mode(VideoMode::Graphics320x200);
+    let tileset = tileset();
+    let map: Vec<u16> = (0..MAP_W * MAP_H)
+        .map(|i| {
+            if i % 7 == 0 {
+                EMPTY_TILE
+            } else {
+                (i % 64) as u16
+            }
+        })
+        .collect();
+    let dst = Rect::new(0, 0, 320, 200);
+    // not aligned to the tiles, so the edge rows and columns are partial
+    let (camera_x, camera_y) = (101, 37);
+
+    let mut group = c.benchmark_group("tilemap");
+    group.bench_function("draw_tilemap", |b| {
+        b.iter(|| {
+            draw_tilemap(
+                black_box(&map),
+                MAP_W,
+                MAP_H,
+                &tileset,
+                camera_x,
+                camera_y,
+                dst,
+            )
+        })
+    });
+    group.bench_function("per tile blit", |b| {
+        b.iter(|| per_tile(black_box(&map), &tileset, camera_x, camera_y, dst))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_tilemap);
+
+dos_main! {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
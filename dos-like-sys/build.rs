@@ -14,6 +14,7 @@ fn main() {
 
     println!("cargo:rerun-if-changed=dos-like/source/dos.c");
     println!("cargo:rerun-if-changed=dos-like/source/dos.h");
+    println!("cargo:rerun-if-changed=extras/extras.c");
 
     let compiled_lib_path = compile(doslike_path);
 
@@ -38,8 +39,20 @@ fn compile(source_path: &Path) -> PathBuf {
     // statically link dos-like source
     let mut build = cc::Build::new();
 
+    if cfg!(feature = "gl-loader") {
+        // take the GLEW compatibility header before any system header,
+        // so that the OpenGL functions are loaded through SDL instead
+        build.include("compat");
+        println!("cargo:rerun-if-changed=compat/GL/glew.h");
+    }
+
+    // the patched copy includes the engine libraries from the original location
+    let dos_c = patch_border_color(&source_path.join("source/dos.c"));
+
     build
-        .file(source_path.join("source/dos.c"))
+        .file(dos_c)
+        .file("extras/extras.c")
+        .include(source_path.join("source"))
         .warnings(false)
         .extra_warnings(false)
         .includes(include_paths)
@@ -62,6 +75,83 @@ fn compile(source_path: &Path) -> PathBuf {
     PathBuf::from("dos-like")
 }
 
+/// The variable holding the border color, defined in `extras/extras.c`.
+const BORDER_VARIABLE: &str = "dos_like_border_xbgr";
+
+/// Writes a copy of `dos.c` to the output directory
+/// which presents each frame with the border color
+/// set through `dos_like_set_border`,
+/// and returns its path.
+///
+/// Both `app_present` and `crtemu_present` take the border color
+/// as their last argument,
+/// which the engine always passes as black.
+fn patch_border_color(dos_c: &Path) -> PathBuf {
+    let source = std::fs::read_to_string(dos_c).expect("Could not read dos.c");
+    let mut patched = format!("extern volatile unsigned int {};\n", BORDER_VARIABLE);
+    let mut rest = source.as_str();
+    let mut count = 0;
+
+    while let Some(at) = find_present_call(rest) {
+        let args_start = at + rest[at..].find('(').unwrap() + 1;
+        match last_argument(&rest[args_start..]) {
+            Some((start, end)) => {
+                patched.push_str(&rest[..args_start + start]);
+                patched.push(' ');
+                patched.push_str(BORDER_VARIABLE);
+                rest = &rest[args_start + end..];
+                count += 1;
+            }
+            None => {
+                patched.push_str(&rest[..args_start]);
+                rest = &rest[args_start..];
+            }
+        }
+    }
+    patched.push_str(rest);
+
+    if count == 0 {
+        println!("cargo:warning=No frame presentation found in dos.c, the border color will not be shown");
+    }
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("dos.c");
+    std::fs::write(&out, patched).expect("Could not write the patched dos.c");
+    out
+}
+
+/// Finds the next call to `app_present` or `crtemu_present`.
+fn find_present_call(source: &str) -> Option<usize> {
+    let mut from = 0;
+    loop {
+        let at = from + source[from..].find("_present")?;
+        let name_start = source[..at]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .map_or(0, |i| i + 1);
+        let after = source[at + "_present".len()..].trim_start();
+        if matches!(&source[name_start..at], "app" | "crtemu") && after.starts_with('(') {
+            return Some(name_start);
+        }
+        from = at + "_present".len();
+    }
+}
+
+/// Finds the span of the last argument of a call,
+/// given the text right after its opening parenthesis.
+fn last_argument(args: &str) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in args.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ',' if depth == 0 => start = Some(i + 1),
+            ')' if depth == 0 => return start.map(|start| (start, i)),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
 fn compute_include_paths(fallback_path: impl AsRef<Path>) -> Vec<PathBuf> {
     let mut include_paths = vec![];
 
@@ -140,7 +230,9 @@ fn link() {
         println!("cargo:rustc-flags=-l SDL2");
     }
 
-    println!("cargo:rustc-flags=-l GLEW");
+    if !cfg!(feature = "gl-loader") {
+        println!("cargo:rustc-flags=-l GLEW");
+    }
     if cfg!(target_os = "macos") {
         println!("cargo:rustc-link-lib=framework=OpenGL");
     } else {
@@ -0,0 +1,117 @@
+/*
+ * Minimal GLEW compatibility header for dos-like.
+ *
+ * Used instead of the system GLEW when the `gl-loader` feature is enabled.
+ * The OpenGL entry points beyond OpenGL 1.1 which are used by the engine
+ * are loaded at run time via SDL_GL_GetProcAddress,
+ * so that only SDL2 and the system OpenGL library are needed.
+ */
+#ifndef DOS_LIKE_COMPAT_GLEW_H
+#define DOS_LIKE_COMPAT_GLEW_H
+
+#include <SDL.h>
+#include <SDL_opengl.h>
+
+#define GLEW_OK 0
+#define GLEW_VERSION_2_0 1
+
+static GLboolean glewExperimental = GL_FALSE;
+
+#define DOS_LIKE_GL_FUNCTIONS(X) \
+    X(PFNGLACTIVETEXTUREPROC, glActiveTexture) \
+    X(PFNGLATTACHSHADERPROC, glAttachShader) \
+    X(PFNGLBINDATTRIBLOCATIONPROC, glBindAttribLocation) \
+    X(PFNGLBINDBUFFERPROC, glBindBuffer) \
+    X(PFNGLBINDFRAMEBUFFERPROC, glBindFramebuffer) \
+    X(PFNGLBINDVERTEXARRAYPROC, glBindVertexArray) \
+    X(PFNGLBUFFERDATAPROC, glBufferData) \
+    X(PFNGLCHECKFRAMEBUFFERSTATUSPROC, glCheckFramebufferStatus) \
+    X(PFNGLCOMPILESHADERPROC, glCompileShader) \
+    X(PFNGLCREATEPROGRAMPROC, glCreateProgram) \
+    X(PFNGLCREATESHADERPROC, glCreateShader) \
+    X(PFNGLDELETEBUFFERSPROC, glDeleteBuffers) \
+    X(PFNGLDELETEFRAMEBUFFERSPROC, glDeleteFramebuffers) \
+    X(PFNGLDELETEPROGRAMPROC, glDeleteProgram) \
+    X(PFNGLDELETESHADERPROC, glDeleteShader) \
+    X(PFNGLDELETEVERTEXARRAYSPROC, glDeleteVertexArrays) \
+    X(PFNGLDETACHSHADERPROC, glDetachShader) \
+    X(PFNGLDISABLEVERTEXATTRIBARRAYPROC, glDisableVertexAttribArray) \
+    X(PFNGLENABLEVERTEXATTRIBARRAYPROC, glEnableVertexAttribArray) \
+    X(PFNGLFRAMEBUFFERTEXTURE2DPROC, glFramebufferTexture2D) \
+    X(PFNGLGENBUFFERSPROC, glGenBuffers) \
+    X(PFNGLGENFRAMEBUFFERSPROC, glGenFramebuffers) \
+    X(PFNGLGENVERTEXARRAYSPROC, glGenVertexArrays) \
+    X(PFNGLGETPROGRAMINFOLOGPROC, glGetProgramInfoLog) \
+    X(PFNGLGETPROGRAMIVPROC, glGetProgramiv) \
+    X(PFNGLGETSHADERINFOLOGPROC, glGetShaderInfoLog) \
+    X(PFNGLGETSHADERIVPROC, glGetShaderiv) \
+    X(PFNGLGETUNIFORMLOCATIONPROC, glGetUniformLocation) \
+    X(PFNGLLINKPROGRAMPROC, glLinkProgram) \
+    X(PFNGLSHADERSOURCEPROC, glShaderSource) \
+    X(PFNGLUNIFORM1FPROC, glUniform1f) \
+    X(PFNGLUNIFORM1IPROC, glUniform1i) \
+    X(PFNGLUNIFORM2FPROC, glUniform2f) \
+    X(PFNGLUNIFORM3FPROC, glUniform3f) \
+    X(PFNGLUNIFORM4FPROC, glUniform4f) \
+    X(PFNGLUNIFORMMATRIX4FVPROC, glUniformMatrix4fv) \
+    X(PFNGLUSEPROGRAMPROC, glUseProgram) \
+    X(PFNGLVERTEXATTRIBPOINTERPROC, glVertexAttribPointer)
+
+/* one function pointer per entry point, shadowing the prototypes */
+#define DOS_LIKE_GL_DECLARE(type, name) static type dos_like_##name = NULL;
+DOS_LIKE_GL_FUNCTIONS(DOS_LIKE_GL_DECLARE)
+#undef DOS_LIKE_GL_DECLARE
+
+#define glActiveTexture dos_like_glActiveTexture
+#define glAttachShader dos_like_glAttachShader
+#define glBindAttribLocation dos_like_glBindAttribLocation
+#define glBindBuffer dos_like_glBindBuffer
+#define glBindFramebuffer dos_like_glBindFramebuffer
+#define glBindVertexArray dos_like_glBindVertexArray
+#define glBufferData dos_like_glBufferData
+#define glCheckFramebufferStatus dos_like_glCheckFramebufferStatus
+#define glCompileShader dos_like_glCompileShader
+#define glCreateProgram dos_like_glCreateProgram
+#define glCreateShader dos_like_glCreateShader
+#define glDeleteBuffers dos_like_glDeleteBuffers
+#define glDeleteFramebuffers dos_like_glDeleteFramebuffers
+#define glDeleteProgram dos_like_glDeleteProgram
+#define glDeleteShader dos_like_glDeleteShader
+#define glDeleteVertexArrays dos_like_glDeleteVertexArrays
+#define glDetachShader dos_like_glDetachShader
+#define glDisableVertexAttribArray dos_like_glDisableVertexAttribArray
+#define glEnableVertexAttribArray dos_like_glEnableVertexAttribArray
+#define glFramebufferTexture2D dos_like_glFramebufferTexture2D
+#define glGenBuffers dos_like_glGenBuffers
+#define glGenFramebuffers dos_like_glGenFramebuffers
+#define glGenVertexArrays dos_like_glGenVertexArrays
+#define glGetProgramInfoLog dos_like_glGetProgramInfoLog
+#define glGetProgramiv dos_like_glGetProgramiv
+#define glGetShaderInfoLog dos_like_glGetShaderInfoLog
+#define glGetShaderiv dos_like_glGetShaderiv
+#define glGetUniformLocation dos_like_glGetUniformLocation
+#define glLinkProgram dos_like_glLinkProgram
+#define glShaderSource dos_like_glShaderSource
+#define glUniform1f dos_like_glUniform1f
+#define glUniform1i dos_like_glUniform1i
+#define glUniform2f dos_like_glUniform2f
+#define glUniform3f dos_like_glUniform3f
+#define glUniform4f dos_like_glUniform4f
+#define glUniformMatrix4fv dos_like_glUniformMatrix4fv
+#define glUseProgram dos_like_glUseProgram
+#define glVertexAttribPointer dos_like_glVertexAttribPointer
+
+/* loads all entry points, must be called with a current GL context */
+static GLenum glewInit(void) {
+#define DOS_LIKE_GL_LOAD(type, name) dos_like_##name = (type)SDL_GL_GetProcAddress(#name);
+    DOS_LIKE_GL_FUNCTIONS(DOS_LIKE_GL_LOAD)
+#undef DOS_LIKE_GL_LOAD
+    return GLEW_OK;
+}
+
+static const GLubyte* glewGetErrorString(GLenum error) {
+    (void)error;
+    return (const GLubyte*)"GL loader error";
+}
+
+#endif /* DOS_LIKE_COMPAT_GLEW_H */
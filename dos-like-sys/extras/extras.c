@@ -0,0 +1,18 @@
+/* Functions needed by the Rust bindings which are not part of dos.h.
+   They are compiled along with the engine, with the same C runtime. */
+
+#include <stdlib.h>
+
+/* The color of the window area outside of the screen, in XBGR.
+   The build script patches the engine to present frames with it. */
+volatile unsigned int dos_like_border_xbgr = 0;
+
+/* Sets the color of the window area outside of the screen, in XBGR. */
+void dos_like_set_border(unsigned int xbgr) {
+    dos_like_border_xbgr = xbgr;
+}
+
+/* Frees memory allocated by the engine, such as the pixels returned by loadgif. */
+void dos_like_free(void* ptr) {
+    free(ptr);
+}
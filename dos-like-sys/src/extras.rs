@@ -0,0 +1,62 @@
+//! Hand written bindings to functions which are not part of `dos.h`:
+//! those compiled along with the engine from `extras/extras.c`,
+//! and a few of the platform layer underneath the engine,
+//! for what the engine does not report, such as the window focus.
+
+use std::os::raw::{c_uint, c_void};
+
+extern "C" {
+    /// Sets the color of the window area outside of the screen,
+    /// as `0xXXBBGGRR`.
+    ///
+    /// The engine is patched by the build script
+    /// to present each frame with this border color,
+    /// which is black by default.
+    pub fn dos_like_set_border(xbgr: c_uint);
+
+    /// Frees memory allocated by the engine,
+    /// such as the pixels returned by [`loadgif`](crate::loadgif),
+    /// with the same allocator that the engine uses.
+    pub fn dos_like_free(ptr: *mut c_void);
+}
+
+/// A window of SDL,
+/// which the engine runs on in Linux and macOS.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[repr(C)]
+#[derive(Debug)]
+pub struct SDL_Window {
+    _unused: [u8; 0],
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+extern "C" {
+    /// Gets the window of the engine if it has the keyboard focus,
+    /// or null otherwise.
+    pub fn SDL_GetKeyboardFocus() -> *mut SDL_Window;
+}
+
+/// A window handle of the Windows API,
+/// which the engine runs on in Windows.
+#[cfg(windows)]
+pub type HWND = *mut c_void;
+
+#[cfg(windows)]
+#[link(name = "user32")]
+extern "system" {
+    /// Gets the window in the foreground,
+    /// which may belong to another process,
+    /// or null if there is none.
+    pub fn GetForegroundWindow() -> HWND;
+
+    /// Gets the thread which created a window,
+    /// and writes the identifier of its process to `process_id`.
+    pub fn GetWindowThreadProcessId(window: HWND, process_id: *mut u32) -> u32;
+}
+
+#[cfg(windows)]
+#[link(name = "kernel32")]
+extern "system" {
+    /// Gets the identifier of the calling process.
+    pub fn GetCurrentProcessId() -> u32;
+}
@@ -4,7 +4,9 @@
 //! 
 //! [1]: https://github.com/mattiasgustavsson/dos-like
 //! 
-//! The bindings are directly generated from the original source code.
+//! The bindings are directly generated from the original source code,
+//! save for a few functions needed in addition,
+//! such as [`dos_like_free`].
 //!
 //! ## Using
 //! 
@@ -29,5 +31,7 @@
 
 #![allow(nonstandard_style)]
 mod bindings;
+mod extras;
 
 pub use bindings::*;
+pub use extras::*;
@@ -0,0 +1,62 @@
+//! HUD text example.
+//!
+//! Draws a grid of many short labels every frame,
+//! either with one `out_text_xy` call each
+//! or all at once through a `TextBatch`.
+//!
+//! Run with `--features profiling`
+//! to compare how much of the frame each way takes.
+//! Press space to switch between them.
+#![no_main]
+
+use dos_like::{
+    clear_screen, dos_main, key_state, out_text_xy, profile, read_keys, set_color,
+    set_double_buffer, set_text_style, set_video_mode, shutting_down, swap_buffers, wait_vbl, Font,
+    KeyCode, TextBatch, VideoMode,
+};
+
+/// The number of labels per row and column.
+const COLUMNS: i32 = 8;
+const ROWS: i32 = 20;
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+    set_text_style(Font::DEFAULT_8X8, false, false, false);
+
+    let mut batch = TextBatch::new();
+    let mut use_batch = true;
+    let mut frame: u32 = 0;
+    while !shutting_down() {
+        wait_vbl();
+        frame = frame.wrapping_add(1);
+
+        clear_screen();
+        let label = |i: i32| format!("{:03}", (frame as i32 + i * 7) % 1000);
+        if use_batch {
+            let _scope = profile::scope("batch");
+            for i in 0..COLUMNS * ROWS {
+                set_color((i % 15 + 1) as u8);
+                batch.add(i % COLUMNS * 40 + 4, i / COLUMNS * 10, label(i));
+            }
+            batch.flush();
+        } else {
+            let _scope = profile::scope("out_text_xy");
+            for i in 0..COLUMNS * ROWS {
+                set_color((i % 15 + 1) as u8);
+                out_text_xy(i % COLUMNS * 40 + 4, i / COLUMNS * 10, label(i));
+            }
+        }
+        profile::draw_overlay();
+        swap_buffers();
+
+        for key in read_keys() {
+            if key.is_pressed() && key.key_code() == KeyCode::KEY_SPACE {
+                use_batch = !use_batch;
+            }
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Example showcasing Deluxe Paint images with color cycling.
+//!
+//! Pass the path to an `.lbm` file to display it,
+//! with its color cycling ranges animated.
+#![no_main]
+
+use dos_like::{
+    dos_main, draw_image, key_state, load_lbm, rotate_palette, set_double_buffer, set_video_mode,
+    shutting_down, swap_buffers, wait_vbl, KeyCode, VideoMode,
+};
+
+dos_main! {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("Usage: lbm <file.lbm>");
+        std::process::exit(-1);
+    };
+    let lbm = load_lbm(&path).unwrap_or_else(|e| {
+        eprintln!("Could not load {}: {}", path, e);
+        std::process::exit(-2);
+    });
+
+    let width = lbm.image().width();
+    let height = lbm.image().height();
+    let mode = if width <= 320 && height <= 200 {
+        VideoMode::Graphics320x200
+    } else {
+        VideoMode::Graphics640x480
+    };
+    set_video_mode(mode);
+    set_double_buffer(true);
+
+    let ranges: Vec<_> = lbm
+        .cycle_ranges()
+        .iter()
+        .copied()
+        .filter(|r| r.active && !r.is_empty() && r.rate > 0)
+        .collect();
+    lbm.image().apply_palette();
+    // time accumulated towards the next step of each range
+    let mut elapsed = vec![0.; ranges.len()];
+
+    while !shutting_down() {
+        wait_vbl();
+
+        for (range, elapsed) in ranges.iter().zip(&mut elapsed) {
+            *elapsed += range.steps_per_second() / 60.;
+            let steps = elapsed.floor();
+            *elapsed -= steps;
+            let steps = if range.reverse { -steps } else { steps };
+            rotate_palette(range.low as usize..range.high as usize + 1, steps as isize);
+        }
+
+        let image = lbm.image();
+        draw_image(0, 0, image);
+        swap_buffers();
+
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Metronome example.
+//!
+//! Ticks on the percussion channel of the music synthesizer
+//! through scheduled note events,
+//! each scheduled on an exact frame of the frame counter,
+//! so the beats keep in time with the frames however long each frame takes.
+//! The measured time between beats is shown below the beat indicator.
+//!
+//! Up and down change the tempo.
+#![no_main]
+
+use std::time::Instant;
+
+use dos_like::{
+    curs_off, dos_main, frame_count, goto_xy, key_state, put_str, read_keys, schedule,
+    shutting_down, text_color, wait_vbl, AudioAction, AudioDelay, KeyCode,
+};
+
+/// The General MIDI percussion channel.
+const DRUMS: u8 = 9;
+/// High and low wood blocks.
+const ACCENT: u8 = 76;
+const TICK: u8 = 77;
+const BEATS_PER_BAR: u64 = 4;
+
+/// Schedules a tick on the given frame,
+/// released a few frames later.
+fn schedule_beat(frame: u64, accent: bool) {
+    let delay = frame.saturating_sub(frame_count()) as u32;
+    let note = if accent { ACCENT } else { TICK };
+    schedule(
+        AudioDelay::Frames(delay),
+        AudioAction::NoteOn {
+            channel: DRUMS,
+            note,
+            velocity: 120,
+        },
+    );
+    schedule(
+        AudioDelay::Frames(delay + 4),
+        AudioAction::NoteOff {
+            channel: DRUMS,
+            note,
+        },
+    );
+}
+
+dos_main! {
+    curs_off();
+    goto_xy(2, 2);
+    put_str("METRONOME DEMO");
+    goto_xy(2, 16);
+    put_str("UP/DOWN - change tempo    ESC - quit");
+
+    // 120 beats per minute at 70 frames per second
+    let mut frames_per_beat: u64 = 35;
+    let mut beat = 0;
+    let mut next_beat = frame_count() + 1;
+    let mut last_beat: Option<Instant> = None;
+
+    // keep one beat scheduled ahead of the one being heard
+    schedule_beat(next_beat, true);
+    while !shutting_down() {
+        wait_vbl();
+
+        if frame_count() >= next_beat {
+            let now = Instant::now();
+            if let Some(last) = last_beat {
+                goto_xy(2, 10);
+                put_str(format!("Last beat interval: {:7.1} ms", (now - last).as_secs_f64() * 1000.));
+            }
+            last_beat = Some(now);
+
+            for i in 0..BEATS_PER_BAR {
+                goto_xy(2 + i as u16 * 4, 8);
+                text_color(if i == beat { 14 } else { 8 });
+                put_str(if i == beat { "[*]" } else { "[ ]" });
+            }
+            text_color(7);
+
+            beat = (beat + 1) % BEATS_PER_BAR;
+            next_beat += frames_per_beat;
+            schedule_beat(next_beat, beat == 0);
+        }
+
+        goto_xy(2, 5);
+        put_str(format!(
+            "Tempo: {:3} BPM ({:2} frames per beat)   ",
+            70 * 60 / frames_per_beat,
+            frames_per_beat
+        ));
+        goto_xy(2, 6);
+        put_str(format!("Frame: {}", frame_count()));
+
+        // a new tempo applies from the beat after the one scheduled
+        for key in read_keys() {
+            if key.is_pressed() {
+                match key.key_code() {
+                    KeyCode::KEY_UP if frames_per_beat > 10 => frames_per_beat -= 1,
+                    KeyCode::KEY_DOWN if frames_per_beat < 70 => frames_per_beat += 1,
+                    _ => {}
+                }
+            }
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Particle system example.
+//!
+//! Click anywhere to set off an explosion:
+//! a burst of sparks falling through a fire ramp of the palette,
+//! with a noise burst played on the next sound channel in turn.
+//! The number of live particles is shown in the corner.
+#![no_main]
+
+use std::time::Duration;
+
+use dos_like::video::particles::{BurstParams, ParticleSystem};
+use dos_like::{
+    clear_screen, create_sound, dos_main, install_ramp, key_state, make_ramp, mouse_x, mouse_y,
+    out_text_xy, play_sound, read_keys, set_color, set_double_buffer, set_video_mode,
+    shutting_down, swap_buffers, wait_vbl, KeyCode, Sound, VideoMode,
+};
+
+/// The number of sound channels taken in turn by the explosions.
+const EXPLOSION_CHANNELS: u8 = 4;
+
+/// Creates a short burst of noise, fading out.
+fn explosion_sound() -> Sound {
+    let rate = 11025;
+    let mut rng: u32 = 0x2545_F491;
+    let samples: Vec<u16> = (0..rate / 2)
+        .map(|i| {
+            // xorshift
+            rng ^= rng << 13;
+            rng ^= rng >> 17;
+            rng ^= rng << 5;
+            let fade = 1. - i as f32 / (rate / 2) as f32;
+            let noise = (rng >> 16) as i16 as f32;
+            (noise * fade * fade) as i16 as u16
+        })
+        .collect();
+    create_sound(1, rate, &samples)
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+    // black to red to yellow to white over colors 32 to 95
+    install_ramp(32, &make_ramp((0, 0, 0), (255, 40, 0), 24, 2.2));
+    install_ramp(56, &make_ramp((255, 40, 0), (255, 220, 40), 24, 2.2));
+    install_ramp(80, &make_ramp((255, 220, 40), (255, 255, 255), 16, 2.2));
+
+    let sound = explosion_sound();
+    let mut channel = 0;
+    let mut sparks = ParticleSystem::new(4000);
+    let sparks_params = BurstParams::new()
+        .with_speed(30., 140.)
+        .with_lifetime(Duration::from_millis(600), Duration::from_millis(1600))
+        .with_colors(95, 32)
+        .with_gravity(90.);
+    // a few brighter, faster embers flying upwards
+    let embers_params = BurstParams::new()
+        .with_speed(120., 200.)
+        .with_direction(-2.4, -0.7)
+        .with_lifetime(Duration::from_millis(300), Duration::from_millis(700))
+        .with_colors(95, 56)
+        .with_gravity(200.);
+
+    while !shutting_down() {
+        wait_vbl();
+
+        for key in read_keys() {
+            if key.is_pressed() && key.key_code() == KeyCode::KEY_LBUTTON {
+                let origin = (mouse_x(), mouse_y());
+                sparks.spawn_burst(origin, 300, &sparks_params);
+                sparks.spawn_burst(origin, 40, &embers_params);
+                play_sound(channel, &sound, false, 255);
+                channel = (channel + 1) % EXPLOSION_CHANNELS;
+            }
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+
+        sparks.update(Duration::from_secs(1) / 70);
+
+        clear_screen();
+        sparks.draw();
+        set_color(15);
+        out_text_xy(4, 4, "CLICK TO EXPLODE");
+        out_text_xy(4, 188, format!("{:4} / {}", sparks.len(), sparks.capacity()));
+        swap_buffers();
+    }
+}
@@ -4,8 +4,8 @@
 #![no_main]
 
 use dos_like::{
-    key_state, screen_buffer, set_double_buffer, set_pal, set_video_mode, shutting_down,
-    swap_buffers_and_get, wait_vbl, KeyCode, VideoMode,
+    install_ramp, key_state, make_multi_ramp, set_double_buffer, set_video_mode, shutting_down,
+    wait_vbl, KeyCode, ScreenBuffer, VideoMode,
 };
 use std::os::raw::c_int;
 
@@ -18,17 +18,27 @@ dos_like::dos_main! {
     let w = 320;
     let h = 200;
 
-    //generate the palette
-    for x in 0..256 {
-        let r = (128. + 128. * (x as f64 * 3.1415 / 32.).sin()) as u8;
-        let g = (128. + 128. * (x as f64 * 3.1415 / 64.).sin()) as u8;
-        let b = (128. + 128. * (x as f64 * 3.1415 / 128.).sin()) as u8;
-        set_pal(x, r >> 2, g >> 2, b >> 2);
-    }
+    //generate the palette,
+    //a ramp which ends where it starts so that it can be rotated
+    let ramp = make_multi_ramp(
+        &[
+            (0.0, (16, 0, 64)),
+            (0.25, (255, 32, 128)),
+            (0.5, (255, 224, 64)),
+            (0.75, (32, 192, 255)),
+            (1.0, (16, 0, 64)),
+        ],
+        255,
+        2.2,
+    )
+    .unwrap();
+    install_ramp(0, &ramp);
+    //a ramp has up to 255 colors, the last one repeats the first
+    install_ramp(255, &ramp[..1]);
 
     //generate the plasma once
-    for y in 0..h {
-        for x in 0..w {
+    for (y, row) in plasma.iter_mut().enumerate() {
+        for (x, value) in row.iter_mut().enumerate() {
             //the plasma buffer is a sum of sines
             let color = (128.0
                 + (128.0 * (x as f32 / 32.).sin())
@@ -40,35 +50,34 @@ dos_like::dos_main! {
                 + (128.0 * (x * x + y * y) as f64 / 16.).sqrt().sin() as f32)
                 as c_int
                 / 4;
-                plasma[y][x] = color;
+            *value = color;
         }
     }
 
     let mut palette_shift = 0;
 
-    unsafe {
-        let mut buffer = screen_buffer();
+    let mut buffer = ScreenBuffer::acquire().unwrap();
 
-        //start the animation loop, it rotates the palette
-        while !shutting_down() {
-            wait_vbl();
+    //start the animation loop, it rotates the palette
+    while !shutting_down() {
+        wait_vbl();
 
-            //the parameter to shift the palette varies with time
-            palette_shift += 1;
+        //the parameter to shift the palette varies with time
+        palette_shift += 1;
 
-            //draw every pixel again, with the shifted palette color
-            for y in 0..h {
-                for x in 0..w {
-                    buffer[x + y * 320] = (plasma[y][x] + palette_shift) as u8;
-                }
+        //draw every pixel again, with the shifted palette color
+        let pixels = buffer.as_mut_slice();
+        for y in 0..h {
+            for x in 0..w {
+                pixels[x + y * 320] = (plasma[y][x] + palette_shift) as u8;
             }
+        }
 
-            //make everything visible
-            buffer = swap_buffers_and_get();
+        //make everything visible
+        buffer.swap();
 
-            if key_state(KeyCode::KEY_ESCAPE) {
-                break;
-            }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
         }
     }
 }
@@ -0,0 +1,211 @@
+//! Raycaster example.
+//!
+//! Walls are drawn one screen column at a time with `draw_tex_column`,
+//! and darkened with distance through shading tables.
+//! The textures are generated at start up.
+//!
+//! Use the arrow keys to move and turn.
+#![no_main]
+
+use dos_like::{
+    bar, dos_main, draw_tex_column, key_state, present, set_color, set_double_buffer, set_pal,
+    set_video_mode, shutting_down, wait_vbl, KeyCode, Texture, VideoMode,
+};
+
+const MAP: [&[u8; 12]; 12] = [
+    b"111111111111",
+    b"1..........1",
+    b"1..2....2..1",
+    b"1..........1",
+    b"1....11....1",
+    b"1....1.....1",
+    b"1..........1",
+    b"1.22....2221",
+    b"1..2.......1",
+    b"1..2...1...1",
+    b"1..........1",
+    b"111111111111",
+];
+
+const TEX_SIZE: usize = 64;
+
+/// The number of colors in each texture's ramp.
+const RAMP: usize = 64;
+
+/// The number of distance shading levels.
+const SHADES: usize = 8;
+
+const CEILING_COLOR: u8 = 200;
+const FLOOR_COLOR: u8 = 201;
+
+/// Generates a brick texture in the first color ramp.
+fn brick_texture() -> Vec<u8> {
+    let mut pixels = vec![0; TEX_SIZE * TEX_SIZE];
+    for y in 0..TEX_SIZE {
+        for x in 0..TEX_SIZE {
+            let row = y / 16;
+            let shift = if row % 2 == 0 { 0 } else { 16 };
+            let mortar = y % 16 == 0 || (x + shift) % 32 == 0;
+            let noise = (x * 7 + y * 13) % 9;
+            pixels[y * TEX_SIZE + x] = if mortar { 20 } else { 44 + noise } as u8;
+        }
+    }
+    pixels
+}
+
+/// Generates a stone texture in the second color ramp.
+fn stone_texture() -> Vec<u8> {
+    let mut pixels = vec![0; TEX_SIZE * TEX_SIZE];
+    for y in 0..TEX_SIZE {
+        for x in 0..TEX_SIZE {
+            pixels[y * TEX_SIZE + x] = (RAMP + 24 + ((x ^ y) & 31)) as u8;
+        }
+    }
+    pixels
+}
+
+/// Builds the tables which darken the colors of both ramps,
+/// from the brightest to the darkest.
+fn shade_tables() -> Vec<[u8; 256]> {
+    (0..SHADES)
+        .map(|level| {
+            let mut table = [0; 256];
+            for (i, entry) in table.iter_mut().enumerate() {
+                *entry = if i < RAMP * 2 {
+                    let (ramp, shade) = (i / RAMP, i % RAMP);
+                    (ramp * RAMP + shade * (SHADES - level) / SHADES) as u8
+                } else {
+                    i as u8
+                };
+            }
+            table
+        })
+        .collect()
+}
+
+fn is_wall(x: f32, y: f32) -> bool {
+    MAP.get(y as usize)
+        .and_then(|row| row.get(x as usize))
+        .is_none_or(|&cell| cell != b'.')
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    for i in 0..RAMP {
+        let v = i as u8;
+        set_pal(i, v, v / 3, v / 4);
+        set_pal(RAMP + i, v, v, v);
+    }
+    set_pal(CEILING_COLOR as usize, 8, 8, 16);
+    set_pal(FLOOR_COLOR as usize, 16, 12, 8);
+
+    let textures = [brick_texture(), stone_texture()];
+    let shades = shade_tables();
+
+    let (mut pos_x, mut pos_y) = (2.5f32, 2.5f32);
+    let mut angle = 0.3f32;
+
+    while !shutting_down() {
+        wait_vbl();
+
+        if key_state(KeyCode::KEY_LEFT) {
+            angle -= 0.05;
+        }
+        if key_state(KeyCode::KEY_RIGHT) {
+            angle += 0.05;
+        }
+        let (dir_y, dir_x) = angle.sin_cos();
+        let step = if key_state(KeyCode::KEY_UP) {
+            0.08
+        } else if key_state(KeyCode::KEY_DOWN) {
+            -0.08
+        } else {
+            0.
+        };
+        if !is_wall(pos_x + dir_x * step, pos_y) {
+            pos_x += dir_x * step;
+        }
+        if !is_wall(pos_x, pos_y + dir_y * step) {
+            pos_y += dir_y * step;
+        }
+
+        set_color(CEILING_COLOR);
+        bar(0, 0, 320, 100);
+        set_color(FLOOR_COLOR);
+        bar(0, 100, 320, 100);
+
+        // the camera plane is perpendicular to the direction
+        let (plane_x, plane_y) = (-dir_y * 0.66, dir_x * 0.66);
+        for x in 0..320 {
+            let camera = 2. * x as f32 / 320. - 1.;
+            let (ray_x, ray_y) = (dir_x + plane_x * camera, dir_y + plane_y * camera);
+
+            // step through the grid until a wall is hit
+            let (mut map_x, mut map_y) = (pos_x as i32, pos_y as i32);
+            let delta_x = (1. / ray_x).abs();
+            let delta_y = (1. / ray_y).abs();
+            let (step_x, mut side_x) = if ray_x < 0. {
+                (-1, (pos_x - map_x as f32) * delta_x)
+            } else {
+                (1, (map_x as f32 + 1. - pos_x) * delta_x)
+            };
+            let (step_y, mut side_y) = if ray_y < 0. {
+                (-1, (pos_y - map_y as f32) * delta_y)
+            } else {
+                (1, (map_y as f32 + 1. - pos_y) * delta_y)
+            };
+            let mut vertical;
+            let cell = loop {
+                if side_x < side_y {
+                    side_x += delta_x;
+                    map_x += step_x;
+                    vertical = false;
+                } else {
+                    side_y += delta_y;
+                    map_y += step_y;
+                    vertical = true;
+                }
+                let cell = MAP[map_y as usize][map_x as usize];
+                if cell != b'.' {
+                    break cell;
+                }
+            };
+            let distance = if vertical {
+                side_y - delta_y
+            } else {
+                side_x - delta_x
+            }
+            .max(0.01);
+
+            let height = (200. / distance) as u32;
+            let y_top = 100 - height as i32 / 2;
+            let wall_x = if vertical {
+                pos_x + distance * ray_x
+            } else {
+                pos_y + distance * ray_y
+            };
+            let tex_x = (wall_x.fract() * TEX_SIZE as f32) as u32;
+            let texture = &textures[(cell - b'1') as usize % textures.len()];
+            // walls facing north and south are a bit darker
+            let shade = ((distance * 1.2) as usize + vertical as usize).min(SHADES - 1);
+
+            draw_tex_column(
+                x,
+                y_top,
+                height,
+                Texture::new(texture, TEX_SIZE as u16, TEX_SIZE as u16),
+                tex_x,
+                None,
+                Some(&shades[shade]),
+            );
+        }
+
+        present();
+
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
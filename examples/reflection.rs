@@ -0,0 +1,96 @@
+//! Example showcasing in-place flipping of screen regions
+//! and palette adjustments.
+//!
+//! A landscape is drawn on the top half of the screen,
+//! and a darkened water reflection of it on the bottom half,
+//! while the whole scene slowly goes from day to night and back.
+#![no_main]
+
+use dos_like::{
+    adjust_palette, blit, dos_main, fill_circle, fill_poly, flip_screen_rect, h_line, key_state,
+    pixels_rect, set_color, set_pal, set_video_mode, shutting_down, wait_vbl, FlipAxis, KeyCode,
+    PaletteSnapshot, Rect, VideoMode,
+};
+
+const WIDTH: u16 = 320;
+const HORIZON: u16 = 100;
+
+/// The first color of the darker half of the palette.
+const DARK: u8 = 128;
+
+/// Sets up colors 0 to 127 for the landscape,
+/// and colors 128 to 255 as darker, bluish copies of them.
+fn setup_palette() {
+    // sky gradient
+    for i in 0..64 {
+        set_pal(i, i as u8 / 4, i as u8 / 2 + 8, 63);
+    }
+    // sun
+    set_pal(64, 63, 60, 30);
+    // mountains
+    set_pal(65, 20, 24, 30);
+    set_pal(66, 12, 16, 22);
+
+    let baseline = PaletteSnapshot::capture();
+    adjust_palette(&baseline, 0.55, 0.8);
+    let dark = PaletteSnapshot::capture();
+    baseline.restore();
+    for (i, &[r, g, b]) in dark.colors()[..DARK as usize].iter().enumerate() {
+        set_pal(DARK as usize + i, r, g, (b + 6).min(63));
+    }
+}
+
+fn draw_landscape() {
+    for y in 0..HORIZON {
+        h_line(0, y as i32, WIDTH, (y * 64 / HORIZON) as u8);
+    }
+    set_color(64);
+    fill_circle(230, 55, 18);
+    set_color(66);
+    fill_poly(&[0, 100, 0, 70, 60, 40, 110, 75, 150, 55, 200, 100]);
+    set_color(65);
+    fill_poly(&[120, 100, 190, 45, 250, 80, 290, 50, 320, 75, 320, 100]);
+}
+
+fn draw_reflection() {
+    let mut pixels = Vec::new();
+    let read = pixels_rect(Rect::new(0, 0, WIDTH, HORIZON), &mut pixels);
+    for p in &mut pixels {
+        *p += DARK;
+    }
+    blit(
+        0,
+        HORIZON as i32,
+        &pixels,
+        read.width,
+        read.height,
+        0,
+        0,
+        read.width,
+        read.height,
+    );
+    flip_screen_rect(0, HORIZON as i32, WIDTH, HORIZON, FlipAxis::Vertical);
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    setup_palette();
+    draw_landscape();
+    draw_reflection();
+
+    let baseline = PaletteSnapshot::capture();
+    let mut frame = 0_u32;
+    while !shutting_down() {
+        wait_vbl();
+        frame += 1;
+
+        // one full day every 10 seconds
+        let t = frame as f32 / 600. * std::f32::consts::TAU;
+        let brightness = 0.65 + 0.35 * t.cos();
+        adjust_palette(&baseline, brightness, 1.);
+
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
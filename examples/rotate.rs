@@ -0,0 +1,66 @@
+//! Rotated sprites example.
+//!
+//! Spins copies of an image around the screen with `rotate_blit`,
+//! each at its own angle and scale,
+//! with the darkest color of the image as its color key.
+//!
+//! Run with `--features profiling`
+//! to see how much of the frame the rotated sprites take.
+//! Press space to switch texture wrapping on and off.
+#![no_main]
+
+use dos_like::{
+    asset_path, clear_screen, dos_main, key_state, load_gif, profile, read_keys, rotate_blit,
+    set_double_buffer, set_video_mode, shutting_down, swap_buffers, wait_vbl, KeyCode, VideoMode,
+};
+
+/// The number of sprites drawn each frame.
+const SPRITES: usize = 12;
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+    let mut image = asset_path("assets/rotozoom.gif")
+        .and_then(|path| load_gif(path.to_string_lossy()))
+        .unwrap_or_else(|e| {
+            eprintln!("Could not load rotozoom.gif: {}", e);
+            std::process::exit(-2);
+        });
+
+    image.apply_palette();
+    let palette = image.palette_colors();
+    let darkest = (0..palette.len())
+        .min_by_key(|&i| palette[i].r as u32 + palette[i].g as u32 + palette[i].b as u32)
+        .unwrap_or(0) as u8;
+    image.set_color_key(Some(darkest));
+
+    let mut time: f32 = 0.;
+    let mut wrap = false;
+    while !shutting_down() {
+        wait_vbl();
+        time += 1. / 70.;
+
+        clear_screen();
+        {
+            let _scope = profile::scope("rotate_blit");
+            for i in 0..SPRITES {
+                let phase = i as f32 * std::f32::consts::TAU / SPRITES as f32;
+                let x = 160 + ((time * 0.5 + phase).cos() * 110.) as i32;
+                let y = 100 + ((time * 0.5 + phase).sin() * 70.) as i32;
+                let scale = 0.2 + 0.15 * (time * 2. + phase).sin().abs();
+                rotate_blit(x, y, &image, time * (1. + i as f32 * 0.2), scale, wrap);
+            }
+        }
+        profile::draw_overlay();
+        swap_buffers();
+
+        for key in read_keys() {
+            if key.is_pressed() && key.key_code() == KeyCode::KEY_SPACE {
+                wrap = !wrap;
+            }
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
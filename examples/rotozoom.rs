@@ -2,54 +2,57 @@
 //!
 //! <https://seancode.com/demofx/>
 //!
+//! Run with `--features profiling`
+//! to see how much of the frame the rotozoom takes.
+//! Press space to switch between [`affine_blit`]
+//! and the original per-pixel floating point loop.
+//!
 //! See end of file for license
 #![no_main]
 
 use dos_like::{
-    dos_main, key_state, load_gif, screen_buffer, set_double_buffer, set_pal, set_video_mode,
-    shutting_down, swap_buffers_and_get, wait_vbl, KeyCode, VideoMode,
+    affine_blit, asset_path, dos_main, key_state, load_gif, profile, read_keys, set_double_buffer,
+    set_video_mode, shutting_down, wait_vbl, KeyCode, Rect, ScreenBuffer, VideoMode,
 };
 use std::f32::consts::PI;
 
 dos_main! {
     set_video_mode(VideoMode::Graphics320x200);
     set_double_buffer(true);
-    let gif = load_gif("assets/rotozoom.gif").unwrap_or_else(|_| {
-        eprintln!("Could not load rotozoom.gif");
-        std::process::exit(-2);
-    });
-
+    let gif = asset_path("assets/rotozoom.gif")
+        .and_then(|path| load_gif(path.to_string_lossy()))
+        .unwrap_or_else(|e| {
+            eprintln!("Could not load rotozoom.gif: {}", e);
+            std::process::exit(-2);
+        });
 
-    let palette = gif.raw_palette();
-    let palcount = gif.palette_count();
     let gif_width = gif.width() as i32;
     let gif_height = gif.height() as i32;
     let gif_data = gif.data();
 
-    for i in 0..palcount as usize {
-        set_pal(
-            i,
-            palette[3 * i + 0],
-            palette[3 * i + 1],
-            palette[3 * i + 2],
-        );
-    }
+    gif.apply_palette();
 
-    unsafe {
-        let mut buffer = screen_buffer();
-        let mut angle = 0.;
-        while !shutting_down() {
-            wait_vbl();
-            let s = (angle * PI / 180.).sin();
-            let c = (angle * PI / 180.).cos();
-            angle = ((angle + 1.) as i32 % 360) as f32;
+    let mut buffer = ScreenBuffer::acquire().unwrap();
+    let mut angle = 0.;
+    let mut use_float_loop = false;
+    while !shutting_down() {
+        wait_vbl();
+        let s = (angle * PI / 180.).sin();
+        let c = (angle * PI / 180.).cos();
+        angle = ((angle + 1.) as i32 % 360) as f32;
+        let zoom = s + 1.;
+
+        if use_float_loop {
+            // the original per-pixel loop, for comparison
+            let _scope = profile::scope("float");
+            let pixels = buffer.as_mut_slice();
             let mut dest_ofs = 0;
             for y in 0..200 {
                 for x in 0..320 {
                     let x = x as f32;
                     let y = y as f32;
-                    let mut u = ((x * c - y * s) * (s + 1.) + 64.) as i32 % gif_width;
-                    let mut v = ((x * s + y * c) * (s + 1.) + 64.) as i32 % gif_height;
+                    let mut u = ((x * c - y * s) * zoom + 64.) as i32 % gif_width;
+                    let mut v = ((x * s + y * c) * zoom + 64.) as i32 % gif_height;
                     if u < 0 {
                         u += gif_width;
                     }
@@ -57,16 +60,33 @@ dos_main! {
                         v += gif_height;
                     }
                     let src_ofs = u + v * gif_width;
-                    buffer[dest_ofs] = gif_data[src_ofs as usize];
+                    pixels[dest_ofs] = gif_data[src_ofs as usize];
                     dest_ofs += 1;
                 }
             }
-            buffer = swap_buffers_and_get();
+        } else {
+            let _scope = profile::scope("affine");
+            affine_blit(
+                Rect::new(0, 0, 320, 200),
+                &gif,
+                (64., 64.),
+                (c * zoom, s * zoom),
+                (-s * zoom, c * zoom),
+                true,
+                None,
+            );
+        }
+        profile::draw_overlay();
+        buffer.swap();
 
-            if key_state(KeyCode::KEY_ESCAPE) {
-                break;
+        for key in read_keys() {
+            if key.is_pressed() && key.key_code() == KeyCode::KEY_SPACE {
+                use_float_loop = !use_float_loop;
             }
         }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
     }
 }
 
@@ -0,0 +1,82 @@
+//! Scrolling example.
+//!
+//! Draws a simple vector map in world coordinates
+//! and scrolls over it with the arrow keys,
+//! letting the draw offset do the camera translation.
+#![no_main]
+
+use dos_like::{
+    bar, circle, clear_screen, dos_main, draw_offset, fill_circle, key_state, line, out_text_xy,
+    present, rectangle, reset_draw_offset, set_color, set_double_buffer, set_draw_offset,
+    set_video_mode, shutting_down, wait_vbl, KeyCode, VideoMode,
+};
+
+/// The size of the world in pixels.
+const WORLD_WIDTH: i32 = 1024;
+const WORLD_HEIGHT: i32 = 768;
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    let mut camera_x = 0;
+    let mut camera_y = 0;
+
+    while !shutting_down() {
+        wait_vbl();
+
+        if key_state(KeyCode::KEY_LEFT) {
+            camera_x -= 2;
+        }
+        if key_state(KeyCode::KEY_RIGHT) {
+            camera_x += 2;
+        }
+        if key_state(KeyCode::KEY_UP) {
+            camera_y -= 2;
+        }
+        if key_state(KeyCode::KEY_DOWN) {
+            camera_y += 2;
+        }
+
+        clear_screen();
+
+        // everything here is drawn in world coordinates
+        set_draw_offset(-camera_x, -camera_y);
+
+        // world border
+        set_color(15);
+        rectangle(0, 0, WORLD_WIDTH as u16, WORLD_HEIGHT as u16);
+
+        // grid
+        set_color(8);
+        for x in (64..WORLD_WIDTH).step_by(64) {
+            line(x, 0, x, WORLD_HEIGHT - 1);
+        }
+        for y in (64..WORLD_HEIGHT).step_by(64) {
+            line(0, y, WORLD_WIDTH - 1, y);
+        }
+
+        // some landmarks, partially crossing the screen edges
+        set_color(4);
+        bar(100, 80, 120, 60);
+        set_color(2);
+        fill_circle(400, 300, 50);
+        set_color(14);
+        circle(700, 500, 90);
+        set_color(9);
+        line(0, 0, WORLD_WIDTH - 1, WORLD_HEIGHT - 1);
+        out_text_xy(110, 90, "HOME");
+
+        // HUD is drawn in screen coordinates
+        let (dx, dy) = draw_offset();
+        reset_draw_offset();
+        set_color(15);
+        out_text_xy(4, 4, format!("CAMERA {},{}", -dx, -dy));
+
+        present();
+
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
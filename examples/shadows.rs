@@ -0,0 +1,178 @@
+//! Sprite shadows example.
+//!
+//! A few characters wander over a checkered floor,
+//! each with a drop shadow drawn from its own silhouette.
+//! Press space to switch between shadows which darken the floor
+//! through a table of darker colors
+//! and shadows in a flat color.
+//! Use the arrow keys to move the light around.
+#![no_main]
+
+use dos_like::{
+    bar, blit_silhouette_flipped, dos_main, get_palette, install_ramp, key_state, make_ramp,
+    mask_blit_flipped, nearest_color, out_text_xy, read_keys, set_color, set_double_buffer,
+    set_pal, set_video_mode, shutting_down, swap_buffers, wait_vbl, Image, KeyCode, Rect,
+    Silhouette, VideoMode,
+};
+
+const SPRITE_WIDTH: u16 = 16;
+const SPRITE_HEIGHT: u16 = 24;
+/// The color around the character in the sprite.
+const COLOR_KEY: u8 = 0;
+/// The flat shadow color.
+const SHADOW_COLOR: u8 = 1;
+
+/// Draws a round character looking to the right.
+fn character_sprite(body: u8) -> Image {
+    let (w, h) = (SPRITE_WIDTH as i32, SPRITE_HEIGHT as i32);
+    let pixels = (0..h)
+        .flat_map(|y| (0..w).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (dx, dy) = (x * 2 - w + 1, y * 2 - 17);
+            let eye = (x == 10 || x == 12) && (y == 6 || y == 7);
+            let feet = y >= 20 && (x == 4 || x == 5 || x == 10 || x == 11);
+            if eye {
+                15
+            } else if dx * dx + dy * dy * 2 < 14 * 14 || feet {
+                body
+            } else {
+                COLOR_KEY
+            }
+        })
+        .collect();
+    Image::from_data(SPRITE_WIDTH as u32, SPRITE_HEIGHT as u32, pixels, &[])
+        .expect("sprite should be valid")
+}
+
+/// A character walking back and forth.
+struct Character {
+    sprite: Image,
+    x: i32,
+    y: i32,
+    speed: i32,
+}
+
+impl Character {
+    fn new(body: u8, x: i32, y: i32, speed: i32) -> Self {
+        Character {
+            sprite: character_sprite(body),
+            x,
+            y,
+            speed,
+        }
+    }
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    set_pal(SHADOW_COLOR as usize, 4, 4, 8);
+    set_pal(15, 63, 63, 63);
+    // the floor, in two checkered ramps
+    install_ramp(16, &make_ramp((40, 80, 40), (120, 220, 120), 16, 2.2));
+    install_ramp(32, &make_ramp((80, 60, 30), (220, 180, 100), 16, 2.2));
+    // the characters
+    set_pal(48, 63, 20, 10);
+    set_pal(49, 15, 30, 63);
+    set_pal(50, 60, 55, 10);
+
+    // each color darkened to about half
+    let palette = get_palette();
+    let darker: [u8; 256] = std::array::from_fn(|i| {
+        let color = palette[i];
+        nearest_color(color.r / 2, color.g / 2, color.b / 2)
+    });
+
+    let mut characters = [
+        Character::new(48, 40, 60, 1),
+        Character::new(49, 200, 100, -2),
+        Character::new(50, 120, 140, 3),
+    ];
+    let frame = Rect::new(0, 0, SPRITE_WIDTH, SPRITE_HEIGHT);
+    let (mut light_x, mut light_y) = (-4, -3);
+    let mut darken = true;
+
+    while !shutting_down() {
+        wait_vbl();
+
+        for key in read_keys() {
+            if key.is_pressed() && key.key_code() == KeyCode::KEY_SPACE {
+                darken = !darken;
+            }
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+        // the shadow falls away from the light
+        if key_state(KeyCode::KEY_LEFT) {
+            light_x = (light_x - 1).max(-8);
+        }
+        if key_state(KeyCode::KEY_RIGHT) {
+            light_x = (light_x + 1).min(8);
+        }
+        if key_state(KeyCode::KEY_UP) {
+            light_y = (light_y - 1).max(-8);
+        }
+        if key_state(KeyCode::KEY_DOWN) {
+            light_y = (light_y + 1).min(8);
+        }
+
+        for character in &mut characters {
+            character.x += character.speed;
+            if character.x < 0 || character.x > 320 - SPRITE_WIDTH as i32 {
+                character.speed = -character.speed;
+                character.x += character.speed;
+            }
+        }
+
+        for y in 0..10 {
+            for x in 0..16 {
+                let ramp = if (x + y) % 2 == 0 { 16 } else { 32 };
+                set_color(ramp + 4 + (y as u8 + x as u8) % 8);
+                bar(x * 20, y * 20, 20, 20);
+            }
+        }
+
+        // shadows first, so that no character is under another's shadow
+        for character in &characters {
+            let flip = character.speed < 0;
+            let silhouette = if darken {
+                Silhouette::Remap(&darker)
+            } else {
+                Silhouette::Color(SHADOW_COLOR)
+            };
+            blit_silhouette_flipped(
+                character.x - light_x,
+                character.y - light_y,
+                character.sprite.data(),
+                SPRITE_WIDTH,
+                SPRITE_HEIGHT,
+                frame,
+                flip,
+                false,
+                COLOR_KEY,
+                silhouette,
+            );
+        }
+        for character in &characters {
+            let flip = character.speed < 0;
+            mask_blit_flipped(
+                character.x,
+                character.y,
+                character.sprite.data(),
+                SPRITE_WIDTH,
+                SPRITE_HEIGHT,
+                frame,
+                flip,
+                false,
+                COLOR_KEY,
+            );
+        }
+
+        set_color(15);
+        let mode = if darken { "DARKEN" } else { "FLAT" };
+        out_text_xy(4, 188, format!("SHADOWS: {}  (SPACE)", mode));
+        swap_buffers();
+    }
+}
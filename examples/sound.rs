@@ -6,26 +6,29 @@
 #![no_main]
 
 use dos_like::{
-    curs_off, dos_like_sys::*, goto_xy, install_user_soundbank, key_state, load_wav, play_sound,
-    put_str, set_sound_mode, set_soundbank, shutting_down, stop_music, stop_sound, KeyCode, Music,
-    SoundMode, Soundbank,
+    asset_path, curs_off, dos_like_sys::*, goto_xy, install_user_soundbank, key_state, load_wav,
+    play_sound, put_str, set_sound_mode, set_soundbank, shutting_down, stop_music, stop_sound,
+    KeyCode, Music, SoundMode, Soundbank,
 };
 
 #[no_mangle]
 pub extern "C" fn dosmain() -> i32 {
+    let asset = |name: &str| {
+        asset_path(name)
+            .unwrap_or_else(|e| panic!("{}", e))
+            .to_string_lossy()
+            .into_owned()
+    };
+
     unsafe {
-        let mus = Music::load_mus("dos-like-sys/dos-like/files/sound/doom.mus")
-            .expect("Could not load doom.mus");
-        let mid = Music::load_mid("dos-like-sys/dos-like/files/sound/simon.mid")
-            .expect("Could not load simon.mid");
-        let r#mod = Music::load_mod("dos-like-sys/dos-like/files/sound/cfodder.mod")
-            .expect("Could not load cfodder.mod");
-        let opb = Music::load_opb("dos-like-sys/dos-like/files/sound/doom.opb")
-            .expect("Could not load doom.opb");
-        let wav = load_wav("dos-like-sys/dos-like/files/sound/soundcard.wav")
-            .expect("Could not load soundcard.wav");
-        let doom_soundbank = install_user_soundbank("dos-like-sys/dos-like/files/sound/doom.op2")
-            .expect("Could not load doom.op2");
+        let mus = Music::load_mus(asset("sound/doom.mus")).expect("Could not load doom.mus");
+        let mid = Music::load_mid(asset("sound/simon.mid")).expect("Could not load simon.mid");
+        let r#mod =
+            Music::load_mod(asset("sound/cfodder.mod")).expect("Could not load cfodder.mod");
+        let opb = Music::load_opb(asset("sound/doom.opb")).expect("Could not load doom.opb");
+        let wav = load_wav(asset("sound/soundcard.wav")).expect("Could not load soundcard.wav");
+        let doom_soundbank =
+            install_user_soundbank(asset("sound/doom.op2")).expect("Could not load doom.op2");
 
         let mut use_awe32 = true;
 
@@ -0,0 +1,117 @@
+//! Split screen example.
+//!
+//! Draws the same scene of bouncing balls twice,
+//! side by side in two viewports,
+//! each with its own camera following a different ball.
+//! Nothing drawn in one half reaches the other.
+#![no_main]
+
+use dos_like::{
+    clear_screen, dos_main, fill_circle, line, out_text_xy, present, rectangle, set_color,
+    set_double_buffer, set_video_mode, shutting_down, wait_vbl, with_viewport, Rect, VideoMode,
+    Viewport,
+};
+
+/// The size of the world in pixels.
+const WORLD_WIDTH: i32 = 480;
+const WORLD_HEIGHT: i32 = 320;
+
+/// The size of each half of the screen.
+const VIEW_WIDTH: u16 = 159;
+const VIEW_HEIGHT: u16 = 200;
+
+struct Ball {
+    x: i32,
+    y: i32,
+    dx: i32,
+    dy: i32,
+    r: u16,
+    color: u8,
+}
+
+impl Ball {
+    fn update(&mut self) {
+        let r = self.r as i32;
+        self.x += self.dx;
+        self.y += self.dy;
+        if self.x - r < 0 || self.x + r >= WORLD_WIDTH {
+            self.dx = -self.dx;
+        }
+        if self.y - r < 0 || self.y + r >= WORLD_HEIGHT {
+            self.dy = -self.dy;
+        }
+    }
+}
+
+/// Draws the whole world in world coordinates.
+fn draw_world(balls: &[Ball]) {
+    set_color(8);
+    for x in (0..WORLD_WIDTH).step_by(40) {
+        line(x, 0, x, WORLD_HEIGHT - 1);
+    }
+    for y in (0..WORLD_HEIGHT).step_by(40) {
+        line(0, y, WORLD_WIDTH - 1, y);
+    }
+    set_color(15);
+    rectangle(0, 0, WORLD_WIDTH as u16, WORLD_HEIGHT as u16);
+
+    for ball in balls {
+        set_color(ball.color);
+        fill_circle(ball.x, ball.y, ball.r);
+    }
+}
+
+/// Gets the viewport for the given half of the screen,
+/// with the camera centered on the given ball.
+fn view(x: i32, ball: &Ball) -> Viewport {
+    let camera_x = ball.x - VIEW_WIDTH as i32 / 2;
+    let camera_y = ball.y - VIEW_HEIGHT as i32 / 2;
+    Viewport::new(
+        Rect::new(x, 0, VIEW_WIDTH, VIEW_HEIGHT),
+        (-camera_x, -camera_y),
+    )
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    let mut balls = [
+        Ball { x: 60, y: 50, dx: 2, dy: 1, r: 12, color: 4 },
+        Ball { x: 300, y: 200, dx: -1, dy: 2, r: 16, color: 2 },
+        Ball { x: 200, y: 100, dx: 3, dy: -2, r: 8, color: 14 },
+        Ball { x: 400, y: 260, dx: -2, dy: -1, r: 20, color: 9 },
+    ];
+
+    while !shutting_down() {
+        wait_vbl();
+
+        for ball in &mut balls {
+            ball.update();
+        }
+
+        clear_screen();
+
+        for (x, target, label) in [(0, 0, "PLAYER 1"), (161, 1, "PLAYER 2")] {
+            with_viewport(view(x, &balls[target]), || {
+                draw_world(&balls);
+                // the label is drawn at the camera position,
+                // so that it stays in the corner of the viewport
+                let ball = &balls[target];
+                set_color(15);
+                out_text_xy(
+                    ball.x - VIEW_WIDTH as i32 / 2 + 4,
+                    ball.y - VIEW_HEIGHT as i32 / 2 + 4,
+                    label,
+                );
+            });
+        }
+
+        // the divider between both halves
+        set_color(7);
+        line(159, 0, 159, 199);
+        line(160, 0, 160, 199);
+
+        present();
+    }
+}
@@ -0,0 +1,49 @@
+//! Example showcasing screen transitions between two images.
+//!
+//! Pass the paths to two GIF files to transition between them.
+//! Otherwise, `assets/rotozoom.gif` is used along with a mirrored copy.
+#![no_main]
+
+use dos_like::{
+    dos_main, key_state, load_gif, set_double_buffer, set_video_mode, shutting_down, transition,
+    wait_vbl, Image, KeyCode, Transition, VideoMode,
+};
+
+fn load(path: &str) -> Image {
+    load_gif(path).unwrap_or_else(|_| {
+        eprintln!("Could not load {}", path);
+        std::process::exit(-2);
+    })
+}
+
+dos_main! {
+    let args: Vec<String> = std::env::args().collect();
+    let (a, b) = if let [_, a, b, ..] = &args[..] {
+        (load(a), load(b))
+    } else {
+        let a = load("assets/rotozoom.gif");
+        let mut b = load("assets/rotozoom.gif");
+        let width = b.width() as usize;
+        for row in b.data_mut().chunks_exact_mut(width) {
+            row.reverse();
+        }
+        (a, b)
+    };
+
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    let mut images = (&a, &b);
+    'outer: for kind in Transition::ALL.iter().cycle() {
+        transition(*kind, 90, images.0, images.1);
+        images = (images.1, images.0);
+
+        // hold the image for a second
+        for _ in 0..60 {
+            wait_vbl();
+            if shutting_down() || key_state(KeyCode::KEY_ESCAPE) {
+                break 'outer;
+            }
+        }
+    }
+}
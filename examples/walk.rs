@@ -0,0 +1,131 @@
+//! Example showcasing sprite sheets and animations.
+//!
+//! Pass the path to a GIF sprite sheet and the size of its frames
+//! to walk with its first four frames.
+//! Otherwise, a stick figure is drawn over a copy of `assets/rotozoom.gif`.
+//!
+//! Use the left and right arrow keys to walk.
+#![no_main]
+
+use std::time::{Duration, Instant};
+
+use dos_like::{
+    args, clear_screen, dos_main, key_state, load_gif, out_text_xy, set_color, set_double_buffer,
+    set_video_mode, shutting_down, swap_buffers, wait_vbl, Animation, AnimationPlayer, Image,
+    KeyCode, LoopMode, SpriteDrawOptions, SpriteSheet, VideoMode,
+};
+
+const FRAME_WIDTH: u32 = 16;
+const FRAME_HEIGHT: u32 = 32;
+
+/// Draws a walking stick figure in 4 frames
+/// over the top left corner of the image.
+fn draw_stick_figure(image: &mut Image) {
+    let width = image.width() as usize;
+    let data = image.data_mut();
+    data[..width * FRAME_HEIGHT as usize].fill(0);
+    // horizontal offsets of the feet in each frame
+    let feet = [(-5, 5), (-2, 2), (5, -5), (2, -2)];
+    for (frame, (left, right)) in feet.into_iter().enumerate() {
+        let mut plot = |x: i32, y: i32| {
+            let x = frame as i32 * FRAME_WIDTH as i32 + 8 + x;
+            data[y as usize * width + x as usize] = 15;
+        };
+        // head
+        for (x, y) in [(-1, 1), (0, 1), (1, 1), (-2, 2), (2, 2), (-2, 3), (2, 3)] {
+            plot(x, y);
+        }
+        for (x, y) in [(-2, 4), (2, 4), (-1, 5), (0, 5), (1, 5)] {
+            plot(x, y);
+        }
+        // body and arms
+        for y in 6..20 {
+            plot(0, y);
+        }
+        for x in 1..5 {
+            plot(-x, 8 + x);
+            plot(x, 8 + x);
+        }
+        // legs
+        for y in 0..12 {
+            plot(left * y / 11, 20 + y);
+            plot(right * y / 11, 20 + y);
+        }
+    }
+}
+
+dos_main! {
+    let (sheet, frame_width) = if let [_, path, width, height, ..] = args() {
+        let path = path.to_string_lossy();
+        let image = load_gif(&path).unwrap_or_else(|_| {
+            eprintln!("Could not load {}", path);
+            std::process::exit(-2);
+        });
+        let parse = |arg: &std::ffi::OsString| arg.to_str().and_then(|arg| arg.parse().ok());
+        let (Some(width), Some(height)) = (parse(width), parse(height)) else {
+            eprintln!("Usage: walk [<sheet.gif> <frame_width> <frame_height>]");
+            std::process::exit(-1);
+        };
+        image.apply_palette();
+        (SpriteSheet::new(image, width, height, 0, 0), width)
+    } else {
+        let mut image = load_gif("assets/rotozoom.gif").unwrap_or_else(|_| {
+            eprintln!("Could not load assets/rotozoom.gif");
+            std::process::exit(-2);
+        });
+        draw_stick_figure(&mut image);
+        (SpriteSheet::new(image, FRAME_WIDTH, FRAME_HEIGHT, 0, 0), FRAME_WIDTH)
+    };
+    if sheet.frame_count() < 4 {
+        eprintln!("The sprite sheet needs at least 4 frames");
+        std::process::exit(-1);
+    }
+
+    set_video_mode(VideoMode::Graphics320x200);
+    set_double_buffer(true);
+
+    let walk = Animation::uniform(0..4, Duration::from_millis(150), LoopMode::Loop);
+    let mut player = AnimationPlayer::new(walk, &sheet);
+    let mut x = 160. - frame_width as f32 / 2.;
+    let mut facing_left = false;
+    let mut steps = 0;
+    let mut last = Instant::now();
+
+    while !shutting_down() {
+        wait_vbl();
+        let now = Instant::now();
+        let delta = now - last;
+        last = now;
+
+        let left = key_state(KeyCode::KEY_LEFT);
+        let right = key_state(KeyCode::KEY_RIGHT);
+        if left != right {
+            facing_left = left;
+            let speed = if left { -40. } else { 40. };
+            x = (x + speed * delta.as_secs_f32()).clamp(0., 320. - frame_width as f32);
+            player.update(delta);
+            // one step for each frame with a foot on the ground
+            if player.frame_started(0) || player.frame_started(2) {
+                steps += 1;
+            }
+        } else {
+            player.reset();
+        }
+
+        player.set_draw_options(
+            SpriteDrawOptions::new()
+                .with_color_key(0)
+                .with_flip_x(facing_left),
+        );
+
+        clear_screen();
+        player.draw(x as i32, 120);
+        set_color(15);
+        out_text_xy(8, 8, format!("Steps: {}", steps));
+        swap_buffers();
+
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
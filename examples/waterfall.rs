@@ -0,0 +1,106 @@
+//! Color cycling example.
+//!
+//! A waterfall is drawn once,
+//! and then animated only by rotating ranges of the palette
+//! with `rotate_palette`:
+//! no pixel on the screen is written to after the first frame.
+#![no_main]
+
+use std::ops::Range;
+
+use dos_like::{
+    dos_main, install_ramp, key_state, make_ramp, out_text_xy, rotate_palette, set_color, set_pal,
+    set_palette_range, set_video_mode, shutting_down, wait_vbl, KeyCode, ScreenBuffer, VideoMode,
+};
+
+/// The first color of the sky gradient.
+const SKY: u8 = 1;
+/// The first color of the rock texture.
+const ROCK: u8 = 16;
+/// The colors of the falling water, cycled downwards.
+const FALL: Range<u8> = 32..48;
+/// The colors of the pool ripples, cycled outwards.
+const POOL: Range<u8> = 48..56;
+/// The color of the text.
+const TEXT: u8 = 63;
+
+/// The columns of the waterfall.
+const FALL_LEFT: usize = 128;
+const FALL_RIGHT: usize = 192;
+/// The top of the pool.
+const POOL_TOP: usize = 150;
+
+/// A cheap hash of a pixel position, for the textures.
+fn noise(x: usize, y: usize) -> usize {
+    let mut h = (x as u32).wrapping_mul(0x9E37_79B9) ^ (y as u32).wrapping_mul(0x85EB_CA6B);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    (h ^ (h >> 12)) as usize
+}
+
+/// Picks the color of a pixel of the scene.
+fn scene_color(x: usize, y: usize) -> u8 {
+    let in_fall = (FALL_LEFT..FALL_RIGHT).contains(&x);
+    if y >= POOL_TOP {
+        // ripples spreading from the foot of the fall
+        let dx = x.abs_diff((FALL_LEFT + FALL_RIGHT) / 2) / 6;
+        let ring = (dx + (y - POOL_TOP) * 2) % POOL.len();
+        POOL.start + ring as u8
+    } else if in_fall && y >= 40 {
+        // streaks of slightly different phase in each column
+        let phase = noise(x, 0) % 3;
+        FALL.start + ((y + phase) % FALL.len()) as u8
+    } else if y < 36 + noise(x / 8, 1) % 12 {
+        // above the bumpy top of the cliff
+        SKY + (y * 15 / POOL_TOP).min(14) as u8
+    } else {
+        ROCK + (noise(x, y) % 4 + y * 8 / POOL_TOP) as u8
+    }
+}
+
+dos_main! {
+    set_video_mode(VideoMode::Graphics320x200);
+
+    install_ramp(SKY as usize, &make_ramp((20, 30, 90), (150, 190, 240), 15, 2.2));
+    install_ramp(ROCK as usize, &make_ramp((110, 95, 80), (40, 32, 28), 12, 2.2));
+    // dark water with two bright streaks, which travel down when cycled
+    let fall: Vec<(u8, u8, u8)> = (0..FALL.len())
+        .map(|i| match i % 8 {
+            0 => (50, 58, 63),
+            1 => (30, 44, 60),
+            _ => (8, 20 + i as u8 % 3 * 2, 44),
+        })
+        .collect();
+    set_palette_range(FALL.start as usize, &fall);
+    let pool: Vec<(u8, u8, u8)> = (0..POOL.len() as u8)
+        .map(|i| if i == 0 { (36, 48, 60) } else { (6, 16 + i, 36 + i) })
+        .collect();
+    set_palette_range(POOL.start as usize, &pool);
+
+    // the only time the screen is drawn
+    let mut screen = ScreenBuffer::acquire().expect("the screen buffer should be available");
+    for (i, pixel) in screen.as_mut_slice().iter_mut().enumerate() {
+        *pixel = scene_color(i % 320, i / 320);
+    }
+    drop(screen);
+    set_pal(TEXT as usize, 63, 63, 63);
+    set_color(TEXT);
+    out_text_xy(4, 4, "PALETTE CYCLING, NO PIXELS REDRAWN");
+
+    let mut frame: u32 = 0;
+    while !shutting_down() {
+        wait_vbl();
+        frame += 1;
+        if frame.is_multiple_of(3) {
+            let range = FALL.start as usize..FALL.end as usize;
+            rotate_palette(range, 1);
+        }
+        if frame.is_multiple_of(10) {
+            let range = POOL.start as usize..POOL.end as usize;
+            rotate_palette(range, 1);
+        }
+        if key_state(KeyCode::KEY_ESCAPE) {
+            break;
+        }
+    }
+}
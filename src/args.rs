@@ -0,0 +1,410 @@
+//! Command line flags handled by the framework.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// A command line flag handled by the framework.
+struct Flag {
+    /// The names of the flag, the long one first
+    names: &'static [&'static str],
+    /// The name of the value taken, if any
+    value: Option<&'static str>,
+    /// The description for the usage listing
+    help: &'static str,
+}
+
+/// The flags handled by the framework, in the order listed in the usage.
+const FLAGS: &[Flag] = &[
+    Flag {
+        names: &["--fullscreen"],
+        value: None,
+        help: "start in fullscreen (the default)",
+    },
+    Flag {
+        names: &["--window", "-w"],
+        value: None,
+        help: "start in a window",
+    },
+    Flag {
+        names: &["--nocrt", "-n"],
+        value: None,
+        help: "disable the CRT screen effects",
+    },
+    // the frame is already left out of this build
+    #[cfg(feature = "disable-screen-frame")]
+    Flag {
+        names: &["--no-frame"],
+        value: None,
+        help: "no CRT screen frame around the viewport (always the case)",
+    },
+    Flag {
+        names: &["--screenshot-dir"],
+        value: Some("PATH"),
+        help: "save screenshots with relative paths into PATH",
+    },
+];
+
+/// Flags for the framework which it cannot honor,
+/// with the reason why.
+const UNSUPPORTED_FLAGS: &[(&str, &str)] = &[
+    ("--scale", "the engine sizes its window by itself"),
+    #[cfg(not(feature = "disable-screen-frame"))]
+    (
+        "--no-frame",
+        "the screen frame can only be left out \
+         by building with the `disable-screen-frame` feature",
+    ),
+];
+
+/// An error in the flags for the framework given on the command line,
+/// see [`LaunchOptions::error`].
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum LaunchError {
+    /// A flag which the framework cannot honor
+    Unsupported {
+        /// The flag given
+        flag: &'static str,
+        /// Why it cannot be honored
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LaunchError::Unsupported { flag, reason } => {
+                write!(f, "Unsupported flag `{}`: {}", flag, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+/// The options given to the framework on the command line,
+/// as taken by [`launch_options`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchOptions {
+    fullscreen: bool,
+    crt: bool,
+    screenshot_dir: Option<PathBuf>,
+    args: Vec<OsString>,
+    error: Option<LaunchError>,
+}
+
+impl LaunchOptions {
+    /// Parses the flags of the framework out of the given arguments,
+    /// starting with the program name.
+    ///
+    /// Arguments which are not valid Unicode
+    /// are never flags of the framework,
+    /// but may be the value of one.
+    fn parse(args: impl IntoIterator<Item = OsString>) -> Self {
+        let mut options = LaunchOptions {
+            fullscreen: true,
+            crt: true,
+            screenshot_dir: None,
+            args: Vec::new(),
+            error: None,
+        };
+        let mut args = args.into_iter().peekable();
+        options.args.extend(args.next());
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                // the rest is for the application
+                options.args.push(arg);
+                options.args.extend(args);
+                break;
+            }
+            let Some(text) = arg.to_str() else {
+                options.args.push(arg);
+                continue;
+            };
+            let (name, inline_value) = match text.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (name, Some(OsString::from(value)))
+                }
+                _ => (text, None),
+            };
+            if let Some(&(flag, reason)) = UNSUPPORTED_FLAGS.iter().find(|(flag, _)| *flag == name)
+            {
+                // kept for the application, along with any value after it
+                options
+                    .error
+                    .get_or_insert(LaunchError::Unsupported { flag, reason });
+                options.args.push(arg);
+                continue;
+            }
+            let Some(flag) = FLAGS.iter().find(|flag| flag.names.contains(&name)) else {
+                options.args.push(arg);
+                continue;
+            };
+            let value = match (flag.value, inline_value) {
+                (None, None) => None,
+                (Some(_), Some(value)) => Some(value),
+                (Some(_), None) if args.peek().is_some_and(|next| !is_flag_like(next)) => {
+                    args.next()
+                }
+                // a flag with a missing or unexpected value is not ours
+                _ => {
+                    options.args.push(arg);
+                    continue;
+                }
+            };
+            match (flag.names[0], value) {
+                // the engine's default, which it leaves for `--window` anywhere
+                ("--fullscreen", _) => {}
+                ("--window", _) => options.fullscreen = false,
+                ("--nocrt", _) => options.crt = false,
+                ("--no-frame", _) => {}
+                ("--screenshot-dir", Some(value)) => {
+                    options.screenshot_dir = Some(PathBuf::from(value))
+                }
+                _ => unreachable!("flag {} is not handled", flag.names[0]),
+            }
+        }
+        options
+    }
+
+    /// Checks whether the application starts in fullscreen,
+    /// which is the default unless `--window` (or `-w`) is given,
+    /// even along with `--fullscreen`.
+    ///
+    /// The engine reads `--window` by itself when it starts.
+    #[inline]
+    pub fn fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Checks whether the CRT screen effects are enabled,
+    /// which is the default unless `--nocrt` (or `-n`) is given.
+    ///
+    /// The engine reads this flag by itself when it starts.
+    #[inline]
+    pub fn crt(&self) -> bool {
+        self.crt
+    }
+
+    /// Gets the first error in the flags for the framework, if any,
+    /// such as a flag which it cannot honor:
+    /// `--scale`, since the engine sizes its window by itself,
+    /// and `--no-frame`
+    /// unless built with the `disable-screen-frame` Cargo feature.
+    ///
+    /// [`dos_main!`](crate::dos_main) checks this before the application starts,
+    /// and exits with the error and the usage of the framework if there is one.
+    #[inline]
+    pub fn error(&self) -> Option<&LaunchError> {
+        self.error.as_ref()
+    }
+
+    /// Gets the directory given with `--screenshot-dir`, if any,
+    /// which is the initial screenshot directory
+    /// (see [`screenshot_dir`](crate::screenshot_dir)).
+    #[inline]
+    pub fn screenshot_dir(&self) -> Option<&Path> {
+        self.screenshot_dir.as_deref()
+    }
+
+    /// Gets the command line arguments left for the application
+    /// (see [`args`]).
+    #[inline]
+    pub fn args(&self) -> &[OsString] {
+        &self.args
+    }
+}
+
+/// Checks whether an argument starts like a flag.
+fn is_flag_like(arg: &OsStr) -> bool {
+    arg.as_encoded_bytes().starts_with(b"-")
+}
+
+/// The options parsed from the command line.
+static LAUNCH_OPTIONS: OnceLock<LaunchOptions> = OnceLock::new();
+
+/// Gets the options given to the framework on the command line.
+///
+/// The flags are parsed on the first call,
+/// from the arguments of the process.
+/// See [`framework_usage`] for the flags understood.
+pub fn launch_options() -> &'static LaunchOptions {
+    LAUNCH_OPTIONS.get_or_init(|| LaunchOptions::parse(std::env::args_os()))
+}
+
+/// Gets the command line arguments of the process,
+/// without the flags handled by the framework
+/// (see [`framework_usage`]).
+///
+/// Like [`std::env::args_os`], the first argument is the program name,
+/// and arguments which are not valid Unicode are kept as they are.
+/// Unknown flags and other arguments are kept as they are and in order,
+/// and so are all arguments after `--`,
+/// including the `--` itself.
+/// A flag of the framework with a missing or invalid value is also kept,
+/// and so is a flag which the framework cannot honor, such as `--scale`,
+/// along with its value.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// match args() {
+///     [_, path] => println!("opening {}", path.to_string_lossy()),
+///     _ => {
+///         eprintln!("Usage: viewer [OPTIONS] <image.gif>\n\nOptions:");
+///         eprint!("{}", framework_usage());
+///     }
+/// }
+/// ```
+#[inline]
+pub fn args() -> &'static [OsString] {
+    launch_options().args()
+}
+
+/// Lists the command line flags handled by the framework,
+/// one per line with a description,
+/// for applications which print their usage.
+pub fn framework_usage() -> String {
+    let names: Vec<String> = FLAGS
+        .iter()
+        .map(|flag| {
+            let names = flag.names.join(", ");
+            match flag.value {
+                Some(value) => format!("{} {}", names, value),
+                None => names,
+            }
+        })
+        .collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    names
+        .iter()
+        .zip(FLAGS)
+        .map(|(names, flag)| format!("  {:width$}  {}\n", names, flag.help, width = width))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> LaunchOptions {
+        LaunchOptions::parse(
+            std::iter::once("app")
+                .chain(args.iter().copied())
+                .map(OsString::from),
+        )
+    }
+
+    #[test]
+    fn framework_flags_are_taken_out() {
+        let options = parse(&["-w", "level1", "--nocrt", "--screenshot-dir", "shots", "-v"]);
+        assert!(!options.fullscreen());
+        assert!(!options.crt());
+        assert_eq!(options.screenshot_dir(), Some(Path::new("shots")));
+        assert_eq!(options.args(), ["app", "level1", "-v"]);
+        assert_eq!(options.error(), None);
+
+        let options = parse(&["--fullscreen", "--screenshot-dir=a b"]);
+        assert!(options.fullscreen());
+        assert!(options.crt());
+        assert_eq!(options.screenshot_dir(), Some(Path::new("a b")));
+        assert_eq!(options.args(), ["app"]);
+    }
+
+    #[test]
+    fn other_arguments_are_kept() {
+        // missing values, unknown flags and everything after `--`
+        let options = parse(&["--unknown=1", "--screenshot-dir", "--", "--window", "-n"]);
+        assert!(options.fullscreen());
+        assert!(options.crt());
+        assert_eq!(options.screenshot_dir(), None);
+        assert_eq!(
+            options.args(),
+            [
+                "app",
+                "--unknown=1",
+                "--screenshot-dir",
+                "--",
+                "--window",
+                "-n"
+            ]
+        );
+        assert_eq!(parse(&["--window=yes"]).args(), ["app", "--window=yes"]);
+        assert_eq!(parse(&[]).args(), ["app"]);
+    }
+
+    #[test]
+    fn unsupported_flags_are_rejected() {
+        let options = parse(&["--scale", "2", "--window"]);
+        assert!(matches!(
+            options.error(),
+            Some(LaunchError::Unsupported {
+                flag: "--scale",
+                ..
+            })
+        ));
+        assert!(options.error().unwrap().to_string().contains("--scale"));
+        assert!(!framework_usage().contains("--scale"));
+        // the flag and its value are left for the application
+        assert!(!options.fullscreen());
+        assert_eq!(options.args(), ["app", "--scale", "2"]);
+        assert_eq!(parse(&["--scale=2"]).args(), ["app", "--scale=2"]);
+
+        let options = parse(&["--no-frame"]);
+        if cfg!(feature = "disable-screen-frame") {
+            assert_eq!(options.error(), None);
+            assert_eq!(options.args(), ["app"]);
+            assert!(framework_usage().contains("--no-frame"));
+        } else {
+            assert!(matches!(
+                options.error(),
+                Some(LaunchError::Unsupported {
+                    flag: "--no-frame",
+                    ..
+                })
+            ));
+            assert!(!framework_usage().contains("--no-frame"));
+            assert_eq!(options.args(), ["app", "--no-frame"]);
+        }
+
+        // only the first error is kept
+        let options = parse(&["--scale=3", "--scale", "4"]);
+        assert!(options.error().is_some());
+        assert_eq!(parse(&["--", "--scale", "2"]).error(), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn arguments_need_not_be_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = OsStr::from_bytes(b"shots\xFF");
+        let options = LaunchOptions::parse(
+            [
+                OsStr::new("app"),
+                path,
+                OsStr::new("--screenshot-dir"),
+                path,
+                OsStr::new("-w"),
+            ]
+            .map(OsString::from),
+        );
+        assert!(!options.fullscreen());
+        assert_eq!(options.screenshot_dir(), Some(Path::new(path)));
+        assert_eq!(options.args(), [OsStr::new("app"), path]);
+    }
+
+    #[test]
+    fn usage_lists_every_flag() {
+        let usage = framework_usage();
+        assert_eq!(usage.lines().count(), FLAGS.len());
+        for flag in FLAGS {
+            for name in flag.names {
+                assert!(usage.contains(name), "{} in {}", name, usage);
+            }
+        }
+        assert!(usage.contains("--screenshot-dir PATH"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Resolution of asset file paths.
+
+use std::path::{Path, PathBuf};
+
+use crate::FileError;
+
+/// The environment variable naming an extra directory
+/// to search for assets first.
+const ASSETS_VAR: &str = "DOS_LIKE_ASSETS";
+
+/// Finds an asset file by its path relative to one of several locations,
+/// so that programs work regardless of the current directory.
+///
+/// The locations are searched in this order:
+///
+/// 1. the directory in the `DOS_LIKE_ASSETS` environment variable, if set
+/// 2. the current directory
+/// 3. the directory of the executable
+/// 4. the directory of this crate's manifest at build time
+///    (`CARGO_MANIFEST_DIR`),
+///    which is where the examples keep their assets
+/// 5. the `dos-like-sys/dos-like/files` directory in the crate's manifest directory,
+///    which has the files bundled with `dos-like`
+///
+/// Returns the first path that exists,
+/// or [`FileError::AssetNotFound`] with all paths tried.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let music = Music::load_mus(asset_path("sound/doom.mus")?.to_string_lossy())?;
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn asset_path(relative: &str) -> Result<PathBuf, FileError> {
+    find_in(relative, &search_dirs())
+}
+
+/// Lists the directories to search for assets, in order.
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os(ASSETS_VAR) {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Ok(dir) = std::env::current_dir() {
+        dirs.push(dir);
+    }
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(dir);
+    }
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    dirs.push(manifest_dir.to_path_buf());
+    dirs.push(manifest_dir.join("dos-like-sys/dos-like/files"));
+
+    // the current directory is often one of the others
+    let mut unique = Vec::with_capacity(dirs.len());
+    for dir in dirs {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    unique
+}
+
+/// Finds the first directory in which the relative path exists.
+fn find_in(relative: &str, dirs: &[PathBuf]) -> Result<PathBuf, FileError> {
+    let tried: Vec<PathBuf> = dirs.iter().map(|dir| dir.join(relative)).collect();
+    match tried.iter().find(|path| path.exists()) {
+        Some(path) => Ok(path.clone()),
+        None => Err(FileError::AssetNotFound(tried)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn finds_asset_in_first_directory_having_it() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        fs::create_dir(second.path().join("sound")).unwrap();
+        fs::write(second.path().join("sound/doom.mus"), b"MUS").unwrap();
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+
+        let path = find_in("sound/doom.mus", &dirs).unwrap();
+        assert_eq!(path, second.path().join("sound/doom.mus"));
+
+        // a copy in an earlier directory takes precedence
+        fs::create_dir(first.path().join("sound")).unwrap();
+        fs::write(first.path().join("sound/doom.mus"), b"MUS").unwrap();
+        let path = find_in("sound/doom.mus", &dirs).unwrap();
+        assert_eq!(path, first.path().join("sound/doom.mus"));
+    }
+
+    #[test]
+    fn lists_all_paths_tried() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let dirs = [first.path().to_path_buf(), second.path().to_path_buf()];
+
+        match find_in("files/missing.gif", &dirs) {
+            Err(FileError::AssetNotFound(tried)) => assert_eq!(
+                tried,
+                [
+                    first.path().join("files/missing.gif"),
+                    second.path().join("files/missing.gif"),
+                ]
+            ),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            find_in("anything", &[]),
+            Err(FileError::AssetNotFound(tried)) if tried.is_empty()
+        ));
+    }
+
+    #[test]
+    fn finds_directories_too() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("images")).unwrap();
+        let path = find_in("images", &[dir.path().to_path_buf()]).unwrap();
+        assert_eq!(path, dir.path().join("images"));
+    }
+
+    #[test]
+    fn search_dirs_are_unique_and_end_with_the_bundled_files() {
+        let dirs = search_dirs();
+        for (i, dir) in dirs.iter().enumerate() {
+            assert!(!dirs[..i].contains(dir), "{} listed twice", dir.display());
+        }
+        // the manifest directory may also be the current one
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert!(dirs.contains(&manifest_dir.to_path_buf()));
+        assert_eq!(
+            dirs.last(),
+            Some(&manifest_dir.join("dos-like-sys/dos-like/files"))
+        );
+    }
+}
@@ -0,0 +1,455 @@
+//! A virtual console showing logged messages
+//! in the bottom rows of the screen,
+//! for debugging.
+//!
+//! Messages are logged with [`log`] (or the [`console_log!`](crate::console_log) macro)
+//! into a ring buffer of lines.
+//! The console is hidden by default,
+//! and can be toggled with a key (F12 by default)
+//! when input events are passed to [`handle_event`].
+//! While visible, PgUp and PgDn scroll back through older lines.
+//!
+//! The console is drawn on top of everything else by [`draw`],
+//! which should be called at the end of each frame.
+//! In text mode it covers the bottom text rows,
+//! and in graphics mode it covers the bottom 8 pixel rows per line
+//! and is written with the default 8x8 font.
+//! The cells or pixels it covers are saved when it is shown,
+//! and put back when it is hidden,
+//! so an application which does not redraw the whole screen every frame
+//! is left as it was.
+//! Anything drawn under the console while it is visible is lost.
+//! Nothing is done while the console is hidden.
+//!
+//! With the `log` Cargo feature,
+//! [`init_logger`] installs the console
+//! as the logger of the [`log`](https://docs.rs/log) crate.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dos_like::*;
+//! let mut frame = 0;
+//! while !shutting_down() {
+//!     wait_vbl();
+//!     for event in read_events() {
+//!         if console::handle_event(&event) {
+//!             continue;
+//!         }
+//!         // handle the event in the application
+//!     }
+//!     frame += 1;
+//!     if frame % 70 == 0 {
+//!         console_log!("frame {}", frame);
+//!     }
+//!     console::draw();
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use crate::{
+    input::{InputEvent, KeyCode},
+    video::{
+        current_video_mode, draw_offset, get_color, out_text_xy, set_color, set_draw_offset,
+        set_text_style, text_style, Font, TextAttr, VideoMode,
+    },
+};
+
+/// The number of lines kept by default.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+/// The number of rows shown by default.
+pub const DEFAULT_ROWS: u16 = 8;
+
+/// The height of a console row in graphics mode.
+const GLYPH_HEIGHT: u16 = 8;
+
+/// The width of a console column in graphics mode.
+const GLYPH_WIDTH: u16 = 8;
+
+/// What the console covers on the screen,
+/// saved when it was first drawn.
+#[derive(Debug)]
+struct Saved {
+    /// The video mode at the time
+    mode: VideoMode,
+    /// The offset of the saved region in the screen buffer
+    start: usize,
+    /// The bytes of the screen buffer from `start`
+    bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Console {
+    lines: VecDeque<String>,
+    capacity: usize,
+    rows: u16,
+    /// The number of lines scrolled back from the newest one
+    scroll: usize,
+    toggle_key: KeyCode,
+    attrs: TextAttr,
+    saved: Option<Saved>,
+}
+
+static CONSOLE: Mutex<Console> = Mutex::new(Console {
+    lines: VecDeque::new(),
+    capacity: DEFAULT_CAPACITY,
+    rows: DEFAULT_ROWS,
+    scroll: 0,
+    toggle_key: KeyCode::KEY_F12,
+    attrs: TextAttr::new(15, 1),
+    saved: None,
+});
+
+/// Whether the console is visible,
+/// kept apart so that a hidden console costs nothing.
+static VISIBLE: AtomicBool = AtomicBool::new(false);
+
+fn lock() -> MutexGuard<'static, Console> {
+    CONSOLE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Logs a formatted message to the console.
+///
+/// Each line of the message becomes a line in the console.
+/// The [`console_log!`](crate::console_log) macro is a shorthand for this.
+pub fn log(args: std::fmt::Arguments) {
+    let mut text = String::new();
+    let _ = text.write_fmt(args);
+    let mut console = lock();
+    for line in text.lines() {
+        console.push(line.to_string());
+    }
+}
+
+/// Removes all lines from the console.
+pub fn clear() {
+    let mut console = lock();
+    console.lines.clear();
+    console.scroll = 0;
+}
+
+/// Gets the number of lines in the console.
+pub fn line_count() -> usize {
+    lock().lines.len()
+}
+
+/// Sets the maximum number of lines kept,
+/// discarding the oldest lines beyond it.
+///
+/// The default is [`DEFAULT_CAPACITY`].
+pub fn set_capacity(capacity: usize) {
+    let mut console = lock();
+    console.capacity = capacity.max(1);
+    console.trim();
+}
+
+/// Gets the maximum number of lines kept.
+pub fn capacity() -> usize {
+    lock().capacity
+}
+
+/// Sets the number of rows of the screen covered by the console.
+///
+/// The default is [`DEFAULT_ROWS`].
+pub fn set_rows(rows: u16) {
+    let mut console = lock();
+    console.restore();
+    console.rows = rows.max(1);
+}
+
+/// Gets the number of rows of the screen covered by the console.
+pub fn rows() -> u16 {
+    lock().rows
+}
+
+/// Sets the key which shows and hides the console
+/// in [`handle_event`].
+///
+/// The default is F12.
+pub fn set_toggle_key(key: KeyCode) {
+    lock().toggle_key = key;
+}
+
+/// Sets the colors of the console.
+///
+/// The default is white on blue.
+pub fn set_attrs(attrs: TextAttr) {
+    lock().attrs = attrs;
+}
+
+/// Shows the console.
+pub fn show() {
+    VISIBLE.store(true, Ordering::Relaxed);
+}
+
+/// Hides the console,
+/// putting back what it covered on the screen.
+pub fn hide() {
+    if VISIBLE.swap(false, Ordering::Relaxed) {
+        lock().restore();
+    }
+}
+
+/// Shows the console if hidden, hides it otherwise.
+pub fn toggle() {
+    if is_visible() {
+        hide();
+    } else {
+        show();
+    }
+}
+
+/// Whether the console is visible.
+pub fn is_visible() -> bool {
+    VISIBLE.load(Ordering::Relaxed)
+}
+
+/// Processes an input event,
+/// toggling the console with the toggle key
+/// and scrolling with PgUp and PgDn while it is visible.
+///
+/// Returns whether the event was consumed by the console.
+pub fn handle_event(event: &InputEvent) -> bool {
+    let InputEvent::Key(key) = *event else {
+        return false;
+    };
+    let mut console = lock();
+    let key_code = key.key_code();
+    if key_code == console.toggle_key {
+        if key.is_pressed() {
+            drop(console);
+            toggle();
+        }
+        return true;
+    }
+    if !is_visible() {
+        return false;
+    }
+    let page = (console.rows as usize).saturating_sub(1).max(1);
+    match key_code {
+        KeyCode::KEY_PRIOR => {
+            if key.is_pressed() {
+                let max = console.lines.len().saturating_sub(console.rows as usize);
+                console.scroll = (console.scroll + page).min(max);
+            }
+            true
+        }
+        KeyCode::KEY_NEXT => {
+            if key.is_pressed() {
+                console.scroll = console.scroll.saturating_sub(page);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Draws the console on the screen if it is visible.
+///
+/// This should be called once per frame,
+/// after everything else is drawn.
+pub fn draw() {
+    if !is_visible() {
+        return;
+    }
+    let mode = current_video_mode();
+    let mut console = lock();
+    if console.saved.as_ref().map(|s| s.mode) != Some(mode) {
+        // the old contents do not belong to this video mode
+        console.saved = None;
+        console.save(mode);
+    }
+    if mode.is_graphics() {
+        console.draw_graphics();
+    } else {
+        console.draw_text();
+    }
+}
+
+/// Gets the screen buffer, in text mode or graphics mode.
+///
+/// In text mode, each cell takes two bytes:
+/// the character followed by the attributes.
+fn screen_bytes(mode: VideoMode) -> &'static mut [u8] {
+    let len = if mode.is_graphics() {
+        let (width, height) = mode.pixel_dimensions();
+        width as usize * height as usize
+    } else {
+        mode.text_columns() as usize * mode.text_rows() as usize * 2
+    };
+    // Safety: the length matches the buffer of the video mode,
+    // and the slice is only used within the console functions
+    unsafe {
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return &mut [];
+        }
+        std::slice::from_raw_parts_mut(buf, len)
+    }
+}
+
+impl Console {
+    fn push(&mut self, line: String) {
+        self.lines.push_back(line);
+        if self.scroll > 0 {
+            // keep showing the same lines
+            self.scroll += 1;
+        }
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.lines.len() > self.capacity {
+            self.lines.pop_front();
+        }
+        let max = self.lines.len().saturating_sub(self.rows as usize);
+        self.scroll = self.scroll.min(max);
+    }
+
+    /// Gets the lines to show, oldest first,
+    /// aligned to the bottom row.
+    fn visible_lines(&self) -> impl Iterator<Item = Option<&str>> {
+        let rows = self.rows as usize;
+        let end = self.lines.len() - self.scroll;
+        let start = end.saturating_sub(rows);
+        let blank = rows - (end - start);
+        std::iter::repeat_n(None, blank)
+            .chain(self.lines.range(start..end).map(|l| Some(l.as_str())))
+    }
+
+    /// Gets the first row covered by the console
+    /// and the number of rows on the screen.
+    fn row_range(&self, mode: VideoMode) -> (usize, usize) {
+        let total = if mode.is_graphics() {
+            (mode.pixel_dimensions().1 / GLYPH_HEIGHT) as usize
+        } else {
+            mode.text_rows() as usize
+        };
+        (total.saturating_sub(self.rows as usize), total)
+    }
+
+    /// Gets the number of bytes in a row of the console.
+    fn row_bytes(mode: VideoMode) -> usize {
+        if mode.is_graphics() {
+            mode.pixel_dimensions().0 as usize * GLYPH_HEIGHT as usize
+        } else {
+            mode.text_columns() as usize * 2
+        }
+    }
+
+    fn save(&mut self, mode: VideoMode) {
+        let (first, _) = self.row_range(mode);
+        let start = first * Self::row_bytes(mode);
+        let screen = screen_bytes(mode);
+        let start = start.min(screen.len());
+        self.saved = Some(Saved {
+            mode,
+            start,
+            bytes: screen[start..].to_vec(),
+        });
+    }
+
+    /// Puts back what the console covered, if still in the same video mode.
+    fn restore(&mut self) {
+        let Some(saved) = self.saved.take() else {
+            return;
+        };
+        if saved.mode != current_video_mode() {
+            return;
+        }
+        let screen = screen_bytes(saved.mode);
+        if let Some(region) = screen.get_mut(saved.start..saved.start + saved.bytes.len()) {
+            region.copy_from_slice(&saved.bytes);
+        }
+    }
+
+    fn draw_text(&self) {
+        let mode = current_video_mode();
+        let columns = mode.text_columns() as usize;
+        let (first, _) = self.row_range(mode);
+        let attr = (self.attrs.background & 0x0F) << 4 | (self.attrs.foreground & 0x0F);
+        let screen = screen_bytes(mode);
+        for (i, line) in self.visible_lines().enumerate() {
+            let row_start = (first + i) * columns * 2;
+            let Some(row) = screen.get_mut(row_start..row_start + columns * 2) else {
+                break;
+            };
+            let mut text = line.unwrap_or_default().bytes();
+            for cell in row.chunks_exact_mut(2) {
+                cell[0] = text.next().unwrap_or(b' ');
+                cell[1] = attr;
+            }
+        }
+    }
+
+    fn draw_graphics(&self) {
+        let mode = current_video_mode();
+        let (width, _) = mode.pixel_dimensions();
+        let columns = (width / GLYPH_WIDTH) as usize;
+        let (first, _) = self.row_range(mode);
+
+        // fill the background
+        let screen = screen_bytes(mode);
+        let start = (first * Self::row_bytes(mode)).min(screen.len());
+        screen[start..].fill(self.attrs.background);
+
+        let offset = draw_offset();
+        let color = get_color();
+        let (font, bold, italic, underline) = text_style();
+        set_draw_offset(0, 0);
+        set_color(self.attrs.foreground);
+        set_text_style(Font::DEFAULT_8X8, false, false, false);
+        for (i, line) in self.visible_lines().enumerate() {
+            let Some(line) = line else {
+                continue;
+            };
+            let line = &line.as_bytes()[..line.len().min(columns)];
+            let y = (first + i) as i32 * GLYPH_HEIGHT as i32;
+            out_text_xy(0, y, line);
+        }
+        set_text_style(font, bold, italic, underline);
+        set_color(color);
+        set_draw_offset(offset.0, offset.1);
+    }
+}
+
+#[cfg(feature = "log")]
+struct ConsoleLogger;
+
+#[cfg(feature = "log")]
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            log(format_args!("[{}] {}", record.level(), record.args()));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(feature = "log")]
+static LOGGER: ConsoleLogger = ConsoleLogger;
+
+/// Installs the console as the logger of the `log` crate,
+/// so that messages up to the given level are logged to the console.
+///
+/// Fails if another logger was already installed.
+#[cfg(feature = "log")]
+pub fn init_logger(level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
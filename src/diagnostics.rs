@@ -0,0 +1,291 @@
+//! Report of the state of the application,
+//! to include in bug reports.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::music::{installed_soundbank_count, is_music_playing};
+use crate::sound::{is_sound_playing, sound_mode, SoundMode, SOUND_CHANNELS};
+use crate::video::{
+    current_video_mode, get_palette, installed_font_count, is_double_buffered, screen_height,
+    screen_width, VideoMode,
+};
+use crate::{frame_count, FIRST_FRAME};
+
+/// A snapshot of the state of the application,
+/// as collected by [`diagnostics`].
+///
+/// Its [`Display`](std::fmt::Display) implementation
+/// writes a readable report over multiple lines.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostics {
+    video_mode: VideoMode,
+    double_buffered: bool,
+    screen_width: u16,
+    screen_height: u16,
+    palette_checksum: u32,
+    sound_mode: Option<SoundMode>,
+    playing_channels: Vec<u8>,
+    music_playing: bool,
+    font_count: Option<usize>,
+    soundbank_count: u32,
+    frame_count: u64,
+    average_fps: Option<f64>,
+}
+
+impl Diagnostics {
+    /// Gets the video mode last set (see [`current_video_mode`](crate::current_video_mode)).
+    #[inline]
+    pub fn video_mode(&self) -> VideoMode {
+        self.video_mode
+    }
+
+    /// Checks whether double buffering was enabled
+    /// (see [`is_double_buffered`]).
+    #[inline]
+    pub fn double_buffered(&self) -> bool {
+        self.double_buffered
+    }
+
+    /// Gets the screen width and height in pixels,
+    /// as reported by the engine.
+    ///
+    /// A size which does not match the video mode
+    /// means that the engine did not switch to it.
+    #[inline]
+    pub fn screen_size(&self) -> (u16, u16) {
+        (self.screen_width, self.screen_height)
+    }
+
+    /// Gets a checksum of the 256 palette colors,
+    /// to tell apart palettes without listing them.
+    ///
+    /// An all-black palette, a common cause of a black screen,
+    /// has the checksum `0xF59C_39C5`.
+    #[inline]
+    pub fn palette_checksum(&self) -> u32 {
+        self.palette_checksum
+    }
+
+    /// Gets the sound mode last set,
+    /// or `None` if the engine is in its default sound mode
+    /// (see [`sound_mode`]).
+    #[inline]
+    pub fn sound_mode(&self) -> Option<SoundMode> {
+        self.sound_mode
+    }
+
+    /// Gets the sound channels playing a sound.
+    #[inline]
+    pub fn playing_channels(&self) -> &[u8] {
+        &self.playing_channels
+    }
+
+    /// Checks whether music was playing.
+    #[inline]
+    pub fn music_playing(&self) -> bool {
+        self.music_playing
+    }
+
+    /// Gets the number of fonts available,
+    /// including the built-in fonts
+    /// (see [`installed_fonts`](crate::installed_fonts)),
+    /// or `None` if the font registry was in use at the time.
+    #[inline]
+    pub fn font_count(&self) -> Option<usize> {
+        self.font_count
+    }
+
+    /// Gets the number of soundbanks installed
+    /// via [`install_user_soundbank`](crate::install_user_soundbank).
+    #[inline]
+    pub fn soundbank_count(&self) -> u32 {
+        self.soundbank_count
+    }
+
+    /// Gets the number of frames passed (see [`frame_count`]).
+    #[inline]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Gets the average number of frames per second
+    /// since the first frame,
+    /// or `None` before the second frame.
+    #[inline]
+    pub fn average_fps(&self) -> Option<f64> {
+        self.average_fps
+    }
+
+    /// Writes the report as pretty-printed JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("diagnostics should always serialize")
+    }
+}
+
+impl std::fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        writeln!(f, "dos-like diagnostics")?;
+        writeln!(
+            f,
+            "  video mode:       {} ({}x{} pixels reported)",
+            self.video_mode, self.screen_width, self.screen_height
+        )?;
+        writeln!(f, "  double buffered:  {}", yes_no(self.double_buffered))?;
+        writeln!(f, "  palette checksum: {:#010x}", self.palette_checksum)?;
+        match self.sound_mode {
+            Some(mode) => writeln!(f, "  sound mode:       {:?}", mode)?,
+            None => writeln!(f, "  sound mode:       engine default")?,
+        }
+        if self.playing_channels.is_empty() {
+            writeln!(f, "  sound channels:   none playing")?;
+        } else {
+            let channels: Vec<_> = self.playing_channels.iter().map(u8::to_string).collect();
+            writeln!(f, "  sound channels:   {} playing", channels.join(", "))?;
+        }
+        writeln!(f, "  music playing:    {}", yes_no(self.music_playing))?;
+        match self.font_count {
+            Some(count) => writeln!(f, "  fonts:            {} (3 built-in)", count)?,
+            None => writeln!(f, "  fonts:            unknown")?,
+        }
+        writeln!(f, "  soundbanks:       {} installed", self.soundbank_count)?;
+        write!(f, "  frames:           {}", self.frame_count)?;
+        if let Some(fps) = self.average_fps {
+            write!(f, ", {:.1} per second on average", fps)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the FNV-1a hash of the palette colors.
+fn palette_checksum() -> u32 {
+    let mut hash: u32 = 0x811C_9DC5;
+    for color in get_palette() {
+        for byte in <[u8; 3]>::from(color) {
+            hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+        }
+    }
+    hash
+}
+
+/// Collects the state of the application for a bug report,
+/// such as when the screen stays black or no sound is heard.
+///
+/// Everything comes from the state kept by this crate
+/// and from the engine's own getters,
+/// so this can be called at any time,
+/// even when the engine is not behaving.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// if key_state(KeyCode::KEY_F12) {
+///     eprintln!("{}", diagnostics());
+/// }
+/// ```
+pub fn diagnostics() -> Diagnostics {
+    let frame_count = frame_count();
+    let average_fps = FIRST_FRAME.get().and_then(|first| {
+        let elapsed = first.elapsed().as_secs_f64();
+        (frame_count > 1 && elapsed > 0.).then(|| (frame_count - 1) as f64 / elapsed)
+    });
+    Diagnostics {
+        video_mode: current_video_mode(),
+        double_buffered: is_double_buffered(),
+        screen_width: screen_width(),
+        screen_height: screen_height(),
+        palette_checksum: palette_checksum(),
+        sound_mode: sound_mode(),
+        playing_channels: (0..SOUND_CHANNELS as u8)
+            .filter(|&channel| is_sound_playing(channel))
+            .collect(),
+        music_playing: is_music_playing(),
+        font_count: installed_font_count(),
+        soundbank_count: installed_soundbank_count(),
+        frame_count,
+        average_fps,
+    }
+}
+
+/// Whether the panic hook was installed.
+static PANIC_HOOK: AtomicBool = AtomicBool::new(false);
+
+/// Writes the [`diagnostics`] report to standard error
+/// whenever the application panics,
+/// after the message of the panic hook in place before.
+///
+/// Calling this more than once has no further effect.
+pub fn dump_diagnostics_on_panic() {
+    if PANIC_HOOK.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        previous(info);
+        eprintln!("{}", diagnostics());
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Diagnostics {
+        Diagnostics {
+            video_mode: VideoMode::Graphics320x200,
+            double_buffered: true,
+            screen_width: 320,
+            screen_height: 200,
+            palette_checksum: 0xF59C_39C5,
+            sound_mode: None,
+            playing_channels: vec![0, 3],
+            music_playing: false,
+            font_count: Some(4),
+            soundbank_count: 1,
+            frame_count: 600,
+            average_fps: Some(59.94),
+        }
+    }
+
+    #[test]
+    fn report_lists_every_field() {
+        let report = sample().to_string();
+        let lines: Vec<_> = report.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "dos-like diagnostics");
+        assert!(lines[1].ends_with("(320x200 pixels reported)"));
+        assert_eq!(lines[2], "  double buffered:  yes");
+        assert_eq!(lines[3], "  palette checksum: 0xf59c39c5");
+        assert_eq!(lines[4], "  sound mode:       engine default");
+        assert_eq!(lines[5], "  sound channels:   0, 3 playing");
+        assert_eq!(lines[7], "  fonts:            4 (3 built-in)");
+        assert_eq!(
+            lines[9],
+            "  frames:           600, 59.9 per second on average"
+        );
+
+        let report = Diagnostics {
+            playing_channels: Vec::new(),
+            font_count: None,
+            average_fps: None,
+            ..sample()
+        }
+        .to_string();
+        assert!(report.contains("  sound channels:   none playing\n"));
+        assert!(report.contains("  fonts:            unknown\n"));
+        assert!(report.ends_with("  frames:           600"));
+    }
+
+    #[test]
+    fn black_palette_checksum() {
+        // the documented checksum of an all-black palette
+        let mut hash: u32 = 0x811C_9DC5;
+        for _ in 0..256 * 3 {
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+        assert_eq!(hash, 0xF59C_39C5);
+    }
+}
@@ -0,0 +1,191 @@
+//! Tracking of the window focus,
+//! and what to do while the window is not focused.
+//!
+//! The engine does not report focus changes,
+//! so the focus is asked to the platform layer underneath it
+//! once per frame, in [`wait_vbl`](crate::wait_vbl).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{input, music, shutting_down, sound};
+
+/// What the frame loop does while the window is not focused,
+/// as set by [`set_focus_policy`].
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum FocusPolicy {
+    /// Keep running as usual.
+    #[default]
+    KeepRunning,
+    /// Pause the frame loop:
+    /// [`wait_vbl`](crate::wait_vbl) does not return
+    /// until the window is focused again
+    /// or the application is shutting down.
+    Pause,
+    /// Run at the given number of frames per second (at least one),
+    /// as [`wait_vbl`](crate::wait_vbl) waits longer to return.
+    Throttle(u32),
+}
+
+/// The focus policy in effect.
+static POLICY: Mutex<FocusPolicy> = Mutex::new(FocusPolicy::KeepRunning);
+
+/// Whether to mute the audio while the window is not focused.
+static MUTE_ON_FOCUS_LOSS: AtomicBool = AtomicBool::new(false);
+
+/// Whether the window was focused at the last frame.
+static FOCUSED: AtomicBool = AtomicBool::new(true);
+
+/// Whether the audio was muted because of a focus loss.
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// When the last frame ended, to throttle the next one.
+static LAST_FRAME: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod platform {
+    pub fn is_focused() -> bool {
+        // Safety: SDL is linked in and initialized by the engine,
+        // and this only reads the window with keyboard focus
+        unsafe { !dos_like_sys::SDL_GetKeyboardFocus().is_null() }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use dos_like_sys::{GetCurrentProcessId, GetForegroundWindow, GetWindowThreadProcessId};
+
+    pub fn is_focused() -> bool {
+        // Safety: both functions accept any window handle,
+        // including a null one when no window is in the foreground
+        unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return false;
+            }
+            let mut process_id = 0;
+            GetWindowThreadProcessId(window, &mut process_id);
+            process_id == GetCurrentProcessId()
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    pub fn is_focused() -> bool {
+        true
+    }
+}
+
+/// Checks whether the application window has the keyboard focus.
+///
+/// On platforms where the focus cannot be known,
+/// such as the web, the window is always reported as focused.
+pub fn has_focus() -> bool {
+    platform::is_focused()
+}
+
+/// Sets what the frame loop does while the window is not focused.
+///
+/// Whatever the policy,
+/// the keys held down when the window loses focus
+/// are released (see [`key_state`](crate::key_state)),
+/// so that they do not get stuck.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_focus_policy(FocusPolicy::Throttle(10));
+/// set_mute_on_focus_loss(true);
+/// while !shutting_down() {
+///     wait_vbl();
+///     // runs at 10 frames per second and silent
+///     // while another window is in front
+/// }
+/// ```
+pub fn set_focus_policy(policy: FocusPolicy) {
+    *POLICY.lock().unwrap_or_else(|e| e.into_inner()) = policy;
+}
+
+/// Gets the focus policy last set with [`set_focus_policy`].
+pub fn focus_policy() -> FocusPolicy {
+    *POLICY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets whether the music and sounds are muted
+/// while the window is not focused.
+///
+/// The volumes last set are restored when the window is focused again.
+/// Changing the volumes while muted takes effect at that point.
+pub fn set_mute_on_focus_loss(mute: bool) {
+    MUTE_ON_FOCUS_LOSS.store(mute, Ordering::Relaxed);
+    if !mute && MUTED.swap(false, Ordering::Relaxed) {
+        set_muted(false);
+    }
+}
+
+/// Mutes or unmutes the music and all sound channels.
+fn set_muted(muted: bool) {
+    sound::silence_channels(muted);
+    music::silence_music(muted);
+}
+
+/// Reacts to a change of focus and applies the focus policy,
+/// to be called once per frame.
+pub(crate) fn update_focus() {
+    input::update_masked_keys();
+
+    let mut focused = has_focus();
+    if !focused {
+        if FOCUSED.swap(false, Ordering::Relaxed) {
+            input::release_all_keys();
+        }
+        if MUTE_ON_FOCUS_LOSS.load(Ordering::Relaxed) {
+            // applied on every frame,
+            // in case a sound or track started since the last one
+            MUTED.store(true, Ordering::Relaxed);
+            set_muted(true);
+        }
+        match focus_policy() {
+            FocusPolicy::KeepRunning => {}
+            FocusPolicy::Pause => {
+                while !focused && !shutting_down() {
+                    unsafe { dos_like_sys::waitvbl() }
+                    focused = has_focus();
+                }
+            }
+            FocusPolicy::Throttle(fps) => {
+                let last_frame = *LAST_FRAME.lock().unwrap_or_else(|e| e.into_inner());
+                let interval = Duration::from_secs(1) / fps.max(1);
+                if let Some(last_frame) = last_frame {
+                    while last_frame.elapsed() < interval && !shutting_down() {
+                        unsafe { dos_like_sys::waitvbl() }
+                    }
+                }
+            }
+        }
+    }
+    if focused {
+        FOCUSED.store(true, Ordering::Relaxed);
+        if MUTED.swap(false, Ordering::Relaxed) {
+            set_muted(false);
+        }
+    }
+    *LAST_FRAME.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn focus_policy_is_kept() {
+        assert_eq!(focus_policy(), FocusPolicy::KeepRunning);
+        set_focus_policy(FocusPolicy::Throttle(10));
+        assert_eq!(focus_policy(), FocusPolicy::Throttle(10));
+        set_focus_policy(FocusPolicy::default());
+        assert_eq!(focus_policy(), FocusPolicy::KeepRunning);
+    }
+}
@@ -0,0 +1,302 @@
+//! Module for basic geometric types,
+//! used to describe positions and regions on the screen.
+//!
+//! All of these types can be built from tuples,
+//! so that call sites can remain terse:
+//!
+//! ```no_run
+//! # use dos_like::geometry::*;
+//! let p: Point = (10, 20).into();
+//! let r: Rect = (0, 0, 320, 200).into();
+//! assert!(r.contains(p));
+//! ```
+
+use std::ops::{Add, Sub};
+
+/// A position in pixels or cells.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Point {
+    /// The position on the X axis.
+    pub x: i32,
+    /// The position on the Y axis.
+    pub y: i32,
+}
+
+impl Point {
+    /// The point at `(0, 0)`.
+    pub const ORIGIN: Point = Point { x: 0, y: 0 };
+
+    /// Creates a new point.
+    #[inline]
+    pub const fn new(x: i32, y: i32) -> Self {
+        Point { x, y }
+    }
+
+    /// Returns this point translated by the given amounts.
+    #[inline]
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        Point {
+            x: self.x.wrapping_add(dx),
+            y: self.y.wrapping_add(dy),
+        }
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    #[inline]
+    fn from((x, y): (i32, i32)) -> Self {
+        Point { x, y }
+    }
+}
+
+impl From<Point> for (i32, i32) {
+    #[inline]
+    fn from(p: Point) -> Self {
+        (p.x, p.y)
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+
+    #[inline]
+    fn add(self, rhs: Point) -> Point {
+        self.offset(rhs.x, rhs.y)
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+
+    #[inline]
+    fn sub(self, rhs: Point) -> Point {
+        Point {
+            x: self.x.wrapping_sub(rhs.x),
+            y: self.y.wrapping_sub(rhs.y),
+        }
+    }
+}
+
+/// A two-dimensional size in pixels or cells.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Size {
+    /// The size on the X axis.
+    pub width: u16,
+    /// The size on the Y axis.
+    pub height: u16,
+}
+
+impl Size {
+    /// Creates a new size.
+    #[inline]
+    pub const fn new(width: u16, height: u16) -> Self {
+        Size { width, height }
+    }
+
+    /// Gets the area covered by this size.
+    #[inline]
+    pub fn area(self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// Checks whether this size covers no area at all.
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+impl From<(u16, u16)> for Size {
+    #[inline]
+    fn from((width, height): (u16, u16)) -> Self {
+        Size { width, height }
+    }
+}
+
+impl From<Size> for (u16, u16) {
+    #[inline]
+    fn from(s: Size) -> Self {
+        (s.width, s.height)
+    }
+}
+
+/// An axis-aligned rectangle,
+/// described by its top-left corner and its size.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Rect {
+    /// The position of the left edge.
+    pub x: i32,
+    /// The position of the top edge.
+    pub y: i32,
+    /// The width of the rectangle.
+    pub width: u16,
+    /// The height of the rectangle.
+    pub height: u16,
+}
+
+impl Rect {
+    /// Creates a new rectangle.
+    #[inline]
+    pub const fn new(x: i32, y: i32, width: u16, height: u16) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Creates a new rectangle from its top-left corner and size.
+    #[inline]
+    pub fn from_origin_size(origin: impl Into<Point>, size: impl Into<Size>) -> Self {
+        let origin = origin.into();
+        let size = size.into();
+        Rect::new(origin.x, origin.y, size.width, size.height)
+    }
+
+    /// Creates a rectangle covering the two given corners,
+    /// both inclusive.
+    ///
+    /// The corners may be given in any order.
+    pub fn from_corners(a: impl Into<Point>, b: impl Into<Point>) -> Self {
+        let a = a.into();
+        let b = b.into();
+        let (x1, x2) = (a.x.min(b.x), a.x.max(b.x));
+        let (y1, y2) = (a.y.min(b.y), a.y.max(b.y));
+        Rect::from_edges(x1, y1, x2 as i64 + 1, y2 as i64 + 1)
+    }
+
+    /// Creates a rectangle from its edges,
+    /// saturating the size if it does not fit.
+    fn from_edges(left: i32, top: i32, right: i64, bottom: i64) -> Self {
+        let width = (right - left as i64).clamp(0, u16::MAX as i64) as u16;
+        let height = (bottom - top as i64).clamp(0, u16::MAX as i64) as u16;
+        Rect::new(left, top, width, height)
+    }
+
+    /// Gets the top-left corner of the rectangle.
+    #[inline]
+    pub fn origin(&self) -> Point {
+        Point::new(self.x, self.y)
+    }
+
+    /// Gets the size of the rectangle.
+    #[inline]
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// Gets the position of the left edge (inclusive).
+    #[inline]
+    pub fn left(&self) -> i32 {
+        self.x
+    }
+
+    /// Gets the position of the top edge (inclusive).
+    #[inline]
+    pub fn top(&self) -> i32 {
+        self.y
+    }
+
+    /// Gets the position of the right edge (exclusive).
+    #[inline]
+    pub fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    /// Gets the position of the bottom edge (exclusive).
+    #[inline]
+    pub fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    /// Checks whether this rectangle covers no area at all.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.size().is_empty()
+    }
+
+    /// Checks whether the given point is inside the rectangle.
+    pub fn contains(&self, point: impl Into<Point>) -> bool {
+        let Point { x, y } = point.into();
+        x >= self.x && (x as i64) < self.right() && y >= self.y && (y as i64) < self.bottom()
+    }
+
+    /// Obtains the area shared by this rectangle and another one,
+    /// or `None` if they do not overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        if right <= left as i64 || bottom <= top as i64 {
+            None
+        } else {
+            Some(Rect::from_edges(left, top, right, bottom))
+        }
+    }
+
+    /// Restricts this rectangle so that it fits inside `bounds`.
+    ///
+    /// If the two rectangles do not overlap,
+    /// an empty rectangle is returned.
+    pub fn clamp_to(&self, bounds: &Rect) -> Rect {
+        self.intersect(bounds)
+            .unwrap_or_else(|| Rect::new(bounds.x, bounds.y, 0, 0))
+    }
+
+    /// Returns this rectangle translated by the given amounts.
+    #[inline]
+    pub fn offset(&self, dx: i32, dy: i32) -> Rect {
+        Rect {
+            x: self.x.wrapping_add(dx),
+            y: self.y.wrapping_add(dy),
+            ..*self
+        }
+    }
+}
+
+impl From<(i32, i32, u16, u16)> for Rect {
+    #[inline]
+    fn from((x, y, width, height): (i32, i32, u16, u16)) -> Self {
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl From<(Point, Size)> for Rect {
+    #[inline]
+    fn from((origin, size): (Point, Size)) -> Self {
+        Rect::from_origin_size(origin, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_from_corners_in_any_order() {
+        let expected = Rect::new(2, 3, 5, 4);
+        assert_eq!(Rect::from_corners((2, 3), (6, 6)), expected);
+        assert_eq!(Rect::from_corners((6, 6), (2, 3)), expected);
+        assert_eq!(Rect::from_corners((6, 3), (2, 6)), expected);
+        assert_eq!(Rect::from_corners((2, 6), (6, 3)), expected);
+        assert_eq!(
+            Rect::from_corners((-4, 1), (-8, -1)),
+            Rect::new(-8, -1, 5, 3)
+        );
+    }
+
+    #[test]
+    fn rect_from_corners_degenerate() {
+        assert_eq!(Rect::from_corners((5, 4), (1, 4)), Rect::new(1, 4, 5, 1));
+        assert_eq!(Rect::from_corners((3, 0), (3, 9)), Rect::new(3, 0, 1, 10));
+        assert_eq!(Rect::from_corners((7, 7), (7, 7)), Rect::new(7, 7, 1, 1));
+    }
+
+    #[test]
+    fn rect_from_corners_saturates() {
+        let rect = Rect::from_corners((i32::MIN, 0), (i32::MAX, 0));
+        assert_eq!(rect, Rect::new(i32::MIN, 0, u16::MAX, 1));
+    }
+}
@@ -1,5 +1,14 @@
 //! Module for keyboard and mouse input functions.
 
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
 use dos_like_sys::{keycode_t, KEY_MODIFIER_RELEASED};
 use smallvec::SmallVec;
 
@@ -19,9 +28,496 @@ impl From<KeyCode> for u32 {
     }
 }
 
+impl KeyCode {
+    /// Gets the canonical name of this key,
+    /// which is the name of its associated constant without the `KEY_` prefix
+    /// (such as `"ESCAPE"`, `"F5"` or `"LCONTROL"`).
+    ///
+    /// Returns `None` if the key code is not known.
+    /// Where more than one constant maps to the same key code,
+    /// the first one is used (e.g. `"KANA"` instead of `"HANGUL"`).
+    pub fn name(self) -> Option<&'static str> {
+        KEY_NAMES
+            .iter()
+            .find(|(key, _)| *key == self)
+            .map(|(_, name)| *name)
+    }
+
+    /// Iterates over all known key codes.
+    pub fn all() -> impl Iterator<Item = KeyCode> {
+        let mut keys: SmallVec<[KeyCode; 256]> = SmallVec::new();
+        for (key, _) in KEY_NAMES {
+            if !keys.contains(key) {
+                keys.push(*key);
+            }
+        }
+        keys.into_iter()
+    }
+
+    /// Checks whether this is one of the letter keys, from A to Z.
+    pub fn is_letter(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_A
+                | KeyCode::KEY_B
+                | KeyCode::KEY_C
+                | KeyCode::KEY_D
+                | KeyCode::KEY_E
+                | KeyCode::KEY_F
+                | KeyCode::KEY_G
+                | KeyCode::KEY_H
+                | KeyCode::KEY_I
+                | KeyCode::KEY_J
+                | KeyCode::KEY_K
+                | KeyCode::KEY_L
+                | KeyCode::KEY_M
+                | KeyCode::KEY_N
+                | KeyCode::KEY_O
+                | KeyCode::KEY_P
+                | KeyCode::KEY_Q
+                | KeyCode::KEY_R
+                | KeyCode::KEY_S
+                | KeyCode::KEY_T
+                | KeyCode::KEY_U
+                | KeyCode::KEY_V
+                | KeyCode::KEY_W
+                | KeyCode::KEY_X
+                | KeyCode::KEY_Y
+                | KeyCode::KEY_Z
+        )
+    }
+
+    /// Checks whether this is one of the digit keys
+    /// of the main keyboard, from 0 to 9.
+    ///
+    /// The digits of the numeric keypad are not included,
+    /// see [`is_numpad`](Self::is_numpad).
+    pub fn is_digit(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_0
+                | KeyCode::KEY_1
+                | KeyCode::KEY_2
+                | KeyCode::KEY_3
+                | KeyCode::KEY_4
+                | KeyCode::KEY_5
+                | KeyCode::KEY_6
+                | KeyCode::KEY_7
+                | KeyCode::KEY_8
+                | KeyCode::KEY_9
+        )
+    }
+
+    /// Checks whether this is one of the keys of the numeric keypad:
+    /// the digits, the arithmetic operators,
+    /// the separator and the decimal point.
+    pub fn is_numpad(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_NUMPAD0
+                | KeyCode::KEY_NUMPAD1
+                | KeyCode::KEY_NUMPAD2
+                | KeyCode::KEY_NUMPAD3
+                | KeyCode::KEY_NUMPAD4
+                | KeyCode::KEY_NUMPAD5
+                | KeyCode::KEY_NUMPAD6
+                | KeyCode::KEY_NUMPAD7
+                | KeyCode::KEY_NUMPAD8
+                | KeyCode::KEY_NUMPAD9
+                | KeyCode::KEY_MULTIPLY
+                | KeyCode::KEY_ADD
+                | KeyCode::KEY_SEPARATOR
+                | KeyCode::KEY_SUBTRACT
+                | KeyCode::KEY_DECIMAL
+                | KeyCode::KEY_DIVIDE
+        )
+    }
+
+    /// Checks whether this is one of the function keys, from F1 to F24.
+    pub fn is_function_key(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_F1
+                | KeyCode::KEY_F2
+                | KeyCode::KEY_F3
+                | KeyCode::KEY_F4
+                | KeyCode::KEY_F5
+                | KeyCode::KEY_F6
+                | KeyCode::KEY_F7
+                | KeyCode::KEY_F8
+                | KeyCode::KEY_F9
+                | KeyCode::KEY_F10
+                | KeyCode::KEY_F11
+                | KeyCode::KEY_F12
+                | KeyCode::KEY_F13
+                | KeyCode::KEY_F14
+                | KeyCode::KEY_F15
+                | KeyCode::KEY_F16
+                | KeyCode::KEY_F17
+                | KeyCode::KEY_F18
+                | KeyCode::KEY_F19
+                | KeyCode::KEY_F20
+                | KeyCode::KEY_F21
+                | KeyCode::KEY_F22
+                | KeyCode::KEY_F23
+                | KeyCode::KEY_F24
+        )
+    }
+
+    /// Checks whether this is one of the modifier keys:
+    /// Shift, Control, Alt (`MENU`) and the Windows keys,
+    /// on either side.
+    ///
+    /// The lock keys, such as Caps Lock, are not included.
+    pub fn is_modifier(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_SHIFT
+                | KeyCode::KEY_LSHIFT
+                | KeyCode::KEY_RSHIFT
+                | KeyCode::KEY_CONTROL
+                | KeyCode::KEY_LCONTROL
+                | KeyCode::KEY_RCONTROL
+                | KeyCode::KEY_MENU
+                | KeyCode::KEY_LMENU
+                | KeyCode::KEY_RMENU
+                | KeyCode::KEY_LWIN
+                | KeyCode::KEY_RWIN
+        )
+    }
+
+    /// Checks whether this is one of the mouse buttons.
+    pub fn is_mouse_button(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_LBUTTON
+                | KeyCode::KEY_RBUTTON
+                | KeyCode::KEY_MBUTTON
+                | KeyCode::KEY_XBUTTON1
+                | KeyCode::KEY_XBUTTON2
+        )
+    }
+
+    /// Checks whether this is one of the navigation keys:
+    /// the arrow keys, Home, End, Page Up (`PRIOR`) and Page Down (`NEXT`).
+    pub fn is_navigation(self) -> bool {
+        matches!(
+            self,
+            KeyCode::KEY_LEFT
+                | KeyCode::KEY_UP
+                | KeyCode::KEY_RIGHT
+                | KeyCode::KEY_DOWN
+                | KeyCode::KEY_HOME
+                | KeyCode::KEY_END
+                | KeyCode::KEY_PRIOR
+                | KeyCode::KEY_NEXT
+        )
+    }
+}
+
+/// The canonical names of all keys, in the order of declaration.
+const KEY_NAMES: &[(KeyCode, &str)] = &[
+    (KeyCode::KEY_LBUTTON, "LBUTTON"),
+    (KeyCode::KEY_RBUTTON, "RBUTTON"),
+    (KeyCode::KEY_CANCEL, "CANCEL"),
+    (KeyCode::KEY_MBUTTON, "MBUTTON"),
+    (KeyCode::KEY_XBUTTON1, "XBUTTON1"),
+    (KeyCode::KEY_XBUTTON2, "XBUTTON2"),
+    (KeyCode::KEY_BACK, "BACK"),
+    (KeyCode::KEY_TAB, "TAB"),
+    (KeyCode::KEY_CLEAR, "CLEAR"),
+    (KeyCode::KEY_RETURN, "RETURN"),
+    (KeyCode::KEY_SHIFT, "SHIFT"),
+    (KeyCode::KEY_CONTROL, "CONTROL"),
+    (KeyCode::KEY_MENU, "MENU"),
+    (KeyCode::KEY_PAUSE, "PAUSE"),
+    (KeyCode::KEY_CAPITAL, "CAPITAL"),
+    (KeyCode::KEY_KANA, "KANA"),
+    (KeyCode::KEY_HANGUL, "HANGUL"),
+    (KeyCode::KEY_JUNJA, "JUNJA"),
+    (KeyCode::KEY_FINAL, "FINAL"),
+    (KeyCode::KEY_HANJA, "HANJA"),
+    (KeyCode::KEY_KANJI, "KANJI"),
+    (KeyCode::KEY_ESCAPE, "ESCAPE"),
+    (KeyCode::KEY_CONVERT, "CONVERT"),
+    (KeyCode::KEY_NONCONVERT, "NONCONVERT"),
+    (KeyCode::KEY_ACCEPT, "ACCEPT"),
+    (KeyCode::KEY_MODECHANGE, "MODECHANGE"),
+    (KeyCode::KEY_SPACE, "SPACE"),
+    (KeyCode::KEY_PRIOR, "PRIOR"),
+    (KeyCode::KEY_NEXT, "NEXT"),
+    (KeyCode::KEY_END, "END"),
+    (KeyCode::KEY_HOME, "HOME"),
+    (KeyCode::KEY_LEFT, "LEFT"),
+    (KeyCode::KEY_UP, "UP"),
+    (KeyCode::KEY_RIGHT, "RIGHT"),
+    (KeyCode::KEY_DOWN, "DOWN"),
+    (KeyCode::KEY_SELECT, "SELECT"),
+    (KeyCode::KEY_PRINT, "PRINT"),
+    (KeyCode::KEY_EXEC, "EXEC"),
+    (KeyCode::KEY_SNAPSHOT, "SNAPSHOT"),
+    (KeyCode::KEY_INSERT, "INSERT"),
+    (KeyCode::KEY_DELETE, "DELETE"),
+    (KeyCode::KEY_HELP, "HELP"),
+    (KeyCode::KEY_0, "0"),
+    (KeyCode::KEY_1, "1"),
+    (KeyCode::KEY_2, "2"),
+    (KeyCode::KEY_3, "3"),
+    (KeyCode::KEY_4, "4"),
+    (KeyCode::KEY_5, "5"),
+    (KeyCode::KEY_6, "6"),
+    (KeyCode::KEY_7, "7"),
+    (KeyCode::KEY_8, "8"),
+    (KeyCode::KEY_9, "9"),
+    (KeyCode::KEY_A, "A"),
+    (KeyCode::KEY_B, "B"),
+    (KeyCode::KEY_C, "C"),
+    (KeyCode::KEY_D, "D"),
+    (KeyCode::KEY_E, "E"),
+    (KeyCode::KEY_F, "F"),
+    (KeyCode::KEY_G, "G"),
+    (KeyCode::KEY_H, "H"),
+    (KeyCode::KEY_I, "I"),
+    (KeyCode::KEY_J, "J"),
+    (KeyCode::KEY_K, "K"),
+    (KeyCode::KEY_L, "L"),
+    (KeyCode::KEY_M, "M"),
+    (KeyCode::KEY_N, "N"),
+    (KeyCode::KEY_O, "O"),
+    (KeyCode::KEY_P, "P"),
+    (KeyCode::KEY_Q, "Q"),
+    (KeyCode::KEY_R, "R"),
+    (KeyCode::KEY_S, "S"),
+    (KeyCode::KEY_T, "T"),
+    (KeyCode::KEY_U, "U"),
+    (KeyCode::KEY_V, "V"),
+    (KeyCode::KEY_W, "W"),
+    (KeyCode::KEY_X, "X"),
+    (KeyCode::KEY_Y, "Y"),
+    (KeyCode::KEY_Z, "Z"),
+    (KeyCode::KEY_LWIN, "LWIN"),
+    (KeyCode::KEY_RWIN, "RWIN"),
+    (KeyCode::KEY_APPS, "APPS"),
+    (KeyCode::KEY_SLEEP, "SLEEP"),
+    (KeyCode::KEY_NUMPAD0, "NUMPAD0"),
+    (KeyCode::KEY_NUMPAD1, "NUMPAD1"),
+    (KeyCode::KEY_NUMPAD2, "NUMPAD2"),
+    (KeyCode::KEY_NUMPAD3, "NUMPAD3"),
+    (KeyCode::KEY_NUMPAD4, "NUMPAD4"),
+    (KeyCode::KEY_NUMPAD5, "NUMPAD5"),
+    (KeyCode::KEY_NUMPAD6, "NUMPAD6"),
+    (KeyCode::KEY_NUMPAD7, "NUMPAD7"),
+    (KeyCode::KEY_NUMPAD8, "NUMPAD8"),
+    (KeyCode::KEY_NUMPAD9, "NUMPAD9"),
+    (KeyCode::KEY_MULTIPLY, "MULTIPLY"),
+    (KeyCode::KEY_ADD, "ADD"),
+    (KeyCode::KEY_SEPARATOR, "SEPARATOR"),
+    (KeyCode::KEY_SUBTRACT, "SUBTRACT"),
+    (KeyCode::KEY_DECIMAL, "DECIMAL"),
+    (KeyCode::KEY_DIVIDE, "DIVIDE"),
+    (KeyCode::KEY_F1, "F1"),
+    (KeyCode::KEY_F2, "F2"),
+    (KeyCode::KEY_F3, "F3"),
+    (KeyCode::KEY_F4, "F4"),
+    (KeyCode::KEY_F5, "F5"),
+    (KeyCode::KEY_F6, "F6"),
+    (KeyCode::KEY_F7, "F7"),
+    (KeyCode::KEY_F8, "F8"),
+    (KeyCode::KEY_F9, "F9"),
+    (KeyCode::KEY_F10, "F10"),
+    (KeyCode::KEY_F11, "F11"),
+    (KeyCode::KEY_F12, "F12"),
+    (KeyCode::KEY_F13, "F13"),
+    (KeyCode::KEY_F14, "F14"),
+    (KeyCode::KEY_F15, "F15"),
+    (KeyCode::KEY_F16, "F16"),
+    (KeyCode::KEY_F17, "F17"),
+    (KeyCode::KEY_F18, "F18"),
+    (KeyCode::KEY_F19, "F19"),
+    (KeyCode::KEY_F20, "F20"),
+    (KeyCode::KEY_F21, "F21"),
+    (KeyCode::KEY_F22, "F22"),
+    (KeyCode::KEY_F23, "F23"),
+    (KeyCode::KEY_F24, "F24"),
+    (KeyCode::KEY_NUMLOCK, "NUMLOCK"),
+    (KeyCode::KEY_SCROLL, "SCROLL"),
+    (KeyCode::KEY_LSHIFT, "LSHIFT"),
+    (KeyCode::KEY_RSHIFT, "RSHIFT"),
+    (KeyCode::KEY_LCONTROL, "LCONTROL"),
+    (KeyCode::KEY_RCONTROL, "RCONTROL"),
+    (KeyCode::KEY_LMENU, "LMENU"),
+    (KeyCode::KEY_RMENU, "RMENU"),
+    (KeyCode::KEY_BROWSER_BACK, "BROWSER_BACK"),
+    (KeyCode::KEY_BROWSER_FORWARD, "BROWSER_FORWARD"),
+    (KeyCode::KEY_BROWSER_REFRESH, "BROWSER_REFRESH"),
+    (KeyCode::KEY_BROWSER_STOP, "BROWSER_STOP"),
+    (KeyCode::KEY_BROWSER_SEARCH, "BROWSER_SEARCH"),
+    (KeyCode::KEY_BROWSER_FAVORITES, "BROWSER_FAVORITES"),
+    (KeyCode::KEY_BROWSER_HOME, "BROWSER_HOME"),
+    (KeyCode::KEY_VOLUME_MUTE, "VOLUME_MUTE"),
+    (KeyCode::KEY_VOLUME_DOWN, "VOLUME_DOWN"),
+    (KeyCode::KEY_VOLUME_UP, "VOLUME_UP"),
+    (KeyCode::KEY_MEDIA_NEXT_TRACK, "MEDIA_NEXT_TRACK"),
+    (KeyCode::KEY_MEDIA_PREV_TRACK, "MEDIA_PREV_TRACK"),
+    (KeyCode::KEY_MEDIA_STOP, "MEDIA_STOP"),
+    (KeyCode::KEY_MEDIA_PLAY_PAUSE, "MEDIA_PLAY_PAUSE"),
+    (KeyCode::KEY_LAUNCH_MAIL, "LAUNCH_MAIL"),
+    (KeyCode::KEY_LAUNCH_MEDIA_SELECT, "LAUNCH_MEDIA_SELECT"),
+    (KeyCode::KEY_LAUNCH_APP1, "LAUNCH_APP1"),
+    (KeyCode::KEY_LAUNCH_APP2, "LAUNCH_APP2"),
+    (KeyCode::KEY_OEM_1, "OEM_1"),
+    (KeyCode::KEY_OEM_PLUS, "OEM_PLUS"),
+    (KeyCode::KEY_OEM_COMMA, "OEM_COMMA"),
+    (KeyCode::KEY_OEM_MINUS, "OEM_MINUS"),
+    (KeyCode::KEY_OEM_PERIOD, "OEM_PERIOD"),
+    (KeyCode::KEY_OEM_2, "OEM_2"),
+    (KeyCode::KEY_OEM_3, "OEM_3"),
+    (KeyCode::KEY_OEM_4, "OEM_4"),
+    (KeyCode::KEY_OEM_5, "OEM_5"),
+    (KeyCode::KEY_OEM_6, "OEM_6"),
+    (KeyCode::KEY_OEM_7, "OEM_7"),
+    (KeyCode::KEY_OEM_8, "OEM_8"),
+    (KeyCode::KEY_OEM_102, "OEM_102"),
+    (KeyCode::KEY_PROCESSKEY, "PROCESSKEY"),
+    (KeyCode::KEY_ATTN, "ATTN"),
+    (KeyCode::KEY_CRSEL, "CRSEL"),
+    (KeyCode::KEY_EXSEL, "EXSEL"),
+    (KeyCode::KEY_EREOF, "EREOF"),
+    (KeyCode::KEY_PLAY, "PLAY"),
+    (KeyCode::KEY_ZOOM, "ZOOM"),
+    (KeyCode::KEY_NONAME, "NONAME"),
+    (KeyCode::KEY_PA1, "PA1"),
+    (KeyCode::KEY_OEM_CLEAR, "OEM_CLEAR"),
+];
+
+/// Common alternative names for keys.
+const KEY_ALIASES: &[(&str, KeyCode)] = &[
+    ("CTRL", KeyCode::KEY_CONTROL),
+    ("LCTRL", KeyCode::KEY_LCONTROL),
+    ("RCTRL", KeyCode::KEY_RCONTROL),
+    ("ALT", KeyCode::KEY_MENU),
+    ("LALT", KeyCode::KEY_LMENU),
+    ("RALT", KeyCode::KEY_RMENU),
+    ("ESC", KeyCode::KEY_ESCAPE),
+    ("ENTER", KeyCode::KEY_RETURN),
+    ("BACKSPACE", KeyCode::KEY_BACK),
+    ("DEL", KeyCode::KEY_DELETE),
+    ("INS", KeyCode::KEY_INSERT),
+    ("PGUP", KeyCode::KEY_PRIOR),
+    ("PAGEUP", KeyCode::KEY_PRIOR),
+    ("PGDN", KeyCode::KEY_NEXT),
+    ("PAGEDOWN", KeyCode::KEY_NEXT),
+    ("CAPSLOCK", KeyCode::KEY_CAPITAL),
+    ("SCROLLLOCK", KeyCode::KEY_SCROLL),
+    ("PRINTSCREEN", KeyCode::KEY_SNAPSHOT),
+    ("LMB", KeyCode::KEY_LBUTTON),
+    ("RMB", KeyCode::KEY_RBUTTON),
+    ("MMB", KeyCode::KEY_MBUTTON),
+];
+
+/// Error returned when parsing a [`KeyCode`] from a string fails.
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct ParseKeyCodeError {
+    /// The text which could not be parsed.
+    pub input: String,
+    /// Known key names which are similar to the input,
+    /// most similar first.
+    pub suggestions: Vec<&'static str>,
+}
+
+impl std::fmt::Display for ParseKeyCodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unknown key name `{}`", self.input)?;
+        if !self.suggestions.is_empty() {
+            write!(f, " (did you mean {}?)", self.suggestions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseKeyCodeError {}
+
+/// Calculates the edit distance between two ASCII strings.
+fn edit_distance(a: &[u8], b: &[u8]) -> usize {
+    let mut row: SmallVec<[usize; 32]> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Parses a key code from its name, case insensitive.
+///
+/// Accepted names are the canonical names from [`KeyCode::name`]
+/// (with or without the `KEY_` prefix)
+/// plus some common aliases,
+/// such as `"Ctrl"` for `"CONTROL"`, `"Esc"` for `"ESCAPE"`,
+/// `"Enter"` for `"RETURN"`, or `"PgUp"` for `"PRIOR"`.
+/// Letters and digits can be given as single characters.
+///
+/// # Example
+///
+/// ```
+/// # use dos_like::KeyCode;
+/// assert_eq!("F5".parse(), Ok(KeyCode::KEY_F5));
+/// assert_eq!("lctrl".parse(), Ok(KeyCode::KEY_LCONTROL));
+/// assert_eq!("Esc".parse(), Ok(KeyCode::KEY_ESCAPE));
+///
+/// for key in KeyCode::all() {
+///     assert_eq!(key.name().unwrap().parse(), Ok(key));
+/// }
+/// ```
+impl std::str::FromStr for KeyCode {
+    type Err = ParseKeyCodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let input = s.trim().to_ascii_uppercase();
+        let name = input.strip_prefix("KEY_").unwrap_or(&input);
+
+        let found = KEY_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(key, _)| *key)
+            .or_else(|| {
+                KEY_ALIASES
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, key)| *key)
+            });
+        if let Some(key) = found {
+            return Ok(key);
+        }
+
+        let mut candidates: SmallVec<[(usize, &'static str); 8]> = KEY_NAMES
+            .iter()
+            .map(|(_, n)| *n)
+            .chain(KEY_ALIASES.iter().map(|(n, _)| *n))
+            .map(|n| (edit_distance(name.as_bytes(), n.as_bytes()), n))
+            .filter(|(distance, _)| *distance <= 2)
+            .collect();
+        candidates.sort_by_key(|(distance, _)| *distance);
+
+        Err(ParseKeyCodeError {
+            input: s.to_string(),
+            suggestions: candidates.into_iter().take(3).map(|(_, n)| n).collect(),
+        })
+    }
+}
+
 /// Checks whether a key is currently pushed (down).
+///
+/// Keys held down when the window lost focus
+/// are reported as released from then on,
+/// until the engine sees them released or pressed again
+/// (see [`has_focus`](crate::has_focus)).
 pub fn key_state(key: KeyCode) -> bool {
-    unsafe { dos_like_sys::keystate(key.0) != 0 }
+    unsafe { dos_like_sys::keystate(key.0) != 0 && !is_key_masked(key) }
 }
 
 /// A key press/release event.
@@ -56,13 +552,202 @@ impl KeyEvent {
     }
 }
 
-/// Reads the key press events available
-/// and saves them in an array.
+/// The maximum number of key events kept
+/// while waiting for [`read_keys`].
+const MAX_PENDING_KEYS: usize = 256;
+
+/// The maximum number of characters kept
+/// while waiting for [`read_chars`].
+const MAX_PENDING_CHARS: usize = 256;
+
+/// Key events taken from the engine but not yet read.
+static PENDING_KEYS: Mutex<VecDeque<KeyEvent>> = Mutex::new(VecDeque::new());
+
+/// Characters taken from the engine
+/// or produced by the numeric keypad but not yet read.
+static PENDING_CHARS: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+
+/// The input events taken from the engine during the current frame,
+/// for the snapshot taken by [`wait_vbl`](crate::wait_vbl),
+/// whether or not they were read in the meantime.
+static FRAME_EVENTS: Mutex<FrameEvents> = Mutex::new(FrameEvents {
+    keys: VecDeque::new(),
+    chars: VecDeque::new(),
+});
+
+/// The input snapshot taken by the last call to [`wait_vbl`](crate::wait_vbl).
+static FRAME_SNAPSHOT: Mutex<Option<InputSnapshot>> = Mutex::new(None);
+
+/// The key events and characters of a frame.
+#[derive(Debug)]
+struct FrameEvents {
+    keys: VecDeque<KeyEvent>,
+    chars: VecDeque<u8>,
+}
+
+impl FrameEvents {
+    fn push_key(&mut self, key: KeyEvent) {
+        if self.keys.len() == MAX_PENDING_KEYS {
+            self.keys.pop_front();
+        }
+        self.keys.push_back(key);
+    }
+
+    fn push_char(&mut self, c: u8) {
+        if self.chars.len() == MAX_PENDING_CHARS {
+            self.chars.pop_front();
+        }
+        self.chars.push_back(c);
+    }
+
+    /// Takes the events, key events first, as in [`read_events`].
+    fn take(&mut self) -> Vec<InputEvent> {
+        self.keys
+            .drain(..)
+            .map(InputEvent::Key)
+            .chain(self.chars.drain(..).map(InputEvent::Char))
+            .collect()
+    }
+}
+
+/// Queues a character until read by [`read_chars`].
+fn push_pending_char(chars: &mut VecDeque<u8>, frame: &mut FrameEvents, c: u8) {
+    if chars.len() == MAX_PENDING_CHARS {
+        chars.pop_front();
+    }
+    chars.push_back(c);
+    frame.push_char(c);
+}
+
+/// Keys held down when the window lost focus,
+/// one bit per key code,
+/// which are reported as released
+/// until the engine sees them released or pressed again.
+static MASKED_KEYS: Mutex<[u64; KEY_WORDS]> = Mutex::new([0; KEY_WORDS]);
+
+/// Checks whether a key was released on focus loss
+/// and the engine still reports it down.
+fn is_key_masked(key: KeyCode) -> bool {
+    let masked = MASKED_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    masked
+        .get(key.0 as usize / 64)
+        .is_some_and(|word| word & (1 << (key.0 % 64)) != 0)
+}
+
+/// Stops masking a key, returning whether it was masked.
+fn unmask_key(key: KeyCode) -> bool {
+    let mut masked = MASKED_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    match masked.get_mut(key.0 as usize / 64) {
+        Some(word) => {
+            let bit = 1 << (key.0 % 64);
+            let was_masked = *word & bit != 0;
+            *word &= !bit;
+            was_masked
+        }
+        None => false,
+    }
+}
+
+/// Stops masking the keys which the engine reports up,
+/// to be called once per frame.
+pub(crate) fn update_masked_keys() {
+    let mut masked = MASKED_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    for (i, word) in masked.iter_mut().enumerate() {
+        let mut bits = *word;
+        while bits != 0 {
+            let bit = bits.trailing_zeros();
+            bits &= bits - 1;
+            let code = (i * 64) as keycode_t + bit as keycode_t;
+            if unsafe { dos_like_sys::keystate(code) == 0 } {
+                *word &= !(1 << bit);
+            }
+        }
+    }
+}
+
+/// Releases every key held down,
+/// to be called when the window loses focus,
+/// since the engine may never see the keys go up.
 ///
-/// This creates an independent copy of the keys,
-/// consuming the underlying buffer in the process.
-pub fn read_keys() -> SmallVec<[KeyEvent; 2]> {
-    let mut keys = SmallVec::new();
+/// A release event is queued for each key,
+/// and the keys are reported as released by [`key_state`]
+/// until the engine sees them released or pressed again.
+pub(crate) fn release_all_keys() {
+    poll_keys();
+    let mut keys = PENDING_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut masked = MASKED_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut frame = FRAME_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
+    for code in 0..dos_like_sys::keycode_t_KEYCOUNT {
+        let bit = 1 << (code % 64);
+        let down = unsafe { dos_like_sys::keystate(code) != 0 };
+        if !down || masked[code as usize / 64] & bit != 0 {
+            continue;
+        }
+        masked[code as usize / 64] |= bit;
+        if keys.len() == MAX_PENDING_KEYS {
+            keys.pop_front();
+        }
+        keys.push_back(KeyEvent(code | KEY_MODIFIER_RELEASED));
+        frame.push_key(KeyEvent(code | KEY_MODIFIER_RELEASED));
+    }
+}
+
+/// Whether NumLock is assumed to be active.
+static NUM_LOCK: AtomicBool = AtomicBool::new(true);
+
+/// Checks whether NumLock is assumed to be active.
+///
+/// The engine cannot query the state of NumLock,
+/// so it is assumed to be active at startup
+/// and toggled whenever the NumLock key is pressed.
+/// Use [`set_num_lock`] if it gets out of sync.
+pub fn is_num_lock_on() -> bool {
+    NUM_LOCK.load(Ordering::Relaxed)
+}
+
+/// Sets whether NumLock is assumed to be active.
+///
+/// See [`is_num_lock_on`].
+pub fn set_num_lock(on: bool) {
+    NUM_LOCK.store(on, Ordering::Relaxed);
+}
+
+/// Gets the character typed by a key of the numeric keypad,
+/// if any.
+fn numpad_char(key: KeyCode, num_lock: bool) -> Option<u8> {
+    let digits = [
+        KeyCode::KEY_NUMPAD0,
+        KeyCode::KEY_NUMPAD1,
+        KeyCode::KEY_NUMPAD2,
+        KeyCode::KEY_NUMPAD3,
+        KeyCode::KEY_NUMPAD4,
+        KeyCode::KEY_NUMPAD5,
+        KeyCode::KEY_NUMPAD6,
+        KeyCode::KEY_NUMPAD7,
+        KeyCode::KEY_NUMPAD8,
+        KeyCode::KEY_NUMPAD9,
+    ];
+    match key {
+        KeyCode::KEY_ADD => Some(b'+'),
+        KeyCode::KEY_SUBTRACT => Some(b'-'),
+        KeyCode::KEY_MULTIPLY => Some(b'*'),
+        KeyCode::KEY_DIVIDE => Some(b'/'),
+        _ if !num_lock => None,
+        KeyCode::KEY_DECIMAL => Some(b'.'),
+        _ => digits
+            .iter()
+            .position(|&k| k == key)
+            .map(|i| b'0' + i as u8),
+    }
+}
+
+/// Takes the key events available from the engine,
+/// keeping them until read by [`read_keys`],
+/// and produces the characters typed on the numeric keypad.
+fn poll_keys() {
+    let mut keys = PENDING_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut chars = PENDING_CHARS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut frame = FRAME_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
 
     // Safety: readkeys is a valid pointer
     // to a null terminated sequence of keycode_t
@@ -73,20 +758,33 @@ pub fn read_keys() -> SmallVec<[KeyEvent; 2]> {
             if c == 0 {
                 break;
             }
-            keys.push(KeyEvent(c));
+            let key = KeyEvent(c);
+            // the release of a masked key was already reported
+            if unmask_key(key.key_code()) && key.is_released() {
+                continue;
+            }
+            if key.is_pressed() {
+                if key.key_code() == KeyCode::KEY_NUMLOCK {
+                    NUM_LOCK.fetch_xor(true, Ordering::Relaxed);
+                }
+                if let Some(c) = numpad_char(key.key_code(), is_num_lock_on()) {
+                    push_pending_char(&mut chars, &mut frame, c);
+                }
+            }
+            if keys.len() == MAX_PENDING_KEYS {
+                keys.pop_front();
+            }
+            keys.push_back(key);
+            frame.push_key(key);
         }
     }
-
-    keys
 }
 
-/// Reads the character input events available
-/// and saves them in an array.
-///
-/// This creates an independent copy of the characters,
-/// consuming the underlying buffer in the process.
-pub fn read_chars() -> SmallVec<[u8; 4]> {
-    let mut keys = SmallVec::new();
+/// Takes the characters available from the engine,
+/// keeping them until read by [`read_chars`].
+fn poll_chars() {
+    let mut chars = PENDING_CHARS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut frame = FRAME_EVENTS.lock().unwrap_or_else(|e| e.into_inner());
 
     // Safety: readchars is a valid pointer
     // to a null terminated sequence of bytes
@@ -97,11 +795,622 @@ pub fn read_chars() -> SmallVec<[u8; 4]> {
             if c == 0 {
                 break;
             }
-            keys.push(c as u8);
+            push_pending_char(&mut chars, &mut frame, c as u8);
         }
     }
+}
 
-    keys
+/// Reads the key press events available
+/// and saves them in an array.
+///
+/// This creates an independent copy of the keys,
+/// consuming the underlying buffer in the process.
+pub fn read_keys() -> SmallVec<[KeyEvent; 2]> {
+    poll_keys();
+    let mut keys = PENDING_KEYS.lock().unwrap_or_else(|e| e.into_inner());
+    keys.drain(..).collect()
+}
+
+/// Reads the character input events available
+/// and saves them in an array.
+///
+/// This creates an independent copy of the characters,
+/// consuming the underlying buffer in the process.
+///
+/// Characters typed on the numeric keypad are included:
+/// the operators at all times,
+/// and the digits and the decimal point while NumLock is active
+/// (see [`is_num_lock_on`]).
+/// The key events are kept for the next call to [`read_keys`].
+pub fn read_chars() -> SmallVec<[u8; 4]> {
+    poll_keys();
+    poll_chars();
+    PENDING_CHARS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .drain(..)
+        .collect()
+}
+
+/// An input event,
+/// either from a key press/release or from character input.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum InputEvent {
+    /// A key was pressed or released.
+    Key(KeyEvent),
+    /// A character was typed.
+    Char(u8),
+}
+
+impl From<KeyEvent> for InputEvent {
+    #[inline]
+    fn from(event: KeyEvent) -> Self {
+        InputEvent::Key(event)
+    }
+}
+
+impl From<u8> for InputEvent {
+    #[inline]
+    fn from(c: u8) -> Self {
+        InputEvent::Char(c)
+    }
+}
+
+/// Reads both the key events and the character input events available
+/// and saves them in an array,
+/// key events first.
+///
+/// This consumes the underlying buffers in the process,
+/// just like [`read_keys`] and [`read_chars`].
+pub fn read_events() -> SmallVec<[InputEvent; 6]> {
+    let mut events = SmallVec::new();
+    events.extend(read_keys().into_iter().map(InputEvent::Key));
+    events.extend(read_chars().into_iter().map(InputEvent::Char));
+    events
+}
+
+// -- input snapshots
+
+/// The number of 64-bit words needed for a bit per key code.
+const KEY_WORDS: usize = (dos_like_sys::keycode_t_KEYCOUNT as usize).div_ceil(64);
+
+/// The state of the keyboard and mouse at one instant,
+/// along with the input events which had arrived by then,
+/// as taken once per frame by [`wait_vbl`](crate::wait_vbl)
+/// (see [`frame_snapshot`])
+/// or on demand by [`snapshot`].
+///
+/// Game logic which queries a snapshot
+/// sees the same input no matter how many times it asks,
+/// even if the engine updates the input in the meantime.
+/// It is plain data,
+/// so it can be handed over to other threads
+/// or kept to replay a session.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// struct Player {
+///     x: i32,
+///     jumping: bool,
+/// }
+///
+/// fn update(player: &mut Player, input: &InputSnapshot) {
+///     if input.key_down(KeyCode::KEY_LEFT) {
+///         player.x -= 1;
+///     }
+///     if input.key_down(KeyCode::KEY_RIGHT) {
+///         player.x += 1;
+///     }
+///     if input.pressed(KeyCode::KEY_SPACE) {
+///         player.jumping = true;
+///     }
+/// }
+///
+/// let mut player = Player { x: 160, jumping: false };
+/// while !shutting_down() {
+///     wait_vbl();
+///     update(&mut player, &frame_snapshot());
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Eq, Hash, PartialEq)]
+pub struct InputSnapshot {
+    /// One bit per key code, set if the key is down
+    keys_down: [u64; KEY_WORDS],
+    mouse_x: i32,
+    mouse_y: i32,
+    mouse_rel_x: i32,
+    mouse_rel_y: i32,
+    events: Vec<InputEvent>,
+}
+
+impl InputSnapshot {
+    /// Checks whether a key or mouse button was down
+    /// when the snapshot was taken.
+    pub fn key_down(&self, key: KeyCode) -> bool {
+        let code = key.0 as usize;
+        self.keys_down
+            .get(code / 64)
+            .is_some_and(|word| word & (1 << (code % 64)) != 0)
+    }
+
+    /// Checks whether the snapshot has a press event of the given key,
+    /// meaning that it was pressed since the previous snapshot
+    /// (or during the previous frame, for [`frame_snapshot`]).
+    ///
+    /// A key pressed and released quickly enough
+    /// counts as pressed even though it is no longer down.
+    pub fn pressed(&self, key: KeyCode) -> bool {
+        self.key_events()
+            .any(|event| event.is_pressed() && event.key_code() == key)
+    }
+
+    /// Checks whether the snapshot has a release event of the given key,
+    /// meaning that it was released since the previous snapshot
+    /// (or during the previous frame, for [`frame_snapshot`]).
+    pub fn released(&self, key: KeyCode) -> bool {
+        self.key_events()
+            .any(|event| event.is_released() && event.key_code() == key)
+    }
+
+    /// Gets the absolute mouse position on the X axis.
+    #[inline]
+    pub fn mouse_x(&self) -> i32 {
+        self.mouse_x
+    }
+
+    /// Gets the absolute mouse position on the Y axis.
+    #[inline]
+    pub fn mouse_y(&self) -> i32 {
+        self.mouse_y
+    }
+
+    /// Gets the mouse relative position on the X axis,
+    /// as in [`mouse_rel_x`].
+    #[inline]
+    pub fn mouse_rel_x(&self) -> i32 {
+        self.mouse_rel_x
+    }
+
+    /// Gets the mouse relative position on the Y axis,
+    /// as in [`mouse_rel_y`].
+    #[inline]
+    pub fn mouse_rel_y(&self) -> i32 {
+        self.mouse_rel_y
+    }
+
+    /// Gets the input events taken with the snapshot,
+    /// key events first,
+    /// as in [`read_events`].
+    #[inline]
+    pub fn events(&self) -> &[InputEvent] {
+        &self.events
+    }
+
+    /// Iterates over the key events taken with the snapshot.
+    pub fn key_events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            InputEvent::Key(key) => Some(*key),
+            InputEvent::Char(_) => None,
+        })
+    }
+
+    /// Iterates over the characters typed, taken with the snapshot.
+    pub fn chars(&self) -> impl Iterator<Item = u8> + '_ {
+        self.events.iter().filter_map(|event| match event {
+            InputEvent::Char(c) => Some(*c),
+            InputEvent::Key(_) => None,
+        })
+    }
+}
+
+/// Takes a snapshot of the keyboard and mouse,
+/// to be queried as many times as necessary during a frame
+/// with consistent results.
+///
+/// The input events available are moved into the snapshot,
+/// consuming the underlying buffers
+/// just like [`read_events`] does,
+/// so this is meant to be called once per frame at most.
+/// Most applications would rather use [`frame_snapshot`],
+/// which does not consume the events.
+/// The mouse wheel is not included,
+/// as the engine does not report it.
+pub fn snapshot() -> InputSnapshot {
+    capture(read_events().into_vec())
+}
+
+/// Gets the snapshot of the keyboard and mouse
+/// taken by the last call to [`wait_vbl`](crate::wait_vbl),
+/// so that all of the game logic of a frame sees the same input.
+///
+/// Its events are those which arrived during the previous frame.
+/// They are not consumed:
+/// [`read_keys`], [`read_chars`] and [`read_events`]
+/// still report them once.
+/// Before the first call to `wait_vbl`,
+/// the snapshot is empty.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let mut x = 160;
+/// while !shutting_down() {
+///     wait_vbl();
+///     let input = frame_snapshot();
+///     if input.key_down(KeyCode::KEY_LEFT) {
+///         x -= 1;
+///     }
+///     if input.pressed(KeyCode::KEY_ESCAPE) {
+///         break;
+///     }
+/// }
+/// ```
+pub fn frame_snapshot() -> InputSnapshot {
+    FRAME_SNAPSHOT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Takes the snapshot of the frame,
+/// to be called once per frame by [`wait_vbl`](crate::wait_vbl).
+pub(crate) fn take_frame_snapshot() {
+    poll_keys();
+    poll_chars();
+    let events = FRAME_EVENTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take();
+    let snapshot = capture(events);
+    *FRAME_SNAPSHOT.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+}
+
+/// Captures the state of the keyboard and mouse
+/// along with the given events.
+fn capture(events: Vec<InputEvent>) -> InputSnapshot {
+    let mut keys_down = [0; KEY_WORDS];
+    for code in 0..dos_like_sys::keycode_t_KEYCOUNT {
+        if key_state(KeyCode(code)) {
+            keys_down[code as usize / 64] |= 1 << (code % 64);
+        }
+    }
+    InputSnapshot {
+        keys_down,
+        mouse_x: mouse_x(),
+        mouse_y: mouse_y(),
+        mouse_rel_x: mouse_rel_x(),
+        mouse_rel_y: mouse_rel_y(),
+        events,
+    }
+}
+
+// -- key sequences and chords
+
+/// Detects a sequence of key presses,
+/// such as a cheat code.
+///
+/// Only key presses are considered:
+/// key releases and character input are ignored.
+/// Pressing a key out of sequence
+/// or waiting too long between keys
+/// starts the detection over,
+/// although the wrong key may still begin a new attempt.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// # use std::time::Duration;
+/// let mut konami = SequenceDetector::new(
+///     &[
+///         KeyCode::KEY_UP, KeyCode::KEY_UP, KeyCode::KEY_DOWN, KeyCode::KEY_DOWN,
+///         KeyCode::KEY_LEFT, KeyCode::KEY_RIGHT, KeyCode::KEY_LEFT, KeyCode::KEY_RIGHT,
+///         KeyCode::KEY_B, KeyCode::KEY_A,
+///     ],
+///     Duration::from_millis(800),
+/// );
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     for event in read_events() {
+///         if konami.feed(&event) {
+///             // unlock all the things
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SequenceDetector {
+    sequence: Vec<KeyCode>,
+    timeout: Duration,
+    /// Number of keys of the sequence matched so far.
+    progress: usize,
+    /// When the last key of the sequence was matched.
+    last_press: Option<Instant>,
+}
+
+impl SequenceDetector {
+    /// Creates a detector for the given sequence of keys,
+    /// where each key must be pressed within `timeout` of the previous one.
+    pub fn new(sequence: &[KeyCode], timeout: Duration) -> Self {
+        SequenceDetector {
+            sequence: sequence.to_vec(),
+            timeout,
+            progress: 0,
+            last_press: None,
+        }
+    }
+
+    /// Gets the sequence of keys to detect.
+    #[inline]
+    pub fn sequence(&self) -> &[KeyCode] {
+        &self.sequence
+    }
+
+    /// Gets the number of keys of the sequence matched so far.
+    #[inline]
+    pub fn progress(&self) -> usize {
+        self.progress
+    }
+
+    /// Discards the keys matched so far.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.progress = 0;
+        self.last_press = None;
+    }
+
+    /// Feeds an input event to the detector,
+    /// returning true if it completes the sequence.
+    ///
+    /// After the sequence is completed,
+    /// detection starts over from the beginning.
+    #[inline]
+    pub fn feed(&mut self, event: &InputEvent) -> bool {
+        self.feed_at(event, Instant::now())
+    }
+
+    /// Feeds an input event which happened at the given instant
+    /// to the detector,
+    /// returning true if it completes the sequence.
+    ///
+    /// This is the same as [`feed`](Self::feed),
+    /// but allows replaying recorded input.
+    pub fn feed_at(&mut self, event: &InputEvent, now: Instant) -> bool {
+        let InputEvent::Key(key) = event else {
+            return false;
+        };
+        if key.is_released() || self.sequence.is_empty() {
+            return false;
+        }
+        let key = key.key_code();
+
+        let timed_out = self
+            .last_press
+            .is_some_and(|last| now.saturating_duration_since(last) > self.timeout);
+        if timed_out {
+            self.progress = 0;
+        }
+
+        // find the longest prefix of the sequence
+        // which ends with this key press
+        let matched = &self.sequence[..self.progress];
+        self.progress = (1..=self.progress + 1)
+            .rev()
+            .find(|&len| {
+                self.sequence[len - 1] == key
+                    && self.sequence[..len - 1] == matched[matched.len() + 1 - len..]
+            })
+            .unwrap_or(0);
+        self.last_press = if self.progress > 0 { Some(now) } else { None };
+
+        if self.progress == self.sequence.len() {
+            self.reset();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The chords last seen fully pressed by [`chord_down`].
+static CHORDS_DOWN: Mutex<Vec<Vec<KeyCode>>> = Mutex::new(Vec::new());
+
+/// Checks whether a combination of keys was just pressed,
+/// such as Ctrl+Shift+D.
+///
+/// This returns true when all keys in the chord are down
+/// and the chord was not fully down on the previous call with the same chord,
+/// so it fires once per press rather than on every frame.
+/// As such, it should be called once per frame for each chord of interest.
+///
+/// The last key of the chord is expected to be pressed last:
+/// holding it down before the others does not trigger the chord.
+///
+/// ```no_run
+/// # use dos_like::*;
+/// while !shutting_down() {
+///     wait_vbl();
+///     if chord_down(&[KeyCode::KEY_CONTROL, KeyCode::KEY_SHIFT, KeyCode::KEY_D]) {
+///         // toggle debug overlay
+///     }
+/// }
+/// ```
+pub fn chord_down(keys: &[KeyCode]) -> bool {
+    let Some((&last, modifiers)) = keys.split_last() else {
+        return false;
+    };
+    let mut chords = CHORDS_DOWN.lock().unwrap_or_else(|e| e.into_inner());
+    let known = chords.iter().position(|chord| chord == keys);
+
+    if !key_state(last) {
+        if let Some(i) = known {
+            chords.swap_remove(i);
+        }
+        return false;
+    }
+    if known.is_some() {
+        // still held since the last call
+        return false;
+    }
+    // the chord is held from now on,
+    // even if the modifiers are not down yet,
+    // so that the last key must be pressed again
+    chords.push(keys.to_vec());
+    modifiers.iter().all(|&key| key_state(key))
+}
+
+// -- conio style keyboard input
+
+/// Extended key code of F1, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F1: u8 = 59;
+/// Extended key code of F2, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F2: u8 = 60;
+/// Extended key code of F3, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F3: u8 = 61;
+/// Extended key code of F4, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F4: u8 = 62;
+/// Extended key code of F5, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F5: u8 = 63;
+/// Extended key code of F6, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F6: u8 = 64;
+/// Extended key code of F7, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F7: u8 = 65;
+/// Extended key code of F8, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F8: u8 = 66;
+/// Extended key code of F9, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F9: u8 = 67;
+/// Extended key code of F10, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F10: u8 = 68;
+/// Extended key code of Home, as returned by [`getch`] after a 0.
+pub const EXT_KEY_HOME: u8 = 71;
+/// Extended key code of the up arrow, as returned by [`getch`] after a 0.
+pub const EXT_KEY_UP: u8 = 72;
+/// Extended key code of Page Up, as returned by [`getch`] after a 0.
+pub const EXT_KEY_PAGE_UP: u8 = 73;
+/// Extended key code of the left arrow, as returned by [`getch`] after a 0.
+pub const EXT_KEY_LEFT: u8 = 75;
+/// Extended key code of the right arrow, as returned by [`getch`] after a 0.
+pub const EXT_KEY_RIGHT: u8 = 77;
+/// Extended key code of End, as returned by [`getch`] after a 0.
+pub const EXT_KEY_END: u8 = 79;
+/// Extended key code of the down arrow, as returned by [`getch`] after a 0.
+pub const EXT_KEY_DOWN: u8 = 80;
+/// Extended key code of Page Down, as returned by [`getch`] after a 0.
+pub const EXT_KEY_PAGE_DOWN: u8 = 81;
+/// Extended key code of Insert, as returned by [`getch`] after a 0.
+pub const EXT_KEY_INSERT: u8 = 82;
+/// Extended key code of Delete, as returned by [`getch`] after a 0.
+pub const EXT_KEY_DELETE: u8 = 83;
+/// Extended key code of F11, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F11: u8 = 133;
+/// Extended key code of F12, as returned by [`getch`] after a 0.
+pub const EXT_KEY_F12: u8 = 134;
+
+/// Pushback buffer of characters pending for [`getch`].
+static CONIO_BUFFER: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+
+/// Maps a key to its extended DOS key code, if it has one.
+fn extended_key_code(key: KeyCode) -> Option<u8> {
+    Some(match key {
+        KeyCode::KEY_F1 => EXT_KEY_F1,
+        KeyCode::KEY_F2 => EXT_KEY_F2,
+        KeyCode::KEY_F3 => EXT_KEY_F3,
+        KeyCode::KEY_F4 => EXT_KEY_F4,
+        KeyCode::KEY_F5 => EXT_KEY_F5,
+        KeyCode::KEY_F6 => EXT_KEY_F6,
+        KeyCode::KEY_F7 => EXT_KEY_F7,
+        KeyCode::KEY_F8 => EXT_KEY_F8,
+        KeyCode::KEY_F9 => EXT_KEY_F9,
+        KeyCode::KEY_F10 => EXT_KEY_F10,
+        KeyCode::KEY_HOME => EXT_KEY_HOME,
+        KeyCode::KEY_UP => EXT_KEY_UP,
+        KeyCode::KEY_PRIOR => EXT_KEY_PAGE_UP,
+        KeyCode::KEY_LEFT => EXT_KEY_LEFT,
+        KeyCode::KEY_RIGHT => EXT_KEY_RIGHT,
+        KeyCode::KEY_END => EXT_KEY_END,
+        KeyCode::KEY_DOWN => EXT_KEY_DOWN,
+        KeyCode::KEY_NEXT => EXT_KEY_PAGE_DOWN,
+        KeyCode::KEY_INSERT => EXT_KEY_INSERT,
+        KeyCode::KEY_DELETE => EXT_KEY_DELETE,
+        KeyCode::KEY_F11 => EXT_KEY_F11,
+        KeyCode::KEY_F12 => EXT_KEY_F12,
+        _ => return None,
+    })
+}
+
+/// Moves any pending input from the engine into the conio buffer,
+/// returning whether the buffer has anything in it.
+fn fill_conio_buffer() -> bool {
+    let mut buffer = CONIO_BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+    if buffer.is_empty() {
+        for key in read_keys() {
+            if let Some(code) = extended_key_code(key.key_code()).filter(|_| key.is_pressed()) {
+                buffer.extend([0, code]);
+            }
+        }
+        buffer.extend(read_chars());
+    }
+    !buffer.is_empty()
+}
+
+/// Checks whether a character is waiting to be read by [`getch`],
+/// without consuming it.
+///
+/// Like in conio, this also reports extended keys
+/// such as the arrow keys and the function keys.
+///
+/// Note that the pending input is taken from the engine's buffers,
+/// so it will no longer be available to [`read_keys`] or [`read_chars`].
+/// Avoid mixing these functions in the same input loop.
+pub fn kbhit() -> bool {
+    fill_conio_buffer()
+}
+
+/// Waits until a character is available and returns it.
+///
+/// While waiting, this function calls [`wait_vbl`](crate::wait_vbl)
+/// so that the application stays responsive.
+///
+/// Extended keys (arrows, function keys, Home, End, etc.)
+/// are encoded the same way as in DOS:
+/// the first call returns 0,
+/// and the next call returns the extended key code
+/// (see the `EXT_KEY_*` constants).
+///
+/// ```no_run
+/// # use dos_like::*;
+/// match getch() {
+///     0 => match getch() {
+///         EXT_KEY_UP => { /* move up */ }
+///         EXT_KEY_DOWN => { /* move down */ }
+///         _ => {}
+///     },
+///     b'q' => { /* quit */ }
+///     _ => {}
+/// }
+/// ```
+///
+/// Returns 0 if the application is shutting down
+/// before a character arrives
+/// (see [`shutting_down`](crate::shutting_down)).
+pub fn getch() -> u8 {
+    loop {
+        if fill_conio_buffer() {
+            let mut buffer = CONIO_BUFFER.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(c) = buffer.pop_front() {
+                return c;
+            }
+        }
+        if crate::shutting_down() {
+            return 0;
+        }
+        crate::wait_vbl();
+    }
 }
 
 /// Gets the absolute mouse position on the X axis.
@@ -304,3 +1613,368 @@ impl KeyCode {
     pub const KEY_OEM_CLEAR: Self = KeyCode(dos_like_sys::keycode_t_KEY_OEM_CLEAR);
     pub const KEYCOUNT: Self = KeyCode(dos_like_sys::keycode_t_KEYCOUNT);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_names_round_trip() {
+        let mut count = 0;
+        for key in KeyCode::all() {
+            let name = key.name().expect("every listed key has a name");
+            assert_eq!(name.parse(), Ok(key), "{}", name);
+            assert_eq!(name.to_ascii_lowercase().parse(), Ok(key), "{}", name);
+            assert_eq!(format!("KEY_{}", name).parse(), Ok(key), "{}", name);
+            count += 1;
+        }
+        assert!(count > 150, "only {} keys listed", count);
+    }
+
+    #[test]
+    fn key_names_are_unique() {
+        for (i, (_, a)) in KEY_NAMES.iter().enumerate() {
+            for (_, b) in &KEY_NAMES[i + 1..] {
+                assert_ne!(a, b);
+            }
+            assert!(
+                !KEY_ALIASES.iter().any(|(alias, _)| alias == a),
+                "alias {} shadows a canonical name",
+                a
+            );
+        }
+    }
+
+    #[test]
+    fn key_aliases_parse() {
+        for (alias, key) in KEY_ALIASES {
+            assert_eq!(alias.parse(), Ok(*key), "{}", alias);
+        }
+        assert_eq!("Ctrl".parse(), "Control".parse::<KeyCode>());
+        assert_eq!("Esc".parse(), Ok(KeyCode::KEY_ESCAPE));
+        assert_eq!("  Space ".parse(), Ok(KeyCode::KEY_SPACE));
+    }
+
+    #[test]
+    fn masked_keys_are_cleared_once_up() {
+        let key = KeyCode::KEY_OEM_CLEAR;
+        assert!(!is_key_masked(key));
+        MASKED_KEYS.lock().unwrap()[key.0 as usize / 64] |= 1 << (key.0 % 64);
+        assert!(is_key_masked(key));
+        assert!(!key_state(key));
+        assert!(unmask_key(key));
+        assert!(!unmask_key(key));
+        assert!(!is_key_masked(KeyCode(keycode_t::MAX >> 1)));
+
+        // the engine reports every key up here
+        MASKED_KEYS.lock().unwrap()[key.0 as usize / 64] |= 1 << (key.0 % 64);
+        update_masked_keys();
+        assert!(!is_key_masked(key));
+    }
+
+    #[test]
+    fn single_characters_parse() {
+        for (c, key) in ('a'..='z')
+            .zip(KeyCode::all().filter(|k| (KeyCode::KEY_A.0..=KeyCode::KEY_Z.0).contains(&k.0)))
+        {
+            assert_eq!(c.to_string().parse(), Ok(key));
+            assert_eq!(c.to_ascii_uppercase().to_string().parse(), Ok(key));
+        }
+        for (c, key) in ('0'..='9')
+            .zip(KeyCode::all().filter(|k| (KeyCode::KEY_0.0..=KeyCode::KEY_9.0).contains(&k.0)))
+        {
+            assert_eq!(c.to_string().parse(), Ok(key));
+        }
+    }
+
+    #[test]
+    fn unknown_key_names_suggest_near_misses() {
+        let err = "Escpe".parse::<KeyCode>().unwrap_err();
+        assert_eq!(err.input, "Escpe");
+        assert_eq!(err.suggestions.first(), Some(&"ESCAPE"));
+        assert!(err.to_string().contains("ESCAPE"));
+
+        let err = "F55".parse::<KeyCode>().unwrap_err();
+        assert!(err.suggestions.contains(&"F5"));
+        assert!(err.suggestions.len() <= 3);
+
+        let err = "definitely not a key".parse::<KeyCode>().unwrap_err();
+        assert!(err.suggestions.is_empty());
+        assert_eq!(err.to_string(), "Unknown key name `definitely not a key`");
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance(b"", b""), 0);
+        assert_eq!(edit_distance(b"TAB", b"TAB"), 0);
+        assert_eq!(edit_distance(b"TAB", b""), 3);
+        assert_eq!(edit_distance(b"ESCPE", b"ESCAPE"), 1);
+        assert_eq!(edit_distance(b"KITTEN", b"SITTING"), 3);
+    }
+
+    fn press(key: KeyCode) -> InputEvent {
+        InputEvent::Key(KeyEvent(key.0))
+    }
+
+    fn release(key: KeyCode) -> InputEvent {
+        InputEvent::Key(KeyEvent(key.0 | KEY_MODIFIER_RELEASED))
+    }
+
+    /// Feeds the events to the detector, one every 100 ms,
+    /// returning after which events the sequence was completed.
+    fn script(detector: &mut SequenceDetector, events: &[InputEvent]) -> Vec<usize> {
+        let start = Instant::now();
+        events
+            .iter()
+            .enumerate()
+            .filter(|(i, event)| {
+                detector.feed_at(event, start + Duration::from_millis(100 * *i as u64))
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn sequence_is_detected_and_starts_over() {
+        let keys = [KeyCode::KEY_UP, KeyCode::KEY_DOWN, KeyCode::KEY_A];
+        let mut detector = SequenceDetector::new(&keys, Duration::from_secs(1));
+        let events: Vec<_> = keys.iter().chain(&keys).map(|k| press(*k)).collect();
+        assert_eq!(script(&mut detector, &events), [2, 5]);
+        assert_eq!(detector.progress(), 0);
+    }
+
+    #[test]
+    fn sequence_ignores_releases_and_characters() {
+        let (a, b) = (KeyCode::KEY_A, KeyCode::KEY_B);
+        let mut detector = SequenceDetector::new(&[a, b], Duration::from_secs(1));
+        let events = [
+            press(a),
+            release(a),
+            InputEvent::Char(b'a'),
+            release(b),
+            press(b),
+        ];
+        assert_eq!(script(&mut detector, &events), [4]);
+    }
+
+    #[test]
+    fn sequence_restarts_on_wrong_key() {
+        let (a, b, c) = (KeyCode::KEY_A, KeyCode::KEY_B, KeyCode::KEY_C);
+        let mut detector = SequenceDetector::new(&[a, b, c], Duration::from_secs(1));
+        // the wrong key does not begin the sequence
+        let events = [press(a), press(b), press(b), press(c)];
+        assert!(script(&mut detector, &events).is_empty());
+        assert_eq!(detector.progress(), 0);
+        // the wrong key begins a new attempt
+        let events = [press(a), press(b), press(a), press(b), press(c)];
+        assert_eq!(script(&mut detector, &events), [4]);
+    }
+
+    #[test]
+    fn sequence_handles_overlapping_prefixes() {
+        let (a, b) = (KeyCode::KEY_A, KeyCode::KEY_B);
+        let mut detector = SequenceDetector::new(&[a, a, b], Duration::from_secs(1));
+        let events = [press(a), press(a), press(a), press(a), press(b)];
+        assert_eq!(script(&mut detector, &events), [4]);
+
+        let mut detector = SequenceDetector::new(&[a, b, a, b, b], Duration::from_secs(1));
+        let events = [
+            press(a),
+            press(b),
+            press(a),
+            press(b),
+            press(a),
+            press(b),
+            press(b),
+        ];
+        assert_eq!(script(&mut detector, &events), [6]);
+        assert_eq!(detector.progress(), 0);
+    }
+
+    #[test]
+    fn sequence_times_out() {
+        let (a, b) = (KeyCode::KEY_A, KeyCode::KEY_B);
+        let mut detector = SequenceDetector::new(&[a, b], Duration::from_millis(100));
+        let start = Instant::now();
+        assert!(!detector.feed_at(&press(a), start));
+        assert_eq!(detector.progress(), 1);
+        // just in time
+        assert!(detector.feed_at(&press(b), start + Duration::from_millis(100)));
+
+        assert!(!detector.feed_at(&press(a), start));
+        assert!(!detector.feed_at(&press(b), start + Duration::from_millis(101)));
+        assert_eq!(detector.progress(), 0);
+
+        // the late key may begin a new attempt
+        assert!(!detector.feed_at(&press(a), start));
+        assert!(!detector.feed_at(&press(a), start + Duration::from_millis(500)));
+        assert_eq!(detector.progress(), 1);
+    }
+
+    #[test]
+    fn sequence_reset_and_empty_sequence() {
+        let (a, b) = (KeyCode::KEY_A, KeyCode::KEY_B);
+        let mut detector = SequenceDetector::new(&[a, b], Duration::from_secs(1));
+        assert!(script(&mut detector, &[press(a)]).is_empty());
+        detector.reset();
+        assert_eq!(detector.progress(), 0);
+        assert!(script(&mut detector, &[press(b)]).is_empty());
+
+        let mut empty = SequenceDetector::new(&[], Duration::from_secs(1));
+        assert!(script(&mut empty, &[press(a), release(a)]).is_empty());
+        assert!(empty.sequence().is_empty());
+    }
+
+    /// The classes of a key as expected from its name,
+    /// in the order of the classifiers.
+    fn expected_classes(name: &str) -> [bool; 7] {
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit());
+        [
+            name.len() == 1 && name.as_bytes()[0].is_ascii_uppercase(),
+            name.len() == 1 && name.as_bytes()[0].is_ascii_digit(),
+            name.strip_prefix("NUMPAD").is_some_and(digits)
+                || [
+                    "MULTIPLY",
+                    "ADD",
+                    "SEPARATOR",
+                    "SUBTRACT",
+                    "DECIMAL",
+                    "DIVIDE",
+                ]
+                .contains(&name),
+            name.strip_prefix('F').is_some_and(digits),
+            [
+                "SHIFT", "LSHIFT", "RSHIFT", "CONTROL", "LCONTROL", "RCONTROL", "MENU", "LMENU",
+                "RMENU", "LWIN", "RWIN",
+            ]
+            .contains(&name),
+            name.ends_with("BUTTON") || name.starts_with("XBUTTON"),
+            [
+                "LEFT", "UP", "RIGHT", "DOWN", "HOME", "END", "PRIOR", "NEXT",
+            ]
+            .contains(&name),
+        ]
+    }
+
+    fn classes(key: KeyCode) -> [bool; 7] {
+        [
+            key.is_letter(),
+            key.is_digit(),
+            key.is_numpad(),
+            key.is_function_key(),
+            key.is_modifier(),
+            key.is_mouse_button(),
+            key.is_navigation(),
+        ]
+    }
+
+    #[test]
+    fn key_classes() {
+        let mut counts = [0; 7];
+        for key in KeyCode::all() {
+            let name = key.name().unwrap();
+            let classes = classes(key);
+            assert_eq!(classes, expected_classes(name), "{}", name);
+            // no key is in more than one class
+            assert!(classes.iter().filter(|&&c| c).count() <= 1, "{}", name);
+            for (count, class) in counts.iter_mut().zip(classes) {
+                *count += class as usize;
+            }
+        }
+        // letters, digits, numpad, function keys, modifiers, mouse, navigation
+        assert_eq!(counts, [26, 10, 16, 24, 11, 5, 8]);
+    }
+
+    #[test]
+    fn key_classes_of_other_codes() {
+        for key in [
+            KeyCode::KEY_SPACE,
+            KeyCode::KEY_RETURN,
+            KeyCode::KEY_ESCAPE,
+            KeyCode::KEY_CAPITAL,
+            KeyCode::KEY_NUMLOCK,
+            KeyCode::KEY_INSERT,
+            KeyCode::KEY_DELETE,
+            KeyCode::KEYCOUNT,
+        ] {
+            assert_eq!(classes(key), [false; 7], "{:?}", key.name());
+        }
+    }
+
+    #[test]
+    fn frame_events_keys_first_and_bounded() {
+        let mut frame = FrameEvents {
+            keys: VecDeque::new(),
+            chars: VecDeque::new(),
+        };
+        frame.push_char(b'a');
+        frame.push_key(KeyEvent(KeyCode::KEY_A.0));
+        frame.push_char(b'b');
+        frame.push_key(KeyEvent(KeyCode::KEY_B.0 | KEY_MODIFIER_RELEASED));
+        assert_eq!(
+            frame.take(),
+            [
+                press(KeyCode::KEY_A),
+                release(KeyCode::KEY_B),
+                InputEvent::Char(b'a'),
+                InputEvent::Char(b'b'),
+            ]
+        );
+        assert!(frame.take().is_empty());
+
+        // the oldest events are dropped
+        for i in 0..MAX_PENDING_CHARS + 10 {
+            frame.push_char(i as u8);
+            frame.push_key(KeyEvent(i as keycode_t));
+        }
+        let events = frame.take();
+        assert_eq!(events.len(), MAX_PENDING_KEYS + MAX_PENDING_CHARS);
+        assert_eq!(events[0], InputEvent::Key(KeyEvent(10)));
+        assert_eq!(events[MAX_PENDING_KEYS], InputEvent::Char(10));
+    }
+
+    #[test]
+    fn snapshot_queries() {
+        let mut snapshot = InputSnapshot {
+            mouse_x: 12,
+            mouse_y: -3,
+            events: vec![
+                press(KeyCode::KEY_SPACE),
+                release(KeyCode::KEY_LEFT),
+                InputEvent::Char(b' '),
+            ],
+            ..Default::default()
+        };
+        for key in [
+            KeyCode::KEY_SPACE,
+            KeyCode::KEY_LBUTTON,
+            KeyCode::KEY_OEM_CLEAR,
+        ] {
+            snapshot.keys_down[key.0 as usize / 64] |= 1 << (key.0 % 64);
+        }
+
+        for key in KeyCode::all() {
+            let down = [
+                KeyCode::KEY_SPACE,
+                KeyCode::KEY_LBUTTON,
+                KeyCode::KEY_OEM_CLEAR,
+            ]
+            .contains(&key);
+            assert_eq!(snapshot.key_down(key), down, "{:?}", key.name());
+        }
+        assert!(!snapshot.key_down(KeyCode::KEYCOUNT));
+        assert!(!snapshot.key_down(KeyCode(keycode_t::MAX >> 1)));
+
+        assert!(snapshot.pressed(KeyCode::KEY_SPACE));
+        assert!(!snapshot.released(KeyCode::KEY_SPACE));
+        assert!(snapshot.released(KeyCode::KEY_LEFT));
+        assert!(!snapshot.pressed(KeyCode::KEY_LEFT));
+        assert_eq!(snapshot.key_events().count(), 2);
+        assert_eq!(snapshot.chars().collect::<Vec<_>>(), b" ");
+        assert_eq!((snapshot.mouse_x(), snapshot.mouse_y()), (12, -3));
+
+        let empty = InputSnapshot::default();
+        assert!(KeyCode::all().all(|key| !empty.key_down(key)));
+        assert!(empty.events().is_empty());
+    }
+}
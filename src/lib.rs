@@ -56,19 +56,71 @@
 //! panic = "abort"
 //! ```
 //!
+//! ## Command line flags
+//!
+//! Applications take a few flags for the framework on the command line,
+//! such as `--window` to start in a window
+//! or `--screenshot-dir PATH` to save screenshots elsewhere,
+//! as listed by [`framework_usage`].
+//! [`args`] gives the other arguments,
+//! so that applications do not need to skip these flags themselves.
+//! Flags which the framework cannot honor, such as `--scale`,
+//! make [`dos_main!`] exit with an error
+//! (see [`LaunchOptions::error`]).
+//!
 //! ## Cargo features
 //!
 //! - **`disable-screen-frame`**:
 //!   when enabled, compiles `dos-like` so that
 //!   the CRT screen frame around the viewport does not appear.
 //!   The other CRT screen effects will remain.
+//! - **`gl-loader`**:
+//!   when enabled, the OpenGL functions needed by `dos-like`
+//!   are loaded at run time through SDL,
+//!   so that GLEW is not required to build or run the application.
+//!   Only relevant on Linux and MacOS.
+//! - **`ogg`**:
+//!   when enabled, sounds can be loaded from Ogg Vorbis files
+//!   (see `sound::load_ogg`).
+//! - **`flac`**:
+//!   when enabled, sounds can be loaded from FLAC files
+//!   (see `sound::load_flac`).
+//! - **`log`**:
+//!   when enabled, the virtual console can be installed
+//!   as the logger of the `log` crate
+//!   (see `console::init_logger`).
+//! - **`profiling`**:
+//!   when enabled, the functions in the `profile` module
+//!   measure the time spent in each frame.
+//!   Otherwise they do nothing.
+//! - **`serde`**:
+//!   when enabled, the report of `diagnostics` can be written as JSON
+//!   (see `Diagnostics::to_json`).
+//! - **`watch`**:
+//!   when enabled, the `watch` module is available
+//!   for reloading images and sounds when their files change,
+//!   which is useful during development.
 #![allow(clippy::too_many_arguments)]
 
+mod args;
+mod assets;
+pub mod console;
+mod diagnostics;
+mod focus;
+pub mod geometry;
 pub mod input;
 pub mod music;
+pub mod profile;
 pub mod sound;
 pub mod video;
+#[cfg(feature = "watch")]
+pub mod watch;
 
+pub use args::{args, framework_usage, launch_options, LaunchError, LaunchOptions};
+pub use assets::asset_path;
+pub use diagnostics::{diagnostics, dump_diagnostics_on_panic, Diagnostics};
+pub use focus::{focus_policy, has_focus, set_focus_policy, set_mute_on_focus_loss, FocusPolicy};
+pub use geometry::*;
 pub use input::*;
 pub use music::*;
 pub use sound::*;
@@ -76,13 +128,52 @@ pub use video::*;
 
 pub use dos_like_sys;
 
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
 /// Calls `waitvbl`, which waits for a vertical blanking signal.
 ///
 /// This should usually be called once per frame.
+/// It also advances the volume ramps of sound channels
+/// (see [`fade_channel`]),
+/// starts queued sounds (see [`queue_sound`]),
+/// runs the scheduled audio actions which are due (see [`schedule`]),
+/// detects the end of the music track (see [`music_finished`]),
+/// applies the focus policy while the window is not focused
+/// (see [`set_focus_policy`]),
+/// and takes the input snapshot of the new frame
+/// (see [`frame_snapshot`]).
+/// Returning from it ends the frame for the [`profile`] module.
 pub fn wait_vbl() {
+    let wait_start = profile::wait_started();
     unsafe {
         dos_like_sys::waitvbl();
     }
+    FRAME_COUNT.fetch_add(1, Ordering::Relaxed);
+    FIRST_FRAME.get_or_init(Instant::now);
+    profile::frame_finished(wait_start);
+    sound::update_channels();
+    sound::run_scheduled();
+    music::update_music();
+    focus::update_focus();
+    input::take_frame_snapshot();
+}
+
+/// The number of calls to [`wait_vbl`] so far.
+static FRAME_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// When the first call to [`wait_vbl`] returned,
+/// to measure the average frame rate.
+static FIRST_FRAME: OnceLock<Instant> = OnceLock::new();
+
+/// Gets the number of frames passed so far,
+/// counted by the calls to [`wait_vbl`].
+///
+/// This is the clock of the audio actions scheduled in frames
+/// (see [`schedule`]).
+pub fn frame_count() -> u64 {
+    FRAME_COUNT.load(Ordering::Relaxed)
 }
 
 /// Checks whether the application should shut down.
@@ -102,12 +193,24 @@ pub fn shutting_down() -> bool {
 }
 
 /// General error type for file loading functions which can fail
+///
+/// More kinds of errors may be added in the future,
+/// so matching on this type needs a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum FileError {
     /// Invalid file path (typically due to the presence of null bytes in the string)
     BadFilePath,
     /// File not found, or failed to read
     FileNotFound,
+    /// The file was read, but its contents are invalid or unsupported
+    InvalidData(String),
+    /// Failed to write the file
+    WriteFailed,
+    /// An asset was not found in any of the locations searched
+    /// (see [`asset_path`]),
+    /// with the paths that were tried
+    AssetNotFound(Vec<std::path::PathBuf>),
 }
 
 impl std::fmt::Display for FileError {
@@ -115,6 +218,15 @@ impl std::fmt::Display for FileError {
         match self {
             FileError::BadFilePath => write!(f, "Invalid file path"),
             FileError::FileNotFound => write!(f, "Failed to read file"),
+            FileError::InvalidData(msg) => write!(f, "Invalid file data: {}", msg),
+            FileError::WriteFailed => write!(f, "Failed to write file"),
+            FileError::AssetNotFound(tried) => {
+                write!(f, "Asset not found, tried:")?;
+                for path in tried {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -122,6 +234,10 @@ impl std::fmt::Display for FileError {
 /// Declares and defines the main application function.
 ///
 /// This macro can be used as an alternative to declaring `dosmain` manually.
+/// Before running the given code,
+/// it checks the flags for the framework on the command line,
+/// and exits with status 2
+/// if there is an error in them (see [`LaunchOptions::error`]).
 ///
 /// # Example
 ///
@@ -143,6 +259,10 @@ impl std::fmt::Display for FileError {
 ///
 /// #[no_mangle]
 /// pub extern "C" fn dosmain(_argc: c_int, _argv: *const *const c_char) -> c_int {
+///     if let Some(error) = dos_like::launch_options().error() {
+///         eprint!("{}\n\nFramework options:\n{}", error, dos_like::framework_usage());
+///         return 2;
+///     }
 ///     println!("Hello");
 ///     0
 /// }
@@ -152,8 +272,50 @@ macro_rules! dos_main {
     ($($t:tt)*) => {
         #[no_mangle]
         pub extern "C" fn dosmain(_argc: std::os::raw::c_int, _argv: *const *const std::os::raw::c_char) -> std::os::raw::c_int {
+            if let Some(error) = $crate::launch_options().error() {
+                eprint!("{}\n\nFramework options:\n{}", error, $crate::framework_usage());
+                return 2;
+            }
             $($t)*;
             0
         }
     };
 }
+
+/// Writes formatted text to the screen in text mode,
+/// in the style of conio's `cprintf`.
+///
+/// The text is written at the current cursor position
+/// with the current text colors.
+/// See [`put_fmt`] for how control characters are handled.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let score = 9001;
+/// cprintf!("Score: {:>6}\r\n", score);
+/// ```
+#[macro_export]
+macro_rules! cprintf {
+    ($($arg:tt)*) => {
+        $crate::video::put_fmt(format_args!($($arg)*))
+    };
+}
+
+/// Logs formatted text to the virtual console,
+/// see [`console::log`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let (x, y) = (10, 20);
+/// console_log!("player at ({}, {})", x, y);
+/// ```
+#[macro_export]
+macro_rules! console_log {
+    ($($arg:tt)*) => {
+        $crate::console::log(format_args!($($arg)*))
+    };
+}
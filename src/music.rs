@@ -2,15 +2,93 @@
 //!
 //! See also [`sound`](super::sound) for the sound module.
 
-use std::{ffi::CString, num::NonZeroU32, os::raw::c_int, ptr::NonNull};
+use std::{
+    ffi::CString,
+    num::NonZeroU32,
+    os::raw::c_int,
+    path::PathBuf,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::FileError;
 
+mod instruments;
+mod looping;
+mod playlist;
+mod tracker;
+
+pub use instruments::GM_INSTRUMENT_NAMES;
+pub use looping::{play_music_with_loop_points, LoopPointError, MusicPosition};
+pub use playlist::{Playlist, RepeatMode};
+
+/// The format of the file which a [`Music`] was created from.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum MusicFormat {
+    /// Standard MIDI file
+    Mid,
+    /// DMX MUS file, as in Doom
+    Mus,
+    /// Amiga module
+    Mod,
+    /// OPL register dump
+    Opb,
+    /// FastTracker 2 extended module
+    /// (converted to a MOD)
+    Xm,
+    /// Scream Tracker 3 module
+    /// (converted to a MOD)
+    S3m,
+}
+
 /// A music object.
 ///
 /// This is a wrapper around the [`dos_like_sys::music_t`] struct.
 #[derive(Debug)]
-pub struct Music(NonNull<dos_like_sys::music_t>);
+pub struct Music(
+    NonNull<dos_like_sys::music_t>,
+    MusicFormat,
+    Option<MusicSource>,
+);
+
+/// Where the data of a music can be read again,
+/// to rebuild it with loop points.
+#[derive(Clone)]
+enum MusicSource {
+    /// The file it was loaded from
+    File(PathBuf),
+    /// A copy of the data it was created from
+    Memory(Arc<[u8]>),
+}
+
+impl std::fmt::Debug for MusicSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MusicSource::File(path) => f.debug_tuple("File").field(path).finish(),
+            MusicSource::Memory(data) => write!(f, "Memory({} bytes)", data.len()),
+        }
+    }
+}
+
+impl MusicSource {
+    fn file(path: &str) -> Option<MusicSource> {
+        Some(MusicSource::File(PathBuf::from(path)))
+    }
+
+    /// Reads the data of the music.
+    fn read(&self) -> Result<Arc<[u8]>, FileError> {
+        match self {
+            MusicSource::File(path) => std::fs::read(path)
+                .map(Arc::from)
+                .map_err(|_| FileError::FileNotFound),
+            MusicSource::Memory(data) => Ok(data.clone()),
+        }
+    }
+}
 
 unsafe impl Send for Music {}
 
@@ -22,7 +100,11 @@ impl Music {
         unsafe {
             let music = dos_like_sys::loadmid(filename.as_ptr() as *const _);
             if let Some(music) = NonNull::new(music) {
-                Ok(Music(music))
+                Ok(Music(
+                    music,
+                    MusicFormat::Mid,
+                    MusicSource::file(path.as_ref()),
+                ))
             } else {
                 Err(FileError::FileNotFound)
             }
@@ -36,7 +118,11 @@ impl Music {
         unsafe {
             let music = dos_like_sys::loadmus(filename.as_ptr() as *const _);
             if let Some(music) = NonNull::new(music) {
-                Ok(Music(music))
+                Ok(Music(
+                    music,
+                    MusicFormat::Mus,
+                    MusicSource::file(path.as_ref()),
+                ))
             } else {
                 Err(FileError::FileNotFound)
             }
@@ -50,7 +136,11 @@ impl Music {
         unsafe {
             let music = dos_like_sys::loadmod(filename.as_ptr() as *const _);
             if let Some(music) = NonNull::new(music) {
-                Ok(Music(music))
+                Ok(Music(
+                    music,
+                    MusicFormat::Mod,
+                    MusicSource::file(path.as_ref()),
+                ))
             } else {
                 Err(FileError::FileNotFound)
             }
@@ -64,7 +154,11 @@ impl Music {
         unsafe {
             let music = dos_like_sys::loadopb(filename.as_ptr() as *const _);
             if let Some(music) = NonNull::new(music) {
-                Ok(Music(music))
+                Ok(Music(
+                    music,
+                    MusicFormat::Opb,
+                    MusicSource::file(path.as_ref()),
+                ))
             } else {
                 Err(FileError::FileNotFound)
             }
@@ -89,10 +183,103 @@ impl Music {
         // no data is never written via the pointer.
         unsafe {
             let music = dos_like_sys::createmus(data.as_ptr() as *mut _, data.len() as c_int);
-            NonNull::new(music).map(Music)
+            NonNull::new(music).map(|music| {
+                Music(
+                    music,
+                    MusicFormat::Mus,
+                    Some(MusicSource::Memory(data.into())),
+                )
+            })
         }
     }
 
+    /// Loads a music from an XM file.
+    ///
+    /// The engine does not play XM modules directly,
+    /// so the module is converted to a MOD beforehand.
+    /// This conversion is lossy:
+    /// only the first 31 instruments are kept,
+    /// each with a single sample,
+    /// patterns are limited to 32 channels,
+    /// and envelopes and effects without a MOD equivalent are dropped.
+    pub fn load_xm(path: impl AsRef<str>) -> Result<Music, FileError> {
+        let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+        Music::load_xm_from_memory(&data)
+    }
+
+    /// Creates a music object from the byte data of an XM file.
+    ///
+    /// See [`Music::load_xm`] for the limitations of XM playback.
+    pub fn load_xm_from_memory(data: &[u8]) -> Result<Music, FileError> {
+        let module = tracker::xm_to_mod(data)?;
+        Music::from_temp_file(module.into(), MusicFormat::Xm)
+    }
+
+    /// Loads a music from an S3M file.
+    ///
+    /// The engine does not play S3M modules directly,
+    /// so the module is converted to a MOD beforehand.
+    /// This conversion is lossy:
+    /// only the first 31 instruments are kept,
+    /// AdLib instruments are silent,
+    /// and effects without a MOD equivalent are dropped.
+    pub fn load_s3m(path: impl AsRef<str>) -> Result<Music, FileError> {
+        let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+        Music::load_s3m_from_memory(&data)
+    }
+
+    /// Creates a music object from the byte data of an S3M file.
+    ///
+    /// See [`Music::load_s3m`] for the limitations of S3M playback.
+    pub fn load_s3m_from_memory(data: &[u8]) -> Result<Music, FileError> {
+        let module = tracker::s3m_to_mod(data)?;
+        Music::from_temp_file(module.into(), MusicFormat::S3m)
+    }
+
+    /// Loads a MIDI file or a module generated in memory
+    /// through the engine's loaders,
+    /// which only read from files.
+    ///
+    /// The data is kept as the source of the music.
+    fn from_temp_file(data: Arc<[u8]>, format: MusicFormat) -> Result<Music, FileError> {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let (extension, load): (_, unsafe extern "C" fn(_) -> _) = match format {
+            MusicFormat::Mid => ("mid", dos_like_sys::loadmid),
+            _ => ("mod", dos_like_sys::loadmod),
+        };
+        let path = std::env::temp_dir().join(format!(
+            "dos-like-{}-{}.{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            extension
+        ));
+        std::fs::write(&path, &data).map_err(|_| FileError::FileNotFound)?;
+        let filename = path
+            .to_str()
+            .and_then(|p| CString::new(p).ok())
+            .ok_or(FileError::BadFilePath);
+        let music = filename.map(|filename| unsafe { NonNull::new(load(filename.as_ptr())) });
+        let _ = std::fs::remove_file(&path);
+
+        match music? {
+            Some(music) => Ok(Music(music, format, Some(MusicSource::Memory(data)))),
+            None => Err(FileError::InvalidData(
+                "the engine rejected the generated file".into(),
+            )),
+        }
+    }
+
+    /// Makes another handle to the same music in the engine.
+    fn handle(&self) -> Music {
+        Music(self.0, self.1, None)
+    }
+
+    /// Gets the format of the file which this music was created from.
+    #[inline]
+    pub fn format(&self) -> MusicFormat {
+        self.1
+    }
+
     /// Plays this music,
     /// stopping any other music currently playing.
     ///
@@ -101,6 +288,20 @@ impl Music {
     pub fn play(&self, loop_: bool, volume: u8) {
         play_music(self, loop_, volume)
     }
+
+    /// Plays this music,
+    /// stopping any other music currently playing,
+    /// and loops back to `loop_start` whenever it reaches `loop_end`.
+    ///
+    /// See [`play_music_with_loop_points`] for the details.
+    pub fn play_with_loop_points(
+        &self,
+        loop_start: MusicPosition,
+        loop_end: MusicPosition,
+        volume: u8,
+    ) -> Result<(), LoopPointError> {
+        play_music_with_loop_points(self, loop_start, loop_end, volume)
+    }
 }
 
 /// Plays this music,
@@ -109,16 +310,133 @@ impl Music {
 /// If `loop_` is true, the music will loop forever.
 /// `volume` is a number between 0 (silent) and 255 (full volume).
 pub fn play_music(music: &Music, loop_: bool, volume: u8) {
+    let mut track = TRACK.lock().unwrap();
+    track.next = None;
+    start_track(&mut track, music, loop_, volume);
+}
+
+/// Starts playing a track in the engine.
+fn start_track(track: &mut TrackState, music: &Music, loop_: bool, volume: u8) {
+    track.started = Some((Instant::now(), false));
+    track.finished = false;
+    track.volume = volume;
     unsafe {
         dos_like_sys::playmusic(music.0.as_ptr(), loop_ as c_int, volume as c_int);
     }
 }
 
 /// Stops any music that is currently playing.
+///
+/// This does not count as the track finishing
+/// (see [`music_finished`]),
+/// and also stops the loop of a track
+/// started with [`play_music_with_loop_points`].
 pub fn stop_music() {
+    {
+        let mut track = TRACK.lock().unwrap();
+        track.started = None;
+        track.finished = false;
+        track.next = None;
+    }
     unsafe { dos_like_sys::stopmusic() }
 }
 
+/// How long to wait for a track to report that it is playing
+/// before considering it finished.
+const START_GRACE: Duration = Duration::from_secs(1);
+
+/// The state of the last track started, for detecting its end.
+struct TrackState {
+    /// When the track was started,
+    /// and whether it has been seen playing since,
+    /// or `None` if no track is playing
+    started: Option<(Instant, bool)>,
+    /// Whether the track finished on this frame
+    finished: bool,
+    /// The function to call when the track finishes
+    on_finished: Option<fn()>,
+    /// The track to loop once the current one finishes,
+    /// see [`play_music_with_loop_points`]
+    next: Option<Music>,
+    /// The last music volume set
+    volume: u8,
+}
+
+static TRACK: Mutex<TrackState> = Mutex::new(TrackState {
+    started: None,
+    finished: false,
+    on_finished: None,
+    next: None,
+    volume: 255,
+});
+
+/// Checks whether the music track finished on this frame.
+///
+/// This is true from the [`wait_vbl`](crate::wait_vbl) call
+/// which detected that the track started with [`play_music`]
+/// stopped on its own,
+/// until the next call to `wait_vbl`
+/// or until another track is started.
+/// Stopping the music with [`stop_music`] does not count,
+/// and a looping track never finishes.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let music = Music::load_mid("fanfare.mid").unwrap();
+/// play_music(&music, false, 255);
+/// while !shutting_down() {
+///     wait_vbl();
+///     if music_finished() {
+///         // show the next screen
+///         break;
+///     }
+/// }
+/// ```
+pub fn music_finished() -> bool {
+    TRACK.lock().unwrap().finished
+}
+
+/// Sets a function to be called from [`wait_vbl`](crate::wait_vbl)
+/// when the music track finishes on its own
+/// (see [`music_finished`]),
+/// or `None` to remove it.
+pub fn set_on_music_finished(callback: Option<fn()>) {
+    TRACK.lock().unwrap().on_finished = callback;
+}
+
+/// Detects the end of the current track,
+/// to be called once per frame.
+pub(crate) fn update_music() {
+    let callback = {
+        let mut track = TRACK.lock().unwrap();
+        track.finished = false;
+        let Some((started, seen_playing)) = track.started else {
+            return;
+        };
+        if is_music_playing() {
+            track.started = Some((started, true));
+            return;
+        }
+        // the engine may take a moment to report that the track is playing
+        if !seen_playing && started.elapsed() <= START_GRACE {
+            return;
+        }
+        if let Some(next) = track.next.take() {
+            let volume = track.volume;
+            start_track(&mut track, &next, true, volume);
+            return;
+        }
+        track.started = None;
+        track.finished = true;
+        track.on_finished
+    };
+    if let Some(callback) = callback {
+        callback();
+    }
+}
+
 /// Checks whether the application is currently playing any music.
 pub fn is_music_playing() -> bool {
     unsafe { dos_like_sys::musicplaying() != 0 }
@@ -126,6 +444,21 @@ pub fn is_music_playing() -> bool {
 
 /// Sets the music volume.
 pub fn set_music_volume(volume: u8) {
+    TRACK.lock().unwrap().volume = volume;
+    unsafe { dos_like_sys::musicvolume(volume as i32) }
+}
+
+/// Sets the engine music volume to zero,
+/// or back to the volume last set,
+/// without changing the volume recorded.
+///
+/// Used to mute the audio while the window is not focused.
+pub(crate) fn silence_music(silent: bool) {
+    let volume = if silent {
+        0
+    } else {
+        TRACK.lock().unwrap().volume
+    };
     unsafe { dos_like_sys::musicvolume(volume as i32) }
 }
 
@@ -183,6 +516,38 @@ impl Soundbank {
     pub fn set_soundbank(&self) {
         set_soundbank(self);
     }
+
+    /// Gets the number of melodic instruments in this soundbank.
+    ///
+    /// SoundFonts installed with [`install_user_soundbank`]
+    /// report the presets found in bank 0 of the file.
+    /// The default soundbanks and .op2 soundbanks
+    /// provide all 128 General MIDI instruments.
+    pub fn instrument_count(&self) -> usize {
+        instruments::with_installed(self.0.get(), |names| {
+            names.iter().filter(|n| n.is_some()).count()
+        })
+        .unwrap_or(GM_INSTRUMENT_NAMES.len())
+    }
+
+    /// Gets the name of the instrument for the given program number,
+    /// or `None` if the soundbank does not have it.
+    ///
+    /// SoundFonts installed with [`install_user_soundbank`]
+    /// report the preset names in the file.
+    /// The default soundbanks and .op2 soundbanks
+    /// report the General MIDI instrument names
+    /// (see [`GM_INSTRUMENT_NAMES`]).
+    pub fn instrument_name(&self, program: u8) -> Option<String> {
+        instruments::with_installed(self.0.get(), |names| {
+            names.get(program as usize).cloned().flatten()
+        })
+        .unwrap_or_else(|| {
+            GM_INSTRUMENT_NAMES
+                .get(program as usize)
+                .map(|name| name.to_string())
+        })
+    }
 }
 
 /// Installs a soundbank from an .sf2 or .op2 file.
@@ -191,11 +556,31 @@ impl Soundbank {
 pub fn install_user_soundbank(filename: impl AsRef<str>) -> Result<Soundbank, FileError> {
     let filename = CString::new(filename.as_ref()).map_err(|_| FileError::BadFilePath)?;
 
-    unsafe {
+    let soundbank = unsafe {
         let soundbank_id = dos_like_sys::installusersoundbank(filename.as_ptr() as *const _);
 
-        Soundbank::from_id(soundbank_id as u32).ok_or(FileError::FileNotFound)
+        Soundbank::from_id(soundbank_id as u32).ok_or(FileError::FileNotFound)?
+    };
+
+    // keep the preset names of SoundFonts to list their instruments
+    let path = filename.to_string_lossy();
+    if path.to_ascii_lowercase().ends_with(".sf2") {
+        let names = std::fs::read(&*path)
+            .ok()
+            .and_then(|data| instruments::sf2_preset_names(&data))
+            .unwrap_or_else(|| vec![None; 128].into_boxed_slice());
+        instruments::register(soundbank.0.get(), names);
     }
+    SOUNDBANK_COUNT.fetch_add(1, Ordering::Relaxed);
+    Ok(soundbank)
+}
+
+/// The number of soundbanks installed via [`install_user_soundbank`].
+static SOUNDBANK_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// Gets the number of soundbanks installed via [`install_user_soundbank`].
+pub(crate) fn installed_soundbank_count() -> u32 {
+    SOUNDBANK_COUNT.load(Ordering::Relaxed)
 }
 
 /// Sets this soundbank for subsequent audio operations.
@@ -209,11 +594,87 @@ pub fn set_soundbank(soundbank: &Soundbank) {
 /// The total number of music channels supported by the engine.
 pub const MUSIC_CHANNELS: u32 = dos_like_sys::MUSIC_CHANNELS;
 
+/// Bit mask of the music channels which were muted.
+static MUTED_CHANNELS: AtomicU32 = AtomicU32::new(0);
+/// Bit mask of the music channels which were soloed.
+static SOLOED_CHANNELS: AtomicU32 = AtomicU32::new(0);
+
+/// Gets the bit mask of the music channels which can be heard.
+fn audible_channels() -> u32 {
+    let muted = MUTED_CHANNELS.load(Ordering::Relaxed);
+    let soloed = SOLOED_CHANNELS.load(Ordering::Relaxed);
+    let all = (1 << MUSIC_CHANNELS) - 1;
+    let enabled = if soloed != 0 { soloed } else { all };
+    enabled & !muted
+}
+
+/// Updates one of the channel bit masks,
+/// releasing all notes on the channels which were silenced as a result.
+fn update_channel_mask(mask: &AtomicU32, channel: u8, enabled: bool) {
+    if channel as u32 >= MUSIC_CHANNELS {
+        return;
+    }
+    let audible_before = audible_channels();
+    if enabled {
+        mask.fetch_or(1 << channel, Ordering::Relaxed);
+    } else {
+        mask.fetch_and(!(1 << channel), Ordering::Relaxed);
+    }
+    let silenced = audible_before & !audible_channels();
+    for channel in 0..MUSIC_CHANNELS as u8 {
+        if silenced & (1 << channel) != 0 {
+            all_notes_off(channel);
+        }
+    }
+}
+
+/// Mutes or unmutes the given music channel.
+///
+/// Notes pushed via [`note_on`] to a channel which is muted are ignored,
+/// and all notes on a channel are released when it becomes muted.
+///
+/// Note that music played from files (see [`Music`])
+/// is not affected.
+pub fn set_channel_mute(channel: u8, muted: bool) {
+    update_channel_mask(&MUTED_CHANNELS, channel, muted);
+}
+
+/// Checks whether the given music channel is muted.
+pub fn is_channel_muted(channel: u8) -> bool {
+    channel < 32 && MUTED_CHANNELS.load(Ordering::Relaxed) & (1 << channel) != 0
+}
+
+/// Enables or disables solo on the given music channel.
+///
+/// While any channel is soloed,
+/// all channels which are not soloed are silenced,
+/// in the same way as with [`set_channel_mute`].
+pub fn set_channel_solo(channel: u8, solo: bool) {
+    update_channel_mask(&SOLOED_CHANNELS, channel, solo);
+}
+
+/// Checks whether the given music channel is soloed.
+pub fn is_channel_solo(channel: u8) -> bool {
+    channel < 32 && SOLOED_CHANNELS.load(Ordering::Relaxed) & (1 << channel) != 0
+}
+
+/// Checks whether notes on the given music channel can be heard,
+/// taking both mute and solo into account.
+pub fn is_channel_audible(channel: u8) -> bool {
+    channel < 32 && audible_channels() & (1 << channel) != 0
+}
+
 /// Pushes a note on the given music channel.
 ///
 /// `note` is a number between 0 and 127 representing the note's pitch.
 /// `velocity` is a number between 0 and 127.
+///
+/// The note is ignored if the channel is silenced
+/// (see [`set_channel_mute`] and [`set_channel_solo`]).
 pub fn note_on(channel: u8, note: u8, velocity: u8) {
+    if !is_channel_audible(channel) {
+        return;
+    }
     unsafe { dos_like_sys::noteon(channel as c_int, note as c_int, velocity as c_int) }
 }
 
@@ -0,0 +1,317 @@
+//! Instrument names of soundbanks.
+
+use std::sync::Mutex;
+
+/// The names of the 128 General MIDI instruments,
+/// indexed by program number.
+pub const GM_INSTRUMENT_NAMES: [&str; 128] = [
+    "Acoustic Grand Piano",
+    "Bright Acoustic Piano",
+    "Electric Grand Piano",
+    "Honky-tonk Piano",
+    "Electric Piano 1",
+    "Electric Piano 2",
+    "Harpsichord",
+    "Clavinet",
+    "Celesta",
+    "Glockenspiel",
+    "Music Box",
+    "Vibraphone",
+    "Marimba",
+    "Xylophone",
+    "Tubular Bells",
+    "Dulcimer",
+    "Drawbar Organ",
+    "Percussive Organ",
+    "Rock Organ",
+    "Church Organ",
+    "Reed Organ",
+    "Accordion",
+    "Harmonica",
+    "Tango Accordion",
+    "Acoustic Guitar (nylon)",
+    "Acoustic Guitar (steel)",
+    "Electric Guitar (jazz)",
+    "Electric Guitar (clean)",
+    "Electric Guitar (muted)",
+    "Overdriven Guitar",
+    "Distortion Guitar",
+    "Guitar Harmonics",
+    "Acoustic Bass",
+    "Electric Bass (finger)",
+    "Electric Bass (pick)",
+    "Fretless Bass",
+    "Slap Bass 1",
+    "Slap Bass 2",
+    "Synth Bass 1",
+    "Synth Bass 2",
+    "Violin",
+    "Viola",
+    "Cello",
+    "Contrabass",
+    "Tremolo Strings",
+    "Pizzicato Strings",
+    "Orchestral Harp",
+    "Timpani",
+    "String Ensemble 1",
+    "String Ensemble 2",
+    "Synth Strings 1",
+    "Synth Strings 2",
+    "Choir Aahs",
+    "Voice Oohs",
+    "Synth Voice",
+    "Orchestra Hit",
+    "Trumpet",
+    "Trombone",
+    "Tuba",
+    "Muted Trumpet",
+    "French Horn",
+    "Brass Section",
+    "Synth Brass 1",
+    "Synth Brass 2",
+    "Soprano Sax",
+    "Alto Sax",
+    "Tenor Sax",
+    "Baritone Sax",
+    "Oboe",
+    "English Horn",
+    "Bassoon",
+    "Clarinet",
+    "Piccolo",
+    "Flute",
+    "Recorder",
+    "Pan Flute",
+    "Blown Bottle",
+    "Shakuhachi",
+    "Whistle",
+    "Ocarina",
+    "Lead 1 (square)",
+    "Lead 2 (sawtooth)",
+    "Lead 3 (calliope)",
+    "Lead 4 (chiff)",
+    "Lead 5 (charang)",
+    "Lead 6 (voice)",
+    "Lead 7 (fifths)",
+    "Lead 8 (bass + lead)",
+    "Pad 1 (new age)",
+    "Pad 2 (warm)",
+    "Pad 3 (polysynth)",
+    "Pad 4 (choir)",
+    "Pad 5 (bowed)",
+    "Pad 6 (metallic)",
+    "Pad 7 (halo)",
+    "Pad 8 (sweep)",
+    "FX 1 (rain)",
+    "FX 2 (soundtrack)",
+    "FX 3 (crystal)",
+    "FX 4 (atmosphere)",
+    "FX 5 (brightness)",
+    "FX 6 (goblins)",
+    "FX 7 (echoes)",
+    "FX 8 (sci-fi)",
+    "Sitar",
+    "Banjo",
+    "Shamisen",
+    "Koto",
+    "Kalimba",
+    "Bagpipe",
+    "Fiddle",
+    "Shanai",
+    "Tinkle Bell",
+    "Agogo",
+    "Steel Drums",
+    "Woodblock",
+    "Taiko Drum",
+    "Melodic Tom",
+    "Synth Drum",
+    "Reverse Cymbal",
+    "Guitar Fret Noise",
+    "Breath Noise",
+    "Seashore",
+    "Bird Tweet",
+    "Telephone Ring",
+    "Helicopter",
+    "Applause",
+    "Gunshot",
+];
+
+/// Instrument names indexed by program number,
+/// with `None` for missing programs.
+type InstrumentNames = Box<[Option<String>]>;
+
+/// The instrument names of each installed SoundFont,
+/// by soundbank identifier.
+static INSTALLED: Mutex<Vec<(u32, InstrumentNames)>> = Mutex::new(Vec::new());
+
+/// Records the presets of an installed SoundFont.
+pub(crate) fn register(id: u32, names: InstrumentNames) {
+    INSTALLED.lock().unwrap().push((id, names));
+}
+
+/// Calls `f` with the instrument names of the given soundbank,
+/// or returns `None` if it has General MIDI instruments.
+pub(crate) fn with_installed<T>(id: u32, f: impl FnOnce(&[Option<String>]) -> T) -> Option<T> {
+    let installed = INSTALLED.lock().unwrap();
+    installed
+        .iter()
+        .find(|(i, _)| *i == id)
+        .map(|(_, names)| f(names))
+}
+
+/// Reads the names of the melodic presets (bank 0) of a SoundFont file,
+/// indexed by program number.
+///
+/// Returns `None` if the preset headers could not be found.
+pub(crate) fn sf2_preset_names(data: &[u8]) -> Option<InstrumentNames> {
+    if data.get(0..4)? != b"RIFF" || data.get(8..12)? != b"sfbk" {
+        return None;
+    }
+    let pdta = find_chunk(&data[12..], b"LIST", Some(b"pdta"))?;
+    let phdr = find_chunk(pdta, b"phdr", None)?;
+
+    let mut names = vec![None; 128];
+    // the last record only marks the end of the list
+    let records = phdr.chunks_exact(38);
+    let count = records.len().saturating_sub(1);
+    for record in records.take(count) {
+        let preset = u16::from_le_bytes([record[20], record[21]]);
+        let bank = u16::from_le_bytes([record[22], record[23]]);
+        if bank != 0 || preset > 127 {
+            continue;
+        }
+        let name = &record[..20];
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        names[preset as usize] = Some(String::from_utf8_lossy(&name[..len]).trim().to_string());
+    }
+    Some(names.into_boxed_slice())
+}
+
+/// Finds a RIFF chunk by its identifier in a sequence of chunks,
+/// returning its contents.
+/// For lists, `list_type` must also match,
+/// and the contents after the list type are returned.
+fn find_chunk<'a>(
+    mut data: &'a [u8],
+    id: &[u8; 4],
+    list_type: Option<&[u8; 4]>,
+) -> Option<&'a [u8]> {
+    while data.len() >= 8 {
+        let size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let contents = data.get(8..8 + size).unwrap_or(&data[8..]);
+        if &data[..4] == id {
+            match list_type {
+                None => return Some(contents),
+                Some(t) if contents.get(..4) == Some(&t[..]) => return Some(&contents[4..]),
+                Some(_) => {}
+            }
+        }
+        // chunks are padded to an even size
+        data = data.get(8 + size + size % 2..)?;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a RIFF chunk, padded to an even size.
+    fn chunk(id: &[u8; 4], contents: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((contents.len() as u32).to_le_bytes());
+        out.extend(contents);
+        if contents.len() % 2 == 1 {
+            out.push(0);
+        }
+        out
+    }
+
+    /// Writes a RIFF list of the given type.
+    fn list(list_type: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut contents = list_type.to_vec();
+        for c in chunks {
+            contents.extend(c);
+        }
+        chunk(b"LIST", &contents)
+    }
+
+    /// Writes a preset header record.
+    fn preset(name: &str, program: u16, bank: u16) -> Vec<u8> {
+        let mut record = [0; 38];
+        record[..name.len()].copy_from_slice(name.as_bytes());
+        record[20..22].copy_from_slice(&program.to_le_bytes());
+        record[22..24].copy_from_slice(&bank.to_le_bytes());
+        record.to_vec()
+    }
+
+    /// Writes a SoundFont file with the given preset headers,
+    /// followed by the terminal record.
+    fn sf2(presets: &[Vec<u8>]) -> Vec<u8> {
+        let mut phdr: Vec<u8> = presets.concat();
+        phdr.extend(preset("EOP", 0, 0));
+        let body = [
+            b"sfbk".to_vec(),
+            // an odd-sized chunk before the presets
+            list(b"INFO", &[chunk(b"INAM", b"Test bank")]),
+            list(b"sdta", &[chunk(b"smpl", &[0; 4])]),
+            list(b"pdta", &[chunk(b"phdr", &phdr)]),
+        ]
+        .concat();
+        chunk(b"RIFF", &body)
+    }
+
+    #[test]
+    fn reads_melodic_preset_names() {
+        let data = sf2(&[
+            preset("Grand Piano", 0, 0),
+            preset("  Strings  ", 48, 0),
+            preset("Drum Kit", 0, 128),
+            preset("Variation", 1, 8),
+            preset("Out of range", 200, 0),
+            preset("Twenty characters!!!", 127, 0),
+        ]);
+        let names = sf2_preset_names(&data).unwrap();
+        assert_eq!(names.len(), 128);
+        assert_eq!(names[0].as_deref(), Some("Grand Piano"));
+        assert_eq!(names[48].as_deref(), Some("Strings"));
+        assert_eq!(names[127].as_deref(), Some("Twenty characters!!!"));
+        assert_eq!(names[1], None);
+        assert_eq!(names.iter().flatten().count(), 3);
+    }
+
+    #[test]
+    fn terminal_record_is_not_a_preset() {
+        let names = sf2_preset_names(&sf2(&[])).unwrap();
+        assert!(names.iter().all(|name| name.is_none()));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(sf2_preset_names(&[]).is_none());
+        assert!(sf2_preset_names(b"RIFF\0\0\0\0WAVEfmt ").is_none());
+
+        // no preset headers
+        let data = chunk(b"RIFF", &[b"sfbk".to_vec(), list(b"INFO", &[])].concat());
+        assert!(sf2_preset_names(&data).is_none());
+    }
+
+    #[test]
+    fn installed_names_by_soundbank() {
+        let mut names = vec![None; 128];
+        names[5] = Some("Bells".to_string());
+        register(0xF00D, names.into_boxed_slice());
+
+        assert_eq!(
+            with_installed(0xF00D, |names| names[5].clone()),
+            Some(Some("Bells".to_string()))
+        );
+        assert_eq!(with_installed(0xBEEF, |names| names.len()), None);
+    }
+
+    #[test]
+    fn general_midi_names() {
+        assert_eq!(GM_INSTRUMENT_NAMES[0], "Acoustic Grand Piano");
+        assert_eq!(GM_INSTRUMENT_NAMES[40], "Violin");
+        assert_eq!(GM_INSTRUMENT_NAMES[127], "Gunshot");
+    }
+}
@@ -0,0 +1,1271 @@
+//! Playback of music with loop points inside the track,
+//! such as an intro followed by a looping body.
+//!
+//! The engine can neither seek nor report the position of a track,
+//! so the music is rebuilt from its source data instead:
+//! modules get a position jump at the end of the loop,
+//! which the engine plays seamlessly,
+//! while MIDI and MUS tracks are cut into an intro and a looping body,
+//! played one after the other.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{start_track, Music, MusicFormat, TRACK};
+use crate::FileError;
+
+/// A position in a music track,
+/// in the unit of its format.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum MusicPosition {
+    /// A number of ticks from the start of the track:
+    /// in MIDI files, ticks as defined by the division in the file header
+    /// (often a fraction of a quarter note);
+    /// in MUS files, ticks of 1/140th of a second
+    Ticks(u32),
+    /// A row (0 to 63) of the pattern
+    /// at the given position in the order table of a module
+    Row {
+        /// The position in the order table
+        order: u8,
+        /// The row of the pattern
+        row: u8,
+    },
+}
+
+/// An error playing music with loop points.
+#[derive(Debug)]
+pub enum LoopPointError {
+    /// The format of the music does not allow jumping to a position
+    Unsupported(MusicFormat),
+    /// The positions are not in the unit of the format of the music
+    WrongUnit,
+    /// The loop does not end after it starts,
+    /// or a position is past the end of the music
+    InvalidRange,
+    /// The data of the music could not be read again or rebuilt
+    File(FileError),
+}
+
+impl std::fmt::Display for LoopPointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoopPointError::Unsupported(format) => {
+                write!(f, "Loop points are not supported for {:?} music", format)
+            }
+            LoopPointError::WrongUnit => f.write_str("Wrong unit of loop points for the music"),
+            LoopPointError::InvalidRange => f.write_str("Invalid range of loop points"),
+            LoopPointError::File(e) => write!(f, "Could not rebuild the music: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LoopPointError {}
+
+impl From<FileError> for LoopPointError {
+    fn from(e: FileError) -> Self {
+        LoopPointError::File(e)
+    }
+}
+
+/// A music rebuilt with loop points.
+#[derive(Debug)]
+struct Prepared {
+    /// The music and loop points it was made from
+    key: (usize, MusicPosition, MusicPosition),
+    /// The part to play once before the loop, if any
+    intro: Option<Music>,
+    /// The part to play in a loop
+    body: Music,
+}
+
+/// The music rebuilt so far,
+/// since the engine never frees music.
+static PREPARED: Mutex<Vec<Prepared>> = Mutex::new(Vec::new());
+
+/// Plays a music,
+/// stopping any other music currently playing,
+/// and loops back to `loop_start` whenever it reaches `loop_end`,
+/// forever.
+///
+/// The positions are [rows](MusicPosition::Row) for modules
+/// (MOD, and XM and S3M as converted to MOD),
+/// or [ticks](MusicPosition::Ticks) for MIDI and MUS files.
+/// `loop_end` is the first position not played in the loop.
+/// For modules, it may also be row 0
+/// of the position right after the last one in the order table,
+/// to loop until the end of the song.
+/// `volume` is a number between 0 (silent) and 255 (full volume).
+///
+/// Since the engine cannot jump to a position in a track,
+/// the music is rebuilt from its source data
+/// (read again from its file, if it was loaded from one)
+/// on the first call with each music and loop points,
+/// and kept for later calls.
+///
+/// - Modules get a position jump at the end of the loop,
+///   taking the place of the effects in two cells of that row
+///   if no cells are free,
+///   and loop seamlessly.
+/// - MIDI and MUS tracks are cut into an intro and a body,
+///   and the body is started in a loop
+///   from [`wait_vbl`](crate::wait_vbl) once the intro finishes,
+///   leaving a gap of up to one frame.
+///   The body starts with the tempo, instruments and controllers
+///   in effect at `loop_start`,
+///   but notes held across `loop_start` are not heard in the loop.
+///
+/// OPB files cannot be cut at a position,
+/// and result in [`LoopPointError::Unsupported`].
+///
+/// The music never [finishes](super::music_finished),
+/// the volume set with [`set_music_volume`](super::set_music_volume)
+/// carries over from the intro to the body,
+/// and [`stop_music`](super::stop_music) or starting another track
+/// stops the loop.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let music = Music::load_mod("boss.mod")?;
+/// // a 4 pattern intro, then loop the rest of the song
+/// play_music_with_loop_points(
+///     &music,
+///     MusicPosition::Row { order: 4, row: 0 },
+///     MusicPosition::Row { order: 12, row: 0 },
+///     255,
+/// )
+/// .expect("boss.mod should be a module with at least 12 positions");
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn play_music_with_loop_points(
+    music: &Music,
+    loop_start: MusicPosition,
+    loop_end: MusicPosition,
+    volume: u8,
+) -> Result<(), LoopPointError> {
+    let key = (music.0.as_ptr() as usize, loop_start, loop_end);
+    let mut prepared = PREPARED.lock().unwrap_or_else(|e| e.into_inner());
+    let index = match prepared.iter().position(|p| p.key == key) {
+        Some(index) => index,
+        None => {
+            let (intro, body) = prepare(music, loop_start, loop_end)?;
+            prepared.push(Prepared { key, intro, body });
+            prepared.len() - 1
+        }
+    };
+    let Prepared { intro, body, .. } = &prepared[index];
+
+    let mut track = TRACK.lock().unwrap();
+    match intro {
+        Some(intro) => {
+            track.next = Some(body.handle());
+            start_track(&mut track, intro, false, volume);
+        }
+        None => {
+            track.next = None;
+            start_track(&mut track, body, true, volume);
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds a music with loop points,
+/// as an optional intro and a body to loop.
+fn prepare(
+    music: &Music,
+    loop_start: MusicPosition,
+    loop_end: MusicPosition,
+) -> Result<(Option<Music>, Music), LoopPointError> {
+    let format = music.format();
+    let data = source_data(music)?;
+    match (format, loop_start, loop_end) {
+        (
+            MusicFormat::Mod | MusicFormat::Xm | MusicFormat::S3m,
+            MusicPosition::Row { order, row },
+            MusicPosition::Row {
+                order: end_order,
+                row: end_row,
+            },
+        ) => {
+            let module = loop_module(&data, (order, row), (end_order, end_row))?;
+            Ok((None, Music::from_temp_file(module.into(), format)?))
+        }
+        (MusicFormat::Mid, MusicPosition::Ticks(start), MusicPosition::Ticks(end)) => {
+            if end <= start {
+                return Err(LoopPointError::InvalidRange);
+            }
+            let midi = parse_midi(&data)?;
+            let part = |start, end| -> Result<Music, FileError> {
+                let data: Arc<[u8]> = write_midi(&midi, start, end).into();
+                Music::from_temp_file(data, MusicFormat::Mid)
+            };
+            let intro = if start > 0 { Some(part(0, end)?) } else { None };
+            Ok((intro, part(start, end)?))
+        }
+        (MusicFormat::Mus, MusicPosition::Ticks(start), MusicPosition::Ticks(end)) => {
+            if end <= start {
+                return Err(LoopPointError::InvalidRange);
+            }
+            let mus = parse_mus(&data)?;
+            let part = |start, end| -> Result<Music, FileError> {
+                Music::try_create_mus(&write_mus(&mus, start, end))
+                    .ok_or_else(|| invalid("the engine rejected the generated file"))
+            };
+            let intro = if start > 0 { Some(part(0, end)?) } else { None };
+            Ok((intro, part(start, end)?))
+        }
+        _ => Err(LoopPointError::WrongUnit),
+    }
+}
+
+/// Reads the source data of a music which can be rebuilt.
+fn source_data(music: &Music) -> Result<Arc<[u8]>, LoopPointError> {
+    match (music.format(), &music.2) {
+        (MusicFormat::Opb, _) | (_, None) => Err(LoopPointError::Unsupported(music.format())),
+        (_, Some(source)) => Ok(source.read()?),
+    }
+}
+
+/// The rate of the ticks of MUS files, per second.
+const MUS_TICK_RATE: f64 = 140.;
+
+/// Gets how long a music plays before it ends,
+/// or `None` if this cannot be determined
+/// or if the music never ends.
+pub(crate) fn music_length(music: &Music) -> Option<Duration> {
+    let data = source_data(music).ok()?;
+    let seconds = match music.format() {
+        MusicFormat::Mod | MusicFormat::Xm | MusicFormat::S3m => {
+            module_timeline(&Module::parse(&data).ok()?).1?
+        }
+        MusicFormat::Mid => {
+            let midi = parse_midi(&data).ok()?;
+            midi_seconds(&midi_tempo_map(&midi), midi_end(&midi))
+        }
+        MusicFormat::Mus => parse_mus(&data).ok()?.length as f64 / MUS_TICK_RATE,
+        MusicFormat::Opb => return None,
+    };
+    Some(Duration::from_secs_f64(seconds))
+}
+
+/// Rebuilds a music to start from the point reached
+/// after playing it for `position`,
+/// or returns `None` if the music ends before that.
+///
+/// Modules start from the beginning of the row played at that point.
+pub(crate) fn music_from(
+    music: &Music,
+    position: Duration,
+) -> Result<Option<Music>, LoopPointError> {
+    let format = music.format();
+    let data = source_data(music)?;
+    let seconds = position.as_secs_f64();
+    let rejected = || invalid("the engine rejected the generated file");
+    Ok(match format {
+        MusicFormat::Mod | MusicFormat::Xm | MusicFormat::S3m => module_from(&data, seconds)?
+            .map(|module| Music::from_temp_file(module.into(), format))
+            .transpose()?,
+        MusicFormat::Mid => {
+            let midi = parse_midi(&data)?;
+            let start = midi_tick_at(&midi_tempo_map(&midi), seconds);
+            let end = midi_end(&midi) + 1;
+            (start < end)
+                .then(|| Music::from_temp_file(write_midi(&midi, start, end).into(), format))
+                .transpose()?
+        }
+        MusicFormat::Mus => {
+            let mus = parse_mus(&data)?;
+            let start = (seconds * MUS_TICK_RATE) as u32;
+            (start < mus.length)
+                .then(|| {
+                    Music::try_create_mus(&write_mus(&mus, start, mus.length)).ok_or_else(rejected)
+                })
+                .transpose()?
+        }
+        MusicFormat::Opb => return Err(LoopPointError::Unsupported(format)),
+    })
+}
+
+fn invalid(message: &str) -> FileError {
+    FileError::InvalidData(message.to_string())
+}
+
+// -- modules
+
+/// The number of rows in a MOD pattern.
+const ROWS: usize = 64;
+/// The maximum number of patterns and order table entries.
+const MAX_PATTERNS: usize = 128;
+/// The offset of the song length, followed by the restart position
+/// and the order table.
+const SONG_LENGTH: usize = 950;
+const ORDERS: usize = 952;
+const SIGNATURE: usize = 1080;
+const PATTERNS: usize = 1084;
+/// The position jump effect.
+const JUMP: u8 = 0x0B;
+/// The pattern break effect.
+const BREAK: u8 = 0x0D;
+
+/// Gets the number of channels of a MOD file from its signature.
+fn mod_channels(signature: &[u8]) -> Option<usize> {
+    let digit = |b: u8| b.is_ascii_digit().then(|| (b - b'0') as usize);
+    match signature {
+        b"M.K." | b"M!K!" | b"M&K!" | b"FLT4" | b"N.T." => Some(4),
+        b"FLT8" | b"OCTA" | b"CD81" => Some(8),
+        [c, b'C', b'H', b'N'] => digit(*c),
+        [a, b, b'C', b'H'] | [a, b, b'C', b'N'] => Some(digit(*a)? * 10 + digit(*b)?),
+        _ => None,
+    }
+}
+
+/// Sets an effect on a row of a pattern,
+/// in the first cell without an effect,
+/// or in the first cell not already given an effect here.
+fn set_row_effect(row: &mut [u8], used: &mut Vec<usize>, effect: u8, param: u8) {
+    let cells = row.len() / 4;
+    let free = (0..cells)
+        .find(|&c| !used.contains(&c) && row[c * 4 + 2] & 0x0F == 0 && row[c * 4 + 3] == 0);
+    let Some(cell) = free.or_else(|| (0..cells).find(|c| !used.contains(c))) else {
+        return;
+    };
+    used.push(cell);
+    row[cell * 4 + 2] = (row[cell * 4 + 2] & 0xF0) | effect;
+    row[cell * 4 + 3] = param;
+}
+
+/// Adds a pattern to those of a module,
+/// returning its index.
+fn add_pattern(patterns: &mut Vec<Vec<u8>>, pattern: Vec<u8>) -> Result<u8, LoopPointError> {
+    if patterns.len() >= MAX_PATTERNS {
+        return Err(invalid("no room for more patterns").into());
+    }
+    patterns.push(pattern);
+    Ok((patterns.len() - 1) as u8)
+}
+
+/// The parts of a MOD file.
+struct Module<'a> {
+    data: &'a [u8],
+    channels: usize,
+    song_length: usize,
+    orders: Vec<u8>,
+    patterns: Vec<Vec<u8>>,
+    /// The offset of the sample data, right after the patterns
+    samples_at: usize,
+}
+
+impl<'a> Module<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, LoopPointError> {
+        let channels = data
+            .get(SIGNATURE..PATTERNS)
+            .and_then(mod_channels)
+            .filter(|&c| c > 0)
+            .ok_or_else(|| invalid("not a MOD file with 31 samples"))?;
+        let song_length = (data[SONG_LENGTH] as usize).min(MAX_PATTERNS);
+        let orders = data[ORDERS..ORDERS + MAX_PATTERNS].to_vec();
+        let pattern_count = orders.iter().map(|&p| p as usize + 1).max().unwrap_or(1);
+        let pattern_len = ROWS * channels * 4;
+        let samples_at = PATTERNS + pattern_count * pattern_len;
+        if data.len() < samples_at {
+            return Err(invalid("truncated module").into());
+        }
+        let patterns = data[PATTERNS..samples_at]
+            .chunks_exact(pattern_len)
+            .map(<[u8]>::to_vec)
+            .collect();
+        Ok(Module {
+            data,
+            channels,
+            song_length,
+            orders,
+            patterns,
+            samples_at,
+        })
+    }
+
+    /// Gets the number of bytes in a row of a pattern.
+    #[inline]
+    fn row_len(&self) -> usize {
+        self.channels * 4
+    }
+
+    /// Gets the cells of a row at a position in the order table.
+    fn row(&self, order: usize, row: usize) -> &[u8] {
+        let pattern = &self.patterns[self.orders[order] as usize];
+        &pattern[row * self.row_len()..][..self.row_len()]
+    }
+
+    /// Writes the module with its current song and patterns.
+    fn write(&self) -> Vec<u8> {
+        let mut out = self.data[..PATTERNS].to_vec();
+        out[SONG_LENGTH] = self.song_length as u8;
+        out[ORDERS..ORDERS + MAX_PATTERNS].copy_from_slice(&self.orders);
+        // the patterns up to the highest one in the order table,
+        // which includes the new ones
+        let used = self
+            .orders
+            .iter()
+            .map(|&p| p as usize + 1)
+            .max()
+            .unwrap_or(1);
+        for pattern in &self.patterns[..used] {
+            out.extend_from_slice(pattern);
+        }
+        out.extend_from_slice(&self.data[self.samples_at..]);
+        out
+    }
+}
+
+/// Rebuilds a MOD file so that it jumps back to `start`
+/// instead of playing the row at `end`,
+/// each given as a position in the order table and a row.
+///
+/// Only position jumps and pattern breaks to row 0 are used,
+/// since players disagree on combining them.
+/// A loop starting in the middle of a pattern
+/// gets a copy of the rest of that pattern
+/// and of the positions up to the end of the loop,
+/// added to the end of the order table.
+fn loop_module(data: &[u8], start: (u8, u8), end: (u8, u8)) -> Result<Vec<u8>, LoopPointError> {
+    let mut module = Module::parse(data)?;
+    let song_length = module.song_length;
+
+    // the row on which to jump, the last one played in the loop
+    let (start_order, start_row) = (start.0 as usize, start.1 as usize);
+    let (end_order, end_row) = (end.0 as usize, end.1 as usize);
+    let (jump_order, jump_row) = match end_row {
+        0 => (end_order.wrapping_sub(1), ROWS - 1),
+        row => (end_order, row - 1),
+    };
+    if start_order >= song_length
+        || start_row >= ROWS
+        || end_row >= ROWS
+        || jump_order >= song_length
+        || (jump_order, jump_row) < (start_order, start_row)
+    {
+        return Err(LoopPointError::InvalidRange);
+    }
+
+    let row_len = module.row_len();
+    // sets a position jump on a row of a copy of a pattern,
+    // in place of any other jump or break
+    let with_jump = |pattern: &[u8], row: usize, target: u8| {
+        let mut pattern = pattern.to_vec();
+        let cells = &mut pattern[row * row_len..][..row_len];
+        for cell in cells.chunks_exact_mut(4) {
+            if matches!(cell[2] & 0x0F, JUMP | BREAK) {
+                cell[2] &= 0xF0;
+                cell[3] = 0;
+            }
+        }
+        set_row_effect(cells, &mut Vec::new(), JUMP, target);
+        pattern
+    };
+
+    let orders = &mut module.orders;
+    let patterns = &mut module.patterns;
+    if start_row == 0 {
+        let pattern = with_jump(
+            &patterns[orders[jump_order] as usize],
+            jump_row,
+            start_order as u8,
+        );
+        orders[jump_order] = add_pattern(patterns, pattern)?;
+    } else {
+        // the loop goes on from a new position at the end of the order table,
+        // starting with the rest of the first pattern
+        let target = song_length;
+        let copies = jump_order - start_order + 1;
+        if target + copies > MAX_PATTERNS {
+            return Err(invalid("no room for more positions").into());
+        }
+        let rest = rest_of_pattern(&patterns[orders[start_order] as usize], row_len, start_row);
+        let rest = if copies == 1 {
+            with_jump(&rest, jump_row - start_row, target as u8)
+        } else {
+            // break to the next position after the rows copied
+            break_after(rest, row_len, ROWS - start_row)
+        };
+        let rest = add_pattern(patterns, rest)?;
+
+        let jump = with_jump(
+            &patterns[orders[jump_order] as usize],
+            jump_row,
+            target as u8,
+        );
+        let jump = add_pattern(patterns, jump)?;
+        orders[jump_order] = jump;
+        orders[target] = rest;
+        for i in 1..copies {
+            orders[target + i] = orders[start_order + i];
+        }
+        module.song_length = target + copies;
+    }
+    Ok(module.write())
+}
+
+/// Copies the rows of a pattern from `row` onwards
+/// to the start of a new pattern.
+fn rest_of_pattern(pattern: &[u8], row_len: usize, row: usize) -> Vec<u8> {
+    let mut rest = vec![0; pattern.len()];
+    rest[..pattern.len() - row * row_len].copy_from_slice(&pattern[row * row_len..]);
+    rest
+}
+
+/// Sets a pattern break on the last of the first `rows` rows of a pattern,
+/// unless that row already jumps or breaks.
+fn break_after(mut pattern: Vec<u8>, row_len: usize, rows: usize) -> Vec<u8> {
+    let cells = &mut pattern[(rows - 1) * row_len..][..row_len];
+    if rows < ROWS
+        && !cells
+            .chunks_exact(4)
+            .any(|cell| matches!(cell[2] & 0x0F, JUMP | BREAK))
+    {
+        set_row_effect(cells, &mut Vec::new(), BREAK, 0);
+    }
+    pattern
+}
+
+/// A row of a module as it is played.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct PlayedRow {
+    order: u8,
+    row: u8,
+    /// The speed and tempo in effect
+    speed: u8,
+    tempo: u8,
+    /// When the row starts, in seconds from the start of the song
+    time: f64,
+}
+
+/// Follows the song of a module row by row,
+/// with its changes of speed and tempo,
+/// position jumps, pattern breaks and pattern delays.
+///
+/// Returns the rows played,
+/// and the length of the song in seconds,
+/// or `None` if the song jumps back and so never ends.
+/// Pattern loops are not followed.
+fn module_timeline(module: &Module) -> (Vec<PlayedRow>, Option<f64>) {
+    let mut played = Vec::new();
+    let mut visited = vec![false; MAX_PATTERNS * ROWS];
+    let (mut order, mut row) = (0, 0);
+    let (mut speed, mut tempo) = (6u8, 125u8);
+    let mut time = 0.;
+    while order < module.song_length {
+        if std::mem::replace(&mut visited[order * ROWS + row], true) {
+            return (played, None);
+        }
+        let (mut jump, mut pattern_break, mut delay) = (None, None, 0);
+        for cell in module.row(order, row).chunks_exact(4) {
+            let param = cell[3];
+            match cell[2] & 0x0F {
+                0x0F if param == 0 => {}
+                0x0F if param < 32 => speed = param,
+                0x0F => tempo = param,
+                JUMP => jump = Some(param as usize),
+                BREAK => pattern_break = Some((param >> 4) as usize * 10 + (param & 0x0F) as usize),
+                0x0E if param >> 4 == 0x0E => delay = (param & 0x0F) as u32,
+                _ => {}
+            }
+        }
+        played.push(PlayedRow {
+            order: order as u8,
+            row: row as u8,
+            speed,
+            tempo,
+            time,
+        });
+        // a tick lasts 2.5 / tempo seconds
+        time += speed as f64 * (1 + delay) as f64 * 2.5 / tempo as f64;
+
+        if jump.is_some() || pattern_break.is_some() {
+            order = jump.unwrap_or(order + 1);
+            row = pattern_break.filter(|&r| r < ROWS).unwrap_or(0);
+        } else if row + 1 < ROWS {
+            row += 1;
+        } else {
+            order += 1;
+            row = 0;
+        }
+    }
+    (played, Some(time))
+}
+
+/// Rebuilds a MOD file to start at the row played at `time` seconds,
+/// with the speed and tempo in effect there,
+/// or returns `None` if the song ends before that.
+///
+/// The positions before the row are dropped,
+/// so position jumps back to them go to the start of the new song instead.
+fn module_from(data: &[u8], time: f64) -> Result<Option<Vec<u8>>, LoopPointError> {
+    let mut module = Module::parse(data)?;
+    let (played, length) = module_timeline(&module);
+    if length.is_some_and(|length| time >= length) {
+        return Ok(None);
+    }
+    let Some(start) = played.iter().rev().find(|r| r.time <= time) else {
+        return Ok(None);
+    };
+    let (order, row) = (start.order as usize, start.row as usize);
+
+    let row_len = module.row_len();
+    let pattern = &module.patterns[module.orders[order] as usize];
+    let mut first = break_after(rest_of_pattern(pattern, row_len, row), row_len, ROWS - row);
+    let mut used = Vec::new();
+    set_row_effect(&mut first[..row_len], &mut used, 0x0F, start.speed);
+    set_row_effect(&mut first[..row_len], &mut used, 0x0F, start.tempo);
+    let first = add_pattern(&mut module.patterns, first)?;
+
+    // the song now starts from the position of the row
+    for pattern in &mut module.patterns {
+        for cell in pattern.chunks_exact_mut(4) {
+            if cell[2] & 0x0F == JUMP {
+                cell[3] = (cell[3] as usize).saturating_sub(order) as u8;
+            }
+        }
+    }
+    let mut orders = vec![0; MAX_PATTERNS];
+    orders[0] = first;
+    let rest = &module.orders[order + 1..module.song_length];
+    orders[1..=rest.len()].copy_from_slice(rest);
+    module.orders = orders;
+    module.song_length -= order;
+    Ok(Some(module.write()))
+}
+
+// -- MIDI files
+
+/// A standard MIDI file.
+#[derive(Debug)]
+struct Midi {
+    format: u16,
+    division: u16,
+    /// The events of each track, with their absolute tick,
+    /// each event with its status byte
+    /// (and without end of track events)
+    tracks: Vec<Vec<(u32, Vec<u8>)>>,
+}
+
+/// Reads a variable length quantity of a MIDI file.
+fn read_vlq(data: &[u8], i: &mut usize) -> Option<u32> {
+    let mut value = 0u32;
+    for _ in 0..4 {
+        let byte = *data.get(*i)?;
+        *i += 1;
+        value = (value << 7) | (byte & 0x7F) as u32;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Writes a variable length quantity of a MIDI file.
+fn write_vlq(out: &mut Vec<u8>, value: u32) {
+    let mut bytes = [0u8; 5];
+    let mut len = 0;
+    let mut v = value;
+    loop {
+        bytes[len] = (v & 0x7F) as u8;
+        len += 1;
+        v >>= 7;
+        if v == 0 {
+            break;
+        }
+    }
+    for i in (0..len).rev() {
+        out.push(bytes[i] | if i > 0 { 0x80 } else { 0 });
+    }
+}
+
+fn parse_midi(data: &[u8]) -> Result<Midi, FileError> {
+    if data.len() < 14 || &data[0..4] != b"MThd" {
+        return Err(invalid("not a MIDI file"));
+    }
+    let read_u16 = |at: usize| u16::from_be_bytes([data[at], data[at + 1]]);
+    let header_len = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let format = read_u16(8);
+    let track_count = read_u16(10);
+    let division = read_u16(12);
+    let mut i = 8 + header_len;
+    let mut tracks = Vec::new();
+    let truncated = || invalid("truncated MIDI file");
+
+    while tracks.len() < track_count as usize && i + 8 <= data.len() {
+        let len = u32::from_be_bytes([data[i + 4], data[i + 5], data[i + 6], data[i + 7]]) as usize;
+        let chunk = data.get(i + 8..i + 8 + len).ok_or_else(truncated)?;
+        let is_track = &data[i..i + 4] == b"MTrk";
+        i += 8 + len;
+        if !is_track {
+            continue;
+        }
+
+        let mut events = Vec::new();
+        let (mut j, mut tick, mut running) = (0, 0u32, 0u8);
+        while j < chunk.len() {
+            tick += read_vlq(chunk, &mut j).ok_or_else(truncated)?;
+            let mut status = *chunk.get(j).ok_or_else(truncated)?;
+            if status < 0x80 {
+                status = running;
+            } else {
+                j += 1;
+            }
+            let start = j;
+            let mut event = vec![status];
+            match status {
+                0x80..=0xBF | 0xE0..=0xEF => j += 2,
+                0xC0..=0xDF => j += 1,
+                0xFF => {
+                    j += 1;
+                    let len = read_vlq(chunk, &mut j).ok_or_else(truncated)?;
+                    j += len as usize;
+                }
+                0xF0 | 0xF7 => {
+                    let len = read_vlq(chunk, &mut j).ok_or_else(truncated)?;
+                    j += len as usize;
+                }
+                _ => return Err(invalid("invalid MIDI event")),
+            }
+            event.extend_from_slice(chunk.get(start..j).ok_or_else(truncated)?);
+            if status < 0xF0 {
+                running = status;
+            }
+            // end of track
+            if event[..2.min(event.len())] == [0xFF, 0x2F] {
+                break;
+            }
+            events.push((tick, event));
+        }
+        tracks.push(events);
+    }
+    Ok(Midi {
+        format,
+        division,
+        tracks,
+    })
+}
+
+/// Writes the part of a MIDI file from tick `start` to tick `end`.
+///
+/// The tempo, instruments, controllers and system exclusive messages
+/// before `start` are kept at the beginning,
+/// and the notes still held at `end` are released there.
+fn write_midi(midi: &Midi, start: u32, end: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(b"MThd");
+    out.extend_from_slice(&6u32.to_be_bytes());
+    out.extend_from_slice(&midi.format.to_be_bytes());
+    out.extend_from_slice(&(midi.tracks.len() as u16).to_be_bytes());
+    out.extend_from_slice(&midi.division.to_be_bytes());
+
+    for events in &midi.tracks {
+        let mut track = Vec::new();
+        let mut last = 0;
+        let mut write = |track: &mut Vec<u8>, tick: u32, event: &[u8]| {
+            write_vlq(track, tick - last);
+            last = tick;
+            track.extend_from_slice(event);
+        };
+        let mut held: Vec<(u8, u8)> = Vec::new();
+        for (tick, event) in events {
+            let (tick, status) = (*tick, event[0]);
+            if tick >= end {
+                break;
+            }
+            if tick < start {
+                let is_state =
+                    matches!(status, 0xB0..=0xEF | 0xF0 | 0xF7) || event[..2] == [0xFF, 0x51];
+                if is_state {
+                    write(&mut track, 0, event);
+                }
+                continue;
+            }
+            let key = (status & 0x0F, event.get(1).copied().unwrap_or(0));
+            match status & 0xF0 {
+                0x90 if event[2] > 0 => held.push(key),
+                0x80 | 0x90 => held.retain(|&k| k != key),
+                _ => {}
+            }
+            write(&mut track, tick - start, event);
+        }
+        for (channel, note) in held {
+            write(&mut track, end - start, &[0x80 | channel, note, 0]);
+        }
+        write(&mut track, end - start, &[0xFF, 0x2F, 0x00]);
+
+        out.extend_from_slice(b"MTrk");
+        out.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        out.extend_from_slice(&track);
+    }
+    out
+}
+
+/// Gets the tick of the last event of a MIDI file.
+fn midi_end(midi: &Midi) -> u32 {
+    midi.tracks
+        .iter()
+        .filter_map(|track| track.last())
+        .map(|(tick, _)| *tick)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Gets the rate of the ticks of a MIDI file per second,
+/// from each change of tempo,
+/// as pairs of the tick of the change and the new rate, in order.
+fn midi_tempo_map(midi: &Midi) -> Vec<(u32, f64)> {
+    if midi.division & 0x8000 != 0 {
+        // frames per second (negative) and ticks per frame
+        let fps = -((midi.division >> 8) as i8) as f64;
+        let per_frame = (midi.division & 0xFF) as f64;
+        return vec![(0, (fps * per_frame).max(1.))];
+    }
+    let per_quarter = midi.division.max(1) as f64;
+    let mut changes: Vec<(u32, u32)> = midi
+        .tracks
+        .iter()
+        .flatten()
+        .filter(|(_, event)| event.len() >= 6 && event[..3] == [0xFF, 0x51, 0x03])
+        .map(|(tick, e)| (*tick, u32::from_be_bytes([0, e[3], e[4], e[5]])))
+        .collect();
+    changes.sort_by_key(|(tick, _)| *tick);
+
+    // 120 beats per minute until told otherwise
+    let mut map = vec![(0, per_quarter * 2.)];
+    for (tick, micros) in changes {
+        let rate = per_quarter * 1e6 / micros.max(1) as f64;
+        match map.last_mut() {
+            Some(last) if last.0 == tick => last.1 = rate,
+            _ => map.push((tick, rate)),
+        }
+    }
+    map
+}
+
+/// Converts a tick of a MIDI file to seconds from the start.
+fn midi_seconds(map: &[(u32, f64)], tick: u32) -> f64 {
+    let mut time = 0.;
+    for (i, &(start, rate)) in map.iter().enumerate() {
+        match map.get(i + 1) {
+            Some(&(end, _)) if end < tick => time += (end - start) as f64 / rate,
+            _ => return time + tick.saturating_sub(start) as f64 / rate,
+        }
+    }
+    time
+}
+
+/// Converts seconds from the start of a MIDI file to a tick.
+fn midi_tick_at(map: &[(u32, f64)], seconds: f64) -> u32 {
+    let mut time = 0.;
+    for (i, &(start, rate)) in map.iter().enumerate() {
+        if let Some(&(end, _)) = map.get(i + 1) {
+            let segment = (end - start) as f64 / rate;
+            if seconds >= time + segment {
+                time += segment;
+                continue;
+            }
+        }
+        return start.saturating_add(((seconds - time).max(0.) * rate) as u32);
+    }
+    0
+}
+
+// -- MUS files
+
+/// The kinds of MUS events.
+const MUS_RELEASE: u8 = 0;
+const MUS_PLAY: u8 = 1;
+const MUS_MEASURE_END: u8 = 5;
+const MUS_SCORE_END: u8 = 6;
+
+/// A MUS event.
+#[derive(Debug, Clone)]
+struct MusEvent {
+    tick: u32,
+    kind: u8,
+    channel: u8,
+    args: Vec<u8>,
+}
+
+/// A DMX MUS file.
+#[derive(Debug)]
+struct Mus {
+    /// The header, up to the start of the score
+    header: Vec<u8>,
+    events: Vec<MusEvent>,
+    /// The number of ticks until the end of the score
+    length: u32,
+}
+
+fn parse_mus(data: &[u8]) -> Result<Mus, FileError> {
+    if data.len() < 16 || &data[0..4] != b"MUS\x1A" {
+        return Err(invalid("not a MUS file"));
+    }
+    let score_start = u16::from_le_bytes([data[6], data[7]]) as usize;
+    let header = data
+        .get(..score_start)
+        .filter(|h| h.len() >= 16)
+        .ok_or_else(|| invalid("truncated MUS file"))?
+        .to_vec();
+    let mut events = Vec::new();
+    let (mut i, mut tick) = (score_start, 0u32);
+    while let Some(&byte) = data.get(i) {
+        i += 1;
+        let kind = (byte >> 4) & 0x07;
+        let channel = byte & 0x0F;
+        let len = match kind {
+            MUS_PLAY => 1 + (data.get(i).is_some_and(|&b| b & 0x80 != 0)) as usize,
+            4 => 2,
+            MUS_MEASURE_END | MUS_SCORE_END => 0,
+            _ => 1,
+        };
+        let args = data
+            .get(i..i + len)
+            .ok_or_else(|| invalid("truncated MUS file"))?
+            .to_vec();
+        i += len;
+        if kind == MUS_SCORE_END {
+            break;
+        }
+        if kind != MUS_MEASURE_END {
+            events.push(MusEvent {
+                tick,
+                kind,
+                channel,
+                args,
+            });
+        }
+        if byte & 0x80 != 0 {
+            tick += read_vlq(data, &mut i).ok_or_else(|| invalid("truncated MUS file"))?;
+        }
+    }
+    Ok(Mus {
+        header,
+        events,
+        length: tick,
+    })
+}
+
+/// Writes the part of a MUS file from tick `start` to tick `end`.
+///
+/// The instruments, controllers and pitch bends before `start`
+/// are kept at the beginning,
+/// the first note of each channel carries the volume in effect,
+/// and the notes still held at `end` are released there.
+fn write_mus(mus: &Mus, start: u32, end: u32) -> Vec<u8> {
+    let mut events: Vec<MusEvent> = Vec::new();
+    let mut volumes = [None; 16];
+    let mut first_note = [true; 16];
+    let mut held: Vec<(u8, u8)> = Vec::new();
+    for event in &mus.events {
+        if event.tick >= end {
+            break;
+        }
+        let channel = event.channel as usize;
+        let note = event.args[0] & 0x7F;
+        if event.kind == MUS_PLAY && event.args.len() > 1 {
+            volumes[channel] = Some(event.args[1]);
+        }
+        if event.tick < start {
+            if !matches!(event.kind, MUS_RELEASE | MUS_PLAY) {
+                events.push(MusEvent {
+                    tick: 0,
+                    ..event.clone()
+                });
+            }
+            continue;
+        }
+        let mut event = MusEvent {
+            tick: event.tick - start,
+            ..event.clone()
+        };
+        match event.kind {
+            MUS_PLAY => {
+                if std::mem::take(&mut first_note[channel]) && event.args.len() == 1 {
+                    if let Some(volume) = volumes[channel] {
+                        event.args = vec![note | 0x80, volume];
+                    }
+                }
+                held.push((event.channel, note));
+            }
+            MUS_RELEASE => held.retain(|&k| k != (event.channel, note)),
+            _ => {}
+        }
+        events.push(event);
+    }
+    for (channel, note) in held {
+        events.push(MusEvent {
+            tick: end - start,
+            kind: MUS_RELEASE,
+            channel,
+            args: vec![note],
+        });
+    }
+    // delays only follow events, so lead with an event which does nothing
+    if events.first().is_none_or(|e| e.tick > 0) {
+        events.insert(
+            0,
+            MusEvent {
+                tick: 0,
+                kind: MUS_MEASURE_END,
+                channel: 0,
+                args: Vec::new(),
+            },
+        );
+    }
+
+    let mut score = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        let next = events.get(i + 1).map_or(end - start, |e| e.tick);
+        let delay = next - event.tick;
+        let last = if delay > 0 { 0x80 } else { 0 };
+        score.push(last | event.kind << 4 | event.channel);
+        score.extend_from_slice(&event.args);
+        if delay > 0 {
+            write_vlq(&mut score, delay);
+        }
+    }
+    score.push(MUS_SCORE_END << 4);
+
+    let mut out = mus.header.clone();
+    out[4..6].copy_from_slice(&(score.len().min(0xFFFF) as u16).to_le_bytes());
+    out.extend_from_slice(&score);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a 4 channel module playing its only pattern twice.
+    fn two_positions() -> Vec<u8> {
+        let mut data = vec![0; PATTERNS];
+        data[SONG_LENGTH] = 2;
+        data[SIGNATURE..PATTERNS].copy_from_slice(b"M.K.");
+        // a different note in each row, to tell them apart
+        let mut pattern = vec![0; ROWS * 16];
+        for (row, cells) in pattern.chunks_exact_mut(16).enumerate() {
+            cells[1] = row as u8 + 1;
+        }
+        data.extend(pattern);
+        data
+    }
+
+    /// Gets the cells of a row of a pattern in a MOD file.
+    fn row(data: &[u8], pattern: u8, row: usize) -> &[u8] {
+        &data[PATTERNS + (pattern as usize * ROWS + row) * 16..][..16]
+    }
+
+    #[test]
+    fn vlq_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 0x0FFF_FFFF] {
+            let mut out = Vec::new();
+            write_vlq(&mut out, value);
+            let mut i = 0;
+            assert_eq!(read_vlq(&out, &mut i), Some(value));
+            assert_eq!(i, out.len());
+        }
+        assert_eq!(read_vlq(&[0x81], &mut 0), None);
+    }
+
+    #[test]
+    fn module_loops_from_the_start_of_a_pattern() {
+        let data = two_positions();
+        let out = loop_module(&data, (0, 0), (2, 0)).unwrap();
+        assert_eq!(out[SONG_LENGTH], 2);
+        assert_eq!(&out[ORDERS..ORDERS + 3], &[0, 1, 0]);
+        // the copy of the pattern jumps back on its last row
+        assert_eq!(&row(&out, 1, ROWS - 1)[..4], &[0, ROWS as u8, JUMP, 0]);
+        assert_eq!(row(&out, 1, 10), row(&out, 0, 10));
+        assert_eq!(&row(&out, 0, ROWS - 1)[2..4], &[0, 0]);
+    }
+
+    #[test]
+    fn module_loops_from_the_middle_of_a_pattern() {
+        let data = two_positions();
+        let out = loop_module(&data, (0, 8), (1, 16)).unwrap();
+        // the loop goes on at a new position 2
+        // with the rest of the pattern,
+        // followed by position 1 again, which jumps back to 2
+        assert_eq!(out[SONG_LENGTH], 4);
+        assert_eq!(&out[ORDERS..ORDERS + 4], &[0, 2, 1, 2]);
+        assert_eq!(row(&out, 1, 0)[1], 9);
+        assert_eq!(&row(&out, 1, ROWS - 9)[..4], &[0, ROWS as u8, BREAK, 0]);
+        assert_eq!(&row(&out, 2, 15)[..4], &[0, 16, JUMP, 2]);
+    }
+
+    #[test]
+    fn module_loop_points_are_checked() {
+        let data = two_positions();
+        for (start, end) in [((1, 0), (1, 0)), ((0, 0), (3, 0)), ((2, 0), (2, 1))] {
+            assert!(matches!(
+                loop_module(&data, start, end),
+                Err(LoopPointError::InvalidRange)
+            ));
+        }
+        assert!(matches!(
+            loop_module(&data[..PATTERNS], (0, 0), (1, 0)),
+            Err(LoopPointError::File(_))
+        ));
+    }
+
+    #[test]
+    fn midi_part_keeps_state_and_releases_notes() {
+        let track = [
+            0x00, 0xC0, 0x05, // program change
+            0x00, 0x90, 0x3C, 0x64, // note on
+            0x60, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // tempo
+            0x00, 0x90, 0x40, 0x64, // note on
+            0x60, 0x80, 0x3C, 0x00, // note off
+            0x60, 0xFF, 0x2F, 0x00, // end of track
+        ];
+        let mut data = b"MThd\0\0\0\x06\0\0\0\x01\0\x60MTrk".to_vec();
+        data.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        data.extend_from_slice(&track);
+        let midi = parse_midi(&data).unwrap();
+        assert_eq!(midi.division, 96);
+        assert_eq!(midi.tracks[0].len(), 5);
+        assert_eq!(midi.tracks[0][4], (192, vec![0x80, 0x3C, 0x00]));
+
+        let part = parse_midi(&write_midi(&midi, 96, 192)).unwrap();
+        assert_eq!(
+            part.tracks,
+            [vec![
+                (0, vec![0xC0, 0x05]),
+                (0, vec![0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20]),
+                (0, vec![0x90, 0x40, 0x64]),
+                (96, vec![0x80, 0x40, 0x00]),
+            ]]
+        );
+        assert!(parse_midi(&data[..data.len() - 2]).is_err());
+    }
+
+    #[test]
+    fn mus_part_keeps_state_and_volume() {
+        let score = [
+            0x11, 0xBC, 0x64, // play note 60 at volume 100
+            0xC1, 0x00, 0x05, 70, // change instrument, then wait
+            0x01, 0x3C, // release note 60
+            0x91, 0x3E, 70,   // play note 62, then wait
+            0x60, // end of score
+        ];
+        let mut data = b"MUS\x1A".to_vec();
+        data.extend_from_slice(&(score.len() as u16).to_le_bytes());
+        data.extend_from_slice(&16_u16.to_le_bytes());
+        data.resize(16, 0);
+        data.extend_from_slice(&score);
+        let mus = parse_mus(&data).unwrap();
+        assert_eq!(mus.events.len(), 4);
+        assert_eq!(mus.events[3].tick, 70);
+
+        let out = write_mus(&mus, 70, 140);
+        let part = parse_mus(&out).unwrap();
+        let events: Vec<_> = part
+            .events
+            .iter()
+            .map(|e| (e.tick, e.kind, e.args.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (0, 4, vec![0x00, 0x05]),
+                (0, MUS_RELEASE, vec![0x3C]),
+                (0, MUS_PLAY, vec![0xBE, 0x64]),
+                (70, MUS_RELEASE, vec![0x3E]),
+            ]
+        );
+        assert_eq!(
+            u16::from_le_bytes([out[4], out[5]]) as usize,
+            out.len() - 16
+        );
+    }
+
+    /// Builds a 4 channel module with one pattern played twice,
+    /// at speed 3, breaking after 16 rows.
+    fn module() -> Vec<u8> {
+        let mut data = vec![0; PATTERNS];
+        data[SONG_LENGTH] = 2;
+        data[SIGNATURE..PATTERNS].copy_from_slice(b"M.K.");
+        let mut pattern = vec![0; ROWS * 16];
+        pattern[2..4].copy_from_slice(&[0x0F, 3]);
+        pattern[15 * 16 + 2] = BREAK;
+        data.extend(pattern);
+        data
+    }
+
+    #[test]
+    fn module_timeline_follows_speed_and_breaks() {
+        let data = module();
+        let (played, length) = module_timeline(&Module::parse(&data).unwrap());
+        assert_eq!(played.len(), 32);
+        assert_eq!((played[16].order, played[16].row), (1, 0));
+        assert_eq!(played[31].speed, 3);
+        // 3 ticks of 20 ms per row
+        assert!((played[1].time - 0.06).abs() < 1e-9);
+        assert!((length.unwrap() - 1.92).abs() < 1e-9);
+    }
+
+    #[test]
+    fn module_timeline_detects_endless_songs() {
+        let mut data = module();
+        // jump back to the start at the end of the second position
+        data[PATTERNS + 15 * 16 + 6..PATTERNS + 15 * 16 + 8].copy_from_slice(&[JUMP, 0]);
+        let (played, length) = module_timeline(&Module::parse(&data).unwrap());
+        assert_eq!(played.len(), 16);
+        assert_eq!(length, None);
+    }
+
+    #[test]
+    fn module_resumes_from_row() {
+        let data = module();
+        let rest = module_from(&data, 0.5).unwrap().unwrap();
+        let module = Module::parse(&rest).unwrap();
+        assert_eq!(module.song_length, 2);
+        assert_eq!(&module.orders[..2], &[1, 0]);
+        // rows 8 to 15 of the first position remain,
+        // with the speed and tempo restored
+        let (played, length) = module_timeline(&module);
+        assert_eq!(played.len(), 8 + 16);
+        assert_eq!((played[0].speed, played[0].tempo), (3, 125));
+        assert!((length.unwrap() - (1.92 - 0.48)).abs() < 1e-9);
+
+        assert!(module_from(&data, 2.).unwrap().is_none());
+        assert!(module_from(&data[..PATTERNS], 0.).is_err());
+    }
+
+    #[test]
+    fn midi_ticks_and_seconds() {
+        let tempo = |tick, micros: u32| {
+            let [_, a, b, c] = micros.to_be_bytes();
+            (tick, vec![0xFF, 0x51, 0x03, a, b, c])
+        };
+        let midi = Midi {
+            format: 1,
+            division: 96,
+            tracks: vec![
+                vec![tempo(192, 250_000)],
+                vec![(0, vec![0x90, 60, 100]), (480, vec![0x80, 60, 0])],
+            ],
+        };
+        let map = midi_tempo_map(&midi);
+        assert_eq!(map, [(0, 192.), (192, 384.)]);
+        assert_eq!(midi_end(&midi), 480);
+        // one second at 120 BPM, then 288 ticks at 240 BPM
+        assert_eq!(midi_seconds(&map, 96), 0.5);
+        assert_eq!(midi_seconds(&map, 480), 1.75);
+        assert_eq!(midi_tick_at(&map, 0.5), 96);
+        assert_eq!(midi_tick_at(&map, 1.75), 480);
+
+        let smpte = Midi {
+            format: 0,
+            division: 0xE728,
+            tracks: Vec::new(),
+        };
+        // 25 frames of 40 ticks per second
+        assert_eq!(midi_tempo_map(&smpte), [(0, 1000.)]);
+    }
+}
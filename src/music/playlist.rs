@@ -0,0 +1,511 @@
+//! Playlists of music tracks.
+
+use std::cell::OnceCell;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::music::looping::{music_from, music_length};
+use crate::music::{music_finished, play_music, set_music_volume, stop_music, Music};
+
+/// What a [`Playlist`] does when a track ends.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum RepeatMode {
+    /// Play each track once, then stop
+    #[default]
+    Off,
+    /// Start over after the last track
+    All,
+    /// Repeat the current track
+    One,
+}
+
+/// The playback state of a playlist.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum State {
+    Stopped,
+    Paused {
+        /// How far into the current track it was paused
+        position: Duration,
+    },
+    /// Waiting for the next track to start
+    Gap {
+        until: Instant,
+    },
+    Playing {
+        /// When the track was started or resumed
+        started: Instant,
+        /// How far into the track it was started or resumed
+        offset: Duration,
+    },
+}
+
+/// A list of music tracks played one after the other.
+///
+/// The playlist owns its tracks,
+/// and must be updated once per frame through [`update`](Playlist::update)
+/// to advance to the next track when the current one ends
+/// (as detected by [`music_finished`]).
+///
+/// A gap of silence can be left between tracks,
+/// and each track can fade in from silence and fade out at its end.
+/// Since the engine plays one music at a time,
+/// tracks cannot overlap:
+/// a [crossfade](Playlist::set_crossfade) fades out the end of each track
+/// and fades in the next one right after.
+///
+/// Pausing keeps the position in the current track,
+/// see [`pause`](Playlist::pause).
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// # use std::time::Duration;
+/// let tracks = vec![
+///     Music::load_mod("title.mod").unwrap(),
+///     Music::load_mod("level1.mod").unwrap(),
+/// ];
+/// let mut playlist = Playlist::new(tracks);
+/// playlist.set_repeat(RepeatMode::All);
+/// playlist.set_gap(Duration::from_secs(2));
+/// playlist.play();
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     playlist.update();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Playlist {
+    tracks: Vec<Music>,
+    /// The order in which tracks are played, as indices into `tracks`
+    order: Vec<usize>,
+    /// The position of the current track in `order`
+    position: usize,
+    shuffle: bool,
+    repeat: RepeatMode,
+    volume: u8,
+    gap: Duration,
+    fade_in: Duration,
+    fade_out: Duration,
+    state: State,
+    /// The length of each track, found when first needed
+    lengths: Vec<OnceCell<Option<Duration>>>,
+    /// The rest of the current track, rebuilt to resume it
+    resumed: Option<Music>,
+    /// The last volume given to the engine
+    applied_volume: u8,
+    rng: u32,
+}
+
+impl Playlist {
+    /// Creates a playlist with the given tracks, in order,
+    /// without shuffling or repeating.
+    pub fn new(tracks: Vec<Music>) -> Self {
+        let order = (0..tracks.len()).collect();
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0)
+            ^ 0x9E37_79B9;
+        let lengths = tracks.iter().map(|_| OnceCell::new()).collect();
+        Playlist {
+            tracks,
+            order,
+            position: 0,
+            shuffle: false,
+            repeat: RepeatMode::Off,
+            volume: 255,
+            gap: Duration::ZERO,
+            fade_in: Duration::ZERO,
+            fade_out: Duration::ZERO,
+            state: State::Stopped,
+            lengths,
+            resumed: None,
+            applied_volume: 255,
+            rng: seed | 1,
+        }
+    }
+
+    /// Gets the tracks of the playlist, in their original order.
+    #[inline]
+    pub fn tracks(&self) -> &[Music] {
+        &self.tracks
+    }
+
+    /// Stops the playlist and takes back its tracks.
+    pub fn into_tracks(mut self) -> Vec<Music> {
+        self.stop();
+        std::mem::take(&mut self.tracks)
+    }
+
+    /// Gets the number of tracks in the playlist.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Checks whether the playlist has no tracks.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Gets the index of the current track in [`tracks`](Self::tracks),
+    /// or `None` if the playlist is stopped.
+    ///
+    /// This is also the track to resume when paused,
+    /// or the next one to play during a gap.
+    pub fn current_track_index(&self) -> Option<usize> {
+        match self.state {
+            State::Stopped => None,
+            _ => self.order.get(self.position).copied(),
+        }
+    }
+
+    /// Enables or disables shuffling.
+    ///
+    /// Enabling shuffling reorders the tracks randomly,
+    /// keeping the current track as the first one.
+    /// Disabling it restores the original order.
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        if shuffle == self.shuffle {
+            return;
+        }
+        self.shuffle = shuffle;
+        let current = self.order.get(self.position).copied();
+        if shuffle {
+            self.shuffle_order(current);
+            self.position = 0;
+        } else {
+            self.order = (0..self.tracks.len()).collect();
+            self.position = current.unwrap_or(0);
+        }
+    }
+
+    /// Checks whether shuffling is enabled.
+    #[inline]
+    pub fn is_shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    /// Sets what happens when a track ends.
+    #[inline]
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    /// Gets what happens when a track ends.
+    #[inline]
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    /// Sets the volume of the music,
+    /// between 0 (silent) and 255 (full volume).
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = volume;
+        if let State::Playing { .. } = self.state {
+            self.apply_volume(Instant::now());
+        }
+    }
+
+    /// Gets the volume of the music.
+    #[inline]
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    /// Sets the silence left between the end of a track
+    /// and the start of the next one.
+    #[inline]
+    pub fn set_gap(&mut self, gap: Duration) {
+        self.gap = gap;
+    }
+
+    /// Sets how long each track takes to fade in from silence
+    /// when it starts.
+    #[inline]
+    pub fn set_fade_in(&mut self, fade_in: Duration) {
+        self.fade_in = fade_in;
+    }
+
+    /// Sets how long each track takes to fade out to silence
+    /// before it ends.
+    ///
+    /// The length of each track is found from its data
+    /// when it first starts playing with a fade out.
+    /// Tracks whose length cannot be found,
+    /// such as OPB files or tracks which loop back forever,
+    /// do not fade out.
+    #[inline]
+    pub fn set_fade_out(&mut self, fade_out: Duration) {
+        self.fade_out = fade_out;
+    }
+
+    /// Crossfades between tracks:
+    /// each track fades out over its last `duration`
+    /// and the next one fades in over the same duration,
+    /// with no gap in between.
+    ///
+    /// Since the engine plays one music at a time,
+    /// the fade in starts as the previous track ends,
+    /// instead of both tracks playing together.
+    /// This is the same as setting the fade in and fade out
+    /// to `duration` and the gap to zero.
+    pub fn set_crossfade(&mut self, duration: Duration) {
+        self.fade_in = duration;
+        self.fade_out = duration;
+        self.gap = Duration::ZERO;
+    }
+
+    /// Starts playing the current track from the beginning,
+    /// or the first track if the playlist was stopped.
+    pub fn play(&mut self) {
+        if self.state == State::Stopped {
+            self.position = 0;
+        }
+        self.start_current();
+    }
+
+    /// Pauses the playlist,
+    /// keeping the position in the current track.
+    ///
+    /// The engine can neither pause nor seek music,
+    /// so the current track is stopped,
+    /// and [`resume`](Self::resume) plays the rest of the track
+    /// rebuilt from its data
+    /// (see [`play_music_with_loop_points`](crate::play_music_with_loop_points)),
+    /// from the position estimated by the time it played.
+    /// Modules resume from the start of the row played when paused.
+    /// OPB files and tracks created without their data
+    /// start over from the beginning instead.
+    ///
+    /// Pausing during a gap between tracks
+    /// starts the next track from the beginning when resumed.
+    pub fn pause(&mut self) {
+        let position = match self.state {
+            State::Stopped | State::Paused { .. } => return,
+            State::Gap { .. } => Duration::ZERO,
+            State::Playing { started, offset } => offset + started.elapsed(),
+        };
+        stop_music();
+        self.state = State::Paused { position };
+    }
+
+    /// Resumes a paused playlist
+    /// from the position where the current track was paused.
+    pub fn resume(&mut self) {
+        if let State::Paused { position } = self.state {
+            self.start_current_at(position);
+        }
+    }
+
+    /// Checks whether the playlist is paused.
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        matches!(self.state, State::Paused { .. })
+    }
+
+    /// Stops the playlist,
+    /// so that it starts from the first track when played again.
+    pub fn stop(&mut self) {
+        if self.state != State::Stopped {
+            stop_music();
+            self.state = State::Stopped;
+            self.resumed = None;
+        }
+    }
+
+    /// Skips to the next track immediately,
+    /// even with [`RepeatMode::One`].
+    ///
+    /// After the last track,
+    /// this goes back to the first track if repeating all tracks,
+    /// or stops the playlist otherwise.
+    pub fn skip(&mut self) {
+        if self.state == State::Stopped {
+            return;
+        }
+        if self.advance() {
+            self.start_current();
+        } else {
+            self.stop();
+        }
+    }
+
+    /// Goes back to the previous track immediately.
+    ///
+    /// Before the first track,
+    /// this goes to the last track if repeating all tracks,
+    /// or restarts the first track otherwise.
+    pub fn previous(&mut self) {
+        if self.state == State::Stopped || self.order.is_empty() {
+            return;
+        }
+        if self.position > 0 {
+            self.position -= 1;
+        } else if self.repeat == RepeatMode::All {
+            self.position = self.order.len() - 1;
+        }
+        self.start_current();
+    }
+
+    /// Advances the playlist,
+    /// to be called once per frame after [`wait_vbl`](crate::wait_vbl).
+    ///
+    /// This detects the end of the current track,
+    /// starts the next one when due,
+    /// and applies the fades.
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        match self.state {
+            State::Stopped | State::Paused { .. } => {}
+            State::Gap { until } => {
+                if now >= until {
+                    self.start_current();
+                }
+            }
+            State::Playing { .. } => {
+                if music_finished() {
+                    self.track_ended(now);
+                } else {
+                    self.apply_volume(now);
+                }
+            }
+        }
+    }
+
+    /// Moves on after the current track ended by itself.
+    fn track_ended(&mut self, now: Instant) {
+        if self.repeat != RepeatMode::One && !self.advance() {
+            self.state = State::Stopped;
+            return;
+        }
+        if self.gap.is_zero() {
+            self.start_current();
+        } else {
+            self.state = State::Gap {
+                until: now + self.gap,
+            };
+        }
+    }
+
+    /// Moves to the next position in the play order,
+    /// returning false if the end was reached without repeating.
+    fn advance(&mut self) -> bool {
+        if self.position + 1 < self.order.len() {
+            self.position += 1;
+            return true;
+        }
+        if self.repeat != RepeatMode::All || self.order.is_empty() {
+            return false;
+        }
+        if self.shuffle {
+            self.shuffle_order(None);
+        }
+        self.position = 0;
+        true
+    }
+
+    fn start_current(&mut self) {
+        self.start_current_at(Duration::ZERO);
+    }
+
+    /// Starts the current track from the given position.
+    fn start_current_at(&mut self, position: Duration) {
+        let Some(&track) = self.order.get(self.position) else {
+            self.state = State::Stopped;
+            return;
+        };
+        let now = Instant::now();
+        self.resumed = None;
+        let mut offset = Duration::ZERO;
+        if !position.is_zero() {
+            match music_from(&self.tracks[track], position) {
+                Ok(Some(rest)) => {
+                    self.resumed = Some(rest);
+                    offset = position;
+                }
+                // paused right at the end
+                Ok(None) => {
+                    self.state = State::Playing {
+                        started: now,
+                        offset: position,
+                    };
+                    self.track_ended(now);
+                    return;
+                }
+                // start over
+                Err(_) => {}
+            }
+        }
+        self.state = State::Playing {
+            started: now,
+            offset,
+        };
+        let volume = self.current_volume(now);
+        self.applied_volume = volume;
+        let music = self.resumed.as_ref().unwrap_or(&self.tracks[track]);
+        play_music(music, false, volume);
+    }
+
+    /// Gives the engine the volume for the current point of the fades,
+    /// if it changed.
+    fn apply_volume(&mut self, now: Instant) {
+        let volume = self.current_volume(now);
+        if volume != self.applied_volume {
+            self.applied_volume = volume;
+            set_music_volume(volume);
+        }
+    }
+
+    /// Gets the volume for the current point of the fades.
+    fn current_volume(&self, now: Instant) -> u8 {
+        let State::Playing { started, offset } = self.state else {
+            return self.volume;
+        };
+        let position = offset + now.saturating_duration_since(started);
+        let mut level = 1f32;
+        if !self.fade_in.is_zero() {
+            level = level.min(position.as_secs_f32() / self.fade_in.as_secs_f32());
+        }
+        if !self.fade_out.is_zero() {
+            if let Some(length) = self.current_length() {
+                let left = length.saturating_sub(position);
+                level = level.min(left.as_secs_f32() / self.fade_out.as_secs_f32());
+            }
+        }
+        (self.volume as f32 * level.clamp(0., 1.)) as u8
+    }
+
+    /// Gets the length of the current track, if it can be found.
+    fn current_length(&self) -> Option<Duration> {
+        let track = *self.order.get(self.position)?;
+        *self.lengths[track].get_or_init(|| music_length(&self.tracks[track]))
+    }
+
+    /// Shuffles the play order,
+    /// placing the given track first.
+    fn shuffle_order(&mut self, first: Option<usize>) {
+        for i in (1..self.order.len()).rev() {
+            // xorshift
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 17;
+            self.rng ^= self.rng << 5;
+            let j = self.rng as usize % (i + 1);
+            self.order.swap(i, j);
+        }
+        if let Some(first) = first {
+            if let Some(i) = self.order.iter().position(|&t| t == first) {
+                self.order.swap(0, i);
+            }
+        }
+    }
+}
+
+impl Drop for Playlist {
+    fn drop(&mut self) {
+        // do not leave a track of this playlist playing
+        self.stop();
+    }
+}
@@ -0,0 +1,933 @@
+//! Conversion of XM and S3M modules into the MOD format,
+//! which the engine is able to play.
+//!
+//! The conversion is lossy:
+//! only the first 31 samples are kept,
+//! instruments are reduced to a single sample,
+//! envelopes and effects without a MOD equivalent are dropped,
+//! and ping-pong loops become forward loops.
+
+use crate::FileError;
+
+/// The number of rows in a MOD pattern.
+const ROWS: usize = 64;
+/// The maximum number of samples in a MOD file.
+const MAX_SAMPLES: usize = 31;
+/// The maximum number of channels supported.
+const MAX_CHANNELS: usize = 32;
+/// The maximum number of entries in the order table.
+const MAX_ORDERS: usize = 128;
+/// The maximum number of patterns.
+const MAX_PATTERNS: usize = 128;
+/// The maximum sample length in a MOD file, in bytes.
+const MAX_SAMPLE_LEN: usize = 0xFFFF * 2;
+
+fn invalid(message: impl Into<String>) -> FileError {
+    FileError::InvalidData(message.into())
+}
+
+/// Reads a little endian `u16`, or 0 if out of bounds.
+#[inline]
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    match data.get(offset..offset + 2) {
+        Some(b) => u16::from_le_bytes([b[0], b[1]]),
+        None => 0,
+    }
+}
+
+/// Reads a little endian `u32`, or 0 if out of bounds.
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    match data.get(offset..offset + 4) {
+        Some(b) => u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+        None => 0,
+    }
+}
+
+/// Reads a byte, or 0 if out of bounds.
+#[inline]
+fn read_u8(data: &[u8], offset: usize) -> u8 {
+    data.get(offset).copied().unwrap_or(0)
+}
+
+/// Calculates the Amiga period of a note,
+/// as a number of semitones above C-0 (period 1712),
+/// with an adjustment in 1/128ths of a semitone.
+fn period(semitones: i32, fine: i32) -> u16 {
+    let octaves = (semitones as f64 + fine as f64 / 128.) / 12.;
+    (1712. / octaves.exp2()).round().clamp(28., 4095.) as u16
+}
+
+/// A sample of a MOD file.
+#[derive(Debug, Default)]
+struct ModSample {
+    /// Signed 8-bit sample data.
+    data: Vec<u8>,
+    /// Volume between 0 and 64.
+    volume: u8,
+    /// Loop start and length in bytes, if looping.
+    looping: Option<(usize, usize)>,
+}
+
+/// A cell of a MOD pattern.
+#[derive(Debug, Default, Copy, Clone)]
+struct Cell {
+    sample: u8,
+    period: u16,
+    effect: u8,
+    param: u8,
+}
+
+impl Cell {
+    fn encode(self) -> [u8; 4] {
+        [
+            (self.sample & 0xF0) | (self.period >> 8) as u8,
+            self.period as u8,
+            (self.sample << 4) | (self.effect & 0x0F),
+            self.param,
+        ]
+    }
+
+    /// Sets an effect, unless one is already present.
+    fn set_effect_if_free(&mut self, effect: u8, param: u8) -> bool {
+        if self.effect == 0 && self.param == 0 {
+            self.effect = effect;
+            self.param = param;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A module in the process of being converted to MOD.
+#[derive(Debug)]
+struct ModBuilder {
+    channels: usize,
+    samples: Vec<ModSample>,
+    /// Patterns of 64 rows, row by row.
+    patterns: Vec<Vec<Cell>>,
+    orders: Vec<u8>,
+}
+
+impl ModBuilder {
+    fn new(channels: usize) -> Self {
+        ModBuilder {
+            channels,
+            samples: Vec::new(),
+            patterns: Vec::new(),
+            orders: Vec::new(),
+        }
+    }
+
+    fn empty_pattern(&self) -> Vec<Cell> {
+        vec![Cell::default(); ROWS * self.channels]
+    }
+
+    /// Sets the initial speed and tempo
+    /// on the first row of the first pattern played.
+    fn set_initial_timing(&mut self, speed: u8, tempo: u8) {
+        let Some(&first) = self.orders.first() else {
+            return;
+        };
+        let channels = self.channels;
+        let row = &mut self.patterns[first as usize][..channels];
+        let mut effects = [(0x0F, speed.clamp(1, 31)), (0x0F, tempo.max(32))].into_iter();
+        let mut pending = effects.next();
+        for cell in row {
+            match pending {
+                Some((effect, param)) => {
+                    if cell.set_effect_if_free(effect, param) {
+                        pending = effects.next();
+                    }
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Writes the module in MOD format.
+    fn build(self) -> Vec<u8> {
+        let mut out = Vec::new();
+        // title
+        out.extend([0; 20]);
+
+        for i in 0..MAX_SAMPLES {
+            let sample = self.samples.get(i);
+            // name
+            out.extend([0; 22]);
+            let len = sample.map(|s| s.data.len() / 2).unwrap_or(0) as u16;
+            out.extend(len.to_be_bytes());
+            // finetune (pitch is already baked into the periods)
+            out.push(0);
+            out.push(sample.map(|s| s.volume.min(64)).unwrap_or(0));
+            let (loop_start, loop_len) = sample
+                .and_then(|s| s.looping)
+                .map(|(start, len)| ((start / 2) as u16, (len / 2) as u16))
+                .unwrap_or((0, 1));
+            out.extend(loop_start.to_be_bytes());
+            out.extend(loop_len.to_be_bytes());
+        }
+
+        out.push(self.orders.len() as u8);
+        out.push(127);
+        let mut orders = [0; MAX_ORDERS];
+        orders[..self.orders.len()].copy_from_slice(&self.orders);
+        out.extend(orders);
+
+        let signature = match self.channels {
+            4 => *b"M.K.",
+            c @ 1..=9 => [b'0' + c as u8, b'C', b'H', b'N'],
+            c => [b'0' + (c / 10) as u8, b'0' + (c % 10) as u8, b'C', b'H'],
+        };
+        out.extend(signature);
+
+        // all patterns up to the highest one in the order table
+        let pattern_count = orders.iter().max().map(|p| *p as usize + 1).unwrap_or(1);
+        for i in 0..pattern_count {
+            match self.patterns.get(i) {
+                Some(pattern) => {
+                    for cell in pattern {
+                        out.extend(cell.encode());
+                    }
+                }
+                None => out.extend(vec![0; ROWS * self.channels * 4]),
+            }
+        }
+
+        for sample in &self.samples {
+            out.extend(&sample.data);
+        }
+        out
+    }
+}
+
+/// Trims sample data and loop points to what fits in a MOD file,
+/// keeping even lengths.
+fn mod_sample(mut data: Vec<u8>, volume: u8, looping: Option<(usize, usize)>) -> ModSample {
+    data.truncate(MAX_SAMPLE_LEN);
+    if !data.len().is_multiple_of(2) {
+        data.push(0);
+    }
+    let looping = looping
+        .map(|(start, len)| {
+            let start = start.min(data.len()) & !1;
+            let len = len.min(data.len() - start) & !1;
+            (start, len)
+        })
+        .filter(|(_, len)| *len > 2);
+    ModSample {
+        data,
+        volume,
+        looping,
+    }
+}
+
+/// Maps the sample number of a cell to one of the kept samples.
+#[inline]
+fn sample_number(instrument: u8) -> u8 {
+    if instrument as usize <= MAX_SAMPLES {
+        instrument
+    } else {
+        0
+    }
+}
+
+/// Gets the number of channels to write,
+/// for the given number of channels in use.
+fn mod_channels(used: usize) -> usize {
+    match used {
+        0..=4 => 4,
+        n => (n + n % 2).min(MAX_CHANNELS),
+    }
+}
+
+// -- S3M
+
+/// Converts an S3M effect into a MOD effect.
+fn s3m_effect(command: u8, info: u8) -> Option<(u8, u8)> {
+    let (hi, lo) = (info >> 4, info & 0x0F);
+    Some(match command {
+        // Axx: set speed
+        1 => (0x0F, info.clamp(1, 31)),
+        // Bxx: position jump
+        2 => (0x0B, info),
+        // Cxx: pattern break
+        3 => (0x0D, info),
+        // Dxy: volume slide, including fine slides
+        4 if lo == 0x0F && hi != 0 => (0x0E, 0xA0 | hi),
+        4 if hi == 0x0F && lo != 0 => (0x0E, 0xB0 | lo),
+        4 => (0x0A, info),
+        // Exx: portamento down, including fine slides
+        5 if hi >= 0x0E => (0x0E, 0x20 | lo),
+        5 => (0x02, info),
+        // Fxx: portamento up, including fine slides
+        6 if hi >= 0x0E => (0x0E, 0x10 | lo),
+        6 => (0x01, info),
+        // Gxx: tone portamento
+        7 => (0x03, info),
+        // Hxy: vibrato
+        8 => (0x04, info),
+        // Jxy: arpeggio
+        10 => (0x00, info),
+        // Kxy: vibrato and volume slide
+        11 => (0x06, info),
+        // Lxy: tone portamento and volume slide
+        12 => (0x05, info),
+        // Oxx: sample offset
+        15 => (0x09, info),
+        // Qxy: retrigger
+        17 => (0x0E, 0x90 | lo),
+        // Rxy: tremolo
+        18 => (0x07, info),
+        // S8x: set panning
+        19 if hi == 0x08 => (0x08, lo * 17),
+        // SBx: pattern loop
+        19 if hi == 0x0B => (0x0E, 0x60 | lo),
+        // SCx, SDx, SEx: note cut, note delay, pattern delay
+        19 if (0x0C..=0x0E).contains(&hi) => (0x0E, info),
+        // Txx: set tempo
+        20 => (0x0F, info.max(32)),
+        // Xxx: set panning
+        24 => (0x08, info.saturating_mul(2)),
+        _ => return None,
+    })
+}
+
+/// Converts an S3M module into the MOD format.
+pub(crate) fn s3m_to_mod(data: &[u8]) -> Result<Vec<u8>, FileError> {
+    if data.get(0x2C..0x30) != Some(b"SCRM") {
+        return Err(invalid("not an S3M module"));
+    }
+    let order_count = read_u16(data, 0x20) as usize;
+    let instrument_count = read_u16(data, 0x22) as usize;
+    let pattern_count = read_u16(data, 0x24) as usize;
+    let unsigned_samples = read_u16(data, 0x2A) == 2;
+    let initial_speed = read_u8(data, 0x31);
+    let initial_tempo = read_u8(data, 0x32);
+
+    // channels enabled, mapped to the channels written
+    let mut channel_map = [None; 32];
+    let mut used = 0;
+    for (c, mapped) in channel_map.iter_mut().enumerate() {
+        if read_u8(data, 0x40 + c) < 16 {
+            *mapped = Some(used);
+            used += 1;
+        }
+    }
+    let channels = mod_channels(used);
+
+    let orders_at = 0x60;
+    let instruments_at = orders_at + order_count;
+    let patterns_at = instruments_at + instrument_count * 2;
+    let mut module = ModBuilder::new(channels);
+
+    // samples, and their C-4 speed to adjust the periods
+    let mut c2spd = vec![8363; instrument_count.min(MAX_SAMPLES) + 1];
+    for i in 0..instrument_count.min(MAX_SAMPLES) {
+        let at = read_u16(data, instruments_at + i * 2) as usize * 16;
+        let header = data.get(at..at + 0x50).unwrap_or(&[]);
+        if read_u8(header, 0) != 1 {
+            module.samples.push(ModSample::default());
+            continue;
+        }
+        let offset =
+            ((read_u8(header, 0x0D) as usize) << 16 | read_u16(header, 0x0E) as usize) * 16;
+        let len = read_u32(header, 0x10) as usize;
+        let flags = read_u8(header, 0x1F);
+        let bytes_per_sample = if flags & 4 != 0 { 2 } else { 1 };
+        c2spd[i + 1] = read_u32(header, 0x20).max(1);
+
+        // keep the left channel and the most significant byte only
+        let raw = data
+            .get(offset..)
+            .unwrap_or(&[])
+            .chunks_exact(bytes_per_sample)
+            .take(len)
+            .map(|s| {
+                let v = s[bytes_per_sample - 1];
+                if unsigned_samples {
+                    v ^ 0x80
+                } else {
+                    v
+                }
+            })
+            .collect();
+        let looping = if flags & 1 != 0 {
+            let start = read_u32(header, 0x14) as usize;
+            let end = read_u32(header, 0x18) as usize;
+            Some((start, end.saturating_sub(start)))
+        } else {
+            None
+        };
+        module
+            .samples
+            .push(mod_sample(raw, read_u8(header, 0x1C), looping));
+    }
+
+    let pattern_count = pattern_count.min(MAX_PATTERNS);
+    for p in 0..pattern_count {
+        let at = read_u16(data, patterns_at + p * 2) as usize * 16;
+        let mut pattern = module.empty_pattern();
+        let mut instruments = [0u8; 32];
+        let mut pos = at + 2;
+        let mut row = 0;
+        while row < ROWS && pos < data.len() {
+            let what = data[pos];
+            pos += 1;
+            if what == 0 {
+                row += 1;
+                continue;
+            }
+            let channel = channel_map[(what & 31) as usize];
+            let mut cell = Cell::default();
+            let mut volume = None;
+            if what & 32 != 0 {
+                let (note, instrument) = (read_u8(data, pos), read_u8(data, pos + 1));
+                pos += 2;
+                if instrument != 0 {
+                    instruments[(what & 31) as usize] = instrument;
+                    cell.sample = sample_number(instrument);
+                }
+                match note {
+                    255 => {}
+                    254 => {
+                        cell.effect = 0x0E;
+                        cell.param = 0xC0;
+                    }
+                    note => {
+                        let semitones = ((note >> 4) as i32 - 2) * 12 + (note & 0x0F) as i32;
+                        let speed = c2spd
+                            .get(instruments[(what & 31) as usize] as usize)
+                            .copied()
+                            .unwrap_or(8363);
+                        let fine = (128. * 12. * (speed as f64 / 8363.).log2()).round() as i32;
+                        cell.period = period(semitones, fine);
+                    }
+                }
+            }
+            if what & 64 != 0 {
+                volume = Some(read_u8(data, pos));
+                pos += 1;
+            }
+            if what & 128 != 0 {
+                let (command, info) = (read_u8(data, pos), read_u8(data, pos + 1));
+                pos += 2;
+                if let Some((effect, param)) = s3m_effect(command, info) {
+                    cell.effect = effect;
+                    cell.param = param;
+                }
+            }
+            if let Some(volume) = volume {
+                cell.set_effect_if_free(0x0C, volume.min(64));
+            }
+            if let Some(c) = channel.filter(|c| *c < channels) {
+                pattern[row * channels + c] = cell;
+            }
+        }
+        module.patterns.push(pattern);
+    }
+
+    module.orders = data
+        .get(orders_at..instruments_at)
+        .unwrap_or(&[])
+        .iter()
+        .copied()
+        // 254 is a marker to skip, 255 is the end of the song
+        .filter(|o| *o != 254)
+        .take_while(|o| *o != 255)
+        .filter(|o| (*o as usize) < pattern_count)
+        .take(MAX_ORDERS)
+        .collect();
+    if module.orders.is_empty() {
+        return Err(invalid("S3M module has no patterns to play"));
+    }
+    module.set_initial_timing(initial_speed, initial_tempo);
+
+    Ok(module.build())
+}
+
+// -- XM
+
+/// An XM sample, with the pitch adjustments relevant to the conversion.
+#[derive(Debug, Default)]
+struct XmSample {
+    sample: ModSample,
+    relative_note: i8,
+    finetune: i8,
+}
+
+/// Converts an XM module into the MOD format.
+pub(crate) fn xm_to_mod(data: &[u8]) -> Result<Vec<u8>, FileError> {
+    if data.get(0..17) != Some(b"Extended Module: ") {
+        return Err(invalid("not an XM module"));
+    }
+    let header_size = read_u32(data, 60) as usize;
+    let song_length = read_u16(data, 64) as usize;
+    let used = read_u16(data, 68) as usize;
+    let pattern_count = read_u16(data, 70) as usize;
+    let instrument_count = read_u16(data, 72) as usize;
+    let initial_speed = read_u16(data, 76) as u8;
+    let initial_tempo = read_u16(data, 78).min(255) as u8;
+    let channels = mod_channels(used);
+    let mut module = ModBuilder::new(channels);
+
+    // read all patterns first, as instruments come after them
+    let mut pos = 60 + header_size;
+    let mut xm_patterns = Vec::with_capacity(pattern_count);
+    for _ in 0..pattern_count {
+        let pattern_header = read_u32(data, pos) as usize;
+        let rows = (read_u16(data, pos + 5) as usize).max(1);
+        let packed_size = read_u16(data, pos + 7) as usize;
+        let start = pos + pattern_header;
+        let packed = data.get(start..start + packed_size).unwrap_or(&[]);
+        xm_patterns.push((rows, packed));
+        pos = start + packed_size;
+    }
+
+    let mut samples = Vec::with_capacity(instrument_count);
+    for _ in 0..instrument_count {
+        let instrument_size = read_u32(data, pos) as usize;
+        let sample_count = read_u16(data, pos + 27) as usize;
+        if sample_count == 0 {
+            samples.push(XmSample::default());
+            pos += instrument_size;
+            continue;
+        }
+        let sample_header_size = read_u32(data, pos + 29) as usize;
+        // the sample played at C-4
+        let chosen = (read_u8(data, pos + 33 + 48) as usize).min(sample_count - 1);
+        pos += instrument_size;
+
+        let headers = pos;
+        pos += sample_header_size * sample_count;
+        let mut instrument = XmSample::default();
+        for s in 0..sample_count {
+            let header = headers + s * sample_header_size;
+            let len = read_u32(data, header) as usize;
+            if s == chosen {
+                let kind = read_u8(data, header + 14);
+                let sixteen_bits = kind & 0x10 != 0;
+                let bytes_per_sample = if sixteen_bits { 2 } else { 1 };
+                // sample data is delta encoded
+                let mut raw = Vec::with_capacity(len / bytes_per_sample);
+                let mut acc = 0i16;
+                for s in data
+                    .get(pos..pos + len)
+                    .unwrap_or(&[])
+                    .chunks_exact(bytes_per_sample)
+                {
+                    if sixteen_bits {
+                        acc = acc.wrapping_add(i16::from_le_bytes([s[0], s[1]]));
+                        raw.push((acc >> 8) as u8);
+                    } else {
+                        acc = (acc as i8).wrapping_add(s[0] as i8) as i16;
+                        raw.push(acc as u8);
+                    }
+                }
+                let looping = if kind & 3 != 0 {
+                    let start = read_u32(data, header + 4) as usize / bytes_per_sample;
+                    let len = read_u32(data, header + 8) as usize / bytes_per_sample;
+                    Some((start, len))
+                } else {
+                    None
+                };
+                instrument = XmSample {
+                    sample: mod_sample(raw, read_u8(data, header + 12), looping),
+                    finetune: read_u8(data, header + 13) as i8,
+                    relative_note: read_u8(data, header + 16) as i8,
+                };
+            }
+            pos += len;
+        }
+        samples.push(instrument);
+    }
+
+    // each XM pattern may span several MOD patterns
+    let mut pattern_map: Vec<Vec<u8>> = Vec::with_capacity(pattern_count);
+    for (rows, packed) in xm_patterns {
+        let mut cells = vec![Cell::default(); rows * channels];
+        let mut instruments = [0u8; MAX_CHANNELS];
+        let mut pos = 0;
+        for row in 0..rows {
+            for c in 0..used {
+                if pos >= packed.len() {
+                    break;
+                }
+                // a packed cell starts with a mask of the fields present,
+                // otherwise all fields are present
+                let mut fields = [0u8; 5];
+                let first = packed[pos];
+                pos += 1;
+                let mask = if first & 0x80 != 0 {
+                    first
+                } else {
+                    fields[0] = first;
+                    0x1E
+                };
+                for (i, field) in fields.iter_mut().enumerate() {
+                    if mask & (1 << i) != 0 {
+                        *field = read_u8(packed, pos);
+                        pos += 1;
+                    }
+                }
+                if c >= channels {
+                    continue;
+                }
+                let [note, instrument, volume, effect, param] = fields;
+                let mut cell = Cell::default();
+                if instrument != 0 {
+                    instruments[c] = instrument;
+                    cell.sample = sample_number(instrument);
+                }
+                match note {
+                    0 => {}
+                    97 => {
+                        cell.effect = 0x0E;
+                        cell.param = 0xC0;
+                    }
+                    note => {
+                        let sample = (instruments[c] as usize)
+                            .checked_sub(1)
+                            .and_then(|i| samples.get(i));
+                        let relative = sample.map(|s| s.relative_note as i32).unwrap_or(0);
+                        let fine = sample.map(|s| s.finetune as i32).unwrap_or(0);
+                        cell.period = period(note as i32 - 1 - 24 + relative, fine);
+                    }
+                }
+                if effect <= 0x0F && (effect != 0 || param != 0) {
+                    cell.effect = effect;
+                    cell.param = param;
+                }
+                if (0x10..=0x50).contains(&volume) {
+                    cell.set_effect_if_free(0x0C, volume - 0x10);
+                }
+                cells[row * channels + c] = cell;
+            }
+        }
+
+        let mut parts = Vec::new();
+        for chunk in cells.chunks(ROWS * channels) {
+            if module.patterns.len() >= MAX_PATTERNS {
+                break;
+            }
+            let mut pattern = module.empty_pattern();
+            pattern[..chunk.len()].copy_from_slice(chunk);
+            let rows_in_chunk = chunk.len() / channels;
+            if rows_in_chunk < ROWS {
+                // end the pattern early
+                let last = &mut pattern[(rows_in_chunk - 1) * channels..rows_in_chunk * channels];
+                if !last
+                    .iter()
+                    .any(|cell| cell.effect == 0x0B || cell.effect == 0x0D)
+                {
+                    for cell in last {
+                        if cell.set_effect_if_free(0x0D, 0) {
+                            break;
+                        }
+                    }
+                }
+            }
+            parts.push(module.patterns.len() as u8);
+            module.patterns.push(pattern);
+        }
+        pattern_map.push(parts);
+    }
+
+    module.orders = data
+        .get(80..80 + song_length.min(256))
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|o| pattern_map.get(*o as usize))
+        .flatten()
+        .copied()
+        .take(MAX_ORDERS)
+        .collect();
+    if module.orders.is_empty() {
+        return Err(invalid("XM module has no patterns to play"));
+    }
+    module.samples = samples
+        .into_iter()
+        .take(MAX_SAMPLES)
+        .map(|s| s.sample)
+        .collect();
+    module.set_initial_timing(initial_speed, initial_tempo);
+
+    Ok(module.build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Offset of the song length in a MOD file.
+    const MOD_SONG_LENGTH: usize = 20 + MAX_SAMPLES * 30;
+    /// Offset of the order table in a MOD file.
+    const MOD_ORDERS: usize = MOD_SONG_LENGTH + 2;
+    /// Offset of the signature in a MOD file.
+    const MOD_SIGNATURE: usize = MOD_ORDERS + MAX_ORDERS;
+    /// Offset of the first pattern in a MOD file.
+    const MOD_PATTERNS: usize = MOD_SIGNATURE + 4;
+
+    /// Decodes a cell of a MOD file with the given number of channels.
+    fn mod_cell(module: &[u8], channels: usize, pattern: usize, row: usize, c: usize) -> Cell {
+        let at = MOD_PATTERNS + ((pattern * ROWS + row) * channels + c) * 4;
+        let b = &module[at..at + 4];
+        Cell {
+            sample: (b[0] & 0xF0) | (b[2] >> 4),
+            period: (((b[0] & 0x0F) as u16) << 8) | b[1] as u16,
+            effect: b[2] & 0x0F,
+            param: b[3],
+        }
+    }
+
+    /// Gets the length, volume, loop start and loop length
+    /// of a sample of a MOD file, in bytes.
+    fn mod_sample_info(module: &[u8], i: usize) -> (usize, u8, usize, usize) {
+        let at = 20 + i * 30 + 22;
+        let word = |o: usize| u16::from_be_bytes([module[at + o], module[at + o + 1]]) as usize;
+        (word(0) * 2, module[at + 3], word(4) * 2, word(6) * 2)
+    }
+
+    /// Builds an S3M module with two channels, one 8-bit sample,
+    /// and the given packed pattern data and order table.
+    fn s3m(orders: &[u8], pattern: &[u8], unsigned: bool) -> Vec<u8> {
+        let sample_data: [u8; 6] = [0x80, 0x90, 0xA0, 0xB0, 0xC0, 0xD0];
+        let mut data = vec![0; 0x60];
+        data[0x1C] = 0x1A;
+        data[0x1D] = 16;
+        data[0x20..0x22].copy_from_slice(&(orders.len() as u16).to_le_bytes());
+        data[0x22] = 1;
+        data[0x24] = 1;
+        data[0x2A] = if unsigned { 2 } else { 1 };
+        data[0x2C..0x30].copy_from_slice(b"SCRM");
+        data[0x31] = 4;
+        data[0x32] = 150;
+        data[0x40..0x60].fill(255);
+        data[0x40] = 0;
+        data[0x41] = 8;
+        data.extend(orders);
+        // parapointers, filled in below
+        let pointers = data.len();
+        data.extend([0; 4]);
+        data.resize(data.len().next_multiple_of(16), 0);
+
+        let instrument = data.len();
+        data[pointers..pointers + 2].copy_from_slice(&((instrument / 16) as u16).to_le_bytes());
+        data.resize(instrument + 0x50, 0);
+        data[instrument] = 1;
+        data[instrument + 0x10] = sample_data.len() as u8;
+        // loop from 2 to 6
+        data[instrument + 0x14] = 2;
+        data[instrument + 0x18] = 6;
+        data[instrument + 0x1C] = 48;
+        data[instrument + 0x1F] = 1;
+        data[instrument + 0x20..instrument + 0x24].copy_from_slice(&8363u32.to_le_bytes());
+        data[instrument + 0x4C..instrument + 0x50].copy_from_slice(b"SCRS");
+
+        let pattern_at = data.len();
+        data[pointers + 2..pointers + 4].copy_from_slice(&((pattern_at / 16) as u16).to_le_bytes());
+        data.extend(((pattern.len() + 2) as u16).to_le_bytes());
+        data.extend(pattern);
+        data.resize(data.len().next_multiple_of(16), 0);
+
+        let sample_at = data.len();
+        data[instrument + 0x0E..instrument + 0x10]
+            .copy_from_slice(&((sample_at / 16) as u16).to_le_bytes());
+        data.extend(sample_data);
+        data
+    }
+
+    #[test]
+    fn s3m_converts_notes_samples_and_orders() {
+        // row 0: C-4 with sample 1 on channel 0, volume 32 on channel 1
+        // row 1: note off on channel 0, Dx0 volume slide on channel 1
+        let pattern = [
+            32,
+            0x40,
+            1, //
+            64 | 1,
+            32,
+            0, //
+            32,
+            254,
+            0, //
+            128 | 1,
+            4,
+            0x30,
+            0,
+        ];
+        let module = s3m_to_mod(&s3m(&[0, 254, 0, 255, 0], &pattern, false)).unwrap();
+
+        assert_eq!(&module[MOD_SIGNATURE..MOD_SIGNATURE + 4], b"M.K.");
+        // the marker is skipped and the end of the song stops the order table
+        assert_eq!(module[MOD_SONG_LENGTH], 2);
+        assert_eq!(&module[MOD_ORDERS..MOD_ORDERS + 3], &[0, 0, 0]);
+        assert_eq!(mod_sample_info(&module, 0), (6, 48, 2, 4));
+        assert_eq!(mod_sample_info(&module, 1), (0, 0, 0, 2));
+        assert_eq!(module.len(), MOD_PATTERNS + ROWS * 4 * 4 + 6);
+        assert_eq!(
+            &module[module.len() - 6..],
+            &[0x80, 0x90, 0xA0, 0xB0, 0xC0, 0xD0]
+        );
+
+        let note = mod_cell(&module, 4, 0, 0, 0);
+        assert_eq!((note.sample, note.period), (1, 428));
+        // the initial speed and tempo fill the first free effects
+        assert_eq!((note.effect, note.param), (0x0F, 4));
+        let volume = mod_cell(&module, 4, 0, 0, 1);
+        assert_eq!((volume.effect, volume.param), (0x0C, 32));
+        let timing = mod_cell(&module, 4, 0, 0, 2);
+        assert_eq!((timing.effect, timing.param), (0x0F, 150));
+
+        let off = mod_cell(&module, 4, 0, 1, 0);
+        assert_eq!((off.period, off.effect, off.param), (0, 0x0E, 0xC0));
+        let slide = mod_cell(&module, 4, 0, 1, 1);
+        assert_eq!((slide.effect, slide.param), (0x0A, 0x30));
+    }
+
+    #[test]
+    fn s3m_unsigned_samples_become_signed() {
+        let module = s3m_to_mod(&s3m(&[0], &[], true)).unwrap();
+        assert_eq!(
+            &module[module.len() - 6..],
+            &[0x00, 0x10, 0x20, 0x30, 0x40, 0x50]
+        );
+    }
+
+    #[test]
+    fn s3m_effects() {
+        assert_eq!(s3m_effect(1, 0), Some((0x0F, 1)));
+        assert_eq!(s3m_effect(1, 99), Some((0x0F, 31)));
+        assert_eq!(s3m_effect(4, 0x3F), Some((0x0E, 0xA3)));
+        assert_eq!(s3m_effect(4, 0xF3), Some((0x0E, 0xB3)));
+        assert_eq!(s3m_effect(4, 0x0F), Some((0x0A, 0x0F)));
+        assert_eq!(s3m_effect(5, 0xE4), Some((0x0E, 0x24)));
+        assert_eq!(s3m_effect(6, 0x12), Some((0x01, 0x12)));
+        assert_eq!(s3m_effect(19, 0x84), Some((0x08, 68)));
+        assert_eq!(s3m_effect(19, 0xD2), Some((0x0E, 0xD2)));
+        assert_eq!(s3m_effect(20, 16), Some((0x0F, 32)));
+        // Ixy: tremor has no MOD equivalent
+        assert_eq!(s3m_effect(9, 0x11), None);
+    }
+
+    #[test]
+    fn s3m_rejects_invalid_modules() {
+        assert!(matches!(s3m_to_mod(&[]), Err(FileError::InvalidData(_))));
+        let mut data = s3m(&[0], &[], false);
+        data[0x2C] = b'X';
+        assert!(matches!(s3m_to_mod(&data), Err(FileError::InvalidData(_))));
+        // no orders to play, or only orders of missing patterns
+        for orders in [&[255, 0][..], &[3]] {
+            let data = s3m(orders, &[], false);
+            assert!(matches!(s3m_to_mod(&data), Err(FileError::InvalidData(_))));
+        }
+    }
+
+    /// Builds an XM module with two channels,
+    /// one pattern with the given number of rows and packed data,
+    /// and one instrument with an 8-bit delta encoded sample.
+    fn xm(rows: u16, packed: &[u8], deltas: &[u8], relative_note: i8) -> Vec<u8> {
+        let mut data = b"Extended Module: ".to_vec();
+        data.resize(60, 0);
+        data[37] = 0x1A;
+        data[58] = 4;
+        data[59] = 1;
+        data.extend(276u32.to_le_bytes());
+        // song length, restart, channels, patterns, instruments, flags
+        for value in [1u16, 0, 2, 1, 1, 1, 6, 125] {
+            data.extend(value.to_le_bytes());
+        }
+        // order table
+        data.extend([0; 256]);
+
+        data.extend(9u32.to_le_bytes());
+        data.push(0);
+        data.extend(rows.to_le_bytes());
+        data.extend((packed.len() as u16).to_le_bytes());
+        data.extend(packed);
+
+        let instrument = data.len();
+        data.resize(instrument + 263, 0);
+        data[instrument..instrument + 4].copy_from_slice(&263u32.to_le_bytes());
+        data[instrument + 27] = 1;
+        data[instrument + 29] = 40;
+
+        let mut sample = [0; 40];
+        sample[0] = deltas.len() as u8;
+        // ping-pong loop over the whole sample
+        sample[8] = deltas.len() as u8;
+        sample[12] = 40;
+        sample[13] = 0;
+        sample[14] = 2;
+        sample[16] = relative_note as u8;
+        data.extend(sample);
+        data.extend(deltas);
+        data
+    }
+
+    #[test]
+    fn xm_converts_notes_samples_and_orders() {
+        // row 0: C-4 with instrument 1 and volume 0x30 on channel 0,
+        // a packed arpeggio on channel 1
+        let packed = [49, 1, 0x40, 0, 0, 0x80 | 0x18, 0, 0x37];
+        let module = xm_to_mod(&xm(1, &packed, &[1, 1, 1, 0xFD], 12)).unwrap();
+
+        assert_eq!(&module[MOD_SIGNATURE..MOD_SIGNATURE + 4], b"M.K.");
+        assert_eq!(module[MOD_SONG_LENGTH], 1);
+        assert_eq!(mod_sample_info(&module, 0), (4, 40, 0, 4));
+        assert_eq!(&module[module.len() - 4..], &[1, 2, 3, 0]);
+
+        let note = mod_cell(&module, 4, 0, 0, 0);
+        // one octave above C-4 because of the relative note
+        assert_eq!((note.sample, note.period), (1, 214));
+        assert_eq!((note.effect, note.param), (0x0C, 0x30));
+        let arpeggio = mod_cell(&module, 4, 0, 0, 1);
+        assert_eq!((arpeggio.effect, arpeggio.param), (0x00, 0x37));
+        // a one row pattern ends with a pattern break,
+        // and the timing goes to the first free cells
+        let end = mod_cell(&module, 4, 0, 0, 2);
+        assert_eq!((end.effect, end.param), (0x0D, 0));
+        let speed = mod_cell(&module, 4, 0, 0, 3);
+        assert_eq!((speed.effect, speed.param), (0x0F, 6));
+    }
+
+    #[test]
+    fn xm_long_patterns_are_split() {
+        // 70 empty rows on two channels
+        let packed = [0x80; 140];
+        let module = xm_to_mod(&xm(70, &packed, &[0, 0], 0)).unwrap();
+        assert_eq!(module[MOD_SONG_LENGTH], 2);
+        assert_eq!(&module[MOD_ORDERS..MOD_ORDERS + 2], &[0, 1]);
+        // the second part ends after 6 rows
+        let end = mod_cell(&module, 4, 1, 5, 0);
+        assert_eq!((end.effect, end.param), (0x0D, 0));
+        assert_eq!(mod_cell(&module, 4, 1, 6, 0).effect, 0);
+        assert_eq!(module.len(), MOD_PATTERNS + 2 * ROWS * 4 * 4 + 2);
+    }
+
+    #[test]
+    fn xm_rejects_invalid_modules() {
+        assert!(matches!(xm_to_mod(&[]), Err(FileError::InvalidData(_))));
+        let mut data = xm(1, &[0x80, 0x80], &[0, 0], 0);
+        data[0] = b'e';
+        assert!(matches!(xm_to_mod(&data), Err(FileError::InvalidData(_))));
+        // song length of 0
+        let mut data = xm(1, &[0x80, 0x80], &[0, 0], 0);
+        data[64] = 0;
+        assert!(matches!(xm_to_mod(&data), Err(FileError::InvalidData(_))));
+    }
+
+    #[test]
+    fn periods_and_channel_counts() {
+        assert_eq!(period(0, 0), 1712);
+        assert_eq!(period(12, 0), 856);
+        assert_eq!(period(36, 0), 214);
+        assert_eq!(period(-100, 0), 4095);
+        assert_eq!(period(200, 0), 28);
+        assert_eq!(mod_channels(0), 4);
+        assert_eq!(mod_channels(5), 6);
+        assert_eq!(mod_channels(8), 8);
+        assert_eq!(mod_channels(40), MAX_CHANNELS);
+    }
+}
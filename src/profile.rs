@@ -0,0 +1,296 @@
+//! Measurement of where the time of each frame goes.
+//!
+//! Named scopes are timed with [`scope`],
+//! and their times are added up over each frame,
+//! along with the time spent waiting in [`wait_vbl`](crate::wait_vbl).
+//! A frame ends when `wait_vbl` returns,
+//! after which its times are available through [`last_frame`]
+//! and can be shown on screen with [`draw_overlay`].
+//!
+//! Up to [`MAX_SCOPES`] different names are tracked per frame,
+//! and the time of any other name is counted as `"other"`.
+//! Nested scopes are counted in full in each of them.
+//!
+//! Unless the `profiling` Cargo feature is enabled,
+//! all of these functions do nothing
+//! and no time is measured.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dos_like::*;
+//! while !shutting_down() {
+//!     wait_vbl();
+//!     {
+//!         let _scope = profile::scope("physics");
+//!         // update the world
+//!     }
+//!     {
+//!         let _scope = profile::scope("render");
+//!         // draw the world
+//!     }
+//!     profile::draw_overlay();
+//! }
+//! ```
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "profiling")]
+use std::sync::{Mutex, MutexGuard};
+
+#[cfg(feature = "profiling")]
+use crate::video::{
+    bar, current_video_mode, cursor_pos, draw_offset, get_color, goto_xy, put_str, set_color,
+    set_draw_offset, text_columns, TextAttr,
+};
+
+/// The time of a frame at 70 frames per second,
+/// the refresh rate of the emulated display.
+pub const FRAME_BUDGET: Duration = Duration::from_nanos(1_000_000_000 / 70);
+
+/// The maximum number of different scope names tracked per frame.
+pub const MAX_SCOPES: usize = 16;
+
+/// The times measured in one frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FrameProfile {
+    scopes: [(&'static str, Duration); MAX_SCOPES],
+    scope_count: usize,
+    wait: Duration,
+    total: Duration,
+}
+
+impl FrameProfile {
+    const EMPTY: FrameProfile = FrameProfile {
+        scopes: [("", Duration::ZERO); MAX_SCOPES],
+        scope_count: 0,
+        wait: Duration::ZERO,
+        total: Duration::ZERO,
+    };
+
+    /// Gets the total time of each named scope in the frame,
+    /// in the order in which they were first entered.
+    pub fn scopes(&self) -> &[(&'static str, Duration)] {
+        &self.scopes[..self.scope_count]
+    }
+
+    /// Gets the time spent waiting in [`wait_vbl`](crate::wait_vbl).
+    pub fn wait(&self) -> Duration {
+        self.wait
+    }
+
+    /// Gets the time from the end of the previous frame to the end of this one.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Gets the fraction of the [`FRAME_BUDGET`] taken by the given time.
+    pub fn budget_fraction(time: Duration) -> f32 {
+        time.as_secs_f32() / FRAME_BUDGET.as_secs_f32()
+    }
+
+    #[cfg(feature = "profiling")]
+    fn add(&mut self, name: &'static str, time: Duration) {
+        let scopes = &mut self.scopes[..self.scope_count];
+        if let Some(entry) = scopes.iter_mut().find(|(n, _)| *n == name) {
+            entry.1 += time;
+        } else if self.scope_count < MAX_SCOPES - 1 {
+            self.scopes[self.scope_count] = (name, time);
+            self.scope_count += 1;
+        } else {
+            // the last entry takes any other name
+            self.scopes[MAX_SCOPES - 1].0 = "other";
+            self.scopes[MAX_SCOPES - 1].1 += time;
+            self.scope_count = MAX_SCOPES;
+        }
+    }
+}
+
+#[cfg(feature = "profiling")]
+#[derive(Debug)]
+struct State {
+    current: FrameProfile,
+    last: FrameProfile,
+    frame_start: Option<Instant>,
+}
+
+#[cfg(feature = "profiling")]
+static STATE: Mutex<State> = Mutex::new(State {
+    current: FrameProfile::EMPTY,
+    last: FrameProfile::EMPTY,
+    frame_start: None,
+});
+
+#[cfg(feature = "profiling")]
+fn lock() -> MutexGuard<'static, State> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A timer which adds the time until it is dropped
+/// to its named scope in the current frame,
+/// created with [`scope`].
+#[derive(Debug)]
+#[must_use = "the scope is measured until the value is dropped"]
+pub struct Scope {
+    #[cfg(feature = "profiling")]
+    name: &'static str,
+    #[cfg(feature = "profiling")]
+    start: Instant,
+}
+
+/// Starts timing a named scope,
+/// until the returned value is dropped.
+#[inline]
+pub fn scope(name: &'static str) -> Scope {
+    #[cfg(not(feature = "profiling"))]
+    let _ = name;
+    Scope {
+        #[cfg(feature = "profiling")]
+        name,
+        #[cfg(feature = "profiling")]
+        start: Instant::now(),
+    }
+}
+
+impl Drop for Scope {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(feature = "profiling")]
+        lock().current.add(self.name, self.start.elapsed());
+    }
+}
+
+/// Gets the times measured in the last complete frame.
+///
+/// Without the `profiling` feature, all times are zero.
+pub fn last_frame() -> FrameProfile {
+    #[cfg(feature = "profiling")]
+    {
+        lock().last
+    }
+    #[cfg(not(feature = "profiling"))]
+    {
+        FrameProfile::EMPTY
+    }
+}
+
+/// Marks the start of the wait in `wait_vbl`.
+#[inline]
+pub(crate) fn wait_started() -> Option<Instant> {
+    cfg!(feature = "profiling").then(Instant::now)
+}
+
+/// Ends the frame at the end of the wait in `wait_vbl`.
+#[inline]
+pub(crate) fn frame_finished(wait_start: Option<Instant>) {
+    #[cfg(feature = "profiling")]
+    if let Some(wait_start) = wait_start {
+        let now = Instant::now();
+        let mut state = lock();
+        state.current.wait += now - wait_start;
+        if let Some(start) = state.frame_start {
+            state.current.total = now - start;
+            state.last = state.current;
+        }
+        state.current = FrameProfile::EMPTY;
+        state.frame_start = Some(now);
+    }
+    #[cfg(not(feature = "profiling"))]
+    let _ = wait_start;
+}
+
+/// The colors of the scopes in the overlay, in order.
+#[cfg(feature = "profiling")]
+const SCOPE_COLORS: [u8; 6] = [4, 2, 1, 5, 6, 3];
+
+/// The color of the wait time in the overlay.
+#[cfg(feature = "profiling")]
+const WAIT_COLOR: u8 = 8;
+
+/// Draws the times of the last frame as a stacked bar
+/// at the top of the screen,
+/// with one segment per scope and one for the wait in `wait_vbl`,
+/// the whole width of the screen being the [`FRAME_BUDGET`].
+///
+/// In text mode the bar takes the first row,
+/// with each segment labeled with the start of the scope name.
+/// In graphics mode it is a 4 pixel tall bar.
+/// The colors are the first 16 palette entries.
+///
+/// Does nothing without the `profiling` feature.
+pub fn draw_overlay() {
+    #[cfg(feature = "profiling")]
+    {
+        let frame = last_frame();
+        let segments = frame
+            .scopes()
+            .iter()
+            .zip(SCOPE_COLORS.iter().cycle())
+            .map(|(&(name, time), &color)| (name, time, color))
+            .chain(std::iter::once(("wait", frame.wait(), WAIT_COLOR)));
+        if current_video_mode().is_graphics() {
+            draw_graphics(segments);
+        } else {
+            draw_text(segments);
+        }
+    }
+}
+
+/// Splits `width` units into segment widths proportional to their times,
+/// relative to the frame budget.
+#[cfg(feature = "profiling")]
+fn segment_widths(
+    segments: impl Iterator<Item = (&'static str, Duration, u8)>,
+    width: u16,
+) -> impl Iterator<Item = (&'static str, u16, u8)> {
+    let mut used = 0u16;
+    segments.map(move |(name, time, color)| {
+        let w = (FrameProfile::budget_fraction(time) * width as f32).round() as u16;
+        let w = w.min(width - used);
+        used += w;
+        (name, w, color)
+    })
+}
+
+#[cfg(feature = "profiling")]
+fn draw_text(segments: impl Iterator<Item = (&'static str, Duration, u8)>) {
+    let columns = text_columns();
+    let original = TextAttr::current();
+    let (cursor_x, cursor_y) = cursor_pos();
+    let mut x = 0;
+    goto_xy(0, 0);
+    for (name, w, color) in segment_widths(segments, columns) {
+        if w == 0 {
+            continue;
+        }
+        TextAttr::new(15, color).apply();
+        let label: String = name.chars().take(w as usize).collect();
+        put_str(format!("{:<width$}", label, width = w as usize));
+        x += w;
+    }
+    TextAttr::new(7, 0).apply();
+    // leave the last cell alone so that the screen does not scroll
+    if x + 1 < columns {
+        put_str(" ".repeat((columns - x - 1) as usize));
+    }
+    original.apply();
+    goto_xy(cursor_x, cursor_y);
+}
+
+#[cfg(feature = "profiling")]
+fn draw_graphics(segments: impl Iterator<Item = (&'static str, Duration, u8)>) {
+    let width = crate::video::screen_width();
+    let offset = draw_offset();
+    let color = get_color();
+    set_draw_offset(0, 0);
+    set_color(0);
+    bar(0, 0, width, 4);
+    let mut x = 0;
+    for (_, w, c) in segment_widths(segments, width) {
+        set_color(c);
+        bar(x as i32, 0, w, 4);
+        x += w;
+    }
+    set_color(color);
+    set_draw_offset(offset.0, offset.1);
+}
@@ -6,12 +6,88 @@ use std::{
     ffi::CString,
     os::raw::{c_int, c_short, c_uint},
     ptr::NonNull,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use dos_like_sys::sound_t;
 
 use crate::FileError;
 
+#[cfg(any(feature = "ogg", feature = "flac"))]
+mod decode;
+mod wav;
+
+/// Decoded audio,
+/// in signed 16-bit samples.
+#[derive(Debug)]
+pub(crate) struct AudioData {
+    /// The number of channels (1 or 2).
+    pub(crate) channels: u32,
+    /// The sample rate in Hz.
+    pub(crate) sample_rate: u32,
+    /// The samples, interleaved by channel.
+    pub(crate) samples: Vec<i16>,
+}
+
+impl AudioData {
+    /// Resamples the audio to the given sample rate,
+    /// by linear interpolation.
+    fn resample(self, sample_rate: u32) -> AudioData {
+        if sample_rate == self.sample_rate || self.samples.is_empty() {
+            return self;
+        }
+        let channels = self.channels as usize;
+        let frames = self.samples.len() / channels;
+        let new_frames =
+            (frames as u64 * sample_rate as u64 / self.sample_rate as u64).max(1) as usize;
+        let step = self.sample_rate as f64 / sample_rate as f64;
+        let mut samples = Vec::with_capacity(new_frames * channels);
+        for i in 0..new_frames {
+            let pos = i as f64 * step;
+            let frame = (pos as usize).min(frames - 1);
+            let next = (frame + 1).min(frames - 1);
+            let t = pos - frame as f64;
+            for c in 0..channels {
+                let a = self.samples[frame * channels + c] as f64;
+                let b = self.samples[next * channels + c] as f64;
+                samples.push((a + (b - a) * t).round() as i16);
+            }
+        }
+        AudioData {
+            channels: self.channels,
+            sample_rate,
+            samples,
+        }
+    }
+
+    /// Creates a sound from the audio,
+    /// resampled to the given sample rate
+    /// or to the nearest one supported by the engine.
+    fn into_sound(self, sample_rate: Option<u32>) -> Result<Sound, FileError> {
+        let sample_rate = sample_rate.unwrap_or(self.sample_rate).clamp(1000, 44100);
+        let audio = self.resample(sample_rate);
+        let p = unsafe {
+            dos_like_sys::createsound(
+                audio.channels as c_int,
+                audio.sample_rate as c_int,
+                (audio.samples.len() / audio.channels as usize) as c_int,
+                audio.samples.as_ptr() as *mut c_short,
+            )
+        };
+        let info = SoundInfo {
+            sample_rate: audio.sample_rate,
+            frames: (audio.samples.len() / audio.channels as usize) as u32,
+        };
+        NonNull::new(p)
+            .map(|p| Sound(p, Some(info)))
+            .ok_or_else(|| FileError::InvalidData("the engine rejected the sound".into()))
+    }
+}
+
 /// The total number of sound channels supported by the engine.
 pub const SOUND_CHANNELS: u32 = dos_like_sys::SOUND_CHANNELS;
 
@@ -21,6 +97,7 @@ pub const SOUND_CHANNELS: u32 = dos_like_sys::SOUND_CHANNELS;
 /// to the `soundmode_t` enum in the original framework,
 /// with idiomatic naming.
 #[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(u32)]
 pub enum SoundMode {
     /// 8-bit mono, 5000 Hz
@@ -81,19 +158,38 @@ pub enum SoundMode {
     Stereo16Bit44100 = dos_like_sys::soundmode_t_soundmode_16bit_stereo_44100,
 }
 
+/// The sound mode last set through this crate.
+static SOUND_MODE: Mutex<Option<SoundMode>> = Mutex::new(None);
+
 /// Sets the application sound mode.
 pub fn set_sound_mode(sound_mode: SoundMode) {
     unsafe {
         dos_like_sys::setsoundmode(sound_mode as c_uint);
     }
+    *SOUND_MODE.lock().unwrap_or_else(|e| e.into_inner()) = Some(sound_mode);
+}
+
+/// Gets the sound mode last set via [`set_sound_mode`],
+/// or `None` if the engine is still in its default sound mode.
+pub fn sound_mode() -> Option<SoundMode> {
+    *SOUND_MODE.lock().unwrap_or_else(|e| e.into_inner())
 }
 
 /// A sound object.
 ///
 /// This is a wrapper around the [`dos_like_sys::sound_t`] struct.
 #[derive(Debug)]
-#[repr(transparent)]
-pub struct Sound(NonNull<sound_t>);
+pub struct Sound(NonNull<sound_t>, Option<SoundInfo>);
+
+/// The properties of a sound
+/// which the engine does not expose.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct SoundInfo {
+    /// The sample rate in Hz.
+    sample_rate: u32,
+    /// The number of samples per channel.
+    frames: u32,
+}
 
 impl Sound {
     /// Loads a new sound from a file.
@@ -104,12 +200,31 @@ impl Sound {
         load_wav(path)
     }
 
+    /// Loads a new sound from an Ogg Vorbis file.
+    ///
+    /// This is equivalent to the module's [`load_ogg`] function.
+    #[cfg(feature = "ogg")]
+    #[inline]
+    pub fn load_ogg(path: impl AsRef<str>) -> Result<Sound, FileError> {
+        load_ogg(path)
+    }
+
+    /// Loads a new sound from a FLAC file.
+    ///
+    /// This is equivalent to the module's [`load_flac`] function.
+    #[cfg(feature = "flac")]
+    #[inline]
+    pub fn load_flac(path: impl AsRef<str>) -> Result<Sound, FileError> {
+        load_flac(path)
+    }
+
     /// Creates a new sound from a buffer.
     ///
     /// Note that this copies the samples internally,
     /// so there is effectively no lifetime dependency with the buffer.
     ///
-    /// Only 1 or 2 channels are supported.
+    /// Only 1 or 2 channels are supported,
+    /// with the samples interleaved by channel.
     /// The sample rate is a number in Hz,
     /// between 1000 and 44100.
     /// The number of samples should also not be zero.
@@ -127,23 +242,132 @@ impl Sound {
     pub fn play(&self, channel: u8, loop_: bool, volume: u8) {
         play_sound(channel, self, loop_, volume);
     }
+
+    /// Gets the sample rate of this sound in Hz.
+    ///
+    /// Returns `None` for WAV files which could only be loaded by the engine.
+    #[inline]
+    pub fn sample_rate(&self) -> Option<u32> {
+        self.1.map(|info| info.sample_rate)
+    }
+
+    /// Gets the length of this sound in samples per channel.
+    ///
+    /// Returns `None` for WAV files which could only be loaded by the engine.
+    #[inline]
+    pub fn frame_count(&self) -> Option<u32> {
+        self.1.map(|info| info.frames)
+    }
+
+    /// Gets the duration of this sound.
+    ///
+    /// Returns `None` for WAV files which could only be loaded by the engine.
+    pub fn duration(&self) -> Option<Duration> {
+        self.1
+            .map(|info| frames_to_duration(info.frames as u64, info.sample_rate))
+    }
 }
 
 unsafe impl Send for Sound {}
 
-/// Loads a new sound from a file.
+/// Loads a new sound from a WAV file.
+///
+/// Besides what the engine supports,
+/// this accepts integer PCM with 8, 16, 24 or 32 bits per sample,
+/// 32-bit floating point PCM, and IMA ADPCM,
+/// in mono or stereo.
+/// The samples are converted to 16 bits,
+/// and resampled if the sample rate is not between 1000 and 44100 Hz.
+///
+/// Files in other formats result in [`FileError::InvalidData`].
 pub fn load_wav(path: impl AsRef<str>) -> Result<Sound, FileError> {
-    let path = CString::new(path.as_ref()).map_err(|_| FileError::BadFilePath)?;
-    let p = unsafe { dos_like_sys::loadwav(path.as_ptr() as *const _) };
-    if let Some(p) = NonNull::new(p) {
-        Ok(Sound(p))
-    } else {
-        Err(FileError::FileNotFound)
+    let path = path.as_ref();
+    let cpath = CString::new(path).map_err(|_| FileError::BadFilePath)?;
+    let data = std::fs::read(path).map_err(|_| FileError::FileNotFound)?;
+
+    match wav::parse(&data) {
+        Ok(audio) => audio.into_sound(None),
+        Err(e) => {
+            // let the engine try it anyway
+            let p = unsafe { dos_like_sys::loadwav(cpath.as_ptr() as *const _) };
+            NonNull::new(p).map(|p| Sound(p, None)).ok_or(e)
+        }
     }
 }
+
+/// Loads a new sound from an Ogg Vorbis file,
+/// keeping its sample rate
+/// (or the nearest one supported, between 1000 and 44100 Hz).
+///
+/// The file is decoded in full,
+/// not streamed,
+/// so the sound takes 2 bytes per sample per channel in memory
+/// (about 10 MiB for 1 minute of stereo audio at 44100 Hz).
+/// Consider [`load_ogg_with_rate`] to save memory on long sounds.
+///
+/// Requires the `ogg` feature.
+#[cfg(feature = "ogg")]
+pub fn load_ogg(path: impl AsRef<str>) -> Result<Sound, FileError> {
+    load_ogg_impl(path.as_ref(), None)
+}
+
+/// Loads a new sound from an Ogg Vorbis file,
+/// resampled to the given sample rate in Hz
+/// (between 1000 and 44100).
+///
+/// See [`load_ogg`] for the memory usage of the sound.
+///
+/// Requires the `ogg` feature.
+#[cfg(feature = "ogg")]
+pub fn load_ogg_with_rate(path: impl AsRef<str>, sample_rate: u32) -> Result<Sound, FileError> {
+    load_ogg_impl(path.as_ref(), Some(sample_rate))
+}
+
+#[cfg(feature = "ogg")]
+fn load_ogg_impl(path: &str, sample_rate: Option<u32>) -> Result<Sound, FileError> {
+    let file = std::fs::File::open(path).map_err(|_| FileError::FileNotFound)?;
+    decode::decode_ogg(std::io::BufReader::new(file))?.into_sound(sample_rate)
+}
+
+/// Loads a new sound from a FLAC file,
+/// keeping its sample rate
+/// (or the nearest one supported, between 1000 and 44100 Hz).
+///
+/// The file is decoded in full,
+/// not streamed,
+/// so the sound takes 2 bytes per sample per channel in memory
+/// (about 10 MiB for 1 minute of stereo audio at 44100 Hz).
+/// Consider [`load_flac_with_rate`] to save memory on long sounds.
+/// Samples with more than 16 bits are reduced to 16 bits.
+///
+/// Requires the `flac` feature.
+#[cfg(feature = "flac")]
+pub fn load_flac(path: impl AsRef<str>) -> Result<Sound, FileError> {
+    load_flac_impl(path.as_ref(), None)
+}
+
+/// Loads a new sound from a FLAC file,
+/// resampled to the given sample rate in Hz
+/// (between 1000 and 44100).
+///
+/// See [`load_flac`] for the memory usage of the sound.
+///
+/// Requires the `flac` feature.
+#[cfg(feature = "flac")]
+pub fn load_flac_with_rate(path: impl AsRef<str>, sample_rate: u32) -> Result<Sound, FileError> {
+    load_flac_impl(path.as_ref(), Some(sample_rate))
+}
+
+#[cfg(feature = "flac")]
+fn load_flac_impl(path: &str, sample_rate: Option<u32>) -> Result<Sound, FileError> {
+    let file = std::fs::File::open(path).map_err(|_| FileError::FileNotFound)?;
+    decode::decode_flac(std::io::BufReader::new(file))?.into_sound(sample_rate)
+}
+
 /// Creates a new sound from a buffer.
 ///
-/// Only 1 or 2 channels are supported.
+/// Only 1 or 2 channels are supported,
+/// with the samples interleaved by channel.
 /// The sample rate is a number in Hz,
 /// between 1000 and 44100.
 /// The number of samples should also not be zero.
@@ -159,26 +383,37 @@ pub fn create_sound(channels: u32, sample_rate: u32, samples: &[u16]) -> Sound {
 /// Creates a new sound from a buffer,
 /// returning `None` if the given parameters are invalid for a sound.
 ///
-/// Only 1 or 2 channels are supported.
+/// Only 1 or 2 channels are supported,
+/// with the samples interleaved by channel.
 /// The sample rate is a number in Hz,
 /// between 1000 and 44100.
 /// The number of samples should also not be zero.
 pub fn try_create_sound(channels: u32, sample_rate: u32, samples: &[u16]) -> Option<Sound> {
+    let info = SoundInfo {
+        sample_rate,
+        frames: (samples.len() / channels.max(1) as usize) as u32,
+    };
     // safety: although we're passing a *mut,
     // nothing is ever written to samples
     unsafe {
         let sound = dos_like_sys::createsound(
             channels as c_int,
             sample_rate as c_int,
-            samples.len() as c_int,
+            info.frames as c_int,
             samples.as_ptr() as *mut c_short,
         );
-        NonNull::new(sound).map(Sound)
+        NonNull::new(sound).map(|p| Sound(p, Some(info)))
     }
 }
 
 /// Plays the sound specified.
+///
+/// This cancels any volume ramp in the channel.
 pub fn play_sound(channel: u8, sound: &Sound, loop_: bool, volume: u8) {
+    set_channel_state(channel, (volume, volume));
+    if let Some(state) = lock_channels().get_mut(channel as usize) {
+        state.playback = Playback::start(sound, loop_);
+    }
     unsafe {
         dos_like_sys::playsound(
             channel as c_int,
@@ -190,7 +425,12 @@ pub fn play_sound(channel: u8, sound: &Sound, loop_: bool, volume: u8) {
 }
 
 /// Stops any sound currently playing in the given channel.
+///
+/// This cancels any volume ramp in the channel,
+/// and also the queued sound unless disabled
+/// through [`set_stop_clears_queue`].
 pub fn stop_sound(channel: u8) {
+    stop_channel_state(channel);
     unsafe {
         dos_like_sys::stopsound(channel as c_int);
     }
@@ -202,8 +442,741 @@ pub fn is_sound_playing(channel: u8) -> bool {
 }
 
 /// Sets the stereo volume of a channel.
+///
+/// This cancels any volume ramp in the channel.
 pub fn set_sound_volume(channel: u8, left: u8, right: u8) {
+    set_channel_state(channel, (left, right));
     unsafe {
         dos_like_sys::soundvolume(channel as c_int, left as c_int, right as c_int);
     }
 }
+
+/// Gets the stereo volume of a channel,
+/// as last set by [`play_sound`], [`set_sound_volume`]
+/// or a volume ramp.
+///
+/// Returns `(0, 0)` for channels out of range.
+pub fn sound_volume(channel: u8) -> (u8, u8) {
+    lock_channels()
+        .get(channel as usize)
+        .map(|state| state.volume)
+        .unwrap_or_default()
+}
+
+// -- volume ramps
+
+/// A gradual change of the volume of a sound channel,
+/// as started by [`fade_channel`] or [`fade_out_and_stop`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct VolumeRamp {
+    from: (u8, u8),
+    to: (u8, u8),
+    start: Instant,
+    duration: Duration,
+    stop_at_end: bool,
+}
+
+impl VolumeRamp {
+    /// Gets the stereo volume at the start of the ramp.
+    #[inline]
+    pub fn from(&self) -> (u8, u8) {
+        self.from
+    }
+
+    /// Gets the stereo volume at the end of the ramp.
+    #[inline]
+    pub fn to(&self) -> (u8, u8) {
+        self.to
+    }
+
+    /// Gets the total duration of the ramp.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Gets the time elapsed since the ramp started.
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Gets how far along the ramp is, between 0 and 1.
+    pub fn progress(&self) -> f32 {
+        if self.duration.is_zero() {
+            return 1.;
+        }
+        (self.elapsed().as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+    }
+
+    /// Checks whether the channel is stopped at the end of the ramp.
+    #[inline]
+    pub fn stops_at_end(&self) -> bool {
+        self.stop_at_end
+    }
+
+    /// Gets the stereo volume at the current point of the ramp.
+    pub fn current(&self) -> (u8, u8) {
+        let t = self.progress();
+        let lerp = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        (lerp(self.from.0, self.to.0), lerp(self.from.1, self.to.1))
+    }
+}
+
+/// The state of a sound channel kept on the Rust side.
+#[derive(Debug)]
+struct ChannelState {
+    /// The last volume set.
+    volume: (u8, u8),
+    /// The volume ramp in progress.
+    ramp: Option<VolumeRamp>,
+    /// The sound to play when the channel frees up.
+    queued: Option<(Sound, PlayOptions)>,
+    /// Whether the queued sound started since it was last checked.
+    queued_started: bool,
+    /// The sound last started, if its properties are known.
+    playback: Option<Playback>,
+}
+
+impl ChannelState {
+    const IDLE: ChannelState = ChannelState {
+        volume: (255, 255),
+        ramp: None,
+        queued: None,
+        queued_started: false,
+        playback: None,
+    };
+}
+
+static CHANNELS: Mutex<[ChannelState; SOUND_CHANNELS as usize]> =
+    Mutex::new([ChannelState::IDLE; SOUND_CHANNELS as usize]);
+
+fn lock_channels() -> std::sync::MutexGuard<'static, [ChannelState; SOUND_CHANNELS as usize]> {
+    CHANNELS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Records a new volume for a channel, cancelling its ramp.
+fn set_channel_state(channel: u8, volume: (u8, u8)) {
+    if let Some(state) = lock_channels().get_mut(channel as usize) {
+        state.volume = volume;
+        state.ramp = None;
+    }
+}
+
+/// Cancels the ramp of a channel being stopped,
+/// and its queued sound if so configured.
+fn stop_channel_state(channel: u8) {
+    let clear_queue = STOP_CLEARS_QUEUE.load(Ordering::Relaxed);
+    if let Some(state) = lock_channels().get_mut(channel as usize) {
+        state.ramp = None;
+        state.playback = None;
+        if clear_queue {
+            state.queued = None;
+        }
+    }
+}
+
+fn start_ramp(channel: u8, to: (u8, u8), duration: Duration, stop_at_end: bool) {
+    if let Some(state) = lock_channels().get_mut(channel as usize) {
+        state.ramp = Some(VolumeRamp {
+            from: state.volume,
+            to,
+            start: Instant::now(),
+            duration,
+            stop_at_end,
+        });
+    }
+}
+
+/// Gradually changes the stereo volume of a channel
+/// from its current volume to the given one,
+/// over the given duration.
+///
+/// The ramp is advanced on every call to [`wait_vbl`](crate::wait_vbl),
+/// so the volume changes in steps of one frame.
+/// Playing a sound on the channel, stopping it,
+/// or setting its volume directly cancels the ramp.
+pub fn fade_channel(channel: u8, target_left: u8, target_right: u8, duration: Duration) {
+    start_ramp(channel, (target_left, target_right), duration, false);
+}
+
+/// Gradually lowers the volume of a channel to zero
+/// over the given duration,
+/// then stops the sound in the channel.
+///
+/// This avoids the click of stopping a sound abruptly.
+/// See [`fade_channel`] for how the ramp is advanced.
+pub fn fade_out_and_stop(channel: u8, duration: Duration) {
+    start_ramp(channel, (0, 0), duration, true);
+}
+
+/// Gets the volume ramp in progress in a channel, if any.
+pub fn channel_ramp(channel: u8) -> Option<VolumeRamp> {
+    lock_channels()
+        .get(channel as usize)
+        .and_then(|state| state.ramp)
+}
+
+// -- sound queue
+
+/// Options for playing a sound.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct PlayOptions {
+    /// Whether the sound plays in a loop.
+    pub looping: bool,
+    /// The volume of the sound.
+    pub volume: u8,
+}
+
+impl Default for PlayOptions {
+    #[inline]
+    fn default() -> Self {
+        PlayOptions::new()
+    }
+}
+
+impl PlayOptions {
+    /// Creates options for playing a sound once at full volume.
+    #[inline]
+    pub const fn new() -> Self {
+        PlayOptions {
+            looping: false,
+            volume: 255,
+        }
+    }
+
+    /// Sets whether the sound plays in a loop.
+    #[inline]
+    pub const fn with_looping(self, looping: bool) -> Self {
+        PlayOptions { looping, ..self }
+    }
+
+    /// Sets the volume of the sound.
+    #[inline]
+    pub const fn with_volume(self, volume: u8) -> Self {
+        PlayOptions { volume, ..self }
+    }
+}
+
+/// Whether stopping a channel also clears its queued sound.
+static STOP_CLEARS_QUEUE: AtomicBool = AtomicBool::new(true);
+
+/// Queues a sound to start playing in a channel
+/// as soon as the sound currently playing there finishes.
+///
+/// If the channel is free, the sound starts right away.
+/// Otherwise, the channel is checked on every call to [`wait_vbl`](crate::wait_vbl),
+/// and the sound starts on the first frame that the channel is free.
+/// Use [`queued_started`] to find out when that happens.
+///
+/// Each channel holds a single queued sound,
+/// so this replaces any sound previously queued in the channel.
+/// A sound playing in a loop never finishes,
+/// unless stopped or faded out with [`fade_out_and_stop`].
+pub fn queue_sound(channel: u8, sound: &Sound, options: PlayOptions) {
+    let mut channels = lock_channels();
+    let Some(state) = channels.get_mut(channel as usize) else {
+        return;
+    };
+    state.queued = Some((Sound(sound.0, sound.1), options));
+    if !is_sound_playing(channel) {
+        start_queued(channel, state);
+    }
+}
+
+/// Checks whether a channel has a sound queued
+/// which did not start yet.
+pub fn has_queued_sound(channel: u8) -> bool {
+    lock_channels()
+        .get(channel as usize)
+        .is_some_and(|state| state.queued.is_some())
+}
+
+/// Removes the sound queued in a channel, if any,
+/// without affecting the sound currently playing.
+pub fn clear_queued_sound(channel: u8) {
+    if let Some(state) = lock_channels().get_mut(channel as usize) {
+        state.queued = None;
+    }
+}
+
+/// Checks whether the sound queued in a channel has started playing
+/// since the last call to this function for the same channel.
+pub fn queued_started(channel: u8) -> bool {
+    lock_channels()
+        .get_mut(channel as usize)
+        .is_some_and(|state| std::mem::take(&mut state.queued_started))
+}
+
+/// Sets whether [`stop_sound`] also removes the sound queued in the channel.
+///
+/// This is enabled by default.
+/// When disabled, stopping a channel
+/// lets the queued sound start on the next frame.
+pub fn set_stop_clears_queue(enabled: bool) {
+    STOP_CLEARS_QUEUE.store(enabled, Ordering::Relaxed);
+}
+
+/// Starts the sound queued in a channel, if any.
+fn start_queued(channel: u8, state: &mut ChannelState) {
+    let Some((sound, options)) = state.queued.take() else {
+        return;
+    };
+    state.volume = (options.volume, options.volume);
+    state.ramp = None;
+    state.queued_started = true;
+    state.playback = Playback::start(&sound, options.looping);
+    unsafe {
+        dos_like_sys::playsound(
+            channel as c_int,
+            sound.0.as_ptr(),
+            options.looping as c_int,
+            options.volume as c_int,
+        );
+    }
+}
+
+/// Advances all volume ramps in progress
+/// and starts the queued sounds of channels which became free,
+/// called once per frame.
+pub(crate) fn update_channels() {
+    let mut channels = lock_channels();
+    for (channel, state) in channels.iter_mut().enumerate() {
+        if let Some(ramp) = state.ramp {
+            let finished = ramp.progress() >= 1.;
+            let (left, right) = if finished { ramp.to } else { ramp.current() };
+            state.volume = (left, right);
+            unsafe {
+                dos_like_sys::soundvolume(channel as c_int, left as c_int, right as c_int);
+                if finished && ramp.stop_at_end {
+                    dos_like_sys::stopsound(channel as c_int);
+                }
+            }
+            if finished {
+                state.ramp = None;
+            }
+        }
+        if state.queued.is_some() && !is_sound_playing(channel as u8) {
+            start_queued(channel as u8, state);
+        }
+    }
+}
+
+/// Sets the engine volume of every channel to zero,
+/// or back to the volume last set,
+/// without changing the volume recorded for the channel.
+///
+/// Used to mute the audio while the window is not focused.
+pub(crate) fn silence_channels(silent: bool) {
+    let channels = lock_channels();
+    for (channel, state) in channels.iter().enumerate() {
+        let (left, right) = if silent { (0, 0) } else { state.volume };
+        unsafe {
+            dos_like_sys::soundvolume(channel as c_int, left as c_int, right as c_int);
+        }
+    }
+}
+
+// -- playback position
+
+/// A sound started in a channel.
+#[derive(Debug, Copy, Clone)]
+struct Playback {
+    start: Instant,
+    info: SoundInfo,
+    looping: bool,
+}
+
+impl Playback {
+    fn start(sound: &Sound, looping: bool) -> Option<Playback> {
+        sound.1.map(|info| Playback {
+            start: Instant::now(),
+            info,
+            looping,
+        })
+    }
+}
+
+fn frames_to_duration(frames: u64, sample_rate: u32) -> Duration {
+    Duration::from_nanos(frames * 1_000_000_000 / sample_rate.max(1) as u64)
+}
+
+/// Gets the position of the sound playing in a channel,
+/// in samples per channel from the start of the sound.
+///
+/// Returns `None` if the channel is not playing anything,
+/// or if the properties of the sound are unknown
+/// (see [`Sound::sample_rate`]).
+///
+/// The engine does not expose the position of its mixer,
+/// so this is estimated from the time since the sound started
+/// and the sample rate of the sound.
+/// The estimate runs ahead of what is heard
+/// by the latency of the audio output,
+/// which is typically within a few tens of milliseconds,
+/// and does not account for the sound being delayed by the engine.
+/// Looping sounds wrap back to 0 at the end of the sound.
+pub fn position_samples(channel: u8) -> Option<u64> {
+    let playback = lock_channels().get(channel as usize)?.playback?;
+    if !is_sound_playing(channel) {
+        return None;
+    }
+    Some(estimate_position(
+        playback.start.elapsed(),
+        playback.info,
+        playback.looping,
+    ))
+}
+
+/// Estimates the position in samples per channel
+/// of a sound which started playing `elapsed` ago.
+fn estimate_position(elapsed: Duration, info: SoundInfo, looping: bool) -> u64 {
+    let frames = (elapsed.as_nanos() * info.sample_rate as u128 / 1_000_000_000) as u64;
+    if looping {
+        frames % (info.frames as u64).max(1)
+    } else {
+        frames.min(info.frames as u64)
+    }
+}
+
+/// Gets the position of the sound playing in a channel,
+/// as the time from the start of the sound.
+///
+/// Returns `None` if the channel is not playing anything,
+/// or if the properties of the sound are unknown.
+///
+/// This is estimated in the same way as [`position_samples`].
+pub fn channel_position(channel: u8) -> Option<Duration> {
+    let sample_rate = lock_channels()
+        .get(channel as usize)?
+        .playback?
+        .info
+        .sample_rate;
+    position_samples(channel).map(|frames| frames_to_duration(frames, sample_rate))
+}
+
+// -- scheduled actions
+
+/// How long to wait before running a scheduled audio action,
+/// see [`schedule`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum AudioDelay {
+    /// A number of frames,
+    /// counted by the calls to [`wait_vbl`](crate::wait_vbl)
+    Frames(u32),
+    /// An amount of time,
+    /// checked on every call to [`wait_vbl`](crate::wait_vbl)
+    Time(Duration),
+}
+
+impl From<Duration> for AudioDelay {
+    #[inline]
+    fn from(time: Duration) -> Self {
+        AudioDelay::Time(time)
+    }
+}
+
+/// An action on the sound channels or the music synthesizer
+/// to run at a later frame, see [`schedule`].
+#[derive(Debug)]
+pub enum AudioAction {
+    /// Play a sound in the given channel,
+    /// or in the first free channel if `None`
+    /// (the sound is not played if no channel is free)
+    Play {
+        /// The sound to play
+        sound: Sound,
+        /// The sound channel
+        channel: Option<u8>,
+        /// How to play the sound
+        options: PlayOptions,
+    },
+    /// Stop the sound playing in a channel, as with [`stop_sound`]
+    Stop {
+        /// The sound channel
+        channel: u8,
+    },
+    /// Push a note on a music channel, as with [`note_on`](crate::note_on)
+    NoteOn {
+        /// The music channel
+        channel: u8,
+        /// The pitch of the note, between 0 and 127
+        note: u8,
+        /// The velocity of the note, between 0 and 127
+        velocity: u8,
+    },
+    /// Release a note on a music channel, as with [`note_off`](crate::note_off)
+    NoteOff {
+        /// The music channel
+        channel: u8,
+        /// The pitch of the note, between 0 and 127
+        note: u8,
+    },
+}
+
+impl AudioAction {
+    /// Creates an action to play the given sound.
+    ///
+    /// As with [`queue_sound`],
+    /// the sound is referred to without being copied,
+    /// so the sound given should still be alive when the action runs.
+    pub fn play(sound: &Sound, channel: Option<u8>, options: PlayOptions) -> Self {
+        AudioAction::Play {
+            sound: Sound(sound.0, sound.1),
+            channel,
+            options,
+        }
+    }
+
+    fn run(self) {
+        match self {
+            AudioAction::Play {
+                sound,
+                channel,
+                options,
+            } => {
+                let channel = channel.or_else(|| {
+                    (0..SOUND_CHANNELS as u8)
+                        .find(|&c| !is_sound_playing(c) && !has_queued_sound(c))
+                });
+                if let Some(channel) = channel {
+                    play_sound(channel, &sound, options.looping, options.volume);
+                }
+            }
+            AudioAction::Stop { channel } => stop_sound(channel),
+            AudioAction::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => crate::note_on(channel, note, velocity),
+            AudioAction::NoteOff { channel, note } => crate::note_off(channel, note),
+        }
+    }
+}
+
+/// Identifies a scheduled audio action,
+/// so that it can be cancelled with [`cancel`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct ScheduleHandle(u64);
+
+/// When a scheduled action is due.
+#[derive(Debug, Copy, Clone)]
+enum Due {
+    Frame(u64),
+    Time(Instant),
+}
+
+impl Due {
+    fn is_due(&self, frame: u64, now: Instant) -> bool {
+        match *self {
+            Due::Frame(due) => frame >= due,
+            Due::Time(due) => now >= due,
+        }
+    }
+}
+
+/// An audio action waiting to run.
+#[derive(Debug)]
+struct Scheduled {
+    handle: ScheduleHandle,
+    due: Due,
+    action: AudioAction,
+}
+
+/// The scheduled actions which did not run yet,
+/// in the order they were scheduled.
+#[derive(Debug)]
+struct Schedule {
+    pending: Vec<Scheduled>,
+    next_handle: u64,
+}
+
+static SCHEDULE: Mutex<Schedule> = Mutex::new(Schedule {
+    pending: Vec::new(),
+    next_handle: 0,
+});
+
+fn lock_schedule() -> std::sync::MutexGuard<'static, Schedule> {
+    SCHEDULE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Schedules an action on the sound channels or the music synthesizer
+/// to run after the given delay,
+/// in frames or in time.
+///
+/// Scheduled actions run on the calls to [`wait_vbl`](crate::wait_vbl):
+/// an action delayed by `n` frames runs on the `n`-th call from now,
+/// and an action delayed by some time
+/// runs on the first call after that time has passed.
+/// Actions due on the same frame run in the order they were scheduled.
+/// An action without delay runs right away.
+///
+/// # Example
+///
+/// Telegraphing an attack with a short beep
+/// and playing the hit half a second later:
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use dos_like::*;
+/// let hit = load_wav("hit.wav")?;
+/// schedule(AudioDelay::Frames(0), AudioAction::NoteOn { channel: 0, note: 84, velocity: 100 });
+/// schedule(AudioDelay::Frames(4), AudioAction::NoteOff { channel: 0, note: 84 });
+/// let attack = schedule(
+///     Duration::from_millis(500),
+///     AudioAction::play(&hit, None, PlayOptions::new()),
+/// );
+/// // the player parried in time
+/// cancel(attack);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn schedule(delay: impl Into<AudioDelay>, action: AudioAction) -> ScheduleHandle {
+    let due = match delay.into() {
+        AudioDelay::Frames(0) => None,
+        AudioDelay::Frames(frames) => Some(Due::Frame(crate::frame_count() + frames as u64)),
+        AudioDelay::Time(time) if time.is_zero() => None,
+        AudioDelay::Time(time) => Some(Due::Time(Instant::now() + time)),
+    };
+    let mut schedule = lock_schedule();
+    let handle = ScheduleHandle(schedule.next_handle);
+    schedule.next_handle += 1;
+    match due {
+        Some(due) => schedule.pending.push(Scheduled {
+            handle,
+            due,
+            action,
+        }),
+        None => {
+            drop(schedule);
+            action.run();
+        }
+    }
+    handle
+}
+
+/// Cancels a scheduled audio action.
+///
+/// Returns `false` if the action already ran or was cancelled before.
+pub fn cancel(handle: ScheduleHandle) -> bool {
+    let mut schedule = lock_schedule();
+    let len = schedule.pending.len();
+    schedule.pending.retain(|s| s.handle != handle);
+    schedule.pending.len() != len
+}
+
+/// Checks whether a scheduled audio action is still waiting to run.
+pub fn is_scheduled(handle: ScheduleHandle) -> bool {
+    lock_schedule().pending.iter().any(|s| s.handle == handle)
+}
+
+/// Cancels all scheduled audio actions.
+pub fn clear_schedule() {
+    lock_schedule().pending.clear();
+}
+
+/// Runs the scheduled actions which are due,
+/// called once per frame.
+pub(crate) fn run_scheduled() {
+    let frame = crate::frame_count();
+    let now = Instant::now();
+    let due: Vec<Scheduled> = {
+        let mut schedule = lock_schedule();
+        if schedule.pending.is_empty() {
+            return;
+        }
+        let (due, pending) = std::mem::take(&mut schedule.pending)
+            .into_iter()
+            .partition(|s| s.due.is_due(frame, now));
+        schedule.pending = pending;
+        due
+    };
+    for scheduled in due {
+        scheduled.action.run();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second of sound at 11025 Hz.
+    const INFO: SoundInfo = SoundInfo {
+        sample_rate: 11025,
+        frames: 11025,
+    };
+
+    #[test]
+    fn position_advances_monotonically() {
+        let mut last = 0;
+        for ms in 0..=1000 {
+            let position = estimate_position(Duration::from_millis(ms), INFO, false);
+            assert!(position >= last, "went back at {} ms", ms);
+            last = position;
+        }
+        assert_eq!(estimate_position(Duration::ZERO, INFO, false), 0);
+        assert_eq!(
+            estimate_position(Duration::from_millis(500), INFO, false),
+            5512
+        );
+    }
+
+    #[test]
+    fn position_clamps_at_the_end() {
+        for ms in [1000, 1001, 5000, 3_600_000] {
+            assert_eq!(
+                estimate_position(Duration::from_millis(ms), INFO, false),
+                11025
+            );
+        }
+    }
+
+    #[test]
+    fn position_wraps_when_looping() {
+        let at = |ms| estimate_position(Duration::from_millis(ms), INFO, true);
+        assert_eq!(at(999), 11013);
+        assert_eq!(at(1000), 0);
+        assert_eq!(at(1500), 5512);
+        assert_eq!(at(2000), 0);
+        assert_eq!(at(60_250), 2756);
+    }
+
+    #[test]
+    fn position_of_empty_sounds() {
+        let info = SoundInfo {
+            sample_rate: 11025,
+            frames: 0,
+        };
+        assert_eq!(estimate_position(Duration::from_secs(1), info, false), 0);
+        assert_eq!(estimate_position(Duration::from_secs(1), info, true), 0);
+    }
+
+    #[test]
+    fn due_by_frame_or_time() {
+        let now = Instant::now();
+        assert!(!Due::Frame(5).is_due(4, now));
+        assert!(Due::Frame(5).is_due(5, now));
+        assert!(Due::Frame(5).is_due(6, now));
+
+        let later = now + Duration::from_millis(20);
+        assert!(!Due::Time(later).is_due(0, now));
+        assert!(Due::Time(later).is_due(0, later));
+        assert!(Due::Time(now).is_due(0, later));
+    }
+
+    #[test]
+    fn scheduled_actions_can_be_cancelled() {
+        let by_frames = schedule(AudioDelay::Frames(3), AudioAction::Stop { channel: 0 });
+        let by_time = schedule(Duration::from_secs(3600), AudioAction::Stop { channel: 1 });
+        assert_ne!(by_frames, by_time);
+        assert!(is_scheduled(by_frames));
+        assert!(is_scheduled(by_time));
+
+        assert!(cancel(by_frames));
+        assert!(!cancel(by_frames));
+        assert!(!is_scheduled(by_frames));
+        assert!(is_scheduled(by_time));
+
+        clear_schedule();
+        assert!(!is_scheduled(by_time));
+    }
+}
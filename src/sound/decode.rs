@@ -0,0 +1,68 @@
+//! Decoding of compressed audio formats with pure Rust decoders,
+//! each behind its own cargo feature.
+
+use crate::{sound::AudioData, FileError};
+
+/// Checks that the number of channels is supported by the engine.
+fn check_channels(channels: u32) -> Result<(), FileError> {
+    if (1..=2).contains(&channels) {
+        Ok(())
+    } else {
+        Err(FileError::InvalidData(format!(
+            "unsupported number of channels: {}",
+            channels
+        )))
+    }
+}
+
+/// Fully decodes an Ogg Vorbis stream.
+#[cfg(feature = "ogg")]
+pub(crate) fn decode_ogg(
+    reader: impl std::io::Read + std::io::Seek,
+) -> Result<AudioData, FileError> {
+    use lewton::inside_ogg::OggStreamReader;
+
+    let invalid = |e: lewton::VorbisError| FileError::InvalidData(e.to_string());
+    let mut stream = OggStreamReader::new(reader).map_err(invalid)?;
+    let channels = stream.ident_hdr.audio_channels as u32;
+    check_channels(channels)?;
+
+    let mut samples = Vec::new();
+    while let Some(packet) = stream.read_dec_packet_itl().map_err(invalid)? {
+        samples.extend(packet);
+    }
+    Ok(AudioData {
+        channels,
+        sample_rate: stream.ident_hdr.audio_sample_rate,
+        samples,
+    })
+}
+
+/// Fully decodes a FLAC stream.
+#[cfg(feature = "flac")]
+pub(crate) fn decode_flac(reader: impl std::io::Read) -> Result<AudioData, FileError> {
+    let invalid = |e: claxon::Error| FileError::InvalidData(e.to_string());
+    let mut flac = claxon::FlacReader::new(reader).map_err(invalid)?;
+    let info = flac.streaminfo();
+    check_channels(info.channels)?;
+
+    let mut samples = Vec::with_capacity(
+        info.samples
+            .map(|n| (n * info.channels as u64) as usize)
+            .unwrap_or(0),
+    );
+    for sample in flac.samples() {
+        let sample = sample.map_err(invalid)?;
+        let sample = if info.bits_per_sample > 16 {
+            sample >> (info.bits_per_sample - 16)
+        } else {
+            sample << (16 - info.bits_per_sample)
+        };
+        samples.push(sample as i16);
+    }
+    Ok(AudioData {
+        channels: info.channels,
+        sample_rate: info.sample_rate,
+        samples,
+    })
+}
@@ -0,0 +1,377 @@
+//! A WAV file parser for the formats which the engine does not load,
+//! converting them to 16-bit samples.
+
+use crate::{sound::AudioData, FileError};
+
+/// Format tag for integer PCM data.
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+/// Format tag for floating point PCM data.
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+/// Format tag for IMA ADPCM data.
+const WAVE_FORMAT_IMA_ADPCM: u16 = 0x0011;
+/// Format tag for data described by a sub-format GUID.
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// The relevant fields of the `fmt ` chunk.
+#[derive(Debug)]
+struct Format {
+    tag: u16,
+    channels: u16,
+    sample_rate: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+}
+
+fn invalid(message: impl Into<String>) -> FileError {
+    FileError::InvalidData(message.into())
+}
+
+#[inline]
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Iterates over the chunks of a RIFF file body,
+/// yielding their identifier and contents.
+fn chunks(mut data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        if data.len() < 8 {
+            return None;
+        }
+        let id = [data[0], data[1], data[2], data[3]];
+        let len = (read_u32(data, 4) as usize).min(data.len() - 8);
+        let body = &data[8..8 + len];
+        // chunks are padded to an even size
+        data = &data[(8 + len + len % 2).min(data.len())..];
+        Some((id, body))
+    })
+}
+
+fn parse_format(chunk: &[u8]) -> Result<Format, FileError> {
+    if chunk.len() < 16 {
+        return Err(invalid("truncated WAV format chunk"));
+    }
+    let mut tag = read_u16(chunk, 0);
+    if tag == WAVE_FORMAT_EXTENSIBLE && chunk.len() >= 26 {
+        // the first two bytes of the sub-format GUID hold the actual tag
+        tag = read_u16(chunk, 24);
+    }
+    Ok(Format {
+        tag,
+        channels: read_u16(chunk, 2),
+        sample_rate: read_u32(chunk, 4),
+        block_align: read_u16(chunk, 12),
+        bits_per_sample: read_u16(chunk, 14),
+    })
+}
+
+/// Parses the contents of a WAV file.
+///
+/// Supports integer PCM (8, 16, 24 and 32 bits),
+/// 32-bit floating point PCM, and IMA ADPCM,
+/// in mono or stereo.
+pub(crate) fn parse(data: &[u8]) -> Result<AudioData, FileError> {
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a RIFF WAVE file"));
+    }
+
+    let mut format = None;
+    let mut body = None;
+    for (id, chunk) in chunks(&data[12..]) {
+        match &id {
+            b"fmt " => format = Some(parse_format(chunk)?),
+            b"data" => body = Some(chunk),
+            _ => {}
+        }
+    }
+    let format = format.ok_or_else(|| invalid("missing WAV format chunk"))?;
+    let body = body.ok_or_else(|| invalid("missing WAV data chunk"))?;
+
+    if !(1..=2).contains(&format.channels) {
+        return Err(invalid(format!(
+            "unsupported number of channels: {}",
+            format.channels
+        )));
+    }
+
+    let samples = match (format.tag, format.bits_per_sample) {
+        (WAVE_FORMAT_PCM, 8) => body.iter().map(|s| (*s as i16 - 128) << 8).collect(),
+        (WAVE_FORMAT_PCM, 16) => body
+            .chunks_exact(2)
+            .map(|s| i16::from_le_bytes([s[0], s[1]]))
+            .collect(),
+        (WAVE_FORMAT_PCM, 24) => body
+            .chunks_exact(3)
+            .map(|s| i16::from_le_bytes([s[1], s[2]]))
+            .collect(),
+        (WAVE_FORMAT_PCM, 32) => body
+            .chunks_exact(4)
+            .map(|s| i16::from_le_bytes([s[2], s[3]]))
+            .collect(),
+        (WAVE_FORMAT_IEEE_FLOAT, 32) => body
+            .chunks_exact(4)
+            .map(|s| {
+                let v = f32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+                (v.clamp(-1., 1.) * i16::MAX as f32) as i16
+            })
+            .collect(),
+        (WAVE_FORMAT_IMA_ADPCM, 4) => decode_ima_adpcm(body, &format)?,
+        (tag, bits) => {
+            return Err(invalid(format!(
+                "unsupported WAV format tag 0x{:04X} ({} bits per sample)",
+                tag, bits
+            )))
+        }
+    };
+
+    if samples.len() < format.channels as usize {
+        return Err(invalid("WAV file has no samples"));
+    }
+
+    Ok(AudioData {
+        channels: format.channels as u32,
+        sample_rate: format.sample_rate,
+        samples,
+    })
+}
+
+/// The IMA ADPCM step sizes.
+const IMA_STEP_TABLE: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// The IMA ADPCM step index adjustments, by nibble.
+const IMA_INDEX_TABLE: [i32; 16] = [-1, -1, -1, -1, 2, 4, 6, 8, -1, -1, -1, -1, 2, 4, 6, 8];
+
+/// The decoding state of one IMA ADPCM channel.
+#[derive(Debug, Copy, Clone)]
+struct ImaChannel {
+    predictor: i32,
+    index: i32,
+}
+
+impl ImaChannel {
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = IMA_STEP_TABLE[self.index as usize];
+        let mut diff = step >> 3;
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 8 != 0 {
+            diff = -diff;
+        }
+        self.predictor = (self.predictor + diff).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.index = (self.index + IMA_INDEX_TABLE[nibble as usize]).clamp(0, 88);
+        self.predictor as i16
+    }
+}
+
+/// Decodes IMA ADPCM data (as in WAV files) into interleaved samples.
+fn decode_ima_adpcm(body: &[u8], format: &Format) -> Result<Vec<i16>, FileError> {
+    let channels = format.channels as usize;
+    let block_align = format.block_align as usize;
+    if block_align <= 4 * channels {
+        return Err(invalid(format!(
+            "invalid IMA ADPCM block size: {}",
+            block_align
+        )));
+    }
+
+    let mut samples = Vec::new();
+    // the last block may be incomplete
+    for block in body.chunks(block_align) {
+        if block.len() < 4 * channels {
+            break;
+        }
+        // each channel starts with a header holding the first sample
+        let mut state = [ImaChannel {
+            predictor: 0,
+            index: 0,
+        }; 2];
+        for (c, state) in state.iter_mut().enumerate().take(channels) {
+            let header = &block[4 * c..4 * c + 4];
+            state.predictor = i16::from_le_bytes([header[0], header[1]]) as i32;
+            state.index = (header[2] as i32).clamp(0, 88);
+            samples.push(state.predictor as i16);
+        }
+
+        // then 4 bytes (8 samples) for each channel in turn
+        let mut frames = [[0i16; 8]; 2];
+        for group in block[4 * channels..].chunks_exact(4 * channels) {
+            for (c, bytes) in group.chunks_exact(4).enumerate() {
+                for (i, byte) in bytes.iter().enumerate() {
+                    frames[c][i * 2] = state[c].decode(byte & 0x0F);
+                    frames[c][i * 2 + 1] = state[c].decode(byte >> 4);
+                }
+            }
+            for i in 0..8 {
+                for frame in frames.iter().take(channels) {
+                    samples.push(frame[i]);
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a WAV file with the given format and data chunk.
+    fn wav(tag: u16, channels: u16, bits: u16, block_align: u16, data: &[u8]) -> Vec<u8> {
+        let mut fmt = Vec::new();
+        fmt.extend_from_slice(&tag.to_le_bytes());
+        fmt.extend_from_slice(&channels.to_le_bytes());
+        fmt.extend_from_slice(&11025u32.to_le_bytes());
+        fmt.extend_from_slice(&(11025 * block_align as u32).to_le_bytes());
+        fmt.extend_from_slice(&block_align.to_le_bytes());
+        fmt.extend_from_slice(&bits.to_le_bytes());
+
+        let mut body = b"WAVE".to_vec();
+        for (id, chunk) in [(b"fmt ", &fmt[..]), (b"LIST", &b"odd"[..]), (b"data", data)] {
+            body.extend_from_slice(id);
+            body.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            body.extend_from_slice(chunk);
+            if chunk.len() % 2 == 1 {
+                body.push(0);
+            }
+        }
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        file.extend_from_slice(&body);
+        file
+    }
+
+    fn invalid_message(result: Result<AudioData, FileError>) -> String {
+        match result {
+            Err(FileError::InvalidData(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn parses_8_bit_pcm() {
+        let audio = parse(&wav(WAVE_FORMAT_PCM, 1, 8, 1, &[0, 128, 255])).unwrap();
+        assert_eq!(audio.channels, 1);
+        assert_eq!(audio.sample_rate, 11025);
+        assert_eq!(audio.samples, [-32768, 0, 32512]);
+    }
+
+    #[test]
+    fn parses_16_bit_stereo_pcm() {
+        let data = [0x01, 0x00, 0xFF, 0xFF, 0x00, 0x80, 0xFF, 0x7F];
+        let audio = parse(&wav(WAVE_FORMAT_PCM, 2, 16, 4, &data)).unwrap();
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples, [1, -1, i16::MIN, i16::MAX]);
+    }
+
+    #[test]
+    fn parses_24_and_32_bit_pcm() {
+        let audio = parse(&wav(WAVE_FORMAT_PCM, 1, 24, 3, &[0xAA, 0x34, 0x12])).unwrap();
+        assert_eq!(audio.samples, [0x1234]);
+        let audio = parse(&wav(WAVE_FORMAT_PCM, 1, 32, 4, &[0xAA, 0xBB, 0xCD, 0xAB])).unwrap();
+        assert_eq!(audio.samples, [0xABCDu16 as i16]);
+    }
+
+    #[test]
+    fn parses_float_pcm() {
+        let data: Vec<u8> = [0.0f32, 1.0, -1.0, 2.0, 0.5]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let audio = parse(&wav(WAVE_FORMAT_IEEE_FLOAT, 1, 32, 4, &data)).unwrap();
+        assert_eq!(audio.samples, [0, 32767, -32767, 32767, 16383]);
+    }
+
+    #[test]
+    fn parses_extensible_format() {
+        let mut file = wav(WAVE_FORMAT_EXTENSIBLE, 1, 16, 2, &[0x34, 0x12]);
+        // extend the format chunk with the sub-format GUID of integer PCM
+        let mut ext = vec![22, 0, 16, 0, 0, 0, 0, 0];
+        ext.extend_from_slice(&WAVE_FORMAT_PCM.to_le_bytes());
+        ext.extend_from_slice(&[0; 14]);
+        file[16] += ext.len() as u8;
+        file.splice(36..36, ext);
+        let riff_len = (file.len() - 8) as u32;
+        file[4..8].copy_from_slice(&riff_len.to_le_bytes());
+
+        let audio = parse(&file).unwrap();
+        assert_eq!(audio.samples, [0x1234]);
+    }
+
+    #[test]
+    fn parses_mono_ima_adpcm() {
+        // a header with the first sample and step index 0,
+        // then the nibbles 7, 0, 0, 8, 0, 0, 0, 0 (low nibble first):
+        // 7 adds 0 + 1 + 3 + 7 and moves to step 16,
+        // each 0 adds an eighth of the step and moves one step down,
+        // and 8 subtracts it
+        let block = [0x00, 0x00, 0x00, 0x00, 0x07, 0x80, 0x00, 0x00];
+        let audio = parse(&wav(WAVE_FORMAT_IMA_ADPCM, 1, 4, 8, &block)).unwrap();
+        assert_eq!(audio.samples, [0, 11, 13, 14, 13, 14, 15, 16, 17]);
+    }
+
+    #[test]
+    fn parses_stereo_ima_adpcm() {
+        let mut block = vec![0x10, 0x00, 0x00, 0x00, 0xF0, 0xFF, 0x00, 0x00];
+        block.extend_from_slice(&[0x00; 4]);
+        block.extend_from_slice(&[0x88; 4]);
+        // an incomplete block at the end is ignored
+        block.extend_from_slice(&[0x00; 3]);
+        let audio = parse(&wav(WAVE_FORMAT_IMA_ADPCM, 2, 4, 16, &block)).unwrap();
+        assert_eq!(audio.channels, 2);
+        assert_eq!(audio.samples.len(), 18);
+        // the headers first, then the channels interleaved
+        assert_eq!(&audio.samples[..4], [16, -16, 16, -16]);
+        let left: Vec<i16> = audio.samples.iter().step_by(2).copied().collect();
+        let right: Vec<i16> = audio.samples.iter().skip(1).step_by(2).copied().collect();
+        assert!(left.windows(2).all(|w| w[1] >= w[0]), "{:?}", left);
+        assert!(right.windows(2).all(|w| w[1] <= w[0]), "{:?}", right);
+    }
+
+    #[test]
+    fn rejects_unsupported_formats() {
+        // A-law
+        let message = invalid_message(parse(&wav(0x0006, 1, 8, 1, &[0; 4])));
+        assert!(message.contains("0x0006"), "{}", message);
+        let message = invalid_message(parse(&wav(WAVE_FORMAT_PCM, 1, 12, 2, &[0; 4])));
+        assert!(message.contains("12 bits"), "{}", message);
+        let message = invalid_message(parse(&wav(WAVE_FORMAT_PCM, 6, 16, 12, &[0; 12])));
+        assert!(message.contains("channels"), "{}", message);
+        let message = invalid_message(parse(&wav(WAVE_FORMAT_IMA_ADPCM, 1, 4, 4, &[0; 8])));
+        assert!(message.contains("block size"), "{}", message);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse(b"").is_err());
+        assert!(parse(b"RIFF\0\0\0\0AVI LIST").is_err());
+        let file = wav(WAVE_FORMAT_PCM, 1, 16, 2, &[]);
+        assert_eq!(invalid_message(parse(&file)), "WAV file has no samples");
+        // cut off in the middle of the format chunk
+        let message = invalid_message(parse(&file[..30]));
+        assert!(message.contains("format chunk"), "{}", message);
+    }
+}
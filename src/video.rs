@@ -2,14 +2,71 @@
 //! including graphics and text output.
 
 use std::{
+    borrow::Cow,
+    cell::RefCell,
     ffi::{CStr, CString},
     num::NonZeroU32,
-    os::raw::{c_int, c_uint},
-    ptr::NonNull,
+    os::raw::{c_char, c_int, c_uint},
+    sync::{
+        atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, Ordering},
+        Mutex, OnceLock,
+    },
 };
 
+use crate::geometry::{Point, Rect, Size};
 use crate::FileError;
 
+mod affine;
+mod animation;
+mod cp437;
+mod dump;
+pub mod effects;
+mod font_registry;
+mod gif;
+#[cfg(test)]
+mod golden;
+mod lbm;
+mod markup;
+mod palette;
+pub mod particles;
+mod raster;
+mod soft_font;
+mod sprite;
+mod surface;
+mod text_batch;
+mod theme;
+mod tilemap;
+mod transition;
+pub mod widgets;
+mod xbin;
+
+pub use affine::{affine_blit, draw_tex_column, rotate_blit, Texture};
+pub use animation::{Animation, AnimationPlayer, FrameSource, LoopMode};
+pub use cp437::{char_to_cp437, encode_cp437};
+pub use dump::{load_screen_dump, load_screen_dump_from_memory, save_screen_dump};
+pub(crate) use font_registry::installed_font_count;
+pub use font_registry::{find_font, installed_fonts, FontInfo};
+pub use gif::{
+    load_gif_animation, load_gif_animation_from_memory, load_gif_from_memory, screenshot_dir,
+    screenshot_gif, set_screenshot_dir, GifAnimation, GifFrame,
+};
+pub use lbm::{load_lbm, load_lbm_from_memory, ColorCycleRange, LbmImage};
+pub use markup::{put_str_markup, strip_markup};
+pub use palette::{
+    adjust_palette, get_palette, get_palette_range, install_ramp, load_gifs_shared,
+    make_multi_ramp, make_ramp, nearest_color, nearest_color_in, rotate_palette, set_palette,
+    set_palette_bytes, set_palette_colors, set_palette_range, tint_palette, Palette,
+    PaletteSnapshot, RampError, Rgb, SharedPaletteSet,
+};
+pub use soft_font::FontError;
+pub use sprite::{SpriteDrawOptions, SpriteSheet};
+pub use surface::Surface;
+pub use text_batch::TextBatch;
+pub use theme::{set_theme, theme, Theme, ThemeRole};
+pub use tilemap::{draw_tilemap, EMPTY_TILE};
+pub use transition::{transition, Transition, TransitionPlayer};
+pub use xbin::{load_xbin, load_xbin_from_memory, XBin};
+
 /// A simple descriptor for whether a video mode is in text or graphics mode.
 #[derive(Debug, Copy, Clone, PartialEq, Hash, Eq)]
 pub enum VideoModeKind {
@@ -26,6 +83,7 @@ pub enum VideoModeKind {
 /// This type maps to the `videomode_t` struct in the original framework.
 /// Each variant is either in text mode or graphics mode.
 #[derive(Debug, Copy, Clone, PartialEq, Hash, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 #[repr(u32)]
 pub enum VideoMode {
     /// Text mode, 40 columns and 25 rows, 8x8 font size.
@@ -61,6 +119,24 @@ pub enum VideoMode {
 }
 
 impl VideoMode {
+    /// All video modes supported by the framework.
+    pub const ALL: [VideoMode; 14] = [
+        VideoMode::Text40x25_8x8,
+        VideoMode::Text40x25_9x16,
+        VideoMode::Text80x25_8x8,
+        VideoMode::Text80x25_8x16,
+        VideoMode::Text80x25_9x16,
+        VideoMode::Text80x43_8x8,
+        VideoMode::Text80x50_8x8,
+        VideoMode::Graphics320x200,
+        VideoMode::Graphics320x240,
+        VideoMode::Graphics320x400,
+        VideoMode::Graphics640x200,
+        VideoMode::Graphics640x350,
+        VideoMode::Graphics640x400,
+        VideoMode::Graphics640x480,
+    ];
+
     /// Gets the kind of video mode this is (text or graphics).
     pub fn kind(self) -> VideoModeKind {
         match self {
@@ -93,6 +169,86 @@ impl VideoMode {
         self.kind() == VideoModeKind::Text
     }
 
+    /// Gets the number of text columns in this video mode.
+    ///
+    /// Returns 0 if this is a graphics mode.
+    pub fn text_columns(self) -> u16 {
+        match self {
+            VideoMode::Text40x25_8x8 | VideoMode::Text40x25_9x16 => 40,
+            VideoMode::Text80x25_8x8
+            | VideoMode::Text80x25_8x16
+            | VideoMode::Text80x25_9x16
+            | VideoMode::Text80x43_8x8
+            | VideoMode::Text80x50_8x8 => 80,
+            _ => 0,
+        }
+    }
+
+    /// Gets the number of text rows in this video mode.
+    ///
+    /// Returns 0 if this is a graphics mode.
+    pub fn text_rows(self) -> u16 {
+        match self {
+            VideoMode::Text40x25_8x8
+            | VideoMode::Text40x25_9x16
+            | VideoMode::Text80x25_8x8
+            | VideoMode::Text80x25_8x16
+            | VideoMode::Text80x25_9x16 => 25,
+            VideoMode::Text80x43_8x8 => 43,
+            VideoMode::Text80x50_8x8 => 50,
+            _ => 0,
+        }
+    }
+
+    /// Gets the font size in pixels (width and height)
+    /// of the text cells in this video mode,
+    /// or `None` if this is a graphics mode.
+    pub fn font_size(self) -> Option<(u8, u8)> {
+        match self {
+            VideoMode::Text40x25_8x8
+            | VideoMode::Text80x25_8x8
+            | VideoMode::Text80x43_8x8
+            | VideoMode::Text80x50_8x8 => Some((8, 8)),
+            VideoMode::Text80x25_8x16 => Some((8, 16)),
+            VideoMode::Text40x25_9x16 | VideoMode::Text80x25_9x16 => Some((9, 16)),
+            _ => None,
+        }
+    }
+
+    /// Gets the resolution of the video mode in pixels (width and height).
+    ///
+    /// In text modes,
+    /// this is the number of text cells times the font size.
+    pub fn pixel_dimensions(self) -> (u16, u16) {
+        match self {
+            VideoMode::Text40x25_8x8 => (320, 200),
+            VideoMode::Text40x25_9x16 => (360, 400),
+            VideoMode::Text80x25_8x8 => (640, 200),
+            VideoMode::Text80x25_8x16 => (640, 400),
+            VideoMode::Text80x25_9x16 => (720, 400),
+            VideoMode::Text80x43_8x8 => (640, 344),
+            VideoMode::Text80x50_8x8 => (640, 400),
+            VideoMode::Graphics320x200 => (320, 200),
+            VideoMode::Graphics320x240 => (320, 240),
+            VideoMode::Graphics320x400 => (320, 400),
+            VideoMode::Graphics640x200 => (640, 200),
+            VideoMode::Graphics640x350 => (640, 350),
+            VideoMode::Graphics640x400 => (640, 400),
+            VideoMode::Graphics640x480 => (640, 480),
+        }
+    }
+
+    /// Gets the pixel aspect ratio of this video mode
+    /// as it appeared on a 4:3 monitor,
+    /// expressed as the height of a pixel divided by its width.
+    ///
+    /// For example, pixels in 320x200 were 1.2 times taller than wide,
+    /// while pixels in 640x480 were square (1.0).
+    pub fn pixel_aspect(self) -> f32 {
+        let (width, height) = self.pixel_dimensions();
+        (width as f32 * 3.) / (height as f32 * 4.)
+    }
+
     /// Sets the application video mode to this one.
     ///
     /// Equivalent to the module's [`set_video_mode`].
@@ -100,22 +256,344 @@ impl VideoMode {
     pub fn set_video_mode(self) {
         set_video_mode(self)
     }
+
+    /// Checks whether this is the current video mode
+    /// (see [`current_video_mode`]).
+    #[inline]
+    pub fn is_current(self) -> bool {
+        current_video_mode() == self
+    }
+
+    /// Obtains the video mode from its raw `videomode_t` value.
+    fn from_raw(value: u32) -> Option<VideoMode> {
+        VideoMode::ALL
+            .into_iter()
+            .find(|mode| *mode as u32 == value)
+    }
+
+    /// Gets the name of the video mode variant.
+    fn variant_name(self) -> &'static str {
+        match self {
+            VideoMode::Text40x25_8x8 => "Text40x25_8x8",
+            VideoMode::Text40x25_9x16 => "Text40x25_9x16",
+            VideoMode::Text80x25_8x8 => "Text80x25_8x8",
+            VideoMode::Text80x25_8x16 => "Text80x25_8x16",
+            VideoMode::Text80x25_9x16 => "Text80x25_9x16",
+            VideoMode::Text80x43_8x8 => "Text80x43_8x8",
+            VideoMode::Text80x50_8x8 => "Text80x50_8x8",
+            VideoMode::Graphics320x200 => "Graphics320x200",
+            VideoMode::Graphics320x240 => "Graphics320x240",
+            VideoMode::Graphics320x400 => "Graphics320x400",
+            VideoMode::Graphics640x200 => "Graphics640x200",
+            VideoMode::Graphics640x350 => "Graphics640x350",
+            VideoMode::Graphics640x400 => "Graphics640x400",
+            VideoMode::Graphics640x480 => "Graphics640x480",
+        }
+    }
+
+    /// Gets the short name of the video mode,
+    /// which is the variant name without its kind.
+    fn short_name(self) -> &'static str {
+        let name = self.variant_name();
+        name.strip_prefix("Graphics")
+            .or_else(|| name.strip_prefix("Text"))
+            .unwrap_or(name)
+    }
+}
+
+/// Formats the video mode in its short form,
+/// such as `320x200` for graphics modes
+/// or `80x25_8x16` for text modes (columns and rows, then font size).
+///
+/// The output can be parsed back with [`FromStr`](std::str::FromStr).
+impl std::fmt::Display for VideoMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.short_name())
+    }
+}
+
+/// Error returned when parsing a [`VideoMode`] from a string fails.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct ParseVideoModeError;
+
+impl std::fmt::Display for ParseVideoModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unrecognized video mode")
+    }
+}
+
+impl std::error::Error for ParseVideoModeError {}
+
+/// Parses a video mode from a string, case insensitive.
+///
+/// The accepted forms are:
+///
+/// - the variant name, such as `Graphics320x200` or `Text80x25_9x16`;
+/// - the short form produced by [`Display`](std::fmt::Display),
+///   such as `320x200` or `80x25_9x16`;
+/// - the number of columns and rows of a text mode without the font size,
+///   such as `80x25`.
+///   Where more than one font size is available,
+///   `80x25` resolves to [`VideoMode::Text80x25_8x16`] (the default mode)
+///   and `40x25` resolves to [`VideoMode::Text40x25_9x16`].
+///
+/// # Example
+///
+/// ```
+/// # use dos_like::VideoMode;
+/// assert_eq!("320x240".parse(), Ok(VideoMode::Graphics320x240));
+/// assert_eq!("80x25".parse(), Ok(VideoMode::Text80x25_8x16));
+///
+/// for mode in VideoMode::ALL {
+///     assert_eq!(mode.to_string().parse(), Ok(mode));
+/// }
+/// ```
+impl std::str::FromStr for VideoMode {
+    type Err = ParseVideoModeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        match s.as_str() {
+            "80x25" => return Ok(VideoMode::Text80x25_8x16),
+            "40x25" => return Ok(VideoMode::Text40x25_9x16),
+            _ => {}
+        }
+        // the size of a text grid, as in `80x50`
+        let grid = s
+            .split_once('x')
+            .and_then(|(columns, rows)| Some((columns.parse().ok()?, rows.parse().ok()?)));
+        VideoMode::ALL
+            .into_iter()
+            .find(|mode| {
+                mode.variant_name().eq_ignore_ascii_case(&s)
+                    || mode.short_name().eq_ignore_ascii_case(&s)
+                    || mode.is_text() && grid == Some((mode.text_columns(), mode.text_rows()))
+            })
+            .ok_or(ParseVideoModeError)
+    }
+}
+
+/// Error returned when converting an unknown raw value into a [`VideoMode`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct UnknownVideoMode {
+    /// The raw value which was converted.
+    pub value: u32,
+}
+
+impl std::fmt::Display for UnknownVideoMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unknown video mode value {}", self.value)
+    }
+}
+
+impl std::error::Error for UnknownVideoMode {}
+
+/// Converts a raw `videomode_t` value into a video mode.
+///
+/// # Example
+///
+/// ```
+/// # use dos_like::VideoMode;
+/// for mode in VideoMode::ALL {
+///     assert_eq!(VideoMode::try_from(mode as u32), Ok(mode));
+/// }
+/// ```
+impl TryFrom<u32> for VideoMode {
+    type Error = UnknownVideoMode;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        VideoMode::from_raw(value).ok_or(UnknownVideoMode { value })
+    }
+}
+
+/// Obtains the raw `videomode_t` value of the video mode.
+impl From<VideoMode> for u32 {
+    fn from(mode: VideoMode) -> u32 {
+        mode as u32
+    }
+}
+
+/// Error type for video operations which were misused,
+/// as reported by the `try_` functions of this module
+/// such as [`try_set_text_style`] and [`try_set_pal`].
+///
+/// The checks are made against the state tracked by this crate
+/// (the current video mode and the fonts known to it),
+/// so they may miss changes made directly through the engine.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum VideoError {
+    /// The font is not a built-in font,
+    /// nor one created via [`Font::from_glyphs`]
+    /// or installed via [`install_user_font`]
+    UnknownFont {
+        /// The identifier of the font.
+        id: u32,
+    },
+    /// The palette index is beyond the 256 entries of the palette
+    PaletteIndexOutOfRange {
+        /// The palette index given.
+        index: usize,
+    },
+    /// The palette entry is not used in the current text mode,
+    /// which only shows the first 16 entries
+    PaletteIndexUnused {
+        /// The palette index given.
+        index: usize,
+        /// The current video mode.
+        mode: VideoMode,
+    },
+    /// A color component is beyond the 6-bit range of the palette (0 to 63)
+    ColorOutOfRange {
+        /// The color component given.
+        value: u8,
+    },
+    /// The operation is only available in graphics mode
+    NotGraphicsMode {
+        /// The current video mode.
+        mode: VideoMode,
+    },
+    /// The engine did not switch to the requested video mode
+    ModeNotApplied {
+        /// The requested video mode.
+        mode: VideoMode,
+    },
+    /// The screen buffer is already acquired by a [`ScreenBuffer`]
+    BufferInUse,
+}
+
+impl std::fmt::Display for VideoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            VideoError::UnknownFont { id } => write!(f, "Unknown font identifier {}", id),
+            VideoError::PaletteIndexOutOfRange { index } => {
+                write!(f, "Palette index {} out of range", index)
+            }
+            VideoError::PaletteIndexUnused { index, mode } => write!(
+                f,
+                "Palette index {} is not used in text mode {}",
+                index, mode
+            ),
+            VideoError::ColorOutOfRange { value } => {
+                write!(f, "Color component {} out of range (0 to 63)", value)
+            }
+            VideoError::NotGraphicsMode { mode } => {
+                write!(f, "Video mode {} is not a graphics mode", mode)
+            }
+            VideoError::ModeNotApplied { mode } => {
+                write!(f, "Could not switch to video mode {}", mode)
+            }
+            VideoError::BufferInUse => write!(f, "Screen buffer already acquired"),
+        }
+    }
+}
+
+impl std::error::Error for VideoError {}
+
+/// The video mode last set through this crate.
+static VIDEO_MODE: AtomicU32 = AtomicU32::new(VideoMode::Text80x25_8x16 as u32);
+
+/// Asks the engine to switch to the video mode,
+/// returning whether it did.
+///
+/// Only graphics modes can be checked,
+/// by the screen resolution reported afterwards.
+fn apply_video_mode(mode: VideoMode) -> bool {
+    unsafe {
+        dos_like_sys::setvideomode(mode as c_uint);
+    }
+    FRONT_BUFFER.store(std::ptr::null_mut(), Ordering::Relaxed);
+    !mode.is_graphics() || (screen_width(), screen_height()) == mode.pixel_dimensions()
 }
 
 /// Sets the video mode.
+///
+/// See [`try_set_video_mode`] to detect whether the engine switched to it.
 #[inline]
 pub fn set_video_mode(mode: VideoMode) {
-    unsafe {
-        dos_like_sys::setvideomode(mode as c_uint);
+    apply_video_mode(mode);
+    VIDEO_MODE.store(mode as u32, Ordering::Relaxed);
+}
+
+/// Sets the video mode,
+/// or returns an error if the engine did not switch to it.
+///
+/// The engine is checked to report the resolution of a graphics mode
+/// after switching.
+/// On error,
+/// the video mode reported by [`current_video_mode`] does not change.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// if let Err(e) = try_set_video_mode(VideoMode::Graphics640x480) {
+///     eprintln!("{}, falling back to 320x200", e);
+///     set_video_mode(VideoMode::Graphics320x200);
+/// }
+/// ```
+pub fn try_set_video_mode(mode: VideoMode) -> Result<(), VideoError> {
+    if !apply_video_mode(mode) {
+        return Err(VideoError::ModeNotApplied { mode });
     }
+    VIDEO_MODE.store(mode as u32, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Gets the current video mode,
+/// as last set via [`set_video_mode`] or [`VideoMode::set_video_mode`].
+///
+/// Before any video mode is set,
+/// this is the default mode, [`VideoMode::Text80x25_8x16`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// if current_video_mode().is_graphics() {
+///     out_text_xy(0, 0, "Hello");
+/// } else {
+///     put_str("Hello");
+/// }
+/// ```
+#[inline]
+pub fn current_video_mode() -> VideoMode {
+    video_mode()
+}
+
+/// Gets the video mode last set via [`set_video_mode`].
+#[inline]
+fn video_mode() -> VideoMode {
+    VideoMode::from_raw(VIDEO_MODE.load(Ordering::Relaxed)).unwrap_or(VideoMode::Text80x25_8x16)
 }
 
+/// Whether double buffering was last enabled through this crate.
+static DOUBLE_BUFFER: AtomicBool = AtomicBool::new(false);
+
 /// Enables or disables screen double buffering.
+///
+/// Changing this in the middle of a frame
+/// only takes effect on the next frame,
+/// so it is best done before drawing anything.
 #[inline]
 pub fn set_double_buffer(enabled: bool) {
     unsafe {
         dos_like_sys::setdoublebuffer(enabled as c_int);
     }
+    FRONT_BUFFER.store(std::ptr::null_mut(), Ordering::Relaxed);
+    DOUBLE_BUFFER.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks whether screen double buffering is enabled,
+/// as set by [`set_double_buffer`].
+///
+/// When enabled, drawing is done on an off-screen buffer
+/// and [`swap_buffers`] must be called for it to be displayed.
+/// Otherwise, drawing goes straight to the visible screen.
+///
+/// Double buffering is disabled by default.
+#[inline]
+pub fn is_double_buffered() -> bool {
+    DOUBLE_BUFFER.load(Ordering::Relaxed)
 }
 
 /// Obtains the screen width in pixels.
@@ -130,12 +608,74 @@ pub fn screen_height() -> u16 {
     unsafe { dos_like_sys::screenheight() as u16 }
 }
 
+/// Counter of changes to the palette,
+/// so that colors remapped to it can be invalidated.
+static PALETTE_GENERATION: AtomicU32 = AtomicU32::new(0);
+
 /// Sets a palette color by index.
+///
+/// The color components are in the 6-bit range of the VGA palette,
+/// from 0 to 63.
+/// See [`try_set_pal`] for a version which checks its arguments.
 #[inline]
 pub fn set_pal(index: usize, r: u8, g: u8, b: u8) {
+    debug_assert!(index < 256, "set_pal: palette index {} out of range", index);
+    debug_assert!(
+        r < 64 && g < 64 && b < 64,
+        "set_pal: color ({}, {}, {}) out of range",
+        r,
+        g,
+        b
+    );
     unsafe {
         dos_like_sys::setpal(index as c_int, r as c_int, g as c_int, b as c_int);
     }
+    PALETTE_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Sets a palette color by index from an [`Rgb`] color.
+///
+/// The same as [`set_pal`],
+/// with the channels in the 6-bit range of the VGA palette,
+/// from 0 to 63.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// # let image = load_gif("sprite.gif")?;
+/// for (i, &color) in image.palette_colors().iter().enumerate() {
+///     set_pal_rgb(i, color);
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+#[inline]
+pub fn set_pal_rgb(index: usize, color: Rgb) {
+    set_pal(index, color.r, color.g, color.b);
+}
+
+/// Sets a palette color by index,
+/// or returns an error if the arguments make no sense
+/// in the current video mode.
+///
+/// The index must be below 256,
+/// or below 16 in text mode,
+/// where only the entries of the 16 text colors are shown.
+/// The color components must be in the 6-bit range of the VGA palette,
+/// from 0 to 63.
+pub fn try_set_pal(index: usize, r: u8, g: u8, b: u8) -> Result<(), VideoError> {
+    if index >= 256 {
+        return Err(VideoError::PaletteIndexOutOfRange { index });
+    }
+    let mode = video_mode();
+    if !mode.is_graphics() && index >= 16 {
+        return Err(VideoError::PaletteIndexUnused { index, mode });
+    }
+    if let Some(&value) = [r, g, b].iter().find(|&&c| c > 63) {
+        return Err(VideoError::ColorOutOfRange { value });
+    }
+    set_pal(index, r, g, b);
+    Ok(())
 }
 
 /// Gets a palette color by index.
@@ -148,6 +688,66 @@ pub fn pal(index: usize) -> (u8, u8, u8) {
     }
 }
 
+/// The current border color, as `0x00RRGGBB` in palette scale.
+static BORDER_COLOR: AtomicU32 = AtomicU32::new(0);
+
+/// Sets the color of the border,
+/// the area of the window around the emulated screen.
+///
+/// As in real VGA hardware,
+/// this can be changed at any time,
+/// for flash effects or to show how long a frame took to draw.
+/// The components are in the same scale as in [`set_pal`],
+/// from 0 to 63, and higher values are clamped.
+/// The new color is shown from the next presented frame on.
+///
+/// The border is black by default.
+/// If the CRT screen frame is enabled,
+/// it is drawn on top of the border,
+/// so the border color only shows around and through the frame.
+pub fn set_border_color(r: u8, g: u8, b: u8) {
+    let (r, g, b) = (r.min(63), g.min(63), b.min(63));
+    BORDER_COLOR.store(u32::from_be_bytes([0, r, g, b]), Ordering::Relaxed);
+    unsafe {
+        dos_like_sys::dos_like_set_border(palette_to_xbgr(r, g, b));
+    }
+}
+
+/// Gets the color of the border,
+/// as set by [`set_border_color`].
+pub fn border_color() -> (u8, u8, u8) {
+    let [_, r, g, b] = BORDER_COLOR.load(Ordering::Relaxed).to_be_bytes();
+    (r, g, b)
+}
+
+/// Converts a color in palette scale to 8 bits per component, as XBGR,
+/// expanding the components as the engine does with the palette.
+fn palette_to_xbgr(r: u8, g: u8, b: u8) -> c_uint {
+    let expand = |c: u8| ((c << 2) | (c >> 4)) as c_uint;
+    expand(b) << 16 | expand(g) << 8 | expand(r)
+}
+
+/// Creates a table mapping each color of one palette
+/// to the nearest color in another palette.
+///
+/// Both palettes are in RGB, 3 bytes per color.
+fn remap_table(from: &[u8], to: &[u8]) -> [u8; 256] {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let Some(src) = from.get(i * 3..i * 3 + 3) else {
+            break;
+        };
+        let nearest = to.chunks_exact(3).enumerate().min_by_key(|(_, dst)| {
+            src.iter()
+                .zip(dst.iter())
+                .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+                .sum::<i32>()
+        });
+        *entry = nearest.map(|(j, _)| j as u8).unwrap_or(i as u8);
+    }
+    table
+}
+
 // -- Graphics buffer manipulation functions
 // Due to the way the original framework works,
 // some operations are hard to be marked as safe by the compiler.
@@ -157,6 +757,8 @@ pub fn pal(index: usize) -> (u8, u8, u8) {
 /// Only makes sense in graphics mode.
 /// The length of the slice is equal to the number of pixels on the screen.
 ///
+/// See [`ScreenBuffer`] for a safe alternative.
+///
 /// # Safety
 ///
 /// It is not guaranteed by the compiler
@@ -172,6 +774,10 @@ pub fn pal(index: usize) -> (u8, u8, u8) {
 /// then it is safe to call [`swap_buffers`] or [`swap_buffers_and_get`]
 /// and immediately drop this slice in favor of the new buffer slice.
 pub unsafe fn screen_buffer() -> &'static mut [u8] {
+    debug_assert!(
+        video_mode().is_graphics(),
+        "screen_buffer: not in graphics mode"
+    );
     // Safety: it is documented that the user
     // must not draw anything through other functions,
     // so that buffer access is truly exclusive.
@@ -234,7 +840,7 @@ pub unsafe fn swap_buffers_and_get() -> &'static mut [u8] {
     // so that buffer access is truly exclusive and not aliased.
     #[allow(unused_unsafe)]
     unsafe {
-        let buf = dos_like_sys::swapbuffers();
+        let buf = raw_swap_buffers();
         let width = dos_like_sys::screenwidth() as usize;
         let height = dos_like_sys::screenheight() as usize;
         std::slice::from_raw_parts_mut(buf, width * height)
@@ -277,93 +883,547 @@ pub fn swap_buffers() {
     // so aliased slices are not possible without calling
     // the other unsafe functions
     unsafe {
-        dos_like_sys::swapbuffers();
+        raw_swap_buffers();
     }
 }
 
-// -- Graphics manipulation functions
-
-/// Blits a rectangular portion of a video data buffer to the screen.
+/// Shows the frame drawn so far.
 ///
-/// - `x` and `y` are the target coordinates of the top-left corner
-///   to blit on the screen
-/// - `width` and `height` are the full dimensions of the source data
-/// - `src_x` and `src_y` are the coordinates of the starting position
-///   to blit from the source data
-/// - `src_width` and `src_height` are the width and height to effectively blit
-///   from the source data
+/// With double buffering enabled, this swaps the screen buffers
+/// as in [`swap_buffers`].
+/// Otherwise drawing already goes straight to the visible screen,
+/// and this does nothing,
+/// so the same loop works either way.
 ///
-/// # Panic
+/// # Example
 ///
-/// Panics if the given source parameters
-/// are incompatible with the length of the source,
-/// since this is likely a bug.
-pub fn blit(
-    x: i32,
-    y: i32,
-    source: &[u8],
-    width: u16,
-    height: u16,
-    src_x: u16,
-    src_y: u16,
-    src_width: u16,
-    src_height: u16,
-) {
-    if width as usize * height as usize > source.len() {
-        panic!(
-            "blit: source data ({} bytes) is too short for resolution {}x{}",
-            source.len(),
-            width,
-            height
-        );
-    }
-
-    // Safety:
-    // - the source data length has been validated against `width` and `height`
-    // - although a *mut pointer is passed, the impl is sure to never write to it
-    unsafe {
-        dos_like_sys::blit(
-            x as c_int,
-            y as c_int,
-            source.as_ptr() as *mut _,
-            width as c_int,
-            height as c_int,
-            src_x as c_int,
-            src_y as c_int,
-            src_width as c_int,
-            src_height as c_int,
-        );
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// set_double_buffer(true);
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     clear_screen();
+///     set_color(14);
+///     bar(10, 10, 100, 50);
+///     present();
+/// }
+/// ```
+pub fn present() {
+    if is_double_buffered() {
+        swap_buffers();
     }
 }
 
-/// Blits a masked rectangular portion of a video data buffer to the screen,
-/// skipping pixels encoded as fully transparent.
+/// Whether a [`ScreenBuffer`] is currently alive.
+static SCREEN_BUFFER_ACQUIRED: AtomicBool = AtomicBool::new(false);
+
+/// Safe, exclusive access to the pixels of the screen buffer.
 ///
-/// - `x` and `y` are the target coordinates of the top-left corner
-///   to blit on the screen
-/// - `width` and `height` are the full dimensions of the source data
-/// - `src_x` and `src_y` are the coordinates of the starting position
-///   to blit from the source data
-/// - `src_width` and `src_height` are the width and height to effectively blit
-///   from the source data
-/// - `color_key` is the color to skip when blitting
+/// At most one `ScreenBuffer` exists at a time:
+/// [`acquire`](ScreenBuffer::acquire) returns `None`
+/// while another one is alive,
+/// and it is released when dropped.
+/// Slices to the pixels borrow the screen buffer,
+/// so they cannot outlive a call to [`swap`](ScreenBuffer::swap).
+/// The buffer and its dimensions are looked up on each access,
+/// so a slice obtained after changing the video mode
+/// always covers the current screen.
 ///
-/// # Panic
+/// This is the safe alternative to [`screen_buffer`]
+/// and [`swap_buffers_and_get`].
+/// The other drawing functions in this module
+/// write to the screen without going through this type,
+/// so they should not be called while a slice from it is in use.
+/// Finish with the slice before drawing in other ways.
 ///
-/// Panics if the given source parameters
-/// are incompatible with the length of the source,
-/// since this is likely a bug.
-pub fn mask_blit(
-    x: i32,
-    y: i32,
-    source: &[u8],
-    width: u16,
-    height: u16,
-    src_x: u16,
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// set_double_buffer(true);
+/// let mut screen = ScreenBuffer::acquire().expect("screen buffer already taken");
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     let width = screen.width() as usize;
+///     for (i, row) in screen.as_mut_slice().chunks_mut(width).enumerate() {
+///         row.fill(i as u8);
+///     }
+///     screen.swap();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ScreenBuffer {
+    /// Keeps the screen buffer in the thread which acquired it
+    _not_send: std::marker::PhantomData<*mut u8>,
+}
+
+impl ScreenBuffer {
+    /// Acquires exclusive access to the screen buffer,
+    /// or returns `None` if another `ScreenBuffer` is alive.
+    pub fn acquire() -> Option<ScreenBuffer> {
+        if SCREEN_BUFFER_ACQUIRED.swap(true, Ordering::Acquire) {
+            return None;
+        }
+        Some(ScreenBuffer {
+            _not_send: std::marker::PhantomData,
+        })
+    }
+
+    /// Acquires exclusive access to the screen buffer,
+    /// or returns an error if another `ScreenBuffer` is alive
+    /// or the video is not in graphics mode.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// set_video_mode(VideoMode::Graphics320x200);
+    /// let mut screen = match ScreenBuffer::try_acquire() {
+    ///     Ok(screen) => screen,
+    ///     Err(e) => panic!("Cannot draw: {}", e),
+    /// };
+    /// screen.as_mut_slice().fill(1);
+    /// ```
+    pub fn try_acquire() -> Result<ScreenBuffer, VideoError> {
+        let mode = video_mode();
+        if !mode.is_graphics() {
+            return Err(VideoError::NotGraphicsMode { mode });
+        }
+        ScreenBuffer::acquire().ok_or(VideoError::BufferInUse)
+    }
+
+    /// Gets the screen width in pixels.
+    #[inline]
+    pub fn width(&self) -> u16 {
+        screen_width()
+    }
+
+    /// Gets the screen height in pixels.
+    #[inline]
+    pub fn height(&self) -> u16 {
+        screen_height()
+    }
+
+    /// Gets the pixels of the screen buffer, row by row.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: the buffer has one byte per pixel on the screen,
+        // and this is the only screen buffer object
+        unsafe {
+            let buf = dos_like_sys::screenbuffer();
+            if buf.is_null() {
+                return &[];
+            }
+            std::slice::from_raw_parts(buf, self.width() as usize * self.height() as usize)
+        }
+    }
+
+    /// Gets the pixels of the screen buffer for writing, row by row.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // Safety: the buffer has one byte per pixel on the screen,
+        // and the exclusive borrow of the only screen buffer object
+        // prevents aliasing through this type
+        unsafe {
+            let buf = dos_like_sys::screenbuffer();
+            if buf.is_null() {
+                return &mut [];
+            }
+            std::slice::from_raw_parts_mut(buf, self.width() as usize * self.height() as usize)
+        }
+    }
+
+    /// Swaps the screen buffers,
+    /// as in [`swap_buffers`].
+    ///
+    /// Subsequent accesses are to the new buffer to draw on.
+    pub fn swap(&mut self) {
+        // Safety: no slice to the previous buffer can be alive,
+        // as they borrow `self`
+        unsafe {
+            raw_swap_buffers();
+        }
+    }
+}
+
+impl std::ops::Deref for ScreenBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl std::ops::DerefMut for ScreenBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        self.as_mut_slice()
+    }
+}
+
+impl Drop for ScreenBuffer {
+    fn drop(&mut self) {
+        SCREEN_BUFFER_ACQUIRED.store(false, Ordering::Release);
+    }
+}
+
+/// The buffer on display after the last swap,
+/// or null if unknown.
+static FRONT_BUFFER: AtomicPtr<u8> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Whether to copy the front buffer into the back buffer after each swap.
+static AUTO_SYNC_BUFFERS: AtomicBool = AtomicBool::new(false);
+
+/// Swaps the screen buffers,
+/// keeping track of the buffer on display
+/// and synchronizing the buffers if requested.
+unsafe fn raw_swap_buffers() -> *mut u8 {
+    let front = dos_like_sys::screenbuffer();
+    let back = dos_like_sys::swapbuffers();
+    FRONT_BUFFER.store(front, Ordering::Relaxed);
+    if AUTO_SYNC_BUFFERS.load(Ordering::Relaxed) {
+        sync_buffers();
+    }
+    back
+}
+
+/// Copies the buffer currently on display into the back buffer,
+/// so that the next frame can be drawn incrementally
+/// on top of the last one.
+///
+/// With double buffering,
+/// the back buffer received after [`swap_buffers`]
+/// otherwise contains the frame from two swaps ago.
+/// This enables renderers which only redraw what changed
+/// (such as with dirty rectangles).
+///
+/// This costs one copy of the full screen buffer.
+/// See also [`set_auto_sync_buffers`]
+/// to do this automatically after each swap.
+///
+/// Does nothing if double buffering is disabled,
+/// or before the buffers were swapped for the first time.
+/// Only makes sense in graphics mode.
+pub fn sync_buffers() {
+    if !is_double_buffered() || !video_mode().is_graphics() {
+        return;
+    }
+    // Safety: both buffers have one byte per pixel on the screen,
+    // and no slice to them is alive as per the contract of `screen_buffer`
+    unsafe {
+        let front = FRONT_BUFFER.load(Ordering::Relaxed);
+        let back = dos_like_sys::screenbuffer();
+        if front.is_null() || front == back {
+            return;
+        }
+        let len = dos_like_sys::screenwidth() as usize * dos_like_sys::screenheight() as usize;
+        std::ptr::copy_nonoverlapping(front, back, len);
+    }
+}
+
+/// Sets whether the buffer on display
+/// should be copied into the back buffer after each swap,
+/// as in [`sync_buffers`].
+///
+/// This is disabled by default,
+/// as it costs one copy of the full screen buffer per frame.
+pub fn set_auto_sync_buffers(enabled: bool) {
+    AUTO_SYNC_BUFFERS.store(enabled, Ordering::Relaxed);
+}
+
+/// Checks whether the buffers are synchronized after each swap,
+/// as set by [`set_auto_sync_buffers`].
+pub fn is_auto_sync_buffers() -> bool {
+    AUTO_SYNC_BUFFERS.load(Ordering::Relaxed)
+}
+
+// -- Draw offset
+
+static DRAW_OFFSET_X: AtomicI32 = AtomicI32::new(0);
+static DRAW_OFFSET_Y: AtomicI32 = AtomicI32::new(0);
+
+/// Sets a global translation applied to the coordinates
+/// of all subsequent drawing primitives, blits and text blits.
+///
+/// This is useful for implementing a camera,
+/// since world coordinates can then be passed directly
+/// to functions such as [`line()`], [`bar`] or [`blit`].
+/// The offset is added to the given coordinates
+/// before the framework clips them against the screen.
+///
+/// Functions of the pure text mode
+/// (such as [`goto_xy`] and [`put_str`])
+/// are not affected.
+#[inline]
+pub fn set_draw_offset(dx: i32, dy: i32) {
+    DRAW_OFFSET_X.store(dx, Ordering::Relaxed);
+    DRAW_OFFSET_Y.store(dy, Ordering::Relaxed);
+}
+
+/// Gets the current draw offset,
+/// as set by [`set_draw_offset`].
+#[inline]
+pub fn draw_offset() -> (i32, i32) {
+    (
+        DRAW_OFFSET_X.load(Ordering::Relaxed),
+        DRAW_OFFSET_Y.load(Ordering::Relaxed),
+    )
+}
+
+/// Resets the draw offset back to `(0, 0)`.
+#[inline]
+pub fn reset_draw_offset() {
+    set_draw_offset(0, 0);
+}
+
+/// Applies the current draw offset to the given coordinates.
+#[inline]
+fn offset_xy(x: i32, y: i32) -> (c_int, c_int) {
+    let (dx, dy) = draw_offset();
+    (x.wrapping_add(dx) as c_int, y.wrapping_add(dy) as c_int)
+}
+
+/// Applies the current draw offset to a flat list of XY coordinates,
+/// only making a copy if the offset is not zero.
+fn offset_points(points: &[i32]) -> Cow<'_, [i32]> {
+    match draw_offset() {
+        (0, 0) => Cow::Borrowed(points),
+        (dx, dy) => Cow::Owned(
+            points
+                .chunks_exact(2)
+                .flat_map(|p| [p[0].wrapping_add(dx), p[1].wrapping_add(dy)])
+                .collect(),
+        ),
+    }
+}
+
+// -- Clip rectangle and viewports
+
+/// The region of the screen which drawing is confined to,
+/// or `None` for the whole screen.
+static CLIP_RECT: Mutex<Option<Rect>> = Mutex::new(None);
+
+/// Confines all subsequent drawing primitives, blits and text blits
+/// to the given region of the screen.
+///
+/// The region is in screen coordinates,
+/// so it is not affected by the draw offset.
+/// While a clip rectangle is set,
+/// primitives are drawn through the rasterizer in this crate
+/// (see [`use_rust_rasterizer`]).
+///
+/// [`clear_screen`], [`fill_screen`]
+/// and the functions of the pure text mode
+/// are not affected.
+#[inline]
+pub fn set_clip_rect(rect: impl Into<Rect>) {
+    set_clip(Some(rect.into()));
+}
+
+/// Gets the current clip rectangle,
+/// as set by [`set_clip_rect`],
+/// or `None` if drawing is not confined.
+#[inline]
+pub fn clip_rect() -> Option<Rect> {
+    *CLIP_RECT.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Removes the clip rectangle,
+/// so that drawing can reach the whole screen again.
+#[inline]
+pub fn reset_clip_rect() {
+    set_clip(None);
+}
+
+fn set_clip(rect: Option<Rect>) {
+    *CLIP_RECT.lock().unwrap_or_else(|e| e.into_inner()) = rect;
+}
+
+/// Gets the whole area of the screen.
+fn screen_rect() -> Rect {
+    Rect::new(0, 0, screen_width(), screen_height())
+}
+
+/// Gets the clip rectangle restricted to the screen,
+/// or `None` if drawing is not confined.
+fn clip_bounds() -> Option<Rect> {
+    let screen = screen_rect();
+    clip_rect().map(|rect| rect.clamp_to(&screen))
+}
+
+/// Gets the region of the screen which can be drawn to,
+/// which is the clip rectangle if there is one
+/// or the whole screen otherwise.
+fn draw_bounds() -> Rect {
+    clip_bounds().unwrap_or_else(screen_rect)
+}
+
+/// Calls the function with a copy of the pixels of the screen
+/// in the given bounds,
+/// and copies the pixels back to the screen afterwards.
+///
+/// The bounds must be inside the screen,
+/// and the function is not called if they are empty.
+/// No copy is made if they cover the whole screen.
+fn with_pixels_in(bounds: Rect, f: impl FnOnce(&mut [u8], u16, u16)) {
+    if bounds.is_empty() {
+        return;
+    }
+    if bounds == screen_rect() {
+        with_screen_pixels(f);
+        return;
+    }
+    let (x, w) = (bounds.x as usize, bounds.width as usize);
+    let rows = bounds.y as usize..bounds.bottom() as usize;
+    let mut copy = Vec::with_capacity(w * bounds.height as usize);
+    with_screen_pixels(|pixels, width, _| {
+        for y in rows.clone() {
+            let start = y * width as usize + x;
+            copy.extend_from_slice(&pixels[start..start + w]);
+        }
+    });
+    f(&mut copy, bounds.width, bounds.height);
+    with_screen_pixels(|pixels, width, _| {
+        for (y, row) in rows.zip(copy.chunks_exact(w)) {
+            let start = y * width as usize + x;
+            pixels[start..start + w].copy_from_slice(row);
+        }
+    });
+}
+
+/// Draws through the engine at the given position on the screen,
+/// confined to the clip rectangle if there is one.
+///
+/// When confined, the engine draws to a copy of the pixels
+/// in the clip rectangle,
+/// and `draw` is given the position relative to it.
+fn draw_clipped(x: c_int, y: c_int, draw: impl FnOnce(c_int, c_int)) {
+    match clip_bounds() {
+        Some(bounds) if video_mode().is_graphics() => {
+            with_pixels_in(bounds, |pixels, width, height| unsafe {
+                dos_like_sys::setdrawtarget(pixels.as_mut_ptr(), width as c_int, height as c_int);
+                draw(x - bounds.x, y - bounds.y);
+                dos_like_sys::resetdrawtarget();
+            })
+        }
+        _ => draw(x, y),
+    }
+}
+
+/// A region of the screen with its own camera,
+/// so that a scene can be drawn more than once,
+/// such as in split-screen games.
+///
+/// See [`with_viewport`].
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Viewport {
+    /// The region of the screen to draw to, in screen coordinates.
+    pub rect: Rect,
+    /// The draw offset, relative to the top-left corner of the region.
+    ///
+    /// To look at the world from a camera at `(x, y)`,
+    /// the offset is `(-x, -y)`.
+    pub offset: (i32, i32),
+}
+
+impl Viewport {
+    /// Creates a new viewport.
+    #[inline]
+    pub const fn new(rect: Rect, offset: (i32, i32)) -> Self {
+        Viewport { rect, offset }
+    }
+}
+
+/// Restores the clip rectangle and draw offset when dropped.
+struct ViewportGuard {
+    clip: Option<Rect>,
+    offset: (i32, i32),
+}
+
+impl Drop for ViewportGuard {
+    fn drop(&mut self) {
+        set_clip(self.clip);
+        set_draw_offset(self.offset.0, self.offset.1);
+    }
+}
+
+/// Runs the given function with drawing confined to a viewport.
+///
+/// The clip rectangle is set to the viewport's region
+/// and the draw offset to the position of the region plus its offset,
+/// so that `(0, 0)` is the top-left corner of the region
+/// when the offset is zero.
+/// Both are restored afterwards.
+///
+/// Viewports can be nested,
+/// in which case the inner region (still in screen coordinates)
+/// is restricted to the outer one.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// fn draw_world() {
+///     set_color(4);
+///     fill_circle(100, 100, 20);
+/// }
+///
+/// set_video_mode(VideoMode::Graphics320x200);
+/// // player 1 on the left half, player 2 on the right half
+/// with_viewport(Viewport::new(Rect::new(0, 0, 160, 200), (0, 0)), draw_world);
+/// with_viewport(Viewport::new(Rect::new(160, 0, 160, 200), (-60, 0)), draw_world);
+/// ```
+pub fn with_viewport<T>(viewport: Viewport, f: impl FnOnce() -> T) -> T {
+    let guard = ViewportGuard {
+        clip: clip_rect(),
+        offset: draw_offset(),
+    };
+    let rect = match guard.clip {
+        Some(outer) => viewport.rect.clamp_to(&outer),
+        None => viewport.rect,
+    };
+    set_clip(Some(rect));
+    let origin = viewport.rect.origin() + Point::from(viewport.offset);
+    set_draw_offset(origin.x, origin.y);
+    let out = f();
+    drop(guard);
+    out
+}
+
+// -- Graphics manipulation functions
+
+/// Blits a rectangular portion of a video data buffer to the screen.
+///
+/// - `x` and `y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `width` and `height` are the full dimensions of the source data
+/// - `src_x` and `src_y` are the coordinates of the starting position
+///   to blit from the source data
+/// - `src_width` and `src_height` are the width and height to effectively blit
+///   from the source data
+///
+/// In graphics mode,
+/// whole rows are copied directly into the screen buffer,
+/// with the same clipping as the engine.
+///
+/// # Panic
+///
+/// Panics if the given source parameters
+/// are incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn blit(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_x: u16,
     src_y: u16,
     src_width: u16,
     src_height: u16,
-    color_key: u8,
 ) {
     if width as usize * height as usize > source.len() {
         panic!(
@@ -374,13 +1434,30 @@ pub fn mask_blit(
         );
     }
 
+    let (x, y) = offset_xy(x, y);
+    if video_mode().is_graphics() {
+        let bounds = draw_bounds();
+        with_screen_pixels(|pixels, screen_w, _| {
+            // copy whole rows at once
+            blit_rows(
+                (pixels, screen_w as usize),
+                bounds,
+                (x, y),
+                (source, width, height),
+                (src_x, src_y, src_width, src_height),
+                |src, dst| dst.copy_from_slice(src),
+            )
+        });
+        return;
+    }
+
     // Safety:
     // - the source data length has been validated against `width` and `height`
     // - although a *mut pointer is passed, the impl is sure to never write to it
     unsafe {
-        dos_like_sys::maskblit(
-            x as c_int,
-            y as c_int,
+        dos_like_sys::blit(
+            x,
+            y,
             source.as_ptr() as *mut _,
             width as c_int,
             height as c_int,
@@ -388,11 +1465,828 @@ pub fn mask_blit(
             src_y as c_int,
             src_width as c_int,
             src_height as c_int,
-            color_key as c_int,
         );
     }
 }
 
+/// Blits a masked rectangular portion of a video data buffer to the screen,
+/// skipping pixels encoded as fully transparent.
+///
+/// - `x` and `y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `width` and `height` are the full dimensions of the source data
+/// - `src_x` and `src_y` are the coordinates of the starting position
+///   to blit from the source data
+/// - `src_width` and `src_height` are the width and height to effectively blit
+///   from the source data
+/// - `color_key` is the color to skip when blitting
+///
+/// Runs of opaque pixels are copied directly into the screen buffer
+/// a row at a time,
+/// with the same clipping as [`blit`].
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source parameters
+/// are incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn mask_blit(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_x: u16,
+    src_y: u16,
+    src_width: u16,
+    src_height: u16,
+    color_key: u8,
+) {
+    if width as usize * height as usize > source.len() {
+        panic!(
+            "blit: source data ({} bytes) is too short for resolution {}x{}",
+            source.len(),
+            width,
+            height
+        );
+    }
+
+    if !video_mode().is_graphics() {
+        return;
+    }
+    let (x, y) = offset_xy(x, y);
+    let bounds = draw_bounds();
+    with_screen_pixels(|pixels, screen_w, _| {
+        blit_rows(
+            (pixels, screen_w as usize),
+            bounds,
+            (x, y),
+            (source, width, height),
+            (src_x, src_y, src_width, src_height),
+            |src, dst| copy_opaque(src, dst, color_key),
+        )
+    });
+}
+
+/// Copies the pixels of `src` other than `color_key` into `dst`,
+/// each run of opaque pixels at once.
+fn copy_opaque(src: &[u8], dst: &mut [u8], color_key: u8) {
+    let mut i = 0;
+    while let Some(start) = src[i..].iter().position(|&p| p != color_key) {
+        let start = i + start;
+        let end = src[start..]
+            .iter()
+            .position(|&p| p == color_key)
+            .map_or(src.len(), |len| start + len);
+        dst[start..end].copy_from_slice(&src[start..end]);
+        i = end;
+    }
+}
+
+/// Blits the visible part of a source region
+/// into a buffer of pixels with rows `pixels_w` long,
+/// clipped to `bounds` in the same way as the engine clips to the screen,
+/// calling `copy` with each row of the source region
+/// and the row of pixels it goes to.
+fn blit_rows(
+    (pixels, pixels_w): (&mut [u8], usize),
+    bounds: Rect,
+    (x, y): (i32, i32),
+    (source, width, height): (&[u8], u16, u16),
+    src: (u16, u16, u16, u16),
+    mut copy: impl FnMut(&[u8], &mut [u8]),
+) {
+    let Some((x, y, src_x, src_y, src_width, src_height)) =
+        clip_blit((x, y), (width, height), src, bounds)
+    else {
+        return;
+    };
+    let src_rows = source[src_y * width as usize..].chunks(width as usize);
+    let dst_rows = pixels[y * pixels_w..].chunks_mut(pixels_w);
+    for (src_row, dst_row) in src_rows.zip(dst_rows).take(src_height) {
+        copy(
+            &src_row[src_x..src_x + src_width],
+            &mut dst_row[x..x + src_width],
+        );
+    }
+}
+
+/// Clips a blit to the given bounds
+/// in the same way as the engine clips to the screen,
+/// returning the target position and the source region to copy,
+/// or `None` if nothing is visible.
+fn clip_blit(
+    (x, y): (i32, i32),
+    (width, height): (u16, u16),
+    (src_x, src_y, src_w, src_h): (u16, u16, u16, u16),
+    bounds: Rect,
+) -> Option<(usize, usize, usize, usize, usize, usize)> {
+    let (mut x, mut y) = (x as i64, y as i64);
+    let (mut src_x, mut src_y) = (src_x as i64, src_y as i64);
+    let (mut src_w, mut src_h) = (src_w as i64, src_h as i64);
+    let (left, top) = (bounds.left() as i64, bounds.top() as i64);
+    if x < left {
+        src_x += left - x;
+        src_w -= left - x;
+        x = left;
+    }
+    if y < top {
+        src_y += top - y;
+        src_h -= top - y;
+        y = top;
+    }
+    src_w = src_w.min(width as i64 - src_x);
+    src_h = src_h.min(height as i64 - src_y);
+    src_w = src_w.min(bounds.right() - x);
+    src_h = src_h.min(bounds.bottom() - y);
+    if src_w <= 0 || src_h <= 0 {
+        return None;
+    }
+    Some((
+        x as usize,
+        y as usize,
+        src_x as usize,
+        src_y as usize,
+        src_w as usize,
+        src_h as usize,
+    ))
+}
+
+/// Blits a rectangular portion of a video data buffer to the screen.
+///
+/// - `dst` is the target position of the top-left corner
+///   to blit on the screen
+/// - `source_size` is the full size of the source data
+/// - `src_rect` is the region to effectively blit from the source data,
+///   which is clamped to the bounds of the source data
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn blit_rect(
+    dst: impl Into<Point>,
+    source: &[u8],
+    source_size: impl Into<Size>,
+    src_rect: impl Into<Rect>,
+) {
+    let dst = dst.into();
+    let source_size = source_size.into();
+    let src_rect = src_rect
+        .into()
+        .clamp_to(&Rect::from_origin_size(Point::ORIGIN, source_size));
+
+    blit(
+        dst.x,
+        dst.y,
+        source,
+        source_size.width,
+        source_size.height,
+        src_rect.x as u16,
+        src_rect.y as u16,
+        src_rect.width,
+        src_rect.height,
+    );
+}
+
+/// Blits a video data buffer to the screen,
+/// scaled so that it appears with the correct proportions
+/// in the current video mode.
+///
+/// The source data is assumed to have square pixels.
+/// It is scaled to `target_height` pixels in height,
+/// and the width is chosen automatically
+/// according to the pixel aspect ratio of the current video mode
+/// (see [`VideoMode::pixel_aspect`]).
+/// Rounding is done with integer arithmetic,
+/// so the same input always results in the same output size.
+/// The source is scaled with nearest neighbor sampling
+/// straight onto the screen, as in [`stretch_blit`],
+/// without allocating a scaled copy.
+///
+/// - `x` and `y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `width` and `height` are the full dimensions of the source data
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source parameters
+/// are incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn blit_aspect_corrected(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    target_height: u16,
+) {
+    if width as usize * height as usize > source.len() {
+        panic!(
+            "blit: source data ({} bytes) is too short for resolution {}x{}",
+            source.len(),
+            width,
+            height
+        );
+    }
+    if width == 0 || height == 0 || target_height == 0 {
+        return;
+    }
+
+    // target_width = target_height * (width / height) * pixel_aspect,
+    // where pixel_aspect = (mode_width * 3) / (mode_height * 4)
+    let (mode_width, mode_height) = video_mode().pixel_dimensions();
+    let num = target_height as u64 * width as u64 * mode_width as u64 * 3;
+    let den = height as u64 * mode_height as u64 * 4;
+    let target_width = ((num + den / 2) / den).min(u16::MAX as u64) as u16;
+    if target_width == 0 {
+        return;
+    }
+
+    stretch(
+        (source, width, height),
+        Rect::new(0, 0, width, height),
+        Rect::new(x, y, target_width, target_height),
+        None,
+    );
+}
+
+/// Blits a rectangular portion of a video data buffer to the screen,
+/// scaled to fill the given destination size
+/// with nearest neighbor sampling.
+///
+/// - `dst_x` and `dst_y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `dst_w` and `dst_h` are the size to scale the region to
+/// - `width` and `height` are the full dimensions of the source data
+/// - `src_rect` is the region to blit from the source data,
+///   which is clamped to the bounds of the source data
+///
+/// The region can be scaled up or down,
+/// with different factors on each axis.
+/// The destination is clipped to the screen and the clip rectangle,
+/// and is subject to the draw offset.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut pixels = Vec::new();
+/// let screen = pixels_rect(Rect::new(0, 0, 320, 200), &mut pixels);
+/// // a minimap of the whole screen at half the size
+/// stretch_blit(160, 0, 160, 100, &pixels, screen.width, screen.height, screen);
+/// ```
+pub fn stretch_blit(
+    dst_x: i32,
+    dst_y: i32,
+    dst_w: u16,
+    dst_h: u16,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+) {
+    let dst = Rect::new(dst_x, dst_y, dst_w, dst_h);
+    stretch((source, width, height), src_rect.into(), dst, None);
+}
+
+/// Blits a rectangular portion of a video data buffer to the screen,
+/// scaled to fill the given destination size
+/// with nearest neighbor sampling,
+/// skipping pixels of the color key.
+///
+/// See [`stretch_blit`] for the meaning of the other parameters.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn stretch_mask_blit(
+    dst_x: i32,
+    dst_y: i32,
+    dst_w: u16,
+    dst_h: u16,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+    color_key: u8,
+) {
+    let dst = Rect::new(dst_x, dst_y, dst_w, dst_h);
+    stretch(
+        (source, width, height),
+        src_rect.into(),
+        dst,
+        Some(color_key),
+    );
+}
+
+/// Draws a whole image scaled to fill the given rectangle on the screen,
+/// with nearest neighbor sampling.
+///
+/// Pixels of the image's [color key](Image::color_key), if any,
+/// are skipped.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let sprite = load_gif("sprite.gif")?;
+/// // twice the size
+/// let (w, h) = (sprite.width() as u16, sprite.height() as u16);
+/// draw_image_stretched(Rect::new(100, 50, w * 2, h * 2), &sprite);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn draw_image_stretched(dst: impl Into<Rect>, image: &Image) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    stretch(
+        (image.data(), width, height),
+        Rect::new(0, 0, width, height),
+        dst.into(),
+        image.color_key(),
+    );
+}
+
+/// Steps through the source positions of consecutive destination pixels
+/// when scaling `from` pixels to `to` pixels,
+/// with exact integer arithmetic.
+struct ScaleStepper {
+    index: usize,
+    remainder: usize,
+    whole: usize,
+    fraction: usize,
+    to: usize,
+}
+
+impl ScaleStepper {
+    /// Starts at the given destination pixel.
+    fn new(from: usize, to: usize, start: usize) -> Self {
+        let position = start * from;
+        ScaleStepper {
+            index: position / to,
+            remainder: position % to,
+            whole: from / to,
+            fraction: from % to,
+            to,
+        }
+    }
+
+    fn next(&mut self) -> usize {
+        let index = self.index;
+        self.index += self.whole;
+        self.remainder += self.fraction;
+        if self.remainder >= self.to {
+            self.remainder -= self.to;
+            self.index += 1;
+        }
+        index
+    }
+}
+
+/// Scales a region of a pixel buffer onto a region of the screen.
+fn stretch(
+    (source, width, height): (&[u8], u16, u16),
+    src_rect: Rect,
+    dst: Rect,
+    color_key: Option<u8>,
+) {
+    if width as usize * height as usize > source.len() {
+        panic!(
+            "stretch_blit: source data ({} bytes) is too short for resolution {}x{}",
+            source.len(),
+            width,
+            height
+        );
+    }
+    let src = src_rect.clamp_to(&Rect::new(0, 0, width, height));
+    if src.is_empty() || dst.is_empty() || !video_mode().is_graphics() {
+        return;
+    }
+
+    // clip the destination to the screen and the clip rectangle
+    let (dst_x, dst_y) = offset_xy(dst.x, dst.y);
+    let (dst_x, dst_y) = (dst_x as i64, dst_y as i64);
+    let bounds = draw_bounds();
+    let x0 = dst_x.max(bounds.left() as i64);
+    let x1 = (dst_x + dst.width as i64).min(bounds.right());
+    let y0 = dst_y.max(bounds.top() as i64);
+    let y1 = (dst_y + dst.height as i64).min(bounds.bottom());
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    let (src_x, src_y) = (src.x as usize, src.y as usize);
+    let (src_w, src_h) = (src.width as usize, src.height as usize);
+    let (dst_w, dst_h) = (dst.width as usize, dst.height as usize);
+    let mut rows = ScaleStepper::new(src_h, dst_h, (y0 - dst_y) as usize);
+    with_screen_pixels(|screen, screen_w, _| {
+        for y in y0..y1 {
+            let src_row = &source[(src_y + rows.next()) * width as usize + src_x..][..src_w];
+            let start = y as usize * screen_w as usize;
+            let dst_row = &mut screen[start + x0 as usize..start + x1 as usize];
+            let mut columns = ScaleStepper::new(src_w, dst_w, (x0 - dst_x) as usize);
+            match color_key {
+                None => {
+                    for pixel in dst_row {
+                        *pixel = src_row[columns.next()];
+                    }
+                }
+                Some(key) => {
+                    for pixel in dst_row {
+                        let color = src_row[columns.next()];
+                        if color != key {
+                            *pixel = color;
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Blits a rectangular portion of a video data buffer to the screen,
+/// mirrored horizontally, vertically or both.
+///
+/// - `x` and `y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `width` and `height` are the full dimensions of the source data
+/// - `src_rect` is the region to blit from the source data,
+///   which is clamped to the bounds of the source data
+/// - `flip_h` mirrors the region left to right
+/// - `flip_v` mirrors the region top to bottom
+///
+/// The region is flipped first and clipped afterwards,
+/// so the mirrored region always lands on the same rectangle of the screen
+/// and only the part of it outside of the visible area is cut.
+/// With neither flag set,
+/// this is the same as [`blit_rect`].
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let sheet = load_gif("player.gif")?;
+/// let (width, height) = (sheet.width() as u16, sheet.height() as u16);
+/// let facing_left = true;
+/// // the artwork faces right
+/// blit_flipped(100, 80, sheet.data(), width, height, Rect::new(0, 0, 16, 24), facing_left, false);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn blit_flipped(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+    flip_h: bool,
+    flip_v: bool,
+) {
+    let src_rect = src_rect.into();
+    if !flip_h && !flip_v {
+        blit_rect((x, y), source, (width, height), src_rect);
+        return;
+    }
+    flipped(
+        (x, y),
+        (source, width, height),
+        src_rect,
+        (flip_h, flip_v),
+        PixelOp::Copy,
+    );
+}
+
+/// Blits a masked rectangular portion of a video data buffer to the screen,
+/// mirrored horizontally, vertically or both,
+/// skipping pixels of the color key.
+///
+/// See [`blit_flipped`] for the meaning of the other parameters.
+/// With neither flag set,
+/// this is the same as [`mask_blit`].
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn mask_blit_flipped(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+    flip_h: bool,
+    flip_v: bool,
+    color_key: u8,
+) {
+    flipped(
+        (x, y),
+        (source, width, height),
+        src_rect.into(),
+        (flip_h, flip_v),
+        PixelOp::Masked(color_key),
+    );
+}
+
+/// What the silhouette of a sprite is drawn with,
+/// see [`blit_silhouette`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum Silhouette<'a> {
+    /// A single flat color.
+    Color(u8),
+    /// The color already on the screen,
+    /// replaced with the color at its index in the table,
+    /// such as a table of darker colors,
+    /// so that a shadow darkens the background
+    /// instead of painting over it.
+    Remap(&'a [u8; 256]),
+}
+
+impl From<u8> for Silhouette<'_> {
+    #[inline]
+    fn from(color: u8) -> Self {
+        Silhouette::Color(color)
+    }
+}
+
+impl<'a> From<&'a [u8; 256]> for Silhouette<'a> {
+    #[inline]
+    fn from(table: &'a [u8; 256]) -> Self {
+        Silhouette::Remap(table)
+    }
+}
+
+/// Blits the silhouette of a rectangular portion of a video data buffer,
+/// such as for the drop shadow of a sprite:
+/// every pixel other than the color key
+/// is drawn as the silhouette color,
+/// or darkens the screen through a table
+/// (see [`Silhouette`]).
+///
+/// - `x` and `y` are the target coordinates of the top-left corner
+///   to blit on the screen
+/// - `width` and `height` are the full dimensions of the source data
+/// - `src_rect` is the region to blit from the source data,
+///   which is clamped to the bounds of the source data
+/// - `color_key` is the color to skip when blitting
+/// - `silhouette` is a palette index for a flat color,
+///   or a reference to a table of 256 indices to remap the screen with
+///
+/// The clipping is the same as in [`mask_blit`].
+/// See [`blit_silhouette_flipped`] to mirror the silhouette.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let sprite = load_gif("player.gif")?;
+/// let (width, height) = (sprite.width() as u16, sprite.height() as u16);
+/// let frame = Rect::new(0, 0, width, height);
+/// // a table which maps each color to a darker one
+/// let palette = get_palette();
+/// let darker: [u8; 256] = std::array::from_fn(|i| {
+///     let color = palette[i];
+///     nearest_color(color.r / 2, color.g / 2, color.b / 2)
+/// });
+/// blit_silhouette(104, 84, sprite.data(), width, height, frame, 0, &darker);
+/// mask_blit(100, 80, sprite.data(), width, height, 0, 0, width, height, 0);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn blit_silhouette<'a>(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+    color_key: u8,
+    silhouette: impl Into<Silhouette<'a>>,
+) {
+    flipped(
+        (x, y),
+        (source, width, height),
+        src_rect.into(),
+        (false, false),
+        PixelOp::Silhouette(color_key, silhouette.into()),
+    );
+}
+
+/// Blits the silhouette of a rectangular portion of a video data buffer,
+/// mirrored horizontally, vertically or both.
+///
+/// See [`blit_silhouette`] for the silhouette
+/// and [`blit_flipped`] for the mirroring.
+/// With neither flag set,
+/// this is the same as [`blit_silhouette`].
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if the given source size
+/// is incompatible with the length of the source,
+/// since this is likely a bug.
+pub fn blit_silhouette_flipped<'a>(
+    x: i32,
+    y: i32,
+    source: &[u8],
+    width: u16,
+    height: u16,
+    src_rect: impl Into<Rect>,
+    flip_h: bool,
+    flip_v: bool,
+    color_key: u8,
+    silhouette: impl Into<Silhouette<'a>>,
+) {
+    flipped(
+        (x, y),
+        (source, width, height),
+        src_rect.into(),
+        (flip_h, flip_v),
+        PixelOp::Silhouette(color_key, silhouette.into()),
+    );
+}
+
+/// Draws a whole image at the given position,
+/// mirrored horizontally, vertically or both.
+///
+/// Pixels of the image's [color key](Image::color_key), if any,
+/// are skipped.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut sprite = load_gif("player.gif")?;
+/// sprite.set_color_key(Some(0));
+/// let (mut x, mut facing_left) = (100, false);
+/// while !shutting_down() {
+///     wait_vbl();
+///     if key_state(KeyCode::KEY_LEFT) {
+///         x -= 1;
+///         facing_left = true;
+///     } else if key_state(KeyCode::KEY_RIGHT) {
+///         x += 1;
+///         facing_left = false;
+///     }
+///     clear_screen();
+///     draw_image_flipped(x, 80, &sprite, facing_left, false);
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn draw_image_flipped(x: i32, y: i32, image: &Image, flip_h: bool, flip_v: bool) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    flipped(
+        (x, y),
+        (image.data(), width, height),
+        Rect::new(0, 0, width, height),
+        (flip_h, flip_v),
+        image.color_key().map_or(PixelOp::Copy, PixelOp::Masked),
+    );
+}
+
+/// How the pixels of a region are written to the screen by [`flipped`].
+#[derive(Debug, Copy, Clone)]
+enum PixelOp<'a> {
+    /// Copy every pixel.
+    Copy,
+    /// Copy the pixels other than the color key.
+    Masked(u8),
+    /// Draw the pixels other than the color key as a silhouette.
+    Silhouette(u8, Silhouette<'a>),
+}
+
+impl PixelOp<'_> {
+    /// Writes a row of source pixels over a row of the screen.
+    fn write_row<'s>(self, dst_row: &mut [u8], src_row: impl Iterator<Item = &'s u8>) {
+        let pixels = dst_row.iter_mut().zip(src_row);
+        match self {
+            PixelOp::Copy => pixels.for_each(|(pixel, &color)| *pixel = color),
+            PixelOp::Masked(key) => {
+                for (pixel, &color) in pixels.filter(|(_, &color)| color != key) {
+                    *pixel = color;
+                }
+            }
+            PixelOp::Silhouette(key, Silhouette::Color(fill)) => {
+                for (pixel, _) in pixels.filter(|(_, &color)| color != key) {
+                    *pixel = fill;
+                }
+            }
+            PixelOp::Silhouette(key, Silhouette::Remap(table)) => {
+                for (pixel, _) in pixels.filter(|(_, &color)| color != key) {
+                    *pixel = table[*pixel as usize];
+                }
+            }
+        }
+    }
+}
+
+/// Copies a region of a pixel buffer onto the screen,
+/// mirrored if requested.
+fn flipped(
+    (x, y): (i32, i32),
+    (source, width, height): (&[u8], u16, u16),
+    src_rect: Rect,
+    (flip_h, flip_v): (bool, bool),
+    op: PixelOp,
+) {
+    if width as usize * height as usize > source.len() {
+        panic!(
+            "blit: source data ({} bytes) is too short for resolution {}x{}",
+            source.len(),
+            width,
+            height
+        );
+    }
+    let src = src_rect.clamp_to(&Rect::new(0, 0, width, height));
+    if src.is_empty() || !video_mode().is_graphics() {
+        return;
+    }
+
+    // the mirrored region covers the same rectangle on the screen,
+    // of which only the visible part is drawn
+    let (x, y) = offset_xy(x, y);
+    let (x, y) = (x as i64, y as i64);
+    let (src_w, src_h) = (src.width as i64, src.height as i64);
+    let bounds = draw_bounds();
+    let x0 = x.max(bounds.left() as i64);
+    let x1 = (x + src_w).min(bounds.right());
+    let y0 = y.max(bounds.top() as i64);
+    let y1 = (y + src_h).min(bounds.bottom());
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    // the columns of the source row for the visible columns,
+    // in the order of the source
+    let (start, end) = if flip_h {
+        (x + src_w - x1, x + src_w - x0)
+    } else {
+        (x0 - x, x1 - x)
+    };
+    let columns = src.x as usize + start as usize..src.x as usize + end as usize;
+    with_screen_pixels(|screen, screen_w, _| {
+        for dst_y in y0..y1 {
+            let row = if flip_v {
+                y + src_h - 1 - dst_y
+            } else {
+                dst_y - y
+            };
+            let src_row = &source[(src.y as usize + row as usize) * width as usize..];
+            let src_row = &src_row[columns.clone()];
+            let start = dst_y as usize * screen_w as usize;
+            let dst_row = &mut screen[start + x0 as usize..start + x1 as usize];
+            match (flip_h, op) {
+                (false, PixelOp::Copy) => dst_row.copy_from_slice(src_row),
+                (false, op) => op.write_row(dst_row, src_row.iter()),
+                (true, op) => op.write_row(dst_row, src_row.iter().rev()),
+            }
+        }
+    });
+}
+
 //void blit( int x, int y, unsigned char* source, int width, int height, int srcx, int srcy, int srcw, int srch );
 //void maskblit( int x, int y, unsigned char* source, int width, int height, int srcx, int srcy, int srcw, int srch, int colorkey );
 
@@ -404,12 +2298,248 @@ pub fn clear_screen() {
     }
 }
 
+/// Fills the whole screen with the given palette color index
+/// in a single pass.
+///
+/// This is equivalent to [`clear_screen`] when `color` is 0.
+/// Like the other drawing operations,
+/// it applies to the off-screen buffer when double buffering is enabled.
+/// The draw offset does not apply.
+///
+/// Does nothing unless the video is in graphics mode.
+pub fn fill_screen(color: u8) {
+    if !video_mode().is_graphics() {
+        return;
+    }
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    unsafe {
+        let buf = dos_like_sys::screenbuffer();
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        std::ptr::write_bytes(buf, color, width * height);
+    }
+}
+
 /// Gets the color of a single pixel on the screen.
 ///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn pixel(x: i32, y: i32) -> u8 {
-    unsafe { dos_like_sys::getpixel(x as c_int, y as c_int) as u8 }
+    let (x, y) = offset_xy(x, y);
+    unsafe { dos_like_sys::getpixel(x, y) as u8 }
+}
+
+/// Gets the color of a single pixel on the screen,
+/// or `None` if the position is outside of the screen.
+///
+/// Unlike [`pixel`],
+/// this reads the screen buffer directly,
+/// which makes it suitable for probing many pixels.
+/// The draw offset applies.
+///
+/// Always returns `None` unless the video is in graphics mode.
+pub fn pixel_at(x: i32, y: i32) -> Option<u8> {
+    if !video_mode().is_graphics() {
+        return None;
+    }
+    let (x, y) = offset_xy(x, y);
+    // Safety: the position is checked against the screen size,
+    // and the buffer is only read here
+    unsafe {
+        let width = dos_like_sys::screenwidth();
+        let height = dos_like_sys::screenheight();
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return None;
+        }
+        let buf = dos_like_sys::screenbuffer();
+        Some(*buf.add(y as usize * width as usize + x as usize))
+    }
+}
+
+/// Reads the colors of the pixels in a region of the screen
+/// into `out`, row by row,
+/// replacing its previous contents.
+///
+/// The region is clipped to the screen,
+/// and the region effectively read is returned,
+/// in the same coordinates as `rect`.
+/// The draw offset applies.
+///
+/// Reads nothing unless the video is in graphics mode.
+pub fn pixels_rect(rect: impl Into<Rect>, out: &mut Vec<u8>) -> Rect {
+    let rect = rect.into();
+    out.clear();
+    if !video_mode().is_graphics() {
+        return Rect::new(rect.x, rect.y, 0, 0);
+    }
+    let (dx, dy) = draw_offset();
+    let screen = Rect::new(0, 0, screen_width(), screen_height());
+    let clipped = rect.offset(dx, dy).clamp_to(&screen);
+
+    // Safety: the region is clipped to the screen,
+    // and the buffer is only read here
+    unsafe {
+        let buf = dos_like_sys::screenbuffer();
+        let width = screen.width as usize;
+        out.reserve(clipped.width as usize * clipped.height as usize);
+        for y in clipped.y..clipped.y + clipped.height as i32 {
+            let row = buf.add(y as usize * width + clipped.x as usize);
+            out.extend_from_slice(std::slice::from_raw_parts(row, clipped.width as usize));
+        }
+    }
+    clipped.offset(-dx, -dy)
+}
+
+/// Captures a region of the screen into a new image,
+/// with the current palette.
+///
+/// The pixels are read from the buffer which drawing functions target,
+/// which is the off-screen buffer when double buffering is enabled,
+/// so a frame can be captured after drawing it and before swapping.
+///
+/// The region is clipped to the screen,
+/// so the image may be smaller than requested,
+/// or even empty.
+/// The draw offset applies.
+///
+/// Captures nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// // keep what is under a dialog box to restore it later
+/// let backing = capture(60, 50, 200, 100);
+/// set_color(1);
+/// bar(60, 50, 200, 100);
+/// // ...
+/// blit(60, 50, backing.data(), 200, 100, 0, 0, 200, 100);
+/// ```
+pub fn capture(x: i32, y: i32, width: u16, height: u16) -> Image {
+    let mut pixels = Vec::new();
+    let region = pixels_rect(Rect::new(x, y, width, height), &mut pixels);
+    Image::from_pixels(
+        region.width as u32,
+        region.height as u32,
+        palette::palette_bytes(),
+        256,
+        pixels,
+    )
+}
+
+/// The axis along which a region is flipped,
+/// see [`flip_screen_rect`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum FlipAxis {
+    /// Mirror left to right
+    Horizontal,
+    /// Mirror top to bottom
+    Vertical,
+    /// Mirror both ways,
+    /// which is the same as rotating by 180 degrees
+    Both,
+}
+
+/// Flips the pixels of a region of the screen in place.
+///
+/// The region is clipped to the screen first,
+/// and only the visible part of it is flipped.
+/// With an odd width or height,
+/// the center column or row stays in place.
+/// The draw offset applies.
+///
+/// Does nothing unless the video is in graphics mode.
+pub fn flip_screen_rect(x: i32, y: i32, width: u16, height: u16, axis: FlipAxis) {
+    if !video_mode().is_graphics() {
+        return;
+    }
+    let screen = Rect::new(0, 0, screen_width(), screen_height());
+    let (x, y) = offset_xy(x, y);
+    let rect = Rect::new(x, y, width, height).clamp_to(&screen);
+    if rect.width == 0 || rect.height == 0 {
+        return;
+    }
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let buf = unsafe { screen_buffer() };
+    let stride = screen.width as usize;
+    let (left, right) = (rect.x as usize, rect.x as usize + rect.width as usize);
+    let (top, bottom) = (rect.y as usize, rect.y as usize + rect.height as usize);
+
+    if matches!(axis, FlipAxis::Vertical | FlipAxis::Both) {
+        for i in 0..(bottom - top) / 2 {
+            let (upper, lower) = buf.split_at_mut((bottom - 1 - i) * stride);
+            upper[(top + i) * stride..][left..right].swap_with_slice(&mut lower[left..right]);
+        }
+    }
+    if matches!(axis, FlipAxis::Horizontal | FlipAxis::Both) {
+        for row in top..bottom {
+            buf[row * stride..][left..right].reverse();
+        }
+    }
+}
+
+/// Whether drawing primitives go through the Rust rasterizer.
+static RUST_RASTERIZER: AtomicBool = AtomicBool::new(false);
+
+/// Enables or disables drawing every primitive to the screen
+/// through the rasterizer in this crate instead of the engine.
+///
+/// The engine may select slightly different pixels
+/// across versions and platforms.
+/// With the Rust rasterizer,
+/// the same calls produce the same pixels everywhere,
+/// which matters when pixel-derived state must agree between machines,
+/// such as in lockstep networking.
+/// It is disabled by default.
+///
+/// Filled polygons are always drawn by the Rust rasterizer,
+/// and so are circles and ellipses crossing the edges of the screen.
+/// The switch additionally covers
+/// [`put_pixel`], [`h_line`], [`line`], [`draw_poly`],
+/// [`rectangle`], [`bar`], all circles and ellipses,
+/// [`flood_fill`] and [`boundary_fill`].
+/// Text and blitting functions copy pixels as they are,
+/// and are not affected.
+///
+/// The pixels are selected by these rules:
+///
+/// - lines use Bresenham's algorithm from the first point to the second,
+///   both inclusive,
+///   with the error term starting at half of the major axis delta
+///   (negated for steep lines),
+///   and a poly-line draws a line between each point and the next;
+/// - circles use the midpoint algorithm,
+///   and filled circles are drawn as horizontal spans
+///   of twice the offset from the center,
+///   starting at the center minus that offset;
+/// - ellipses use the two region midpoint algorithm,
+///   filled the same way as circles;
+/// - filled polygons are drawn with the even-odd rule
+///   at integer rows,
+///   with each edge intersection truncated towards zero,
+///   and the bottom-most row excluded;
+/// - flood fills and boundary fills cover
+///   the pixels connected horizontally and vertically,
+///   never diagonally.
+pub fn use_rust_rasterizer(enabled: bool) {
+    RUST_RASTERIZER.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether every primitive is drawn through the rasterizer in this crate,
+/// see [`use_rust_rasterizer`].
+pub fn is_rust_rasterizer() -> bool {
+    RUST_RASTERIZER.load(Ordering::Relaxed)
+}
+
+/// Whether to draw to the screen through the Rust rasterizer,
+/// which is also the case while a clip rectangle is set.
+#[inline]
+fn rust_raster() -> bool {
+    (is_rust_rasterizer() || clip_rect().is_some()) && video_mode().is_graphics()
 }
 
 /// Puts a color on a single pixel.
@@ -417,8 +2547,70 @@ pub fn pixel(x: i32, y: i32) -> u8 {
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn put_pixel(x: u16, y: u16, color: u8) {
+    let (x, y) = offset_xy(x as i32, y as i32);
+    if rust_raster() {
+        raster::put_pixel(&raster::Clip::screen(color), x, y);
+        return;
+    }
     unsafe {
-        dos_like_sys::putpixel(x as c_int, y as c_int, color as c_int);
+        dos_like_sys::putpixel(x, y, color as c_int);
+    }
+}
+
+/// Puts colors on many pixels at once,
+/// given as `(x, y, color)`.
+///
+/// The pixels are written straight into the screen buffer
+/// in a single pass,
+/// which is much faster than calling [`put_pixel`] for each of them,
+/// as for particles and star fields.
+/// The draw offset applies,
+/// and pixels outside of the screen or the clip rectangle are skipped.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let stars: Vec<(i32, i32, u8)> = (0..200)
+///     .map(|i| ((i * 97) % 320, (i * 61) % 200, 16 + (i % 16) as u8))
+///     .collect();
+/// while !shutting_down() {
+///     wait_vbl();
+///     clear_screen();
+///     put_pixels(stars.iter().copied());
+/// }
+/// ```
+pub fn put_pixels(pixels: impl IntoIterator<Item = (i32, i32, u8)>) {
+    if !video_mode().is_graphics() {
+        return;
+    }
+    let offset = draw_offset();
+    let bounds = draw_bounds();
+    with_screen_pixels(|screen, screen_w, _| {
+        put_pixels_in(screen, screen_w as usize, bounds, offset, pixels)
+    });
+}
+
+/// Puts colors on pixels of a buffer with rows of `row_len` pixels,
+/// moved by `offset` and skipping those outside of `bounds`.
+fn put_pixels_in(
+    target: &mut [u8],
+    row_len: usize,
+    bounds: Rect,
+    offset: (i32, i32),
+    pixels: impl IntoIterator<Item = (i32, i32, u8)>,
+) {
+    let (left, top) = (bounds.left() as i64, bounds.top() as i64);
+    let (right, bottom) = (bounds.right(), bounds.bottom());
+    let (dx, dy) = (offset.0 as i64, offset.1 as i64);
+    for (x, y, color) in pixels {
+        let (x, y) = (x as i64 + dx, y as i64 + dy);
+        if x >= left && x < right && y >= top && y < bottom {
+            target[y as usize * row_len + x as usize] = color;
+        }
     }
 }
 
@@ -427,8 +2619,13 @@ pub fn put_pixel(x: u16, y: u16, color: u8) {
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn h_line(x: i32, y: i32, len: u16, color: u8) {
+    let (x, y) = offset_xy(x, y);
+    if rust_raster() {
+        raster::bar(&raster::Clip::screen(color), x, y, len, 1);
+        return;
+    }
     unsafe {
-        dos_like_sys::hline(x as c_int, y as c_int, len as c_int, color as c_int);
+        dos_like_sys::hline(x, y, len as c_int, color as c_int);
     }
 }
 
@@ -456,115 +2653,491 @@ pub fn get_color() -> u8 {
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn line(x1: i32, y1: i32, x2: i32, y2: i32) {
+    let (x1, y1) = offset_xy(x1, y1);
+    let (x2, y2) = offset_xy(x2, y2);
+    if rust_raster() {
+        raster::line(&raster::Clip::screen(get_color()), x1, y1, x2, y2);
+        return;
+    }
     unsafe {
-        dos_like_sys::line(x1 as c_int, y1 as c_int, x2 as c_int, y2 as c_int);
+        dos_like_sys::line(x1, y1, x2, y2);
     }
 }
 
-/// Draws a non-filled rectangle on the screen.
+/// Applies the current draw offset to a subpixel position.
+#[inline]
+fn offset_f(x: f32, y: f32) -> (f32, f32) {
+    let (dx, dy) = draw_offset();
+    (x + dx as f32, y + dy as f32)
+}
+
+/// Puts a color on the pixel nearest to a subpixel position.
+///
+/// Integer coordinates are pixel centers,
+/// and a position is snapped to the nearest one,
+/// with halfway cases going towards positive infinity
+/// (`(x + 0.5).floor()`).
+/// So a point moving by 0.25 pixels per frame
+/// moves to the next pixel exactly every fourth frame.
+///
+/// The same rule applies to [`line_f`] and [`circle_f`].
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn put_pixel_f(x: f32, y: f32, color: u8) {
+    let (x, y) = offset_f(x, y);
+    raster::put_pixel_f(&raster::Clip::screen(color), x, y);
+}
+
+/// Draws a line on the screen between two subpixel positions.
+///
+/// Unlike [`line()`], which only takes whole pixels,
+/// one pixel is drawn for each pixel along the longer axis,
+/// at the exact position of the line on the other axis
+/// snapped to the nearest pixel
+/// (see [`put_pixel_f`] for the rounding rule).
+/// This keeps the line steady
+/// while its endpoints move by fractions of a pixel.
+///
+/// The line is drawn by the rasterizer in this crate
+/// and clipped like the other primitives.
+///
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut t: f32 = 0.;
+/// while !shutting_down() {
+///     wait_vbl();
+///     clear_screen();
+///     t += 0.01;
+///     set_color(15);
+///     line_f(160., 100., 160. + t.cos() * 90., 100. + t.sin() * 90.);
+/// }
+/// ```
+pub fn line_f(x1: f32, y1: f32, x2: f32, y2: f32) {
+    let (x1, y1) = offset_f(x1, y1);
+    let (x2, y2) = offset_f(x2, y2);
+    raster::line_f(&raster::Clip::screen(get_color()), x1, y1, x2, y2);
+}
+
+/// Draws a circle with no filling on the screen,
+/// around a subpixel center and with a subpixel radius.
+///
+/// Each eighth of the circle is drawn
+/// with one pixel for each pixel along its longer axis,
+/// at the exact position of the circle snapped to the nearest pixel
+/// (see [`put_pixel_f`] for the rounding rule).
+///
+/// The circle is drawn by the rasterizer in this crate
+/// and clipped like the other primitives.
+///
+/// Only makes sense in graphics mode.
+pub fn circle_f(x: f32, y: f32, r: f32) {
+    let (x, y) = offset_f(x, y);
+    raster::circle_f(&raster::Clip::screen(get_color()), x, y, r);
+}
+
+/// Draws a non-filled rectangle on the screen,
+/// with its top left corner at `(x1, y1)`.
+///
+/// As in the engine, the bottom edge is drawn `height` rows below the top one,
+/// so the outline spans `width` by `height + 1` pixels.
+/// See [`rectangle_r`] and [`rectangle_corners`]
+/// to outline exactly a given region instead.
 ///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn rectangle(x1: i32, y1: i32, width: u16, height: u16) {
+    let (x1, y1) = offset_xy(x1, y1);
+    if rust_raster() {
+        raster::rectangle(&raster::Clip::screen(get_color()), x1, y1, width, height);
+        return;
+    }
     unsafe {
-        dos_like_sys::rectangle(x1 as c_int, y1 as c_int, width as c_int, height as c_int);
+        dos_like_sys::rectangle(x1, y1, width as c_int, height as c_int);
+    }
+}
+
+/// Gets the arguments to [`rectangle`]
+/// which outline exactly the given region,
+/// or `None` if it is empty.
+fn outline_args(rect: Rect) -> Option<(i32, i32, u16, u16)> {
+    if rect.width == 0 || rect.height == 0 {
+        return None;
+    }
+    Some((rect.x, rect.y, rect.width, rect.height - 1))
+}
+
+/// Draws a non-filled rectangle on the screen
+/// covering exactly the given region.
+///
+/// A region 1 pixel wide or tall is drawn as a line,
+/// and nothing is drawn if it is empty.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn rectangle_r(rect: impl Into<Rect>) {
+    if let Some((x, y, width, height)) = outline_args(rect.into()) {
+        rectangle(x, y, width, height);
     }
 }
 
-/// Draws a filled rectangle on the screen.
+/// Draws a non-filled rectangle on the screen
+/// between two opposite corners, both inclusive.
+///
+/// The corners may be given in any order,
+/// such as the start and end points of a mouse drag.
+/// If both corners are on the same column or row,
+/// a line is drawn,
+/// and if they are the same point, a single pixel.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn rectangle_corners(x1: i32, y1: i32, x2: i32, y2: i32) {
+    rectangle_r(Rect::from_corners((x1, y1), (x2, y2)));
+}
+
+/// Draws a filled rectangle on the screen,
+/// with its top left corner at `(x1, y1)`.
+///
+/// Nothing is drawn if either dimension is zero.
+/// See [`bar_corners`] to fill a rectangle
+/// between two opposite corners instead.
 ///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn bar(x1: i32, y1: i32, width: u16, height: u16) {
+    let (x1, y1) = offset_xy(x1, y1);
+    if rust_raster() {
+        raster::bar(&raster::Clip::screen(get_color()), x1, y1, width, height);
+        return;
+    }
+    unsafe {
+        dos_like_sys::bar(x1, y1, width as c_int, height as c_int);
+    }
+}
+
+/// Draws a filled rectangle on the screen
+/// covering the given region.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn bar_r(rect: impl Into<Rect>) {
+    let rect = rect.into();
+    bar(rect.x, rect.y, rect.width, rect.height);
+}
+
+/// Draws a filled rectangle on the screen
+/// between two opposite corners, both inclusive.
+///
+/// The corners may be given in any order.
+/// If both corners are on the same column or row,
+/// a line is drawn,
+/// and if they are the same point, a single pixel.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn bar_corners(x1: i32, y1: i32, x2: i32, y2: i32) {
+    bar_r(Rect::from_corners((x1, y1), (x2, y2)));
+}
+
+/// Whether a shape with the given center and radii
+/// can be drawn by the engine,
+/// which only happens when it lies fully on the screen
+/// and the Rust rasterizer is not required.
+/// Otherwise the engine would write outside of the screen buffer.
+fn engine_fits(x: c_int, y: c_int, rx: u16, ry: u16) -> bool {
+    let (x, y, rx, ry) = (x as i64, y as i64, rx as i64, ry as i64);
+    !rust_raster()
+        && x - rx >= 0
+        && y - ry >= 0
+        && x + rx < screen_width() as i64
+        && y + ry < screen_height() as i64
+}
+
+/// Calls the function with the pixels of the screen buffer
+/// and its dimensions.
+fn with_screen_pixels(f: impl FnOnce(&mut [u8], u16, u16)) {
+    // Safety: the slice does not outlive this call,
+    // and nothing else draws to the screen in the meantime
     unsafe {
-        dos_like_sys::bar(x1 as c_int, y1 as c_int, width as c_int, height as c_int);
+        let width = dos_like_sys::screenwidth();
+        let height = dos_like_sys::screenheight();
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return;
+        }
+        let pixels = std::slice::from_raw_parts_mut(buf, width as usize * height as usize);
+        f(pixels, width as u16, height as u16);
     }
 }
 
 /// Draws a circle with no filling on the screen.
 ///
+/// A circle fully on the screen is drawn by the engine,
+/// unless [`use_rust_rasterizer`] is on.
+/// Otherwise it is drawn by the rasterizer in this crate,
+/// which selects the same pixels and clips them to the screen,
+/// so its center may be anywhere,
+/// including outside of the screen.
+///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn circle(x: i32, y: i32, r: u16) {
-    unsafe {
-        dos_like_sys::circle(x as c_int, y as c_int, r as c_int);
+    if !video_mode().is_graphics() {
+        return;
+    }
+    let (x, y) = offset_xy(x, y);
+    if engine_fits(x, y, r, r) {
+        unsafe {
+            dos_like_sys::circle(x, y, r as c_int);
+        }
+        return;
     }
+    raster::circle(&raster::Clip::screen(get_color()), x, y, r);
 }
 
 /// Draws a filled circle on the screen.
 ///
+/// A circle fully on the screen is drawn by the engine,
+/// unless [`use_rust_rasterizer`] is on.
+/// Otherwise it is drawn by the rasterizer in this crate,
+/// which selects the same pixels and clips them to the screen,
+/// so its center may be anywhere,
+/// including outside of the screen.
+///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn fill_circle(x: i32, y: i32, r: u16) {
-    unsafe {
-        dos_like_sys::fillcircle(x as c_int, y as c_int, r as c_int);
+    if !video_mode().is_graphics() {
+        return;
     }
+    let (x, y) = offset_xy(x, y);
+    if engine_fits(x, y, r, r) {
+        unsafe {
+            dos_like_sys::fillcircle(x, y, r as c_int);
+        }
+        return;
+    }
+    raster::fill_circle(&raster::Clip::screen(get_color()), x, y, r);
+}
+
+/// Gets the vertical radius which makes a circle of radius `r`
+/// look round in the current video mode.
+fn aspect_corrected_radius(r: u16) -> u16 {
+    (r as f32 / video_mode().pixel_aspect())
+        .round()
+        .min(u16::MAX as f32) as u16
+}
+
+/// Draws a circle with no filling on the screen,
+/// compensating for the pixel aspect ratio of the current video mode
+/// so that it appears round on a 4:3 display
+/// (see [`VideoMode::pixel_aspect`]).
+///
+/// `r` is the horizontal radius in pixels.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn circle_aspect(x: i32, y: i32, r: u16) {
+    ellipse(x, y, r, aspect_corrected_radius(r));
+}
+
+/// Draws a filled circle on the screen,
+/// compensating for the pixel aspect ratio of the current video mode
+/// so that it appears round on a 4:3 display
+/// (see [`VideoMode::pixel_aspect`]).
+///
+/// `r` is the horizontal radius in pixels.
+///
+/// Only makes sense in graphics mode.
+#[inline]
+pub fn fill_circle_aspect(x: i32, y: i32, r: u16) {
+    fill_ellipse(x, y, r, aspect_corrected_radius(r));
 }
 
 /// Draws a non-filled ellipse on the screen.
 ///
+/// An ellipse fully on the screen is drawn by the engine,
+/// unless [`use_rust_rasterizer`] is on.
+/// Otherwise it is drawn by the rasterizer in this crate,
+/// which selects the same pixels and clips them to the screen,
+/// so its center may be anywhere,
+/// including outside of the screen.
+///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn ellipse(x: i32, y: i32, rx: u16, ry: u16) {
-    unsafe {
-        dos_like_sys::ellipse(x as c_int, y as c_int, rx as c_int, ry as c_int);
+    if !video_mode().is_graphics() {
+        return;
+    }
+    let (x, y) = offset_xy(x, y);
+    if engine_fits(x, y, rx, ry) {
+        unsafe {
+            dos_like_sys::ellipse(x, y, rx as c_int, ry as c_int);
+        }
+        return;
     }
+    raster::ellipse(&raster::Clip::screen(get_color()), x, y, rx, ry);
 }
 
 /// Draws a filled ellipse on the screen.
 ///
+/// An ellipse fully on the screen is drawn by the engine,
+/// unless [`use_rust_rasterizer`] is on.
+/// Otherwise it is drawn by the rasterizer in this crate,
+/// which selects the same pixels and clips them to the screen,
+/// so its center may be anywhere,
+/// including outside of the screen.
+///
 /// Only makes sense in graphics mode.
 #[inline]
 pub fn fill_ellipse(x: i32, y: i32, rx: u16, ry: u16) {
-    unsafe {
-        dos_like_sys::fillellipse(x as c_int, y as c_int, rx as c_int, ry as c_int);
+    if !video_mode().is_graphics() {
+        return;
     }
+    let (x, y) = offset_xy(x, y);
+    if engine_fits(x, y, rx, ry) {
+        unsafe {
+            dos_like_sys::fillellipse(x, y, rx as c_int, ry as c_int);
+        }
+        return;
+    }
+    raster::fill_ellipse(&raster::Clip::screen(get_color()), x, y, rx, ry);
+}
+
+/// Error type for drawing polygons.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum PolyError {
+    /// The list of coordinates has an odd length,
+    /// so it does not describe whole points
+    OddLength {
+        /// The length of the list of coordinates.
+        len: usize,
+    },
+}
+
+impl std::fmt::Display for PolyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PolyError::OddLength { len } => {
+                write!(f, "Odd number of polygon coordinates ({})", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolyError {}
+
+fn check_poly(points: &[i32]) -> Result<(), PolyError> {
+    if !points.len().is_multiple_of(2) {
+        return Err(PolyError::OddLength { len: points.len() });
+    }
+    Ok(())
+}
+
+/// Gets the coordinates of the whole points in the list,
+/// without the last coordinate if the list is not even.
+fn whole_points(points: &[i32]) -> &[i32] {
+    &points[..points.len() / 2 * 2]
 }
 
 /// Draws a poly-line on the screen,
 /// with the given flat list of XY coordinates in pixels.
 ///
-/// Only makes sense in graphics mode.
-///
-/// # Panic
+/// Nothing is drawn if the list is empty.
+/// If the list is not even, its last coordinate is ignored.
+/// See [`try_draw_poly`] to detect that case instead.
 ///
-/// Panics if the given list of points is empty or not even.
+/// Only makes sense in graphics mode.
 #[inline]
 pub fn draw_poly(points: &[i32]) {
-    assert!(!points.is_empty() && points.len() % 2 == 0);
+    // cannot fail with whole points
+    let _ = try_draw_poly(whole_points(points));
+}
+
+/// Draws a poly-line on the screen,
+/// with the given flat list of XY coordinates in pixels,
+/// or returns an error if the list is not even.
+///
+/// Nothing is drawn if the list is empty.
+///
+/// Only makes sense in graphics mode.
+pub fn try_draw_poly(points: &[i32]) -> Result<(), PolyError> {
+    check_poly(points)?;
+    if points.is_empty() {
+        return Ok(());
+    }
+
+    let points = offset_points(points);
+    if rust_raster() {
+        raster::draw_poly(&raster::Clip::screen(get_color()), &points);
+        return Ok(());
+    }
 
     // Safety: although the pointer type is *mut,
     // it never really writes via the pointer.
     unsafe {
         dos_like_sys::drawpoly(points.as_ptr() as *mut _, (points.len() / 2) as c_int);
     }
+    Ok(())
 }
 
 /// Draws a filled polygon on the screen,
 /// with the given flat list of XY coordinates in pixels.
 ///
-/// Only makes sense in graphics mode.
-///
-/// # Panic
+/// The polygon is filled with an even-odd rule,
+/// and there is no limit to the number of points.
+/// Nothing is drawn if the list is empty.
+/// If the list is not even, its last coordinate is ignored.
+/// See [`try_fill_poly`] to detect that case instead.
 ///
-/// Panics if the given list of points is empty or not even.
+/// Only makes sense in graphics mode.
 #[inline]
 pub fn fill_poly(points: &[i32]) {
-    assert!(!points.is_empty() && points.len() % 2 == 0);
+    // cannot fail with whole points
+    let _ = try_fill_poly(whole_points(points));
+}
 
-    // Safety: although the pointer type is *mut,
-    // it never really writes via the pointer.
-    unsafe {
-        dos_like_sys::fillpoly(points.as_ptr() as *mut _, (points.len() / 2) as c_int);
+/// Draws a filled polygon on the screen,
+/// with the given flat list of XY coordinates in pixels,
+/// or returns an error if the list is not even.
+///
+/// The polygon is filled with an even-odd rule,
+/// and there is no limit to the number of points.
+/// Nothing is drawn if the list is empty.
+///
+/// Only makes sense in graphics mode.
+pub fn try_fill_poly(points: &[i32]) -> Result<(), PolyError> {
+    check_poly(points)?;
+    if !video_mode().is_graphics() {
+        return Ok(());
     }
+
+    let points = offset_points(points);
+    raster::fill_poly(&raster::Clip::screen(get_color()), &points);
+    Ok(())
 }
 
 /// Flood fills the screen from the given position.
 ///
 /// Only makes sense in graphics mode.
 pub fn flood_fill(x: i32, y: i32) {
+    let (x, y) = offset_xy(x, y);
+    if rust_raster() {
+        let color = get_color();
+        let bounds = draw_bounds();
+        with_pixels_in(bounds, |pixels, width, height| {
+            raster::flood_fill(pixels, width, height, x - bounds.x, y - bounds.y, color)
+        });
+        return;
+    }
     unsafe {
-        dos_like_sys::floodfill(x as c_int, y as c_int);
+        dos_like_sys::floodfill(x, y);
     }
 }
 
@@ -573,22 +3146,146 @@ pub fn flood_fill(x: i32, y: i32) {
 ///
 /// Only makes sense in graphics mode.
 pub fn boundary_fill(x: i32, y: i32, boundary: u8) {
+    let (x, y) = offset_xy(x, y);
+    if rust_raster() {
+        let color = get_color();
+        let bounds = draw_bounds();
+        with_pixels_in(bounds, |pixels, width, height| {
+            let (x, y) = (x - bounds.x, y - bounds.y);
+            raster::boundary_fill(pixels, width, height, x, y, boundary, color)
+        });
+        return;
+    }
     unsafe {
-        dos_like_sys::boundaryfill(x as c_int, y as c_int, boundary as c_int);
+        dos_like_sys::boundaryfill(x, y, boundary as c_int);
+    }
+}
+
+/// Blits a text to the screen at the given position.
+///
+/// XY coordinates are in pixels.
+///
+/// With the built-in fonts,
+/// nothing is drawn if the text contains a null character (`\0`).
+///
+/// Only makes sense in graphics mode.
+pub fn out_text_xy(x: i32, y: i32, text: impl AsRef<[u8]>) {
+    if let Some((font, style)) = current_soft_font() {
+        soft_font::draw_text(font, style, x, y, text.as_ref(), None, false);
+        return;
+    }
+
+    let (x, y) = offset_xy(x, y);
+    with_c_text(text.as_ref(), |text| {
+        draw_clipped(x, y, |x, y| unsafe {
+            dos_like_sys::outtextxy(x, y, text as *const _);
+        })
+    });
+}
+
+/// Additional effects for text drawn via [`out_text_styled`].
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct TextEffect {
+    /// The color of an outline around the text, if any.
+    pub outline: Option<u8>,
+    /// The offset and color of a drop shadow, if any.
+    pub shadow: Option<(i32, i32, u8)>,
+}
+
+impl TextEffect {
+    /// Creates a text effect with no outline and no shadow.
+    #[inline]
+    pub const fn new() -> Self {
+        TextEffect {
+            outline: None,
+            shadow: None,
+        }
+    }
+
+    /// Adds an outline of the given color around the text.
+    #[inline]
+    pub const fn with_outline(mut self, color: u8) -> Self {
+        self.outline = Some(color);
+        self
+    }
+
+    /// Adds a drop shadow of the given color,
+    /// offset by the given number of pixels.
+    #[inline]
+    pub const fn with_shadow(mut self, dx: i32, dy: i32, color: u8) -> Self {
+        self.shadow = Some((dx, dy, color));
+        self
     }
 }
 
-/// Blits a text to the screen at the given position.
+/// Blits a text to the screen at the given position,
+/// with an outline and/or a drop shadow
+/// to keep it readable over busy artwork.
+///
+/// The shadow is drawn first,
+/// then the outline (by stamping the text at the 8 neighboring pixels),
+/// and finally the text itself in the current color,
+/// using the current font and style (see [`set_text_style`]).
 ///
 /// XY coordinates are in pixels.
 ///
+/// With the built-in fonts,
+/// nothing is drawn if the text contains a null character (`\0`).
+///
 /// Only makes sense in graphics mode.
-pub fn out_text_xy(x: i32, y: i32, text: impl AsRef<[u8]>) {
-    let text = CString::new(text.as_ref()).unwrap();
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// set_color(15);
+/// out_text_styled(8, 8, "SCORE: 1000", TextEffect::new().with_outline(0).with_shadow(2, 2, 8));
+/// ```
+pub fn out_text_styled(x: i32, y: i32, text: impl AsRef<[u8]>, effect: TextEffect) {
+    let text = text.as_ref();
+    if let Some((font, style)) = current_soft_font() {
+        stamp_styled(x, y, effect, |x, y| {
+            soft_font::draw_text(font, style, x, y, text, None, false)
+        });
+        return;
+    }
 
-    unsafe {
-        dos_like_sys::outtextxy(x as c_int, y as c_int, text.as_ptr() as *const _);
+    let (x, y) = offset_xy(x, y);
+    // encoded once for all stamps
+    with_c_text(text, |text| {
+        stamp_styled(x, y, effect, |x, y| unsafe {
+            dos_like_sys::outtextxy(x, y, text as *const _);
+        })
+    });
+}
+
+/// Calls `stamp` at each position of the text effect,
+/// with the drawing color set accordingly,
+/// and at the position of the text itself in the current color.
+fn stamp_styled(x: i32, y: i32, effect: TextEffect, stamp: impl Fn(i32, i32)) {
+    let color = get_color();
+    if let Some((dx, dy, shadow)) = effect.shadow {
+        set_color(shadow);
+        stamp(x + dx, y + dy);
     }
+    if let Some(outline) = effect.outline {
+        set_color(outline);
+        for (dx, dy) in [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ] {
+            stamp(x + dx, y + dy);
+        }
+    }
+    set_color(color);
+    stamp(x, y);
 }
 
 /// Blits a text to the screen at the given position,
@@ -596,41 +3293,151 @@ pub fn out_text_xy(x: i32, y: i32, text: impl AsRef<[u8]>) {
 ///
 /// XY coordinates and width are in pixels.
 ///
+/// With the built-in fonts,
+/// nothing is drawn if the text contains a null character (`\0`).
+///
 /// Only makes sense in graphics mode.
 pub fn wrap_text_xy(x: i32, y: i32, text: impl AsRef<[u8]>, width: u16) {
-    let text = CString::new(text.as_ref()).unwrap();
-
-    unsafe {
-        dos_like_sys::wraptextxy(
-            x as c_int,
-            y as c_int,
-            text.as_ptr() as *const _,
-            width as c_int,
-        );
+    if let Some((font, style)) = current_soft_font() {
+        soft_font::draw_text(font, style, x, y, text.as_ref(), Some(width), false);
+        return;
     }
+
+    let (x, y) = offset_xy(x, y);
+    with_c_text(text.as_ref(), |text| {
+        draw_clipped(x, y, |x, y| unsafe {
+            dos_like_sys::wraptextxy(x, y, text as *const _, width as c_int);
+        })
+    });
 }
 
 /// Blits a text to the screen centered at the given position.
 ///
 /// XY coordinates and width are in pixels.
 ///
+/// With the built-in fonts,
+/// nothing is drawn if the text contains a null character (`\0`).
+///
 /// Only makes sense in graphics mode.
 pub fn center_text_xy(x: i32, y: i32, text: impl AsRef<[u8]>, width: u16) {
-    let text = CString::new(text.as_ref()).unwrap();
+    if let Some((font, style)) = current_soft_font() {
+        soft_font::draw_text(font, style, x, y, text.as_ref(), Some(width), true);
+        return;
+    }
 
-    unsafe {
-        dos_like_sys::centertextxy(
-            x as c_int,
-            y as c_int,
-            text.as_ptr() as *const _,
-            width as c_int,
-        );
+    let (x, y) = offset_xy(x, y);
+    with_c_text(text.as_ref(), |text| {
+        draw_clipped(x, y, |x, y| unsafe {
+            dos_like_sys::centertextxy(x, y, text as *const _, width as c_int);
+        })
+    });
+}
+
+/// The longest text passed to the engine through the reused text buffer.
+/// Longer text is copied into a new allocation.
+const TEXT_BUFFER_LIMIT: usize = 256;
+
+thread_local! {
+    /// A buffer for passing short null terminated text to the engine
+    /// without allocating on every call.
+    static TEXT_BUFFER: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(TEXT_BUFFER_LIMIT + 1));
+}
+
+/// Calls `f` with a pointer to a null terminated copy of the given text.
+///
+/// `f` is not called at all if the text contains a null character,
+/// since the engine would cut the text short.
+fn with_c_text(text: &[u8], f: impl FnOnce(*const c_char)) {
+    if text.contains(&0) {
+        return;
+    }
+    if text.len() > TEXT_BUFFER_LIMIT {
+        let text = CString::new(text).expect("text has no null characters");
+        f(text.as_ptr());
+        return;
     }
+    TEXT_BUFFER.with(|buf| {
+        let mut buf = buf.borrow_mut();
+        buf.clear();
+        buf.extend_from_slice(text);
+        buf.push(0);
+        f(buf.as_ptr() as *const c_char);
+    });
+}
+
+/// Gets the current font if it was created from glyph bitmaps,
+/// along with the bold, italic and underline flags.
+fn current_soft_font() -> Option<(&'static soft_font::SoftFont, (bool, bool, bool))> {
+    let (font, bold, italic, underline) = text_style();
+    soft_font::get(font).map(|f| (f, (bold, italic, underline)))
 }
 
 // -- Image reading
 
-/// An image loaded from a file.
+/// Error type for creating an image from pixel data,
+/// see [`Image::from_data`].
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum ImageError {
+    /// The image is too large to be drawn
+    /// (both width and height must be at most 65535)
+    TooLarge {
+        /// The requested width.
+        width: u32,
+        /// The requested height.
+        height: u32,
+    },
+    /// The pixel data does not have one byte per pixel
+    InvalidDataLength {
+        /// The expected length of the data in bytes.
+        expected: usize,
+        /// The actual length of the data in bytes.
+        actual: usize,
+    },
+    /// The palette is not made of whole RGB colors,
+    /// or has more than 256 of them
+    InvalidPaletteLength {
+        /// The length of the palette in bytes.
+        len: usize,
+    },
+    /// The palette does not fit in the global palette
+    /// from the given index
+    PaletteRangeOverflow {
+        /// The first index of the global palette requested.
+        start: u8,
+        /// The number of colors of the palette.
+        count: u32,
+    },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::TooLarge { width, height } => {
+                write!(f, "Image of {}x{} is too large", width, height)
+            }
+            ImageError::InvalidDataLength { expected, actual } => write!(
+                f,
+                "Invalid pixel data length (expected {} bytes, got {})",
+                expected, actual
+            ),
+            ImageError::InvalidPaletteLength { len } => {
+                write!(f, "Invalid palette length ({} bytes)", len)
+            }
+            ImageError::PaletteRangeOverflow { start, count } => write!(
+                f,
+                "Palette of {} colors does not fit from index {}",
+                count, start
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// An image loaded from a file or created from pixel data.
+///
+/// The image owns its pixel data,
+/// which is freed when the image is dropped.
 #[derive(Debug)]
 pub struct Image {
     /// The color palette of the image.
@@ -641,14 +3448,96 @@ pub struct Image {
     width: u32,
     /// The height of the image.
     height: u32,
-    /// Pointer to the indexed pixel data.
-    data: NonNull<u8>,
+    /// The indexed pixel data.
+    data: Box<[u8]>,
+    /// The palette index which is transparent, if any.
+    color_key: Option<u8>,
+    /// The pixel data remapped to the global palette,
+    /// see [`PaletteStrategy::RemapToCurrent`].
+    remapped: Mutex<Option<RemappedPixels>>,
 }
 
-unsafe impl Send for Image {}
-unsafe impl Sync for Image {}
+/// Pixel data remapped to the global palette.
+#[derive(Debug)]
+struct RemappedPixels {
+    /// The palette generation which the pixels were remapped to.
+    generation: u32,
+    /// The remapped pixel data.
+    pixels: Box<[u8]>,
+}
 
 impl Image {
+    /// Creates an image from pixel data decoded in Rust.
+    pub(crate) fn from_pixels(
+        width: u32,
+        height: u32,
+        palette: [u8; 768],
+        palette_count: u32,
+        pixels: Vec<u8>,
+    ) -> Image {
+        debug_assert_eq!(pixels.len(), width as usize * height as usize);
+        Image {
+            palette,
+            palette_count,
+            width,
+            height,
+            data: pixels.into_boxed_slice(),
+            color_key: None,
+            remapped: Mutex::new(None),
+        }
+    }
+
+    /// Creates an image from indexed pixel data, row by row,
+    /// and its palette,
+    /// such as for generated textures or images decoded in other formats.
+    ///
+    /// The palette is in RGB, 3 bytes per color,
+    /// with up to 256 colors.
+    /// Like the palettes of images loaded with [`load_gif`],
+    /// the values are expected in the range of [`set_pal`] (0 to 63).
+    ///
+    /// # Example
+    ///
+    /// A gradient of grays:
+    ///
+    /// ```
+    /// # use dos_like::*;
+    /// let palette: Vec<u8> = (0..64).flat_map(|v| [v, v, v]).collect();
+    /// let pixels: Vec<u8> = (0..64 * 16).map(|i| (i % 64) as u8).collect();
+    /// let image = Image::from_data(64, 16, pixels, &palette)?;
+    /// assert_eq!(image.palette_count(), 64);
+    /// # Ok::<(), ImageError>(())
+    /// ```
+    pub fn from_data(
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+        palette: &[u8],
+    ) -> Result<Image, ImageError> {
+        if width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(ImageError::TooLarge { width, height });
+        }
+        let expected = width as usize * height as usize;
+        if pixels.len() != expected {
+            return Err(ImageError::InvalidDataLength {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        if !palette.len().is_multiple_of(3) || palette.len() > 768 {
+            return Err(ImageError::InvalidPaletteLength { len: palette.len() });
+        }
+        let mut full_palette = [0; 768];
+        full_palette[..palette.len()].copy_from_slice(palette);
+        Ok(Image::from_pixels(
+            width,
+            height,
+            full_palette,
+            palette.len() as u32 / 3,
+            pixels,
+        ))
+    }
+
     pub fn width(&self) -> u32 {
         self.width
     }
@@ -660,23 +3549,26 @@ impl Image {
     /// Gets the image data as a slice of bytes,
     /// each byte representing a pixel indexed by the image's palette.
     pub fn data(&self) -> &[u8] {
-        unsafe {
-            std::slice::from_raw_parts(
-                self.data.as_ptr(),
-                self.width as usize * self.height as usize,
-            )
-        }
+        &self.data
     }
 
     /// Gets the image data as a mutable slice of bytes,
     /// each byte representing a pixel indexed by the image's palette.
     pub fn data_mut(&mut self) -> &mut [u8] {
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                self.data.as_ptr(),
-                self.width as usize * self.height as usize,
-            )
-        }
+        self.invalidate_remapped();
+        &mut self.data
+    }
+
+    /// Gets the palette index which is transparent in the image, if any,
+    /// as used by [`draw_image_masked`].
+    pub fn color_key(&self) -> Option<u8> {
+        self.color_key
+    }
+
+    /// Sets the palette index which is transparent in the image,
+    /// or `None` if the image is fully opaque.
+    pub fn set_color_key(&mut self, color_key: Option<u8>) {
+        self.color_key = color_key;
     }
 
     /// Gets the number of pixel colors in the palette.
@@ -686,19 +3578,44 @@ impl Image {
     }
 
     /// Gets the image's color palette as a slice of bytes, in RGB
-    /// (8 bits per channel).
+    /// (one byte per channel, from 0 to 63, see [`apply_palette`](Self::apply_palette)).
     pub fn palette(&self) -> &[u8] {
         &self.palette[..self.palette_count as usize * 3]
     }
 
     /// Gets the image's color palette as a mutable slice of bytes, in RGB
-    /// (8 bits per channel).
+    /// (one byte per channel, from 0 to 63).
     pub fn palette_mut(&mut self) -> &mut [u8] {
-        &mut self.palette[..self.palette_count as usize]
+        self.invalidate_remapped();
+        &mut self.palette[..self.palette_count as usize * 3]
+    }
+
+    /// Gets the image's color palette as a slice of colors
+    /// (channels from 0 to 63).
+    ///
+    /// This is the same palette as [`palette`](Self::palette),
+    /// one [`Rgb`] per color.
+    #[inline]
+    pub fn palette_colors(&self) -> &[Rgb] {
+        Rgb::from_bytes(self.palette())
+    }
+
+    /// Gets the image's color palette as a mutable slice of colors
+    /// (channels from 0 to 63).
+    #[inline]
+    pub fn palette_colors_mut(&mut self) -> &mut [Rgb] {
+        Rgb::from_bytes_mut(self.palette_mut())
+    }
+
+    /// Gets a color of the image's palette by index,
+    /// or `None` if the palette does not have that many colors.
+    #[inline]
+    pub fn color(&self, index: u8) -> Option<Rgb> {
+        self.palette_colors().get(index as usize).copied()
     }
 
     /// Gets the image's color palette as a reference to the underlying array,
-    /// in RGB (8 bits per channel).
+    /// in RGB (one byte per channel, from 0 to 63).
     ///
     /// The maximum expected size of any palette is 768 bytes
     /// (256 colors * 3 bytes per color).
@@ -708,11 +3625,362 @@ impl Image {
     pub fn raw_palette(&self) -> &[u8; 768] {
         &self.palette
     }
+
+    /// Creates a new image with a copy of a region of this one,
+    /// with the same palette and color key,
+    /// such as to cut the frames of a sprite sheet once at load time.
+    ///
+    /// The region is clamped to the bounds of the image,
+    /// so the new image can be smaller than requested,
+    /// or even empty if the region is entirely outside of the image.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// let sheet = load_gif("walk.gif")?;
+    /// // a strip of 16x24 frames
+    /// let frames: Vec<Image> = (0..sheet.width() / 16)
+    ///     .map(|i| sheet.sub_image(i * 16, 0, 16, 24))
+    ///     .collect();
+    /// # Ok::<(), FileError>(())
+    /// ```
+    pub fn sub_image(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        let x0 = x.min(self.width) as usize;
+        let y0 = y.min(self.height) as usize;
+        let x1 = x.saturating_add(width).min(self.width) as usize;
+        let y1 = y.saturating_add(height).min(self.height) as usize;
+        let mut pixels = Vec::with_capacity((x1 - x0) * (y1 - y0));
+        if x1 > x0 {
+            for row in self
+                .data()
+                .chunks_exact(self.width as usize)
+                .take(y1)
+                .skip(y0)
+            {
+                pixels.extend_from_slice(&row[x0..x1]);
+            }
+        }
+        let mut image = Image::from_pixels(
+            (x1 - x0) as u32,
+            (y1 - y0) as u32,
+            self.palette,
+            self.palette_count,
+            pixels,
+        );
+        image.color_key = self.color_key;
+        image
+    }
+
+    /// Installs the image's palette into the global palette,
+    /// from index 0 on,
+    /// leaving the colors past [`palette_count`](Self::palette_count) as they are.
+    ///
+    /// The palettes of images are kept in the 6-bit range
+    /// which the engine takes (0 to 63),
+    /// as the 8-bit colors of GIF files are scaled down when loaded,
+    /// so the colors are installed as they are,
+    /// with no shifting needed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// set_video_mode(VideoMode::Graphics320x200);
+    /// let background = load_gif("background.gif")?;
+    /// background.apply_palette();
+    /// draw_image(0, 0, &background);
+    /// # Ok::<(), FileError>(())
+    /// ```
+    #[inline]
+    pub fn apply_palette(&self) {
+        set_palette_colors(0, self.palette_colors());
+    }
+
+    /// Installs the image's palette into the global palette,
+    /// from the index `start` on,
+    /// leaving the other colors as they are.
+    ///
+    /// Only the colors are installed:
+    /// the pixels of the image keep pointing at the indices from 0 on.
+    /// See [`install_palette_range`](Self::install_palette_range)
+    /// to also move the pixels to the colors installed.
+    ///
+    /// Returns an error without installing anything
+    /// if the palette would go past index 255.
+    pub fn apply_palette_at(&self, start: u8) -> Result<(), ImageError> {
+        self.check_palette_range(start)?;
+        set_palette_colors(start as usize, self.palette_colors());
+        Ok(())
+    }
+
+    /// Installs the image's palette into the global palette
+    /// from the index `dst_start` on,
+    /// and returns a copy of the image
+    /// with its pixels and palette moved by the same offset,
+    /// so that it draws with the colors installed.
+    ///
+    /// This is for scenes which reserve ranges of the global palette
+    /// to different images,
+    /// such as 0 to 127 for the background and 128 to 191 for sprites.
+    /// The whole palette of the image ([`palette_count`](Self::palette_count) colors)
+    /// is installed,
+    /// and the color key, if any, is moved along with the pixels.
+    /// The palette of the copy has the colors at their new indices,
+    /// and black below them,
+    /// so it should be drawn with the palette assumed to be in place
+    /// (such as with [`draw_image`] or [`PaletteStrategy::AssumeInstalled`])
+    /// rather than installing it again.
+    ///
+    /// Returns an error without installing anything
+    /// if the palette would go past index 255.
+    /// See [`install_palette_range_in_place`](Self::install_palette_range_in_place)
+    /// to change this image instead of making a copy.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// set_video_mode(VideoMode::Graphics320x200);
+    /// let background = load_gif("background.gif")?;
+    /// let sprite = load_gif("sprite.gif")?;
+    /// // colors 0 to 127 for the background, 128 to 191 for sprites
+    /// let background = background.install_palette_range(0).expect("background should fit");
+    /// let sprite = sprite.install_palette_range(128).expect("sprite should fit");
+    /// draw_image(0, 0, &background);
+    /// draw_image_masked(100, 80, &sprite);
+    /// # Ok::<(), FileError>(())
+    /// ```
+    pub fn install_palette_range(&self, dst_start: u8) -> Result<Image, ImageError> {
+        self.check_palette_range(dst_start)?;
+        set_palette_bytes(dst_start as usize, self.palette());
+        let mut image = Image::from_pixels(
+            self.width,
+            self.height,
+            self.palette,
+            self.palette_count,
+            self.data.to_vec(),
+        );
+        image.color_key = self.color_key;
+        image.offset_palette(dst_start);
+        Ok(image)
+    }
+
+    /// Installs the image's palette into the global palette
+    /// from the index `dst_start` on,
+    /// and moves the pixels and palette of this image by the same offset,
+    /// so that it draws with the colors installed.
+    ///
+    /// The image is left as it was on error.
+    /// See [`install_palette_range`](Self::install_palette_range) for the details.
+    pub fn install_palette_range_in_place(&mut self, dst_start: u8) -> Result<(), ImageError> {
+        self.check_palette_range(dst_start)?;
+        set_palette_bytes(dst_start as usize, self.palette());
+        self.offset_palette(dst_start);
+        Ok(())
+    }
+
+    /// Checks that the palette fits in the global palette
+    /// from the given index.
+    fn check_palette_range(&self, dst_start: u8) -> Result<(), ImageError> {
+        if dst_start as u32 + self.palette_count > 256 {
+            return Err(ImageError::PaletteRangeOverflow {
+                start: dst_start,
+                count: self.palette_count,
+            });
+        }
+        Ok(())
+    }
+
+    /// Moves the pixels, palette and color key up by the given offset,
+    /// which must leave the palette within 256 colors.
+    fn offset_palette(&mut self, offset: u8) {
+        let (start, count) = (offset as usize, self.palette_count as usize);
+        self.palette.copy_within(..count * 3, start * 3);
+        self.palette[..start * 3].fill(0);
+        self.palette_count += offset as u32;
+        for pixel in self.data.iter_mut() {
+            *pixel = pixel.wrapping_add(offset);
+        }
+        self.color_key = self.color_key.map(|key| key.wrapping_add(offset));
+        self.invalidate_remapped();
+    }
+
+    /// Discards the pixel data remapped to the global palette.
+    fn invalidate_remapped(&mut self) {
+        *self.remapped.get_mut().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Calls the given function with the pixel data
+    /// remapped to the current global palette,
+    /// remapping it only if the global palette changed since last time.
+    fn with_remapped<T>(&self, f: impl FnOnce(&[u8]) -> T) -> T {
+        let generation = PALETTE_GENERATION.load(Ordering::Relaxed);
+        let mut remapped = self.remapped.lock().unwrap_or_else(|e| e.into_inner());
+        match &*remapped {
+            Some(r) if r.generation == generation => {}
+            _ => {
+                let table = remap_table(self.palette(), &palette::palette_bytes());
+                *remapped = Some(RemappedPixels {
+                    generation,
+                    pixels: self.data().iter().map(|c| table[*c as usize]).collect(),
+                });
+            }
+        }
+        f(&remapped
+            .as_ref()
+            .expect("remapped pixels should be present")
+            .pixels)
+    }
+}
+
+/// Draws a whole image at the given position,
+/// taking its dimensions from the image itself.
+///
+/// The image's palette is assumed to be in place,
+/// see [`draw_image_auto`] for other ways to handle it.
+///
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let background = load_gif("background.gif")?;
+/// draw_image(0, 0, &background);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn draw_image(x: i32, y: i32, image: &Image) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    blit(x, y, image.data(), width, height, 0, 0, width, height);
+}
+
+/// Draws a whole image at the given position,
+/// skipping the pixels of the image's [color key](Image::color_key).
+///
+/// If the image has no color key,
+/// the pixels of palette index 0 are skipped.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut sprite = load_gif("sprite.gif")?;
+/// if sprite.color_key().is_none() {
+///     sprite.set_color_key(Some(255));
+/// }
+/// draw_image_masked(100, 80, &sprite);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn draw_image_masked(x: i32, y: i32, image: &Image) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    let color_key = image.color_key().unwrap_or(0);
+    mask_blit(
+        x,
+        y,
+        image.data(),
+        width,
+        height,
+        0,
+        0,
+        width,
+        height,
+        color_key,
+    );
+}
+
+/// Draws a region of an image at the given position,
+/// taking its dimensions from the image itself.
+///
+/// The region is clamped to the bounds of the image,
+/// so only the part of it inside the image is drawn.
+///
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let tiles = load_gif("tiles.gif")?;
+/// // the third 16x16 tile of the first row
+/// draw_image_region(100, 80, &tiles, Rect::new(32, 0, 16, 16));
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn draw_image_region(x: i32, y: i32, image: &Image, src_rect: impl Into<Rect>) {
+    let size = (image.width() as u16, image.height() as u16);
+    blit_rect((x, y), image.data(), size, src_rect);
+}
+
+/// How to reconcile the palette of an image
+/// with the global palette when drawing it,
+/// see [`draw_image_auto`].
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum PaletteStrategy {
+    /// Replace the entries of the global palette
+    /// with the colors of the image's palette
+    /// before drawing it
+    /// (affecting everything else on screen using those entries)
+    InstallPalette,
+    /// Draw the image with each color replaced
+    /// by the nearest color in the global palette.
+    ///
+    /// The remapped pixels are cached in the image,
+    /// and only calculated again
+    /// when the global palette or the image changes.
+    RemapToCurrent,
+    /// Draw the image as is,
+    /// assuming that its palette is already in place
+    #[default]
+    AssumeInstalled,
+}
+
+/// Draws a whole image at the given position,
+/// reconciling its palette with the global palette
+/// according to the given strategy.
+///
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let background = load_gif("background.gif").unwrap();
+/// let sprite = load_gif("sprite.gif").unwrap();
+///
+/// // the background's palette becomes the global palette
+/// draw_image_auto(0, 0, &background, PaletteStrategy::InstallPalette);
+/// // the sprite is drawn with the closest colors available
+/// draw_image_auto(100, 50, &sprite, PaletteStrategy::RemapToCurrent);
+/// ```
+pub fn draw_image_auto(x: i32, y: i32, image: &Image, strategy: PaletteStrategy) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+    let draw = |pixels: &[u8]| blit(x, y, pixels, width, height, 0, 0, width, height);
+    match strategy {
+        PaletteStrategy::InstallPalette => {
+            image.apply_palette();
+            draw(image.data());
+        }
+        PaletteStrategy::RemapToCurrent => image.with_remapped(draw),
+        PaletteStrategy::AssumeInstalled => draw(image.data()),
+    }
 }
 
 /// Loads an image from a GIF file.
+///
+/// If the file declares a transparent color,
+/// it becomes the [color key](Image::color_key) of the image.
 pub fn load_gif(path: impl AsRef<str>) -> Result<Image, FileError> {
     let filename = CString::new(path.as_ref()).map_err(|_| FileError::BadFilePath)?;
+    // the engine does not report transparency, so look for it here
+    let color_key = std::fs::read(path.as_ref())
+        .ok()
+        .and_then(|data| gif::transparent_index(&data));
     let mut width = 0;
     let mut height = 0;
     let mut palcount = 0;
@@ -727,20 +3995,39 @@ pub fn load_gif(path: impl AsRef<str>) -> Result<Image, FileError> {
             palette.as_mut_ptr(),
         );
 
-        if let Some(data) = NonNull::new(data) {
-            Ok(Image {
-                width: width as u32,
-                height: height as u32,
-                palette_count: palcount as u32,
-                palette,
-                data,
-            })
-        } else {
-            Err(FileError::FileNotFound)
+        if data.is_null() {
+            return Err(FileError::FileNotFound);
         }
+        // take a copy of the pixels so that they are freed with the image,
+        // and give the buffer back to the allocator of the engine
+        let len = width.max(0) as usize * height.max(0) as usize;
+        let pixels = std::slice::from_raw_parts(data, len).to_vec();
+        free_engine_buffer(data);
+
+        let mut image = Image::from_pixels(
+            width as u32,
+            height as u32,
+            palette,
+            palcount as u32,
+            pixels,
+        );
+        image.color_key = color_key;
+        Ok(image)
     }
 }
 
+/// Gives a buffer allocated by the engine back to the allocator of the engine.
+///
+/// # Safety
+///
+/// The buffer must have been allocated by the engine,
+/// and must not be used afterwards.
+unsafe fn free_engine_buffer(ptr: *mut u8) {
+    #[cfg(test)]
+    tests::ENGINE_FREES.with(|frees| frees.set(frees.get() + 1));
+    dos_like_sys::dos_like_free(ptr.cast());
+}
+
 // -- Font manipulation functions --
 
 /// A font identifier.
@@ -770,6 +4057,105 @@ impl Font {
         unsafe { NonZeroU32::new_unchecked(dos_like_sys::DEFAULT_FONT_9X16) },
     );
 
+    /// The glyph size of [`Font::DEFAULT_8X8`], in pixels.
+    pub const GLYPH_SIZE_8X8: (u8, u8) = (8, 8);
+
+    /// The glyph size of [`Font::DEFAULT_8X16`], in pixels.
+    pub const GLYPH_SIZE_8X16: (u8, u8) = (8, 16);
+
+    /// The glyph size of [`Font::DEFAULT_9X16`], in pixels.
+    pub const GLYPH_SIZE_9X16: (u8, u8) = (9, 16);
+
+    /// Gets the glyph size of this font in pixels as `(width, height)`,
+    /// or `None` if this is not one of the built-in fonts.
+    pub fn builtin_glyph_size(self) -> Option<(u8, u8)> {
+        match self {
+            Font::DEFAULT_8X8 => Some(Font::GLYPH_SIZE_8X8),
+            Font::DEFAULT_8X16 => Some(Font::GLYPH_SIZE_8X16),
+            Font::DEFAULT_9X16 => Some(Font::GLYPH_SIZE_9X16),
+            _ => None,
+        }
+    }
+
+    /// Gets the number of bytes taken by each glyph
+    /// of a built-in font or a font created via [`Font::from_glyphs`],
+    /// or `None` if the glyph data of this font is not available.
+    pub fn bytes_per_glyph(self) -> Option<usize> {
+        if let Some(font) = soft_font::get(self) {
+            return Some(font.bytes_per_glyph());
+        }
+        let (width, height) = self.builtin_glyph_size()?;
+        Some((width as usize).div_ceil(8) * height as usize)
+    }
+
+    /// Gets the bitmap of a single glyph
+    /// of a built-in font or a font created via [`Font::from_glyphs`],
+    /// or `None` if the glyph data of this font is not available.
+    ///
+    /// See [`builtin_font_data`] for the layout of the data.
+    pub fn glyph(self, c: u8) -> Option<&'static [u8]> {
+        if let Some(font) = soft_font::get(self) {
+            return Some(font.glyph(c));
+        }
+        let len = self.bytes_per_glyph()?;
+        builtin_font_data(self).map(|data| &data[c as usize * len..][..len])
+    }
+
+    /// Creates a new font from in-memory glyph bitmaps.
+    ///
+    /// `glyphs` must contain the 256 glyphs of the font in order,
+    /// in the same layout as described in [`builtin_font_data`]:
+    /// `ceil(width / 8) * height` bytes per glyph,
+    /// rows from top to bottom,
+    /// most significant bit first.
+    /// Both `width` and `height` must be between 1 and 32.
+    ///
+    /// The font can then be used through [`set_text_style`]
+    /// and the text blitting functions such as [`out_text_xy`].
+    /// Unlike fonts installed from files,
+    /// these are rendered by this crate
+    /// in the color set via [`set_color`].
+    ///
+    /// The font is listed in [`installed_fonts`]
+    /// with a label such as `"Custom 4x6"`,
+    /// see [`Font::from_glyphs_labeled`] to choose it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// // a font of solid 4x6 blocks
+    /// let font = Font::from_glyphs(4, 6, &[0xF0; 256 * 6])?;
+    /// set_text_style(font, false, false, false);
+    /// out_text_xy(10, 10, "Hello");
+    /// # Ok::<(), FontError>(())
+    /// ```
+    pub fn from_glyphs(width: u8, height: u8, glyphs: &[u8]) -> Result<Font, FontError> {
+        Font::from_glyphs_labeled(
+            format!("Custom {}x{}", width, height),
+            width,
+            height,
+            glyphs,
+        )
+    }
+
+    /// Creates a new font from in-memory glyph bitmaps,
+    /// with a label to find it by later
+    /// (see [`find_font`]).
+    ///
+    /// See [`Font::from_glyphs`] for the layout of the glyphs,
+    /// which is otherwise the same.
+    pub fn from_glyphs_labeled(
+        label: impl Into<String>,
+        width: u8,
+        height: u8,
+        glyphs: &[u8],
+    ) -> Result<Font, FontError> {
+        let font = soft_font::install(width, height, glyphs)?;
+        font_registry::lock().push(font_registry::glyph_font(font, label.into(), width, height));
+        Ok(font)
+    }
+
     /// Installs a font from a .fnt file
     ///
     /// This is the same as the module's [`install_user_font`] function.
@@ -790,39 +4176,213 @@ impl Font {
         NonZeroU32::new(id as u32).map(Font)
     }
 
-    #[inline]
-    fn to_id(self) -> c_int {
-        self.0.get() as c_int
-    }
+    /// Checks whether this font can be used for text:
+    /// whether it is a built-in font,
+    /// a font created via [`Font::from_glyphs`],
+    /// or a font installed via [`install_user_font`].
+    ///
+    /// Identifiers made up with [`Font::from_id`] are not known
+    /// unless they match one of these.
+    pub fn is_known(self) -> bool {
+        self.builtin_glyph_size().is_some() || font_registry::contains(self)
+    }
+
+    #[inline]
+    fn to_id(self) -> c_int {
+        self.0.get() as c_int
+    }
+}
+
+/// Installs a font from a .fnt file.
+///
+/// Returns the identifier of the font.
+/// Installing the same file again returns the same identifier
+/// without installing it twice.
+/// The font is then listed in [`installed_fonts`].
+pub fn install_user_font(filename: impl AsRef<str>) -> Result<Font, FileError> {
+    let path = filename.as_ref();
+    let filename = CString::new(path).map_err(|_| FileError::BadFilePath)?;
+
+    let mut fonts = font_registry::lock();
+    if let Some(font) = font_registry::find_file(&fonts, path) {
+        return Ok(font);
+    }
+    let font = unsafe {
+        let font_id = dos_like_sys::installuserfont(filename.as_ptr() as *const _);
+
+        Font::from_id(font_id as u32).ok_or(FileError::FileNotFound)?
+    };
+    fonts.push(font_registry::file_font(font, path));
+    Ok(font)
+}
+
+/// The text style last set through this crate,
+/// packed as the font identifier followed by three style bits.
+static TEXT_STYLE: AtomicU32 = AtomicU32::new(dos_like_sys::DEFAULT_FONT_8X8 << 3);
+
+/// Sets the font and style of upcoming text blit operations.
+///
+/// This is only available in graphics mode with a font loaded.
+/// The operations is ignored if `FontId` does not correspond to a valid font.
+/// See [`try_set_text_style`] to detect this.
+#[inline]
+pub fn set_text_style(font: Font, bold: bool, italic: bool, underline: bool) {
+    debug_assert!(
+        font.is_known(),
+        "set_text_style: unknown font identifier {}",
+        font.0
+    );
+    // fonts created from glyph bitmaps are not known to the engine
+    if soft_font::get(font).is_none() {
+        unsafe {
+            dos_like_sys::settextstyle(
+                font.to_id(),
+                bold as c_int,
+                italic as c_int,
+                underline as c_int,
+            );
+        }
+    }
+    TEXT_STYLE.store(
+        (font.0.get() << 3) | (bold as u32) << 2 | (italic as u32) << 1 | underline as u32,
+        Ordering::Relaxed,
+    );
+}
+
+/// Sets the font and style of upcoming text blit operations,
+/// or returns an error if the font is not known to this crate
+/// (see [`Font::is_known`]).
+///
+/// On error, the text style does not change.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let font = Font::from_id(42).unwrap();
+/// assert!(try_set_text_style(font, false, false, false).is_err());
+/// ```
+pub fn try_set_text_style(
+    font: Font,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+) -> Result<(), VideoError> {
+    if !font.is_known() {
+        return Err(VideoError::UnknownFont { id: font.0.get() });
+    }
+    set_text_style(font, bold, italic, underline);
+    Ok(())
+}
+
+/// Gets the font and style of upcoming text blit operations,
+/// as last set via [`set_text_style`],
+/// in the order `(font, bold, italic, underline)`.
+pub fn text_style() -> (Font, bool, bool, bool) {
+    let style = TEXT_STYLE.load(Ordering::Relaxed);
+    (
+        Font::from_id(style >> 3).unwrap_or(Font::DEFAULT_8X8),
+        style & 0b100 != 0,
+        style & 0b010 != 0,
+        style & 0b001 != 0,
+    )
 }
 
-/// Installs a font from a .fnt file.
-///
-/// Returns the identifier of the font.
-pub fn install_user_font(filename: impl AsRef<str>) -> Result<Font, FileError> {
-    let filename = CString::new(filename.as_ref()).map_err(|_| FileError::BadFilePath)?;
+/// The glyph bitmaps of the built-in fonts,
+/// captured on first use.
+static BUILTIN_FONT_DATA: [OnceLock<Box<[u8]>>; 3] =
+    [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+
+/// Captures the glyphs of a font
+/// by drawing each one of them to an off-screen target.
+fn capture_font_glyphs(font: Font, width: u8, height: u8) -> Box<[u8]> {
+    let (width, height) = (width as usize, height as usize);
+    let row_bytes = width.div_ceil(8);
+    let mut data = vec![0; 256 * row_bytes * height];
+    let mut target = vec![0_u8; width * height];
+
+    let previous_color = get_color();
+    let (previous_font, bold, italic, underline) = text_style();
 
     unsafe {
-        let font_id = dos_like_sys::installuserfont(filename.as_ptr() as *const _);
+        dos_like_sys::setdrawtarget(target.as_mut_ptr(), width as c_int, height as c_int);
+        dos_like_sys::setcolor(1);
+        dos_like_sys::settextstyle(font.to_id(), 0, 0, 0);
+    }
+
+    // glyph 0 cannot be drawn through a C string, and is blank anyway
+    for (c, glyph) in data
+        .chunks_exact_mut(row_bytes * height)
+        .enumerate()
+        .skip(1)
+    {
+        target.fill(0);
+        let text = [c as u8, 0];
+        unsafe {
+            dos_like_sys::outtextxy(0, 0, text.as_ptr() as *const _);
+        }
+        for (y, row) in target.chunks_exact(width).enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, p)| **p != 0) {
+                glyph[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
 
-        Font::from_id(font_id as u32).ok_or(FileError::FileNotFound)
+    unsafe {
+        dos_like_sys::resetdrawtarget();
     }
+    set_color(previous_color);
+    set_text_style(previous_font, bold, italic, underline);
+
+    data.into_boxed_slice()
 }
 
-/// Sets the font and style of upcoming text blit operations.
+/// Gets the raw glyph bitmaps of one of the built-in fonts,
+/// or `None` if the font is not a built-in one
+/// or its glyphs cannot be captured yet (see below).
 ///
-/// This is only available in graphics mode with a font loaded.
-/// The operations is ignored if `FontId` does not correspond to a valid font.
-#[inline]
-pub fn set_text_style(font: Font, bold: bool, italic: bool, underline: bool) {
-    unsafe {
-        dos_like_sys::settextstyle(
-            font.to_id(),
-            bold as c_int,
-            italic as c_int,
-            underline as c_int,
-        );
+/// The data contains the 256 glyphs of code page 437 in order.
+/// Each glyph takes [`Font::bytes_per_glyph`] bytes,
+/// with one or more bytes per row from top to bottom,
+/// and one bit per pixel, most significant bit first
+/// (so the leftmost pixel of a row is `0x80` in its first byte).
+/// A set bit means that the pixel is drawn in the foreground color.
+///
+/// The glyphs are captured from the engine on first use
+/// by drawing them to an off-screen target,
+/// so this must first be called in graphics mode,
+/// and returns `None` until then.
+/// The current color and text style are preserved,
+/// but any custom draw target is reset.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let data = builtin_font_data(Font::DEFAULT_8X8).unwrap();
+/// let glyph_a = &data[b'A' as usize * 8..][..8];
+/// // draw the letter A four times bigger
+/// for (y, row) in glyph_a.iter().enumerate() {
+///     for x in 0..8 {
+///         if row & (0x80 >> x) != 0 {
+///             bar(x * 4, y as i32 * 4, 4, 4);
+///         }
+///     }
+/// }
+/// ```
+pub fn builtin_font_data(font: Font) -> Option<&'static [u8]> {
+    let (width, height) = font.builtin_glyph_size()?;
+    let data = &BUILTIN_FONT_DATA[font.0.get() as usize - 1];
+    if let Some(data) = data.get() {
+        return Some(data);
+    }
+    // the engine only draws text in graphics mode
+    if !video_mode().is_graphics() {
+        return None;
     }
+    Some(data.get_or_init(|| capture_font_glyphs(font, width, height)))
 }
 
 // --- Pure text mode functions ---
@@ -833,17 +4393,15 @@ pub fn set_text_style(font: Font, bold: bool, italic: bool, underline: bool) {
 ///
 /// This is equivalent to creating a [`CString`](std::ffi::CString)
 /// (so that it is null terminated)
-/// and calling [`put_cstr`].
+/// and calling [`put_cstr`],
+/// but short strings are copied into a reused buffer instead.
 ///
-/// # Panics
-///
-/// Panics if the given string cannot be converted to be printed to the screen.
-/// Always check for null characters (`\0`) in the string
-/// before calling this function.
+/// Nothing is written if the string contains a null character (`\0`).
 #[inline]
 pub fn put_str(string: impl AsRef<str>) {
-    let text = CString::new(string.as_ref()).unwrap();
-    put_cstr(&text)
+    with_c_text(string.as_ref().as_bytes(), |text| unsafe {
+        dos_like_sys::cputs(text as *const _);
+    });
 }
 
 /// Writes a C string to the screen, at the current cursor position.
@@ -859,6 +4417,90 @@ pub fn put_cstr(text: impl AsRef<CStr>) {
     }
 }
 
+/// A text writer in the style of conio's `cprintf`,
+/// which buffers text on the stack before passing it to the engine.
+struct ConioWriter {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl ConioWriter {
+    fn new() -> Self {
+        ConioWriter {
+            buf: [0; 128],
+            len: 0,
+        }
+    }
+
+    /// Writes the buffered text to the screen.
+    fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        self.buf[self.len] = 0;
+        // Safety: the buffer is null terminated
+        // and contains no other null characters
+        unsafe {
+            dos_like_sys::cputs(self.buf.as_ptr() as *const _);
+        }
+        self.len = 0;
+    }
+}
+
+impl std::fmt::Write for ConioWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for c in s.bytes() {
+            match c {
+                // skip null characters, which would end the string early
+                0 => {}
+                b'\r' => {
+                    self.flush();
+                    goto_xy(0, where_y());
+                }
+                b'\n' => {
+                    // line feed only, the column is retained;
+                    // let the engine handle scrolling at the bottom
+                    self.flush();
+                    let x = where_x();
+                    put_cstr(c"\n");
+                    goto_xy(x, where_y());
+                }
+                c => {
+                    self.buf[self.len] = c;
+                    self.len += 1;
+                    if self.len == self.buf.len() - 1 {
+                        self.flush();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes formatted text to the screen, at the current cursor position,
+/// using the current text colors.
+///
+/// This is the function behind the [`cprintf!`](crate::cprintf) macro.
+/// Like in conio,
+/// `\n` moves the cursor one line down while keeping its column,
+/// and `\r` moves it back to the start of the line.
+/// Text wraps at the right edge of the screen
+/// and the screen scrolls when writing past the bottom row.
+/// Null characters are skipped.
+///
+/// Does nothing unless the video is in text mode.
+pub fn put_fmt(args: std::fmt::Arguments) {
+    let mut writer = ConioWriter::new();
+    // writing to the screen never fails
+    let _ = std::fmt::Write::write_fmt(&mut writer, args);
+    writer.flush();
+}
+
+/// The text colors last set through this crate.
+static TEXT_COLOR: AtomicU32 = AtomicU32::new(7);
+static TEXT_BACKGROUND: AtomicU32 = AtomicU32::new(0);
+
 /// Sets the color of the text.
 ///
 /// Only works in text mode.
@@ -867,6 +4509,7 @@ pub fn text_color(color: u32) {
     unsafe {
         dos_like_sys::textcolor(color as c_int);
     }
+    TEXT_COLOR.store(color, Ordering::Relaxed);
 }
 
 /// Sets the background color of the text by palette color index.
@@ -877,18 +4520,182 @@ pub fn text_background(color: u8) {
     unsafe {
         dos_like_sys::textbackground(color as c_int);
     }
+    TEXT_BACKGROUND.store(color as u32, Ordering::Relaxed);
+}
+
+/// A pair of text mode colors for the foreground and the background.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct TextAttr {
+    /// The foreground (text) color.
+    pub foreground: u8,
+    /// The background color.
+    pub background: u8,
+}
+
+impl TextAttr {
+    /// Creates a new text attribute pair.
+    #[inline]
+    pub const fn new(foreground: u8, background: u8) -> Self {
+        TextAttr {
+            foreground,
+            background,
+        }
+    }
+
+    /// Gets the text colors currently in use,
+    /// as last set via [`text_color`] and [`text_background`].
+    #[inline]
+    pub fn current() -> Self {
+        TextAttr::new(
+            TEXT_COLOR.load(Ordering::Relaxed) as u8,
+            TEXT_BACKGROUND.load(Ordering::Relaxed) as u8,
+        )
+    }
+
+    /// Sets these colors for subsequent text output.
+    ///
+    /// Equivalent to calling [`text_color`] and [`text_background`].
+    ///
+    /// Only works in text mode.
+    #[inline]
+    pub fn apply(self) {
+        text_color(self.foreground as u32);
+        text_background(self.background);
+    }
+}
+
+impl Default for TextAttr {
+    /// Light gray on black, as in a fresh DOS prompt.
+    fn default() -> Self {
+        TextAttr::new(7, 0)
+    }
+}
+
+/// Writes characters with the given colors
+/// directly into the cells of the text screen,
+/// starting at the given position and going right,
+/// without moving the cursor or changing the current text colors.
+///
+/// The characters are cut at the right edge of the screen.
+/// Does nothing unless the video is in text mode.
+pub(crate) fn put_cells(x: u16, y: u16, text: impl IntoIterator<Item = u8>, attrs: TextAttr) {
+    let mode = video_mode();
+    let (columns, rows) = (mode.text_columns() as usize, mode.text_rows() as usize);
+    let (x, y) = (x as usize, y as usize);
+    if x >= columns || y >= rows {
+        return;
+    }
+    // Safety: in text mode, the screen buffer has two bytes per cell,
+    // the character followed by the attributes,
+    // and the slice is dropped before returning
+    let screen = unsafe {
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return;
+        }
+        std::slice::from_raw_parts_mut(buf, columns * rows * 2)
+    };
+    let row = &mut screen[(y * columns + x) * 2..(y + 1) * columns * 2];
+    write_cells(row, text, attrs);
+}
+
+/// Writes characters with the given colors into a row of text cells,
+/// stopping at the end of the row or of the characters.
+fn write_cells(row: &mut [u8], text: impl IntoIterator<Item = u8>, attrs: TextAttr) {
+    let attr = (attrs.background & 0x0F) << 4 | (attrs.foreground & 0x0F);
+    for (cell, c) in row.chunks_exact_mut(2).zip(text) {
+        cell[0] = c;
+        cell[1] = attr;
+    }
+}
+
+/// Gets the number of text columns in the current video mode.
+///
+/// Returns 0 if the video is not in text mode.
+#[inline]
+pub fn text_columns() -> u16 {
+    video_mode().text_columns()
+}
+
+/// Gets the number of text rows in the current video mode.
+///
+/// Returns 0 if the video is not in text mode.
+#[inline]
+pub fn text_rows() -> u16 {
+    video_mode().text_rows()
+}
+
+/// Error returned when attempting to move the text cursor
+/// outside of the text grid.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct CursorOutOfBounds {
+    /// The requested X position.
+    pub x: u16,
+    /// The requested Y position.
+    pub y: u16,
+    /// The number of columns in the text grid.
+    pub columns: u16,
+    /// The number of rows in the text grid.
+    pub rows: u16,
+}
+
+impl std::fmt::Display for CursorOutOfBounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Cursor position ({}, {}) is outside of the {}x{} text grid",
+            self.x, self.y, self.columns, self.rows
+        )
+    }
 }
 
 /// Moves the cursor to the specified position.
 ///
+/// The position is clamped to the current text grid
+/// (see [`text_columns`] and [`text_rows`]).
+/// Use [`try_goto_xy`] to reject positions out of bounds instead.
+///
 /// Only works in text mode.
 #[inline]
 pub fn goto_xy(x: u16, y: u16) {
+    let x = x.min(text_columns().saturating_sub(1));
+    let y = y.min(text_rows().saturating_sub(1));
     unsafe {
         dos_like_sys::gotoxy(x as c_int, y as c_int);
     }
 }
 
+/// Moves the cursor to the specified position,
+/// failing if it is outside of the current text grid.
+///
+/// Only works in text mode.
+pub fn try_goto_xy(x: u16, y: u16) -> Result<(), CursorOutOfBounds> {
+    let columns = text_columns();
+    let rows = text_rows();
+    if x >= columns || y >= rows {
+        return Err(CursorOutOfBounds {
+            x,
+            y,
+            columns,
+            rows,
+        });
+    }
+    goto_xy(x, y);
+    Ok(())
+}
+
+/// Moves the cursor relative to its current position.
+///
+/// The resulting position is clamped to the current text grid.
+///
+/// Only works in text mode.
+pub fn move_cursor(dx: i16, dy: i16) {
+    let (x, y) = cursor_pos();
+    let x = (x as i32 + dx as i32).max(0) as u16;
+    let y = (y as i32 + dy as i32).max(0) as u16;
+    goto_xy(x, y);
+}
+
 /// Gets the cursor's current X position.
 ///
 /// Returns 0 if the video is not in text mode.
@@ -902,7 +4709,18 @@ pub fn where_x() -> u16 {
 /// Returns 0 if the video is not in text mode.
 #[inline]
 pub fn where_y() -> u16 {
-    unsafe { dos_like_sys::wherex().max(0) as u16 }
+    unsafe { dos_like_sys::wherey().max(0) as u16 }
+}
+
+/// Gets the cursor's current position as a pair of X and Y.
+///
+/// Useful for saving the cursor position
+/// and restoring it later with [`goto_xy`].
+///
+/// Returns `(0, 0)` if the video is not in text mode.
+#[inline]
+pub fn cursor_pos() -> (u16, u16) {
+    (where_x(), where_y())
 }
 
 /// Clears the screen when in text mode.
@@ -933,3 +4751,642 @@ pub fn curs_off() {
         dos_like_sys::cursoff();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn video_mode_display_round_trip() {
+        for mode in VideoMode::ALL {
+            assert_eq!(mode.to_string().parse(), Ok(mode), "{:?}", mode);
+            assert_eq!(
+                mode.to_string().to_ascii_uppercase().parse(),
+                Ok(mode),
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn video_mode_parses_variant_names() {
+        for mode in VideoMode::ALL {
+            let name = format!("{:?}", mode);
+            assert_eq!(name.parse(), Ok(mode));
+            assert_eq!(name.to_ascii_lowercase().parse(), Ok(mode));
+        }
+        assert_eq!(" Graphics320x200 ".parse(), Ok(VideoMode::Graphics320x200));
+    }
+
+    #[test]
+    fn video_mode_parses_short_text_forms() {
+        assert_eq!("80x25".parse(), Ok(VideoMode::Text80x25_8x16));
+        assert_eq!("40x25".parse(), Ok(VideoMode::Text40x25_9x16));
+        assert_eq!("80x43".parse(), Ok(VideoMode::Text80x43_8x8));
+        assert_eq!("80x50".parse(), Ok(VideoMode::Text80x50_8x8));
+        assert_eq!("80X25".parse(), Ok(VideoMode::Text80x25_8x16));
+        assert_eq!("80X43".parse(), Ok(VideoMode::Text80x43_8x8));
+        assert_eq!("80X50".parse(), Ok(VideoMode::Text80x50_8x8));
+        assert_eq!("80x25_8x8".parse(), Ok(VideoMode::Text80x25_8x8));
+        assert_eq!("80x25_9x16".parse(), Ok(VideoMode::Text80x25_9x16));
+        assert_eq!("320x200".parse(), Ok(VideoMode::Graphics320x200));
+        assert_eq!("640x480".parse(), Ok(VideoMode::Graphics640x480));
+    }
+
+    #[test]
+    fn video_mode_rejects_unknown_strings() {
+        for s in ["", "320", "320x201", "Graphics", "80x25_8x9", "x", "80x25x"] {
+            assert_eq!(s.parse::<VideoMode>(), Err(ParseVideoModeError), "{:?}", s);
+        }
+    }
+
+    /// Serializes the tests which change the video mode.
+    pub(crate) static VIDEO_MODE_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn builtin_font_data_matches_out_text_xy() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fonts = [Font::DEFAULT_8X8, Font::DEFAULT_8X16, Font::DEFAULT_9X16];
+
+        // nothing is captured outside of graphics mode
+        set_video_mode(VideoMode::Text80x25_8x16);
+        for (font, data) in fonts.into_iter().zip(&BUILTIN_FONT_DATA) {
+            if data.get().is_none() {
+                assert_eq!(builtin_font_data(font), None);
+                assert!(data.get().is_none());
+            }
+        }
+
+        set_video_mode(VideoMode::Graphics320x200);
+        let width = screen_width() as usize;
+        for font in fonts {
+            let data = builtin_font_data(font).unwrap();
+            let (glyph_w, glyph_h) = font.builtin_glyph_size().unwrap();
+            let len = font.bytes_per_glyph().unwrap();
+            let glyph = &data[b'A' as usize * len..][..len];
+            let row_bytes = len / glyph_h as usize;
+
+            clear_screen();
+            set_color(15);
+            set_text_style(font, false, false, false);
+            out_text_xy(0, 0, "A");
+            let screen = unsafe { screen_buffer() };
+            for y in 0..glyph_h as usize {
+                for x in 0..glyph_w as usize {
+                    let set = glyph[y * row_bytes + x / 8] & (0x80 >> (x % 8)) != 0;
+                    assert_eq!(
+                        screen[y * width + x] != 0,
+                        set,
+                        "{:?} at ({}, {})",
+                        font,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn current_video_mode_follows_set_video_mode() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        set_video_mode(VideoMode::Graphics320x200);
+        assert_eq!(current_video_mode(), VideoMode::Graphics320x200);
+        assert!(VideoMode::Graphics320x200.is_current());
+        assert!(!VideoMode::Text80x25_8x16.is_current());
+
+        // the method form updates it as well
+        VideoMode::Text40x25_8x8.set_video_mode();
+        assert_eq!(current_video_mode(), VideoMode::Text40x25_8x8);
+        assert!(VideoMode::Text40x25_8x8.is_current());
+        assert!(!VideoMode::Graphics320x200.is_current());
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+        assert!(VideoMode::Text80x25_8x16.is_current());
+    }
+
+    #[test]
+    fn try_set_pal_checks_index_and_components() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+        assert_eq!(
+            try_set_pal(256, 0, 0, 0),
+            Err(VideoError::PaletteIndexOutOfRange { index: 256 })
+        );
+        assert_eq!(
+            try_set_pal(16, 0, 0, 0),
+            Err(VideoError::PaletteIndexUnused {
+                index: 16,
+                mode: VideoMode::Text80x25_8x16
+            })
+        );
+        assert_eq!(
+            try_set_pal(1, 10, 64, 0),
+            Err(VideoError::ColorOutOfRange { value: 64 })
+        );
+        assert_eq!(try_set_pal(15, 63, 0, 0), Ok(()));
+
+        set_video_mode(VideoMode::Graphics320x200);
+        assert_eq!(try_set_pal(16, 0, 0, 0), Ok(()));
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn try_set_text_style_rejects_unknown_fonts() {
+        let unknown = Font::from_id(4321).unwrap();
+        assert!(!unknown.is_known());
+        assert_eq!(
+            try_set_text_style(unknown, false, false, false),
+            Err(VideoError::UnknownFont { id: 4321 })
+        );
+        assert!(Font::DEFAULT_8X8.is_known());
+    }
+
+    #[test]
+    fn flipped_blits_mirror_and_clip() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Graphics320x200);
+        let width = screen_width() as usize;
+        let source = [1, 2, 3, 4, 5, 6];
+        let clear = || unsafe { screen_buffer() }.fill(0);
+        let rows = |n: usize| {
+            let screen = unsafe { screen_buffer() };
+            (0..2)
+                .map(|y| screen[y * width..y * width + n].to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        clear();
+        blit_flipped(0, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), false, false);
+        assert_eq!(rows(4), [[1, 2, 3, 0], [4, 5, 6, 0]]);
+
+        clear();
+        blit_flipped(0, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), true, false);
+        assert_eq!(rows(4), [[3, 2, 1, 0], [6, 5, 4, 0]]);
+
+        clear();
+        blit_flipped(0, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), false, true);
+        assert_eq!(rows(4), [[4, 5, 6, 0], [1, 2, 3, 0]]);
+
+        // the part off screen is cut after mirroring
+        clear();
+        blit_flipped(-1, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), true, true);
+        assert_eq!(rows(3), [[5, 4, 0], [2, 1, 0]]);
+
+        clear();
+        blit_flipped(0, 0, &source, 3, 2, Rect::new(1, 0, 2, 1), true, false);
+        assert_eq!(rows(3), [[3, 2, 0], [0, 0, 0]]);
+
+        clear();
+        mask_blit_flipped(0, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), true, false, 2);
+        assert_eq!(rows(4), [[3, 0, 1, 0], [6, 5, 4, 0]]);
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn image_from_data_checks_its_input() {
+        let palette = [0, 0, 0, 63, 63, 63];
+        let mut image = Image::from_data(3, 2, vec![0, 1, 0, 1, 0, 1], &palette).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.palette_count(), 2);
+        assert_eq!(&image.palette()[..6], palette);
+        assert!(image.palette()[6..].iter().all(|&v| v == 0));
+        image.data_mut()[2] = 1;
+        assert_eq!(image.data(), [0, 1, 1, 1, 0, 1]);
+
+        assert_eq!(
+            Image::from_data(3, 2, vec![0; 5], &palette).err(),
+            Some(ImageError::InvalidDataLength {
+                expected: 6,
+                actual: 5
+            })
+        );
+        assert_eq!(
+            Image::from_data(1, 1, vec![0], &[0; 4]).err(),
+            Some(ImageError::InvalidPaletteLength { len: 4 })
+        );
+        assert_eq!(
+            Image::from_data(1, 1, vec![0], &[0; 771]).err(),
+            Some(ImageError::InvalidPaletteLength { len: 771 })
+        );
+        assert_eq!(
+            Image::from_data(70_000, 0, vec![], &palette).err(),
+            Some(ImageError::TooLarge {
+                width: 70_000,
+                height: 0
+            })
+        );
+    }
+
+    #[test]
+    fn image_palette_colors() {
+        let palette = [0, 0, 0, 63, 32, 0];
+        let mut image = Image::from_data(1, 1, vec![1], &palette).unwrap();
+        assert_eq!(image.palette_mut().len(), 6);
+        assert_eq!(
+            image.palette_colors(),
+            [Rgb::new(0, 0, 0), Rgb::new(63, 32, 0)]
+        );
+        assert_eq!(image.color(1), Some(Rgb::new(63, 32, 0)));
+        assert_eq!(image.color(2), None);
+
+        image.palette_colors_mut()[0] = Rgb::new(10, 20, 30);
+        assert_eq!(image.palette(), [10, 20, 30, 63, 32, 0]);
+    }
+
+    #[test]
+    fn install_palette_range_shifts_the_image() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let palette = [10, 0, 0, 0, 20, 0, 0, 0, 30];
+        let mut image = Image::from_data(2, 2, vec![0, 1, 2, 1], &palette).unwrap();
+        image.set_color_key(Some(0));
+
+        let shifted = image.install_palette_range(100).unwrap();
+        assert_eq!(shifted.data(), [100, 101, 102, 101]);
+        assert_eq!(shifted.color_key(), Some(100));
+        assert_eq!(shifted.palette_count(), 103);
+        assert!(shifted.palette()[..300].iter().all(|&v| v == 0));
+        assert_eq!(&shifted.palette()[300..], palette);
+        // the original is left as it was
+        assert_eq!(image.data(), [0, 1, 2, 1]);
+
+        image.install_palette_range_in_place(253).unwrap();
+        assert_eq!(image.data(), [253, 254, 255, 254]);
+        assert_eq!(image.color(255), Some(Rgb::new(0, 0, 30)));
+        assert_eq!(
+            image.install_palette_range_in_place(1),
+            Err(ImageError::PaletteRangeOverflow {
+                start: 1,
+                count: 256
+            })
+        );
+        assert_eq!(image.data(), [253, 254, 255, 254]);
+    }
+
+    #[test]
+    fn apply_palette_at_checks_the_range() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let palette: Vec<u8> = (0..10).flat_map(|v| [v, v, v]).collect();
+        let image = Image::from_data(1, 1, vec![0], &palette).unwrap();
+        let generation = PALETTE_GENERATION.load(Ordering::Relaxed);
+        assert_eq!(
+            image.apply_palette_at(250),
+            Err(ImageError::PaletteRangeOverflow {
+                start: 250,
+                count: 10
+            })
+        );
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation);
+        assert_eq!(image.apply_palette_at(246), Ok(()));
+        image.apply_palette();
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation + 2);
+        // the pixels are left as they are
+        assert_eq!(image.data(), [0]);
+    }
+
+    #[test]
+    fn sub_image_copies_a_clamped_region() {
+        let mut palette = [0; 768];
+        palette[3..6].copy_from_slice(&[63, 32, 0]);
+        let mut image = Image::from_pixels(4, 3, palette, 2, (0..12).collect());
+        image.set_color_key(Some(5));
+
+        let sub = image.sub_image(1, 1, 2, 2);
+        assert_eq!((sub.width(), sub.height()), (2, 2));
+        assert_eq!(sub.data(), [5, 6, 9, 10]);
+        assert_eq!(sub.palette(), image.palette());
+        assert_eq!(sub.palette_count(), 2);
+        assert_eq!(sub.color_key(), Some(5));
+
+        let sub = image.sub_image(2, 1, 10, 10);
+        assert_eq!((sub.width(), sub.height()), (2, 2));
+        assert_eq!(sub.data(), [6, 7, 10, 11]);
+
+        let sub = image.sub_image(4, 0, 2, 2);
+        assert_eq!(sub.width(), 0);
+        assert!(sub.data().is_empty());
+    }
+
+    #[test]
+    fn silhouettes_paint_or_remap() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Graphics320x200);
+        let width = screen_width() as usize;
+        let source = [0, 1, 2, 0, 3, 0];
+        let rows = || {
+            let screen = unsafe { screen_buffer() };
+            [screen[..4].to_vec(), screen[width..width + 4].to_vec()]
+        };
+
+        unsafe { screen_buffer() }.fill(5);
+        blit_silhouette(0, 0, &source, 3, 2, Rect::new(0, 0, 3, 2), 0, 9);
+        assert_eq!(rows(), [[5, 9, 9, 5], [5, 9, 5, 5]]);
+
+        let mut darker = [0; 256];
+        for (i, entry) in darker.iter_mut().enumerate() {
+            *entry = (i / 2) as u8;
+        }
+        unsafe { screen_buffer() }.fill(8);
+        blit_silhouette_flipped(
+            0,
+            0,
+            &source,
+            3,
+            2,
+            Rect::new(0, 0, 3, 2),
+            true,
+            false,
+            0,
+            &darker,
+        );
+        assert_eq!(rows(), [[4, 4, 8, 8], [8, 4, 8, 8]]);
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn border_color_is_clamped_and_read_back() {
+        assert_eq!(border_color(), (0, 0, 0));
+        set_border_color(10, 70, 63);
+        assert_eq!(border_color(), (10, 63, 63));
+        set_border_color(0, 0, 0);
+        assert_eq!(border_color(), (0, 0, 0));
+    }
+
+    #[test]
+    fn border_color_expands_to_xbgr() {
+        assert_eq!(palette_to_xbgr(0, 0, 0), 0);
+        assert_eq!(palette_to_xbgr(63, 0, 0), 0x0000FF);
+        assert_eq!(palette_to_xbgr(0, 63, 0), 0x00FF00);
+        assert_eq!(palette_to_xbgr(0, 0, 63), 0xFF0000);
+        assert_eq!(palette_to_xbgr(32, 16, 1), 0x044182);
+    }
+
+    /// Reads the numbers in the name of a video mode,
+    /// such as `[80, 25, 8, 16]` for `Text80x25_8x16`.
+    fn numbers_in_name(mode: VideoMode) -> Vec<u16> {
+        format!("{:?}", mode)
+            .split(|c: char| !c.is_ascii_digit())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn video_mode_tables_match_names() {
+        for mode in VideoMode::ALL {
+            let numbers = numbers_in_name(mode);
+            if mode.is_text() {
+                let [columns, rows, font_w, font_h] = numbers[..] else {
+                    panic!("unexpected name of {:?}", mode);
+                };
+                assert_eq!(mode.text_columns(), columns, "{:?}", mode);
+                assert_eq!(mode.text_rows(), rows, "{:?}", mode);
+                assert_eq!(
+                    mode.font_size(),
+                    Some((font_w as u8, font_h as u8)),
+                    "{:?}",
+                    mode
+                );
+                assert_eq!(
+                    mode.pixel_dimensions(),
+                    (columns * font_w, rows * font_h),
+                    "{:?}",
+                    mode
+                );
+            } else {
+                let [width, height] = numbers[..] else {
+                    panic!("unexpected name of {:?}", mode);
+                };
+                assert_eq!(mode.text_columns(), 0, "{:?}", mode);
+                assert_eq!(mode.text_rows(), 0, "{:?}", mode);
+                assert_eq!(mode.font_size(), None, "{:?}", mode);
+                assert_eq!(mode.pixel_dimensions(), (width, height), "{:?}", mode);
+            }
+            assert_ne!(mode.is_text(), mode.is_graphics());
+        }
+    }
+
+    #[test]
+    fn video_mode_pixel_aspect() {
+        assert_eq!(VideoMode::Graphics320x200.pixel_aspect(), 1.2);
+        assert_eq!(VideoMode::Graphics320x240.pixel_aspect(), 1.);
+        assert_eq!(VideoMode::Graphics640x480.pixel_aspect(), 1.);
+        assert_eq!(VideoMode::Graphics320x400.pixel_aspect(), 0.6);
+        assert_eq!(VideoMode::Text80x25_9x16.pixel_aspect(), 1.35);
+        assert_eq!(VideoMode::Text80x25_8x16.pixel_aspect(), 1.2);
+    }
+
+    #[test]
+    fn video_mode_all_is_complete() {
+        for (i, mode) in VideoMode::ALL.iter().enumerate() {
+            assert!(
+                !VideoMode::ALL[..i].contains(mode),
+                "{:?} listed twice",
+                mode
+            );
+        }
+        assert_eq!(VideoMode::ALL.iter().filter(|m| m.is_text()).count(), 7);
+        assert_eq!(VideoMode::ALL.iter().filter(|m| m.is_graphics()).count(), 7);
+    }
+
+    /// Outlines the region between two corners on a blank 12 by 10 buffer,
+    /// as `rectangle_corners` does on the screen,
+    /// and returns the pixels drawn.
+    fn outline_corners(a: (i32, i32), b: (i32, i32)) -> Vec<(i32, i32)> {
+        let mut pixels = [0; 12 * 10];
+        if let Some((x, y, width, height)) = outline_args(Rect::from_corners(a, b)) {
+            raster::rectangle(
+                &raster::Clip::pixels(&mut pixels, 12, 10, 1),
+                x,
+                y,
+                width,
+                height,
+            );
+        }
+        (0..10)
+            .flat_map(|y| (0..12).map(move |x| (x, y)))
+            .filter(|&(x, y)| pixels[y as usize * 12 + x as usize] != 0)
+            .collect()
+    }
+
+    #[test]
+    fn scale_stepper_matches_division() {
+        for from in 1..12 {
+            for to in 1..12 {
+                for start in 0..to {
+                    let mut stepper = ScaleStepper::new(from, to, start);
+                    for dst in start..to {
+                        let expected = dst * from / to;
+                        assert_eq!(stepper.next(), expected, "{}->{} at {}", from, to, dst);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rectangle_corners_in_any_order() {
+        let expected = outline_corners((2, 3), (6, 7));
+        assert_eq!(expected.len(), 2 * 5 + 2 * 3);
+        assert!(expected
+            .iter()
+            .all(|&(x, y)| (2..=6).contains(&x) && (3..=7).contains(&y)));
+        assert!(expected.contains(&(2, 3)) && expected.contains(&(6, 7)));
+        assert!(!expected.contains(&(4, 5)));
+        assert_eq!(outline_corners((6, 7), (2, 3)), expected);
+        assert_eq!(outline_corners((6, 3), (2, 7)), expected);
+        assert_eq!(outline_corners((2, 7), (6, 3)), expected);
+    }
+
+    #[test]
+    fn rectangle_corners_degenerate() {
+        // same row
+        assert_eq!(
+            outline_corners((7, 4), (3, 4)),
+            [(3, 4), (4, 4), (5, 4), (6, 4), (7, 4)]
+        );
+        // same column
+        assert_eq!(
+            outline_corners((5, 6), (5, 2)),
+            [(5, 2), (5, 3), (5, 4), (5, 5), (5, 6)]
+        );
+        // same point
+        assert_eq!(outline_corners((8, 1), (8, 1)), [(8, 1)]);
+        // two pixels apart on both axes
+        assert_eq!(
+            outline_corners((1, 1), (0, 0)),
+            [(0, 0), (1, 0), (0, 1), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn rectangle_extends_one_row_below() {
+        assert_eq!(outline_args(Rect::new(1, 2, 3, 4)), Some((1, 2, 3, 3)));
+        assert_eq!(outline_args(Rect::new(1, 2, 0, 4)), None);
+        assert_eq!(outline_args(Rect::new(1, 2, 3, 0)), None);
+
+        let mut pixels = [0; 6 * 6];
+        raster::rectangle(&raster::Clip::pixels(&mut pixels, 6, 6, 1), 1, 1, 3, 2);
+        #[rustfmt::skip]
+        let expected = [
+            0, 0, 0, 0, 0, 0,
+            0, 1, 1, 1, 0, 0,
+            0, 1, 0, 1, 0, 0,
+            0, 1, 1, 1, 0, 0,
+            0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn put_pixels_offset_and_clipped() {
+        let (w, h) = (8, 6);
+        let mut pixels = vec![0; w * h];
+        put_pixels_in(
+            &mut pixels,
+            w,
+            Rect::new(1, 1, 6, 4),
+            (1, 2),
+            [
+                (0, 0, 1),
+                (5, 2, 2),
+                (-1, 0, 3),
+                (5, 3, 4),
+                (6, 0, 5),
+                (i32::MAX, 0, 6),
+                (i32::MIN, i32::MIN, 7),
+                (2, 1, 8),
+                (2, 1, 9),
+            ],
+        );
+        #[rustfmt::skip]
+        let expected = [
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+            0, 1, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 9, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 2, 0,
+            0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        assert_eq!(pixels, expected);
+    }
+
+    /// An allocator counting the bytes allocated and not yet freed
+    /// by each thread, to check that images free their memory.
+    mod counting {
+        use std::alloc::{GlobalAlloc, Layout, System};
+        use std::cell::Cell;
+
+        thread_local! {
+            static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+        }
+
+        struct Counting;
+
+        fn count(delta: isize) {
+            let _ = LIVE_BYTES.try_with(|live| live.set(live.get() + delta));
+        }
+
+        unsafe impl GlobalAlloc for Counting {
+            unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+                count(layout.size() as isize);
+                System.alloc(layout)
+            }
+
+            unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+                count(-(layout.size() as isize));
+                System.dealloc(ptr, layout)
+            }
+        }
+
+        #[global_allocator]
+        static ALLOCATOR: Counting = Counting;
+
+        /// Gets the bytes allocated and not yet freed by this thread.
+        pub fn live_bytes() -> isize {
+            LIVE_BYTES.with(|live| live.get())
+        }
+    }
+
+    thread_local! {
+        /// The buffers of the engine freed by this thread.
+        pub(super) static ENGINE_FREES: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    #[test]
+    fn load_gif_frees_the_engine_buffer_once() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/rotozoom.gif");
+        let before = ENGINE_FREES.with(|frees| frees.get());
+        for _ in 0..10 {
+            let image = load_gif(path).unwrap();
+            drop(image);
+        }
+        assert_eq!(ENGINE_FREES.with(|frees| frees.get()), before + 10);
+    }
+
+    #[test]
+    fn dropped_images_free_their_pixels() {
+        let gif =
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/rotozoom.gif")).unwrap();
+        let palette: Vec<u8> = (0..48).collect();
+
+        let before = counting::live_bytes();
+        for i in 0..100 {
+            let loaded = load_gif_from_memory(&gif).unwrap();
+            let mut built =
+                Image::from_data(64, 32, vec![i as u8 % 16; 64 * 32], &palette).unwrap();
+            built.data_mut()[0] = 1;
+            let animation = load_gif_animation_from_memory(&gif).unwrap();
+            drop((loaded, built, animation));
+        }
+        assert_eq!(counting::live_bytes(), before);
+    }
+}
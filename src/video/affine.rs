@@ -0,0 +1,501 @@
+//! Texture mapping onto the screen,
+//! of whole planes and of wall columns.
+
+use crate::video::{draw_bounds, offset_xy, video_mode, Image, Rect, Surface};
+
+/// The number of fractional bits of the fixed-point texture coordinates.
+const FRAC_BITS: u32 = 16;
+
+/// A buffer of indexed pixels to map with [`affine_blit`] or [`draw_tex_column`].
+///
+/// Images and surfaces can be converted into a texture,
+/// or one can be made from any pixel buffer with [`Texture::new`].
+#[derive(Debug, Copy, Clone)]
+pub struct Texture<'a> {
+    pixels: &'a [u8],
+    width: u16,
+    height: u16,
+}
+
+impl<'a> Texture<'a> {
+    /// Creates a texture from a buffer of `width` by `height` pixels,
+    /// row by row.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the buffer is too short for the given dimensions,
+    /// since this is likely a bug.
+    pub fn new(pixels: &'a [u8], width: u16, height: u16) -> Self {
+        if width as usize * height as usize > pixels.len() {
+            panic!(
+                "Texture: pixel data ({} bytes) is too short for resolution {}x{}",
+                pixels.len(),
+                width,
+                height
+            );
+        }
+        Texture {
+            pixels,
+            width,
+            height,
+        }
+    }
+
+    /// Gets the width of the texture in pixels.
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Gets the height of the texture in pixels.
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+}
+
+impl<'a> From<&'a Image> for Texture<'a> {
+    #[inline]
+    fn from(image: &'a Image) -> Self {
+        Texture::new(image.data(), image.width() as u16, image.height() as u16)
+    }
+}
+
+impl<'a> From<&'a Surface> for Texture<'a> {
+    #[inline]
+    fn from(surface: &'a Surface) -> Self {
+        Texture::new(surface.pixels(), surface.width(), surface.height())
+    }
+}
+
+/// Converts a coordinate to fixed point.
+#[inline]
+fn fixed(v: f32) -> i64 {
+    (v as f64 * (1 << FRAC_BITS) as f64) as i64
+}
+
+/// Fills a region of the screen with a texture
+/// mapped through an affine transformation,
+/// as in the rotating and zooming planes of demos and Mode 7 racers.
+///
+/// The texture coordinates of the top left pixel of `dst` are `origin`,
+/// and they advance by `u_axis` for each pixel to the right
+/// and by `v_axis` for each pixel down.
+/// Texture coordinates outside of the texture
+/// wrap around if `wrap` is true,
+/// or are clamped to the nearest edge otherwise.
+/// Texture pixels of the color key, if any, are not drawn.
+///
+/// The coordinates are stepped in 16.16 fixed point,
+/// so the texture and the steps should be well below 32768 pixels.
+/// The region is clipped to the screen and the clip rectangle,
+/// and is subject to the draw offset.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// A plane rotating and zooming around the screen's top left corner:
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let texture = load_gif("texture.gif").unwrap();
+/// let mut angle: f32 = 0.;
+/// while !shutting_down() {
+///     wait_vbl();
+///     angle += 0.01;
+///     let (s, c) = angle.sin_cos();
+///     let zoom = 1. + s * 0.5;
+///     affine_blit(
+///         Rect::new(0, 0, 320, 200),
+///         &texture,
+///         (0., 0.),
+///         (c * zoom, s * zoom),
+///         (-s * zoom, c * zoom),
+///         true,
+///         None,
+///     );
+/// }
+/// ```
+pub fn affine_blit<'a>(
+    dst: impl Into<Rect>,
+    source: impl Into<Texture<'a>>,
+    origin: (f32, f32),
+    u_axis: (f32, f32),
+    v_axis: (f32, f32),
+    wrap: bool,
+    color_key: Option<u8>,
+) {
+    let dst = dst.into();
+    let source = source.into();
+    if !video_mode().is_graphics() || source.width == 0 || source.height == 0 {
+        return;
+    }
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let (screen, screen_w) = unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return;
+        }
+        (
+            std::slice::from_raw_parts_mut(buf, width * height),
+            width as i64,
+        )
+    };
+
+    // clip the destination to the screen and the clip rectangle
+    let bounds = draw_bounds();
+    let (dst_x, dst_y) = offset_xy(dst.x, dst.y);
+    let (dst_x, dst_y) = (dst_x as i64, dst_y as i64);
+    let x0 = dst_x.max(bounds.left() as i64);
+    let x1 = (dst_x + dst.width as i64).min(bounds.right());
+    let y0 = dst_y.max(bounds.top() as i64);
+    let y1 = (dst_y + dst.height as i64).min(bounds.bottom());
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    let (ux, uy) = (fixed(u_axis.0), fixed(u_axis.1));
+    let (vx, vy) = (fixed(v_axis.0), fixed(v_axis.1));
+    let (ox, oy) = (fixed(origin.0), fixed(origin.1));
+    let (tex_w, tex_h) = (source.width as i64, source.height as i64);
+    let texel = |fx: i64, fy: i64| {
+        let (tx, ty) = (fx >> FRAC_BITS, fy >> FRAC_BITS);
+        let (tx, ty) = if wrap {
+            (tx.rem_euclid(tex_w), ty.rem_euclid(tex_h))
+        } else {
+            (tx.clamp(0, tex_w - 1), ty.clamp(0, tex_h - 1))
+        };
+        source.pixels[(ty * tex_w + tx) as usize]
+    };
+
+    for y in y0..y1 {
+        let (i, j) = (x0 - dst_x, y - dst_y);
+        let mut fx = ox + i * ux + j * vx;
+        let mut fy = oy + i * uy + j * vy;
+        let row = &mut screen[(y * screen_w + x0) as usize..(y * screen_w + x1) as usize];
+        match color_key {
+            None => {
+                for pixel in row {
+                    *pixel = texel(fx, fy);
+                    fx += ux;
+                    fy += uy;
+                }
+            }
+            Some(key) => {
+                for pixel in row {
+                    let color = texel(fx, fy);
+                    if color != key {
+                        *pixel = color;
+                    }
+                    fx += ux;
+                    fy += uy;
+                }
+            }
+        }
+    }
+}
+
+/// Draws an image rotated and scaled about its center,
+/// as for the sprites of ships and turrets.
+///
+/// - `center_x` and `center_y` are the screen position
+///   where the center of the image lands
+/// - `angle` is the rotation in radians,
+///   clockwise on the screen
+/// - `scale` is the size factor, 1 for the original size
+/// - `wrap` makes the texture repeat beyond the image,
+///   filling the whole bounding box of the rotated image,
+///   instead of drawing nothing there
+///
+/// Pixels of the image's [color key](Image::color_key), if any,
+/// are not drawn.
+/// The image is never read outside of its bounds.
+///
+/// Only the bounding box of the rotated image is visited,
+/// with the texture coordinates stepped in 16.16 fixed point.
+/// The drawing is clipped to the screen and the clip rectangle,
+/// and is subject to the draw offset.
+///
+/// Does nothing unless the video is in graphics mode,
+/// or if the scale is not positive.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let ship = load_gif("ship.gif")?;
+/// let mut angle: f32 = 0.;
+/// while !shutting_down() {
+///     wait_vbl();
+///     clear_screen();
+///     angle += 0.05;
+///     rotate_blit(160, 100, &ship, angle, 2., false);
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn rotate_blit(
+    center_x: i32,
+    center_y: i32,
+    image: &Image,
+    angle: f32,
+    scale: f32,
+    wrap: bool,
+) {
+    let source = Texture::from(image);
+    // also rejects NaN
+    if !video_mode().is_graphics()
+        || source.width == 0
+        || source.height == 0
+        || !(scale > 0. && scale.is_finite() && angle.is_finite())
+    {
+        return;
+    }
+
+    // the bounding box of the rotated image
+    let (sin, cos) = (angle as f64).sin_cos();
+    let (half_w, half_h) = (
+        source.width as f64 * scale as f64 / 2.,
+        source.height as f64 * scale as f64 / 2.,
+    );
+    let extent_x = (half_w * cos).abs() + (half_h * sin).abs();
+    let extent_y = (half_w * sin).abs() + (half_h * cos).abs();
+    let (cx, cy) = offset_xy(center_x, center_y);
+    let (cx, cy) = (cx as f64, cy as f64);
+
+    // clip it to the screen and the clip rectangle
+    let bounds = draw_bounds();
+    let x0 = ((cx - extent_x).floor() as i64).max(bounds.left() as i64);
+    let x1 = ((cx + extent_x).ceil() as i64).min(bounds.right());
+    let y0 = ((cy - extent_y).floor() as i64).max(bounds.top() as i64);
+    let y1 = ((cy + extent_y).ceil() as i64).min(bounds.bottom());
+    if x0 >= x1 || y0 >= y1 {
+        return;
+    }
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let (screen, screen_w) = unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return;
+        }
+        (
+            std::slice::from_raw_parts_mut(buf, width * height),
+            width as i64,
+        )
+    };
+
+    // map the center of each screen pixel back into the image
+    let inv = 1. / scale as f64;
+    let (ux, uy) = (fixed((cos * inv) as f32), fixed((-sin * inv) as f32));
+    let (vx, vy) = (fixed((sin * inv) as f32), fixed((cos * inv) as f32));
+    let (dx, dy) = (x0 as f64 + 0.5 - cx, y0 as f64 + 0.5 - cy);
+    let ox = fixed(((dx * cos + dy * sin) * inv + source.width as f64 / 2.) as f32);
+    let oy = fixed(((dy * cos - dx * sin) * inv + source.height as f64 / 2.) as f32);
+
+    let (tex_w, tex_h) = (source.width as i64, source.height as i64);
+    let color_key = image.color_key();
+    for (j, y) in (y0..y1).enumerate() {
+        let j = j as i64;
+        let mut fx = ox + j * vx;
+        let mut fy = oy + j * vy;
+        let row = &mut screen[(y * screen_w + x0) as usize..(y * screen_w + x1) as usize];
+        for pixel in row {
+            let (tx, ty) = (fx >> FRAC_BITS, fy >> FRAC_BITS);
+            fx += ux;
+            fy += uy;
+            let (tx, ty) = if wrap {
+                (tx.rem_euclid(tex_w), ty.rem_euclid(tex_h))
+            } else if (tx as u64) < tex_w as u64 && (ty as u64) < tex_h as u64 {
+                (tx, ty)
+            } else {
+                continue;
+            };
+            let color = source.pixels[(ty * tex_w + tx) as usize];
+            if Some(color) != color_key {
+                *pixel = color;
+            }
+        }
+    }
+}
+
+/// Draws a vertical strip of a texture scaled to the given height,
+/// as the walls of a raycaster.
+///
+/// - `x` and `y_top` are the screen position of the top of the strip
+/// - `height` is the height of the strip on the screen,
+///   which may extend beyond the top and bottom of the screen
+/// - `tex_x` is the column of the texture to draw,
+///   wrapping around the texture width
+/// - texture pixels of the color key, if any, are not drawn
+/// - if `shade_remap` is given,
+///   each pixel is replaced by its entry in the table,
+///   such as to darken distant walls
+///
+/// The texture column is stepped in 16.16 fixed point,
+/// and the strip is clipped to the screen and the clip rectangle.
+/// The draw offset applies.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let wall = load_gif("wall.gif").unwrap();
+/// for x in 0..320 {
+///     // a wall receding to the right
+///     let height = 180 - x as u32 / 4;
+///     let y_top = 100 - height as i32 / 2;
+///     draw_tex_column(x, y_top, height, &wall, x as u32, None, None);
+/// }
+/// ```
+pub fn draw_tex_column<'a>(
+    x: i32,
+    y_top: i32,
+    height: u32,
+    texture: impl Into<Texture<'a>>,
+    tex_x: u32,
+    color_key: Option<u8>,
+    shade_remap: Option<&[u8; 256]>,
+) {
+    let texture = texture.into();
+    if !video_mode().is_graphics() || height == 0 || texture.width == 0 || texture.height == 0 {
+        return;
+    }
+    let (x, y_top) = offset_xy(x, y_top);
+    let (x, y_top) = (x as i64, y_top as i64);
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let (screen, screen_w) = unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return;
+        }
+        (
+            std::slice::from_raw_parts_mut(buf, width * height),
+            width as i64,
+        )
+    };
+    let bounds = draw_bounds();
+    if x < bounds.left() as i64 || x >= bounds.right() {
+        return;
+    }
+    let y0 = y_top.max(bounds.top() as i64);
+    let y1 = (y_top + height as i64).min(bounds.bottom());
+    if y0 >= y1 {
+        return;
+    }
+
+    let tex_w = texture.width as usize;
+    let tex_h = texture.height as i64;
+    let column = tex_x as usize % tex_w;
+    let step = (tex_h << FRAC_BITS) / height as i64;
+    let mut ty = (y0 - y_top) * step;
+    for y in y0..y1 {
+        let row = ((ty >> FRAC_BITS) as usize).min(tex_h as usize - 1);
+        let color = texture.pixels[row * tex_w + column];
+        ty += step;
+        if Some(color) == color_key {
+            continue;
+        }
+        let color = match shade_remap {
+            Some(remap) => remap[color as usize],
+            None => color,
+        };
+        screen[(y * screen_w + x) as usize] = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::tests::VIDEO_MODE_LOCK;
+    use crate::video::{screen_buffer, screen_width, set_video_mode, VideoMode};
+
+    /// Gets a region of the screen, row by row.
+    fn screen_rows(x: usize, y: usize, width: usize, height: usize) -> Vec<Vec<u8>> {
+        let screen = unsafe { screen_buffer() };
+        let screen_w = screen_width() as usize;
+        (y..y + height)
+            .map(|y| screen[y * screen_w + x..][..width].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn rotate_blit_turns_clockwise() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Graphics320x200);
+        let mut image = Image::from_pixels(4, 2, [0; 768], 16, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        unsafe { screen_buffer() }.fill(0);
+        rotate_blit(10, 10, &image, 0., 1., false);
+        assert_eq!(
+            screen_rows(7, 8, 6, 4),
+            [
+                [0, 0, 0, 0, 0, 0],
+                [0, 1, 2, 3, 4, 0],
+                [0, 5, 6, 7, 8, 0],
+                [0, 0, 0, 0, 0, 0],
+            ]
+        );
+
+        unsafe { screen_buffer() }.fill(0);
+        rotate_blit(10, 10, &image, std::f32::consts::FRAC_PI_2, 1., false);
+        assert_eq!(
+            screen_rows(8, 7, 4, 6),
+            [
+                [0, 0, 0, 0],
+                [0, 5, 1, 0],
+                [0, 6, 2, 0],
+                [0, 7, 3, 0],
+                [0, 8, 4, 0],
+                [0, 0, 0, 0],
+            ]
+        );
+
+        // the color key is skipped and the rest is clipped to the screen
+        image.set_color_key(Some(6));
+        unsafe { screen_buffer() }.fill(9);
+        rotate_blit(1, 1, &image, 0., 1., false);
+        assert_eq!(screen_rows(0, 0, 4, 2), [[2, 3, 4, 9], [9, 7, 8, 9]]);
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn rotate_blit_scales_and_wraps() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Graphics320x200);
+        let image = Image::from_pixels(2, 2, [0; 768], 16, vec![1, 2, 3, 4]);
+
+        unsafe { screen_buffer() }.fill(0);
+        rotate_blit(10, 10, &image, 0., 2., false);
+        assert_eq!(
+            screen_rows(8, 8, 4, 4),
+            [[1, 1, 2, 2], [1, 1, 2, 2], [3, 3, 4, 4], [3, 3, 4, 4]]
+        );
+
+        // at 45 degrees, the corners of the bounding box
+        // are only filled when wrapping
+        let angle = std::f32::consts::FRAC_PI_4;
+        unsafe { screen_buffer() }.fill(0);
+        rotate_blit(10, 10, &image, angle, 4., false);
+        assert_eq!(screen_rows(5, 5, 1, 1), [[0]]);
+        rotate_blit(10, 10, &image, angle, 4., true);
+        assert_ne!(screen_rows(5, 5, 1, 1), [[0]]);
+
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+}
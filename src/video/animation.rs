@@ -0,0 +1,346 @@
+//! Frame-based animations of sprites.
+
+use std::time::Duration;
+
+use crate::video::sprite::draw_region;
+use crate::video::{Image, Rect, SpriteDrawOptions, SpriteSheet};
+
+/// How an animation behaves after its last frame.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum LoopMode {
+    /// Start over from the first frame
+    #[default]
+    Loop,
+    /// Stop at the last frame
+    Once,
+    /// Play backwards to the first frame, then forwards again
+    PingPong,
+}
+
+/// A sequence of frames to show,
+/// each for its own duration.
+///
+/// Frames are given as indices into the frame source
+/// of an [`AnimationPlayer`]:
+/// either the frames of a [`SpriteSheet`]
+/// or a list of images.
+#[derive(Debug, Default, Clone, Eq, Hash, PartialEq)]
+pub struct Animation {
+    frames: Vec<(usize, Duration)>,
+    loop_mode: LoopMode,
+}
+
+impl Animation {
+    /// Creates an animation without frames.
+    #[inline]
+    pub fn new(loop_mode: LoopMode) -> Self {
+        Animation {
+            frames: Vec::new(),
+            loop_mode,
+        }
+    }
+
+    /// Creates an animation showing each of the given frames
+    /// for the same duration.
+    pub fn uniform(
+        frames: impl IntoIterator<Item = usize>,
+        duration: Duration,
+        loop_mode: LoopMode,
+    ) -> Self {
+        Animation {
+            frames: frames.into_iter().map(|i| (i, duration)).collect(),
+            loop_mode,
+        }
+    }
+
+    /// Adds a frame to the end of the animation.
+    #[inline]
+    pub fn with_frame(mut self, index: usize, duration: Duration) -> Self {
+        self.push_frame(index, duration);
+        self
+    }
+
+    /// Adds a frame to the end of the animation.
+    #[inline]
+    pub fn push_frame(&mut self, index: usize, duration: Duration) {
+        self.frames.push((index, duration));
+    }
+
+    /// Gets the frames of the animation and their durations.
+    #[inline]
+    pub fn frames(&self) -> &[(usize, Duration)] {
+        &self.frames
+    }
+
+    /// Gets the loop mode of the animation.
+    #[inline]
+    pub fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    /// Gets the time it takes to go through all frames once.
+    pub fn total_duration(&self) -> Duration {
+        self.frames.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+/// Where the frames of an animation are taken from.
+#[derive(Debug, Copy, Clone)]
+pub enum FrameSource<'a> {
+    /// The frames of a sprite sheet
+    Sheet(&'a SpriteSheet),
+    /// A list of images, one per frame
+    Images(&'a [Image]),
+}
+
+impl<'a> From<&'a SpriteSheet> for FrameSource<'a> {
+    #[inline]
+    fn from(sheet: &'a SpriteSheet) -> Self {
+        FrameSource::Sheet(sheet)
+    }
+}
+
+impl<'a> From<&'a [Image]> for FrameSource<'a> {
+    #[inline]
+    fn from(images: &'a [Image]) -> Self {
+        FrameSource::Images(images)
+    }
+}
+
+impl<'a> From<&'a Vec<Image>> for FrameSource<'a> {
+    #[inline]
+    fn from(images: &'a Vec<Image>) -> Self {
+        FrameSource::Images(images)
+    }
+}
+
+/// Plays an animation over a source of frames.
+///
+/// The player is advanced by the time elapsed since the last update,
+/// so the animation runs at the same speed
+/// regardless of how often it is updated or drawn.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// # use std::time::{Duration, Instant};
+/// let sheet = SpriteSheet::new(load_gif("walk.gif").unwrap(), 16, 32, 0, 0);
+/// let walk = Animation::uniform(0..4, Duration::from_millis(120), LoopMode::Loop);
+/// let mut player = AnimationPlayer::new(walk, &sheet);
+///
+/// let mut last = Instant::now();
+/// while !shutting_down() {
+///     wait_vbl();
+///     let now = Instant::now();
+///     player.update(now - last);
+///     last = now;
+///     if player.frame_started(1) || player.frame_started(3) {
+///         // play a footstep sound
+///     }
+///     clear_screen();
+///     player.draw(100, 80);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer<'a> {
+    animation: Animation,
+    source: FrameSource<'a>,
+    options: SpriteDrawOptions,
+    /// Position in the sequence of frames
+    step: usize,
+    /// Time spent in the current step
+    elapsed: Duration,
+    /// Whether ping-pong playback is going backwards
+    backwards: bool,
+    finished: bool,
+    /// Whether the first frame was already reported as started
+    started_first: bool,
+    /// Frames started during the last update
+    started: Vec<usize>,
+}
+
+impl<'a> AnimationPlayer<'a> {
+    /// Creates a player for the given animation,
+    /// starting at its first frame.
+    pub fn new(animation: Animation, source: impl Into<FrameSource<'a>>) -> Self {
+        AnimationPlayer {
+            animation,
+            source: source.into(),
+            options: SpriteDrawOptions::new(),
+            step: 0,
+            elapsed: Duration::ZERO,
+            backwards: false,
+            finished: false,
+            started_first: false,
+            started: Vec::new(),
+        }
+    }
+
+    /// Gets the animation being played.
+    #[inline]
+    pub fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    /// Sets the options used to draw the frames.
+    #[inline]
+    pub fn set_draw_options(&mut self, options: SpriteDrawOptions) {
+        self.options = options;
+    }
+
+    /// Gets the options used to draw the frames.
+    #[inline]
+    pub fn draw_options(&self) -> SpriteDrawOptions {
+        self.options
+    }
+
+    /// Restarts the animation from its first frame.
+    pub fn reset(&mut self) {
+        self.step = 0;
+        self.elapsed = Duration::ZERO;
+        self.backwards = false;
+        self.finished = false;
+        self.started_first = false;
+        self.started.clear();
+    }
+
+    /// Advances the animation by the given amount of time.
+    ///
+    /// Several frames may be skipped over in a single update
+    /// if the time given is longer than their durations.
+    pub fn update(&mut self, delta: Duration) {
+        self.started.clear();
+        let frames = self.animation.frames.len();
+        if frames == 0 {
+            return;
+        }
+        if !self.started_first {
+            self.started_first = true;
+            self.started.push(self.current_frame());
+        }
+        if self.finished {
+            return;
+        }
+
+        self.elapsed += delta;
+        // frames without duration are passed over without waiting,
+        // but not indefinitely
+        let mut instant_steps = 0;
+        loop {
+            let duration = self.animation.frames[self.step].1;
+            if self.elapsed < duration {
+                break;
+            }
+            if duration.is_zero() {
+                instant_steps += 1;
+                if instant_steps > frames * 2 {
+                    break;
+                }
+            }
+            self.elapsed -= duration;
+            if !self.advance() {
+                self.finished = true;
+                self.elapsed = Duration::ZERO;
+                break;
+            }
+            self.started.push(self.current_frame());
+        }
+    }
+
+    /// Moves to the next step of the sequence,
+    /// returning false if there is none.
+    fn advance(&mut self) -> bool {
+        let last = self.animation.frames.len() - 1;
+        match self.animation.loop_mode {
+            LoopMode::Loop => self.step = if self.step < last { self.step + 1 } else { 0 },
+            LoopMode::Once if self.step < last => self.step += 1,
+            LoopMode::Once => return false,
+            LoopMode::PingPong if last == 0 => {}
+            LoopMode::PingPong => {
+                if self.step == last {
+                    self.backwards = true;
+                } else if self.step == 0 {
+                    self.backwards = false;
+                }
+                if self.backwards {
+                    self.step -= 1;
+                } else {
+                    self.step += 1;
+                }
+            }
+        }
+        true
+    }
+
+    /// Gets the index of the frame currently shown.
+    ///
+    /// Returns 0 if the animation has no frames.
+    #[inline]
+    pub fn current_frame(&self) -> usize {
+        self.animation
+            .frames
+            .get(self.step)
+            .map(|(i, _)| *i)
+            .unwrap_or(0)
+    }
+
+    /// Gets the position of the current frame
+    /// in the sequence of frames of the animation.
+    #[inline]
+    pub fn current_step(&self) -> usize {
+        self.step
+    }
+
+    /// Checks whether an animation played once
+    /// has reached the end of its last frame.
+    ///
+    /// Looping animations never finish.
+    #[inline]
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Checks whether the frame with the given index
+    /// started being shown during the last update.
+    ///
+    /// The first frame of the animation
+    /// is reported on the first update.
+    #[inline]
+    pub fn frame_started(&self, index: usize) -> bool {
+        self.started.contains(&index)
+    }
+
+    /// Gets the indices of all frames
+    /// which started being shown during the last update,
+    /// in order.
+    #[inline]
+    pub fn started_frames(&self) -> &[usize] {
+        &self.started
+    }
+
+    /// Draws the current frame
+    /// with its top left corner at the given position.
+    ///
+    /// Nothing is drawn if the animation has no frames
+    /// or the frame index is out of bounds of the frame source.
+    pub fn draw(&self, x: i32, y: i32) {
+        if self.animation.frames.is_empty() {
+            return;
+        }
+        let index = self.current_frame();
+        match self.source {
+            FrameSource::Sheet(sheet) => {
+                if index < sheet.frame_count() {
+                    sheet.draw_frame(index, x, y, self.options);
+                }
+            }
+            FrameSource::Images(images) => {
+                if let Some(image) = images.get(index) {
+                    let rect = Rect::new(0, 0, image.width() as u16, image.height() as u16);
+                    draw_region(image, rect, x, y, self.options);
+                }
+            }
+        }
+    }
+}
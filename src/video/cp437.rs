@@ -0,0 +1,57 @@
+//! Conversion of text to code page 437,
+//! the character set of the fonts.
+
+/// The characters of code page 437 from 0x80 to 0xFF.
+const UPPER: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å', //
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ', //
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»', //
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', //
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', //
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', //
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', //
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{A0}',
+];
+
+/// The symbols shown for the control characters from 0x01 to 0x1F,
+/// and for 0x7F.
+const SYMBOLS: [char; 32] = [
+    '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼', '►', //
+    '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼', '⌂',
+];
+
+/// Converts a character to its code in code page 437,
+/// or `None` if it has none.
+///
+/// ASCII characters keep their codes,
+/// including control characters such as `'\n'`.
+/// The symbols which the fonts show for control characters
+/// (such as `'☺'` or `'♪'`)
+/// are converted to those codes as well.
+pub fn char_to_cp437(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        return Some(c as u8);
+    }
+    if let Some(i) = UPPER.iter().position(|&u| u == c) {
+        return Some(0x80 + i as u8);
+    }
+    match SYMBOLS.iter().position(|&s| s == c)? {
+        31 => Some(0x7F),
+        i => Some(1 + i as u8),
+    }
+}
+
+/// Converts text to code page 437,
+/// replacing the characters which it does not have with `'?'`.
+///
+/// # Example
+///
+/// ```
+/// # use dos_like::*;
+/// assert_eq!(encode_cp437("Café ♥"), b"Caf\x82 \x03");
+/// ```
+pub fn encode_cp437(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| char_to_cp437(c).unwrap_or(b'?'))
+        .collect()
+}
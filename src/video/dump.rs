@@ -0,0 +1,216 @@
+//! Raw dumps of the screen buffer and palette.
+
+use std::sync::atomic::Ordering;
+
+use crate::video::palette::palette_bytes;
+use crate::video::{is_double_buffered, video_mode, Image, FRONT_BUFFER};
+use crate::FileError;
+
+/// The magic bytes at the start of a screen dump.
+const MAGIC: &[u8; 4] = b"DLSD";
+
+/// Saves the contents of the screen as currently displayed,
+/// along with the full palette,
+/// to a screen dump file.
+///
+/// With double buffering,
+/// this is the buffer presented by the last call to
+/// [`swap_buffers`](super::swap_buffers)
+/// (or the current buffer if there was no swap yet),
+/// not the one being drawn to.
+///
+/// See the [`load_screen_dump`] function
+/// for loading it back as an image.
+///
+/// Fails with [`FileError::InvalidData`] if not in graphics mode.
+///
+/// # File format
+///
+/// A screen dump file is laid out as follows,
+/// with all integers in little endian:
+///
+/// | Offset | Size          | Contents                                  |
+/// |--------|---------------|-------------------------------------------|
+/// | 0      | 4             | magic bytes `DLSD`                        |
+/// | 4      | 2             | width in pixels                           |
+/// | 6      | 2             | height in pixels                          |
+/// | 8      | 2             | number of palette colors (1 to 256)       |
+/// | 10     | colors * 3    | palette in RGB, as passed to [`set_pal`]  |
+/// | ...    | width * height| indexed pixel data, row by row            |
+///
+/// [`set_pal`]: super::set_pal
+pub fn save_screen_dump(path: impl AsRef<str>) -> Result<(), FileError> {
+    if !video_mode().is_graphics() {
+        return Err(FileError::InvalidData(
+            "the screen is not in graphics mode".to_string(),
+        ));
+    }
+    let (width, height, pixels) = displayed_pixels();
+
+    let out = encode(width as u16, height as u16, &palette_bytes(), &pixels);
+
+    std::fs::write(path.as_ref(), out).map_err(|_| FileError::WriteFailed)
+}
+
+/// Copies the pixels of the screen as currently displayed,
+/// along with the width and height of the screen.
+///
+/// With double buffering,
+/// this is the buffer presented by the last swap
+/// (or the current buffer if there was no swap yet).
+pub(super) fn displayed_pixels() -> (usize, usize, Vec<u8>) {
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and it is only read here
+    unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let front = FRONT_BUFFER.load(Ordering::Relaxed);
+        let buffer = if is_double_buffered() && !front.is_null() {
+            front
+        } else {
+            dos_like_sys::screenbuffer()
+        };
+        let pixels = std::slice::from_raw_parts(buffer, width * height).to_vec();
+        (width, height, pixels)
+    }
+}
+
+/// Writes a screen dump of the given pixels
+/// and palette in RGB, of 1 to 256 colors.
+fn encode(width: u16, height: u16, palette: &[u8], pixels: &[u8]) -> Vec<u8> {
+    debug_assert!(palette.len().is_multiple_of(3) && (3..=768).contains(&palette.len()));
+    debug_assert_eq!(pixels.len(), width as usize * height as usize);
+    let mut out = Vec::with_capacity(10 + palette.len() + pixels.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&(palette.len() as u16 / 3).to_le_bytes());
+    out.extend_from_slice(palette);
+    out.extend_from_slice(pixels);
+    out
+}
+
+/// Loads a screen dump file
+/// (as saved by [`save_screen_dump`])
+/// into an image.
+pub fn load_screen_dump(path: impl AsRef<str>) -> Result<Image, FileError> {
+    let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+    load_screen_dump_from_memory(&data)
+}
+
+/// Loads the byte data of a screen dump file into an image.
+pub fn load_screen_dump_from_memory(data: &[u8]) -> Result<Image, FileError> {
+    let invalid = |message: &str| FileError::InvalidData(message.to_string());
+    if data.len() < 10 || &data[0..4] != MAGIC {
+        return Err(invalid("not a screen dump"));
+    }
+    let width = u16::from_le_bytes([data[4], data[5]]) as usize;
+    let height = u16::from_le_bytes([data[6], data[7]]) as usize;
+    let palette_count = u16::from_le_bytes([data[8], data[9]]) as usize;
+    if !(1..=256).contains(&palette_count) {
+        return Err(invalid("invalid number of palette colors"));
+    }
+
+    let pixels_at = 10 + palette_count * 3;
+    if data.len() != pixels_at + width * height {
+        return Err(invalid("unexpected screen dump size"));
+    }
+    let mut palette = [0; 768];
+    palette[..palette_count * 3].copy_from_slice(&data[10..pixels_at]);
+
+    Ok(Image::from_pixels(
+        width as u32,
+        height as u32,
+        palette,
+        palette_count as u32,
+        data[pixels_at..].to_vec(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 3x2 dump with a palette of 2 colors.
+    const DUMP: &[u8] = &[
+        b'D', b'L', b'S', b'D', // magic
+        3, 0, 2, 0, // width and height
+        2, 0, // palette colors
+        0, 0, 0, 63, 32, 1, // palette
+        0, 1, 1, // pixels
+        1, 0, 0,
+    ];
+
+    fn invalid_message(result: Result<Image, FileError>) -> String {
+        match result {
+            Err(FileError::InvalidData(message)) => message,
+            Err(e) => panic!("unexpected error {}", e),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn encodes_the_documented_layout() {
+        let dump = encode(3, 2, &[0, 0, 0, 63, 32, 1], &[0, 1, 1, 1, 0, 0]);
+        assert_eq!(dump, DUMP);
+    }
+
+    #[test]
+    fn round_trip() {
+        let image = load_screen_dump_from_memory(DUMP).unwrap();
+        assert_eq!((image.width(), image.height()), (3, 2));
+        assert_eq!(image.palette_count(), 2);
+        assert_eq!(image.palette(), [0, 0, 0, 63, 32, 1]);
+        assert_eq!(image.data(), [0, 1, 1, 1, 0, 0]);
+
+        let palette: Vec<u8> = (0..=255).flat_map(|c| [c, c / 2, 255 - c]).collect();
+        let pixels: Vec<u8> = (0..=255).rev().collect();
+        let dump = encode(16, 16, &palette, &pixels);
+        assert_eq!(dump.len(), 10 + 768 + 256);
+        let image = load_screen_dump_from_memory(&dump).unwrap();
+        assert_eq!(image.palette_count(), 256);
+        assert_eq!(image.palette(), palette);
+        assert_eq!(image.data(), pixels);
+        assert_eq!(encode(16, 16, image.palette(), image.data()), dump);
+    }
+
+    #[test]
+    fn rejects_truncated_dumps() {
+        for len in 0..DUMP.len() {
+            let message = invalid_message(load_screen_dump_from_memory(&DUMP[..len]));
+            if len < 10 {
+                assert_eq!(message, "not a screen dump");
+            } else {
+                assert_eq!(message, "unexpected screen dump size");
+            }
+        }
+        let mut longer = DUMP.to_vec();
+        longer.push(0);
+        assert_eq!(
+            invalid_message(load_screen_dump_from_memory(&longer)),
+            "unexpected screen dump size"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut dump = DUMP.to_vec();
+        dump[3] = b'X';
+        assert_eq!(
+            invalid_message(load_screen_dump_from_memory(&dump)),
+            "not a screen dump"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_palette_counts() {
+        for count in [0u16, 257, u16::MAX] {
+            let mut dump = DUMP.to_vec();
+            dump[8..10].copy_from_slice(&count.to_le_bytes());
+            assert_eq!(
+                invalid_message(load_screen_dump_from_memory(&dump)),
+                "invalid number of palette colors"
+            );
+        }
+    }
+}
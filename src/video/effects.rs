@@ -0,0 +1,346 @@
+//! Module for text effects of intro screens and title cards.
+//!
+//! Effects keep their own state across frames,
+//! are advanced with the time passed since the last frame,
+//! and are drawn on demand.
+//! They work in both video modes:
+//! in text mode the characters are written straight into the text cells,
+//! and in graphics mode they are drawn with [`out_text_xy`]
+//! in the current font.
+//!
+//! Text is converted to code page 437 once, when the effect is created
+//! (see [`encode_cp437`]),
+//! so updating and drawing an effect does not allocate.
+
+use std::time::Duration;
+
+use crate::video::{
+    encode_cp437, get_color, out_text_xy, screen_width, set_color, soft_font, text_style,
+    video_mode, Font, TextAttr,
+};
+
+/// Gets the size of a character cell of the current font in pixels.
+///
+/// Fonts installed from files are assumed to be 8x8.
+fn glyph_size() -> (i32, i32) {
+    let (font, ..) = text_style();
+    let (width, height) = soft_font::get(font)
+        .map(|f| (f.width, f.height))
+        .or_else(|| font.builtin_glyph_size())
+        .unwrap_or(Font::GLYPH_SIZE_8X8);
+    (width as i32, height as i32)
+}
+
+/// Gets the text mode screen buffer,
+/// with two bytes per cell:
+/// the character followed by the attributes.
+fn text_cells() -> &'static mut [u8] {
+    let mode = video_mode();
+    let len = mode.text_columns() as usize * mode.text_rows() as usize * 2;
+    // Safety: the length matches the buffer of the text mode,
+    // and the slice is only used within the drawing functions
+    unsafe {
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return &mut [];
+        }
+        std::slice::from_raw_parts_mut(buf, len)
+    }
+}
+
+/// Writes characters into the text cells of a row from the given column,
+/// skipping the ones outside of the screen.
+fn put_cells(x: i32, y: i32, text: &[u8], attrs: TextAttr) {
+    let mode = video_mode();
+    let (columns, rows) = (mode.text_columns() as i32, mode.text_rows() as i32);
+    if y < 0 || y >= rows {
+        return;
+    }
+    let attr = (attrs.background & 0x0F) << 4 | (attrs.foreground & 0x0F);
+    let row = &mut text_cells()[(y * columns) as usize * 2..][..columns as usize * 2];
+    for (i, &c) in text.iter().enumerate() {
+        let column = x + i as i32;
+        if column >= columns {
+            break;
+        }
+        if column >= 0 {
+            row[column as usize * 2] = c;
+            row[column as usize * 2 + 1] = attr;
+        }
+    }
+}
+
+/// Draws text in graphics mode in the color of the attributes,
+/// leaving the current color as it was.
+fn draw_graphics_text(x: i32, y: i32, text: &[u8], attrs: TextAttr) {
+    let color = get_color();
+    set_color(attrs.foreground);
+    out_text_xy(x, y, text);
+    set_color(color);
+}
+
+/// Text revealed one character at a time,
+/// as if typed in.
+///
+/// Line breaks (`'\n'`) start a new line below the first one,
+/// at the same horizontal position.
+///
+/// # Example
+///
+/// Playing a sound for each character typed:
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use dos_like::{*, effects::Typewriter};
+/// set_video_mode(VideoMode::Text80x25_9x16);
+/// let click = load_wav("click.wav")?;
+/// let mut intro = Typewriter::new(10, 5, "IT WAS A DARK\nAND STORMY NIGHT...", 12.);
+/// while !shutting_down() && !intro.finished() {
+///     wait_vbl();
+///     intro.update(Duration::from_secs(1) / 70);
+///     if intro.just_revealed().iter().any(|&c| c != b' ' && c != b'\n') {
+///         play_sound(0, &click, false, 255);
+///     }
+///     intro.draw();
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Typewriter {
+    x: i32,
+    y: i32,
+    /// The text in code page 437
+    text: Vec<u8>,
+    chars_per_second: f32,
+    attrs: TextAttr,
+    elapsed: Duration,
+    /// The number of characters revealed
+    revealed: usize,
+    /// The number of characters revealed before the last update
+    previous: usize,
+}
+
+impl Typewriter {
+    /// Creates a typewriter effect with nothing revealed yet.
+    ///
+    /// In text mode, `x` and `y` are the column and row of the first character;
+    /// in graphics mode, they are its position in pixels
+    /// (subject to the draw offset).
+    pub fn new(x: i32, y: i32, text: impl AsRef<str>, chars_per_second: f32) -> Self {
+        Typewriter {
+            x,
+            y,
+            text: encode_cp437(text.as_ref()),
+            chars_per_second: chars_per_second.max(0.),
+            attrs: TextAttr::default(),
+            elapsed: Duration::ZERO,
+            revealed: 0,
+            previous: 0,
+        }
+    }
+
+    /// Sets the colors of the text.
+    ///
+    /// In graphics mode only the foreground color is used,
+    /// and the background is left as it is.
+    #[inline]
+    pub fn with_attrs(mut self, attrs: TextAttr) -> Self {
+        self.attrs = attrs;
+        self
+    }
+
+    /// Sets the colors of the text.
+    #[inline]
+    pub fn set_attrs(&mut self, attrs: TextAttr) {
+        self.attrs = attrs;
+    }
+
+    /// Reveals more characters according to the time passed.
+    pub fn update(&mut self, delta: Duration) {
+        self.previous = self.revealed;
+        if self.finished() {
+            return;
+        }
+        self.elapsed += delta;
+        let count = self.elapsed.as_secs_f64() * self.chars_per_second as f64;
+        self.revealed = (count as usize).min(self.text.len());
+    }
+
+    /// Reveals the whole text at once,
+    /// such as when the player presses a key to skip.
+    pub fn skip(&mut self) {
+        self.previous = self.revealed;
+        self.revealed = self.text.len();
+    }
+
+    /// Hides the whole text to type it in again.
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        self.revealed = 0;
+        self.previous = 0;
+    }
+
+    /// Gets the characters revealed so far, in code page 437.
+    #[inline]
+    pub fn revealed(&self) -> &[u8] {
+        &self.text[..self.revealed]
+    }
+
+    /// Gets the characters revealed during the last update, in code page 437,
+    /// such as to play a sound for each of them.
+    #[inline]
+    pub fn just_revealed(&self) -> &[u8] {
+        &self.text[self.previous..self.revealed]
+    }
+
+    /// Checks whether the whole text is revealed.
+    #[inline]
+    pub fn finished(&self) -> bool {
+        self.revealed == self.text.len()
+    }
+
+    /// Draws the characters revealed so far.
+    pub fn draw(&self) {
+        let graphics = video_mode().is_graphics();
+        let line_height = if graphics { glyph_size().1 } else { 1 };
+        for (i, line) in self.revealed().split(|&c| c == b'\n').enumerate() {
+            let y = self.y + i as i32 * line_height;
+            if graphics {
+                draw_graphics_text(self.x, y, line, self.attrs);
+            } else {
+                put_cells(self.x, y, line, self.attrs);
+            }
+        }
+    }
+}
+
+/// A line of text scrolling from right to left across the whole screen,
+/// wrapping around seamlessly.
+///
+/// The text is repeated as many times as it takes to fill the line,
+/// so it should end with some spaces
+/// to separate the end from the start.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use dos_like::{*, effects::Marquee};
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut marquee = Marquee::new(23, "GREETINGS TO ALL THE CODERS OUT THERE!    ", 10.);
+/// while !shutting_down() {
+///     wait_vbl();
+///     clear_screen();
+///     marquee.update(Duration::from_secs(1) / 70);
+///     marquee.draw();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Marquee {
+    row: i32,
+    /// The text in code page 437
+    text: Vec<u8>,
+    cells_per_second: f32,
+    attrs: TextAttr,
+    /// The number of cells scrolled in the current lap
+    position: f64,
+    laps: u32,
+}
+
+impl Marquee {
+    /// Creates a marquee with the start of the text
+    /// at the left edge of the screen.
+    ///
+    /// In text mode, `row` is a row of text cells;
+    /// in graphics mode, it is a row of character cells of the current font,
+    /// and the text scrolls pixel by pixel.
+    pub fn new(row: i32, text: impl AsRef<str>, cells_per_second: f32) -> Self {
+        Marquee {
+            row,
+            text: encode_cp437(text.as_ref()),
+            cells_per_second,
+            attrs: TextAttr::default(),
+            position: 0.,
+            laps: 0,
+        }
+    }
+
+    /// Sets the colors of the text.
+    ///
+    /// In graphics mode only the foreground color is used,
+    /// and the background is left as it is.
+    #[inline]
+    pub fn with_attrs(mut self, attrs: TextAttr) -> Self {
+        self.attrs = attrs;
+        self
+    }
+
+    /// Sets the colors of the text.
+    #[inline]
+    pub fn set_attrs(&mut self, attrs: TextAttr) {
+        self.attrs = attrs;
+    }
+
+    /// Scrolls the text according to the time passed.
+    ///
+    /// A negative speed scrolls from left to right.
+    pub fn update(&mut self, delta: Duration) {
+        let len = self.text.len() as f64;
+        if len == 0. {
+            return;
+        }
+        self.position += delta.as_secs_f64() * self.cells_per_second as f64;
+        while self.position >= len {
+            self.position -= len;
+            self.laps = self.laps.wrapping_add(1);
+        }
+        if self.position < 0. {
+            self.position = self.position.rem_euclid(len);
+        }
+    }
+
+    /// Gets the number of times the whole text went by,
+    /// such as to move on to the next screen after the first one.
+    #[inline]
+    pub fn laps(&self) -> u32 {
+        self.laps
+    }
+
+    /// Draws the visible part of the text.
+    pub fn draw(&self) {
+        if self.text.is_empty() {
+            return;
+        }
+        let first = self.position as usize;
+        if video_mode().is_graphics() {
+            let (glyph_w, glyph_h) = glyph_size();
+            let shift = (self.position.fract() * glyph_w as f64) as i32;
+            let cells = screen_width() as i32 / glyph_w + 2;
+            self.draw_runs(first, cells, |x, run| {
+                let x = x * glyph_w - shift;
+                draw_graphics_text(x, self.row * glyph_h, run, self.attrs);
+            });
+        } else {
+            let cells = video_mode().text_columns() as i32;
+            self.draw_runs(first, cells, |x, run| {
+                put_cells(x, self.row, run, self.attrs)
+            });
+        }
+    }
+
+    /// Calls `draw` with each run of consecutive characters
+    /// of the repeated text from the given character,
+    /// and the cell where it starts,
+    /// until the given number of cells is covered.
+    fn draw_runs(&self, first: usize, cells: i32, mut draw: impl FnMut(i32, &[u8])) {
+        let mut start = first % self.text.len();
+        let mut x = 0;
+        while x < cells {
+            let run = &self.text[start..];
+            let run = &run[..run.len().min((cells - x) as usize)];
+            draw(x, run);
+            x += run.len() as i32;
+            start = 0;
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Registry of the fonts known to this crate,
+//! so that they can be listed and looked up by name,
+//! such as in a settings menu.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crate::video::Font;
+
+/// Information about a font known to this crate,
+/// see [`installed_fonts`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FontInfo {
+    font: Font,
+    label: String,
+    path: Option<String>,
+    /// The path to the font file in canonical form,
+    /// to recognize the same file installed twice
+    key: Option<PathBuf>,
+    glyph_width: Option<u8>,
+    glyph_height: Option<u8>,
+}
+
+impl FontInfo {
+    /// Gets the font identifier.
+    #[inline]
+    pub fn font(&self) -> Font {
+        self.font
+    }
+
+    /// Gets the name of the font.
+    ///
+    /// This is `"Default 8x8"`, `"Default 8x16"` or `"Default 9x16"`
+    /// for the built-in fonts,
+    /// the file name without its extension for fonts installed from files,
+    /// and the label given for fonts created from glyph bitmaps.
+    #[inline]
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Gets the path of the file the font was installed from,
+    /// as given to [`install_user_font`](super::install_user_font),
+    /// or `None` if it did not come from a file.
+    #[inline]
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// Gets the width of each glyph in pixels,
+    /// or `None` if it is not known.
+    ///
+    /// Fonts installed from files may have glyphs of varying widths,
+    /// so their width is never known.
+    #[inline]
+    pub fn glyph_width(&self) -> Option<u8> {
+        self.glyph_width
+    }
+
+    /// Gets the height of each glyph in pixels,
+    /// or `None` if it is not known.
+    ///
+    /// For fonts installed from files,
+    /// this is the height declared in the header of the file.
+    #[inline]
+    pub fn glyph_height(&self) -> Option<u8> {
+        self.glyph_height
+    }
+}
+
+/// The fonts installed or created through this crate,
+/// in order.
+static FONTS: Mutex<Vec<FontInfo>> = Mutex::new(Vec::new());
+
+/// Locks the registry,
+/// such as to check for a font file and install it at once.
+pub(super) fn lock() -> MutexGuard<'static, Vec<FontInfo>> {
+    FONTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Describes one of the built-in fonts.
+fn builtin(font: Font, label: &str) -> FontInfo {
+    let (width, height) = font
+        .builtin_glyph_size()
+        .expect("font should be a built-in font");
+    FontInfo {
+        font,
+        label: label.to_string(),
+        path: None,
+        key: None,
+        glyph_width: Some(width),
+        glyph_height: Some(height),
+    }
+}
+
+/// Gets the canonical form of a font file path,
+/// falling back to the path as given.
+fn canonical_path(path: &str) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+/// Finds a font already installed from the given file.
+pub(super) fn find_file(fonts: &[FontInfo], path: &str) -> Option<Font> {
+    let key = canonical_path(path);
+    fonts
+        .iter()
+        .find(|info| info.key.as_ref() == Some(&key))
+        .map(|info| info.font)
+}
+
+/// Describes a font installed from a file.
+pub(super) fn file_font(font: Font, path: &str) -> FontInfo {
+    let label = std::path::Path::new(path)
+        .file_stem()
+        .map_or_else(|| path.to_string(), |s| s.to_string_lossy().into_owned());
+    // the header starts with the size of the whole file,
+    // followed by the glyph height
+    let glyph_height = std::fs::read(path).ok().and_then(|data| {
+        let size = u32::from_le_bytes(data.get(..4)?.try_into().ok()?);
+        (size as usize == data.len()).then_some(*data.get(4)?)
+    });
+    FontInfo {
+        font,
+        label,
+        path: Some(path.to_string()),
+        key: Some(canonical_path(path)),
+        glyph_width: None,
+        glyph_height,
+    }
+}
+
+/// Describes a font created from glyph bitmaps.
+pub(super) fn glyph_font(font: Font, label: String, width: u8, height: u8) -> FontInfo {
+    FontInfo {
+        font,
+        label,
+        path: None,
+        key: None,
+        glyph_width: Some(width),
+        glyph_height: Some(height),
+    }
+}
+
+/// Checks whether the font was installed or created through this crate.
+pub(super) fn contains(font: Font) -> bool {
+    lock().iter().any(|info| info.font == font)
+}
+
+/// Lists the fonts available for text:
+/// the built-in fonts first,
+/// followed by the fonts installed via
+/// [`install_user_font`](super::install_user_font)
+/// or created via [`Font::from_glyphs`] and [`Font::from_glyphs_labeled`],
+/// in the order they were added.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// install_user_font("fonts/volter.fnt")?;
+/// for (i, info) in installed_fonts().iter().enumerate() {
+///     let height = info.glyph_height().map_or("?".to_string(), |h| h.to_string());
+///     println!("{}. {} ({} pixels tall)", i + 1, info.label(), height);
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn installed_fonts() -> Vec<FontInfo> {
+    let mut fonts = vec![
+        builtin(Font::DEFAULT_8X8, "Default 8x8"),
+        builtin(Font::DEFAULT_8X16, "Default 8x16"),
+        builtin(Font::DEFAULT_9X16, "Default 9x16"),
+    ];
+    fonts.extend(lock().iter().cloned());
+    fonts
+}
+
+/// Counts the fonts in [`installed_fonts`]
+/// without waiting for the registry,
+/// such as from a panic hook,
+/// or returns `None` if it is locked.
+pub(crate) fn installed_font_count() -> Option<usize> {
+    let user_fonts = match FONTS.try_lock() {
+        Ok(fonts) => fonts.len(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().len(),
+        Err(TryLockError::WouldBlock) => return None,
+    };
+    Some(3 + user_fonts)
+}
+
+/// Finds a font by its label (see [`FontInfo::label`])
+/// or by the path it was installed from.
+///
+/// If more than one font matches,
+/// the first one in [`installed_fonts`] is returned.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// install_user_font("fonts/volter.fnt")?;
+/// // later, such as when loading the settings
+/// let font = find_font("volter").unwrap_or(Font::DEFAULT_8X8);
+/// set_text_style(font, false, false, false);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn find_font(label: &str) -> Option<Font> {
+    installed_fonts()
+        .into_iter()
+        .find(|info| info.label == label || info.path.as_deref() == Some(label))
+        .map(|info| info.font)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_fonts_come_first() {
+        let fonts = installed_fonts();
+        let builtin: Vec<_> = fonts[..3].iter().map(|info| info.font()).collect();
+        assert_eq!(
+            builtin,
+            [Font::DEFAULT_8X8, Font::DEFAULT_8X16, Font::DEFAULT_9X16]
+        );
+        assert_eq!(fonts[2].label(), "Default 9x16");
+        assert_eq!(fonts[2].glyph_width(), Some(9));
+        assert_eq!(fonts[2].glyph_height(), Some(16));
+        assert_eq!(fonts[2].path(), None);
+        assert_eq!(find_font("Default 8x16"), Some(Font::DEFAULT_8X16));
+        assert_eq!(find_font("no such font"), None);
+    }
+
+    #[test]
+    fn glyph_fonts_are_found_by_label() {
+        let font = Font::from_glyphs_labeled("registry test", 4, 6, &[0xF0; 256 * 6]).unwrap();
+        let info = installed_fonts()
+            .into_iter()
+            .find(|info| info.font() == font)
+            .unwrap();
+        assert_eq!(info.label(), "registry test");
+        assert_eq!(
+            (info.glyph_width(), info.glyph_height()),
+            (Some(4), Some(6))
+        );
+        assert_eq!(find_font("registry test"), Some(font));
+        assert!(contains(font));
+    }
+
+    #[test]
+    fn file_fonts_take_the_height_from_the_header() {
+        let dir = std::env::temp_dir().join("dos-like-font-registry-test");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tiny.fnt");
+        let mut data = vec![0; 16];
+        data[..4].copy_from_slice(&16_u32.to_le_bytes());
+        data[4] = 12;
+        std::fs::write(&path, &data).unwrap();
+        let path = path.to_str().unwrap();
+
+        let info = file_font(Font::DEFAULT_8X8, path);
+        assert_eq!(info.label(), "tiny");
+        assert_eq!(info.path(), Some(path));
+        assert_eq!(info.glyph_width(), None);
+        assert_eq!(info.glyph_height(), Some(12));
+
+        // the same file through another path is recognized
+        let other = dir.join(".").join("tiny.fnt");
+        assert_eq!(
+            find_file(&[info], other.to_str().unwrap()),
+            Some(Font::DEFAULT_8X8)
+        );
+
+        // a header with the wrong size gives no height
+        data[..4].copy_from_slice(&20_u32.to_le_bytes());
+        std::fs::write(path, &data).unwrap();
+        assert_eq!(file_font(Font::DEFAULT_8X8, path).glyph_height(), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1162 @@
+//! Saving of images and screenshots as GIF files,
+//! loading of GIF files from memory and of animated GIF files,
+//! and reading of what the engine leaves out when loading them.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::Duration;
+
+use crate::video::{dump::displayed_pixels, palette::palette_bytes, video_mode, Image};
+use crate::{launch_options, FileError};
+
+/// The maximum number of codes in the LZW dictionary.
+const MAX_CODES: u16 = 4096;
+
+impl Image {
+    /// Saves the image to a GIF file.
+    ///
+    /// The palette is written as is,
+    /// padded with black up to the next power of two,
+    /// so an image loaded with [`load_gif`](super::load_gif)
+    /// and saved again
+    /// loads back with the same pixels and palette.
+    /// The color key, if any, is saved as the transparent color.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use dos_like::*;
+    /// let mut image = load_gif("sprite.gif")?;
+    /// // flip the image upside down
+    /// let width = image.width() as usize;
+    /// let rows: Vec<_> = image.data().chunks(width).rev().flatten().copied().collect();
+    /// image.data_mut().copy_from_slice(&rows);
+    /// image.save_gif("flipped.gif")?;
+    /// # Ok::<(), FileError>(())
+    /// ```
+    pub fn save_gif(&self, path: impl AsRef<str>) -> Result<(), FileError> {
+        let data = encode_gif(
+            self.width(),
+            self.height(),
+            self.palette(),
+            self.data(),
+            self.color_key(),
+        )?;
+        std::fs::write(path.as_ref(), data).map_err(|_| FileError::WriteFailed)
+    }
+}
+
+/// Saves the contents of the screen as currently displayed,
+/// along with the full palette,
+/// to a GIF file.
+///
+/// As with [`save_screen_dump`](super::save_screen_dump),
+/// with double buffering this is the buffer presented by the last swap,
+/// not the one being drawn to.
+///
+/// A relative path is taken from the screenshot directory, if any
+/// (see [`set_screenshot_dir`]),
+/// which is created if it does not exist.
+///
+/// Fails with [`FileError::InvalidData`] if not in graphics mode.
+///
+/// # Example
+///
+/// Taking a screenshot when F12 is pressed:
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut count = 0;
+/// while !shutting_down() {
+///     wait_vbl();
+///     // draw the game
+///     for key in read_keys() {
+///         if key.is_pressed() && key.key_code() == KeyCode::KEY_F12 {
+///             count += 1;
+///             if let Err(e) = screenshot_gif(format!("shot{:03}.gif", count)) {
+///                 eprintln!("Could not save screenshot: {}", e);
+///             }
+///         }
+///     }
+/// }
+/// ```
+pub fn screenshot_gif(path: impl AsRef<str>) -> Result<(), FileError> {
+    if !video_mode().is_graphics() {
+        return Err(FileError::InvalidData(
+            "the screen is not in graphics mode".to_string(),
+        ));
+    }
+    let (width, height, pixels) = displayed_pixels();
+    let data = encode_gif(width as u32, height as u32, &palette_bytes(), &pixels, None)?;
+    let path = Path::new(path.as_ref());
+    let path = match screenshot_dir() {
+        Some(dir) if path.is_relative() => {
+            std::fs::create_dir_all(&dir).map_err(|_| FileError::WriteFailed)?;
+            dir.join(path)
+        }
+        _ => path.to_path_buf(),
+    };
+    std::fs::write(path, data).map_err(|_| FileError::WriteFailed)
+}
+
+/// The directory of screenshots with relative paths.
+static SCREENSHOT_DIR: OnceLock<Mutex<Option<PathBuf>>> = OnceLock::new();
+
+fn lock_screenshot_dir() -> MutexGuard<'static, Option<PathBuf>> {
+    SCREENSHOT_DIR
+        .get_or_init(|| Mutex::new(launch_options().screenshot_dir().map(Path::to_path_buf)))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Sets the directory where [`screenshot_gif`] saves
+/// the screenshots given a relative path,
+/// or `None` to save them relative to the current directory.
+///
+/// It starts as the directory given on the command line
+/// with `--screenshot-dir`, if any
+/// (see [`launch_options`](crate::launch_options)).
+pub fn set_screenshot_dir(dir: Option<PathBuf>) {
+    *lock_screenshot_dir() = dir;
+}
+
+/// Gets the directory where [`screenshot_gif`] saves
+/// the screenshots given a relative path,
+/// if any (see [`set_screenshot_dir`]).
+pub fn screenshot_dir() -> Option<PathBuf> {
+    lock_screenshot_dir().clone()
+}
+
+/// Encodes an indexed image into the bytes of a GIF file.
+///
+/// The palette is in the 6-bit range of the VGA palette,
+/// as in images loaded by the engine,
+/// and is scaled to 8 bits in the file.
+fn encode_gif(
+    width: u32,
+    height: u32,
+    palette: &[u8],
+    pixels: &[u8],
+    color_key: Option<u8>,
+) -> Result<Vec<u8>, FileError> {
+    if width > u16::MAX as u32 || height > u16::MAX as u32 {
+        return Err(FileError::InvalidData(format!(
+            "image of {}x{} is too large for a GIF file",
+            width, height
+        )));
+    }
+
+    // the color table must cover the palette and every pixel value
+    let colors = pixels
+        .iter()
+        .map(|&c| c as usize + 1)
+        .max()
+        .unwrap_or(0)
+        .max(palette.len() / 3);
+    let bits = (colors.max(2) as u32).next_power_of_two().trailing_zeros();
+
+    let mut out = Vec::with_capacity(32 + 768 + pixels.len());
+    // transparency needs the extensions of the later version
+    out.extend_from_slice(if color_key.is_some() {
+        b"GIF89a"
+    } else {
+        b"GIF87a"
+    });
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    // global color table, 8 bits per primary color
+    out.push(0x80 | 0x70 | (bits - 1) as u8);
+    // background color and aspect ratio
+    out.extend_from_slice(&[0, 0]);
+    for i in 0..(3 << bits) {
+        let v = palette.get(i).map_or(0, |&v| v.min(63));
+        out.push(v << 2 | v >> 4);
+    }
+
+    if let Some(key) = color_key {
+        // graphic control extension with a transparent color
+        out.extend_from_slice(&[b'!', 0xF9, 4, 0x01, 0, 0, key, 0]);
+    }
+
+    // image descriptor
+    out.push(b',');
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out.extend_from_slice(&(width as u16).to_le_bytes());
+    out.extend_from_slice(&(height as u16).to_le_bytes());
+    out.push(0);
+
+    let min_code_size = bits.max(2) as u8;
+    out.push(min_code_size);
+    let data = lzw_compress(min_code_size, pixels);
+    for block in data.chunks(255) {
+        out.push(block.len() as u8);
+        out.extend_from_slice(block);
+    }
+    out.push(0);
+
+    out.push(b';');
+    Ok(out)
+}
+
+/// Writes codes of varying size, least significant bit first.
+#[derive(Debug, Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    acc: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.acc |= (code as u32) << self.bits;
+        self.bits += size as u32;
+        while self.bits >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+/// Compresses pixel data with the variable code size LZW of GIF files.
+fn lzw_compress(min_code_size: u8, pixels: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut dictionary: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next = end + 1;
+
+    writer.write(clear, code_size);
+    let Some((&first, rest)) = pixels.split_first() else {
+        writer.write(end, code_size);
+        return writer.finish();
+    };
+
+    let mut prefix = first as u16;
+    for &pixel in rest {
+        if let Some(&code) = dictionary.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, code_size);
+        if next < MAX_CODES {
+            dictionary.insert((prefix, pixel), next);
+            if next == 1 << code_size {
+                code_size += 1;
+            }
+            next += 1;
+        } else {
+            // the dictionary is full, start over
+            writer.write(clear, code_size);
+            dictionary.clear();
+            code_size = min_code_size + 1;
+            next = end + 1;
+        }
+        prefix = pixel as u16;
+    }
+    writer.write(prefix, code_size);
+    writer.write(end, code_size);
+    writer.finish()
+}
+
+/// Finds the transparent color declared for the first image
+/// of the given GIF file data, if any.
+pub(super) fn transparent_index(data: &[u8]) -> Option<u8> {
+    if data.len() < 13 || !data.starts_with(b"GIF") {
+        return None;
+    }
+    let flags = data[10];
+    let mut i = 13;
+    if flags & 0x80 != 0 {
+        i += 3 << ((flags & 0x07) + 1);
+    }
+    // only extensions may come before the first image
+    while *data.get(i)? == b'!' {
+        let label = *data.get(i + 1)?;
+        i += 2;
+        if label == 0xF9 && data.get(i) == Some(&4) {
+            let packed = *data.get(i + 1)?;
+            return (packed & 0x01 != 0).then_some(*data.get(i + 4)?);
+        }
+        // skip the sub-blocks of any other extension
+        loop {
+            let len = *data.get(i)? as usize;
+            i += 1 + len;
+            if len == 0 {
+                break;
+            }
+        }
+    }
+    None
+}
+
+/// Loads a GIF image from the bytes of a GIF file,
+/// such as one embedded in the program with [`include_bytes!`].
+///
+/// The image is decoded in Rust,
+/// following the same rules as [`load_gif`](super::load_gif):
+/// only the first image of the file is loaded,
+/// on a canvas of the size of the whole file
+/// (any part of the canvas which the image does not cover
+/// takes the background color),
+/// and the palette is the local color table of the image if it has one,
+/// or the global color table otherwise,
+/// scaled to the 6-bit range of the VGA palette.
+/// The transparent color, if any, becomes the [color key](Image::color_key).
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// static LOGO: &[u8] = include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/rotozoom.gif"));
+///
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let logo = load_gif_from_memory(LOGO)?;
+/// draw_image(0, 0, &logo);
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn load_gif_from_memory(data: &[u8]) -> Result<Image, FileError> {
+    let gif = parse_gif(data, false)?;
+    let first = &gif.images[0];
+    let mut pixels = vec![gif.background; gif.width * gif.height];
+    draw_gif_image(&mut pixels, gif.width, gif.height, first, None);
+    Ok(gif_to_image(
+        gif.width,
+        gif.height,
+        first.table,
+        pixels,
+        first.transparent,
+    ))
+}
+
+/// A frame of an animated GIF file,
+/// see [`load_gif_animation`].
+#[derive(Debug)]
+pub struct GifFrame {
+    image: Image,
+    delay: u16,
+}
+
+impl GifFrame {
+    /// Gets the image of the frame,
+    /// the size of the whole animation.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Gets the image of the frame mutably.
+    #[inline]
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+
+    /// Discards the delay of the frame,
+    /// keeping only the image.
+    #[inline]
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Gets how long the frame is shown, in hundredths of a second.
+    #[inline]
+    pub fn delay(&self) -> u16 {
+        self.delay
+    }
+
+    /// Gets how long the frame is shown.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.delay as u64 * 10)
+    }
+}
+
+/// All of the frames of an animated GIF file,
+/// see [`load_gif_animation`].
+#[derive(Debug)]
+pub struct GifAnimation {
+    frames: Vec<GifFrame>,
+    loop_count: Option<u16>,
+}
+
+impl GifAnimation {
+    /// Gets the frames of the animation, in order.
+    ///
+    /// There is always at least one frame.
+    #[inline]
+    pub fn frames(&self) -> &[GifFrame] {
+        &self.frames
+    }
+
+    /// Gets the frames of the animation mutably.
+    #[inline]
+    pub fn frames_mut(&mut self) -> &mut [GifFrame] {
+        &mut self.frames
+    }
+
+    /// Discards the loop count,
+    /// keeping only the frames.
+    #[inline]
+    pub fn into_frames(self) -> Vec<GifFrame> {
+        self.frames
+    }
+
+    /// Gets the loop count declared in the file:
+    /// `None` if the animation is to be played once,
+    /// `Some(0)` if it loops forever,
+    /// or `Some(n)` if it is repeated `n` more times after the first.
+    #[inline]
+    pub fn loop_count(&self) -> Option<u16> {
+        self.loop_count
+    }
+
+    /// Gets the time it takes to go through all frames once.
+    pub fn total_duration(&self) -> Duration {
+        self.frames.iter().map(GifFrame::duration).sum()
+    }
+
+    /// Gets the frame to show at the given time
+    /// since the animation started,
+    /// taking the loop count into account.
+    ///
+    /// Once the animation is over, this is the last frame.
+    /// Frames with a delay of zero are never returned,
+    /// unless all of them have no delay,
+    /// in which case this is always the first frame.
+    pub fn frame_at(&self, time: Duration) -> &GifFrame {
+        let total = self.frames.iter().map(|f| f.delay as u64).sum::<u64>();
+        if total == 0 {
+            return &self.frames[0];
+        }
+        let mut time = time.as_millis() as u64 / 10;
+        let plays = match self.loop_count {
+            Some(0) => None,
+            Some(n) => Some(n as u64 + 1),
+            None => Some(1),
+        };
+        if plays.is_some_and(|plays| time >= total * plays) {
+            return self.frames.last().unwrap();
+        }
+        time %= total;
+        for frame in &self.frames {
+            if time < frame.delay as u64 {
+                return frame;
+            }
+            time -= frame.delay as u64;
+        }
+        self.frames.last().unwrap()
+    }
+}
+
+/// Loads all of the frames of an animated GIF file,
+/// each with its delay,
+/// along with the loop count of the animation.
+///
+/// Each frame is composited over the previous ones
+/// as the file describes,
+/// so that it is a full image ready to be drawn.
+/// Parts of a frame restored to the background
+/// take the transparent color of the first frame,
+/// or the background color if it has none.
+/// Every frame takes the palette of its own image in the file
+/// (the local color table, or the global one),
+/// and the transparent color of the first frame as its
+/// [color key](Image::color_key).
+///
+/// The first frame is the same image as the one loaded by
+/// [`load_gif`](super::load_gif).
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// # use std::time::Instant;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let animation = load_gif_animation("intro.gif")?;
+/// let first = animation.frames()[0].image();
+/// let palette = first.raw_palette();
+/// for i in 0..first.palette_count() as usize {
+///     set_pal(i, palette[3 * i], palette[3 * i + 1], palette[3 * i + 2]);
+/// }
+///
+/// let start = Instant::now();
+/// while !shutting_down() {
+///     wait_vbl();
+///     draw_image(0, 0, animation.frame_at(start.elapsed()).image());
+/// }
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn load_gif_animation(path: impl AsRef<str>) -> Result<GifAnimation, FileError> {
+    let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+    load_gif_animation_from_memory(&data)
+}
+
+/// Loads all of the frames of an animated GIF file
+/// from the bytes of the file.
+///
+/// See [`load_gif_animation`] for how the frames are composited.
+pub fn load_gif_animation_from_memory(data: &[u8]) -> Result<GifAnimation, FileError> {
+    let gif = parse_gif(data, true)?;
+    let (width, height) = (gif.width, gif.height);
+    let color_key = gif.images[0].transparent;
+    let mut canvas = vec![gif.background; width * height];
+    let mut frames = Vec::with_capacity(gif.images.len());
+    for (n, image) in gif.images.iter().enumerate() {
+        let previous = (image.disposal == 3).then(|| canvas.clone());
+        // the first frame is drawn as is, like with `load_gif`
+        let transparent = if n == 0 { None } else { image.transparent };
+        draw_gif_image(&mut canvas, width, height, image, transparent);
+        frames.push(GifFrame {
+            image: gif_to_image(width, height, image.table, canvas.clone(), color_key),
+            delay: image.delay,
+        });
+
+        match (image.disposal, previous) {
+            // restore to background
+            (2, _) => {
+                let fill = color_key.unwrap_or(gif.background);
+                for_each_gif_row(&mut canvas, width, height, image, |row, _| row.fill(fill));
+            }
+            // restore to previous
+            (3, Some(previous)) => canvas = previous,
+            // leave in place
+            _ => {}
+        }
+    }
+    Ok(GifAnimation {
+        frames,
+        loop_count: gif.loop_count,
+    })
+}
+
+/// An image stored in a GIF file, before it is composited.
+#[derive(Debug)]
+struct GifImage<'a> {
+    left: usize,
+    top: usize,
+    width: usize,
+    /// The local color table, or the global one
+    table: &'a [u8],
+    /// The color indices, row by row from the top
+    indices: Vec<u8>,
+    /// The delay in hundredths of a second
+    delay: u16,
+    /// What to do with the image after it is shown
+    disposal: u8,
+    transparent: Option<u8>,
+}
+
+/// The contents of a GIF file.
+#[derive(Debug)]
+struct GifData<'a> {
+    width: usize,
+    height: usize,
+    background: u8,
+    /// The loop count of the application extension, if any
+    loop_count: Option<u16>,
+    /// The images in the file, at least one
+    images: Vec<GifImage<'a>>,
+}
+
+fn invalid(message: &str) -> FileError {
+    FileError::InvalidData(message.to_string())
+}
+
+/// Reads a sequence of data sub-blocks from the given position,
+/// up to and including the block terminator,
+/// returning their contents concatenated.
+fn read_sub_blocks(data: &[u8], i: &mut usize) -> Result<Vec<u8>, FileError> {
+    let mut out = Vec::new();
+    loop {
+        let len = *data.get(*i).ok_or_else(|| invalid("truncated data"))? as usize;
+        *i += 1;
+        if len == 0 {
+            return Ok(out);
+        }
+        out.extend_from_slice(
+            data.get(*i..*i + len)
+                .ok_or_else(|| invalid("truncated data"))?,
+        );
+        *i += len;
+    }
+}
+
+/// Reads the images of a GIF file,
+/// stopping after the first one unless `all` is set.
+fn parse_gif(data: &[u8], all: bool) -> Result<GifData<'_>, FileError> {
+    if data.len() < 13 || !(data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")) {
+        return Err(invalid("not a GIF file"));
+    }
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let flags = data[10];
+    let mut gif = GifData {
+        width: read_u16(6) as usize,
+        height: read_u16(8) as usize,
+        background: data[11],
+        loop_count: None,
+        images: Vec::new(),
+    };
+    let mut i = 13;
+    let mut global = None;
+    if flags & 0x80 != 0 {
+        let len = 3 << ((flags & 0x07) + 1);
+        global = Some(
+            data.get(i..i + len)
+                .ok_or_else(|| invalid("truncated palette"))?,
+        );
+        i += len;
+    }
+
+    // the graphic control extension applies to the next image only
+    let mut control = (0, 0, None);
+    loop {
+        match data.get(i) {
+            Some(b'!') => {
+                let label = *data.get(i + 1).ok_or_else(|| invalid("truncated data"))?;
+                i += 2;
+                let block = read_sub_blocks(data, &mut i)?;
+                match label {
+                    0xF9 if block.len() >= 4 => {
+                        let delay = u16::from_le_bytes([block[1], block[2]]);
+                        let disposal = (block[0] >> 2) & 0x07;
+                        let transparent = (block[0] & 0x01 != 0).then_some(block[3]);
+                        control = (delay, disposal, transparent);
+                    }
+                    0xFF if block.len() >= 14
+                        && (block.starts_with(b"NETSCAPE2.0")
+                            || block.starts_with(b"ANIMEXTS1.0"))
+                        && block[11] == 1 =>
+                    {
+                        gif.loop_count = Some(u16::from_le_bytes([block[12], block[13]]));
+                    }
+                    _ => {}
+                }
+            }
+            Some(b',') => {
+                let descriptor = data
+                    .get(i + 1..i + 10)
+                    .ok_or_else(|| invalid("truncated image descriptor"))?;
+                i += 10;
+                let read_u16 =
+                    |at: usize| u16::from_le_bytes([descriptor[at], descriptor[at + 1]]) as usize;
+                let (width, height) = (read_u16(4), read_u16(6));
+                let image_flags = descriptor[8];
+                let mut table = global;
+                if image_flags & 0x80 != 0 {
+                    let len = 3 << ((image_flags & 0x07) + 1);
+                    table = Some(
+                        data.get(i..i + len)
+                            .ok_or_else(|| invalid("truncated palette"))?,
+                    );
+                    i += len;
+                }
+                let table = table.ok_or_else(|| invalid("no palette in file"))?;
+
+                let min_code_size = *data.get(i).ok_or_else(|| invalid("truncated image data"))?;
+                if !(1..=11).contains(&min_code_size) {
+                    return Err(invalid("invalid code size"));
+                }
+                i += 1;
+                let compressed = read_sub_blocks(data, &mut i)?;
+                let mut indices = lzw_decompress(min_code_size, &compressed, width * height);
+                indices.resize(width * height, 0);
+                if image_flags & 0x40 != 0 && width > 0 {
+                    indices = deinterlace(&indices, width, height);
+                }
+
+                let (delay, disposal, transparent) = std::mem::take(&mut control);
+                gif.images.push(GifImage {
+                    left: read_u16(0),
+                    top: read_u16(2),
+                    width,
+                    table,
+                    indices,
+                    delay,
+                    disposal,
+                    transparent,
+                });
+                if !all {
+                    break;
+                }
+            }
+            // the trailer, or anything else, ends the file
+            _ => break,
+        }
+    }
+    if gif.images.is_empty() {
+        return Err(invalid("no image in file"));
+    }
+    Ok(gif)
+}
+
+/// Puts the rows of an interlaced image in order from the top,
+/// as they are stored in four passes.
+fn deinterlace(indices: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = vec![0; indices.len()];
+    let rows = [(0, 8), (4, 8), (2, 4), (1, 2)]
+        .into_iter()
+        .flat_map(|(start, step)| (start..height).step_by(step));
+    for (y, src) in rows.zip(indices.chunks_exact(width)) {
+        out[y * width..][..width].copy_from_slice(src);
+    }
+    out
+}
+
+/// Calls `f` with each row of the canvas covered by an image of a GIF file
+/// and the matching row of the image,
+/// leaving out the parts outside of the canvas.
+fn for_each_gif_row(
+    canvas: &mut [u8],
+    width: usize,
+    height: usize,
+    image: &GifImage,
+    mut f: impl FnMut(&mut [u8], &[u8]),
+) {
+    if image.left >= width || image.width == 0 {
+        return;
+    }
+    let len = image.width.min(width - image.left);
+    let rows = image.indices.chunks_exact(image.width);
+    for (y, src) in (image.top..height).zip(rows) {
+        f(&mut canvas[y * width + image.left..][..len], &src[..len]);
+    }
+}
+
+/// Draws an image of a GIF file onto the canvas,
+/// leaving out the pixels of the given transparent color, if any.
+fn draw_gif_image(
+    canvas: &mut [u8],
+    width: usize,
+    height: usize,
+    image: &GifImage,
+    transparent: Option<u8>,
+) {
+    for_each_gif_row(canvas, width, height, image, |dst, src| match transparent {
+        None => dst.copy_from_slice(src),
+        Some(key) => {
+            for (d, &s) in dst.iter_mut().zip(src) {
+                if s != key {
+                    *d = s;
+                }
+            }
+        }
+    });
+}
+
+/// Makes an image out of a canvas and a GIF color table.
+fn gif_to_image(
+    width: usize,
+    height: usize,
+    table: &[u8],
+    pixels: Vec<u8>,
+    color_key: Option<u8>,
+) -> Image {
+    let mut palette = [0; 768];
+    for (dst, &src) in palette.iter_mut().zip(table) {
+        *dst = src >> 2;
+    }
+    let mut image = Image::from_pixels(
+        width as u32,
+        height as u32,
+        palette,
+        (table.len() / 3) as u32,
+        pixels,
+    );
+    image.set_color_key(color_key);
+    image
+}
+
+/// Decompresses the variable code size LZW of GIF files,
+/// producing up to `len` pixels.
+///
+/// Decoding stops early at the end code or at an invalid code.
+fn lzw_decompress(min_code_size: u8, data: &[u8], len: usize) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = Vec::with_capacity(len);
+    // each code is the previous code and the last pixel of its string
+    let mut prefix = [0u16; MAX_CODES as usize];
+    let mut suffix = [0u8; MAX_CODES as usize];
+    let mut first = [0u8; MAX_CODES as usize];
+    for code in 0..clear {
+        suffix[code as usize] = code as u8;
+        first[code as usize] = code as u8;
+    }
+    let mut code_size = min_code_size + 1;
+    let mut next = end + 1;
+    let mut previous: Option<u16> = None;
+    let mut string = Vec::new();
+
+    let (mut acc, mut bits) = (0u32, 0u32);
+    let mut bytes = data.iter();
+    while out.len() < len {
+        while bits < code_size as u32 {
+            let Some(&byte) = bytes.next() else {
+                return out;
+            };
+            acc |= (byte as u32) << bits;
+            bits += 8;
+        }
+        let code = (acc & ((1 << code_size) - 1)) as u16;
+        acc >>= code_size;
+        bits -= code_size as u32;
+
+        if code == clear {
+            code_size = min_code_size + 1;
+            next = end + 1;
+            previous = None;
+            continue;
+        }
+        if code == end {
+            break;
+        }
+        let Some(prev) = previous else {
+            if code >= clear {
+                break;
+            }
+            out.push(code as u8);
+            previous = Some(code);
+            continue;
+        };
+
+        // the string of a code not yet in the dictionary
+        // is the previous string followed by its own first pixel
+        let known = code < next;
+        if !known && code != next {
+            break;
+        }
+        let head = if known {
+            first[code as usize]
+        } else {
+            first[prev as usize]
+        };
+        if next < MAX_CODES {
+            prefix[next as usize] = prev;
+            suffix[next as usize] = head;
+            first[next as usize] = first[prev as usize];
+            next += 1;
+            if next == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+        }
+
+        string.clear();
+        let mut c = code;
+        while c >= clear {
+            string.push(suffix[c as usize]);
+            c = prefix[c as usize];
+        }
+        string.push(c as u8);
+        out.extend(string.iter().rev());
+        previous = Some(code);
+    }
+    out.truncate(len);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_header_and_color_table() {
+        let palette = [63, 0, 0, 0, 32, 0, 0, 0, 1];
+        let data = encode_gif(3, 2, &palette, &[0, 1, 2, 2, 1, 0], None).unwrap();
+
+        assert_eq!(&data[..6], b"GIF87a");
+        assert_eq!(&data[6..10], &[3, 0, 2, 0]);
+        // global color table of 4 colors
+        assert_eq!(data[10], 0xF1);
+        assert_eq!(&data[11..13], &[0, 0]);
+        assert_eq!(
+            &data[13..25],
+            &[255, 0, 0, 0, 130, 0, 0, 0, 4, 0, 0, 0],
+            "the palette is scaled to 8 bits and padded with black"
+        );
+        // image descriptor of the whole canvas
+        assert_eq!(&data[25..35], &[b',', 0, 0, 0, 0, 3, 0, 2, 0, 0]);
+        assert_eq!(data[35], 2, "minimum code size");
+        assert_eq!(data.last(), Some(&b';'));
+    }
+
+    #[test]
+    fn gif_color_table_covers_pixels() {
+        // a pixel past the palette makes the table grow
+        let data = encode_gif(2, 1, &[0, 0, 0, 63, 63, 63], &[0, 5], None).unwrap();
+        assert_eq!(data[10] & 0x07, 2, "a table of 8 colors");
+        assert_eq!(data[13 + 8 * 3], b',');
+
+        // 256 colors at most
+        let palette = [0; 768];
+        let data = encode_gif(1, 1, &palette, &[255], None).unwrap();
+        assert_eq!(data[10] & 0x07, 7);
+        assert_eq!(data[13 + 256 * 3 + 10], 8, "minimum code size");
+    }
+
+    #[test]
+    fn gif_rejects_large_images() {
+        assert!(matches!(
+            encode_gif(70_000, 1, &[0, 0, 0], &vec![0; 70_000], None),
+            Err(FileError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn gif_color_key() {
+        let palette = [0, 0, 0, 63, 63, 63];
+        let data = encode_gif(2, 2, &palette, &[0, 1, 1, 0], Some(1)).unwrap();
+        assert_eq!(&data[..6], b"GIF89a");
+        // graphic control extension right after the color table
+        assert_eq!(&data[19..27], &[b'!', 0xF9, 4, 0x01, 0, 0, 1, 0]);
+        assert_eq!(data[27], b',');
+        assert_eq!(transparent_index(&data), Some(1));
+
+        let data = encode_gif(2, 2, &palette, &[0, 1, 1, 0], None).unwrap();
+        assert_eq!(transparent_index(&data), None);
+    }
+
+    #[test]
+    fn transparent_index_skips_other_extensions() {
+        let mut data = encode_gif(1, 1, &[0, 0, 0], &[0], Some(0)).unwrap();
+        // a comment extension before the graphic control extension
+        data.splice(19..19, [b'!', 0xFE, 3, b'h', b'i', b'!', 0]);
+        assert_eq!(transparent_index(&data), Some(0));
+
+        assert_eq!(transparent_index(b"GIF89a"), None);
+        assert_eq!(transparent_index(&data[..20]), None);
+    }
+
+    #[test]
+    fn lzw_codes() {
+        // clear, 0, then the new code for 0 0, 0, end, 3 bits each
+        assert_eq!(lzw_compress(2, &[0, 0, 0, 0]), [0x84, 0x51]);
+        // clear and end only
+        assert_eq!(lzw_compress(2, &[]), [0x2C]);
+    }
+
+    /// The path of the GIF file in the assets directory.
+    const ASSET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/rotozoom.gif");
+
+    #[test]
+    fn memory_decodes_encoded_gif() {
+        let (width, height) = (37u32, 11u32);
+        let pixels: Vec<u8> = (0..width * height).map(|i| (i * 7 % 23) as u8).collect();
+        let palette: Vec<u8> = (0..23 * 3).map(|i| (i % 64) as u8).collect();
+        for key in [None, Some(5)] {
+            let data = encode_gif(width, height, &palette, &pixels, key).unwrap();
+            let image = load_gif_from_memory(&data).unwrap();
+            assert_eq!((image.width(), image.height()), (width, height));
+            assert_eq!(image.data(), &pixels[..]);
+            assert_eq!(&image.palette()[..palette.len()], &palette[..]);
+            assert_eq!(image.color_key(), key);
+        }
+    }
+
+    #[test]
+    fn memory_matches_file_path() {
+        let data = std::fs::read(ASSET).unwrap();
+        let from_memory = load_gif_from_memory(&data).unwrap();
+        let from_file = load_gif_animation(ASSET).unwrap().into_frames().remove(0);
+        let from_file = from_file.image();
+        assert_eq!(from_memory.width(), from_file.width());
+        assert_eq!(from_memory.height(), from_file.height());
+        assert_eq!(from_memory.data(), from_file.data());
+        assert_eq!(from_memory.palette(), from_file.palette());
+        assert_eq!(from_memory.color_key(), from_file.color_key());
+        assert_eq!(from_memory.color_key(), transparent_index(&data));
+    }
+
+    /// An animation of one-pixel frames with the given delays,
+    /// each frame holding its own index as its pixel.
+    fn animation(delays: &[u16], loop_count: Option<u16>) -> GifAnimation {
+        let frames = delays
+            .iter()
+            .enumerate()
+            .map(|(i, &delay)| GifFrame {
+                image: Image::from_data(1, 1, vec![i as u8], &[0, 0, 0]).unwrap(),
+                delay,
+            })
+            .collect();
+        GifAnimation { frames, loop_count }
+    }
+
+    fn frame_index(animation: &GifAnimation, ms: u64) -> u8 {
+        animation.frame_at(Duration::from_millis(ms)).image().data()[0]
+    }
+
+    #[test]
+    fn frame_at_follows_delays_and_loops() {
+        let once = animation(&[10, 0, 20], None);
+        assert_eq!(once.total_duration(), Duration::from_millis(300));
+        assert_eq!(frame_index(&once, 0), 0);
+        assert_eq!(frame_index(&once, 99), 0);
+        // the frame without delay is skipped
+        assert_eq!(frame_index(&once, 100), 2);
+        assert_eq!(frame_index(&once, 299), 2);
+        assert_eq!(frame_index(&once, 10_000), 2);
+
+        let forever = animation(&[10, 0, 20], Some(0));
+        assert_eq!(frame_index(&forever, 300), 0);
+        assert_eq!(frame_index(&forever, 3_150), 2);
+
+        let twice = animation(&[10, 20], Some(1));
+        assert_eq!(frame_index(&twice, 350), 0);
+        assert_eq!(frame_index(&twice, 600), 1);
+
+        let instant = animation(&[0, 0], Some(0));
+        assert_eq!(frame_index(&instant, 1_000), 0);
+    }
+
+    #[test]
+    fn animation_frames_cover_the_canvas() {
+        let data = std::fs::read(ASSET).unwrap();
+        let animation = load_gif_animation_from_memory(&data).unwrap();
+        assert!(!animation.frames().is_empty());
+        let (width, height) = {
+            let first = animation.frames()[0].image();
+            (first.width(), first.height())
+        };
+        for frame in animation.frames() {
+            let image = frame.image();
+            assert_eq!((image.width(), image.height()), (width, height));
+            assert_eq!(image.data().len(), width as usize * height as usize);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs the GIF loader of the engine"]
+    fn memory_matches_engine() {
+        let data = std::fs::read(ASSET).unwrap();
+        let from_memory = load_gif_from_memory(&data).unwrap();
+        let from_engine = crate::video::load_gif(ASSET).unwrap();
+        assert_eq!(from_memory.width(), from_engine.width());
+        assert_eq!(from_memory.height(), from_engine.height());
+        assert_eq!(from_memory.data(), from_engine.data());
+        assert_eq!(from_memory.palette(), from_engine.palette());
+        assert_eq!(from_memory.color_key(), from_engine.color_key());
+    }
+
+    #[test]
+    fn memory_rejects_invalid_data() {
+        let data = std::fs::read(ASSET).unwrap();
+        assert!(load_gif_from_memory(&[]).is_err());
+        assert!(load_gif_from_memory(b"GIF89a").is_err());
+        // a truncated file may decode in part, but never panics
+        for len in (0..data.len()).step_by(97) {
+            if let Ok(image) = load_gif_from_memory(&data[..len]) {
+                assert_eq!(
+                    image.data().len(),
+                    image.width() as usize * image.height() as usize
+                );
+            }
+        }
+        assert!(load_gif_from_memory(b"PNG\x89 definitely not a GIF").is_err());
+    }
+
+    /// A deterministic pseudo-random byte sequence, with values below `limit`.
+    fn noise(len: usize, limit: u16) -> Vec<u8> {
+        let mut state = 0x2545_F491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % limit as u32) as u8
+            })
+            .collect()
+    }
+
+    /// Saves an image to a GIF file and loads it back from memory.
+    fn round_trip(image: &Image) -> Image {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("image.gif");
+        image.save_gif(path.to_str().unwrap()).unwrap();
+        let data = std::fs::read(&path).unwrap();
+        load_gif_from_memory(&data).unwrap()
+    }
+
+    fn assert_same_image(image: &Image, loaded: &Image) {
+        assert_eq!(loaded.width(), image.width());
+        assert_eq!(loaded.height(), image.height());
+        assert_eq!(loaded.data(), image.data());
+        assert_eq!(loaded.color_key(), image.color_key());
+        // the palette is padded with black up to a power of two
+        let palette = image.palette();
+        assert!(loaded.palette_count().is_power_of_two());
+        assert!(loaded.palette_count() >= image.palette_count());
+        assert_eq!(&loaded.palette()[..palette.len()], palette);
+        assert!(loaded.palette()[palette.len()..].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn gif_round_trip() {
+        let palette: Vec<u8> = (0..=255u8)
+            .flat_map(|i| [i % 64, i / 4, 63 - i % 64])
+            .collect();
+        let image = Image::from_data(37, 23, noise(37 * 23, 256), &palette).unwrap();
+        assert_same_image(&image, &round_trip(&image));
+    }
+
+    #[test]
+    fn gif_round_trip_color_key() {
+        let palette = [0, 0, 0, 63, 0, 0, 0, 63, 0, 0, 0, 63];
+        let mut image = Image::from_data(16, 9, noise(16 * 9, 4), &palette).unwrap();
+        image.set_color_key(Some(2));
+        let loaded = round_trip(&image);
+        assert_eq!(loaded.color_key(), Some(2));
+        assert_same_image(&image, &loaded);
+    }
+
+    #[test]
+    fn gif_round_trip_uneven_palettes() {
+        for colors in [1, 2, 3, 5, 17, 100, 129] {
+            let palette: Vec<u8> = (0..colors * 3).map(|i| (i % 64) as u8).collect();
+            let image = Image::from_data(11, 7, noise(11 * 7, colors as u16), &palette).unwrap();
+            assert_same_image(&image, &round_trip(&image));
+        }
+    }
+
+    #[test]
+    fn gif_round_trip_single_color() {
+        // long runs fill the dictionary and reset it
+        let image = Image::from_data(320, 200, vec![1; 320 * 200], &[0, 0, 0, 63, 63, 63]).unwrap();
+        assert_same_image(&image, &round_trip(&image));
+
+        let image = Image::from_data(1, 1, vec![0], &[10, 20, 30]).unwrap();
+        assert_same_image(&image, &round_trip(&image));
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        for min_code_size in 2..=8 {
+            let limit = 1u16 << min_code_size;
+            for pixels in [
+                Vec::new(),
+                vec![0],
+                noise(10_000, limit),
+                vec![(limit - 1) as u8; 10_000],
+                (0..20_000)
+                    .map(|i| (i / 7 % limit as usize) as u8)
+                    .collect(),
+            ] {
+                let data = lzw_compress(min_code_size, &pixels);
+                assert_eq!(
+                    lzw_decompress(min_code_size, &data, pixels.len()),
+                    pixels,
+                    "min code size {}",
+                    min_code_size
+                );
+            }
+        }
+    }
+}
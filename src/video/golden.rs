@@ -0,0 +1,579 @@
+//! Golden image tests of the drawing primitives.
+//!
+//! Each scene in [`SCENES`] is drawn into a pixel buffer
+//! and compared against its reference dump in `tests/golden`.
+//! Run the tests with the environment variable
+//! `DOS_LIKE_UPDATE_GOLDEN=1` to write the reference dumps anew
+//! instead of comparing against them,
+//! and review the changes before checking them in.
+//!
+//! The scenes in [`FONT_SCENES`] draw text in each built-in font,
+//! with the glyphs captured from the engine (see [`builtin_font_data`]).
+//! They switch to a graphics video mode,
+//! so they are checked by an ignored test,
+//! to run with `cargo test golden -- --ignored`.
+//!
+//! # Dump format
+//!
+//! A dump is a text file with the width and height on the first line,
+//! as in `32x24`,
+//! followed by one line per row of pixels,
+//! with one character per pixel:
+//! `.` for color 0,
+//! then `1` to `9` and `A` to `Z` for colors 1 to 35.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use crate::geometry::Rect;
+use crate::video::raster::{self, Clip};
+use crate::video::{blit_rows, builtin_font_data, copy_opaque, set_video_mode, Font, VideoMode};
+
+/// The characters for each color in a dump.
+const ALPHABET: &[u8; 36] = b".123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The environment variable to write the reference dumps
+/// instead of checking them.
+const UPDATE_VAR: &str = "DOS_LIKE_UPDATE_GOLDEN";
+
+/// The maximum number of mismatched pixels listed in a failure.
+const MAX_LISTED: usize = 8;
+
+/// A pixel buffer which scenes are drawn to.
+pub(crate) struct Canvas {
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: u16, height: u16) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize],
+        }
+    }
+
+    /// Gets a clip over the whole canvas with the given color.
+    pub(crate) fn clip(&mut self, color: u8) -> Clip<'_> {
+        Clip::pixels(&mut self.pixels, self.width, self.height, color)
+    }
+
+    /// Draws text with the glyphs of a built-in font,
+    /// one glyph cell after the other,
+    /// skipping the pixels outside of the canvas.
+    fn draw_text(&mut self, font: Font, x: i32, y: i32, text: &[u8], color: u8) {
+        let data = builtin_font_data(font).expect("the glyphs of a built-in font");
+        let (glyph_width, glyph_height) = font.builtin_glyph_size().unwrap();
+        let len = font.bytes_per_glyph().unwrap();
+        let row_bytes = len / glyph_height as usize;
+
+        for (i, &c) in text.iter().enumerate() {
+            let glyph = &data[c as usize * len..][..len];
+            let left = x + i as i32 * glyph_width as i32;
+            for gy in 0..glyph_height as usize {
+                for gx in 0..glyph_width as usize {
+                    if glyph[gy * row_bytes + gx / 8] & (0x80 >> (gx % 8)) == 0 {
+                        continue;
+                    }
+                    let (px, py) = (left + gx as i32, y + gy as i32);
+                    if (0..self.width as i32).contains(&px) && (0..self.height as i32).contains(&py)
+                    {
+                        self.pixels[py as usize * self.width as usize + px as usize] = color;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A named drawing, compared against the dump of the same name.
+pub(crate) struct Scene {
+    pub(crate) name: &'static str,
+    pub(crate) width: u16,
+    pub(crate) height: u16,
+    pub(crate) draw: fn(&mut Canvas),
+}
+
+/// All scenes checked by the golden image tests.
+pub(crate) static SCENES: &[Scene] = &[
+    Scene {
+        name: "line_octants",
+        width: 33,
+        height: 33,
+        draw: line_octants,
+    },
+    Scene {
+        name: "lines_clipped",
+        width: 24,
+        height: 16,
+        draw: lines_clipped,
+    },
+    Scene {
+        name: "lines_subpixel",
+        width: 24,
+        height: 16,
+        draw: lines_subpixel,
+    },
+    Scene {
+        name: "rectangles",
+        width: 24,
+        height: 16,
+        draw: rectangles,
+    },
+    Scene {
+        name: "circles_clipped",
+        width: 40,
+        height: 30,
+        draw: circles_clipped,
+    },
+    Scene {
+        name: "ellipses_clipped",
+        width: 40,
+        height: 30,
+        draw: ellipses_clipped,
+    },
+    Scene {
+        name: "polygon_edge_cases",
+        width: 40,
+        height: 30,
+        draw: polygon_edge_cases,
+    },
+    Scene {
+        name: "blits",
+        width: 24,
+        height: 16,
+        draw: blits,
+    },
+];
+
+/// Lines from the center to points all around it,
+/// covering all octants and the lines in between.
+fn line_octants(canvas: &mut Canvas) {
+    let ends = [
+        (30, 16),
+        (30, 9),
+        (30, 2),
+        (23, 2),
+        (16, 2),
+        (9, 2),
+        (2, 2),
+        (2, 9),
+        (2, 16),
+        (2, 23),
+        (2, 30),
+        (9, 30),
+        (16, 30),
+        (23, 30),
+        (30, 30),
+        (30, 23),
+    ];
+    for (i, (x, y)) in ends.into_iter().enumerate() {
+        raster::line(&canvas.clip(1 + i as u8), 16, 16, x, y);
+    }
+}
+
+/// Lines crossing the edges, with far away endpoints.
+fn lines_clipped(canvas: &mut Canvas) {
+    raster::line(&canvas.clip(1), -100, 2, 100, 2);
+    raster::line(&canvas.clip(2), -10, -10, 30, 30);
+    raster::line(&canvas.clip(3), 20, -1000, 21, 1000);
+    raster::line(&canvas.clip(4), -5, 20, 30, -3);
+    raster::line(&canvas.clip(5), 1_000_000, 8, -1_000_000, 9);
+    raster::draw_poly(&canvas.clip(6), &[3, 12, 3, 12]);
+    raster::draw_poly(&canvas.clip(7), &[-4, 14, 8, 14, 8, 20, 1]);
+}
+
+/// Lines, pixels and circles at subpixel positions.
+fn lines_subpixel(canvas: &mut Canvas) {
+    raster::line_f(&canvas.clip(1), 0.5, 0.5, 22.5, 3.25);
+    raster::line_f(&canvas.clip(2), 1.25, 14.75, 4.75, 2.5);
+    raster::line_f(&canvas.clip(3), 6.4, 6.4, 6.6, 6.6);
+    raster::line_f(&canvas.clip(4), 30.0, 15.0, -5.0, 8.5);
+    raster::put_pixel_f(&canvas.clip(5), 10.49, 10.5);
+    raster::put_pixel_f(&canvas.clip(5), -0.5, -0.5);
+    raster::circle_f(&canvas.clip(6), 16.5, 9.25, 5.5);
+    raster::circle_f(&canvas.clip(7), 22.0, 14.0, 4.0);
+}
+
+/// Filled and non-filled rectangles,
+/// including degenerate and clipped ones.
+fn rectangles(canvas: &mut Canvas) {
+    raster::rectangle(&canvas.clip(1), 1, 1, 6, 4);
+    raster::rectangle(&canvas.clip(2), 8, 1, 1, 1);
+    raster::rectangle(&canvas.clip(3), 10, 1, 5, 1);
+    raster::rectangle(&canvas.clip(4), 16, 1, 1, 5);
+    raster::rectangle(&canvas.clip(5), 18, 1, 0, 5);
+    raster::rectangle(&canvas.clip(6), -3, 7, 6, 12);
+    raster::rectangle(&canvas.clip(7), 20, -2, 10, 6);
+    raster::bar(&canvas.clip(8), 5, 7, 4, 3);
+    raster::bar(&canvas.clip(9), 10, 7, 0, 3);
+    raster::bar(&canvas.clip(10), 20, 12, 100, 100);
+    raster::bar(&canvas.clip(11), -100, 14, 110, 1);
+}
+
+/// Circles of small sizes, and circles crossing the edges.
+fn circles_clipped(canvas: &mut Canvas) {
+    raster::circle(&canvas.clip(1), 3, 3, 0);
+    raster::circle(&canvas.clip(2), 8, 3, 1);
+    raster::circle(&canvas.clip(3), 15, 4, 3);
+    raster::fill_circle(&canvas.clip(4), 25, 5, 4);
+    raster::circle(&canvas.clip(5), 0, 29, 8);
+    raster::fill_circle(&canvas.clip(6), 39, 29, 9);
+    raster::fill_circle(&canvas.clip(7), 20, 20, 0);
+    raster::circle(&canvas.clip(8), 20, 60, 38);
+    raster::circle(&canvas.clip(9), -30_000, 15, 30_020);
+    raster::fill_circle(&canvas.clip(10), 60, -10, 35);
+}
+
+/// Ellipses of small sizes, and ellipses crossing the edges.
+fn ellipses_clipped(canvas: &mut Canvas) {
+    raster::ellipse(&canvas.clip(1), 4, 3, 3, 0);
+    raster::ellipse(&canvas.clip(2), 10, 4, 0, 3);
+    raster::ellipse(&canvas.clip(3), 20, 5, 7, 3);
+    raster::fill_ellipse(&canvas.clip(4), 32, 5, 5, 4);
+    raster::fill_ellipse(&canvas.clip(5), 3, 14, 1, 1);
+    raster::ellipse(&canvas.clip(6), -2, 22, 10, 6);
+    raster::fill_ellipse(&canvas.clip(7), 38, 28, 12, 5);
+    raster::fill_ellipse(&canvas.clip(8), 20, 20, 0, 4);
+    raster::ellipse(&canvas.clip(9), 20, 40, 30, 15);
+}
+
+/// Convex, concave and self-intersecting polygons,
+/// degenerate ones, and ones crossing the edges.
+fn polygon_edge_cases(canvas: &mut Canvas) {
+    // triangle
+    raster::fill_poly(&canvas.clip(1), &[2, 1, 10, 1, 6, 8]);
+    // concave arrow
+    raster::fill_poly(&canvas.clip(2), &[12, 1, 20, 4, 12, 8, 15, 4]);
+    // bow tie, crossing itself
+    raster::fill_poly(&canvas.clip(3), &[22, 1, 30, 8, 30, 1, 22, 8]);
+    // no height, a single point, repeated points
+    raster::fill_poly(&canvas.clip(4), &[32, 2, 38, 2, 35, 2]);
+    raster::fill_poly(&canvas.clip(4), &[34, 5]);
+    raster::fill_poly(&canvas.clip(5), &[32, 6, 32, 6, 38, 6, 38, 9, 38, 9]);
+    // crossing the left and bottom edges, with a trailing coordinate
+    raster::fill_poly(&canvas.clip(6), &[-5, 12, 8, 14, 3, 40, 7]);
+    // star, with overlapping winding
+    raster::fill_poly(
+        &canvas.clip(7),
+        &[
+            20, 10, 23, 19, 31, 19, 24, 24, 27, 32, 20, 27, 13, 32, 16, 24, 9, 19, 17, 19,
+        ],
+    );
+    // far away vertices
+    raster::fill_poly(&canvas.clip(8), &[34, 12, 100_000, 20, 34, 28]);
+    // outline on top of a filled polygon
+    raster::draw_poly(&canvas.clip(9), &[2, 1, 10, 1, 6, 8, 2, 1]);
+}
+
+/// The scenes drawing text in the built-in fonts.
+pub(crate) static FONT_SCENES: &[Scene] = &[
+    Scene {
+        name: "text_8x8",
+        width: 12 * 8,
+        height: 2 * 8 + 4,
+        draw: text_8x8,
+    },
+    Scene {
+        name: "text_8x16",
+        width: 12 * 8,
+        height: 2 * 16 + 4,
+        draw: text_8x16,
+    },
+    Scene {
+        name: "text_9x16",
+        width: 12 * 9,
+        height: 2 * 16 + 4,
+        draw: text_9x16,
+    },
+];
+
+fn text_8x8(canvas: &mut Canvas) {
+    font_sample(canvas, Font::DEFAULT_8X8);
+}
+
+fn text_8x16(canvas: &mut Canvas) {
+    font_sample(canvas, Font::DEFAULT_8X16);
+}
+
+fn text_9x16(canvas: &mut Canvas) {
+    font_sample(canvas, Font::DEFAULT_9X16);
+}
+
+/// Two lines of text, the second one with box drawing
+/// and other code page 437 characters,
+/// and a third one cut by the bottom and right edges.
+fn font_sample(canvas: &mut Canvas, font: Font) {
+    let (glyph_width, glyph_height) = font.builtin_glyph_size().unwrap();
+    let (glyph_width, glyph_height) = (glyph_width as i32, glyph_height as i32);
+    canvas.draw_text(font, 0, 0, b"Hello, DOS!", 15);
+    canvas.draw_text(
+        font,
+        0,
+        glyph_height,
+        b"\xC9\xCD\xBB\x01\x03\xB0\xB1\xB2\xDB 0~",
+        14,
+    );
+    canvas.draw_text(font, 9 * glyph_width + 3, 2 * glyph_height, b"#|A", 9);
+}
+
+/// A 6 by 4 sprite with transparent pixels of color 0,
+/// including a whole transparent row and column.
+#[rustfmt::skip]
+const SPRITE: [u8; 24] = [
+    1, 2, 0, 3, 4, 0,
+    0, 5, 6, 0, 7, 0,
+    0, 0, 0, 0, 0, 0,
+    8, 9, 10, 11, 12, 0,
+];
+
+/// Blits a region of the sprite onto the canvas
+/// as [`blit`](crate::video::blit) does on the screen,
+/// or as [`mask_blit`](crate::video::mask_blit) with color 0 if `masked`.
+fn blit_sprite(canvas: &mut Canvas, x: i32, y: i32, src: (u16, u16, u16, u16), masked: bool) {
+    let bounds = Rect::new(0, 0, canvas.width, canvas.height);
+    let pixels = (&mut canvas.pixels[..], canvas.width as usize);
+    if masked {
+        blit_rows(pixels, bounds, (x, y), (&SPRITE, 6, 4), src, |src, dst| {
+            copy_opaque(src, dst, 0)
+        });
+    } else {
+        blit_rows(pixels, bounds, (x, y), (&SPRITE, 6, 4), src, |src, dst| {
+            dst.copy_from_slice(src)
+        });
+    }
+}
+
+/// Opaque and masked blits of a sprite,
+/// of parts of it, and crossing each edge.
+fn blits(canvas: &mut Canvas) {
+    // background, so that transparent pixels show
+    canvas.pixels[8 * 24..].fill(13);
+    let whole = (0, 0, 6, 4);
+    blit_sprite(canvas, 1, 1, whole, false);
+    blit_sprite(canvas, 8, 1, whole, true);
+    // a part of the sprite
+    blit_sprite(canvas, 15, 1, (1, 1, 3, 3), false);
+    // crossing the edges
+    blit_sprite(canvas, -3, 9, whole, true);
+    blit_sprite(canvas, 20, 11, whole, true);
+    blit_sprite(canvas, 10, 14, whole, false);
+    blit_sprite(canvas, 10, -3, whole, false);
+    // outside of the canvas or of the sprite
+    blit_sprite(canvas, -6, 9, whole, false);
+    blit_sprite(canvas, 24, 9, whole, false);
+    blit_sprite(canvas, 12, 9, (6, 0, 4, 4), true);
+    // a source region reaching past the sprite
+    blit_sprite(canvas, 14, 9, (3, 2, 10, 10), true);
+}
+
+/// Writes the pixels of a canvas as a dump.
+pub(crate) fn serialize(width: u16, height: u16, pixels: &[u8]) -> String {
+    let mut out = format!("{}x{}\n", width, height);
+    for row in pixels.chunks(width.max(1) as usize).take(height as usize) {
+        for &c in row {
+            let symbol = ALPHABET
+                .get(c as usize)
+                .unwrap_or_else(|| panic!("color {} cannot be written in a dump", c));
+            out.push(*symbol as char);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Reads a dump back into its width, height and pixels.
+pub(crate) fn deserialize(dump: &str) -> Result<(u16, u16, Vec<u8>), String> {
+    let mut lines = dump.lines();
+    let size = lines.next().ok_or("empty dump")?;
+    let (width, height) = size
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse::<u16>().ok()?, h.parse::<u16>().ok()?)))
+        .ok_or_else(|| format!("invalid size line {:?}", size))?;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize);
+    for (y, line) in lines.enumerate() {
+        if line.len() != width as usize {
+            return Err(format!("row {} has {} pixels", y, line.len()));
+        }
+        for c in line.bytes() {
+            let color = ALPHABET
+                .iter()
+                .position(|s| *s == c)
+                .ok_or_else(|| format!("invalid pixel {:?} in row {}", c as char, y))?;
+            pixels.push(color as u8);
+        }
+    }
+    if pixels.len() != width as usize * height as usize {
+        return Err(format!(
+            "expected {} rows, found {}",
+            height,
+            pixels.len() / width.max(1) as usize
+        ));
+    }
+    Ok((width, height, pixels))
+}
+
+/// Describes the differences between the expected and actual pixels,
+/// or returns `None` if there are none.
+pub(crate) fn diff(expected: (u16, u16, &[u8]), actual: (u16, u16, &[u8])) -> Option<String> {
+    if (expected.0, expected.1) != (actual.0, actual.1) {
+        return Some(format!(
+            "expected a size of {}x{}, got {}x{}",
+            expected.0, expected.1, actual.0, actual.1
+        ));
+    }
+    let width = actual.0.max(1) as usize;
+    let mismatches: Vec<_> = expected
+        .2
+        .iter()
+        .zip(actual.2)
+        .enumerate()
+        .filter(|(_, (e, a))| e != a)
+        .collect();
+    if mismatches.is_empty() {
+        return None;
+    }
+    let mut out = format!("{} pixels differ:", mismatches.len());
+    for (i, (e, a)) in mismatches.iter().take(MAX_LISTED) {
+        let _ = write!(
+            out,
+            "\n  at ({}, {}): expected {}, got {}",
+            i % width,
+            i / width,
+            e,
+            a
+        );
+    }
+    if mismatches.len() > MAX_LISTED {
+        let _ = write!(out, "\n  and {} more", mismatches.len() - MAX_LISTED);
+    }
+    Some(out)
+}
+
+/// The path of the reference dump of a scene.
+fn reference_path(name: &str) -> PathBuf {
+    [env!("CARGO_MANIFEST_DIR"), "tests", "golden"]
+        .iter()
+        .collect::<PathBuf>()
+        .join(format!("{}.txt", name))
+}
+
+/// Draws a scene and checks it against its reference dump,
+/// or writes the reference dump if requested.
+fn check(scene: &Scene) -> Result<(), String> {
+    let mut canvas = Canvas::new(scene.width, scene.height);
+    (scene.draw)(&mut canvas);
+    let actual = serialize(canvas.width, canvas.height, &canvas.pixels);
+    let path = reference_path(scene.name);
+
+    if std::env::var_os(UPDATE_VAR).is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        return std::fs::write(&path, actual).map_err(|e| e.to_string());
+    }
+
+    let reference = std::fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "could not read {} ({}), set {}=1 to create it",
+            path.display(),
+            e,
+            UPDATE_VAR
+        )
+    })?;
+    let (width, height, expected) = deserialize(&reference)?;
+    match diff(
+        (width, height, &expected),
+        (canvas.width, canvas.height, &canvas.pixels),
+    ) {
+        None => Ok(()),
+        Some(message) => Err(format!("{}\nactual:\n{}", message, actual)),
+    }
+}
+
+/// Checks all of the given scenes,
+/// failing with the differences of every mismatched scene.
+fn check_all(scenes: &[Scene]) {
+    let failures: Vec<String> = scenes
+        .iter()
+        .filter_map(|scene| {
+            check(scene)
+                .err()
+                .map(|e| format!("scene `{}`: {}", scene.name, e))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{}\n\nset {}=1 to update the reference dumps",
+        failures.join("\n\n"),
+        UPDATE_VAR
+    );
+}
+
+#[test]
+fn golden_scenes() {
+    check_all(SCENES);
+}
+
+#[test]
+#[ignore = "captures the fonts of the engine in graphics mode"]
+fn golden_font_scenes() {
+    let _lock = super::tests::VIDEO_MODE_LOCK
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    set_video_mode(VideoMode::Graphics320x200);
+    check_all(FONT_SCENES);
+    set_video_mode(VideoMode::Text80x25_8x16);
+}
+
+#[test]
+fn scene_names_are_unique() {
+    let all: Vec<_> = SCENES.iter().chain(FONT_SCENES).collect();
+    for (i, scene) in all.iter().enumerate() {
+        assert!(
+            all[..i].iter().all(|s| s.name != scene.name),
+            "duplicate scene {}",
+            scene.name
+        );
+    }
+}
+
+#[test]
+fn dump_round_trip() {
+    let pixels: Vec<u8> = (0..36).collect();
+    let dump = serialize(9, 4, &pixels);
+    assert!(dump.starts_with("9x4\n.12345678\n9ABCDEFGH\n"));
+    assert_eq!(deserialize(&dump), Ok((9, 4, pixels)));
+}
+
+#[test]
+fn invalid_dumps() {
+    assert!(deserialize("").is_err());
+    assert!(deserialize("3by1\n...\n").is_err());
+    assert!(deserialize("3x1\n..\n").is_err());
+    assert!(deserialize("3x2\n...\n").is_err());
+    assert!(deserialize("3x1\n.a.\n").is_err());
+}
+
+#[test]
+fn diff_lists_first_mismatches() {
+    let expected = [0; 20];
+    let mut actual = [0; 20];
+    assert_eq!(diff((5, 4, &expected), (5, 4, &actual)), None);
+
+    actual[7] = 3;
+    assert_eq!(
+        diff((5, 4, &expected), (5, 4, &actual)).unwrap(),
+        "1 pixels differ:\n  at (2, 1): expected 0, got 3"
+    );
+
+    actual.fill(1);
+    let message = diff((5, 4, &expected), (5, 4, &actual)).unwrap();
+    assert!(message.starts_with("20 pixels differ:\n  at (0, 0): expected 0, got 1"));
+    assert!(message.ends_with("\n  and 12 more"));
+
+    assert_eq!(
+        diff((5, 4, &expected), (4, 5, &actual)).unwrap(),
+        "expected a size of 5x4, got 4x5"
+    );
+}
@@ -0,0 +1,272 @@
+//! Loading of IFF images from Deluxe Paint (`.lbm` and `.bbm` files).
+
+use crate::video::Image;
+use crate::FileError;
+
+/// A color cycling range,
+/// as defined in the `CRNG` chunks of an IFF image.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct ColorCycleRange {
+    /// The first palette index in the range.
+    pub low: u8,
+    /// The last palette index in the range (inclusive).
+    pub high: u8,
+    /// The cycling rate,
+    /// where 16384 means 60 steps per second.
+    pub rate: u16,
+    /// Whether the colors cycle downwards instead of upwards.
+    pub reverse: bool,
+    /// Whether the range is enabled.
+    pub active: bool,
+}
+
+impl ColorCycleRange {
+    /// Gets the number of cycling steps per second.
+    #[inline]
+    pub fn steps_per_second(&self) -> f32 {
+        self.rate as f32 * 60. / 16384.
+    }
+
+    /// Gets the number of colors in the range.
+    #[inline]
+    pub fn len(&self) -> usize {
+        (self.high as usize + 1).saturating_sub(self.low as usize)
+    }
+
+    /// Checks whether the range has no colors to cycle.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.high <= self.low
+    }
+}
+
+/// An image loaded from an IFF file,
+/// along with its color cycling ranges.
+#[derive(Debug)]
+pub struct LbmImage {
+    image: Image,
+    cycle_ranges: Vec<ColorCycleRange>,
+}
+
+impl LbmImage {
+    /// Gets the image.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Gets the image mutably.
+    #[inline]
+    pub fn image_mut(&mut self) -> &mut Image {
+        &mut self.image
+    }
+
+    /// Discards the color cycling ranges,
+    /// keeping only the image.
+    #[inline]
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Gets the color cycling ranges of the image,
+    /// including the inactive ones.
+    #[inline]
+    pub fn cycle_ranges(&self) -> &[ColorCycleRange] {
+        &self.cycle_ranges
+    }
+}
+
+fn invalid(message: impl Into<String>) -> FileError {
+    FileError::InvalidData(message.into())
+}
+
+#[inline]
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+#[inline]
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// The relevant fields of the `BMHD` chunk.
+#[derive(Debug)]
+struct BitmapHeader {
+    width: usize,
+    height: usize,
+    planes: usize,
+    masking: u8,
+    compression: u8,
+}
+
+/// Decompresses ByteRun1 data until `len` bytes are produced.
+fn unpack_byte_run1(mut data: &[u8], len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len);
+    while out.len() < len {
+        let Some((&n, rest)) = data.split_first() else {
+            break;
+        };
+        data = rest;
+        match n as i8 {
+            n @ 0..=127 => {
+                let count = (n as usize + 1).min(data.len());
+                out.extend_from_slice(&data[..count]);
+                data = &data[count..];
+            }
+            -128 => {}
+            n => {
+                let Some((&value, rest)) = data.split_first() else {
+                    break;
+                };
+                data = rest;
+                out.extend(std::iter::repeat_n(value, (1 - n as isize) as usize));
+            }
+        }
+    }
+    out.resize(len, 0);
+    out
+}
+
+/// Loads an image from an IFF file created by Deluxe Paint
+/// (`.lbm` or `.bbm`),
+/// either in chunky (`PBM`) or planar (`ILBM`) form,
+/// uncompressed or compressed with ByteRun1,
+/// with up to 256 colors.
+///
+/// The palette is converted to the same range
+/// as the one of images loaded through [`load_gif`](super::load_gif),
+/// so that it can be passed directly to [`set_pal`](super::set_pal).
+///
+/// The color cycling ranges of the image
+/// are also available in the returned value.
+pub fn load_lbm(path: impl AsRef<str>) -> Result<LbmImage, FileError> {
+    let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+    load_lbm_from_memory(&data)
+}
+
+/// Loads an image from the byte data of an IFF file
+/// created by Deluxe Paint.
+///
+/// See [`load_lbm`] for the supported formats.
+pub fn load_lbm_from_memory(data: &[u8]) -> Result<LbmImage, FileError> {
+    if data.len() < 12 || &data[0..4] != b"FORM" {
+        return Err(invalid("not an IFF file"));
+    }
+    let planar = match &data[8..12] {
+        b"ILBM" => true,
+        b"PBM " => false,
+        _ => return Err(invalid("not an IFF image")),
+    };
+
+    let mut header = None;
+    let mut palette = [0; 768];
+    let mut palette_count = 0;
+    let mut cycle_ranges = Vec::new();
+    let mut body = None;
+
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let len = read_u32(data, pos + 4) as usize;
+        let chunk = &data[pos + 8..(pos + 8 + len).min(data.len())];
+        match id {
+            b"BMHD" if chunk.len() >= 20 => {
+                header = Some(BitmapHeader {
+                    width: read_u16(chunk, 0) as usize,
+                    height: read_u16(chunk, 2) as usize,
+                    planes: chunk[8] as usize,
+                    masking: chunk[9],
+                    compression: chunk[10],
+                });
+            }
+            b"CMAP" => {
+                palette_count = (chunk.len() / 3).min(256);
+                for (dst, src) in palette.iter_mut().zip(&chunk[..palette_count * 3]) {
+                    // same range as the palettes from `load_gif`
+                    *dst = src >> 2;
+                }
+            }
+            b"CRNG" if chunk.len() >= 8 => {
+                let flags = read_u16(chunk, 4);
+                cycle_ranges.push(ColorCycleRange {
+                    rate: read_u16(chunk, 2),
+                    active: flags & 1 != 0,
+                    reverse: flags & 2 != 0,
+                    low: chunk[6],
+                    high: chunk[7],
+                });
+            }
+            b"BODY" => body = Some(chunk),
+            _ => {}
+        }
+        // chunks are padded to an even size
+        pos += 8 + len + len % 2;
+    }
+
+    let header = header.ok_or_else(|| invalid("missing IFF bitmap header"))?;
+    let body = body.ok_or_else(|| invalid("missing IFF image body"))?;
+    if header.width == 0 || header.height == 0 {
+        return Err(invalid("empty IFF image"));
+    }
+    if planar && !(1..=8).contains(&header.planes) {
+        return Err(invalid(format!(
+            "unsupported number of bit planes: {}",
+            header.planes
+        )));
+    }
+
+    let (width, height) = (header.width, header.height);
+    // rows are padded to an even size in chunky images,
+    // and to 16 pixels per plane in planar images
+    let row_len = if planar {
+        let planes = header.planes + (header.masking == 1) as usize;
+        width.div_ceil(16) * 2 * planes
+    } else {
+        width + width % 2
+    };
+    let raw = match header.compression {
+        0 => {
+            let mut raw = body.to_vec();
+            raw.resize(row_len * height, 0);
+            raw
+        }
+        1 => unpack_byte_run1(body, row_len * height),
+        c => return Err(invalid(format!("unsupported IFF compression: {}", c))),
+    };
+
+    let mut pixels = vec![0; width * height];
+    for (row, out) in raw
+        .chunks_exact(row_len)
+        .zip(pixels.chunks_exact_mut(width))
+    {
+        if planar {
+            let plane_len = width.div_ceil(16) * 2;
+            for (plane, bits) in row.chunks_exact(plane_len).take(header.planes).enumerate() {
+                for (x, pixel) in out.iter_mut().enumerate() {
+                    if bits[x / 8] & (0x80 >> (x % 8)) != 0 {
+                        *pixel |= 1 << plane;
+                    }
+                }
+            }
+        } else {
+            out.copy_from_slice(&row[..width]);
+        }
+    }
+
+    Ok(LbmImage {
+        image: Image::from_pixels(
+            width as u32,
+            height as u32,
+            palette,
+            palette_count as u32,
+            pixels,
+        ),
+        cycle_ranges,
+    })
+}
@@ -0,0 +1,160 @@
+//! Inline color markup for text mode output.
+
+use crate::video::{put_str, TextAttr};
+
+/// The names of the 16 text mode colors, by index.
+const COLOR_NAMES: [&str; 16] = [
+    "black",
+    "blue",
+    "green",
+    "cyan",
+    "red",
+    "magenta",
+    "brown",
+    "lightgray",
+    "darkgray",
+    "lightblue",
+    "lightgreen",
+    "lightcyan",
+    "lightred",
+    "lightmagenta",
+    "yellow",
+    "white",
+];
+
+/// A piece of text with markup.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Segment<'a> {
+    /// Plain text to print.
+    Text(&'a str),
+    /// Switch the foreground color.
+    Foreground(u8),
+    /// Switch the background color.
+    Background(u8),
+    /// Restore the colors before the last switch.
+    Pop,
+}
+
+/// Parses a color by name or by index.
+fn parse_color(name: &str) -> Option<u8> {
+    COLOR_NAMES
+        .iter()
+        .position(|n| n.eq_ignore_ascii_case(name))
+        .map(|i| i as u8)
+        .or_else(|| name.parse().ok().filter(|c| *c < 16))
+}
+
+/// Parses the contents of a tag (without the braces).
+fn parse_tag(tag: &str) -> Option<Segment<'static>> {
+    if tag == "/" {
+        return Some(Segment::Pop);
+    }
+    match tag.split_once(':') {
+        Some(("fg", color)) => parse_color(color).map(Segment::Foreground),
+        Some(("bg", color)) => parse_color(color).map(Segment::Background),
+        Some(_) => None,
+        None => parse_color(tag).map(Segment::Foreground),
+    }
+}
+
+/// Iterates over the segments of a string with markup.
+fn segments(mut s: &str) -> impl Iterator<Item = Segment<'_>> {
+    std::iter::from_fn(move || {
+        if s.is_empty() {
+            return None;
+        }
+        if let Some(rest) = s.strip_prefix("{{") {
+            s = rest;
+            return Some(Segment::Text("{"));
+        }
+        if s.starts_with('{') {
+            if let Some(end) = s.find('}') {
+                if let Some(segment) = parse_tag(&s[1..end]) {
+                    s = &s[end + 1..];
+                    return Some(segment);
+                }
+            }
+            // not a valid tag, print the brace as is
+            let (text, rest) = s.split_at(1);
+            s = rest;
+            return Some(Segment::Text(text));
+        }
+        let end = s.find('{').unwrap_or(s.len());
+        let (text, rest) = s.split_at(end);
+        s = rest;
+        Some(Segment::Text(text))
+    })
+}
+
+/// Writes a string with inline color markup to the screen,
+/// at the current cursor position.
+///
+/// The markup grammar is as follows:
+///
+/// - `{color}` or `{fg:color}` switches the foreground color;
+/// - `{bg:color}` switches the background color;
+/// - `{/}` restores the colors in effect before the last switch;
+/// - `{{` writes a literal `{`.
+///
+/// Colors are either one of the 16 standard color names
+/// (`black`, `blue`, `green`, `cyan`, `red`, `magenta`, `brown`,
+/// `lightgray`, `darkgray`, `lightblue`, `lightgreen`, `lightcyan`,
+/// `lightred`, `lightmagenta`, `yellow`, `white`),
+/// case insensitive,
+/// or a color index between 0 and 15.
+/// Tags which do not follow this grammar are written literally.
+///
+/// The colors in effect before the call are restored at the end.
+///
+/// Does nothing unless the video is in text mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// put_str_markup("{lightred}Error:{/} file {bg:blue}{yellow}README{/}{/} not found");
+/// ```
+///
+/// # Panics
+///
+/// Panics if the given string contains null characters (`\0`).
+pub fn put_str_markup(s: impl AsRef<str>) {
+    let original = TextAttr::current();
+    let mut stack: Vec<TextAttr> = Vec::new();
+    let mut current = original;
+
+    for segment in segments(s.as_ref()) {
+        match segment {
+            Segment::Text(text) => put_str(text),
+            Segment::Foreground(color) => {
+                stack.push(current);
+                current.foreground = color;
+                current.apply();
+            }
+            Segment::Background(color) => {
+                stack.push(current);
+                current.background = color;
+                current.apply();
+            }
+            Segment::Pop => {
+                current = stack.pop().unwrap_or(original);
+                current.apply();
+            }
+        }
+    }
+
+    original.apply();
+}
+
+/// Removes all color markup from the given string,
+/// leaving only the text that would be written by [`put_str_markup`].
+///
+/// This is useful for calculating the length of the text on screen.
+pub fn strip_markup(s: &str) -> String {
+    segments(s)
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,1045 @@
+//! Whole-palette operations and effects.
+
+use std::ops::Range;
+use std::os::raw::c_int;
+use std::sync::{atomic::Ordering, Mutex};
+
+use crate::video::{load_gif, pal, Image, PALETTE_GENERATION};
+use crate::FileError;
+
+/// The number of colors in the palette.
+const PALETTE_SIZE: usize = 256;
+
+/// A palette color, with one byte per channel.
+///
+/// It has the layout of three bytes in RGB order,
+/// so that a palette kept as bytes can be viewed as colors
+/// (see [`Image::palette_colors`]).
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+#[repr(C)]
+pub struct Rgb {
+    /// The red channel
+    pub r: u8,
+    /// The green channel
+    pub g: u8,
+    /// The blue channel
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Creates a color from its channels.
+    #[inline]
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Rgb { r, g, b }
+    }
+
+    /// Views bytes in RGB order as colors,
+    /// leaving out any bytes past the last whole color.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> &[Rgb] {
+        // Safety: `Rgb` is made of three bytes with an alignment of 1
+        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const Rgb, bytes.len() / 3) }
+    }
+
+    /// Views bytes in RGB order as mutable colors,
+    /// leaving out any bytes past the last whole color.
+    pub(crate) fn from_bytes_mut(bytes: &mut [u8]) -> &mut [Rgb] {
+        // Safety: `Rgb` is made of three bytes with an alignment of 1
+        unsafe { std::slice::from_raw_parts_mut(bytes.as_mut_ptr() as *mut Rgb, bytes.len() / 3) }
+    }
+}
+
+impl From<(u8, u8, u8)> for Rgb {
+    #[inline]
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+impl From<Rgb> for (u8, u8, u8) {
+    #[inline]
+    fn from(color: Rgb) -> Self {
+        (color.r, color.g, color.b)
+    }
+}
+
+impl From<[u8; 3]> for Rgb {
+    #[inline]
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Rgb { r, g, b }
+    }
+}
+
+impl From<Rgb> for [u8; 3] {
+    #[inline]
+    fn from(color: Rgb) -> Self {
+        [color.r, color.g, color.b]
+    }
+}
+
+/// Writes colors into the palette from the given index on,
+/// with a single palette change for cached remappings.
+fn upload_palette(start: usize, colors: impl ExactSizeIterator<Item = (u8, u8, u8)>) {
+    debug_assert!(
+        start + colors.len() <= PALETTE_SIZE,
+        "set_palette: {} colors from index {} go past the end of the palette",
+        colors.len(),
+        start
+    );
+    for (i, (r, g, b)) in colors.enumerate() {
+        debug_assert!(
+            r < 64 && g < 64 && b < 64,
+            "set_palette: color ({}, {}, {}) out of range",
+            r,
+            g,
+            b
+        );
+        unsafe {
+            dos_like_sys::setpal((start + i) as c_int, r as c_int, g as c_int, b as c_int);
+        }
+    }
+    PALETTE_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Sets the palette colors from index 0 on,
+/// leaving the colors past the ones given as they are.
+///
+/// The color components are in the 6-bit range of the VGA palette,
+/// from 0 to 63, as in [`set_pal`](super::set_pal).
+/// The engine takes one color at a time,
+/// but this is cheaper than calling [`set_pal`](super::set_pal) for each color,
+/// as the images remapped to the current palette
+/// (see [`draw_image_auto`](super::draw_image_auto))
+/// are only invalidated once.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// // a grayscale palette
+/// let grays: Vec<_> = (0..64).map(|v| (v, v, v)).collect();
+/// set_palette(&grays);
+/// ```
+#[inline]
+pub fn set_palette(colors: &[(u8, u8, u8)]) {
+    set_palette_range(0, colors);
+}
+
+/// Sets the palette colors from the given index on,
+/// leaving the other colors as they are.
+///
+/// See [`set_palette`].
+#[inline]
+pub fn set_palette_range(start: usize, colors: &[(u8, u8, u8)]) {
+    upload_palette(start, colors.iter().copied());
+}
+
+/// Sets the palette colors from the given index on,
+/// from a slice of [`Rgb`] colors,
+/// such as the palette of an image (see [`Image::palette_colors`]).
+///
+/// See [`set_palette`].
+#[inline]
+pub fn set_palette_colors(start: usize, colors: &[Rgb]) {
+    upload_palette(start, colors.iter().map(|&color| color.into()));
+}
+
+/// Sets the palette colors from the given index on,
+/// from a slice of bytes in RGB order (up to 768 bytes for 256 colors),
+/// such as the palette of an image (see [`Image::palette`]).
+///
+/// Bytes past the last whole color are ignored.
+/// See [`set_palette`].
+#[inline]
+pub fn set_palette_bytes(start: usize, bytes: &[u8]) {
+    set_palette_colors(start, Rgb::from_bytes(bytes));
+}
+
+/// Gets all colors of the palette at once,
+/// in the engine's 6-bit values (0 to 63).
+///
+/// Each color is the same as given by [`pal`](super::pal) for its index.
+/// See [`Palette`] for effects computed on the whole palette.
+///
+/// # Example
+///
+/// Fading from the current palette to black:
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let from = get_palette();
+/// for step in (0..=32).rev() {
+///     wait_vbl();
+///     let fade = |c: u8| (c as u32 * step / 32) as u8;
+///     let colors: Vec<_> = from
+///         .iter()
+///         .map(|c| (fade(c.r), fade(c.g), fade(c.b)))
+///         .collect();
+///     set_palette(&colors);
+/// }
+/// ```
+pub fn get_palette() -> [Rgb; PALETTE_SIZE] {
+    let mut colors = [Rgb::default(); PALETTE_SIZE];
+    get_palette_range(0, &mut colors);
+    colors
+}
+
+/// Gets the colors of the palette from the given index on,
+/// as many as fit in `out`,
+/// in the engine's 6-bit values (0 to 63).
+///
+/// Entries of `out` past the end of the palette are left as they are.
+/// Each color is the same as given by [`pal`](super::pal) for its index.
+pub fn get_palette_range(start: usize, out: &mut [Rgb]) {
+    for (color, index) in out.iter_mut().zip(start..PALETTE_SIZE) {
+        *color = pal(index).into();
+    }
+}
+
+/// Rotates the palette colors in the given range of indices
+/// by `amount` places, wrapping around within the range,
+/// for color cycling effects such as water, fire or plasma.
+///
+/// A positive amount moves each color to a higher index,
+/// with the last colors of the range coming back at its start,
+/// and a negative amount moves the colors the other way.
+/// The colors outside of the range are left as they are,
+/// and the range is cut at the end of the palette.
+///
+/// This only rewrites the colors in the range,
+/// so it is cheap enough to call on every frame,
+/// unlike animating the pixels of the screen.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// // the colors of the water flow through indices 64 to 79
+/// let mut frame = 0;
+/// while !shutting_down() {
+///     wait_vbl();
+///     frame += 1;
+///     if frame % 4 == 0 {
+///         rotate_palette(64..80, 1);
+///     }
+/// }
+/// ```
+pub fn rotate_palette(range: Range<usize>, amount: isize) {
+    let range = range.start.min(PALETTE_SIZE)..range.end.min(PALETTE_SIZE);
+    if range.len() < 2 {
+        return;
+    }
+    let amount = amount.rem_euclid(range.len() as isize) as usize;
+    if amount == 0 {
+        return;
+    }
+    let mut colors = [Rgb::default(); PALETTE_SIZE];
+    let colors = &mut colors[..range.len()];
+    get_palette_range(range.start, colors);
+    colors.rotate_right(amount);
+    upload_palette(range.start, colors.iter().map(|&color| color.into()));
+}
+
+/// Gets all colors of the palette as bytes in RGB order.
+pub(crate) fn palette_bytes() -> [u8; PALETTE_SIZE * 3] {
+    let mut bytes = [0; PALETTE_SIZE * 3];
+    for (rgb, color) in bytes.chunks_exact_mut(3).zip(get_palette()) {
+        rgb.copy_from_slice(&<[u8; 3]>::from(color));
+    }
+    bytes
+}
+
+/// A copy of all colors of the palette at one point in time,
+/// in the engine's 6-bit values (0 to 63).
+///
+/// Palette effects such as [`adjust_palette`] and [`tint_palette`]
+/// are computed from a snapshot instead of the live palette,
+/// so that applying them repeatedly does not accumulate rounding errors,
+/// and the original colors can be brought back with [`restore`](Self::restore).
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let baseline = PaletteSnapshot::capture();
+/// // lights out
+/// adjust_palette(&baseline, 0.25, 1.);
+/// // ...
+/// baseline.restore();
+/// ```
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct PaletteSnapshot {
+    colors: [[u8; 3]; PALETTE_SIZE],
+}
+
+impl PaletteSnapshot {
+    /// Captures the current palette.
+    pub fn capture() -> Self {
+        PaletteSnapshot {
+            colors: get_palette().map(<[u8; 3]>::from),
+        }
+    }
+
+    /// Gets the captured colors, in 6-bit values.
+    #[inline]
+    pub fn colors(&self) -> &[[u8; 3]; PALETTE_SIZE] {
+        &self.colors
+    }
+
+    /// Installs the captured colors back into the palette.
+    pub fn restore(&self) {
+        upload_palette(0, self.colors.iter().map(|&color| Rgb::from(color).into()));
+    }
+
+    /// Installs the captured colors transformed by `f`,
+    /// which maps each 8-bit channel value of a color to a new one.
+    fn install_with(&self, mut f: impl FnMut([f32; 3]) -> [f32; 3]) {
+        upload_palette(
+            0,
+            self.colors.iter().map(|color| {
+                let [r, g, b] = f(color.map(|c| to_8bit(c) as f32)).map(to_6bit);
+                (r, g, b)
+            }),
+        );
+    }
+}
+
+/// Converts a 6-bit color value to 8 bits,
+/// so that 63 becomes 255.
+#[inline]
+fn to_8bit(value: u8) -> u8 {
+    let value = value.min(63);
+    (value << 2) | (value >> 4)
+}
+
+/// Converts an 8-bit color value to the nearest 6-bit value,
+/// clamping out of range values.
+#[inline]
+fn to_6bit(value: f32) -> u8 {
+    let value = value.round().clamp(0., 255.) as u32;
+    ((value * 63 + 127) / 255) as u8
+}
+
+/// Installs an adjusted version of the `baseline` palette,
+/// for effects such as dark rooms, night scenes or flashes.
+///
+/// - `brightness` scales all colors,
+///   so that `1.` keeps them, `0.` turns them black,
+///   and values above `1.` brighten them
+/// - `contrast` scales the distance of each color channel
+///   from the middle value,
+///   so that `1.` keeps them, `0.` turns all colors gray,
+///   and values above `1.` increase the contrast
+///
+/// Contrast is applied before brightness.
+/// The computation is done on 8-bit values,
+/// and the results are clamped to the valid range.
+/// Call [`PaletteSnapshot::restore`] to go back to the original palette.
+pub fn adjust_palette(baseline: &PaletteSnapshot, brightness: f32, contrast: f32) {
+    baseline.install_with(|color| color.map(|c| ((c - 128.) * contrast + 128.) * brightness));
+}
+
+/// Installs a version of the `baseline` palette
+/// blended towards the given color,
+/// for effects such as damage flashes or underwater scenes.
+///
+/// The tint color is given in 8-bit values (0 to 255),
+/// and `strength` is how much of it is blended in,
+/// from `0.` (no tint) to `1.` (all colors become the tint color).
+/// Call [`PaletteSnapshot::restore`] to go back to the original palette.
+pub fn tint_palette(baseline: &PaletteSnapshot, r: u8, g: u8, b: u8, strength: f32) {
+    let strength = strength.clamp(0., 1.);
+    let tint = [r as f32, g as f32, b as f32];
+    baseline.install_with(|color| {
+        let mut out = color;
+        for (c, t) in out.iter_mut().zip(tint) {
+            *c += (t - *c) * strength;
+        }
+        out
+    });
+}
+
+/// The largest value of a color channel in the engine's palette.
+const CHANNEL_MAX: f32 = 63.;
+
+/// Rounds a color channel value to the nearest valid one.
+#[inline]
+fn to_channel(value: f32) -> u8 {
+    value.round().clamp(0., CHANNEL_MAX) as u8
+}
+
+/// A full palette of 256 colors,
+/// in the engine's 6-bit values (0 to 63),
+/// to build palette effects such as fades, flashes and tints.
+///
+/// The operations make a new palette from this one,
+/// which can then be installed with [`apply`](Self::apply).
+/// Keeping the original palette around and computing each step from it,
+/// rather than from the previous step,
+/// avoids accumulating rounding errors,
+/// and the results are always clamped to the valid range.
+///
+/// # Example
+///
+/// Fading to black over 30 frames:
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let from = Palette::from_screen();
+/// for frame in 1..=30 {
+///     wait_vbl();
+///     from.lerp(&Palette::black(), frame as f32 / 30.).apply();
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Palette {
+    colors: [Rgb; PALETTE_SIZE],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::black()
+    }
+}
+
+impl From<[Rgb; PALETTE_SIZE]> for Palette {
+    #[inline]
+    fn from(colors: [Rgb; PALETTE_SIZE]) -> Self {
+        Palette { colors }
+    }
+}
+
+impl Palette {
+    /// Creates a palette with all colors black.
+    #[inline]
+    pub const fn black() -> Self {
+        Palette {
+            colors: [Rgb::new(0, 0, 0); PALETTE_SIZE],
+        }
+    }
+
+    /// Creates a palette with the colors currently on the screen
+    /// (see [`get_palette`]).
+    #[inline]
+    pub fn from_screen() -> Self {
+        Palette {
+            colors: get_palette(),
+        }
+    }
+
+    /// Gets the colors of the palette.
+    #[inline]
+    pub fn colors(&self) -> &[Rgb; PALETTE_SIZE] {
+        &self.colors
+    }
+
+    /// Gets the colors of the palette for modification.
+    ///
+    /// Channels above 63 are clamped when the palette is installed.
+    #[inline]
+    pub fn colors_mut(&mut self) -> &mut [Rgb; PALETTE_SIZE] {
+        &mut self.colors
+    }
+
+    /// Installs all colors of the palette on the screen.
+    pub fn apply(&self) {
+        upload_palette(
+            0,
+            self.colors
+                .iter()
+                .map(|color| (color.r.min(63), color.g.min(63), color.b.min(63))),
+        );
+    }
+
+    /// Makes a new palette by applying `f` to each channel of each color,
+    /// along with the matching channel of `other`.
+    fn map_channels(&self, other: &Palette, f: impl Fn(f32, f32) -> f32) -> Palette {
+        let mut colors = self.colors;
+        for (color, other) in colors.iter_mut().zip(&other.colors) {
+            *color = Rgb::new(
+                to_channel(f(color.r as f32, other.r as f32)),
+                to_channel(f(color.g as f32, other.g as f32)),
+                to_channel(f(color.b as f32, other.b as f32)),
+            );
+        }
+        Palette { colors }
+    }
+
+    /// Interpolates linearly between this palette and `other`,
+    /// so that `t = 0.` gives the colors of this palette
+    /// and `t = 1.` gives the colors of `other`.
+    ///
+    /// `t` is clamped between `0.` and `1.`,
+    /// and both ends give back the exact colors.
+    pub fn lerp(&self, other: &Palette, t: f32) -> Palette {
+        let t = t.clamp(0., 1.);
+        self.map_channels(other, |a, b| a + (b - a) * t)
+    }
+
+    /// Scales the brightness of all colors,
+    /// so that `1.` keeps them, `0.` turns them black,
+    /// and values above `1.` brighten them up to white.
+    pub fn scaled(&self, brightness: f32) -> Palette {
+        let brightness = brightness.max(0.);
+        self.map_channels(self, |c, _| c * brightness)
+    }
+
+    /// Blends all colors towards the given color,
+    /// in 6-bit values (0 to 63) like the palette,
+    /// for effects such as damage flashes or underwater scenes.
+    ///
+    /// `amount` is how much of the color is blended in,
+    /// from `0.` (no tint) to `1.` (all colors become the tint color).
+    pub fn tinted(&self, r: u8, g: u8, b: u8, amount: f32) -> Palette {
+        let tint = Palette {
+            colors: [Rgb::new(r.min(63), g.min(63), b.min(63)); PALETTE_SIZE],
+        };
+        self.lerp(&tint, amount)
+    }
+}
+
+/// Finds the index of the color in `palette` nearest to the given color,
+/// by Euclidean distance.
+///
+/// The palette is in RGB, 3 bytes per color,
+/// and the color is in the same scale as the palette.
+/// Ties are resolved in favor of the lowest index.
+/// Returns 0 if the palette is empty.
+pub fn nearest_color_in(palette: &[u8], rgb: (u8, u8, u8)) -> u8 {
+    let mut best = (u32::MAX, 0);
+    for (i, c) in palette.chunks_exact(3).take(PALETTE_SIZE).enumerate() {
+        let distance = [(c[0], rgb.0), (c[1], rgb.1), (c[2], rgb.2)]
+            .iter()
+            .map(|&(a, b)| (a as i32 - b as i32).pow(2) as u32)
+            .sum::<u32>();
+        if distance < best.0 {
+            best = (distance, i as u8);
+        }
+    }
+    best.1
+}
+
+/// Marks an entry of the lookup table not calculated yet.
+const UNKNOWN: u16 = u16::MAX;
+
+/// A lookup table of the nearest palette index for each 6-bit color,
+/// filled as colors are requested.
+struct NearestCache {
+    /// The palette generation which the table is valid for
+    generation: u32,
+    palette: Vec<u8>,
+    table: Box<[u16]>,
+}
+
+static NEAREST_CACHE: Mutex<Option<NearestCache>> = Mutex::new(None);
+
+/// Finds the index of the color in the current palette
+/// nearest to the given color,
+/// in the engine's 6-bit values (0 to 63).
+///
+/// Ties are resolved in favor of the lowest index.
+/// Results are cached in a lookup table,
+/// so calling this many times per frame is cheap.
+/// The table is discarded when the palette is changed
+/// through the functions of this crate, such as [`set_pal`](super::set_pal).
+pub fn nearest_color(r: u8, g: u8, b: u8) -> u8 {
+    let (r, g, b) = (r.min(63), g.min(63), b.min(63));
+    let generation = PALETTE_GENERATION.load(Ordering::Relaxed);
+    let mut cache = NEAREST_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = match &mut *cache {
+        Some(c) if c.generation == generation => c,
+        cache => {
+            let palette = palette_bytes().to_vec();
+            let mut table = cache
+                .take()
+                .map(|c| c.table)
+                .unwrap_or_else(|| vec![UNKNOWN; 64 * 64 * 64].into_boxed_slice());
+            table.fill(UNKNOWN);
+            cache.insert(NearestCache {
+                generation,
+                palette,
+                table,
+            })
+        }
+    };
+    let entry = &mut cache.table[(r as usize) << 12 | (g as usize) << 6 | b as usize];
+    if *entry == UNKNOWN {
+        *entry = nearest_color_in(&cache.palette, (r, g, b)) as u16;
+    }
+    *entry as u8
+}
+
+/// Error type for building palette ramps with [`make_multi_ramp`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RampError {
+    /// No color stops were given
+    NoStops,
+    /// A color stop is positioned before the previous one
+    UnorderedStops {
+        /// The index of the offending stop.
+        index: usize,
+    },
+}
+
+impl std::fmt::Display for RampError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RampError::NoStops => write!(f, "No color stops given"),
+            RampError::UnorderedStops { index } => {
+                write!(f, "Color stop #{} is out of order", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RampError {}
+
+/// Interpolates between two 8-bit colors in a gamma-corrected space,
+/// returning the 8-bit result.
+fn lerp_gamma(from: (u8, u8, u8), to: (u8, u8, u8), t: f32, gamma: f32) -> [f32; 3] {
+    let linear = |c: u8| (c as f32 / 255.).powf(gamma);
+    let from = [from.0, from.1, from.2].map(linear);
+    let to = [to.0, to.1, to.2].map(linear);
+    let mut out = [0.; 3];
+    for ((o, a), b) in out.iter_mut().zip(from).zip(to) {
+        *o = (a + (b - a) * t).powf(1. / gamma) * 255.;
+    }
+    out
+}
+
+/// Creates a gradient of `steps` palette colors
+/// from `start` to `end`, both included.
+///
+/// The end colors are given in 8-bit values (0 to 255),
+/// and the resulting colors are in the engine's 6-bit values,
+/// ready for [`install_ramp`] or [`set_pal`](super::set_pal).
+///
+/// The colors are interpolated after raising them to the power of `gamma`,
+/// so that a gamma of around 2.2 gives perceptually even midtones,
+/// whereas a gamma of `1.` is a plain linear interpolation.
+/// A single step ramp only contains `start`.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// // a fire ramp over colors 0 to 63
+/// let ramp = make_ramp((0, 0, 0), (255, 80, 0), 32, 2.2);
+/// install_ramp(0, &ramp);
+/// install_ramp(32, &make_ramp((255, 80, 0), (255, 255, 200), 32, 2.2));
+/// ```
+pub fn make_ramp(
+    start: (u8, u8, u8),
+    end: (u8, u8, u8),
+    steps: u8,
+    gamma: f32,
+) -> Vec<(u8, u8, u8)> {
+    let last = steps.saturating_sub(1).max(1) as f32;
+    (0..steps)
+        .map(|i| {
+            let [r, g, b] = lerp_gamma(start, end, i as f32 / last, gamma).map(to_6bit);
+            (r, g, b)
+        })
+        .collect()
+}
+
+/// Creates a gradient of `steps` palette colors
+/// through multiple color stops.
+///
+/// Each stop is a position between `0.` (the first step)
+/// and `1.` (the last step) and an 8-bit color.
+/// Steps before the first stop or after the last one
+/// take the color of that stop.
+/// Colors are interpolated in the same way as in [`make_ramp`],
+/// and the result is in the engine's 6-bit values.
+///
+/// Fails if no stops are given,
+/// or if the stops are not in ascending order of position.
+pub fn make_multi_ramp(
+    stops: &[(f32, (u8, u8, u8))],
+    steps: u8,
+    gamma: f32,
+) -> Result<Vec<(u8, u8, u8)>, RampError> {
+    if stops.is_empty() {
+        return Err(RampError::NoStops);
+    }
+    if let Some(index) =
+        (1..stops.len()).find(|&i| stops[i].0.is_nan() || stops[i].0 < stops[i - 1].0)
+    {
+        return Err(RampError::UnorderedStops { index });
+    }
+
+    let last = steps.saturating_sub(1).max(1) as f32;
+    Ok((0..steps)
+        .map(|i| {
+            let t = i as f32 / last;
+            // the stops surrounding this position
+            let next = stops.iter().position(|s| s.0 > t).unwrap_or(stops.len());
+            let color = match next {
+                0 => {
+                    let c = stops[0].1;
+                    [c.0, c.1, c.2].map(f32::from)
+                }
+                n if n == stops.len() => {
+                    let c = stops[n - 1].1;
+                    [c.0, c.1, c.2].map(f32::from)
+                }
+                n => {
+                    let (p0, c0) = stops[n - 1];
+                    let (p1, c1) = stops[n];
+                    lerp_gamma(c0, c1, (t - p0) / (p1 - p0), gamma)
+                }
+            };
+            let [r, g, b] = color.map(to_6bit);
+            (r, g, b)
+        })
+        .collect())
+}
+
+/// Writes the colors of a ramp into the palette,
+/// starting at the given index.
+///
+/// Colors which would go past the end of the palette are ignored.
+pub fn install_ramp(start_index: usize, ramp: &[(u8, u8, u8)]) {
+    let len = ramp.len().min(PALETTE_SIZE.saturating_sub(start_index));
+    set_palette_range(start_index, &ramp[..len]);
+}
+
+/// A set of images sharing a single palette,
+/// as loaded by [`load_gifs_shared`].
+#[derive(Debug)]
+pub struct SharedPaletteSet {
+    images: Vec<Image>,
+    palette: Vec<u8>,
+    remapped: Vec<String>,
+}
+
+impl SharedPaletteSet {
+    /// Gets the images, in the order of the paths given.
+    #[inline]
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    /// Takes the images, in the order of the paths given.
+    #[inline]
+    pub fn into_images(self) -> Vec<Image> {
+        self.images
+    }
+
+    /// Gets the unified palette, in RGB (3 bytes per color).
+    #[inline]
+    pub fn palette(&self) -> &[u8] {
+        &self.palette
+    }
+
+    /// Gets the paths of the images
+    /// whose palette did not match the ones before them,
+    /// and so had their pixels remapped to the unified palette.
+    ///
+    /// This is empty if all images were exported with the same palette.
+    #[inline]
+    pub fn remapped_paths(&self) -> &[String] {
+        &self.remapped
+    }
+
+    /// Installs the unified palette as the global palette.
+    pub fn apply(&self) {
+        set_palette_bytes(0, &self.palette);
+    }
+}
+
+/// Loads multiple GIF images into a single shared palette.
+///
+/// The palette of the first image is taken as the base.
+/// Images with the same palette (or a prefix of it) are kept as is.
+/// Otherwise, each color used by the image
+/// is matched exactly against the unified palette,
+/// appending the colors which are missing,
+/// and the pixels are remapped to the unified palette.
+/// The paths of remapped images are listed in
+/// [`remapped_paths`](SharedPaletteSet::remapped_paths).
+///
+/// All images get the unified palette,
+/// but it is not installed until [`apply`](SharedPaletteSet::apply) is called.
+///
+/// Fails if any image cannot be loaded,
+/// or with [`FileError::InvalidData`] naming the file
+/// if its colors do not fit in 256 palette entries.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let set = load_gifs_shared(&["hero.gif", "enemy.gif", "coin.gif"])?;
+/// for path in set.remapped_paths() {
+///     eprintln!("warning: {} has a different palette", path);
+/// }
+/// set.apply();
+/// # Ok::<(), FileError>(())
+/// ```
+pub fn load_gifs_shared(paths: &[&str]) -> Result<SharedPaletteSet, FileError> {
+    let mut images = Vec::with_capacity(paths.len());
+    let mut palette: Vec<u8> = Vec::new();
+    let mut remapped = Vec::new();
+
+    for (n, &path) in paths.iter().enumerate() {
+        let mut image = load_gif(path)?;
+        let own = image.palette();
+        let shared = own.len().min(palette.len());
+        if n == 0 || own[..shared] == palette[..shared] {
+            // same palette, possibly with more colors
+            if own.len() > palette.len() {
+                palette.extend_from_slice(&own[palette.len()..]);
+            }
+        } else {
+            let mut table = [0_u8; PALETTE_SIZE];
+            let mut used = [false; PALETTE_SIZE];
+            for &p in image.data() {
+                used[p as usize] = true;
+            }
+            for (i, _) in used.iter().enumerate().filter(|(_, u)| **u) {
+                let rgb = own.get(i * 3..i * 3 + 3).unwrap_or(&[0, 0, 0]);
+                let found = palette.chunks_exact(3).position(|c| c == rgb);
+                table[i] = match found {
+                    Some(j) => j as u8,
+                    None if palette.len() < PALETTE_SIZE * 3 => {
+                        palette.extend_from_slice(rgb);
+                        (palette.len() / 3 - 1) as u8
+                    }
+                    None => {
+                        return Err(FileError::InvalidData(format!(
+                            "{}: colors do not fit in a shared palette of {} colors",
+                            path, PALETTE_SIZE
+                        )))
+                    }
+                };
+            }
+            for p in image.data_mut() {
+                *p = table[*p as usize];
+            }
+            remapped.push(path.to_string());
+        }
+        images.push(image);
+    }
+
+    for image in &mut images {
+        image.palette[..palette.len()].copy_from_slice(&palette);
+        image.palette_count = (palette.len() / 3) as u32;
+        image.invalidate_remapped();
+    }
+
+    Ok(SharedPaletteSet {
+        images,
+        palette,
+        remapped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_of_zero_steps_is_empty() {
+        assert!(make_ramp((0, 0, 0), (255, 255, 255), 0, 2.2).is_empty());
+        assert_eq!(make_multi_ramp(&[(0., (255, 0, 0))], 0, 1.), Ok(vec![]));
+    }
+
+    #[test]
+    fn ramp_of_one_step_is_the_start() {
+        assert_eq!(make_ramp((255, 128, 0), (0, 0, 255), 1, 2.2), [(63, 32, 0)]);
+        assert_eq!(
+            make_multi_ramp(&[(0., (255, 128, 0)), (1., (0, 0, 255))], 1, 2.2),
+            Ok(vec![(63, 32, 0)])
+        );
+    }
+
+    #[test]
+    fn ramp_between_equal_endpoints_is_flat() {
+        for gamma in [1., 2.2] {
+            let ramp = make_ramp((100, 150, 200), (100, 150, 200), 16, gamma);
+            assert_eq!(ramp.len(), 16);
+            assert!(ramp.iter().all(|&c| c == (25, 37, 49)), "{:?}", ramp);
+        }
+    }
+
+    #[test]
+    fn ramp_includes_both_ends() {
+        let ramp = make_ramp((0, 0, 0), (255, 255, 255), 64, 1.);
+        assert_eq!(ramp.len(), 64);
+        assert_eq!(ramp[0], (0, 0, 0));
+        assert_eq!(ramp[63], (63, 63, 63));
+        // linear, so one 6-bit level per step
+        assert!(ramp
+            .iter()
+            .enumerate()
+            .all(|(i, &c)| c == (i as u8, i as u8, i as u8)));
+    }
+
+    #[test]
+    fn gamma_brightens_midtones() {
+        let linear = make_ramp((0, 0, 0), (255, 255, 255), 3, 1.);
+        let corrected = make_ramp((0, 0, 0), (255, 255, 255), 3, 2.2);
+        assert_eq!(linear[1], (32, 32, 32));
+        assert!(corrected[1].0 > linear[1].0);
+        assert_eq!(corrected[0], linear[0]);
+        assert_eq!(corrected[2], linear[2]);
+    }
+
+    #[test]
+    fn nearest_color_in_picks_the_closest_entry() {
+        let palette = [0, 0, 0, 63, 0, 0, 0, 63, 0, 0, 0, 63, 63, 63, 63];
+        assert_eq!(nearest_color_in(&palette, (0, 0, 0)), 0);
+        assert_eq!(nearest_color_in(&palette, (50, 10, 5)), 1);
+        assert_eq!(nearest_color_in(&palette, (2, 40, 20)), 2);
+        assert_eq!(nearest_color_in(&palette, (60, 58, 62)), 4);
+    }
+
+    #[test]
+    fn nearest_color_in_prefers_the_lowest_index() {
+        let palette = [10, 10, 10, 30, 30, 30, 10, 10, 10];
+        assert_eq!(nearest_color_in(&palette, (10, 10, 10)), 0);
+        assert_eq!(nearest_color_in(&palette, (20, 20, 20)), 0);
+        assert_eq!(nearest_color_in(&[], (20, 20, 20)), 0);
+    }
+
+    #[test]
+    fn rgb_views_and_conversions() {
+        let color = Rgb::new(1, 2, 3);
+        assert_eq!(Rgb::from((1, 2, 3)), color);
+        assert_eq!(Rgb::from([1, 2, 3]), color);
+        assert_eq!(<(u8, u8, u8)>::from(color), (1, 2, 3));
+        assert_eq!(<[u8; 3]>::from(color), [1, 2, 3]);
+
+        let mut bytes = [1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(Rgb::from_bytes(&bytes), [color, Rgb::new(4, 5, 6)]);
+        Rgb::from_bytes_mut(&mut bytes)[1].g = 63;
+        assert_eq!(bytes, [1, 2, 3, 4, 63, 6, 7]);
+    }
+
+    #[test]
+    fn palette_upload_changes_the_palette_once() {
+        let _lock = crate::video::tests::VIDEO_MODE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let grays: Vec<_> = (0..64).map(|v| (v, v, v)).collect();
+        let generation = PALETTE_GENERATION.load(Ordering::Relaxed);
+        set_palette(&grays);
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation + 1);
+        set_palette_bytes(250, &[0; 19]);
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation + 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "go past the end of the palette")]
+    fn palette_upload_past_the_end() {
+        set_palette_range(255, &[(0, 0, 0), (0, 0, 0)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn palette_upload_out_of_range() {
+        set_palette_colors(0, &[Rgb::new(0, 64, 0)]);
+    }
+
+    #[test]
+    fn palette_reads_agree_with_pal() {
+        let _lock = crate::video::tests::VIDEO_MODE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let colors = get_palette();
+        let bytes = palette_bytes();
+        for (i, color) in colors.iter().enumerate() {
+            assert_eq!(<(u8, u8, u8)>::from(*color), pal(i));
+            assert_eq!(bytes[i * 3..i * 3 + 3], <[u8; 3]>::from(*color));
+        }
+
+        // entries past the end of the palette are left alone
+        let marker = Rgb::new(99, 99, 99);
+        let mut out = [marker; 10];
+        get_palette_range(250, &mut out);
+        assert_eq!(out[..6], colors[250..]);
+        assert_eq!(out[6..], [marker; 4]);
+    }
+
+    #[test]
+    fn palette_effects_round_and_clamp() {
+        let mut bright = Palette::black();
+        for (i, color) in bright.colors_mut().iter_mut().enumerate() {
+            *color = Rgb::new((i % 64) as u8, 63, 10);
+        }
+        let black = Palette::default();
+
+        // the ends give back the exact colors
+        assert_eq!(bright.lerp(&black, 0.), bright);
+        assert_eq!(bright.lerp(&black, 1.), black);
+        assert_eq!(bright.lerp(&black, 2.), black);
+        assert_eq!(bright.lerp(&black, 0.5).colors()[21], Rgb::new(11, 32, 5));
+
+        assert_eq!(bright.scaled(1.), bright);
+        assert_eq!(bright.scaled(2.).colors()[40], Rgb::new(63, 63, 20));
+        assert_eq!(bright.scaled(-1.), black);
+
+        let tinted = bright.tinted(63, 0, 200, 0.25);
+        assert_eq!(tinted.colors()[0], Rgb::new(16, 47, 23));
+    }
+
+    #[test]
+    fn rotate_palette_within_the_range() {
+        let _lock = crate::video::tests::VIDEO_MODE_LOCK
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let generation = PALETTE_GENERATION.load(Ordering::Relaxed);
+        // nothing to rotate
+        rotate_palette(10..11, 1);
+        rotate_palette(10..20, -10);
+        rotate_palette(300..400, 1);
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation);
+
+        let before = get_palette();
+        rotate_palette(250..300, -1);
+        assert_eq!(PALETTE_GENERATION.load(Ordering::Relaxed), generation + 1);
+        let after = get_palette();
+        assert_eq!(after[..250], before[..250]);
+        assert_eq!(after[250..255], before[251..]);
+        assert_eq!(after[255], before[250]);
+        rotate_palette(250..256, 1);
+    }
+
+    #[test]
+    fn multi_ramp_holds_colors_outside_of_the_stops() {
+        let ramp = make_multi_ramp(&[(0.25, (255, 0, 0)), (0.75, (0, 0, 255))], 5, 1.).unwrap();
+        assert_eq!(ramp[0], (63, 0, 0));
+        assert_eq!(ramp[1], (63, 0, 0));
+        assert_eq!(ramp[2], (32, 0, 32));
+        assert_eq!(ramp[3], (0, 0, 63));
+        assert_eq!(ramp[4], (0, 0, 63));
+    }
+
+    #[test]
+    fn multi_ramp_with_repeated_stops() {
+        // a sharp edge in the middle
+        let stops = [
+            (0., (0, 0, 0)),
+            (0.5, (0, 0, 0)),
+            (0.5, (255, 255, 255)),
+            (1., (255, 255, 255)),
+        ];
+        let ramp = make_multi_ramp(&stops, 5, 2.2).unwrap();
+        assert_eq!(ramp[..2], [(0, 0, 0); 2]);
+        assert_eq!(ramp[2..], [(63, 63, 63); 3]);
+    }
+
+    #[test]
+    fn multi_ramp_errors() {
+        assert_eq!(make_multi_ramp(&[], 8, 1.), Err(RampError::NoStops));
+        assert_eq!(
+            make_multi_ramp(&[(0., (0, 0, 0)), (1., (0, 0, 0)), (0.5, (0, 0, 0))], 8, 1.),
+            Err(RampError::UnorderedStops { index: 2 })
+        );
+        assert_eq!(
+            make_multi_ramp(&[(0., (0, 0, 0)), (f32::NAN, (0, 0, 0))], 8, 1.),
+            Err(RampError::UnorderedStops { index: 1 })
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Module for simple particle systems,
+//! such as explosions and sparkles.
+//!
+//! A [`ParticleSystem`] keeps a fixed number of particles,
+//! each a single pixel moving on its own
+//! and fading through a range of palette colors over its lifetime.
+//! Particles which die are reused by later bursts,
+//! so updating and drawing a system does not allocate.
+
+use std::f32::consts::TAU;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::geometry::Point;
+use crate::video::put_pixels;
+
+/// The parameters of the particles spawned in a burst,
+/// see [`ParticleSystem::spawn_burst`].
+///
+/// Each particle picks its own speed, direction and lifetime
+/// at random within the ranges given.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BurstParams {
+    /// The slowest and fastest speeds, in pixels per second.
+    pub speed: (f32, f32),
+    /// The range of directions, in radians,
+    /// clockwise from the positive X axis
+    /// (which points to the right, with Y pointing down).
+    pub direction: (f32, f32),
+    /// The shortest and longest lifetimes.
+    pub lifetime: (Duration, Duration),
+    /// The palette indices of the color of a particle
+    /// when it is spawned and when it dies.
+    ///
+    /// The color goes through the indices in between over its lifetime,
+    /// so this is usually a ramp of the palette (see [`make_ramp`](super::make_ramp)).
+    /// The first index may be higher than the last.
+    pub colors: (u8, u8),
+    /// The acceleration downwards, in pixels per second squared.
+    pub gravity: f32,
+}
+
+impl Default for BurstParams {
+    fn default() -> Self {
+        BurstParams::new()
+    }
+}
+
+impl BurstParams {
+    /// Creates the parameters of a burst in all directions,
+    /// between 20 and 80 pixels per second,
+    /// lasting half a second to a second,
+    /// in colors 15 to 0, without gravity.
+    #[inline]
+    pub const fn new() -> Self {
+        BurstParams {
+            speed: (20., 80.),
+            direction: (0., TAU),
+            lifetime: (Duration::from_millis(500), Duration::from_secs(1)),
+            colors: (15, 0),
+            gravity: 0.,
+        }
+    }
+
+    /// Sets the range of speeds, in pixels per second.
+    #[inline]
+    pub const fn with_speed(self, min: f32, max: f32) -> Self {
+        BurstParams {
+            speed: (min, max),
+            ..self
+        }
+    }
+
+    /// Sets the range of directions, in radians.
+    #[inline]
+    pub const fn with_direction(self, min: f32, max: f32) -> Self {
+        BurstParams {
+            direction: (min, max),
+            ..self
+        }
+    }
+
+    /// Sets the range of lifetimes.
+    #[inline]
+    pub const fn with_lifetime(self, min: Duration, max: Duration) -> Self {
+        BurstParams {
+            lifetime: (min, max),
+            ..self
+        }
+    }
+
+    /// Sets the palette indices at the start and at the end of the lifetime.
+    #[inline]
+    pub const fn with_colors(self, start: u8, end: u8) -> Self {
+        BurstParams {
+            colors: (start, end),
+            ..self
+        }
+    }
+
+    /// Sets the acceleration downwards, in pixels per second squared.
+    #[inline]
+    pub const fn with_gravity(self, gravity: f32) -> Self {
+        BurstParams { gravity, ..self }
+    }
+}
+
+/// A particle in a system.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    gravity: f32,
+    /// The time lived so far, in seconds
+    age: f32,
+    /// The time to live, in seconds
+    lifetime: f32,
+    colors: (u8, u8),
+}
+
+impl Particle {
+    /// Gets the palette index of the particle at its current age.
+    fn color(&self) -> u8 {
+        let (start, end) = (self.colors.0 as f32, self.colors.1 as f32);
+        let t = (self.age / self.lifetime).min(1.);
+        (start + (end - start) * t).round() as u8
+    }
+}
+
+/// A pool of particles with a fixed capacity.
+///
+/// Particles are spawned in bursts from a point,
+/// moved with [`update`](Self::update)
+/// and drawn as single pixels with [`draw`](Self::draw).
+/// A burst which does not fit in the pool only spawns as many
+/// particles as there is room for,
+/// and the room of particles which die is reused by later bursts.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// use dos_like::video::particles::{BurstParams, ParticleSystem};
+/// use std::time::Duration;
+///
+/// set_video_mode(VideoMode::Graphics320x200);
+/// set_double_buffer(true);
+/// let mut sparks = ParticleSystem::new(2000);
+/// let params = BurstParams::new().with_colors(47, 32).with_gravity(60.);
+/// while !shutting_down() {
+///     wait_vbl();
+///     if key_state(KeyCode::KEY_SPACE) {
+///         sparks.spawn_burst((160, 100), 50, &params);
+///     }
+///     sparks.update(Duration::from_secs(1) / 70);
+///     clear_screen();
+///     sparks.draw();
+///     swap_buffers();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParticleSystem {
+    /// The pool, with the live particles first
+    particles: Box<[Particle]>,
+    /// The number of live particles
+    live: usize,
+    /// The state of the random number generator
+    rng: u32,
+}
+
+impl ParticleSystem {
+    /// Creates a system with room for the given number of particles,
+    /// none of them alive.
+    pub fn new(capacity: usize) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0)
+            ^ 0x9E37_79B9;
+        ParticleSystem {
+            particles: vec![Particle::default(); capacity].into_boxed_slice(),
+            live: 0,
+            rng: seed | 1,
+        }
+    }
+
+    /// Gets the maximum number of particles alive at once.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.particles.len()
+    }
+
+    /// Gets the number of particles alive.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    /// Checks whether no particles are alive.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    /// Kills all particles.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.live = 0;
+    }
+
+    /// Gets a random number between 0 and 1.
+    fn random(&mut self) -> f32 {
+        // xorshift
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Gets a random number in the given range.
+    fn random_in(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.random()
+    }
+
+    /// Spawns up to `count` particles at the given point,
+    /// in pixel coordinates,
+    /// returning the number of particles spawned,
+    /// which is less than `count` if the pool is full.
+    pub fn spawn_burst(
+        &mut self,
+        origin: impl Into<Point>,
+        count: usize,
+        params: &BurstParams,
+    ) -> usize {
+        let origin = origin.into();
+        let count = count.min(self.capacity() - self.live);
+        let lifetime = (
+            params.lifetime.0.as_secs_f32(),
+            params.lifetime.1.as_secs_f32(),
+        );
+        for _ in 0..count {
+            let speed = self.random_in(params.speed);
+            let direction = self.random_in(params.direction);
+            let lifetime = self.random_in(lifetime).max(f32::EPSILON);
+            self.particles[self.live] = Particle {
+                // the center of the pixel
+                x: origin.x as f32 + 0.5,
+                y: origin.y as f32 + 0.5,
+                vx: speed * direction.cos(),
+                vy: speed * direction.sin(),
+                gravity: params.gravity,
+                age: 0.,
+                lifetime,
+                colors: params.colors,
+            };
+            self.live += 1;
+        }
+        count
+    }
+
+    /// Moves the particles by the time passed since the last update,
+    /// killing those which reach the end of their lifetime.
+    pub fn update(&mut self, delta: Duration) {
+        let dt = delta.as_secs_f32();
+        let mut i = 0;
+        while i < self.live {
+            let particle = &mut self.particles[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                // the last live particle takes its place
+                self.live -= 1;
+                self.particles.swap(i, self.live);
+                continue;
+            }
+            particle.vy += particle.gravity * dt;
+            particle.x += particle.vx * dt;
+            particle.y += particle.vy * dt;
+            i += 1;
+        }
+    }
+
+    /// Draws the live particles as single pixels,
+    /// all in one pass with [`put_pixels`]
+    /// into the draw target or the screen buffer
+    /// (the back buffer when double buffering is enabled).
+    ///
+    /// The draw offset and clip rectangle in effect apply,
+    /// and particles outside of them are skipped.
+    /// Particles spawned later are drawn over earlier ones.
+    ///
+    /// Nothing is drawn unless drawing to a buffer or in graphics mode.
+    pub fn draw(&self) {
+        put_pixels(self.particles[..self.live].iter().map(|particle| {
+            (
+                particle.x.floor() as i32,
+                particle.y.floor() as i32,
+                particle.color(),
+            )
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_fill_the_pool_and_recycle() {
+        let params = BurstParams::new()
+            .with_lifetime(Duration::from_millis(100), Duration::from_millis(200))
+            .with_colors(40, 32);
+        let mut system = ParticleSystem::new(50);
+        assert!(system.is_empty());
+        assert_eq!(system.spawn_burst((10, 10), 30, &params), 30);
+        assert_eq!(system.spawn_burst((10, 10), 30, &params), 20);
+        assert_eq!(system.len(), 50);
+        assert_eq!(system.spawn_burst((10, 10), 1, &params), 0);
+
+        // nobody dies before the shortest lifetime
+        system.update(Duration::from_millis(50));
+        assert_eq!(system.len(), 50);
+        // everybody dies after the longest one
+        system.update(Duration::from_millis(200));
+        assert!(system.is_empty());
+
+        // the slots are reused
+        let pool = system.particles.as_ptr();
+        assert_eq!(system.spawn_burst((0, 0), 50, &params), 50);
+        assert_eq!(system.particles.as_ptr(), pool);
+        assert_eq!(system.capacity(), 50);
+    }
+
+    #[test]
+    fn particles_move_and_fade() {
+        let params = BurstParams::new()
+            .with_speed(10., 10.)
+            .with_direction(0., 0.)
+            .with_lifetime(Duration::from_secs(1), Duration::from_secs(1))
+            .with_colors(20, 10);
+        let mut system = ParticleSystem::new(4);
+        system.spawn_burst((5, 7), 1, &params);
+        let particle = system.particles[0];
+        assert_eq!((particle.x, particle.y), (5.5, 7.5));
+        assert_eq!(particle.color(), 20);
+
+        system.update(Duration::from_millis(500));
+        let particle = system.particles[0];
+        assert!((particle.x - 10.5).abs() < 1e-4);
+        assert_eq!(particle.y, 7.5);
+        assert!((10..20).contains(&particle.color()));
+    }
+}
@@ -0,0 +1,828 @@
+//! Rasterization of shapes in Rust,
+//! to the screen or to off-screen surfaces,
+//! following the same pixel selection as the engine,
+//! but with wide arithmetic and clipping
+//! so that large shapes crossing the edges of the screen are drawn correctly.
+
+use std::{cell::Cell, os::raw::c_int};
+
+use crate::video::{draw_bounds, screen_width};
+
+/// The region which pixels can be drawn to,
+/// either on the screen or in a pixel buffer,
+/// along with the color to draw with.
+pub(crate) struct Clip<'a> {
+    /// The pixel buffer, or `None` to draw to the screen
+    pixels: Option<&'a [Cell<u8>]>,
+    /// The width of a row of pixels
+    width: i64,
+    /// The edges of the region, the right and bottom ones exclusive
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+    color: u8,
+}
+
+impl Clip<'static> {
+    /// Targets the screen, through the engine,
+    /// confined to the clip rectangle if there is one.
+    pub(crate) fn screen(color: u8) -> Self {
+        let bounds = draw_bounds();
+        Clip {
+            pixels: None,
+            width: screen_width() as i64,
+            left: bounds.left() as i64,
+            top: bounds.top() as i64,
+            right: bounds.right(),
+            bottom: bounds.bottom(),
+            color,
+        }
+    }
+}
+
+impl<'a> Clip<'a> {
+    /// Targets a buffer of `width` by `height` pixels.
+    pub(crate) fn pixels(pixels: &'a mut [u8], width: u16, height: u16, color: u8) -> Self {
+        debug_assert_eq!(pixels.len(), width as usize * height as usize);
+        Clip {
+            pixels: Some(Cell::from_mut(pixels).as_slice_of_cells()),
+            width: width as i64,
+            left: 0,
+            top: 0,
+            right: width as i64,
+            bottom: height as i64,
+            color,
+        }
+    }
+
+    /// Draws a single pixel, if inside the region.
+    #[inline]
+    fn plot(&self, x: i64, y: i64) {
+        if x >= self.left && y >= self.top && x < self.right && y < self.bottom {
+            match self.pixels {
+                Some(pixels) => pixels[(y * self.width + x) as usize].set(self.color),
+                None => unsafe {
+                    dos_like_sys::putpixel(x as c_int, y as c_int, self.color as c_int);
+                },
+            }
+        }
+    }
+
+    /// Draws a horizontal span of `len` pixels,
+    /// clipped to the region.
+    #[inline]
+    fn span(&self, x: i64, y: i64, len: i64) {
+        if y < self.top || y >= self.bottom {
+            return;
+        }
+        let x1 = x.max(self.left);
+        let x2 = (x + len).min(self.right);
+        if x2 > x1 {
+            match self.pixels {
+                Some(pixels) => {
+                    let row = (y * self.width) as usize;
+                    for pixel in &pixels[row + x1 as usize..row + x2 as usize] {
+                        pixel.set(self.color);
+                    }
+                }
+                None => unsafe {
+                    dos_like_sys::hline(
+                        x1 as c_int,
+                        y as c_int,
+                        (x2 - x1) as c_int,
+                        self.color as c_int,
+                    );
+                },
+            }
+        }
+    }
+}
+
+/// Draws a line between two points, both inclusive,
+/// selecting the same pixels as the engine.
+pub(crate) fn line(clip: &Clip, x1: i32, y1: i32, x2: i32, y2: i32) {
+    let (x1, y1, x2, y2) = (x1 as i64, y1 as i64, x2 as i64, y2 as i64);
+    let dx = (x2 - x1).abs();
+    let sx = if x1 < x2 { 1 } else { -1 };
+    let dy = (y2 - y1).abs();
+    let sy = if y1 < y2 { 1 } else { -1 };
+    let mut err = if dx > dy { dx } else { -dy } / 2;
+    let (mut x, mut y) = (x1, y1);
+    while x != x2 || y != y2 {
+        clip.plot(x, y);
+        let e2 = err;
+        if e2 > -dx {
+            err -= dy;
+            x += sx;
+        }
+        if e2 < dy {
+            err += dx;
+            y += sy;
+        }
+    }
+    clip.plot(x, y);
+}
+
+/// Snaps a subpixel coordinate to the nearest pixel,
+/// where integer coordinates are pixel centers
+/// and halfway cases go towards positive infinity,
+/// so that a coordinate moving at a constant speed
+/// moves to the next pixel at regular intervals.
+#[inline]
+pub(crate) fn snap(v: f64) -> i64 {
+    (v + 0.5).floor() as i64
+}
+
+/// Draws the pixel nearest to a subpixel position.
+pub(crate) fn put_pixel_f(clip: &Clip, x: f32, y: f32) {
+    clip.plot(snap(x as f64), snap(y as f64));
+}
+
+/// Draws a line between two subpixel positions, both inclusive.
+///
+/// One pixel is drawn at each pixel center along the major axis,
+/// at the exact position of the line on the minor axis snapped to a pixel,
+/// so that moving the line by a fraction of a pixel
+/// moves all of its pixels consistently.
+pub(crate) fn line_f(clip: &Clip, x1: f32, y1: f32, x2: f32, y2: f32) {
+    let (x1, y1, x2, y2) = (x1 as f64, y1 as f64, x2 as f64, y2 as f64);
+    let x_major = (x2 - x1).abs() >= (y2 - y1).abs();
+    // work along the major axis as `a`, with `b` as the minor axis
+    let (a1, b1, a2, b2) = if x_major {
+        (x1, y1, x2, y2)
+    } else {
+        (y1, x1, y2, x2)
+    };
+    let (a1, b1, a2, b2) = if a1 <= a2 {
+        (a1, b1, a2, b2)
+    } else {
+        (a2, b2, a1, b1)
+    };
+    let slope = if a2 > a1 { (b2 - b1) / (a2 - a1) } else { 0. };
+    let start = snap(a1);
+    let end = snap(a2);
+    for a in start..=end {
+        // stay within the segment at the pixels of the endpoints
+        let t = (a as f64).clamp(a1, a2);
+        let b = snap(b1 + (t - a1) * slope);
+        if x_major {
+            clip.plot(a, b);
+        } else {
+            clip.plot(b, a);
+        }
+    }
+}
+
+/// Draws the outline of a circle around a subpixel center
+/// with a subpixel radius.
+///
+/// Each octant is drawn with one pixel per pixel center
+/// along its major axis,
+/// at the exact position of the circle snapped to a pixel.
+pub(crate) fn circle_f(clip: &Clip, x: f32, y: f32, r: f32) {
+    let (x, y, r) = (x as f64, y as f64, r.max(0.) as f64);
+    let reach = r * std::f64::consts::FRAC_1_SQRT_2;
+    // the top and bottom arcs, column by column
+    for i in snap(x - reach)..=snap(x + reach) {
+        // stay within the octant at the pixels of the diagonals
+        let d = (i as f64 - x).clamp(-reach, reach);
+        let h = (r * r - d * d).sqrt();
+        clip.plot(i, snap(y - h));
+        clip.plot(i, snap(y + h));
+    }
+    // the left and right arcs, row by row
+    for j in snap(y - reach)..=snap(y + reach) {
+        // stay within the octant at the pixels of the diagonals
+        let d = (j as f64 - y).clamp(-reach, reach);
+        let w = (r * r - d * d).sqrt();
+        clip.plot(snap(x - w), j);
+        clip.plot(snap(x + w), j);
+    }
+}
+
+/// Draws a single pixel.
+pub(crate) fn put_pixel(clip: &Clip, x: i32, y: i32) {
+    clip.plot(x as i64, y as i64);
+}
+
+/// Fills a rectangle of the given size.
+pub(crate) fn bar(clip: &Clip, x: i32, y: i32, width: u16, height: u16) {
+    let (x, y) = (x as i64, y as i64);
+    for y in y.max(clip.top)..(y + height as i64).min(clip.bottom) {
+        clip.span(x, y, width as i64);
+    }
+}
+
+/// Draws the outline of a rectangle as the engine does,
+/// with the corner already offset on the screen:
+/// the bottom edge is drawn `height` rows below the top one,
+/// and the sides span both edges.
+pub(crate) fn rectangle(clip: &Clip, x: i32, y: i32, width: u16, height: u16) {
+    let (x, y, width, height) = (x as i64, y as i64, width as i64, height as i64);
+    clip.span(x, y, width);
+    clip.span(x, y + height, width);
+    for y in y.max(clip.top)..(y + height + 1).min(clip.bottom) {
+        clip.plot(x, y);
+        clip.plot(x + width - 1, y);
+    }
+}
+
+/// Draws the outline of a circle,
+/// with the center already offset on the screen.
+pub(crate) fn circle(clip: &Clip, x: i32, y: i32, r: u16) {
+    let (x, y, r) = (x as i64, y as i64, r as i64);
+    let mut f = 1 - r;
+    let mut dx = 0;
+    let mut dy = -2 * r;
+    let mut ix = 0;
+    let mut iy = r;
+
+    clip.plot(x, y + r);
+    clip.plot(x, y - r);
+    clip.plot(x + r, y);
+    clip.plot(x - r, y);
+
+    while ix < iy {
+        if f >= 0 {
+            iy -= 1;
+            dy += 2;
+            f += dy;
+        }
+        ix += 1;
+        dx += 2;
+        f += dx + 1;
+
+        clip.plot(x + ix, y + iy);
+        clip.plot(x - ix, y + iy);
+        clip.plot(x + ix, y - iy);
+        clip.plot(x - ix, y - iy);
+        clip.plot(x + iy, y + ix);
+        clip.plot(x - iy, y + ix);
+        clip.plot(x + iy, y - ix);
+        clip.plot(x - iy, y - ix);
+    }
+}
+
+/// Draws a filled circle,
+/// with the center already offset on the screen.
+pub(crate) fn fill_circle(clip: &Clip, x: i32, y: i32, r: u16) {
+    let (x, y, r) = (x as i64, y as i64, r as i64);
+    let mut f = 1 - r;
+    let mut dx = 0;
+    let mut dy = -2 * r;
+    let mut ix = 0;
+    let mut iy = r;
+
+    while ix <= iy {
+        clip.span(x - iy, y + ix, 2 * iy);
+        clip.span(x - iy, y - ix, 2 * iy);
+        if f >= 0 {
+            clip.span(x - ix, y + iy, 2 * ix);
+            clip.span(x - ix, y - iy, 2 * ix);
+
+            iy -= 1;
+            dy += 2;
+            f += dy;
+        }
+        ix += 1;
+        dx += 2;
+        f += dx + 1;
+    }
+}
+
+/// Draws the outline of an ellipse,
+/// with the center already offset on the screen.
+pub(crate) fn ellipse(clip: &Clip, x: i32, y: i32, rx: u16, ry: u16) {
+    let (x, y, rx, ry) = (x as i64, y as i64, rx as i64, ry as i64);
+    let plot4 = |wx: i64, wy: i64| {
+        clip.plot(x + wx, y - wy);
+        clip.plot(x - wx, y - wy);
+        clip.plot(x + wx, y + wy);
+        clip.plot(x - wx, y + wy);
+    };
+    let asq = rx * rx;
+    let bsq = ry * ry;
+
+    clip.plot(x, y + ry);
+    clip.plot(x, y - ry);
+
+    let mut wx = 0;
+    let mut wy = ry;
+    let mut xa = 0;
+    let mut ya = asq * 2 * ry;
+    let mut thresh = asq / 4 - asq * ry;
+    loop {
+        thresh += xa + bsq;
+        if thresh >= 0 {
+            ya -= asq * 2;
+            thresh -= ya;
+            wy -= 1;
+        }
+        xa += bsq * 2;
+        wx += 1;
+        if xa >= ya {
+            break;
+        }
+        plot4(wx, wy);
+    }
+
+    clip.plot(x + rx, y);
+    clip.plot(x - rx, y);
+
+    let mut wx = rx;
+    let mut wy = 0;
+    let mut xa = bsq * 2 * rx;
+    let mut ya = 0;
+    let mut thresh = bsq / 4 - bsq * rx;
+    loop {
+        thresh += ya + asq;
+        if thresh >= 0 {
+            xa -= bsq * 2;
+            thresh -= xa;
+            wx -= 1;
+        }
+        ya += asq * 2;
+        wy += 1;
+        if ya > xa {
+            break;
+        }
+        plot4(wx, wy);
+    }
+}
+
+/// Draws a filled ellipse,
+/// with the center already offset on the screen.
+pub(crate) fn fill_ellipse(clip: &Clip, x: i32, y: i32, rx: u16, ry: u16) {
+    let (x, y, rx, ry) = (x as i64, y as i64, rx as i64, ry as i64);
+    let span2 = |wx: i64, wy: i64| {
+        clip.span(x - wx, y - wy, wx * 2);
+        clip.span(x - wx, y + wy, wx * 2);
+    };
+    let asq = rx * rx;
+    let bsq = ry * ry;
+
+    let mut wx = 0;
+    let mut wy = ry;
+    let mut xa = 0;
+    let mut ya = asq * 2 * ry;
+    let mut thresh = asq / 4 - asq * ry;
+    loop {
+        thresh += xa + bsq;
+        if thresh >= 0 {
+            ya -= asq * 2;
+            thresh -= ya;
+            span2(wx, wy);
+            wy -= 1;
+        }
+        xa += bsq * 2;
+        wx += 1;
+        if xa >= ya {
+            break;
+        }
+    }
+
+    clip.span(x - rx, y, rx * 2);
+
+    let mut wx = rx;
+    let mut wy = 0;
+    let mut xa = bsq * 2 * rx;
+    let mut ya = 0;
+    let mut thresh = bsq / 4 - bsq * rx;
+    loop {
+        thresh += ya + asq;
+        if thresh >= 0 {
+            xa -= bsq * 2;
+            thresh -= xa;
+            wx -= 1;
+        }
+        ya += asq * 2;
+        wy += 1;
+        if ya > xa {
+            break;
+        }
+        span2(wx, wy);
+    }
+}
+
+/// Draws a filled polygon
+/// from a flat list of XY coordinates already offset,
+/// with no limit on the number of points.
+pub(crate) fn fill_poly(clip: &Clip, points: &[i32]) {
+    let points: Vec<(i64, i64)> = points
+        .chunks_exact(2)
+        .map(|p| (p[0] as i64, p[1] as i64))
+        .collect();
+    let Some(min_y) = points.iter().map(|p| p.1).min() else {
+        return;
+    };
+    let max_y = points.iter().map(|p| p.1).max().unwrap_or(min_y);
+
+    let mut nodes = Vec::new();
+    for y in min_y.max(clip.top)..max_y.min(clip.bottom) {
+        // find the intersections with each edge
+        nodes.clear();
+        let mut j = points.len() - 1;
+        for (i, &(xi, yi)) in points.iter().enumerate() {
+            let (xj, yj) = points[j];
+            if (yi <= y && yj > y) || (yj <= y && yi > y) {
+                // the product may not fit in 64 bits, but the quotient does
+                let dx = (y - yi) as i128 * (xj - xi) as i128 / (yj - yi) as i128;
+                nodes.push(xi + dx as i64);
+            }
+            j = i;
+        }
+        nodes.sort_unstable();
+        for pair in nodes.chunks_exact(2) {
+            clip.span(pair[0], y, pair[1] - pair[0]);
+        }
+    }
+}
+
+/// Draws a poly-line
+/// from a flat list of XY coordinates already offset on the screen,
+/// with a line between each point and the next.
+/// A trailing odd coordinate is ignored.
+pub(crate) fn draw_poly(clip: &Clip, points: &[i32]) {
+    let points = &points[..points.len() / 2 * 2];
+    if let [x, y] = *points {
+        put_pixel(clip, x, y);
+    }
+    for pair in points.windows(4).step_by(2) {
+        line(clip, pair[0], pair[1], pair[2], pair[3]);
+    }
+}
+
+/// Fills the region of pixels around `(x, y)`
+/// which satisfy `fillable`,
+/// connected horizontally and vertically.
+///
+/// Pixels of the fill color must not be fillable.
+fn fill_region(
+    pixels: &mut [u8],
+    width: u16,
+    height: u16,
+    x: i32,
+    y: i32,
+    color: u8,
+    fillable: impl Fn(u8) -> bool,
+) {
+    let (width, height) = (width as usize, height as usize);
+    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+        return;
+    }
+    debug_assert!(!fillable(color));
+    let mut stack = vec![(x as usize, y as usize)];
+    while let Some((x, y)) = stack.pop() {
+        let row = &mut pixels[y * width..(y + 1) * width];
+        if !fillable(row[x]) {
+            continue;
+        }
+        let mut left = x;
+        while left > 0 && fillable(row[left - 1]) {
+            left -= 1;
+        }
+        let mut right = x;
+        while right + 1 < width && fillable(row[right + 1]) {
+            right += 1;
+        }
+        row[left..=right].fill(color);
+
+        // look for the runs to fill above and below the span
+        for y in [y.wrapping_sub(1), y + 1] {
+            if y >= height {
+                continue;
+            }
+            let row = &pixels[y * width..(y + 1) * width];
+            let mut in_run = false;
+            for (x, &p) in row.iter().enumerate().take(right + 1).skip(left) {
+                let fill = fillable(p);
+                if fill && !in_run {
+                    stack.push((x, y));
+                }
+                in_run = fill;
+            }
+        }
+    }
+}
+
+/// Fills the region of pixels of the same color as the one at `(x, y)`,
+/// connected horizontally and vertically,
+/// with the given color.
+pub(crate) fn flood_fill(pixels: &mut [u8], width: u16, height: u16, x: i32, y: i32, color: u8) {
+    if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+        return;
+    }
+    let target = pixels[y as usize * width as usize + x as usize];
+    if target != color {
+        fill_region(pixels, width, height, x, y, color, |p| p == target);
+    }
+}
+
+/// Fills the region of pixels around `(x, y)`
+/// up to the pixels of the boundary color or the fill color,
+/// connected horizontally and vertically,
+/// with the given color.
+pub(crate) fn boundary_fill(
+    pixels: &mut [u8],
+    width: u16,
+    height: u16,
+    x: i32,
+    y: i32,
+    boundary: u8,
+    color: u8,
+) {
+    fill_region(pixels, width, height, x, y, color, |p| {
+        p != boundary && p != color
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A small xorshift generator,
+    /// so that the fuzz tests are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// Generates a value in `-range..range`.
+        fn coord(&mut self, range: i32) -> i32 {
+            (self.next() % (2 * range as u64)) as i32 - range
+        }
+    }
+
+    const WIDTH: u16 = 24;
+    const HEIGHT: u16 = 16;
+
+    #[test]
+    fn fill_poly_fuzz() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        for _ in 0..2000 {
+            // odd lengths included, the last coordinate is ignored
+            let len = (rng.next() % 15) as usize;
+            let points: Vec<i32> = (0..len).map(|_| rng.coord(40)).collect();
+            pixels.fill(0);
+            fill_poly(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1), &points);
+
+            // nothing is drawn outside of the bounding box
+            let xs = points.chunks_exact(2).map(|p| p[0]);
+            let ys = points.chunks_exact(2).map(|p| p[1]);
+            let (min_x, max_x) = (xs.clone().min(), xs.max());
+            let (min_y, max_y) = (ys.clone().min(), ys.max());
+            for (i, &p) in pixels.iter().enumerate() {
+                if p == 0 {
+                    continue;
+                }
+                let (x, y) = ((i % WIDTH as usize) as i32, (i / WIDTH as usize) as i32);
+                assert!(
+                    Some(x) >= min_x && Some(x) <= max_x && Some(y) >= min_y && Some(y) <= max_y,
+                    "pixel ({}, {}) outside of {:?}",
+                    x,
+                    y,
+                    points
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fill_poly_extreme_coordinates() {
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        {
+            let clip = Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1);
+            fill_poly(
+                &clip,
+                &[i32::MIN, i32::MIN, i32::MAX, 0, i32::MIN, i32::MAX],
+            );
+            fill_poly(
+                &clip,
+                &[i32::MAX, i32::MIN, i32::MIN, i32::MAX, i32::MAX, i32::MAX],
+            );
+            fill_poly(&clip, &[i32::MIN, 5, i32::MAX, 5, 0, 5]);
+        }
+        assert!(pixels.iter().any(|&p| p != 0));
+    }
+
+    #[test]
+    fn poly_edge_cases() {
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        {
+            let clip = Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1);
+            fill_poly(&clip, &[]);
+            fill_poly(&clip, &[3]);
+            fill_poly(&clip, &[3, 4]);
+            fill_poly(&clip, &[3, 4, 9]);
+        }
+        assert!(pixels.iter().all(|&p| p == 0));
+
+        // a trailing coordinate is ignored
+        let mut odd = pixels;
+        fill_poly(
+            &Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1),
+            &[2, 2, 9, 2, 5, 8],
+        );
+        fill_poly(
+            &Clip::pixels(&mut odd, WIDTH, HEIGHT, 1),
+            &[2, 2, 9, 2, 5, 8, 20],
+        );
+        assert!(pixels.iter().any(|&p| p != 0));
+        assert_eq!(pixels, odd);
+    }
+
+    #[test]
+    fn draw_poly_fuzz() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        for _ in 0..2000 {
+            let len = (rng.next() % 15) as usize;
+            let points: Vec<i32> = (0..len).map(|_| rng.coord(40)).collect();
+            pixels.fill(0);
+            draw_poly(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1), &points);
+
+            // every vertex on the buffer is drawn
+            for p in points.chunks_exact(2) {
+                let (x, y) = (p[0], p[1]);
+                if (0..WIDTH as i32).contains(&x) && (0..HEIGHT as i32).contains(&y) {
+                    let i = y as usize * WIDTH as usize + x as usize;
+                    assert_eq!(pixels[i], 1, "vertex ({}, {}) of {:?}", x, y, points);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn draw_poly_edge_cases() {
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        {
+            let clip = Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1);
+            draw_poly(&clip, &[]);
+            draw_poly(&clip, &[3]);
+        }
+        assert!(pixels.iter().all(|&p| p == 0));
+
+        // a single point, with or without a trailing coordinate
+        draw_poly(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1), &[3, 4]);
+        draw_poly(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 2), &[5, 6, 7]);
+        assert_eq!(pixels[4 * WIDTH as usize + 3], 1);
+        assert_eq!(pixels[6 * WIDTH as usize + 5], 2);
+        assert_eq!(pixels.iter().filter(|&&p| p != 0).count(), 2);
+    }
+
+    /// Lists the drawn pixels of a buffer, row by row.
+    fn drawn(pixels: &[u8]) -> Vec<(i32, i32)> {
+        pixels
+            .iter()
+            .enumerate()
+            .filter(|(_, &p)| p != 0)
+            .map(|(i, _)| ((i % WIDTH as usize) as i32, (i / WIDTH as usize) as i32))
+            .collect()
+    }
+
+    #[test]
+    fn snap_rounds_halfway_up() {
+        assert_eq!(snap(0.), 0);
+        assert_eq!(snap(0.49), 0);
+        assert_eq!(snap(0.5), 1);
+        assert_eq!(snap(1.5), 2);
+        assert_eq!(snap(2.5), 3);
+        assert_eq!(snap(-0.5), 0);
+        assert_eq!(snap(-0.51), -1);
+        assert_eq!(snap(-1.5), -1);
+    }
+
+    #[test]
+    fn put_pixel_f_steps_regularly() {
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        let mut last = None;
+        let mut steps = Vec::new();
+        for frame in 0..40 {
+            pixels.fill(0);
+            put_pixel_f(
+                &Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1),
+                2. + frame as f32 * 0.25,
+                3.,
+            );
+            let points = drawn(&pixels);
+            assert_eq!(points.len(), 1);
+            if last.is_some_and(|p| p != points[0]) {
+                steps.push(frame);
+            }
+            last = Some(points[0]);
+        }
+        // a step every fourth frame, never every third or fifth
+        assert!(!steps.is_empty());
+        assert!(steps.windows(2).all(|w| w[1] - w[0] == 4), "{:?}", steps);
+    }
+
+    #[test]
+    fn line_f_one_pixel_per_major_step() {
+        let mut rng = Rng(0x0DDB_1A5E_5BAD_5EED);
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        for _ in 0..500 {
+            let mut coord = |range: f32| (rng.next() % 10_000) as f32 / 10_000. * range;
+            let (x1, y1) = (coord(WIDTH as f32 - 1.), coord(HEIGHT as f32 - 1.));
+            let (x2, y2) = (coord(WIDTH as f32 - 1.), coord(HEIGHT as f32 - 1.));
+            pixels.fill(0);
+            line_f(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1), x1, y1, x2, y2);
+            let points = drawn(&pixels);
+
+            // exactly one pixel for each pixel center along the major axis,
+            // from one endpoint to the other
+            let x_major = (x2 - x1).abs() >= (y2 - y1).abs();
+            let (a1, a2) = if x_major { (x1, x2) } else { (y1, y2) };
+            let (start, end) = (snap(a1.min(a2) as f64), snap(a1.max(a2) as f64));
+            let mut majors: Vec<i64> = points
+                .iter()
+                .map(|&(x, y)| if x_major { x as i64 } else { y as i64 })
+                .collect();
+            majors.sort_unstable();
+            majors.dedup();
+            let span = (end - start + 1) as usize;
+            assert_eq!(majors.first(), Some(&start));
+            assert_eq!(majors.last(), Some(&end));
+            assert_eq!(points.len(), span, "{:?}", (x1, y1, x2, y2));
+            assert_eq!(majors.len(), span, "{:?}", (x1, y1, x2, y2));
+        }
+    }
+
+    #[test]
+    fn line_f_moves_forward() {
+        // a shallow line slowly moving down
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        let mut previous: Option<Vec<(i32, i32)>> = None;
+        for frame in 0..64 {
+            let dy = frame as f32 / 16.;
+            pixels.fill(0);
+            line_f(
+                &Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1),
+                1.,
+                2. + dy,
+                22.,
+                7.3 + dy,
+            );
+            let mut points = drawn(&pixels);
+            points.sort_unstable();
+            assert_eq!(points.len(), 22);
+            if let Some(previous) = &previous {
+                // each column stays put or moves down by one pixel
+                for (&(x0, y0), &(x1, y1)) in previous.iter().zip(&points) {
+                    assert_eq!(x0, x1);
+                    assert!(y1 == y0 || y1 == y0 + 1, "column {} at frame {}", x1, frame);
+                }
+            }
+            previous = Some(points);
+        }
+    }
+
+    #[test]
+    fn circle_f_outline() {
+        let mut rng = Rng(0x1234_5678_9ABC_DEF1);
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        for _ in 0..500 {
+            let mut unit = || (rng.next() % 10_000) as f32 / 10_000.;
+            let (x, y) = (10. + unit() * 3., 6. + unit() * 3.);
+            let r = 1. + unit() * 5.;
+            pixels.fill(0);
+            circle_f(&Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1), x, y, r);
+            let points = drawn(&pixels);
+            assert!(!points.is_empty());
+
+            for &(px, py) in &points {
+                // every pixel is near the circle
+                let d = ((px as f32 - x).powi(2) + (py as f32 - y).powi(2)).sqrt();
+                assert!((d - r).abs() <= 1., "({}, {}) of {:?}", px, py, (x, y, r));
+                // and has two 8-connected neighbors, or more at the joints
+                let neighbors = points
+                    .iter()
+                    .filter(|&&(qx, qy)| {
+                        (qx, qy) != (px, py) && (qx - px).abs() <= 1 && (qy - py).abs() <= 1
+                    })
+                    .count();
+                assert!(neighbors >= 2, "({}, {}) of {:?}", px, py, (x, y, r));
+            }
+        }
+    }
+
+    #[test]
+    fn subpixel_clipped() {
+        let mut pixels = [0; WIDTH as usize * HEIGHT as usize];
+        {
+            let clip = Clip::pixels(&mut pixels, WIDTH, HEIGHT, 1);
+            put_pixel_f(&clip, -0.6, 3.);
+            put_pixel_f(&clip, WIDTH as f32 - 0.5, 3.);
+            put_pixel_f(&clip, f32::NAN, f32::INFINITY);
+            line_f(&clip, -100., -5., 100., -5.);
+            circle_f(&clip, 12., 8., 100.);
+        }
+        assert!(pixels.iter().all(|&p| p == 0));
+    }
+}
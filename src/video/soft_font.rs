@@ -0,0 +1,207 @@
+//! Fonts created from in-memory glyph bitmaps,
+//! which are rendered by this crate rather than by the engine.
+
+use std::{os::raw::c_int, sync::Mutex};
+
+use crate::video::{clip_bounds, get_color, offset_xy, Font};
+
+/// The first identifier given to fonts created from glyph bitmaps,
+/// chosen to be well beyond the identifiers given by the engine.
+const SOFT_FONT_ID_BASE: u32 = 0x0100_0000;
+
+/// The maximum glyph width and height supported, in pixels.
+const MAX_GLYPH_SIZE: u8 = 32;
+
+/// Error type for creating a font from glyph bitmaps.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum FontError {
+    /// The glyph size is not supported
+    /// (both width and height must be between 1 and 32)
+    InvalidGlyphSize {
+        /// The requested glyph width.
+        width: u8,
+        /// The requested glyph height.
+        height: u8,
+    },
+    /// The glyph data does not have the expected length
+    InvalidDataLength {
+        /// The expected length of the data in bytes.
+        expected: usize,
+        /// The actual length of the data in bytes.
+        actual: usize,
+    },
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FontError::InvalidGlyphSize { width, height } => {
+                write!(f, "Unsupported glyph size {}x{}", width, height)
+            }
+            FontError::InvalidDataLength { expected, actual } => write!(
+                f,
+                "Invalid glyph data length (expected {} bytes, got {})",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+/// A font created from glyph bitmaps.
+#[derive(Debug)]
+pub(crate) struct SoftFont {
+    pub(crate) width: u8,
+    pub(crate) height: u8,
+    pub(crate) data: Box<[u8]>,
+}
+
+impl SoftFont {
+    #[inline]
+    pub(crate) fn bytes_per_glyph(&self) -> usize {
+        (self.width as usize).div_ceil(8) * self.height as usize
+    }
+
+    #[inline]
+    pub(crate) fn glyph(&self, c: u8) -> &[u8] {
+        let len = self.bytes_per_glyph();
+        &self.data[c as usize * len..][..len]
+    }
+
+    /// Gets the width in pixels of a single line of text.
+    #[inline]
+    fn line_width(&self, line: &[u8]) -> i32 {
+        line.len() as i32 * self.width as i32
+    }
+
+    /// Draws a single line of text at the given position (with offset),
+    /// in the current color.
+    pub(crate) fn draw_line(
+        &self,
+        x: i32,
+        y: i32,
+        line: &[u8],
+        bold: bool,
+        italic: bool,
+        underline: bool,
+    ) {
+        let color = get_color() as c_int;
+        // the engine only clips to the screen
+        let bounds = clip_bounds();
+        let visible = |x: c_int, y: c_int| bounds.is_none_or(|b| b.contains((x, y)));
+        let row_bytes = (self.width as usize).div_ceil(8);
+        let height = self.height as i32;
+        for (i, c) in line.iter().enumerate() {
+            let gx = x + i as i32 * self.width as i32;
+            for (gy, row) in self.glyph(*c).chunks_exact(row_bytes).enumerate() {
+                // lean the upper rows to the right when in italic
+                let skew = if italic { (height - gy as i32) / 4 } else { 0 };
+                for px in 0..self.width as usize {
+                    if row[px / 8] & (0x80 >> (px % 8)) != 0 {
+                        let (sx, sy) = offset_xy(gx + px as i32 + skew, y + gy as i32);
+                        unsafe {
+                            if visible(sx, sy) {
+                                dos_like_sys::putpixel(sx, sy, color);
+                            }
+                            if bold && visible(sx + 1, sy) {
+                                dos_like_sys::putpixel(sx + 1, sy, color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if underline && !line.is_empty() {
+            let (sx, sy) = offset_xy(x, y + height - 1);
+            let (mut sx, mut ex) = (sx as i64, sx as i64 + self.line_width(line) as i64);
+            if let Some(b) = bounds {
+                if sy < b.top() || sy as i64 >= b.bottom() {
+                    return;
+                }
+                sx = sx.max(b.left() as i64);
+                ex = ex.min(b.right());
+            }
+            if ex > sx {
+                unsafe {
+                    dos_like_sys::hline(sx as c_int, sy, (ex - sx) as c_int, color);
+                }
+            }
+        }
+    }
+}
+
+/// The fonts created from glyph bitmaps so far.
+///
+/// Fonts are never uninstalled,
+/// just like the ones installed in the engine.
+static SOFT_FONTS: Mutex<Vec<&'static SoftFont>> = Mutex::new(Vec::new());
+
+/// Registers a new font from glyph bitmaps.
+pub(crate) fn install(width: u8, height: u8, glyphs: &[u8]) -> Result<Font, FontError> {
+    if !(1..=MAX_GLYPH_SIZE).contains(&width) || !(1..=MAX_GLYPH_SIZE).contains(&height) {
+        return Err(FontError::InvalidGlyphSize { width, height });
+    }
+    let expected = 256 * (width as usize).div_ceil(8) * height as usize;
+    if glyphs.len() != expected {
+        return Err(FontError::InvalidDataLength {
+            expected,
+            actual: glyphs.len(),
+        });
+    }
+
+    let font: &'static SoftFont = Box::leak(Box::new(SoftFont {
+        width,
+        height,
+        data: glyphs.into(),
+    }));
+    let mut fonts = SOFT_FONTS.lock().unwrap_or_else(|e| e.into_inner());
+    fonts.push(font);
+    let id = SOFT_FONT_ID_BASE + fonts.len() as u32;
+    Ok(Font::from_id(id).expect("font identifier should not be zero"))
+}
+
+/// Retrieves a font created from glyph bitmaps by its identifier,
+/// or `None` if the font is not one of them.
+pub(crate) fn get(font: Font) -> Option<&'static SoftFont> {
+    let index = font.0.get().checked_sub(SOFT_FONT_ID_BASE + 1)?;
+    let fonts = SOFT_FONTS.lock().unwrap_or_else(|e| e.into_inner());
+    fonts.get(index as usize).copied()
+}
+
+/// Draws text with a font created from glyph bitmaps.
+///
+/// If `width` is given,
+/// the text is wrapped around before going beyond that width,
+/// or centered within that width if `center` is also true.
+pub(crate) fn draw_text(
+    font: &SoftFont,
+    style: (bool, bool, bool),
+    x: i32,
+    y: i32,
+    text: &[u8],
+    width: Option<u16>,
+    center: bool,
+) {
+    let (bold, italic, underline) = style;
+    let max_chars = width
+        .map(|w| (w as usize / font.width as usize).max(1))
+        .unwrap_or(usize::MAX);
+
+    let mut line_y = y;
+    for line in text.split(|c| *c == b'\n') {
+        let chunks: Vec<&[u8]> = if center || width.is_none() {
+            vec![line]
+        } else {
+            line.chunks(max_chars).collect()
+        };
+        for chunk in chunks {
+            let line_x = match (center, width) {
+                (true, Some(w)) => x + (w as i32 - font.line_width(chunk)) / 2,
+                _ => x,
+            };
+            font.draw_line(line_x, line_y, chunk, bold, italic, underline);
+            line_y += font.height as i32;
+        }
+    }
+}
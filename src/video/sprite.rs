@@ -0,0 +1,251 @@
+//! Sprite sheets: images containing a grid of frames.
+
+use crate::video::{blit, mask_blit, Image, Rect};
+
+/// Options for drawing a frame of a sprite sheet.
+#[derive(Debug, Default, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct SpriteDrawOptions {
+    /// The color to skip when drawing, if any.
+    pub color_key: Option<u8>,
+    /// Whether to mirror the frame horizontally.
+    pub flip_x: bool,
+    /// Whether to mirror the frame vertically.
+    pub flip_y: bool,
+}
+
+impl SpriteDrawOptions {
+    /// Creates options for drawing a frame as is.
+    #[inline]
+    pub const fn new() -> Self {
+        SpriteDrawOptions {
+            color_key: None,
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+
+    /// Skips the pixels of the given color when drawing.
+    #[inline]
+    pub const fn with_color_key(self, color: u8) -> Self {
+        SpriteDrawOptions {
+            color_key: Some(color),
+            ..self
+        }
+    }
+
+    /// Mirrors the frame horizontally.
+    #[inline]
+    pub const fn with_flip_x(self, flip_x: bool) -> Self {
+        SpriteDrawOptions { flip_x, ..self }
+    }
+
+    /// Mirrors the frame vertically.
+    #[inline]
+    pub const fn with_flip_y(self, flip_y: bool) -> Self {
+        SpriteDrawOptions { flip_y, ..self }
+    }
+}
+
+/// An image containing a grid of equally sized frames,
+/// addressed by index in row-major order.
+///
+/// The grid starts `margin` pixels away from the top left corner of the image,
+/// with `spacing` pixels between adjacent frames.
+/// If the dimensions of the image do not fit an exact number of frames,
+/// the partial row and column at the right and bottom edges are ignored.
+#[derive(Debug)]
+pub struct SpriteSheet {
+    image: Image,
+    frame_width: u32,
+    frame_height: u32,
+    margin: u32,
+    spacing: u32,
+    columns: u32,
+    rows: u32,
+}
+
+impl SpriteSheet {
+    /// Creates a sprite sheet from an image
+    /// and the layout of its frames.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the frame width or height is zero,
+    /// since this is likely a bug.
+    pub fn new(image: Image, frame_w: u32, frame_h: u32, margin: u32, spacing: u32) -> Self {
+        if frame_w == 0 || frame_h == 0 {
+            panic!("SpriteSheet: invalid frame size {}x{}", frame_w, frame_h);
+        }
+        let fit = |len: u32, frame: u32| {
+            // n frames take n * frame + (n - 1) * spacing pixels
+            (len.saturating_sub(margin * 2) + spacing) / (frame + spacing)
+        };
+        let columns = fit(image.width(), frame_w);
+        let rows = fit(image.height(), frame_h);
+        SpriteSheet {
+            image,
+            frame_width: frame_w,
+            frame_height: frame_h,
+            margin,
+            spacing,
+            columns,
+            rows,
+        }
+    }
+
+    /// Gets the underlying image.
+    #[inline]
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    /// Discards the frame layout,
+    /// keeping only the image.
+    #[inline]
+    pub fn into_image(self) -> Image {
+        self.image
+    }
+
+    /// Gets the width of each frame.
+    #[inline]
+    pub fn frame_width(&self) -> u32 {
+        self.frame_width
+    }
+
+    /// Gets the height of each frame.
+    #[inline]
+    pub fn frame_height(&self) -> u32 {
+        self.frame_height
+    }
+
+    /// Gets the number of full frames in each row of the sheet.
+    #[inline]
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// Gets the number of full rows of frames in the sheet.
+    #[inline]
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// Gets the total number of frames in the sheet.
+    #[inline]
+    pub fn frame_count(&self) -> usize {
+        self.columns as usize * self.rows as usize
+    }
+
+    /// Gets the region of the image occupied by the frame at the given index.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn frame_rect(&self, i: usize) -> Rect {
+        if i >= self.frame_count() {
+            panic!(
+                "SpriteSheet: frame index {} out of bounds ({} frames)",
+                i,
+                self.frame_count()
+            );
+        }
+        let column = (i % self.columns as usize) as u32;
+        let row = (i / self.columns as usize) as u32;
+        Rect::new(
+            (self.margin + column * (self.frame_width + self.spacing)) as i32,
+            (self.margin + row * (self.frame_height + self.spacing)) as i32,
+            self.frame_width as u16,
+            self.frame_height as u16,
+        )
+    }
+
+    /// Draws the frame at the given index
+    /// with its top left corner at the given position.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the index is out of bounds.
+    #[inline]
+    pub fn draw_frame(&self, i: usize, x: i32, y: i32, options: SpriteDrawOptions) {
+        draw_region(&self.image, self.frame_rect(i), x, y, options);
+    }
+}
+
+/// Draws a region of an image
+/// with its top left corner at the given position.
+pub(crate) fn draw_region(image: &Image, rect: Rect, x: i32, y: i32, options: SpriteDrawOptions) {
+    let (width, height) = (image.width() as u16, image.height() as u16);
+
+    if !options.flip_x && !options.flip_y {
+        let (src_x, src_y) = (rect.x as u16, rect.y as u16);
+        match options.color_key {
+            Some(key) => mask_blit(
+                x,
+                y,
+                image.data(),
+                width,
+                height,
+                src_x,
+                src_y,
+                rect.width,
+                rect.height,
+                key,
+            ),
+            None => blit(
+                x,
+                y,
+                image.data(),
+                width,
+                height,
+                src_x,
+                src_y,
+                rect.width,
+                rect.height,
+            ),
+        }
+        return;
+    }
+
+    // the engine has no flipped blits, so blit a mirrored copy instead
+    let (frame_w, frame_h) = (rect.width as usize, rect.height as usize);
+    let mut frame = Vec::with_capacity(frame_w * frame_h);
+    for row in 0..frame_h {
+        let src_row = if options.flip_y {
+            frame_h - 1 - row
+        } else {
+            row
+        };
+        let start = (rect.y as usize + src_row) * width as usize + rect.x as usize;
+        let pixels = &image.data()[start..start + frame_w];
+        if options.flip_x {
+            frame.extend(pixels.iter().rev());
+        } else {
+            frame.extend_from_slice(pixels);
+        }
+    }
+    match options.color_key {
+        Some(key) => mask_blit(
+            x,
+            y,
+            &frame,
+            rect.width,
+            rect.height,
+            0,
+            0,
+            rect.width,
+            rect.height,
+            key,
+        ),
+        None => blit(
+            x,
+            y,
+            &frame,
+            rect.width,
+            rect.height,
+            0,
+            0,
+            rect.width,
+            rect.height,
+        ),
+    }
+}
@@ -0,0 +1,272 @@
+//! Off-screen drawing surfaces.
+
+use crate::video::{
+    blit, clip_rect, draw_offset, get_color, mask_blit, out_text_xy, raster, raster::Clip,
+    set_clip, set_color, set_draw_offset, Image,
+};
+
+/// An off-screen buffer of indexed pixels
+/// which can be drawn to with the same primitives as the screen.
+///
+/// Shapes are rasterized by the same code as the screen functions,
+/// so they select exactly the same pixels.
+/// Each surface has its own drawing color
+/// (see [`set_color`](Self::set_color)),
+/// and is not affected by the draw offset.
+///
+/// Once composed,
+/// a surface can be drawn to the screen with
+/// [`blit_to_screen`](Self::blit_to_screen),
+/// or turned into an [`Image`] with [`to_image`](Self::to_image).
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// let mut badge = Surface::new(32, 32);
+/// badge.set_color(14);
+/// badge.fill_circle(16, 16, 15);
+/// badge.set_color(4);
+/// badge.out_text_xy(8, 12, "OK");
+///
+/// set_video_mode(VideoMode::Graphics320x200);
+/// badge.blit_to_screen(100, 80, Some(0));
+/// ```
+#[derive(Debug, Clone, Eq, Hash, PartialEq)]
+pub struct Surface {
+    width: u16,
+    height: u16,
+    pixels: Box<[u8]>,
+    color: u8,
+}
+
+impl Surface {
+    /// Creates a surface of the given size,
+    /// with all pixels set to color 0.
+    pub fn new(width: u16, height: u16) -> Self {
+        Surface {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize].into_boxed_slice(),
+            color: 15,
+        }
+    }
+
+    /// Creates a surface with a copy of the pixels of an image.
+    ///
+    /// # Panic
+    ///
+    /// Panics if the image is larger than 65535 pixels on either side.
+    pub fn from_image(image: &Image) -> Self {
+        let width = u16::try_from(image.width()).expect("image is too wide for a surface");
+        let height = u16::try_from(image.height()).expect("image is too tall for a surface");
+        Surface {
+            width,
+            height,
+            pixels: image.data().into(),
+            color: 15,
+        }
+    }
+
+    /// Gets the width of the surface in pixels.
+    #[inline]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Gets the height of the surface in pixels.
+    #[inline]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Gets the pixels of the surface, row by row.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Gets mutable access to the pixels of the surface, row by row.
+    #[inline]
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Sets the color used by the drawing functions of this surface.
+    #[inline]
+    pub fn set_color(&mut self, color: u8) {
+        self.color = color;
+    }
+
+    /// Gets the color used by the drawing functions of this surface.
+    #[inline]
+    pub fn color(&self) -> u8 {
+        self.color
+    }
+
+    /// Sets all pixels of the surface to the given color.
+    pub fn clear(&mut self, color: u8) {
+        self.pixels.fill(color);
+    }
+
+    fn clip(&mut self) -> Clip<'_> {
+        Clip::pixels(&mut self.pixels, self.width, self.height, self.color)
+    }
+
+    /// Sets a single pixel to the given color,
+    /// if it is inside the surface.
+    pub fn put_pixel(&mut self, x: i32, y: i32, color: u8) {
+        let clip = Clip::pixels(&mut self.pixels, self.width, self.height, color);
+        raster::put_pixel(&clip, x, y);
+    }
+
+    /// Gets the color of a single pixel,
+    /// or `None` if it is outside the surface.
+    pub fn pixel_at(&self, x: i32, y: i32) -> Option<u8> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some(self.pixels[y as usize * self.width as usize + x as usize])
+    }
+
+    /// Draws a line between two points, both inclusive.
+    pub fn line(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        raster::line(&self.clip(), x1, y1, x2, y2);
+    }
+
+    /// Draws a non-filled rectangle,
+    /// as in [`rectangle`](super::rectangle).
+    pub fn rectangle(&mut self, x: i32, y: i32, width: u16, height: u16) {
+        raster::rectangle(&self.clip(), x, y, width, height);
+    }
+
+    /// Draws a filled rectangle,
+    /// as in [`bar`](super::bar).
+    pub fn bar(&mut self, x: i32, y: i32, width: u16, height: u16) {
+        raster::bar(&self.clip(), x, y, width, height);
+    }
+
+    /// Draws a circle with no filling.
+    pub fn circle(&mut self, x: i32, y: i32, r: u16) {
+        raster::circle(&self.clip(), x, y, r);
+    }
+
+    /// Draws a filled circle.
+    pub fn fill_circle(&mut self, x: i32, y: i32, r: u16) {
+        raster::fill_circle(&self.clip(), x, y, r);
+    }
+
+    /// Draws an ellipse with no filling.
+    pub fn ellipse(&mut self, x: i32, y: i32, rx: u16, ry: u16) {
+        raster::ellipse(&self.clip(), x, y, rx, ry);
+    }
+
+    /// Draws a filled ellipse.
+    pub fn fill_ellipse(&mut self, x: i32, y: i32, rx: u16, ry: u16) {
+        raster::fill_ellipse(&self.clip(), x, y, rx, ry);
+    }
+
+    /// Draws a filled polygon,
+    /// with the given flat list of XY coordinates in pixels.
+    ///
+    /// A trailing odd coordinate is ignored.
+    pub fn fill_poly(&mut self, points: &[i32]) {
+        raster::fill_poly(&self.clip(), points);
+    }
+
+    /// Draws text at the given position,
+    /// with the current font and text style (see [`set_text_style`](super::set_text_style)).
+    ///
+    /// The text is drawn by the engine,
+    /// so this only works in graphics mode.
+    pub fn out_text_xy(&mut self, x: i32, y: i32, text: impl AsRef<[u8]>) {
+        let previous_color = get_color();
+        let previous_offset = draw_offset();
+        let previous_clip = clip_rect();
+        set_color(self.color);
+        set_draw_offset(0, 0);
+        set_clip(None);
+        unsafe {
+            dos_like_sys::setdrawtarget(
+                self.pixels.as_mut_ptr(),
+                self.width as _,
+                self.height as _,
+            );
+        }
+        out_text_xy(x, y, text);
+        unsafe {
+            dos_like_sys::resetdrawtarget();
+        }
+        set_clip(previous_clip);
+        set_draw_offset(previous_offset.0, previous_offset.1);
+        set_color(previous_color);
+    }
+
+    /// Copies another surface into this one at the given position,
+    /// skipping the pixels of `color_key` if given.
+    pub fn blit_surface(&mut self, x: i32, y: i32, source: &Surface, color_key: Option<u8>) {
+        self.blit_pixels(x, y, &source.pixels, source.width as usize, color_key);
+    }
+
+    /// Copies an image into this surface at the given position,
+    /// skipping the pixels of `color_key` if given.
+    pub fn blit_image(&mut self, x: i32, y: i32, source: &Image, color_key: Option<u8>) {
+        self.blit_pixels(x, y, source.data(), source.width() as usize, color_key);
+    }
+
+    fn blit_pixels(&mut self, x: i32, y: i32, source: &[u8], width: usize, color_key: Option<u8>) {
+        if width == 0 {
+            return;
+        }
+        let height = source.len() / width;
+        let (dst_w, dst_h) = (self.width as i64, self.height as i64);
+        let (x, y) = (x as i64, y as i64);
+        let x0 = x.max(0);
+        let x1 = (x + width as i64).min(dst_w);
+        if x1 <= x0 {
+            return;
+        }
+        for dy in y.max(0)..(y + height as i64).min(dst_h) {
+            let src = &source[(dy - y) as usize * width..][(x0 - x) as usize..(x1 - x) as usize];
+            let dst = &mut self.pixels[(dy * dst_w) as usize..][x0 as usize..x1 as usize];
+            match color_key {
+                None => dst.copy_from_slice(src),
+                Some(key) => {
+                    for (d, &s) in dst.iter_mut().zip(src) {
+                        if s != key {
+                            *d = s;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the whole surface to the screen at the given position,
+    /// skipping the pixels of `color_key` if given.
+    ///
+    /// The position is subject to the draw offset.
+    pub fn blit_to_screen(&self, x: i32, y: i32, color_key: Option<u8>) {
+        let (w, h) = (self.width, self.height);
+        match color_key {
+            None => blit(x, y, &self.pixels, w, h, 0, 0, w, h),
+            Some(key) => mask_blit(x, y, &self.pixels, w, h, 0, 0, w, h, key),
+        }
+    }
+
+    /// Creates an image with a copy of the pixels of this surface
+    /// and the given palette,
+    /// as a flat list of 6-bit RGB values (up to 256 colors).
+    pub fn to_image(&self, palette: &[u8]) -> Image {
+        let count = (palette.len() / 3).min(256);
+        let mut full_palette = [0; 768];
+        full_palette[..count * 3].copy_from_slice(&palette[..count * 3]);
+        Image::from_pixels(
+            self.width as u32,
+            self.height as u32,
+            full_palette,
+            count as u32,
+            self.pixels.to_vec(),
+        )
+    }
+}
@@ -0,0 +1,301 @@
+//! Batched text drawing for graphics mode,
+//! such as the many short labels of a HUD.
+
+use std::ops::Range;
+
+use crate::video::{
+    builtin_font_data, draw_bounds, get_color, offset_xy, out_text_xy, set_color, set_text_style,
+    text_style, video_mode, Font, Rect,
+};
+
+/// A text recorded in a [`TextBatch`],
+/// along with the style and color in effect when it was added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    x: i32,
+    y: i32,
+    font: Font,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+    color: u8,
+    /// The bytes of the text in the batch's text buffer
+    text: Range<usize>,
+}
+
+impl Entry {
+    #[inline]
+    fn style(&self) -> (Font, bool, bool, bool) {
+        (self.font, self.bold, self.italic, self.underline)
+    }
+}
+
+/// A list of texts to draw at once in graphics mode,
+/// each with the font, style and color in effect when it was added.
+///
+/// Drawing a batch with [`flush`](Self::flush) makes the same pixels
+/// as calling [`out_text_xy`] for each text,
+/// but plain text in the built-in fonts is written
+/// straight into the screen buffer
+/// from the glyph bitmaps of [`builtin_font_data`],
+/// without going through the engine.
+/// Other texts are drawn through [`out_text_xy`],
+/// grouped by style so that it is switched as few times as possible.
+///
+/// The texts are kept in a single buffer,
+/// so a batch reused from frame to frame does not allocate
+/// once it has grown to fit a frame.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// let mut hud = TextBatch::new();
+/// let (mut score, mut lives) = (0, 3);
+/// while !shutting_down() {
+///     wait_vbl();
+///     score += 10;
+///     set_color(15);
+///     hud.add(4, 4, format!("SCORE {:06}", score));
+///     set_color(12);
+///     hud.add(260, 4, format!("LIVES {}", lives));
+///     hud.flush();
+/// }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TextBatch {
+    entries: Vec<Entry>,
+    text: Vec<u8>,
+}
+
+impl TextBatch {
+    /// Creates an empty batch.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a text to draw at the given position,
+    /// in the current font, style and color
+    /// (see [`set_text_style`] and [`set_color`]).
+    ///
+    /// XY coordinates are in pixels.
+    pub fn add(&mut self, x: i32, y: i32, text: impl AsRef<[u8]>) {
+        let (font, bold, italic, underline) = text_style();
+        let start = self.text.len();
+        self.text.extend_from_slice(text.as_ref());
+        self.entries.push(Entry {
+            x,
+            y,
+            font,
+            bold,
+            italic,
+            underline,
+            color: get_color(),
+            text: start..self.text.len(),
+        });
+    }
+
+    /// Gets the number of texts recorded.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether no texts are recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Discards the texts recorded without drawing them.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.text.clear();
+    }
+
+    /// Draws all of the texts recorded and clears the batch.
+    ///
+    /// The draw offset and clip rectangle in effect when flushing apply.
+    /// The texts written straight into the screen buffer are drawn first,
+    /// and the rest follow grouped by style,
+    /// so texts may be drawn in another order than they were added,
+    /// which only shows where they overlap.
+    /// The current text style and color are left as they were.
+    ///
+    /// Nothing is drawn unless the video is in graphics mode.
+    pub fn flush(&mut self) {
+        if !video_mode().is_graphics() {
+            self.clear();
+            return;
+        }
+        let previous_style = text_style();
+        let previous_color = get_color();
+
+        // plain text in the built-in fonts first, straight to the screen
+        let bounds = draw_bounds();
+        let mut switches = Vec::new();
+        for entry in &self.entries {
+            let text = &self.text[entry.text.clone()];
+            if !draw_direct(entry, text, bounds) {
+                switches.push(entry);
+            }
+        }
+
+        // then everything else through the engine, one style at a time
+        switches.sort_by_key(|entry| {
+            let (font, bold, italic, underline) = entry.style();
+            (font.0.get(), bold, italic, underline)
+        });
+        let mut style = None;
+        for entry in switches {
+            if style != Some(entry.style()) {
+                let (font, bold, italic, underline) = entry.style();
+                set_text_style(font, bold, italic, underline);
+                style = Some(entry.style());
+            }
+            set_color(entry.color);
+            out_text_xy(entry.x, entry.y, &self.text[entry.text.clone()]);
+        }
+
+        if style.is_some() {
+            let (font, bold, italic, underline) = previous_style;
+            set_text_style(font, bold, italic, underline);
+        }
+        set_color(previous_color);
+        self.clear();
+    }
+}
+
+/// Writes the glyphs of a text into the screen buffer,
+/// if it is plain text in one of the built-in fonts.
+///
+/// Returns `false` without drawing anything
+/// if the text needs to go through the engine:
+/// with any style flag,
+/// with line breaks,
+/// or with a null character (which the engine draws nothing for).
+fn draw_direct(entry: &Entry, text: &[u8], bounds: Rect) -> bool {
+    if entry.bold || entry.italic || entry.underline || text.contains(&b'\n') {
+        return false;
+    }
+    let Some((width, height)) = entry.font.builtin_glyph_size() else {
+        return false;
+    };
+    if text.contains(&0) {
+        return true;
+    }
+    let Some(data) = builtin_font_data(entry.font) else {
+        return false;
+    };
+
+    let (width, height) = (width as i64, height as i64);
+    let row_bytes = (width as usize).div_ceil(8);
+    let glyph_len = row_bytes * height as usize;
+    let (x, y) = offset_xy(entry.x, entry.y);
+    let (x, y) = (x as i64, y as i64);
+    let (left, top) = (bounds.left() as i64, bounds.top() as i64);
+    let (right, bottom) = (bounds.right(), bounds.bottom());
+    if y >= bottom || y + height <= top {
+        return true;
+    }
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let (screen, screen_w) = unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let buf = dos_like_sys::screenbuffer();
+        if buf.is_null() {
+            return true;
+        }
+        (
+            std::slice::from_raw_parts_mut(buf, width * height),
+            width as i64,
+        )
+    };
+
+    let rows = y.max(top)..(y + height).min(bottom);
+    for (i, &c) in text.iter().enumerate() {
+        let gx = x + i as i64 * width;
+        if gx >= right {
+            break;
+        }
+        if gx + width <= left {
+            continue;
+        }
+        let glyph = &data[c as usize * glyph_len..][..glyph_len];
+        let columns = gx.max(left)..(gx + width).min(right);
+        for sy in rows.clone() {
+            let row = &glyph[(sy - y) as usize * row_bytes..][..row_bytes];
+            for sx in columns.clone() {
+                let px = (sx - gx) as usize;
+                if row[px / 8] & (0x80 >> (px % 8)) != 0 {
+                    screen[(sy * screen_w + sx) as usize] = entry.color;
+                }
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::video::tests::VIDEO_MODE_LOCK;
+    use crate::video::{screen_buffer, set_video_mode, VideoMode};
+
+    /// The labels to draw, with their font, bold flag and color.
+    const LABELS: [(i32, i32, Font, bool, u8, &str); 6] = [
+        (4, 4, Font::DEFAULT_8X8, false, 15, "SCORE 001230"),
+        (-5, 20, Font::DEFAULT_8X16, false, 12, "LIVES 3"),
+        (300, 40, Font::DEFAULT_9X16, false, 14, "clipped"),
+        (20, 60, Font::DEFAULT_8X8, true, 10, "BOLD"),
+        (20, 80, Font::DEFAULT_8X16, false, 9, "two\nlines"),
+        (0, 196, Font::DEFAULT_8X8, false, 7, "bottom"),
+    ];
+
+    #[test]
+    fn flush_matches_out_text_xy() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Graphics320x200);
+
+        unsafe { screen_buffer() }.fill(0);
+        for (x, y, font, bold, color, text) in LABELS {
+            set_text_style(font, bold, false, false);
+            set_color(color);
+            out_text_xy(x, y, text);
+        }
+        let expected = unsafe { screen_buffer() }.to_vec();
+
+        unsafe { screen_buffer() }.fill(0);
+        let mut batch = TextBatch::new();
+        for (x, y, font, bold, color, text) in LABELS {
+            set_text_style(font, bold, false, false);
+            set_color(color);
+            batch.add(x, y, text);
+        }
+        assert_eq!(batch.len(), LABELS.len());
+        set_text_style(Font::DEFAULT_9X16, false, true, false);
+        set_color(3);
+        batch.flush();
+
+        assert!(unsafe { screen_buffer() }[..] == expected[..]);
+        assert!(batch.is_empty());
+        assert_eq!(text_style(), (Font::DEFAULT_9X16, false, true, false));
+        assert_eq!(get_color(), 3);
+        set_video_mode(VideoMode::Text80x25_8x16);
+    }
+
+    #[test]
+    fn flush_outside_of_graphics_mode_only_clears() {
+        let _lock = VIDEO_MODE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        set_video_mode(VideoMode::Text80x25_8x16);
+        let mut batch = TextBatch::new();
+        batch.add(0, 0, "text");
+        batch.flush();
+        assert!(batch.is_empty());
+    }
+}
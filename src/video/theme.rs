@@ -0,0 +1,215 @@
+//! Text mode color themes,
+//! mapping the roles of text in a user interface to colors.
+
+use std::sync::Mutex;
+
+use crate::video::TextAttr;
+
+/// The role of some text in a user interface,
+/// which a [`Theme`] gives the colors of.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum ThemeRole {
+    /// Regular text.
+    Normal,
+    /// Text standing out from the rest,
+    /// such as the selected item of a [`Menu`](super::widgets::Menu).
+    Highlight,
+    /// Text of something which cannot be used at the moment,
+    /// such as a disabled item of a [`Menu`](super::widgets::Menu).
+    Disabled,
+    /// The title of a window or the top bar of the screen,
+    /// as drawn by [`Frame`](super::widgets::Frame).
+    TitleBar,
+    /// The frame around a window or a panel,
+    /// as drawn by [`Frame`](super::widgets::Frame).
+    Border,
+    /// Error messages.
+    Error,
+    /// Text being edited,
+    /// as drawn by [`TextField`](super::widgets::TextField).
+    Input,
+    /// The filled portion of a progress bar over its background,
+    /// as drawn by [`ProgressBar`](super::widgets::ProgressBar).
+    Progress,
+}
+
+impl ThemeRole {
+    /// The number of roles.
+    const COUNT: usize = 8;
+
+    /// All roles, in order.
+    pub const ALL: [ThemeRole; Self::COUNT] = [
+        ThemeRole::Normal,
+        ThemeRole::Highlight,
+        ThemeRole::Disabled,
+        ThemeRole::TitleBar,
+        ThemeRole::Border,
+        ThemeRole::Error,
+        ThemeRole::Input,
+        ThemeRole::Progress,
+    ];
+
+    /// Gets the colors of this role in the current theme
+    /// (see [`theme`]).
+    #[inline]
+    pub fn attrs(self) -> TextAttr {
+        theme().attrs(self)
+    }
+
+    /// Sets the colors of this role in the current theme
+    /// for subsequent text output.
+    ///
+    /// Only works in text mode.
+    #[inline]
+    pub fn apply(self) {
+        self.attrs().apply();
+    }
+}
+
+/// The text colors of each role of a user interface,
+/// so that an application can switch between color schemes,
+/// such as for a high contrast mode,
+/// by drawing through roles instead of fixed colors.
+///
+/// The current theme is set with [`set_theme`],
+/// and the widgets in [`widgets`](super::widgets)
+/// take their colors from it whenever they are drawn.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Text80x25_9x16);
+/// // the Borland look, with error messages in black on yellow
+/// set_theme(Theme::BORLAND.with(ThemeRole::Error, TextAttr::new(0, 14)));
+/// goto_xy(2, 1);
+/// ThemeRole::TitleBar.apply();
+/// put_str(" File  Edit  Search ");
+/// goto_xy(2, 3);
+/// ThemeRole::Error.apply();
+/// put_str("File not found");
+/// ```
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub struct Theme {
+    attrs: [TextAttr; ThemeRole::COUNT],
+}
+
+impl Default for Theme {
+    /// The classic theme (see [`Theme::CLASSIC`]).
+    fn default() -> Self {
+        Theme::CLASSIC
+    }
+}
+
+impl Theme {
+    /// Light gray on black, as in a fresh DOS prompt,
+    /// with inverted colors for highlights, title bars and input.
+    pub const CLASSIC: Theme = Theme {
+        attrs: [
+            // normal
+            TextAttr::new(7, 0),
+            // highlight
+            TextAttr::new(0, 7),
+            // disabled
+            TextAttr::new(8, 0),
+            // title bar
+            TextAttr::new(0, 7),
+            // border
+            TextAttr::new(7, 0),
+            // error
+            TextAttr::new(12, 0),
+            // input
+            TextAttr::new(0, 7),
+            // progress
+            TextAttr::new(15, 0),
+        ],
+    };
+
+    /// Yellow on blue, as in the Borland IDEs of the time,
+    /// with light gray title bars.
+    pub const BORLAND: Theme = Theme {
+        attrs: [
+            // normal
+            TextAttr::new(14, 1),
+            // highlight
+            TextAttr::new(0, 2),
+            // disabled
+            TextAttr::new(7, 1),
+            // title bar
+            TextAttr::new(0, 7),
+            // border
+            TextAttr::new(15, 1),
+            // error
+            TextAttr::new(15, 4),
+            // input
+            TextAttr::new(15, 3),
+            // progress
+            TextAttr::new(11, 1),
+        ],
+    };
+
+    /// Gets the colors of a role.
+    #[inline]
+    pub const fn attrs(&self, role: ThemeRole) -> TextAttr {
+        self.attrs[role as usize]
+    }
+
+    /// Sets the colors of a role.
+    #[inline]
+    pub fn set_attrs(&mut self, role: ThemeRole, attrs: TextAttr) {
+        self.attrs[role as usize] = attrs;
+    }
+
+    /// Changes the colors of a role,
+    /// returning the modified theme.
+    #[inline]
+    pub const fn with(mut self, role: ThemeRole, attrs: TextAttr) -> Self {
+        self.attrs[role as usize] = attrs;
+        self
+    }
+}
+
+/// The current theme.
+static THEME: Mutex<Theme> = Mutex::new(Theme::CLASSIC);
+
+/// Sets the theme of the text user interface.
+///
+/// Anything drawn afterwards through the roles,
+/// including the widgets in [`widgets`](super::widgets),
+/// takes the colors of the new theme,
+/// so redrawing the screen is enough to switch themes.
+pub fn set_theme(theme: Theme) {
+    *THEME.lock().unwrap_or_else(|e| e.into_inner()) = theme;
+}
+
+/// Gets the theme of the text user interface,
+/// which is [`Theme::CLASSIC`] unless changed with [`set_theme`].
+pub fn theme() -> Theme {
+    *THEME.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_index_their_colors() {
+        for (i, role) in ThemeRole::ALL.into_iter().enumerate() {
+            assert_eq!(role as usize, i);
+            let theme = Theme::CLASSIC.with(role, TextAttr::new(1, 2));
+            for other in ThemeRole::ALL {
+                let expected = if other == role {
+                    TextAttr::new(1, 2)
+                } else {
+                    Theme::CLASSIC.attrs(other)
+                };
+                assert_eq!(theme.attrs(other), expected, "{:?}", other);
+            }
+
+            let mut set = Theme::BORLAND;
+            set.set_attrs(role, TextAttr::new(1, 2));
+            assert_eq!(set, Theme::BORLAND.with(role, TextAttr::new(1, 2)));
+        }
+        assert_eq!(Theme::default(), Theme::CLASSIC);
+    }
+}
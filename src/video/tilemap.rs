@@ -0,0 +1,115 @@
+//! Drawing of tile maps.
+
+use crate::video::{draw_bounds, offset_xy, video_mode, Rect, SpriteSheet};
+
+/// The tile index for empty cells of a tile map,
+/// which are not drawn.
+pub const EMPTY_TILE: u16 = u16::MAX;
+
+/// Draws the visible portion of a tile map into a region of the screen.
+///
+/// - `map` contains the tile index of each cell, row by row,
+///   as the index of a frame in `tileset`
+/// - `map_w` and `map_h` are the dimensions of the map in tiles
+/// - `camera_x` and `camera_y` are the position in the map, in pixels,
+///   shown at the top left corner of `dst`
+/// - `dst` is the region of the screen to draw to,
+///   which is subject to the draw offset
+///
+/// All tiles are the size of a frame of the tile set.
+/// Cells with [`EMPTY_TILE`] or any other index not in the tile set
+/// are left untouched, as is the region outside of the map.
+///
+/// The tiles are copied row by row directly into the screen buffer,
+/// which is much faster than blitting each tile separately.
+/// Color keys are not supported.
+///
+/// Does nothing unless the video is in graphics mode.
+///
+/// # Panic
+///
+/// Panics if `map` has fewer than `map_w * map_h` cells,
+/// since this is likely a bug.
+pub fn draw_tilemap(
+    map: &[u16],
+    map_w: u32,
+    map_h: u32,
+    tileset: &SpriteSheet,
+    camera_x: i32,
+    camera_y: i32,
+    dst: Rect,
+) {
+    if (map.len() as u64) < map_w as u64 * map_h as u64 {
+        panic!(
+            "draw_tilemap: map ({} cells) is too short for dimensions {}x{}",
+            map.len(),
+            map_w,
+            map_h
+        );
+    }
+    if !video_mode().is_graphics() {
+        return;
+    }
+
+    let (tile_w, tile_h) = (tileset.frame_width() as i64, tileset.frame_height() as i64);
+    let tiles = tileset.frame_count();
+    let image = tileset.image();
+    let image_w = image.width() as usize;
+    let pixels = image.data();
+
+    // Safety: the buffer has one byte per pixel on the screen,
+    // and no slice to it is alive as per the contract of `screen_buffer`
+    let (screen, screen_w) = unsafe {
+        let width = dos_like_sys::screenwidth() as usize;
+        let height = dos_like_sys::screenheight() as usize;
+        let buf = dos_like_sys::screenbuffer();
+        (
+            std::slice::from_raw_parts_mut(buf, width * height),
+            width as i64,
+        )
+    };
+
+    // clip the destination to the screen and the clip rectangle
+    let bounds = draw_bounds();
+    let (dst_x, dst_y) = offset_xy(dst.x, dst.y);
+    let (dst_x, dst_y) = (dst_x as i64, dst_y as i64);
+    let x0 = dst_x.max(bounds.left() as i64);
+    let x1 = (dst_x + dst.width as i64).min(bounds.right());
+    let y0 = dst_y.max(bounds.top() as i64);
+    let y1 = (dst_y + dst.height as i64).min(bounds.bottom());
+
+    for y in y0..y1 {
+        let world_y = camera_y as i64 + (y - dst_y);
+        let tile_y = world_y.div_euclid(tile_h);
+        if tile_y < 0 || tile_y >= map_h as i64 {
+            continue;
+        }
+        let row_in_tile = world_y.rem_euclid(tile_h);
+        let map_row = &map[tile_y as usize * map_w as usize..][..map_w as usize];
+        let screen_row = &mut screen[(y * screen_w) as usize..][..screen_w as usize];
+
+        // copy one span per tile, clipped on both edges
+        let mut x = x0;
+        while x < x1 {
+            let world_x = camera_x as i64 + (x - dst_x);
+            let tile_x = world_x.div_euclid(tile_w);
+            let col_in_tile = world_x.rem_euclid(tile_w);
+            let span = (tile_w - col_in_tile).min(x1 - x);
+
+            let tile = if (0..map_w as i64).contains(&tile_x) {
+                map_row[tile_x as usize] as usize
+            } else {
+                EMPTY_TILE as usize
+            };
+            if tile != EMPTY_TILE as usize && tile < tiles {
+                let rect = tileset.frame_rect(tile);
+                let start = (rect.y as usize + row_in_tile as usize) * image_w
+                    + rect.x as usize
+                    + col_in_tile as usize;
+                screen_row[x as usize..(x + span) as usize]
+                    .copy_from_slice(&pixels[start..start + span as usize]);
+            }
+            x += span;
+        }
+    }
+}
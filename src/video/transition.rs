@@ -0,0 +1,259 @@
+//! Full screen transition effects between two images.
+
+use crate::video::{
+    is_double_buffered, remap_table, screen_height, screen_width, swap_buffers, video_mode, Image,
+};
+use crate::{shutting_down, wait_vbl};
+
+/// A kind of screen transition.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq)]
+pub enum Transition {
+    /// Pixels of the target image appear one by one in random order
+    Dissolve,
+    /// The target image is revealed from left to right
+    WipeHorizontal,
+    /// The target image is revealed from top to bottom
+    WipeVertical,
+    /// The target image is revealed by a circle
+    /// growing from the center of the screen
+    Iris,
+}
+
+impl Transition {
+    /// All kinds of transitions.
+    pub const ALL: [Transition; 4] = [
+        Transition::Dissolve,
+        Transition::WipeHorizontal,
+        Transition::WipeVertical,
+        Transition::Iris,
+    ];
+}
+
+/// A screen transition from one image to another,
+/// advanced one frame at a time.
+///
+/// Both images are drawn at the top left corner of the screen,
+/// and pixels outside of an image are drawn with color 0.
+///
+/// The screen can only hold one palette at a time,
+/// so when the images have different palettes,
+/// the palette of the source image is used
+/// during the first half of the transition,
+/// and the palette of the target image during the second half.
+/// Pixels from the other image are remapped
+/// to the nearest color in the palette in use.
+///
+/// Only makes sense in graphics mode.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::*;
+/// set_video_mode(VideoMode::Graphics320x200);
+/// set_double_buffer(true);
+/// let title = load_gif("title.gif").unwrap();
+/// let level = load_gif("level.gif").unwrap();
+///
+/// let mut player = TransitionPlayer::new(Transition::Iris, 60, &title, &level);
+/// while !shutting_down() {
+///     wait_vbl();
+///     if !player.step() {
+///         break;
+///     }
+///     swap_buffers();
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TransitionPlayer<'a> {
+    kind: Transition,
+    duration_frames: u32,
+    frame: u32,
+    from: &'a Image,
+    to: &'a Image,
+    /// The screen width at the start of the transition.
+    width: usize,
+    /// The screen height at the start of the transition.
+    height: usize,
+    /// The frame index at which each pixel appears (dissolve only).
+    ranks: Vec<u32>,
+    /// Colors of the target image in the palette of the source image.
+    to_in_from: [u8; 256],
+    /// Colors of the source image in the palette of the target image.
+    from_in_to: [u8; 256],
+    /// Whether the palette of the target image was set,
+    /// or `None` if no palette was set yet.
+    to_palette: Option<bool>,
+}
+
+impl<'a> TransitionPlayer<'a> {
+    /// Prepares a transition from one image to another,
+    /// taking the given number of frames.
+    ///
+    /// The transition covers the whole screen
+    /// at its size at the time of the call.
+    pub fn new(kind: Transition, duration_frames: u32, from: &'a Image, to: &'a Image) -> Self {
+        let width = screen_width() as usize;
+        let height = screen_height() as usize;
+        let duration_frames = duration_frames.max(1);
+
+        let ranks = if kind == Transition::Dissolve {
+            dissolve_ranks(width * height, duration_frames)
+        } else {
+            Vec::new()
+        };
+
+        TransitionPlayer {
+            kind,
+            duration_frames,
+            frame: 0,
+            from,
+            to,
+            width,
+            height,
+            ranks,
+            to_in_from: remap_table(to.palette(), from.palette()),
+            from_in_to: remap_table(from.palette(), to.palette()),
+            to_palette: None,
+        }
+    }
+
+    /// Gets the kind of transition.
+    #[inline]
+    pub fn kind(&self) -> Transition {
+        self.kind
+    }
+
+    /// Gets the number of frames already rendered.
+    #[inline]
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Checks whether all frames of the transition were rendered,
+    /// the last one showing the target image alone.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.frame > self.duration_frames
+    }
+
+    /// Renders the next frame of the transition
+    /// into the current screen buffer
+    /// (the back buffer if double buffering is enabled),
+    /// switching palettes if necessary.
+    ///
+    /// Returns `false` without drawing anything
+    /// if the transition was already finished.
+    pub fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+
+        let to_palette = self.frame * 2 >= self.duration_frames;
+        if self.to_palette != Some(to_palette) {
+            let image = if to_palette { self.to } else { self.from };
+            image.apply_palette();
+            self.to_palette = Some(to_palette);
+        }
+
+        if video_mode().is_graphics() {
+            self.render(to_palette);
+        }
+
+        self.frame += 1;
+        true
+    }
+
+    /// Renders the current frame into the screen buffer.
+    fn render(&self, to_palette: bool) {
+        // Safety: the buffer has one byte per pixel on the screen,
+        // and no slice to it is alive as per the contract of `screen_buffer`
+        let buffer = unsafe {
+            let len = dos_like_sys::screenwidth() as usize * dos_like_sys::screenheight() as usize;
+            std::slice::from_raw_parts_mut(dos_like_sys::screenbuffer(), len)
+        };
+
+        let (w, h) = (self.width, self.height);
+        let (frame, duration) = (self.frame as u64, self.duration_frames as u64);
+        // the radius of the iris, squared
+        let (cx, cy) = (w as i64 / 2, h as i64 / 2);
+        let iris = ((cx * cx + cy * cy) as u64 * frame * frame / (duration * duration)) as i64;
+        let wipe_x = w as u64 * frame / duration;
+        let wipe_y = h as u64 * frame / duration;
+
+        for (y, row) in buffer.chunks_exact_mut(w).take(h).enumerate() {
+            for (x, out) in row.iter_mut().enumerate() {
+                let reveal = match self.kind {
+                    Transition::Dissolve => (self.ranks[y * w + x] as u64) < frame,
+                    Transition::WipeHorizontal => (x as u64) < wipe_x,
+                    Transition::WipeVertical => (y as u64) < wipe_y,
+                    Transition::Iris => {
+                        let (dx, dy) = (x as i64 - cx, y as i64 - cy);
+                        dx * dx + dy * dy <= iris
+                    }
+                };
+                *out = match (reveal, to_palette) {
+                    (true, true) => image_pixel(self.to, x, y),
+                    (true, false) => self.to_in_from[image_pixel(self.to, x, y) as usize],
+                    (false, true) => self.from_in_to[image_pixel(self.from, x, y) as usize],
+                    (false, false) => image_pixel(self.from, x, y),
+                };
+            }
+        }
+    }
+}
+
+/// Plays a full screen transition from one image to another,
+/// taking the given number of frames,
+/// and blocking until it is done
+/// or until the application is shutting down.
+///
+/// If double buffering is enabled,
+/// the buffers are swapped after each frame.
+/// See [`TransitionPlayer`] for details on how the images are drawn.
+///
+/// Only makes sense in graphics mode.
+pub fn transition(kind: Transition, duration_frames: u32, from: &Image, to: &Image) {
+    let mut player = TransitionPlayer::new(kind, duration_frames, from, to);
+    while !shutting_down() {
+        wait_vbl();
+        if !player.step() {
+            break;
+        }
+        if is_double_buffered() {
+            swap_buffers();
+        }
+    }
+}
+
+/// Gets the color of an image at the given position,
+/// or 0 if outside of the image.
+#[inline]
+fn image_pixel(image: &Image, x: usize, y: usize) -> u8 {
+    let width = image.width() as usize;
+    if x < width && y < image.height() as usize {
+        image.data()[y * width + x]
+    } else {
+        0
+    }
+}
+
+/// Assigns to each of the given number of pixels
+/// the frame at which it appears in a dissolve,
+/// in a fixed pseudo-random order (Fisher-Yates shuffle).
+fn dissolve_ranks(len: usize, duration_frames: u32) -> Vec<u32> {
+    let mut order: Vec<u32> = (0..len as u32).collect();
+    // xorshift, so that the pattern is the same on every run
+    let mut state = 0x2545_f491_u32;
+    for i in (1..len).rev() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        order.swap(i, state as usize % (i + 1));
+    }
+
+    let mut ranks = vec![0; len];
+    for (i, pixel) in order.into_iter().enumerate() {
+        ranks[pixel as usize] = (i as u64 * duration_frames as u64 / len.max(1) as u64) as u32;
+    }
+    ranks
+}
@@ -0,0 +1,967 @@
+//! Module for simple text mode user interface widgets.
+//!
+//! Widgets keep their own state across frames,
+//! are fed with input events (see [`read_events`](crate::input::read_events)),
+//! and are drawn on demand:
+//! [`Frame`] boxes a window or a panel,
+//! [`Menu`] offers a list of choices,
+//! [`TextField`] edits a line of text,
+//! and [`ProgressBar`] and [`Spinner`] show that work is in progress.
+//! Unless given colors of their own,
+//! they are drawn with the colors of their role in the current theme
+//! (see [`set_theme`](super::set_theme)).
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::input::{InputEvent, KeyCode};
+use crate::video::{goto_xy, put_cells, theme, TextAttr, Theme, ThemeRole};
+
+/// A character filter for a [`TextField`].
+type CharFilter = Box<dyn Fn(u8) -> bool + Send>;
+
+/// An editable single line text field with a fixed maximum length,
+/// similar to what `cgets` provides in conio,
+/// but which persists across frames.
+///
+/// Only printable ASCII characters are accepted.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::{*, widgets::TextField};
+/// // an IP address input field
+/// let mut field = TextField::new(15)
+///     .with_filter(|c| c.is_ascii_digit() || c == b'.');
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     for event in read_events() {
+///         field.handle_event(&event);
+///     }
+///     field.draw(10, 5, 16);
+/// }
+/// ```
+pub struct TextField {
+    /// The current text.
+    text: String,
+    /// The maximum number of characters.
+    max_len: usize,
+    /// The cursor position, in characters.
+    cursor: usize,
+    /// Whether typed characters replace the ones under the cursor.
+    overwrite: bool,
+    /// Optional filter of accepted characters.
+    filter: Option<CharFilter>,
+}
+
+impl std::fmt::Debug for TextField {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TextField")
+            .field("text", &self.text)
+            .field("max_len", &self.max_len)
+            .field("cursor", &self.cursor)
+            .field("overwrite", &self.overwrite)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl TextField {
+    /// Creates a new empty text field
+    /// which accepts up to `max_len` characters.
+    pub fn new(max_len: usize) -> Self {
+        TextField {
+            text: String::with_capacity(max_len),
+            max_len,
+            cursor: 0,
+            overwrite: false,
+            filter: None,
+        }
+    }
+
+    /// Sets a filter for the characters accepted by the field.
+    ///
+    /// The filter receives each typed character
+    /// and returns whether it should be accepted.
+    pub fn with_filter(mut self, filter: impl Fn(u8) -> bool + Send + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Gets the current text of the field.
+    #[inline]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text of the field,
+    /// moving the cursor to the end.
+    ///
+    /// Characters which would not be accepted by the field are skipped,
+    /// and the text is truncated to the maximum length.
+    pub fn set_text(&mut self, text: impl AsRef<str>) {
+        self.text.clear();
+        self.cursor = 0;
+        for c in text.as_ref().bytes() {
+            if self.text.len() == self.max_len {
+                break;
+            }
+            if self.accepts(c) {
+                self.text.push(c as char);
+            }
+        }
+        self.cursor = self.text.len();
+    }
+
+    /// Gets the maximum number of characters of the field.
+    #[inline]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Gets the cursor position, in characters.
+    #[inline]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Checks whether the field is in overwrite mode
+    /// (as opposed to insert mode).
+    #[inline]
+    pub fn is_overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    /// Sets whether the field is in overwrite mode
+    /// (as opposed to insert mode).
+    #[inline]
+    pub fn set_overwrite(&mut self, overwrite: bool) {
+        self.overwrite = overwrite;
+    }
+
+    /// Checks whether a character would be accepted by the field.
+    fn accepts(&self, c: u8) -> bool {
+        (b' '..=b'~').contains(&c) && self.filter.as_ref().map(|f| f(c)).unwrap_or(true)
+    }
+
+    /// Processes an input event.
+    ///
+    /// Returns whether the event was consumed by the field.
+    pub fn handle_event(&mut self, event: &InputEvent) -> bool {
+        match *event {
+            InputEvent::Char(c) => self.type_char(c),
+            InputEvent::Key(key) if key.is_pressed() => self.press_key(key.key_code()),
+            InputEvent::Key(_) => false,
+        }
+    }
+
+    fn type_char(&mut self, c: u8) -> bool {
+        if !self.accepts(c) {
+            return false;
+        }
+        if self.overwrite && self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+            self.text.insert(self.cursor, c as char);
+        } else if self.text.len() < self.max_len {
+            self.text.insert(self.cursor, c as char);
+        } else {
+            return false;
+        }
+        self.cursor += 1;
+        true
+    }
+
+    fn press_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::KEY_LEFT => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::KEY_RIGHT => self.cursor = (self.cursor + 1).min(self.text.len()),
+            KeyCode::KEY_HOME => self.cursor = 0,
+            KeyCode::KEY_END => self.cursor = self.text.len(),
+            KeyCode::KEY_INSERT => self.overwrite = !self.overwrite,
+            KeyCode::KEY_BACK => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.cursor);
+                }
+            }
+            KeyCode::KEY_DELETE => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the field at the given cell position,
+    /// spanning `width` cells,
+    /// and places the text cursor at the edit position.
+    ///
+    /// If the text does not fit in the given width,
+    /// it is scrolled so that the cursor remains visible.
+    /// The cells are written directly to the screen,
+    /// leaving the current text colors as they were.
+    ///
+    /// The colors are those of [`ThemeRole::Input`] in the current theme.
+    ///
+    /// Only works in text mode.
+    #[inline]
+    pub fn draw(&self, x: u16, y: u16, width: u16) {
+        self.draw_with_attrs(x, y, width, ThemeRole::Input.attrs());
+    }
+
+    /// Draws the field like [`draw`](Self::draw),
+    /// with the given colors instead of those of the theme.
+    ///
+    /// Only works in text mode.
+    pub fn draw_with_attrs(&self, x: u16, y: u16, width: u16, attrs: TextAttr) {
+        let width = width as usize;
+        if width == 0 {
+            return;
+        }
+        let start = (self.cursor + 1).saturating_sub(width);
+        let end = (start + width).min(self.text.len());
+
+        let text = self.text[start..end].bytes();
+        put_cells(x, y, text.chain(std::iter::repeat(b' ')).take(width), attrs);
+        goto_xy(x + (self.cursor - start) as u16, y);
+    }
+}
+
+/// The shaded block characters of code page 437,
+/// from a quarter to fully filled.
+const BLOCKS: [u8; 4] = [0xB0, 0xB1, 0xB2, 0xDB];
+
+/// A horizontal progress bar drawn with block characters,
+/// with a resolution of a quarter of a cell.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::{*, widgets::ProgressBar};
+/// let mut bar = ProgressBar::new(10, 12, 60);
+/// for i in 0..=100 {
+///     wait_vbl();
+///     bar.set_fraction(i as f32 / 100.);
+///     bar.draw();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressBar {
+    x: u16,
+    y: u16,
+    width: u16,
+    fraction: f32,
+    attrs: Option<TextAttr>,
+}
+
+impl ProgressBar {
+    /// Creates an empty progress bar
+    /// at the given cell position,
+    /// spanning `width_cells` cells.
+    pub fn new(x: u16, y: u16, width_cells: u16) -> Self {
+        ProgressBar {
+            x,
+            y,
+            width: width_cells,
+            fraction: 0.,
+            attrs: None,
+        }
+    }
+
+    /// Sets the colors of the bar,
+    /// where the foreground is the filled portion,
+    /// or `None` to take those of [`ThemeRole::Progress`]
+    /// in the current theme, as by default.
+    #[inline]
+    pub fn with_attrs(mut self, attrs: impl Into<Option<TextAttr>>) -> Self {
+        self.attrs = attrs.into();
+        self
+    }
+
+    /// Sets the colors of the bar,
+    /// where the foreground is the filled portion,
+    /// or `None` to take those of [`ThemeRole::Progress`]
+    /// in the current theme.
+    #[inline]
+    pub fn set_attrs(&mut self, attrs: impl Into<Option<TextAttr>>) {
+        self.attrs = attrs.into();
+    }
+
+    /// Gets the filled fraction of the bar, between 0 and 1.
+    #[inline]
+    pub fn fraction(&self) -> f32 {
+        self.fraction
+    }
+
+    /// Sets the filled fraction of the bar,
+    /// clamped between 0 and 1.
+    #[inline]
+    pub fn set_fraction(&mut self, fraction: f32) {
+        self.fraction = if fraction.is_nan() {
+            0.
+        } else {
+            fraction.clamp(0., 1.)
+        };
+    }
+
+    /// Draws the bar with its colors,
+    /// or those of the current theme (see [`with_attrs`](Self::with_attrs)).
+    ///
+    /// The cells are written directly to the screen,
+    /// leaving the cursor and the current text colors as they were.
+    ///
+    /// Only works in text mode.
+    pub fn draw(&self) {
+        let attrs = self.attrs.unwrap_or_else(|| ThemeRole::Progress.attrs());
+        put_cells(self.x, self.y, self.cells(), attrs);
+    }
+
+    /// The characters of each cell of the bar.
+    fn cells(&self) -> impl Iterator<Item = u8> {
+        let width = self.width as usize;
+        let quarters = (self.fraction * (width * 4) as f32).round() as usize;
+        (0..width).map(move |i| match (quarters / 4).cmp(&i) {
+            Ordering::Greater => BLOCKS[3],
+            Ordering::Equal if !quarters.is_multiple_of(4) => BLOCKS[quarters % 4 - 1],
+            _ => b' ',
+        })
+    }
+}
+
+/// A single cell spinner cycling through `|`, `/`, `-` and `\`,
+/// to show that work is in progress.
+///
+/// The spinner moves to its next character
+/// every given number of frames,
+/// counted by calls to [`tick`](Spinner::tick).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spinner {
+    x: u16,
+    y: u16,
+    frames_per_step: u32,
+    frame: u32,
+    attrs: Option<TextAttr>,
+}
+
+impl Spinner {
+    /// The characters shown by the spinner, in order.
+    pub const CHARS: [u8; 4] = [b'|', b'/', b'-', b'\\'];
+
+    /// Creates a spinner at the given cell position,
+    /// which moves every `frames_per_step` frames.
+    ///
+    /// A value of 0 is treated as 1.
+    pub fn new(x: u16, y: u16, frames_per_step: u32) -> Self {
+        Spinner {
+            x,
+            y,
+            frames_per_step: frames_per_step.max(1),
+            frame: 0,
+            attrs: None,
+        }
+    }
+
+    /// Sets the colors of the spinner,
+    /// or `None` to take those of [`ThemeRole::Normal`]
+    /// in the current theme, as by default.
+    #[inline]
+    pub fn with_attrs(mut self, attrs: impl Into<Option<TextAttr>>) -> Self {
+        self.attrs = attrs.into();
+        self
+    }
+
+    /// Sets the colors of the spinner,
+    /// or `None` to take those of [`ThemeRole::Normal`]
+    /// in the current theme.
+    #[inline]
+    pub fn set_attrs(&mut self, attrs: impl Into<Option<TextAttr>>) {
+        self.attrs = attrs.into();
+    }
+
+    /// Counts one frame,
+    /// to be called once per frame.
+    #[inline]
+    pub fn tick(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Gets the character currently shown.
+    #[inline]
+    pub fn current_char(&self) -> u8 {
+        Self::CHARS[(self.frame / self.frames_per_step) as usize % Self::CHARS.len()]
+    }
+
+    /// Draws the spinner with its colors,
+    /// or those of the current theme (see [`with_attrs`](Self::with_attrs)).
+    ///
+    /// The cell is written directly to the screen,
+    /// leaving the cursor and the current text colors as they were.
+    ///
+    /// Only works in text mode.
+    pub fn draw(&self) {
+        let attrs = self.attrs.unwrap_or_else(|| ThemeRole::Normal.attrs());
+        put_cells(self.x, self.y, [self.current_char()], attrs);
+    }
+}
+
+/// The line drawing characters of code page 437
+/// for the corners and edges of a [`Frame`],
+/// in the order top left, top right, bottom left, bottom right,
+/// horizontal and vertical.
+const SINGLE_LINES: [u8; 6] = [0xDA, 0xBF, 0xC0, 0xD9, 0xC4, 0xB3];
+const DOUBLE_LINES: [u8; 6] = [0xC9, 0xBB, 0xC8, 0xBC, 0xCD, 0xBA];
+
+/// A box drawn with line characters around a window or a panel,
+/// with an optional title centered on its top edge.
+///
+/// The border takes the colors of [`ThemeRole::Border`],
+/// the title those of [`ThemeRole::TitleBar`],
+/// and the inside is cleared with those of [`ThemeRole::Normal`],
+/// so that the contents can be drawn over it afterwards.
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::{*, widgets::Frame};
+/// let frame = Frame::new(20, 8, 40, 9).with_title("Save As");
+/// frame.draw();
+/// goto_xy(22, 10);
+/// ThemeRole::Normal.apply();
+/// put_str("File name:");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+    title: String,
+    double: bool,
+    theme: Option<Theme>,
+}
+
+impl Frame {
+    /// Creates a frame with single lines and no title,
+    /// with its top left corner at the given cell position,
+    /// spanning `width` by `height` cells including the border.
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Frame {
+            x,
+            y,
+            width,
+            height,
+            title: String::new(),
+            double: false,
+            theme: None,
+        }
+    }
+
+    /// Sets the title of the frame,
+    /// or removes it if empty.
+    #[inline]
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Sets the title of the frame,
+    /// or removes it if empty.
+    #[inline]
+    pub fn set_title(&mut self, title: impl Into<String>) {
+        self.title = title.into();
+    }
+
+    /// Gets the title of the frame.
+    #[inline]
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Sets whether the border is drawn with double lines
+    /// instead of single lines.
+    #[inline]
+    pub fn with_double_lines(mut self, double: bool) -> Self {
+        self.double = double;
+        self
+    }
+
+    /// Sets the theme which the frame takes its colors from,
+    /// or `None` to take them from the current theme, as by default.
+    #[inline]
+    pub fn with_theme(mut self, theme: impl Into<Option<Theme>>) -> Self {
+        self.theme = theme.into();
+        self
+    }
+
+    /// Sets the theme which the frame takes its colors from,
+    /// or `None` to take them from the current theme.
+    #[inline]
+    pub fn set_theme(&mut self, theme: impl Into<Option<Theme>>) {
+        self.theme = theme.into();
+    }
+
+    /// Gets the range of cells of the title in the top row,
+    /// which is padded with a space on each side.
+    fn title_range(&self) -> Range<usize> {
+        let width = self.width as usize;
+        // truncated to leave a corner and an edge cell on each side
+        let len = self.title.len().min(width.saturating_sub(6));
+        if len == 0 {
+            return 0..0;
+        }
+        let start = (width - len - 2) / 2;
+        start..start + len + 2
+    }
+
+    /// Gets the cells of a row of the frame, counting from the top.
+    fn row(&self, row: usize) -> impl Iterator<Item = u8> + '_ {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = if self.double
+        {
+            DOUBLE_LINES
+        } else {
+            SINGLE_LINES
+        };
+        let (left, fill, right) = if row == 0 {
+            (top_left, horizontal, top_right)
+        } else if row + 1 == height {
+            (bottom_left, horizontal, bottom_right)
+        } else {
+            (vertical, b' ', vertical)
+        };
+        let title = if row == 0 { self.title_range() } else { 0..0 };
+        let mut label = self.title.bytes();
+        (0..width).map(move |column| {
+            if column == 0 {
+                left
+            } else if column + 1 == width {
+                right
+            } else if !title.contains(&column) {
+                fill
+            } else if column == title.start || column + 1 == title.end {
+                b' '
+            } else {
+                match label.next() {
+                    Some(0) | None => b' ',
+                    Some(c) => c,
+                }
+            }
+        })
+    }
+
+    /// Draws the frame with the colors of its theme,
+    /// or those of the current theme (see [`with_theme`](Self::with_theme)).
+    ///
+    /// Nothing is drawn if the frame is less than 2 cells wide or high.
+    /// The cells are written directly to the screen,
+    /// leaving the cursor and the current text colors as they were.
+    ///
+    /// Only works in text mode.
+    pub fn draw(&self) {
+        let (width, height) = (self.width as usize, self.height as usize);
+        if width < 2 || height < 2 {
+            return;
+        }
+        let theme = self.theme.unwrap_or_else(theme);
+        let border = theme.attrs(ThemeRole::Border);
+        let normal = theme.attrs(ThemeRole::Normal);
+        let column = |i: usize| self.x.saturating_add(i as u16);
+
+        // top edge, with the title in the middle
+        let title = self.title_range();
+        put_cells(self.x, self.y, self.row(0).take(title.start), border);
+        put_cells(
+            column(title.start),
+            self.y,
+            self.row(0).take(title.end).skip(title.start),
+            theme.attrs(ThemeRole::TitleBar),
+        );
+        put_cells(
+            column(title.end),
+            self.y,
+            self.row(0).skip(title.end),
+            border,
+        );
+
+        // sides, with the inside cleared in between
+        for (i, y) in (1..height - 1).zip(self.y.saturating_add(1)..) {
+            put_cells(self.x, y, self.row(i).take(1), border);
+            put_cells(column(1), y, self.row(i).take(width - 1).skip(1), normal);
+            put_cells(column(width - 1), y, self.row(i).skip(width - 1), border);
+        }
+
+        if let Some(y) = self.y.checked_add(self.height - 1) {
+            put_cells(self.x, y, self.row(height - 1), border);
+        }
+    }
+}
+
+/// An item of a [`Menu`].
+#[derive(Debug, Clone, PartialEq)]
+struct MenuItem {
+    label: String,
+    enabled: bool,
+}
+
+/// A vertical menu of items, one per row,
+/// where an item is selected with the arrow keys
+/// and chosen with Enter.
+///
+/// Items which are disabled are skipped over when moving the selection.
+/// The selected item takes the colors of [`ThemeRole::Highlight`],
+/// disabled items those of [`ThemeRole::Disabled`],
+/// and the other items those of [`ThemeRole::Normal`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use dos_like::{*, widgets::Menu};
+/// let mut menu = Menu::new(30, 10, 20)
+///     .with_item("New Game")
+///     .with_item("Continue")
+///     .with_item("Quit");
+/// menu.set_enabled(1, false);
+///
+/// while !shutting_down() {
+///     wait_vbl();
+///     for event in read_events() {
+///         match menu.handle_event(&event) {
+///             Some(0) => { /* start a new game */ }
+///             Some(2) => return,
+///             _ => {}
+///         }
+///     }
+///     menu.draw();
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    x: u16,
+    y: u16,
+    width: u16,
+    items: Vec<MenuItem>,
+    selected: Option<usize>,
+    theme: Option<Theme>,
+}
+
+impl Menu {
+    /// Creates an empty menu
+    /// with its first item at the given cell position,
+    /// with rows spanning `width` cells.
+    pub fn new(x: u16, y: u16, width: u16) -> Self {
+        Menu {
+            x,
+            y,
+            width,
+            items: Vec::new(),
+            selected: None,
+            theme: None,
+        }
+    }
+
+    /// Adds an item at the end of the menu,
+    /// returning the modified menu.
+    #[inline]
+    pub fn with_item(mut self, label: impl Into<String>) -> Self {
+        self.push_item(label);
+        self
+    }
+
+    /// Adds an item at the end of the menu.
+    ///
+    /// The item is selected if no item was.
+    pub fn push_item(&mut self, label: impl Into<String>) {
+        self.items.push(MenuItem {
+            label: label.into(),
+            enabled: true,
+        });
+        if self.selected.is_none() {
+            self.selected = Some(self.items.len() - 1);
+        }
+    }
+
+    /// Sets the theme which the menu takes its colors from,
+    /// or `None` to take them from the current theme, as by default.
+    #[inline]
+    pub fn with_theme(mut self, theme: impl Into<Option<Theme>>) -> Self {
+        self.theme = theme.into();
+        self
+    }
+
+    /// Sets the theme which the menu takes its colors from,
+    /// or `None` to take them from the current theme.
+    #[inline]
+    pub fn set_theme(&mut self, theme: impl Into<Option<Theme>>) {
+        self.theme = theme.into();
+    }
+
+    /// Gets the number of items in the menu.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks whether the menu has no items.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Gets the label of an item,
+    /// or `None` if there is no item at the given index.
+    #[inline]
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.items.get(index).map(|item| item.label.as_str())
+    }
+
+    /// Checks whether an item exists and is enabled.
+    #[inline]
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.items.get(index).is_some_and(|item| item.enabled)
+    }
+
+    /// Enables or disables an item.
+    ///
+    /// Disabling the selected item moves the selection
+    /// to the next enabled item, if any.
+    /// Does nothing if there is no item at the given index.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        let Some(item) = self.items.get_mut(index) else {
+            return;
+        };
+        item.enabled = enabled;
+        if enabled && self.selected.is_none() {
+            self.selected = Some(index);
+        } else if !enabled && self.selected == Some(index) {
+            self.selected = self.next_enabled(index, 1);
+        }
+    }
+
+    /// Gets the index of the selected item,
+    /// or `None` if no item is enabled.
+    #[inline]
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Selects an item.
+    ///
+    /// Returns whether it was selected,
+    /// which is not the case if it does not exist or is disabled.
+    pub fn set_selected(&mut self, index: usize) -> bool {
+        if self.is_enabled(index) {
+            self.selected = Some(index);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Finds the next enabled item after `from`
+    /// in the given direction (1 or -1), wrapping around,
+    /// which is `from` itself if no other item is enabled.
+    fn next_enabled(&self, from: usize, step: isize) -> Option<usize> {
+        let len = self.items.len() as isize;
+        (1..=len)
+            .map(|i| (from as isize + i * step).rem_euclid(len) as usize)
+            .find(|&i| self.items[i].enabled)
+    }
+
+    /// Processes an input event.
+    ///
+    /// The up and down arrows move the selection
+    /// to the previous or next enabled item, wrapping around,
+    /// and Home and End to the first or last enabled item.
+    ///
+    /// Returns the index of the selected item when Enter is pressed,
+    /// so that the application can act on the choice.
+    pub fn handle_event(&mut self, event: &InputEvent) -> Option<usize> {
+        match *event {
+            InputEvent::Key(key) if key.is_pressed() => self.press_key(key.key_code()),
+            _ => None,
+        }
+    }
+
+    fn press_key(&mut self, key: KeyCode) -> Option<usize> {
+        let selected = self.selected?;
+        let last = self.items.len() - 1;
+        self.selected = match key {
+            KeyCode::KEY_UP => self.next_enabled(selected, -1),
+            KeyCode::KEY_DOWN => self.next_enabled(selected, 1),
+            KeyCode::KEY_HOME => self.next_enabled(last, 1),
+            KeyCode::KEY_END => self.next_enabled(0, -1),
+            KeyCode::KEY_RETURN => return Some(selected),
+            _ => return None,
+        };
+        None
+    }
+
+    /// Draws the menu with the colors of its theme,
+    /// or those of the current theme (see [`with_theme`](Self::with_theme)).
+    ///
+    /// Each label is preceded by a space
+    /// and truncated to the width of the menu.
+    /// The cells are written directly to the screen,
+    /// leaving the cursor and the current text colors as they were.
+    ///
+    /// Only works in text mode.
+    pub fn draw(&self) {
+        let width = self.width as usize;
+        if width == 0 {
+            return;
+        }
+        let theme = self.theme.unwrap_or_else(theme);
+        for (i, (row, item)) in (self.y..).zip(&self.items).enumerate() {
+            let role = if !item.enabled {
+                ThemeRole::Disabled
+            } else if self.selected == Some(i) {
+                ThemeRole::Highlight
+            } else {
+                ThemeRole::Normal
+            };
+            let label = item.label.bytes().map(|c| if c == 0 { b' ' } else { c });
+            let cells = std::iter::once(b' ')
+                .chain(label)
+                .chain(std::iter::repeat(b' '))
+                .take(width);
+            put_cells(self.x, row, cells, theme.attrs(role));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar_cells(width: u16, fraction: f32) -> Vec<u8> {
+        let mut bar = ProgressBar::new(0, 0, width);
+        bar.set_fraction(fraction);
+        bar.cells().collect()
+    }
+
+    #[test]
+    fn progress_bar_fills_quarter_cells() {
+        assert_eq!(bar_cells(4, 0.), b"    ");
+        assert_eq!(bar_cells(4, 1. / 16.), [0xB0, b' ', b' ', b' ']);
+        assert_eq!(bar_cells(4, 0.5), [0xDB, 0xDB, b' ', b' ']);
+        assert_eq!(bar_cells(4, 0.75 + 3. / 16.), [0xDB, 0xDB, 0xDB, 0xB2]);
+        assert_eq!(bar_cells(4, 1.), [0xDB; 4]);
+        assert_eq!(bar_cells(4, f32::NAN), b"    ");
+        assert_eq!(bar_cells(0, 1.), b"");
+    }
+
+    #[test]
+    fn spinner_steps_every_few_frames() {
+        let mut spinner = Spinner::new(0, 0, 2);
+        let mut shown = vec![];
+        for _ in 0..8 {
+            shown.push(spinner.current_char());
+            spinner.tick();
+        }
+        assert_eq!(shown, b"||//--\\\\");
+    }
+
+    fn menu(enabled: &[bool]) -> Menu {
+        let mut menu = Menu::new(0, 0, 10);
+        for (i, &enabled) in enabled.iter().enumerate() {
+            menu.push_item(format!("Item {}", i));
+            menu.set_enabled(i, enabled);
+        }
+        menu
+    }
+
+    #[test]
+    fn menu_skips_disabled_items_and_wraps() {
+        let mut menu = menu(&[false, true, false, true, true, false]);
+        assert_eq!(menu.selected(), Some(1));
+
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            assert_eq!(menu.press_key(KeyCode::KEY_DOWN), None);
+            visited.push(menu.selected().unwrap());
+        }
+        assert_eq!(visited, [3, 4, 1, 3]);
+
+        assert_eq!(menu.press_key(KeyCode::KEY_UP), None);
+        assert_eq!(menu.selected(), Some(1));
+        menu.press_key(KeyCode::KEY_UP);
+        assert_eq!(menu.selected(), Some(4));
+
+        menu.press_key(KeyCode::KEY_HOME);
+        assert_eq!(menu.selected(), Some(1));
+        menu.press_key(KeyCode::KEY_END);
+        assert_eq!(menu.selected(), Some(4));
+        assert_eq!(menu.press_key(KeyCode::KEY_RETURN), Some(4));
+    }
+
+    #[test]
+    fn menu_selection_follows_enabled_items() {
+        let mut menu = menu(&[true, true, true]);
+        assert!(menu.set_selected(2));
+        assert!(!menu.set_selected(3));
+        menu.set_enabled(2, false);
+        assert_eq!(menu.selected(), Some(0));
+        assert!(!menu.set_selected(2));
+
+        menu.set_enabled(0, false);
+        menu.set_enabled(1, false);
+        assert_eq!(menu.selected(), None);
+        assert_eq!(menu.press_key(KeyCode::KEY_DOWN), None);
+        assert_eq!(menu.press_key(KeyCode::KEY_RETURN), None);
+
+        menu.set_enabled(1, true);
+        assert_eq!(menu.selected(), Some(1));
+        assert_eq!(menu.press_key(KeyCode::KEY_DOWN), None);
+        assert_eq!(menu.selected(), Some(1));
+
+        assert_eq!(Menu::new(0, 0, 10).press_key(KeyCode::KEY_RETURN), None);
+    }
+
+    fn frame_rows(frame: &Frame) -> (Vec<Vec<u8>>, Range<usize>) {
+        let rows = (0..frame.height as usize)
+            .map(|i| frame.row(i).collect())
+            .collect();
+        (rows, frame.title_range())
+    }
+
+    #[test]
+    fn frame_rows_with_title() {
+        let (rows, title) = frame_rows(&Frame::new(0, 0, 10, 3).with_title("Hi"));
+        assert_eq!(
+            rows,
+            [
+                vec![0xDA, 0xC4, 0xC4, b' ', b'H', b'i', b' ', 0xC4, 0xC4, 0xBF],
+                vec![0xB3, b' ', b' ', b' ', b' ', b' ', b' ', b' ', b' ', 0xB3],
+                vec![0xC0, 0xC4, 0xC4, 0xC4, 0xC4, 0xC4, 0xC4, 0xC4, 0xC4, 0xD9],
+            ]
+        );
+        assert_eq!(title, 3..7);
+
+        // long titles leave the corners and an edge cell on each side
+        let (rows, title) = frame_rows(
+            &Frame::new(0, 0, 8, 2)
+                .with_title("Long title")
+                .with_double_lines(true),
+        );
+        assert_eq!(
+            rows,
+            [
+                vec![0xC9, 0xCD, b' ', b'L', b'o', b' ', 0xCD, 0xBB],
+                vec![0xC8, 0xCD, 0xCD, 0xCD, 0xCD, 0xCD, 0xCD, 0xBC],
+            ]
+        );
+        assert_eq!(title, 2..6);
+
+        let (rows, title) = frame_rows(&Frame::new(0, 0, 6, 4).with_title("Hidden"));
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0][1..5].iter().all(|&c| c == 0xC4));
+        assert_eq!(title, 0..0);
+
+        // the characters which would end the text are shown as spaces
+        let (rows, _) = frame_rows(&Frame::new(0, 0, 10, 2).with_title("a\0b"));
+        assert_eq!(rows[0][2..7], *b" a b ");
+    }
+}
@@ -0,0 +1,325 @@
+//! Loading and drawing of XBin text art files (`.xb`).
+
+use std::sync::OnceLock;
+
+use crate::video::{
+    bar, builtin_font_data, get_color, goto_xy, put_cstr, set_color, set_palette_bytes, soft_font,
+    text_columns, text_rows, video_mode, Font, TextAttr,
+};
+use crate::FileError;
+
+/// The maximum number of rows supported,
+/// as in the tallest text mode.
+const MAX_ROWS: u16 = 50;
+
+/// Flag for the presence of a palette.
+const FLAG_PALETTE: u8 = 1;
+/// Flag for the presence of a font.
+const FLAG_FONT: u8 = 2;
+/// Flag for compressed cell data.
+const FLAG_COMPRESS: u8 = 4;
+/// Flag for background colors with high intensity instead of blinking.
+const FLAG_NON_BLINK: u8 = 8;
+/// Flag for a font with 512 characters.
+const FLAG_512_CHARS: u8 = 16;
+
+/// A text art image loaded from an XBin file,
+/// with an optional palette and font of its own.
+#[derive(Debug)]
+pub struct XBin {
+    width: u16,
+    height: u16,
+    font_height: u8,
+    non_blink: bool,
+    /// The 16 colors of the palette, in the range of [`set_pal`](super::set_pal).
+    palette: Option<[u8; 48]>,
+    /// The glyphs of the first 256 characters of the font.
+    font_glyphs: Option<Box<[u8]>>,
+    /// Character and attribute of each cell, row by row.
+    cells: Vec<u8>,
+    /// The font used to draw the image in graphics mode,
+    /// installed on first use.
+    font: OnceLock<Option<Font>>,
+}
+
+impl XBin {
+    /// Gets the width of the image in cells.
+    #[inline]
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    /// Gets the height of the image in cells.
+    #[inline]
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Gets the height of each character in pixels.
+    #[inline]
+    pub fn font_height(&self) -> u8 {
+        self.font_height
+    }
+
+    /// Gets the 16 color palette of the image, if any,
+    /// in RGB with values between 0 and 63.
+    #[inline]
+    pub fn palette(&self) -> Option<&[u8; 48]> {
+        self.palette.as_ref()
+    }
+
+    /// Gets the glyph data of the font of the image, if any,
+    /// as 256 glyphs of 8 pixels wide and [`font_height`](Self::font_height) tall.
+    ///
+    /// Only the first 256 characters of 512 character fonts are kept.
+    #[inline]
+    pub fn font_glyphs(&self) -> Option<&[u8]> {
+        self.font_glyphs.as_deref()
+    }
+
+    /// Gets the character and attribute of a cell,
+    /// or `None` if outside of the image.
+    pub fn cell(&self, x: u16, y: u16) -> Option<(u8, u8)> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 2;
+        Some((self.cells[i], self.cells[i + 1]))
+    }
+
+    /// Gets the background color of an attribute.
+    #[inline]
+    fn background(&self, attr: u8) -> u8 {
+        if self.non_blink {
+            attr >> 4
+        } else {
+            (attr >> 4) & 7
+        }
+    }
+
+    /// Gets the font to draw the image with in graphics mode,
+    /// installing it if necessary.
+    fn font(&self) -> Option<Font> {
+        *self.font.get_or_init(|| match &self.font_glyphs {
+            Some(glyphs) => Font::from_glyphs(8, self.font_height, glyphs).ok(),
+            None => {
+                let builtin = if self.font_height == 8 {
+                    Font::DEFAULT_8X8
+                } else {
+                    Font::DEFAULT_8X16
+                };
+                let (width, height) = builtin.builtin_glyph_size()?;
+                Font::from_glyphs(width, height, builtin_font_data(builtin)?).ok()
+            }
+        })
+    }
+
+    /// Installs the palette of the image (if any) in the first 16 entries,
+    /// and draws the image with its top left corner at the given position.
+    ///
+    /// In text mode, the position is in cells,
+    /// and the image is drawn with the font of the current text mode.
+    /// The characters 0 and 10 are drawn as spaces.
+    ///
+    /// In graphics mode, the position is in pixels,
+    /// and the image is drawn with its own font
+    /// (or the built-in font closest in size if it has none),
+    /// which is installed on the first call.
+    pub fn install_and_draw(&self, x: i32, y: i32) {
+        if let Some(palette) = &self.palette {
+            set_palette_bytes(0, palette);
+        }
+        if video_mode().is_graphics() {
+            self.draw_graphics(x, y);
+        } else {
+            self.draw_text(x, y);
+        }
+    }
+
+    fn draw_text(&self, x: i32, y: i32) {
+        let original = TextAttr::current();
+        let (columns, rows) = (text_columns() as i32, text_rows() as i32);
+
+        for row in 0..self.height as i32 {
+            let cy = y + row;
+            if cy < 0 || cy >= rows {
+                continue;
+            }
+            let first = (-x).max(0);
+            let last = (columns - x).min(self.width as i32);
+            let mut col = first;
+            while col < last {
+                // write runs of cells with the same attribute at once
+                let (_, attr) = self.cell(col as u16, row as u16).unwrap_or_default();
+                let mut run = Vec::new();
+                let start = col;
+                while col < last {
+                    let (c, a) = self.cell(col as u16, row as u16).unwrap_or_default();
+                    if a != attr {
+                        break;
+                    }
+                    run.push(if c == 0 || c == b'\n' { b' ' } else { c });
+                    col += 1;
+                }
+                run.push(0);
+                goto_xy((x + start) as u16, cy as u16);
+                TextAttr::new(attr & 0x0F, self.background(attr)).apply();
+                if let Ok(text) = std::ffi::CStr::from_bytes_with_nul(&run) {
+                    put_cstr(text);
+                }
+            }
+        }
+
+        original.apply();
+    }
+
+    fn draw_graphics(&self, x: i32, y: i32) {
+        let Some(font) = self.font().and_then(soft_font::get) else {
+            return;
+        };
+        let (cell_width, cell_height) = (font.width as i32, font.height as i32);
+        let original = get_color();
+
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let (c, attr) = self.cell(col, row).unwrap_or_default();
+                let (cx, cy) = (x + col as i32 * cell_width, y + row as i32 * cell_height);
+                set_color(self.background(attr));
+                bar(cx, cy, cell_width as u16, cell_height as u16);
+                set_color(attr & 0x0F);
+                font.draw_line(cx, cy, &[c], false, false, false);
+            }
+        }
+
+        set_color(original);
+    }
+}
+
+fn invalid(message: impl Into<String>) -> FileError {
+    FileError::InvalidData(message.into())
+}
+
+/// Decompresses XBin cell data until `len` bytes are produced.
+fn unpack_cells(mut data: &[u8], len: usize) -> Result<Vec<u8>, FileError> {
+    let truncated = || invalid("truncated XBin image data");
+    let mut out = Vec::with_capacity(len);
+    while out.len() < len {
+        let (&b, rest) = data.split_first().ok_or_else(truncated)?;
+        data = rest;
+        let count = (b & 0x3F) as usize + 1;
+        // the fixed and varying bytes of each run
+        let (fixed, varying) = match b >> 6 {
+            0 => (0, 2),
+            1 | 2 => (1, 1),
+            _ => (2, 0),
+        };
+        let needed = fixed + count * varying;
+        if data.len() < needed {
+            return Err(truncated());
+        }
+        let (run, rest) = data.split_at(needed);
+        data = rest;
+        for i in 0..count {
+            let (c, a) = match b >> 6 {
+                0 => (run[i * 2], run[i * 2 + 1]),
+                // same character, varying attributes
+                1 => (run[0], run[1 + i]),
+                // same attribute, varying characters
+                2 => (run[1 + i], run[0]),
+                _ => (run[0], run[1]),
+            };
+            out.extend_from_slice(&[c, a]);
+        }
+    }
+    out.truncate(len);
+    Ok(out)
+}
+
+/// Loads a text art image from an XBin file.
+///
+/// Files with more than 50 rows,
+/// which do not fit in any text mode,
+/// are rejected with [`FileError::InvalidData`].
+pub fn load_xbin(path: impl AsRef<str>) -> Result<XBin, FileError> {
+    let data = std::fs::read(path.as_ref()).map_err(|_| FileError::FileNotFound)?;
+    load_xbin_from_memory(&data)
+}
+
+/// Loads a text art image from the byte data of an XBin file.
+///
+/// See [`load_xbin`] for the supported files.
+pub fn load_xbin_from_memory(data: &[u8]) -> Result<XBin, FileError> {
+    if data.len() < 11 || &data[0..5] != b"XBIN\x1A" {
+        return Err(invalid("not an XBin file"));
+    }
+    let width = u16::from_le_bytes([data[5], data[6]]);
+    let height = u16::from_le_bytes([data[7], data[8]]);
+    let font_height = data[9];
+    let flags = data[10];
+    if width == 0 || height == 0 {
+        return Err(invalid("empty XBin image"));
+    }
+    if height > MAX_ROWS {
+        return Err(invalid(format!(
+            "XBin image has {} rows, more than the maximum of {}",
+            height, MAX_ROWS
+        )));
+    }
+    if flags & FLAG_FONT != 0 && !(1..=32).contains(&font_height) {
+        return Err(invalid(format!(
+            "invalid XBin font height: {}",
+            font_height
+        )));
+    }
+
+    let mut rest = &data[11..];
+    let mut take = |len: usize| -> Result<&[u8], FileError> {
+        if rest.len() < len {
+            return Err(invalid("truncated XBin file"));
+        }
+        let (chunk, remainder) = rest.split_at(len);
+        rest = remainder;
+        Ok(chunk)
+    };
+
+    let palette = if flags & FLAG_PALETTE != 0 {
+        let mut palette = [0; 48];
+        palette.copy_from_slice(take(48)?);
+        // the values are 6-bit, any higher bits are meaningless
+        for c in &mut palette {
+            *c &= 0x3F;
+        }
+        Some(palette)
+    } else {
+        None
+    };
+    let font_glyphs = if flags & FLAG_FONT != 0 {
+        let chars = if flags & FLAG_512_CHARS != 0 {
+            512
+        } else {
+            256
+        };
+        let glyphs = take(chars * font_height as usize)?;
+        Some(glyphs[..256 * font_height as usize].into())
+    } else {
+        None
+    };
+
+    let len = width as usize * height as usize * 2;
+    let cells = if flags & FLAG_COMPRESS != 0 {
+        unpack_cells(rest, len)?
+    } else {
+        take(len)?.to_vec()
+    };
+
+    Ok(XBin {
+        width,
+        height,
+        font_height: if font_height == 0 { 16 } else { font_height },
+        non_blink: flags & FLAG_NON_BLINK != 0,
+        palette,
+        font_glyphs,
+        cells,
+        font: OnceLock::new(),
+    })
+}
@@ -0,0 +1,214 @@
+//! Hot reloading of assets during development.
+//!
+//! A watched asset keeps track of the modification time of its file,
+//! and loads it again when the file changes,
+//! so that art and sounds can be iterated on
+//! without restarting the program.
+//!
+//! Watched assets must be updated once per frame
+//! through [`update`](Watched::update),
+//! which only looks at the file system
+//! once every [`interval`](Watched::set_interval).
+//!
+//! If reloading fails,
+//! for instance because the editor is still writing the file,
+//! the previous asset is kept,
+//! the error is available through [`last_error`](Watched::last_error),
+//! and loading is tried again on the next check.
+//!
+//! Images are freed when replaced,
+//! but sounds are never freed by the engine,
+//! so each reload of a sound leaks the memory of the previous one.
+//! This module is meant for development builds only.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use dos_like::*;
+//! # use dos_like::watch::*;
+//! set_video_mode(VideoMode::Graphics320x200);
+//! let mut image = watch_image("files/title.gif")?;
+//! let mut strategy = PaletteStrategy::InstallPalette;
+//!
+//! while !shutting_down() {
+//!     wait_vbl();
+//!     image.update();
+//!     if image.reloaded() {
+//!         // the palette may have changed as well
+//!         strategy = PaletteStrategy::InstallPalette;
+//!     }
+//!     draw_image_auto(0, 0, image.get(), strategy);
+//!     strategy = PaletteStrategy::AssumeInstalled;
+//! }
+//! # Ok::<(), FileError>(())
+//! ```
+use std::{
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{load_gif, FileError, Image, Sound};
+
+/// The default time between checks of the file.
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An asset which is loaded again when its file changes.
+#[derive(Debug)]
+pub struct Watched<T> {
+    path: String,
+    loader: fn(&str) -> Result<T, FileError>,
+    asset: T,
+    /// The modification time of the file which was loaded
+    modified: Option<SystemTime>,
+    interval: Duration,
+    last_check: Instant,
+    reloaded: bool,
+    error: Option<FileError>,
+}
+
+/// An image which is loaded again when its file changes.
+pub type WatchedImage = Watched<Image>;
+
+/// A sound which is loaded again when its file changes.
+pub type WatchedSound = Watched<Sound>;
+
+/// Loads a GIF image and watches its file for changes.
+pub fn watch_image(path: impl AsRef<str>) -> Result<WatchedImage, FileError> {
+    Watched::with_loader(path, |path| load_gif(path))
+}
+
+/// Loads a sound and watches its file for changes.
+///
+/// The file format is chosen by the file extension:
+/// `.ogg` and `.flac` files are supported
+/// when the respective Cargo features are enabled,
+/// and any other file is loaded as WAV.
+pub fn watch_sound(path: impl AsRef<str>) -> Result<WatchedSound, FileError> {
+    Watched::with_loader(path, load_sound)
+}
+
+/// Loads a sound by the file extension.
+fn load_sound(path: &str) -> Result<Sound, FileError> {
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    match extension.as_deref() {
+        #[cfg(feature = "ogg")]
+        Some("ogg") => crate::load_ogg(path),
+        #[cfg(feature = "flac")]
+        Some("flac") => crate::load_flac(path),
+        _ => crate::load_wav(path),
+    }
+}
+
+/// Gets the modification time of a file.
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl<T> Watched<T> {
+    /// Loads an asset with the given function
+    /// and watches its file for changes.
+    ///
+    /// The same function is used to load the asset again.
+    pub fn with_loader(
+        path: impl AsRef<str>,
+        loader: fn(&str) -> Result<T, FileError>,
+    ) -> Result<Self, FileError> {
+        let path = path.as_ref().to_string();
+        let modified = modified(&path);
+        let asset = loader(&path)?;
+        Ok(Watched {
+            path,
+            loader,
+            asset,
+            modified,
+            interval: DEFAULT_INTERVAL,
+            last_check: Instant::now(),
+            reloaded: false,
+            error: None,
+        })
+    }
+
+    /// Gets the current asset.
+    pub fn get(&self) -> &T {
+        &self.asset
+    }
+
+    /// Gets the path of the watched file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Sets the minimum time between checks of the file.
+    ///
+    /// The default is half a second.
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+    }
+
+    /// Gets the minimum time between checks of the file.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Whether the asset was loaded again
+    /// in the last call to [`update`](Watched::update).
+    pub fn reloaded(&self) -> bool {
+        self.reloaded
+    }
+
+    /// Gets the error of the last failed attempt to load the asset again,
+    /// or `None` if the current asset is up to date with the file.
+    pub fn last_error(&self) -> Option<&FileError> {
+        self.error.as_ref()
+    }
+
+    /// Checks the file for changes and loads the asset again if it changed,
+    /// or if the last attempt failed.
+    ///
+    /// This should be called once per frame.
+    /// The file is only checked once the interval has passed
+    /// since the last check.
+    pub fn update(&mut self) {
+        self.reloaded = false;
+        if self.last_check.elapsed() < self.interval {
+            return;
+        }
+        self.last_check = Instant::now();
+        self.check();
+    }
+
+    /// Checks the file for changes and loads the asset again if it changed,
+    /// regardless of the interval.
+    ///
+    /// Returns whether the asset was loaded again.
+    pub fn reload_now(&mut self) -> bool {
+        self.reloaded = false;
+        self.last_check = Instant::now();
+        self.check();
+        self.reloaded
+    }
+
+    fn check(&mut self) {
+        let Some(modified) = modified(&self.path) else {
+            // the file may be replaced at the moment
+            self.error = Some(FileError::FileNotFound);
+            return;
+        };
+        if self.error.is_none() && self.modified == Some(modified) {
+            return;
+        }
+        match (self.loader)(&self.path) {
+            Ok(asset) => {
+                self.asset = asset;
+                self.modified = Some(modified);
+                self.reloaded = true;
+                self.error = None;
+            }
+            Err(e) => {
+                self.error = Some(e);
+            }
+        }
+    }
+}